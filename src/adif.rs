@@ -0,0 +1,282 @@
+// ADIF export of the saved receptions, for the logging programs amateur radio operators keep
+// their main log in. Every pass, or every frame, becomes a record the way a short-wave listener
+// logs a station heard: BOTAN's call sign in CW via satellite at the reception time, on the band
+// of the pass frequency, with the receiving station's call sign and grid square when given. Only
+// frames this station decoded count; frames imported from other stations are left out. The file
+// is ADIF 3 in its .adi form, plain ASCII with every field written as <NAME:length>value.
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::export;
+use crate::timeutil;
+
+/// BOTAN's call sign, the station every record logs
+const CALL_SIGN: &str = "JS1YPT";
+
+/// The CW beacon frequency, for passes that did not record one [Hz]
+const BEACON_HZ: f64 = 437_375_000.0;
+
+const ADIF_VERSION: &str = "3.1.4";
+
+/// Amateur bands satellites use, as ADIF names them, with their edges [MHz]
+const BANDS: [(&str, f64, f64); 8] = [
+    ("10m", 28.0, 29.7),
+    ("6m", 50.0, 54.0),
+    ("2m", 144.0, 148.0),
+    ("1.25m", 222.0, 225.0),
+    ("70cm", 420.0, 450.0),
+    ("33cm", 902.0, 928.0),
+    ("23cm", 1240.0, 1300.0),
+    ("13cm", 2300.0, 2450.0),
+];
+
+/// What each record stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Records {
+    Pass,
+    Frame,
+}
+
+impl FromStr for Records {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pass" => Ok(Records::Pass),
+            "frame" => Ok(Records::Frame),
+            _ => Err(format!("Unknown record kind '{}'. Expected pass or frame", s)),
+        }
+    }
+}
+
+/// A Maidenhead locator of 2 to 8 characters, e.g. PM95vq
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid(pub String);
+
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = [0, 2, 4, 6].into_iter().filter(|&i| i < s.len()).all(|i| {
+            let pair = &s.as_bytes()[i..(i + 2).min(s.len())];
+            let in_range = |c: u8, last: u8| pair.len() == 2 && pair.iter().all(|b| (c..=last).contains(&b.to_ascii_uppercase()));
+            match i {
+                0 => in_range(b'A', b'R'),
+                2 | 6 => in_range(b'0', b'9'),
+                _ => in_range(b'A', b'X'),
+            }
+        });
+        if !valid || s.is_empty() || s.len() > 8 {
+            return Err(format!("Invalid grid square '{}'. Expected a Maidenhead locator such as PM95vq", s));
+        }
+        // Fields in capitals, subsquares in lower case, as logging programs write them
+        let grid = s.char_indices().map(|(i, c)| if i >= 4 { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect();
+        Ok(Grid(grid))
+    }
+}
+
+impl Grid {
+    /// The six-character locator of a position
+    pub fn at(lat: f64, lon: f64) -> Self {
+        let lon = (lon + 180.0).rem_euclid(360.0);
+        let lat = (lat + 90.0).clamp(0.0, 180.0 - 1e-9);
+        let letter = |base: u8, index: f64| (base + index as u8) as char;
+        let grid = [
+            letter(b'A', lon / 20.0),
+            letter(b'A', lat / 10.0),
+            letter(b'0', lon % 20.0 / 2.0),
+            letter(b'0', lat % 10.0),
+            letter(b'a', lon % 2.0 * 12.0),
+            letter(b'a', lat % 1.0 * 24.0),
+        ];
+        Grid(grid.iter().collect())
+    }
+}
+
+/// The receiving station
+#[derive(Debug, Default)]
+pub struct Station {
+    pub call: Option<String>,
+    pub grid: Option<Grid>,
+}
+
+/// The ADIF band of a frequency
+pub fn band(hz: f64) -> Option<&'static str> {
+    let mhz = hz / 1e6;
+    BANDS.iter().find(|(_, low, high)| (*low..=*high).contains(&mhz)).map(|(name, _, _)| *name)
+}
+
+/// Append a field; characters ADI cannot carry become '?' and line breaks spaces
+fn field(out: &mut String, name: &str, value: &str) {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            '\r' | '\n' | '\t' => ' ',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '?',
+        })
+        .collect();
+    write!(out, "<{}:{}>{} ", name, value.len(), value).unwrap();
+}
+
+/// QSO_DATE and TIME_ON, or QSO_DATE_OFF and TIME_OFF, of a time
+fn date_time(out: &mut String, suffix: &str, unix: f64) {
+    let utc = timeutil::format_utc(unix);
+    field(out, &format!("QSO_DATE{}", suffix), &utc[..10].replace('-', ""));
+    field(out, &format!("TIME_{}", if suffix.is_empty() { "ON" } else { "OFF" }), &utc[11..19].replace(':', ""));
+}
+
+fn header(out: &mut String) {
+    out.push_str("Receptions of BOTAN exported by botan_cw_decoder\n");
+    field(out, "ADIF_VER", ADIF_VERSION);
+    field(out, "PROGRAMID", "botan_cw_decoder");
+    field(out, "PROGRAMVERSION", env!("CARGO_PKG_VERSION"));
+    let now = timeutil::format_utc(timeutil::now_unix());
+    field(out, "CREATED_TIMESTAMP", &format!("{} {}", &now[..10].replace('-', ""), &now[11..19].replace(':', "")));
+    out.push_str("<EOH>\n");
+}
+
+/// The fields every record has, after the times
+fn common(out: &mut String, frequency_hz: f64, station: &Station) {
+    if let Some(band) = band(frequency_hz) {
+        field(out, "BAND", band);
+    }
+    field(out, "FREQ", &format!("{:.6}", frequency_hz / 1e6));
+    field(out, "MODE", "CW");
+    field(out, "PROP_MODE", "SAT");
+    field(out, "SAT_NAME", "BOTAN");
+    field(out, "SWL", "Y");
+    if let Some(call) = &station.call {
+        field(out, "STATION_CALLSIGN", call);
+        field(out, "OPERATOR", call);
+    }
+    if let Some(grid) = &station.grid {
+        field(out, "MY_GRIDSQUARE", &grid.0);
+    }
+}
+
+/// The reception time of a frame this station decoded
+fn decoded(beacon: &Value) -> Option<f64> {
+    let received = (beacon["data"].is_object() || beacon["typed"].is_object()) && beacon["observer"].is_null();
+    received.then(|| beacon["time"].as_str().and_then(|t| timeutil::parse_utc(t).ok())).flatten()
+}
+
+/// Write the receptions of every pass under `dir` between `from` and `to` (exclusive) as ADIF,
+/// one record per pass or per frame; returns the number of records
+pub fn export(
+    dir: &Path,
+    from: Option<f64>,
+    to: Option<f64>,
+    records: Records,
+    station: &Station,
+    out: &mut impl Write,
+) -> Result<usize, String> {
+    let mut text = String::new();
+    header(&mut text);
+    let mut count = 0;
+    export::for_each_pass(dir, |pass| {
+        let frequency_hz = pass["frequency_hz"].as_f64().unwrap_or(BEACON_HZ);
+        let in_range = |time: &f64| from.is_none_or(|from| *time >= from) && to.is_none_or(|to| *time < to);
+        let beacons: Vec<(f64, &Value)> =
+            pass["beacons"].as_array().into_iter().flatten().filter_map(|b| Some((decoded(b)?, b))).filter(|(t, _)| in_range(t)).collect();
+        match records {
+            Records::Pass if !beacons.is_empty() => {
+                let time = |key: &str| pass[key].as_str().and_then(|t| timeutil::parse_utc(t).ok());
+                let (first, last) = (beacons[0].0, beacons[beacons.len() - 1].0);
+                field(&mut text, "CALL", CALL_SIGN);
+                date_time(&mut text, "", time("aos").unwrap_or(first).min(first));
+                date_time(&mut text, "_OFF", time("los").unwrap_or(last).max(last));
+                common(&mut text, frequency_hz, station);
+                let mut comment = format!("{} frame{}", beacons.len(), if beacons.len() == 1 { "" } else { "s" });
+                if let Some(elevation) = pass["max_elevation_deg"].as_f64() {
+                    write!(comment, ", max elevation {:.0} deg", elevation).unwrap();
+                }
+                if let Some(orbit) = beacons.iter().find_map(|(_, b)| b["orbit"].as_u64()) {
+                    write!(comment, ", orbit {}", orbit).unwrap();
+                }
+                field(&mut text, "COMMENT", &comment);
+                text.push_str("<EOR>\n");
+                count += 1;
+            }
+            Records::Pass => {}
+            Records::Frame => {
+                for (time, beacon) in beacons {
+                    field(&mut text, "CALL", CALL_SIGN);
+                    date_time(&mut text, "", time);
+                    common(&mut text, frequency_hz, station);
+                    field(&mut text, "COMMENT", beacon["text"].as_str().unwrap_or_default());
+                    text.push_str("<EOR>\n");
+                    count += 1;
+                }
+            }
+        }
+        Ok(())
+    })?;
+    out.write_all(text.as_bytes()).map_err(|e| format!("Cannot write ADIF: {}", e))?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::frames::Frame;
+    use crate::orbit::Pass;
+    use crate::scheduler::{self, PassRecord};
+
+    #[test]
+    fn test_export() {
+        let root = std::env::temp_dir().join(format!("botan_adif_test_{}", std::process::id()));
+        let pass = Pass {
+            aos_unix: 1714566896.0,
+            los_unix: 1714567496.0,
+            max_elevation_unix: 1714567196.0,
+            max_elevation_deg: 41.8,
+            aos_azimuth_deg: 10.0,
+            los_azimuth_deg: 170.0,
+        };
+        let mut record = PassRecord::new(&pass, Some(437_375_000.0));
+        for (i, line) in ["BOTAN JS1YPT A67C8D5E2AA13608", "BOTAN JS1YPT A67C", "BOTAN JS1YPT A57EB76823210F08"].iter().enumerate() {
+            record.add_beacon(pass.aos_unix + 60.0 * (i + 1) as f64, line, None, parse_botan_beacon(line).map(Frame::Housekeeping));
+        }
+        let directory = scheduler::pass_directory(&root, pass.aos_unix);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("pass.json"), serde_json::to_string(&record).unwrap()).unwrap();
+
+        let station = Station { call: Some("JA1XYZ".to_string()), grid: Some("pm95VQ".parse().unwrap()) };
+        let mut out = Vec::new();
+        assert_eq!(export(&root, None, None, Records::Pass, &station, &mut out), Ok(1));
+        let adif = String::from_utf8(out).unwrap();
+        assert!(adif.contains("<ADIF_VER:5>3.1.4 "));
+        assert!(adif.contains(
+            "<CALL:6>JS1YPT <QSO_DATE:8>20240501 <TIME_ON:6>123456 <QSO_DATE_OFF:8>20240501 <TIME_OFF:6>124456 \
+             <BAND:4>70cm <FREQ:10>437.375000 <MODE:2>CW <PROP_MODE:3>SAT <SAT_NAME:5>BOTAN <SWL:1>Y \
+             <STATION_CALLSIGN:6>JA1XYZ <OPERATOR:6>JA1XYZ <MY_GRIDSQUARE:6>PM95vq \
+             <COMMENT:30>2 frames, max elevation 42 deg <EOR>\n"
+        ));
+
+        // The line that failed to decode is not a reception
+        let mut out = Vec::new();
+        assert_eq!(export(&root, None, Some(pass.aos_unix + 150.0), Records::Frame, &Station::default(), &mut out), Ok(1));
+        let adif = String::from_utf8(out).unwrap();
+        assert!(adif.contains("<TIME_ON:6>123556 "));
+        assert!(adif.contains("<COMMENT:29>BOTAN JS1YPT A67C8D5E2AA13608 <EOR>"));
+        assert!(!adif.contains("MY_GRIDSQUARE"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(Grid::at(35.66, 139.74).0, "PM95up");
+        assert_eq!(Grid::at(-33.87, 151.21).0, "QF56od");
+        assert_eq!("fn31".parse(), Ok(Grid("FN31".to_string())));
+        assert!("SS00".parse::<Grid>().is_err());
+        assert!("PM9".parse::<Grid>().is_err());
+        assert_eq!(band(145.825e6), Some("2m"));
+        assert_eq!(band(10e9), None);
+    }
+}
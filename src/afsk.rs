@@ -0,0 +1,225 @@
+// Bell 202 AFSK demodulation of the 1200 baud packet downlink, from audio to AX.25 frames. Each
+// sample is correlated with the mark (1200 Hz) and space (2200 Hz) tones over one bit; the
+// difference of the two energies is the soft bit, which a digital PLL samples once per bit,
+// nudging its phase towards every transition it sees. The NRZI bits then go through HDLC
+// deframing: flags, bit unstuffing and the frame check sequence, which most noise fails.
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+
+use crate::ax25;
+
+const BAUD: f64 = 1200.0;
+const MARK_HZ: f64 = 1200.0;
+const SPACE_HZ: f64 = 2200.0;
+const FLAG: u8 = 0x7E;
+const MIN_FRAME: usize = 17;    // Two addresses, control and FCS
+const MAX_FRAME: usize = 330;
+const PLL_INERTIA: f64 = 0.75;  // Share of the phase error kept at each transition
+
+/// Sliding correlation with one tone over a bit period
+struct Correlator {
+    step: f64,
+    phase: f64,
+    products: VecDeque<(f32, f32)>,
+    sum: (f32, f32),
+    len: usize,
+}
+
+impl Correlator {
+    fn new(sample_rate: f64, tone_hz: f64, len: usize) -> Self {
+        Correlator { step: TAU * tone_hz / sample_rate, phase: 0.0, products: VecDeque::with_capacity(len + 1), sum: (0.0, 0.0), len }
+    }
+
+    /// Energy of the tone over the last bit period, including `sample`
+    fn push(&mut self, sample: f32) -> f32 {
+        let product = (sample * self.phase.cos() as f32, sample * self.phase.sin() as f32);
+        self.phase = (self.phase + self.step) % TAU;
+        self.products.push_back(product);
+        self.sum = (self.sum.0 + product.0, self.sum.1 + product.1);
+        if self.products.len() > self.len {
+            let (i, q) = self.products.pop_front().unwrap();
+            self.sum = (self.sum.0 - i, self.sum.1 - q);
+        }
+        self.sum.0 * self.sum.0 + self.sum.1 * self.sum.1
+    }
+}
+
+/// Collects HDLC frames from NRZI-decoded bits
+#[derive(Debug, Default)]
+struct Deframer {
+    pattern: u8,        // Last eight bits, the newest in the top bit
+    byte: u8,
+    bits: usize,        // Bits in `byte`
+    frame: Vec<u8>,
+    active: bool,       // Between a flag and an abort or overlong frame
+}
+
+impl Deframer {
+    /// Add one bit, returning a frame with a valid FCS when a flag completes it
+    fn push(&mut self, bit: bool) -> Option<Vec<u8>> {
+        self.pattern = self.pattern >> 1 | (bit as u8) << 7;
+        if self.pattern == FLAG {
+            // The flag's first seven bits went into `byte`, so a whole frame leaves exactly seven
+            let frame = std::mem::take(&mut self.frame);
+            let done = self.active && self.bits == 7 && frame.len() >= MIN_FRAME;
+            self.active = true;
+            self.bits = 0;
+            return done.then_some(frame).filter(|frame| {
+                let (body, check) = frame.split_at(frame.len() - 2);
+                ax25::fcs(body).to_le_bytes() == check
+            });
+        }
+        if self.pattern == 0xFE {
+            // Seven ones: abort
+            self.active = false;
+            return None;
+        }
+        if self.pattern & 0xFC == 0x7C {
+            // A zero stuffed after five ones
+            return None;
+        }
+        if self.active {
+            self.byte = self.byte >> 1 | (bit as u8) << 7;
+            self.bits += 1;
+            if self.bits == 8 {
+                self.frame.push(self.byte);
+                self.bits = 0;
+                if self.frame.len() > MAX_FRAME {
+                    self.active = false;
+                    self.frame.clear();
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Demodulates AFSK audio into AX.25 frames, FCS included
+pub struct AfskDemodulator {
+    mark: Correlator,
+    space: Correlator,
+    step: f64,          // Bit clock advance per sample
+    clock: f64,         // -0.5 to 0.5; a bit is sampled when it wraps
+    level: bool,        // Current soft bit sign
+    last_bit: bool,     // Previous sampled line level, for NRZI
+    deframer: Deframer,
+}
+
+impl AfskDemodulator {
+    pub fn new(sample_rate: f64) -> Self {
+        let len = (sample_rate / BAUD).round().max(1.0) as usize;
+        AfskDemodulator {
+            mark: Correlator::new(sample_rate, MARK_HZ, len),
+            space: Correlator::new(sample_rate, SPACE_HZ, len),
+            step: BAUD / sample_rate,
+            clock: 0.0,
+            level: false,
+            last_bit: false,
+            deframer: Deframer::default(),
+        }
+    }
+
+    /// Process audio samples, returning the frames completed in them
+    pub fn process(&mut self, samples: &[f32]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &sample in samples {
+            let mark = self.mark.push(sample);
+            let space = self.space.push(sample);
+            let level = mark > space;
+            if level != self.level {
+                self.level = level;
+                self.clock *= PLL_INERTIA;
+            }
+
+            self.clock += self.step;
+            if self.clock >= 0.5 {
+                self.clock -= 1.0;
+                // NRZI: no change is a one, a change a zero
+                let bit = level == self.last_bit;
+                self.last_bit = level;
+                frames.extend(self.deframer.push(bit));
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// AFSK audio of `frame` followed by its FCS, between flags
+    fn modulate(frame: &[u8], sample_rate: f64) -> Vec<f32> {
+        let mut frame = frame.to_vec();
+        frame.extend(ax25::fcs(&frame).to_le_bytes());
+
+        let flag: Vec<bool> = (0..8).map(|i| FLAG >> i & 1 == 1).collect();
+        let mut bits: Vec<bool> = flag.repeat(20);
+        let mut ones = 0;
+        for bit in frame.iter().flat_map(|&byte| (0..8).map(move |i| byte >> i & 1 == 1)) {
+            bits.push(bit);
+            ones = if bit { ones + 1 } else { 0 };
+            if ones == 5 {
+                bits.push(false);
+                ones = 0;
+            }
+        }
+        bits.extend(flag.repeat(4));
+
+        let (mut samples, mut phase, mut tone, mut time) = (Vec::new(), 0.0f64, true, 0.0);
+        for bit in bits {
+            if !bit {
+                tone = !tone;
+            }
+            let freq = if tone { MARK_HZ } else { SPACE_HZ };
+            time += sample_rate / BAUD;
+            while (samples.len() as f64) < time {
+                samples.push(0.5 * phase.sin() as f32);
+                phase += TAU * freq / sample_rate;
+            }
+        }
+        samples
+    }
+
+    fn ui_frame(info: &[u8]) -> Vec<u8> {
+        let mut frame: Vec<u8> = b"CQ    ".iter().map(|b| b << 1).collect();
+        frame.push(0x60);
+        frame.extend(b"JS1YPT".iter().map(|b| b << 1));
+        frame.push(0x61);
+        frame.extend([0x03, 0xF0]);
+        frame.extend(info);
+        frame
+    }
+
+    #[test]
+    fn test_demodulate() {
+        // Stuffed bits in the payload, and 22050 Hz for a fractional number of samples per bit
+        let frame = ui_frame(b"BOTAN JS1YPT A67C8D5E2AA13608 \xFF\xFF\x7E");
+        for sample_rate in [48000.0, 22050.0] {
+            let mut demod = AfskDemodulator::new(sample_rate);
+            let frames = demod.process(&modulate(&frame, sample_rate));
+            assert_eq!(frames.len(), 1, "{}", sample_rate);
+            let ui = ax25::parse_ui(&frames[0], true).unwrap();
+            assert_eq!(ui.info, frame[16..]);
+        }
+    }
+
+    #[test]
+    fn test_demodulate_noise() {
+        let frame = ui_frame(b"BOTAN JS1YPT A67C8D5E2AA13608");
+        let mut samples = modulate(&frame, 48000.0);
+        // Uniform noise at about 6 dB below the signal
+        let mut state = 1u32;
+        for sample in &mut samples {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            *sample += (state >> 8) as f32 / (1 << 24) as f32 * 0.6 - 0.3;
+        }
+        // Fed in small blocks, as from a live source
+        let mut demod = AfskDemodulator::new(48000.0);
+        let frames: Vec<Vec<u8>> = samples.chunks(100).flat_map(|block| demod.process(block)).collect();
+        assert_eq!(frames.len(), 1);
+
+        // Silence and noise alone give no frames
+        assert!(AfskDemodulator::new(48000.0).process(&[0.0; 48000]).is_empty());
+    }
+}
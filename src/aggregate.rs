@@ -0,0 +1,277 @@
+// Downsampled telemetry history for `aggregate`: the frames `export` would write are grouped
+// into fixed intervals counted from the Unix epoch (so 1d buckets are UTC days), and every
+// numeric channel is reduced to the requested statistics per interval. Channels are the numeric
+// export columns plus the fields of typed frames, named "frame_type.field". Intervals without a
+// decoded frame are left out rather than written as empty rows.
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::export::{self, csv_value};
+use crate::parquet::ColumnType;
+use crate::timeutil;
+
+/// Length of the aggregation interval in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval(pub f64);
+
+impl FromStr for Interval {
+    type Err = String;
+
+    /// "90s", "15m", "1h", "1d", "1w" or plain seconds
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid interval '{}'. Expected a duration such as 15m, 1h or 1d", s);
+        let s = s.trim();
+        let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let scale = match unit.to_ascii_lowercase().as_str() {
+            "" | "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            "w" => 7.0 * 86400.0,
+            _ => return Err(err()),
+        };
+        match number.parse::<f64>() {
+            Ok(n) if n > 0.0 && n.is_finite() => Ok(Interval(n * scale)),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// A statistic of the values of one channel in one interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    Count,
+    Mean,
+    Min,
+    Max,
+    Median,
+    Std,
+    First,
+    Last,
+}
+
+impl FromStr for Stat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "count" => Ok(Stat::Count),
+            "mean" | "avg" => Ok(Stat::Mean),
+            "min" => Ok(Stat::Min),
+            "max" => Ok(Stat::Max),
+            "median" => Ok(Stat::Median),
+            "std" => Ok(Stat::Std),
+            "first" => Ok(Stat::First),
+            "last" => Ok(Stat::Last),
+            _ => Err(format!("Unknown statistic '{}'. Expected count, mean, min, max, median, std, first or last", s)),
+        }
+    }
+}
+
+impl Stat {
+    fn name(self) -> &'static str {
+        match self {
+            Stat::Count => "count",
+            Stat::Mean => "mean",
+            Stat::Min => "min",
+            Stat::Max => "max",
+            Stat::Median => "median",
+            Stat::Std => "std",
+            Stat::First => "first",
+            Stat::Last => "last",
+        }
+    }
+
+    /// The statistic of `values` in reception order, which are never empty
+    pub fn of(self, values: &[f64]) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        match self {
+            Stat::Count => values.len() as f64,
+            Stat::Mean => mean,
+            Stat::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Stat::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Stat::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] }
+            }
+            // Population standard deviation
+            Stat::Std => (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt(),
+            Stat::First => values[0],
+            Stat::Last => values[values.len() - 1],
+        }
+    }
+}
+
+/// The frames of one interval: how many, and the values of each channel by channel index
+#[derive(Default)]
+struct Bucket {
+    frames: usize,
+    values: BTreeMap<usize, Vec<f64>>,
+}
+
+/// Numeric channels of one export row, by name
+pub(crate) fn row_channels(row: &[String]) -> Vec<(String, f64)> {
+    let mut channels = Vec::new();
+    let columns = export::columns();
+    // The orbit number is a count, not a measurement
+    for (name, value) in columns.iter().zip(row).filter(|(name, _)| **name != "orbit") {
+        if matches!(export::column_type(name), ColumnType::Int32 | ColumnType::Double) {
+            if let Ok(value) = value.parse::<f64>() {
+                channels.push((name.to_string(), value));
+            }
+        }
+    }
+    let column = |name: &str| columns.iter().position(|c| *c == name).map(|idx| row[idx].as_str()).unwrap_or_default();
+    for pair in column("fields").split(';') {
+        if let Some((name, value)) = pair.split_once('=') {
+            if let Ok(value) = value.parse::<f64>() {
+                channels.push((format!("{}.{}", column("frame_type"), name), value));
+            }
+        }
+    }
+    channels
+}
+
+/// Round away float noise such as 4.279600000000001
+pub(crate) fn number(value: f64) -> String {
+    ((value * 1e6).round() / 1e6).to_string()
+}
+
+/// Write the `stats` of every channel, or of `channels` when given, per `interval` over the
+/// history under `dir` between `from` and `to` as CSV; returns the number of intervals
+pub fn aggregate(
+    dir: &Path,
+    from: Option<f64>,
+    to: Option<f64>,
+    interval: Interval,
+    stats: &[Stat],
+    channels: &[String],
+    out: &mut impl Write,
+) -> Result<usize, String> {
+    let mut names: Vec<String> = channels.to_vec();
+    let mut buckets: BTreeMap<i64, Bucket> = BTreeMap::new();
+    export::for_each_row(dir, from, to, |row| {
+        // Lines that did not decode have no frame
+        if row[3].is_empty() {
+            return Ok(());
+        }
+        let Ok(time) = timeutil::parse_utc(&row[0]) else {
+            return Ok(());
+        };
+        let bucket = buckets.entry((time / interval.0).floor() as i64).or_default();
+        bucket.frames += 1;
+        for (name, value) in row_channels(&row) {
+            let idx = match names.iter().position(|n| *n == name) {
+                Some(idx) => idx,
+                None if channels.is_empty() => {
+                    names.push(name);
+                    names.len() - 1
+                }
+                None => continue,
+            };
+            bucket.values.entry(idx).or_default().push(value);
+        }
+        Ok(())
+    })?;
+    // A requested channel without a single value is most likely misspelt
+    if !buckets.is_empty() {
+        if let Some(idx) = (0..names.len()).find(|idx| !buckets.values().any(|bucket| bucket.values.contains_key(idx))) {
+            return Err(format!("Channel '{}' has no values in the history", names[idx]));
+        }
+    }
+
+    let write_error = |e: std::io::Error| format!("Cannot write aggregate: {}", e);
+    let mut header = vec!["time".to_string(), "frames".to_string()];
+    header.extend(names.iter().flat_map(|name| stats.iter().map(move |stat| format!("{}_{}", name, stat.name()))));
+    writeln!(out, "{}", header.iter().map(|h| csv_value(h)).collect::<Vec<_>>().join(",")).map_err(write_error)?;
+    for (key, bucket) in &buckets {
+        let mut row = vec![timeutil::format_display(*key as f64 * interval.0), bucket.frames.to_string()];
+        for idx in 0..names.len() {
+            let values = bucket.values.get(&idx);
+            row.extend(stats.iter().map(|stat| values.map(|values| number(stat.of(values))).unwrap_or_default()));
+        }
+        writeln!(out, "{}", row.join(",")).map_err(write_error)?;
+    }
+    Ok(buckets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::frames::Frame;
+    use crate::orbit::Pass;
+    use crate::scheduler::{save_pass, PassRecord};
+
+    #[test]
+    fn test_interval() {
+        assert_eq!("1h".parse(), Ok(Interval(3600.0)));
+        assert_eq!("15m".parse(), Ok(Interval(900.0)));
+        assert_eq!("1d".parse(), Ok(Interval(86400.0)));
+        assert_eq!("90".parse(), Ok(Interval(90.0)));
+        assert!("0h".parse::<Interval>().is_err());
+        assert!("1y".parse::<Interval>().is_err());
+        assert!("h".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let values = [4.0, 1.0, 3.0, 2.0];
+        let of = |s: &str| s.parse::<Stat>().unwrap().of(&values);
+        assert_eq!([of("count"), of("mean"), of("min"), of("max"), of("median")], [4.0, 2.5, 1.0, 4.0, 2.5]);
+        assert_eq!([of("first"), of("last")], [4.0, 2.0]);
+        assert!((of("std") - 1.25f64.sqrt()).abs() < 1e-12);
+        assert!("mode".parse::<Stat>().is_err());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let root = std::env::temp_dir().join(format!("botan_aggregate_test_{}", std::process::id()));
+        let aos = 1714564800.0;    // 2024-05-01T12:00:00Z
+        let pass = Pass {
+            aos_unix: aos,
+            los_unix: aos + 7200.0,
+            max_elevation_unix: aos + 3600.0,
+            max_elevation_deg: 42.5,
+            aos_azimuth_deg: 10.0,
+            los_azimuth_deg: 170.0,
+        };
+        let mut record = PassRecord::new(&pass, None);
+        // Two frames in the first hour, a failed line and one frame in the second
+        for (offset, line) in [
+            (60.0, "BOTAN JS1YPT SI8640 A67C8D5E2AA13608"),
+            (120.0, "BOTAN JS1YPT SI9040 A57EB76823210E08"),
+            (3660.0, "BOTAN JS1YPT A67C"),
+            (3720.0, "BOTAN JS1YPT A57EB76823210E08"),
+        ] {
+            record.add_beacon(aos + offset, line, None, parse_botan_beacon(line).map(Frame::Housekeeping));
+        }
+        save_pass(&root, aos, &record, None).unwrap();
+
+        let stats = [Stat::Min, Stat::Max];
+        let mut out = Vec::new();
+        let channels = ["bat_v".to_string(), "raw_rssi".to_string()];
+        assert_eq!(aggregate(&root, None, None, Interval(3600.0), &stats, &channels, &mut out), Ok(2));
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "time,frames,bat_v_min,bat_v_max,raw_rssi_min,raw_rssi_max");
+        assert_eq!(lines[1], "2024-05-01T12:00:00Z,2,4.253865,4.279646,134,144");
+        assert_eq!(lines[2], "2024-05-01T13:00:00Z,1,4.253865,4.253865,,");
+
+        // Every channel with a value, in one daily bucket
+        let mut out = Vec::new();
+        assert_eq!(aggregate(&root, None, None, Interval(86400.0), &[Stat::Count], &[], &mut out), Ok(1));
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.starts_with("time,frames,raw_rssi_count,raw_snr_count,bat_v_count,"));
+        assert!(csv.contains("\n2024-05-01T00:00:00Z,3,2,2,3,"));
+
+        let unknown = ["bat_w".to_string()];
+        assert!(aggregate(&root, None, None, Interval(3600.0), &stats, &unknown, &mut Vec::new()).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+// Audible alerts, for operators who cannot watch the screen while pointing an antenna. A decoded
+// frame plays one sound and a frame with a value outside --limits another; by default these are
+// CW sidetones, "R" for a decode and "SOS" at a higher pitch for an alarm. Sounds are played by
+// afplay on macOS, otherwise by paplay or aplay, whichever is installed.
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+use crate::cw_synth::{self, CwSynthConfig};
+use crate::frames::Frame;
+use crate::limits::Limits;
+use crate::logging::debug;
+use crate::wav;
+
+const DECODE_MORSE: &str = ".-.";
+const ALARM_MORSE: &str = "... --- ...";
+
+/// Plays a sound for every frame printed
+#[derive(Debug, Clone)]
+pub struct Alert {
+    decode: PathBuf,
+    alarm: PathBuf,
+    limits: Limits,
+}
+
+impl Alert {
+    /// Sound files for decodes and alarms; built-in tones stand in for the ones not given
+    pub fn new(decode: Option<&str>, alarm: Option<&str>, limits: Limits) -> Result<Self, String> {
+        let decode = match decode {
+            Some(path) => PathBuf::from(path),
+            None => write_tone("decode", DECODE_MORSE, 800.0)?,
+        };
+        let alarm = match alarm {
+            Some(path) => PathBuf::from(path),
+            None => write_tone("alarm", ALARM_MORSE, 1200.0)?,
+        };
+        for path in [&decode, &alarm] {
+            if !path.is_file() {
+                return Err(format!("Cannot read sound '{}'", path.display()));
+            }
+        }
+        Ok(Alert { decode, alarm, limits })
+    }
+
+    /// The sound for `frame`
+    fn sound(&self, frame: &Frame) -> &PathBuf {
+        match frame {
+            Frame::Housekeeping(data) if !self.limits.alarms(&data.telemetry).is_empty() => &self.alarm,
+            _ => &self.decode,
+        }
+    }
+
+    pub fn play(&self, frame: &Frame) {
+        let sound = self.sound(frame).clone();
+        // Playback takes a second or two; don't hold up decoding
+        thread::spawn(move || {
+            if let Err(error) = play_sound(&sound) {
+                debug!("sound playback failed"; sound = sound.display(), error = error);
+            }
+        });
+    }
+}
+
+/// Play a sound file with the first player installed, returning when it has finished
+pub fn play_sound(sound: &Path) -> Result<(), String> {
+    for player in players() {
+        match Command::new(player).arg(sound).status() {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("{} failed: {}", player, error)),
+            Ok(_) => return Ok(()),
+        }
+    }
+    Err(format!("No sound player found (tried {})", players().join(", ")))
+}
+
+fn players() -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        &["afplay"]
+    } else {
+        &["paplay", "aplay"]
+    }
+}
+
+/// Write a built-in tone to the temporary directory
+fn write_tone(name: &str, morse: &str, pitch_hz: f64) -> Result<PathBuf, String> {
+    let config = CwSynthConfig { wpm: 25.0, pitch_hz, sample_rate: 22050, amplitude: 0.5, ..Default::default() };
+    let path = std::env::temp_dir().join(format!("botan_cw_decoder_{}.wav", name));
+    wav::write_wav_mono16(&path.to_string_lossy(), config.sample_rate, &cw_synth::synthesize(morse, &config))
+        .map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_sound_choice() {
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.0}}"#).unwrap();
+        let alert = Alert::new(None, None, limits).unwrap();
+        assert_eq!(wav::WavReader::open(&alert.alarm.to_string_lossy()).unwrap().sample_rate(), 22050);
+
+        let normal = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap());
+        let low_battery = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT 807C8D5E2AA13608").unwrap());
+        assert_eq!(alert.sound(&normal), &alert.decode);
+        assert_eq!(alert.sound(&low_battery), &alert.alarm);
+        assert!(Alert::new(Some("/nonexistent.wav"), None, limits).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+// Line-by-line reading of beacon archives too large to load into memory, such as a year of
+// SatNOGS exports on a ground-station SBC. On Unix the file is memory-mapped read-only and
+// scanned sequentially, so the kernel pages it in and drops it again as needed; elsewhere it
+// is streamed through a buffered reader.
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `path` if it is a file, or every file under it in name order, e.g. a directory of captures
+/// with one file per day
+pub fn files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?.map(|entry| entry.map(|e| e.path())).collect::<io::Result<_>>()?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        files.extend(files_in(&entry)?);
+    }
+    Ok(files)
+}
+
+fn files_in(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_dir() {
+        files(path)
+    } else {
+        Ok(vec![path.to_path_buf()])
+    }
+}
+
+/// Call `f` with the 1-based number and text of every line; bytes that are not UTF-8 are replaced
+pub fn for_each_line(path: &Path, mut f: impl FnMut(usize, &str)) -> io::Result<()> {
+    let file = File::open(path)?;
+    #[cfg(unix)]
+    {
+        if let Some(map) = Mmap::new(&file)? {
+            // A final newline ends the last line rather than starting an empty one
+            let bytes = map.as_bytes();
+            let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+            for (index, line) in bytes.split(|&b| b == b'\n').enumerate() {
+                f(index + 1, trim_line(&String::from_utf8_lossy(line)));
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        use std::io::BufRead;
+        let mut reader = io::BufReader::new(file);
+        let mut buf = Vec::new();
+        let mut number = 0;
+        while reader.read_until(b'\n', &mut buf)? > 0 {
+            number += 1;
+            f(number, trim_line(&String::from_utf8_lossy(&buf)));
+            buf.clear();
+        }
+        Ok(())
+    }
+}
+
+fn trim_line(line: &str) -> &str {
+    line.trim_end_matches(['\r', '\n'])
+}
+
+/// Read-only private mapping of a whole file
+#[cfg(unix)]
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mmap {
+    /// Map `file`; None for an empty file, which cannot be mapped
+    fn new(file: &File) -> io::Result<Option<Self>> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        if len == 0 {
+            return Ok(None);
+        }
+        // SAFETY: a fresh read-only mapping of an open file; checked for MAP_FAILED below
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        // Only a hint: read ahead aggressively and free pages behind the scan
+        unsafe { libc::madvise(ptr, len, libc::MADV_SEQUENTIAL) };
+        Ok(Some(Mmap { ptr, len }))
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the mapping is valid for `len` bytes until dropped. Archives are not expected
+        // to be truncated while being read; if one is, reading past the new end raises SIGBUS.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_of(content: &[u8]) -> Vec<(usize, String)> {
+        let path = std::env::temp_dir().join(format!("botan_archive_test_{}_{}", std::process::id(), content.len()));
+        std::fs::write(&path, content).unwrap();
+        let mut lines = Vec::new();
+        for_each_line(&path, |number, line| lines.push((number, line.to_string()))).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_for_each_line() {
+        let lines = lines_of(b"BOTAN JS1YPT A67C8D5E2AA13608\r\n\nnoise \xff\nlast");
+        assert_eq!(
+            lines,
+            [
+                (1, "BOTAN JS1YPT A67C8D5E2AA13608".to_string()),
+                (2, String::new()),
+                (3, "noise \u{FFFD}".to_string()),
+                (4, "last".to_string()),
+            ]
+        );
+        assert_eq!(lines_of(b"one\ntwo\n").len(), 2);
+        assert!(lines_of(b"").is_empty());
+    }
+
+    #[test]
+    fn test_files() {
+        let root = std::env::temp_dir().join(format!("botan_archive_files_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("2024-05")).unwrap();
+        for name in ["b.kiss", "2024-05/a.kiss", "a.kiss"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        let found = files(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(found, [root.join("2024-05/a.kiss"), root.join("a.kiss"), root.join("b.kiss")]);
+        assert_eq!(files(Path::new("capture.kiss")).unwrap(), [PathBuf::from("capture.kiss")]);
+    }
+}
@@ -0,0 +1,431 @@
+// Arrow IPC streams for `export --format arrow` (the `arrow` feature): the same typed columns as
+// Parquet, as record batches pyarrow, polars or R's arrow package read straight into memory
+// without parsing, e.g. from a pipe. A stream is a schema message, one record batch message per
+// BATCH_ROWS rows and an end marker. Each message is
+//
+//   0xFFFFFFFF | metadata length | Message flatbuffer, padded to 8 bytes | body
+//
+// where the body holds the column buffers (validity bitmap, then values or offsets and UTF-8
+// data), each padded to 8 bytes. The flatbuffers are laid out front to back by `Builder`.
+use std::io::{self, Write};
+
+use crate::parquet::{Cell, ColumnType};
+
+pub const BATCH_ROWS: usize = 65536;
+
+const CONTINUATION: [u8; 4] = [0xFF; 4];
+const METADATA_V5: i16 = 4;
+
+/// MessageHeader union members
+const SCHEMA: u8 = 1;
+const RECORD_BATCH: u8 = 3;
+
+/// A flatbuffer table member, in its slot of the table's schema
+enum Member {
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Bool(bool),
+    Str(String),
+    Table(Table),
+    Tables(Vec<Table>),
+    Structs(Vec<[i64; 2]>),    // FieldNode and Buffer are both two longs
+}
+
+impl Member {
+    /// Inline size, which is also the alignment
+    fn size(&self) -> usize {
+        match self {
+            Member::U8(_) | Member::Bool(_) => 1,
+            Member::I16(_) => 2,
+            Member::I64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+type Table = Vec<(u16, Member)>;
+
+/// Writes flatbuffers front to back: every table is preceded by its vtable and followed by the
+/// strings, vectors and tables it refers to, so all offsets point forward as they must
+struct Builder(Vec<u8>);
+
+impl Builder {
+    fn align(&mut self, alignment: usize) {
+        while !self.0.len().is_multiple_of(alignment) {
+            self.0.push(0);
+        }
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        self.0[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn table(&mut self, table: &Table) -> usize {
+        // Member positions after the vtable offset, each aligned to its size
+        let mut end = 4usize;
+        let positions: Vec<usize> = table
+            .iter()
+            .map(|(_, member)| {
+                let position = end.next_multiple_of(member.size());
+                end = position + member.size();
+                position
+            })
+            .collect();
+
+        self.align(2);
+        let vtable = self.0.len();
+        let slots = table.iter().map(|(slot, _)| *slot as usize + 1).max().unwrap_or(0);
+        let mut entries = vec![0u16; slots];
+        for ((slot, _), position) in table.iter().zip(&positions) {
+            entries[*slot as usize] = *position as u16;
+        }
+        for entry in [(4 + 2 * slots) as u16, end as u16].into_iter().chain(entries) {
+            self.0.extend_from_slice(&entry.to_le_bytes());
+        }
+
+        self.align(8);
+        let start = self.0.len();
+        self.0.resize(start + end, 0);
+        self.0[start..start + 4].copy_from_slice(&((start - vtable) as i32).to_le_bytes());
+        let mut references = Vec::new();
+        for ((_, member), position) in table.iter().zip(positions) {
+            let at = start + position;
+            let bytes = match member {
+                Member::U8(value) => vec![*value],
+                Member::Bool(value) => vec![*value as u8],
+                Member::I16(value) => value.to_le_bytes().to_vec(),
+                Member::I32(value) => value.to_le_bytes().to_vec(),
+                Member::I64(value) => value.to_le_bytes().to_vec(),
+                _ => {
+                    references.push((at, member));
+                    continue;
+                }
+            };
+            self.0[at..at + bytes.len()].copy_from_slice(&bytes);
+        }
+        for (at, member) in references {
+            let target = self.reference(member);
+            self.patch(at, target);
+        }
+        start
+    }
+
+    /// A string, vector or table referred to from a table; returns where it starts
+    fn reference(&mut self, member: &Member) -> usize {
+        match member {
+            Member::Table(table) => self.table(table),
+            Member::Str(text) => {
+                self.align(4);
+                let start = self.0.len();
+                self.u32(text.len() as u32);
+                self.0.extend_from_slice(text.as_bytes());
+                self.0.push(0);
+                start
+            }
+            Member::Tables(tables) => {
+                self.align(4);
+                let start = self.0.len();
+                self.u32(tables.len() as u32);
+                let slots = self.0.len();
+                self.0.resize(slots + 4 * tables.len(), 0);
+                for (idx, table) in tables.iter().enumerate() {
+                    let target = self.table(table);
+                    self.patch(slots + 4 * idx, target);
+                }
+                start
+            }
+            Member::Structs(structs) => {
+                // The elements are 8-byte aligned, after the 4-byte length
+                self.align(4);
+                if self.0.len().is_multiple_of(8) {
+                    self.u32(0);
+                }
+                let start = self.0.len();
+                self.u32(structs.len() as u32);
+                for value in structs.iter().flatten() {
+                    self.0.extend_from_slice(&value.to_le_bytes());
+                }
+                start
+            }
+            _ => unreachable!("scalars are inline"),
+        }
+    }
+
+    /// A whole buffer with `root` as its root table, padded to 8 bytes
+    fn finish(root: &Table) -> Vec<u8> {
+        let mut builder = Builder(vec![0; 4]);
+        let start = builder.table(root);
+        builder.patch(0, start);
+        builder.align(8);
+        builder.0
+    }
+}
+
+/// The Arrow type of a column: Type union member and its table
+fn arrow_type(kind: ColumnType) -> (u8, Table) {
+    match kind {
+        ColumnType::Boolean => (6, vec![]),
+        ColumnType::Int32 => (2, vec![(0, Member::I32(32)), (1, Member::Bool(true))]),
+        ColumnType::Double => (3, vec![(0, Member::I16(2))]),    // DOUBLE precision
+        ColumnType::Text => (5, vec![]),
+        // Milliseconds, UTC
+        ColumnType::Timestamp => (10, vec![(0, Member::I16(1)), (1, Member::Str("UTC".to_string()))]),
+    }
+}
+
+fn write_message(out: &mut impl Write, header_type: u8, header: Table, body: &[u8]) -> io::Result<()> {
+    let message = vec![
+        (0, Member::I16(METADATA_V5)),
+        (1, Member::U8(header_type)),
+        (2, Member::Table(header)),
+        (3, Member::I64(body.len() as i64)),
+    ];
+    let metadata = Builder::finish(&message);
+    out.write_all(&CONTINUATION)?;
+    out.write_all(&(metadata.len() as u32).to_le_bytes())?;
+    out.write_all(&metadata)?;
+    out.write_all(body)
+}
+
+/// Bit-pack `bits` LSB first
+fn bitmap(bits: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for (idx, bit) in bits.enumerate() {
+        if idx % 8 == 0 {
+            packed.push(0);
+        }
+        packed[idx / 8] |= (bit as u8) << (idx % 8);
+    }
+    packed
+}
+
+/// Streams rows as record batches of BATCH_ROWS rows
+pub struct StreamWriter {
+    columns: Vec<(String, ColumnType)>,
+    rows: Vec<Vec<Option<Cell>>>,
+}
+
+impl StreamWriter {
+    /// Start a stream of `columns` (names and types) by writing its schema
+    pub fn start(columns: &[(&str, ColumnType)], out: &mut impl Write) -> io::Result<Self> {
+        let fields = columns
+            .iter()
+            .map(|&(name, kind)| {
+                let (type_type, table) = arrow_type(kind);
+                vec![
+                    (0, Member::Str(name.to_string())),
+                    (1, Member::Bool(true)),    // Nullable
+                    (2, Member::U8(type_type)),
+                    (3, Member::Table(table)),
+                    (5, Member::Tables(vec![])),    // No children
+                ]
+            })
+            .collect();
+        write_message(out, SCHEMA, vec![(0, Member::I16(0)), (1, Member::Tables(fields))], &[])?;
+        let columns = columns.iter().map(|&(name, kind)| (name.to_string(), kind)).collect();
+        Ok(StreamWriter { columns, rows: Vec::new() })
+    }
+
+    /// Add a row, writing a record batch once BATCH_ROWS are buffered
+    pub fn push(&mut self, row: Vec<Option<Cell>>, out: &mut impl Write) -> io::Result<()> {
+        self.rows.push(row);
+        if self.rows.len() == BATCH_ROWS {
+            self.flush(out)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut body = Vec::new();
+        let mut nodes = Vec::new();
+        let mut buffers = Vec::new();
+        let mut add_buffer = |body: &mut Vec<u8>, bytes: Vec<u8>| {
+            buffers.push([body.len() as i64, bytes.len() as i64]);
+            body.extend(bytes);
+            body.resize(body.len().next_multiple_of(8), 0);
+        };
+        for (idx, (_, kind)) in self.columns.iter().enumerate() {
+            let cells: Vec<Option<&Cell>> = self.rows.iter().map(|row| row[idx].as_ref()).collect();
+            nodes.push([cells.len() as i64, cells.iter().filter(|cell| cell.is_none()).count() as i64]);
+            add_buffer(&mut body, bitmap(cells.iter().map(Option::is_some)));
+            if *kind == ColumnType::Text {
+                let (mut offsets, mut data) = (0i32.to_le_bytes().to_vec(), Vec::new());
+                for cell in &cells {
+                    if let Some(Cell::Text(text)) = cell {
+                        data.extend_from_slice(text.as_bytes());
+                    }
+                    offsets.extend_from_slice(&(data.len() as i32).to_le_bytes());
+                }
+                add_buffer(&mut body, offsets);
+                add_buffer(&mut body, data);
+                continue;
+            }
+            let values = match kind {
+                ColumnType::Boolean => bitmap(cells.iter().map(|cell| *cell == Some(&Cell::Boolean(true)))),
+                _ => cells
+                    .iter()
+                    .flat_map(|cell| match cell {
+                        Some(Cell::Int32(value)) => value.to_le_bytes().to_vec(),
+                        Some(Cell::Double(value)) => value.to_le_bytes().to_vec(),
+                        Some(Cell::Timestamp(value)) => value.to_le_bytes().to_vec(),
+                        _ => vec![0; if *kind == ColumnType::Int32 { 4 } else { 8 }],
+                    })
+                    .collect(),
+            };
+            add_buffer(&mut body, values);
+        }
+        let batch = vec![(0, Member::I64(self.rows.len() as i64)), (1, Member::Structs(nodes)), (2, Member::Structs(buffers))];
+        self.rows.clear();
+        write_message(out, RECORD_BATCH, batch, &body)
+    }
+
+    /// Write the rows still buffered and end the stream
+    pub fn finish(mut self, out: &mut impl Write) -> io::Result<()> {
+        if !self.rows.is_empty() {
+            self.flush(out)?;
+        }
+        out.write_all(&CONTINUATION)?;
+        out.write_all(&[0; 4])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads tables of a flatbuffer
+    #[derive(Clone, Copy)]
+    struct TableRef<'a> {
+        buf: &'a [u8],
+        start: usize,
+    }
+
+    impl<'a> TableRef<'a> {
+        fn root(buf: &'a [u8]) -> Self {
+            TableRef { buf, start: u32_at(buf, 0) }
+        }
+
+        fn position(&self, slot: usize) -> Option<usize> {
+            let vtable = self.start - i32::from_le_bytes(self.buf[self.start..self.start + 4].try_into().unwrap()) as usize;
+            let size = u16::from_le_bytes(self.buf[vtable..vtable + 2].try_into().unwrap()) as usize;
+            let entry = vtable + 4 + 2 * slot;
+            let offset = if entry < vtable + size { u16::from_le_bytes(self.buf[entry..entry + 2].try_into().unwrap()) } else { 0 };
+            (offset != 0).then_some(self.start + offset as usize)
+        }
+
+        fn scalar<const N: usize>(&self, slot: usize) -> [u8; N] {
+            let at = self.position(slot).unwrap();
+            assert_eq!(at % N, 0, "misaligned member");
+            self.buf[at..at + N].try_into().unwrap()
+        }
+
+        fn target(&self, slot: usize) -> usize {
+            let at = self.position(slot).unwrap();
+            at + u32_at(self.buf, at)
+        }
+
+        fn table(&self, slot: usize) -> TableRef<'a> {
+            TableRef { buf: self.buf, start: self.target(slot) }
+        }
+
+        fn string(&self, slot: usize) -> &'a str {
+            let at = self.target(slot);
+            std::str::from_utf8(&self.buf[at + 4..at + 4 + u32_at(self.buf, at)]).unwrap()
+        }
+
+        fn tables(&self, slot: usize) -> Vec<TableRef<'a>> {
+            let at = self.target(slot);
+            (0..u32_at(self.buf, at)).map(|idx| at + 4 + 4 * idx).map(|at| TableRef { buf: self.buf, start: at + u32_at(self.buf, at) }).collect()
+        }
+
+        fn structs(&self, slot: usize) -> Vec<[i64; 2]> {
+            let at = self.target(slot);
+            assert_eq!((at + 4) % 8, 0, "misaligned structs");
+            (0..u32_at(self.buf, at)).map(|idx| at + 4 + 16 * idx).map(|at| [i64_at(self.buf, at), i64_at(self.buf, at + 8)]).collect()
+        }
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    fn i64_at(buf: &[u8], at: usize) -> i64 {
+        i64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+    }
+
+    /// Split a stream into (metadata, body) messages
+    fn messages(mut stream: &[u8]) -> Vec<(&[u8], &[u8])> {
+        let mut messages = Vec::new();
+        loop {
+            assert_eq!(stream[..4], CONTINUATION);
+            let length = u32_at(stream, 4);
+            if length == 0 {
+                assert_eq!(stream.len(), 8);
+                return messages;
+            }
+            assert_eq!(length % 8, 0);
+            let metadata = &stream[8..8 + length];
+            let body_length = i64::from_le_bytes(TableRef::root(metadata).scalar(3)) as usize;
+            messages.push((metadata, &stream[8 + length..8 + length + body_length]));
+            stream = &stream[8 + length + body_length..];
+        }
+    }
+
+    #[test]
+    fn test_stream() {
+        let columns = [("time", ColumnType::Timestamp), ("raw_rssi", ColumnType::Int32), ("kill_sw", ColumnType::Boolean), ("frame", ColumnType::Text)];
+        let mut out = Vec::new();
+        let mut writer = StreamWriter::start(&columns, &mut out).unwrap();
+        for i in 0..BATCH_ROWS + 2 {
+            let row = vec![
+                Some(Cell::Timestamp(1714566896000 + i as i64)),
+                (i % 2 == 1).then_some(Cell::Int32(i as i32)),
+                Some(Cell::Boolean(i == 1)),
+                (i < 2).then(|| Cell::Text(["A67C", "8D"][i].to_string())),
+            ];
+            writer.push(row, &mut out).unwrap();
+        }
+        writer.finish(&mut out).unwrap();
+
+        let messages = messages(&out);
+        assert_eq!(messages.len(), 3);
+        let schema = TableRef::root(messages[0].0);
+        assert_eq!(schema.scalar(0), METADATA_V5.to_le_bytes());
+        assert_eq!(schema.scalar(1), [SCHEMA]);
+        let fields = schema.table(2).tables(1);
+        assert_eq!(fields.iter().map(|field| field.string(0)).collect::<Vec<_>>(), ["time", "raw_rssi", "kill_sw", "frame"]);
+        assert_eq!(fields.iter().map(|field| field.scalar::<1>(2)[0]).collect::<Vec<_>>(), [10, 2, 6, 5]);
+        assert_eq!(fields[0].table(3).string(1), "UTC");
+        assert_eq!(i32::from_le_bytes(fields[1].table(3).scalar(0)), 32);
+        assert!(fields.iter().all(|field| field.tables(5).is_empty()));
+
+        let (metadata, body) = messages[1];
+        let batch = TableRef::root(metadata);
+        assert_eq!(batch.scalar(1), [RECORD_BATCH]);
+        let batch = batch.table(2);
+        assert_eq!(i64::from_le_bytes(batch.scalar(0)), BATCH_ROWS as i64);
+        let nodes = batch.structs(1);
+        assert_eq!(nodes[1], [BATCH_ROWS as i64, BATCH_ROWS as i64 / 2]);
+        assert_eq!(nodes[3], [BATCH_ROWS as i64, BATCH_ROWS as i64 - 2]);
+        let buffers = batch.structs(2);
+        assert_eq!(buffers.len(), 2 + 2 + 2 + 3);
+        assert!(buffers.iter().all(|[offset, _]| offset % 8 == 0));
+        let buffer = |idx: usize| &body[buffers[idx][0] as usize..(buffers[idx][0] + buffers[idx][1]) as usize];
+        assert_eq!(i64_at(buffer(1), 8), 1714566896001);
+        assert_eq!(buffer(2)[0], 0b1010_1010);
+        assert_eq!(i32::from_le_bytes(buffer(3)[4..8].try_into().unwrap()), 1);
+        assert_eq!(buffer(5)[0], 0b10);
+        assert_eq!(buffer(6)[0], 0b11);
+        assert_eq!(buffer(7)[..12], [0, 0, 0, 0, 4, 0, 0, 0, 6, 0, 0, 0]);
+        assert_eq!(buffer(8), b"A67C8D");
+
+        let batch = TableRef::root(messages[2].0).table(2);
+        assert_eq!(i64::from_le_bytes(batch.scalar(0)), 2);
+    }
+}
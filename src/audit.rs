@@ -0,0 +1,111 @@
+// Audit log of every beacon line handed to the decoder, decoded or not, as one JSON object per
+// line: the input exactly as received, when, what became of it and how it was decoded (ID,
+// source, version, calibration and corrections).
+// Only the input and the outcome are kept, not the converted values, so a decode that looks
+// wrong later can be redone from the original with the decoder of the day. The file is only ever
+// appended to; --rotate starts new files, but rotated audit logs are never deleted.
+use serde::Serialize;
+
+use crate::frames::Frame;
+use crate::migrate;
+use crate::provenance::Provenance;
+use crate::rotate::{Rotation, RotatingFile};
+use crate::timeutil;
+
+/// One line of the audit log
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    schema_version: u64,
+    time: String,
+    input: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    copy: Option<f64>,
+    outcome: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    version: &'static str,
+    id: &'a str,
+    source: &'a str,
+    calibration: &'a str,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    corrections: &'a [String],
+}
+
+pub struct AuditLog {
+    file: RotatingFile,
+}
+
+impl AuditLog {
+    pub fn open(path: &std::path::Path, rotation: Rotation) -> Result<Self, String> {
+        Ok(AuditLog { file: RotatingFile::open(path, rotation, usize::MAX)? })
+    }
+
+    /// Append the outcome of decoding `input`, received at `time` (now, when unknown)
+    pub fn record(
+        &mut self,
+        input: &str,
+        time: Option<f64>,
+        copy: Option<f64>,
+        result: &Result<Frame, String>,
+        provenance: &Provenance,
+    ) -> Result<(), String> {
+        let time = time.unwrap_or_else(timeutil::now_unix);
+        let record = Record {
+            schema_version: migrate::SCHEMA_VERSION,
+            time: timeutil::format_utc(time),
+            input,
+            copy,
+            outcome: if result.is_ok() { "decoded" } else { "failed" },
+            frame_type: result.as_ref().ok().map(|frame| match frame {
+                Frame::Housekeeping(_) => "housekeeping",
+                Frame::Typed(typed) => typed.frame_type.as_str(),
+            }),
+            frame: result.as_ref().ok().map(|frame| frame.bytes().iter().map(|b| format!("{:02X}", b)).collect()),
+            error: result.as_ref().err().map(String::as_str),
+            version: provenance.version,
+            id: &provenance.id,
+            source: &provenance.source,
+            calibration: &provenance.calibration,
+            corrections: &provenance.corrections,
+        };
+        let line = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+        self.file.write_line(&line, time).map_err(|e| format!("Cannot write audit log: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_record() {
+        let dir = std::env::temp_dir().join(format!("botan_audit_test_{}", std::process::id()));
+        let path = dir.join("audit.jsonl");
+        let mut log = AuditLog::open(&path, Rotation::Never).unwrap();
+        let provenance = Provenance::new("decode-file beacons.txt:1", "builtin", Vec::new());
+        let good = "BOTAN JS1YPT SI8640 A67C8D5E2AA13608";
+        log.record(good, Some(1714566896.0), Some(0.75), &parse_botan_beacon(good).map(Frame::Housekeeping), &provenance).unwrap();
+        let bad = "BOTAN JS1YPT A67C";
+        log.record(bad, Some(1714566956.0), None, &parse_botan_beacon(bad).map(Frame::Housekeeping), &provenance).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines[0]["input"], good);
+        assert_eq!(lines[0]["time"], "2024-05-01T12:34:56Z");
+        assert_eq!(lines[0]["frame"], "A67C8D5E2AA13608");
+        assert_eq!(lines[0]["frame_type"], "housekeeping");
+        assert_eq!(lines[0]["copy"], 0.75);
+        assert_eq!(lines[0]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(lines[0]["id"], provenance.id);
+        assert_eq!(lines[0]["source"], "decode-file beacons.txt:1");
+        assert_eq!(lines[1]["outcome"], "failed");
+        assert!(lines[1]["error"].as_str().unwrap().starts_with("Invalid data length"));
+        assert!(lines[1].get("frame").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+// AX.25 UI frames, the link layer of the packet downlink, as a TNC hands them over: address
+// fields, control, PID and information, optionally followed by the frame check sequence. The
+// information field is passed on as a beacon line: a text payload from its "BOTAN" onwards, a
+// binary payload as its hex data block behind "BOTAN <source call>", so frame types and the
+// housekeeping parser handle both like a CW copy.
+use std::fmt;
+use std::str::FromStr;
+
+const CONTROL_UI: u8 = 0x03;
+const POLL_FINAL: u8 = 0x10;
+const MAX_DIGIPEATERS: usize = 8;
+
+/// A station address: call sign and SSID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub call: String,
+    pub ssid: u8,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ssid == 0 {
+            write!(f, "{}", self.call)
+        } else {
+            write!(f, "{}-{}", self.call, self.ssid)
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    /// An address as monitors print it, e.g. "JS1YPT-1"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (call, ssid) = s.split_once('-').unwrap_or((s, "0"));
+        let ssid = ssid.parse().ok().filter(|ssid| *ssid <= 15);
+        match ssid {
+            Some(ssid) if (1..=6).contains(&call.len()) && call.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                Ok(Address { call: call.to_ascii_uppercase(), ssid })
+            }
+            _ => Err(format!("Invalid AX.25 address '{}'", s)),
+        }
+    }
+}
+
+/// A received UI frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UiFrame {
+    pub destination: Address,
+    pub source: Address,
+    pub digipeaters: Vec<Address>,
+    pub pid: u8,
+    pub info: Vec<u8>,
+}
+
+impl UiFrame {
+    /// The payload as a line for the beacon parser
+    pub fn beacon_line(&self) -> String {
+        if let Ok(text) = std::str::from_utf8(&self.info) {
+            if let Some(start) = text.find("BOTAN") {
+                return text[start..].trim_end().to_string();
+            }
+        }
+        let hex: String = self.info.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("BOTAN {} {}", self.source.call, hex)
+    }
+}
+
+/// CRC-16/X.25 of `data`, the frame check sequence of AX.25
+pub fn fcs(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// One 7-byte address field: the call shifted left one bit, then the SSID byte
+fn parse_address(field: &[u8]) -> Result<Address, String> {
+    let call: String = field[..6].iter().map(|&b| (b >> 1) as char).collect();
+    let call = call.trim_end();
+    if call.is_empty() || !call.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid AX.25 address '{}'", call.escape_default()));
+    }
+    Ok(Address { call: call.to_string(), ssid: (field[6] >> 1) & 0x0F })
+}
+
+/// Parse a UI frame, checking and removing its trailing FCS when `with_fcs`
+pub fn parse_ui(frame: &[u8], with_fcs: bool) -> Result<UiFrame, String> {
+    let frame = if with_fcs {
+        let Some((body, check)) = frame.split_last_chunk::<2>() else {
+            return Err(format!("Invalid AX.25 frame: {} bytes", frame.len()));
+        };
+        let (received, computed) = (u16::from_le_bytes(*check), fcs(body));
+        if received != computed {
+            return Err(format!("Invalid AX.25 FCS {:04X}, expected {:04X}", received, computed));
+        }
+        body
+    } else {
+        frame
+    };
+
+    // Addresses run until one has the extension bit set
+    let mut addresses = Vec::new();
+    let mut pos = 0;
+    loop {
+        let Some(field) = frame.get(pos..pos + 7) else {
+            return Err(format!("Invalid AX.25 frame: address field ends after {} bytes", frame.len()));
+        };
+        addresses.push(parse_address(field)?);
+        pos += 7;
+        if field[6] & 1 == 1 {
+            break;
+        }
+        if addresses.len() == 2 + MAX_DIGIPEATERS {
+            return Err("Invalid AX.25 frame: too many digipeaters".to_string());
+        }
+    }
+    if addresses.len() < 2 {
+        return Err("Invalid AX.25 frame: no source address".to_string());
+    }
+    let (control, pid) = match frame.get(pos..pos + 2) {
+        Some(&[control, pid]) => (control, pid),
+        _ => return Err("Invalid AX.25 frame: no control and PID".to_string()),
+    };
+    if control & !POLL_FINAL != CONTROL_UI {
+        return Err(format!("Invalid AX.25 frame: control {:02X} is not UI", control));
+    }
+
+    let mut addresses = addresses.into_iter();
+    Ok(UiFrame {
+        destination: addresses.next().unwrap(),
+        source: addresses.next().unwrap(),
+        digipeaters: addresses.collect(),
+        pid,
+        info: frame[pos + 2..].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A UI frame from `source` to `destination` carrying `info`, with its FCS
+    fn ui_frame(destination: &str, source: &str, info: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        for (call, last) in [(destination, false), (source, true)] {
+            let (call, ssid) = call.split_once('-').map_or((call, 0), |(c, s)| (c, s.parse().unwrap()));
+            frame.extend(format!("{:<6}", call).bytes().map(|b| b << 1));
+            frame.push(0x60 | ssid << 1 | last as u8);
+        }
+        frame.extend([CONTROL_UI, 0xF0]);
+        frame.extend(info);
+        let check = fcs(&frame);
+        frame.extend(check.to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_fcs() {
+        assert_eq!(fcs(b"123456789"), 0x906E);
+    }
+
+    #[test]
+    fn test_parse_ui() {
+        let frame = ui_frame("CQ", "JS1YPT-1", b"BOTAN JS1YPT A67C8D5E2AA13608\r");
+        let ui = parse_ui(&frame, true).unwrap();
+        assert_eq!(ui.destination.to_string(), "CQ");
+        assert_eq!(ui.source, Address { call: "JS1YPT".to_string(), ssid: 1 });
+        assert!(ui.digipeaters.is_empty());
+        assert_eq!(ui.pid, 0xF0);
+        assert_eq!(ui.beacon_line(), "BOTAN JS1YPT A67C8D5E2AA13608");
+
+        // Without the FCS, as most KISS TNCs deliver frames
+        assert_eq!(parse_ui(&frame[..frame.len() - 2], false), Ok(ui));
+
+        let binary = parse_ui(&ui_frame("CQ", "JS1YPT", &[0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08]), true).unwrap();
+        assert_eq!(binary.beacon_line(), "BOTAN JS1YPT A67C8D5E2AA13608");
+    }
+
+    #[test]
+    fn test_parse_ui_errors() {
+        let mut frame = ui_frame("CQ", "JS1YPT", b"BOTAN");
+        let last = frame.len() - 3;
+        frame[last] ^= 1;
+        assert!(parse_ui(&frame, true).unwrap_err().starts_with("Invalid AX.25 FCS"));
+        assert!(parse_ui(&[0x03], true).unwrap_err().starts_with("Invalid AX.25 frame"));
+        assert!(parse_ui(&frame[..10], false).unwrap_err().contains("address field ends"));
+
+        let mut connected = ui_frame("CQ", "JS1YPT", b"");
+        connected[14] = 0x00;
+        assert!(parse_ui(&connected, false).unwrap_err().contains("is not UI"));
+    }
+}
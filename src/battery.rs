@@ -0,0 +1,231 @@
+// Long-term battery health from the saved history, for `battery`. Beacons only cover a few
+// minutes per pass, so charge cannot be integrated over an orbit; aging is tracked through
+// what the beacons do show, window by window:
+//
+// - voltage in sunlight and in eclipse, where the battery carries the load alone
+// - mean charge current in sunlight and discharge current in eclipse
+// - internal resistance and open-circuit voltage, from a least-squares fit of V = OCV + R * I
+//   over the window's frames (charging current raises the terminal voltage)
+//
+// A cell that loses capacity sags further in eclipse and its resistance grows, so the trends of
+// eclipse voltage and resistance over the windows are the early warning.
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::aggregate::Interval;
+use crate::export;
+use crate::timeutil;
+
+/// Trends are only reported over at least this many windows
+const MIN_TREND_WINDOWS: usize = 3;
+/// Resistance needs frames spread over at least this current range [mA]
+const MIN_CURRENT_SPREAD: f64 = 50.0;
+/// Eclipse voltage falling faster than this per 30 days [V]
+const ECLIPSE_DROP_WARNING: f64 = 0.05;
+/// Resistance growing by more than this share over the history
+const RESISTANCE_RISE_WARNING: f64 = 0.25;
+/// Eclipse voltage below this is a deep discharge for the single Li-ion cell [V]
+const LOW_VOLTAGE: f64 = 3.6;
+
+/// One frame's battery readings
+struct Sample {
+    time: f64,
+    voltage: f64,
+    current: f64,
+    temperature: Option<f64>,
+    eclipse: Option<bool>,    // Not known for imported frames
+}
+
+/// Battery state over one window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Window {
+    pub start: String,
+    pub frames: usize,
+    pub sunlight_v: Option<f64>,
+    pub eclipse_v: Option<f64>,
+    pub eclipse_min_v: Option<f64>,
+    pub charge_ma: Option<f64>,
+    pub discharge_ma: Option<f64>,
+    pub resistance_mohm: Option<f64>,
+    pub open_circuit_v: Option<f64>,
+    pub temperature_c: Option<f64>,
+    #[serde(skip)]
+    middle: f64,
+}
+
+/// Change per 30 days, where enough windows have the value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trends {
+    pub eclipse_v: Option<f64>,
+    pub resistance_mohm: Option<f64>,
+    pub open_circuit_v: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub window: f64,
+    pub windows: Vec<Window>,
+    pub trends: Trends,
+    pub warnings: Vec<String>,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Least-squares slope and intercept of y over x
+fn fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    if points.len() < 2 || sxx == 0.0 {
+        return None;
+    }
+    let slope = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum::<f64>() / sxx;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+fn window(start: f64, length: f64, samples: &[Sample]) -> Window {
+    let phase = |eclipse: bool| samples.iter().filter(move |s| s.eclipse == Some(eclipse));
+    let currents = samples.iter().map(|s| s.current);
+    let spread = currents.clone().fold(f64::NEG_INFINITY, f64::max) - currents.fold(f64::INFINITY, f64::min);
+    let points: Vec<(f64, f64)> = samples.iter().map(|s| (s.current / 1000.0, s.voltage)).collect();
+    let line = if spread >= MIN_CURRENT_SPREAD { fit(&points) } else { None };
+    Window {
+        start: timeutil::format_display(start),
+        frames: samples.len(),
+        sunlight_v: mean(phase(false).map(|s| s.voltage)),
+        eclipse_v: mean(phase(true).map(|s| s.voltage)),
+        eclipse_min_v: phase(true).map(|s| s.voltage).reduce(f64::min),
+        charge_ma: mean(phase(false).map(|s| s.current)),
+        discharge_ma: mean(phase(true).map(|s| -s.current)),
+        resistance_mohm: line.map(|(slope, _)| slope * 1000.0),
+        open_circuit_v: line.map(|(_, intercept)| intercept),
+        temperature_c: mean(samples.iter().filter_map(|s| s.temperature)),
+        middle: start + length / 2.0,
+    }
+}
+
+/// Change per 30 days of `value` over the windows that have it
+fn trend(windows: &[Window], value: impl Fn(&Window) -> Option<f64>) -> Option<f64> {
+    let points: Vec<(f64, f64)> = windows.iter().filter_map(|w| Some((w.middle / (30.0 * 86400.0), value(w)?))).collect();
+    if points.len() < MIN_TREND_WINDOWS {
+        return None;
+    }
+    fit(&points).map(|(slope, _)| slope)
+}
+
+fn warnings(windows: &[Window], trends: &Trends) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(drop) = trends.eclipse_v.filter(|slope| *slope < -ECLIPSE_DROP_WARNING) {
+        warnings.push(format!("Eclipse voltage is falling by {:.3} V per 30 days", -drop));
+    }
+    let resistances: Vec<(f64, f64)> = windows.iter().filter_map(|w| Some((w.middle, w.resistance_mohm?))).collect();
+    if let (Some(slope), Some(&(first, initial)), Some(&(last, _))) = (trends.resistance_mohm, resistances.first(), resistances.last()) {
+        let rise = slope * (last - first) / (30.0 * 86400.0);
+        if initial > 0.0 && rise / initial > RESISTANCE_RISE_WARNING {
+            warnings.push(format!("Internal resistance has grown by {:.0}% since {}", 100.0 * rise / initial, timeutil::format_display(first)));
+        }
+    }
+    if let Some(low) = windows.iter().find(|w| w.eclipse_min_v.is_some_and(|v| v < LOW_VOLTAGE)) {
+        warnings.push(format!("Battery fell to {:.2} V in eclipse in the window from {}", low.eclipse_min_v.unwrap(), low.start));
+    }
+    warnings
+}
+
+/// Battery health per `window` over the history under `dir` between `from` and `to`
+pub fn analyse(dir: &Path, from: Option<f64>, to: Option<f64>, window_length: Interval) -> Result<Report, String> {
+    let columns = export::columns();
+    let index = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+    let (time, bat_v, bat_i, bat_t, illumination) = (index("time"), index("bat_v"), index("bat_i"), index("bat_t"), index("illumination"));
+    let mut samples = Vec::new();
+    export::for_each_row(dir, from, to, |row| {
+        let (Ok(time), Ok(voltage), Ok(current)) = (timeutil::parse_utc(&row[time]), row[bat_v].parse(), row[bat_i].parse()) else {
+            return Ok(());
+        };
+        let eclipse = match row[illumination].as_str() {
+            "eclipse" => Some(true),
+            "sunlight" => Some(false),
+            _ => None,
+        };
+        samples.push(Sample { time, voltage, current, temperature: row[bat_t].parse().ok(), eclipse });
+        Ok(())
+    })?;
+    samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let length = window_length.0;
+    let windows: Vec<Window> = samples
+        .chunk_by(|a, b| (a.time / length).floor() == (b.time / length).floor())
+        .map(|chunk| window((chunk[0].time / length).floor() * length, length, chunk))
+        .collect();
+    let trends = Trends {
+        eclipse_v: trend(&windows, |w| w.eclipse_v),
+        resistance_mohm: trend(&windows, |w| w.resistance_mohm),
+        open_circuit_v: trend(&windows, |w| w.open_circuit_v),
+    };
+    let warnings = warnings(&windows, &trends);
+    Ok(Report { window: length, windows, trends, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: f64, voltage: f64, current: f64, eclipse: bool) -> Sample {
+        Sample { time, voltage, current, temperature: Some(20.0), eclipse: Some(eclipse) }
+    }
+
+    #[test]
+    fn test_window_fit() {
+        // 4.0 V open circuit, 200 mΩ
+        let samples: Vec<Sample> = [(-150.0, true), (-100.0, true), (100.0, false), (200.0, false)]
+            .into_iter()
+            .map(|(current, eclipse)| sample(0.0, 4.0 + 0.2 * current / 1000.0, current, eclipse))
+            .collect();
+        let w = window(0.0, 86400.0, &samples);
+        assert_eq!(w.frames, 4);
+        assert!((w.resistance_mohm.unwrap() - 200.0).abs() < 1e-9);
+        assert!((w.open_circuit_v.unwrap() - 4.0).abs() < 1e-9);
+        assert_eq!(w.discharge_ma, Some(125.0));
+        assert_eq!(w.charge_ma, Some(150.0));
+        assert_eq!(w.eclipse_min_v, Some(4.0 - 0.03));
+
+        // Too little current spread for a resistance: -150 and -140 mA
+        let w = window(0.0, 86400.0, &[sample(0.0, 3.97, -150.0, true), sample(0.0, 3.972, -140.0, true)]);
+        assert_eq!(w.resistance_mohm, None);
+    }
+
+    #[test]
+    fn test_aging_warnings() {
+        // Weekly windows over ten weeks: eclipse voltage falls 0.03 V and resistance grows
+        // 20 mΩ a week
+        let week = 7.0 * 86400.0;
+        let windows: Vec<Window> = (0..10)
+            .map(|n| {
+                let r = 0.1 + 0.02 * n as f64;
+                let samples: Vec<Sample> = [-200.0, 200.0]
+                    .into_iter()
+                    .map(|current| sample(n as f64 * week, 3.9 - 0.03 * n as f64 + r * current / 1000.0, current, current < 0.0))
+                    .collect();
+                window(n as f64 * week, week, &samples)
+            })
+            .collect();
+        let trends = Trends {
+            eclipse_v: trend(&windows, |w| w.eclipse_v),
+            resistance_mohm: trend(&windows, |w| w.resistance_mohm),
+            open_circuit_v: trend(&windows, |w| w.open_circuit_v),
+        };
+        assert!((trends.open_circuit_v.unwrap() + 0.03 * 30.0 / 7.0).abs() < 1e-9);
+        let warnings = warnings(&windows, &trends);
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].starts_with("Eclipse voltage is falling by 0.146 V"));
+        assert!(warnings[1].starts_with("Internal resistance has grown by 180%"));
+        assert!(warnings[2].contains("3.57 V"));
+
+        // Too few windows for trends
+        assert_eq!(trend(&windows[..2], |w| w.eclipse_v), None);
+    }
+}
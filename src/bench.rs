@@ -0,0 +1,95 @@
+// Timing benchmarks for the paths bulk decoding spends its time in. They are ignored by a plain
+// `cargo test`; run them optimized, one at a time:
+//
+//   cargo test --release bench_ -- --ignored --nocapture --test-threads=1
+//
+// Each reports the median time per call over 21 samples of about 10 ms.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::botan_parser::{self, parse_botan_beacon};
+use crate::calibration::CalibrationTable;
+use crate::BotanDecoder;
+
+const BEACON: &str = "BOTAN JS1YPT SI8640 A67C8D5E2AA13608";
+
+/// Median time per call of `f` [ns]
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) -> f64 {
+    // Double the batch until it takes long enough to time reliably; this also warms up
+    let mut iters: u32 = 1;
+    loop {
+        let start = Instant::now();
+        for _ in 0..iters {
+            black_box(f());
+        }
+        if start.elapsed() >= Duration::from_millis(10) {
+            break;
+        }
+        iters *= 2;
+    }
+
+    let mut samples: Vec<f64> = (0..21)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iters {
+                black_box(f());
+            }
+            start.elapsed().as_nanos() as f64 / iters as f64
+        })
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    let median = samples[samples.len() / 2];
+    println!(
+        "{:<24} {:>10.1} ns/iter  (min {:.1}, max {:.1})",
+        name,
+        median,
+        samples[0],
+        samples[samples.len() - 1]
+    );
+    median
+}
+
+#[test]
+#[ignore]
+fn bench_parse_beacon() {
+    bench("parse_botan_beacon", || parse_botan_beacon(black_box(BEACON)));
+}
+
+#[test]
+#[ignore]
+fn bench_telemetry_conversion() {
+    let frame = [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08];
+    let table = CalibrationTable::default();
+    bench("parse_telemetry_bytes", || botan_parser::parse_telemetry_bytes(black_box(&frame), &table));
+}
+
+#[test]
+#[ignore]
+fn bench_report() {
+    let data = parse_botan_beacon(BEACON).unwrap();
+    bench("report formatting", || black_box(&data).to_string());
+}
+
+#[test]
+#[ignore]
+fn bench_morse_decode() {
+    let decoder = BotanDecoder::new();
+    let morse = decoder.encode(BEACON).unwrap();
+    bench("BotanDecoder::decode", || decoder.decode(black_box(&morse)));
+    let mut text = String::new();
+    bench("BotanDecoder::decode_into", || {
+        text.clear();
+        decoder.decode_into(black_box(&morse), &mut text).map(|_| text.len())
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_bulk_parse() {
+    // An archive's worth of distinct frames, as when reprocessing old passes
+    let lines: Vec<String> = (0..10_000u32)
+        .map(|i| format!("BOTAN JS1YPT SI{:04X} A67C8D5E2A{:06X}", i & 0xFFFF, i))
+        .collect();
+    let per_batch = bench("parse 10k frames", || lines.iter().filter(|l| parse_botan_beacon(l).is_ok()).count());
+    println!("{:<24} {:>10.0} frames/s", "", lines.len() as f64 / (per_batch * 1e-9));
+}
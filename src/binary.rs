@@ -0,0 +1,125 @@
+// Raw binary frames, as some demodulators write them: back-to-back records of a fixed size (the
+// 8 bytes of a housekeeping frame unless told otherwise), or records each preceded by their
+// length as an unsigned integer of 1, 2 or 4 bytes.
+use std::str::FromStr;
+
+/// How the records of a binary stream are delimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    Fixed(usize),    // Every record this many bytes
+    U8,              // A one-byte length before each record
+    U16Be,
+    U16Le,
+    U32Be,
+    U32Le,
+}
+
+impl Framing {
+    /// Bytes of the length prefix
+    fn prefix_len(self) -> usize {
+        match self {
+            Framing::Fixed(_) => 0,
+            Framing::U8 => 1,
+            Framing::U16Be | Framing::U16Le => 2,
+            Framing::U32Be | Framing::U32Le => 4,
+        }
+    }
+
+    /// Length of the record starting at `bytes`, once its prefix is there
+    fn record_len(self, bytes: &[u8]) -> Option<usize> {
+        let prefix = bytes.get(..self.prefix_len())?;
+        Some(match self {
+            Framing::Fixed(size) => size,
+            Framing::U8 => prefix[0] as usize,
+            Framing::U16Be => u16::from_be_bytes([prefix[0], prefix[1]]) as usize,
+            Framing::U16Le => u16::from_le_bytes([prefix[0], prefix[1]]) as usize,
+            Framing::U32Be => u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize,
+            Framing::U32Le => u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize,
+        })
+    }
+}
+
+impl FromStr for Framing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Framing::Fixed(8)),
+            "u8" => Ok(Framing::U8),
+            "u16be" => Ok(Framing::U16Be),
+            "u16le" => Ok(Framing::U16Le),
+            "u32be" => Ok(Framing::U32Be),
+            "u32le" => Ok(Framing::U32Le),
+            _ => Err(format!("Unknown framing '{}'. Expected fixed, u8, u16be, u16le, u32be or u32le", s)),
+        }
+    }
+}
+
+/// Splits a binary stream, fed in arbitrary chunks, into its records
+#[derive(Debug)]
+pub struct RecordSplitter {
+    framing: Framing,
+    pending: Vec<u8>,
+}
+
+impl RecordSplitter {
+    pub fn new(framing: Framing) -> Self {
+        RecordSplitter { framing, pending: Vec::new() }
+    }
+
+    /// Add received bytes, returning the records they complete without their length prefix
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.pending.extend_from_slice(bytes);
+        let mut records = Vec::new();
+        let mut start = 0;
+        let prefix = self.framing.prefix_len();
+        while let Some(len) = self.framing.record_len(&self.pending[start..]) {
+            let end = start + prefix + len;
+            if end > self.pending.len() {
+                break;
+            }
+            records.push(self.pending[start + prefix..end].to_vec());
+            start = end;
+        }
+        self.pending.drain(..start);
+        records
+    }
+
+    /// Bytes of an incomplete record left at the end of the stream
+    pub fn leftover(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// A record as a line for the beacon parser
+pub fn beacon_line(record: &[u8]) -> String {
+    let hex: String = record.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("BOTAN JS1YPT {}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_splitter() {
+        let frame = [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08];
+        let mut fixed = RecordSplitter::new("fixed".parse().unwrap());
+        assert!(fixed.push(&frame[..5]).is_empty());
+        let records = fixed.push(&[&frame[5..], &frame[..], &frame[..3]].concat());
+        assert_eq!(records, [frame.to_vec(), frame.to_vec()]);
+        assert_eq!(fixed.leftover(), 3);
+        assert_eq!(beacon_line(&records[0]), "BOTAN JS1YPT A67C8D5E2AA13608");
+
+        // Length-prefixed records may differ in length; an empty one is passed on as it is
+        let mut prefixed = RecordSplitter::new(Framing::U16Le);
+        let stream = [&[8, 0][..], &frame, &[0, 0], &[3, 0], &frame[..3]].concat();
+        assert_eq!(prefixed.push(&stream[..1]), Vec::<Vec<u8>>::new());
+        assert_eq!(prefixed.push(&stream[1..]), [frame.to_vec(), vec![], frame[..3].to_vec()]);
+        assert_eq!(prefixed.leftover(), 0);
+        let mut prefixed = RecordSplitter::new(Framing::U32Be);
+        assert_eq!(prefixed.push(&[0, 0, 0, 2, 0xAB, 0xCD]), [vec![0xAB, 0xCD]]);
+
+        assert!("u24".parse::<Framing>().is_err());
+    }
+}
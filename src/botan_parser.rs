@@ -1,23 +1,95 @@
-use std::fmt;
+use std::borrow::Cow;
+use std::fmt::{self, Write as _};
+
+use serde::{Deserialize, Serialize};
+
+use crate::breakdown;
+use crate::calibration::{CalibrationTable, Channel};
+use crate::codes::FieldLabels;
+use crate::confidence::Confidence;
+use crate::i18n::{self, display_width, Lang};
+use crate::limits::{self, Limit, Limits, Severity};
+use crate::logging::trace;
+use crate::style::{paint, Paint, Painted};
+use crate::units::{Celsius, Milliamps, Quantity, Units, Volts};
 
 /// Represents the parsed BOTAN beacon data
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct BotanBeaconData {
-    pub satellite_name: String,
-    pub call_sign: String,
+    pub satellite_name: &'static str,
+    pub call_sign: &'static str,
     pub rssi: Option<BotanRSSI>,
+    #[serde(serialize_with = "serialize_hex")]
+    pub frame: [u8; 8],            // Raw telemetry bytes, shown as hex
     pub telemetry: BotanTelemetry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,   // Filled in by the printer, which sees the whole stream
+}
+
+pub(crate) fn serialize_hex<S: serde::Serializer, B: AsRef<[u8]>>(bytes: &B, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&bytes.as_ref().iter().map(|b| format!("{:02X}", b)).collect::<String>())
 }
 
 /// Represents RSSI information if available
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct BotanRSSI {
-    pub rssi_dbm: f64,      // RSSI in dBm
-    pub snr_db: f64,        // Signal-to-Noise Ratio in dB
+    pub raw_rssi: u8,              // First SI byte
+    pub raw_snr: u8,               // Second SI byte
+    pub rssi_dbm: Option<f64>,     // RSSI in dBm, once a conversion is configured
+    pub snr_db: Option<f64>,       // Signal-to-Noise Ratio in dB, once a conversion is configured
+}
+
+/// Linear conversion of one SI byte: value = raw * slope + offset
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalConversion {
+    #[serde(default = "default_slope")]
+    pub slope: f64,
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub signed: bool,    // Interpret the raw byte as two's complement
+}
+
+fn default_slope() -> f64 {
+    1.0
+}
+
+impl SignalConversion {
+    pub fn apply(&self, raw: u8) -> f64 {
+        let raw = if self.signed { raw as i8 as f64 } else { raw as f64 };
+        raw * self.slope + self.offset
+    }
+}
+
+/// Conversions from the raw SI bytes to dBm and dB; fields left out stay raw
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalCalibration {
+    pub rssi: Option<SignalConversion>,
+    pub snr: Option<SignalConversion>,
+}
+
+impl SignalCalibration {
+    /// Load a JSON calibration, e.g. {"rssi": {"slope": -0.5, "offset": -20}, "snr": {"signed": true}}
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read signal calibration '{}': {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid signal calibration '{}': {}", path, e))
+    }
+
+    fn convert(&self, raw_rssi: u8, raw_snr: u8) -> BotanRSSI {
+        BotanRSSI {
+            raw_rssi,
+            raw_snr,
+            rssi_dbm: self.rssi.map(|c| c.apply(raw_rssi)),
+            snr_db: self.snr.map(|c| c.apply(raw_snr)),
+        }
+    }
 }
 
 /// Represents the 8-byte telemetry data block
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct BotanTelemetry {
     pub bat_v: f64,        // Battery Voltage [V]
     pub bat_i: f64,        // Battery Current [mA]  
@@ -29,8 +101,70 @@ pub struct BotanTelemetry {
     pub data3: Data3Flags, // Mission status flags
 }
 
+impl BotanTelemetry {
+    /// Battery voltage (BAT_V)
+    pub fn battery_voltage(&self) -> Volts {
+        Volts(self.bat_v)
+    }
+
+    /// Battery charge (+) or discharge (-) current (BAT_I)
+    pub fn battery_current(&self) -> Milliamps {
+        Milliamps(self.bat_i)
+    }
+
+    /// Battery temperature (BAT_T)
+    pub fn battery_temperature(&self) -> Celsius {
+        Celsius(self.bat_t)
+    }
+
+    /// Circuit board temperature (BPB_T)
+    pub fn board_temperature(&self) -> Celsius {
+        Celsius(self.bpb_t)
+    }
+
+    /// Current consumption of the bus (RAW_I)
+    pub fn consumption_current(&self) -> Milliamps {
+        Milliamps(self.raw_i)
+    }
+}
+
+// Comparison helpers for assertions on decoded beacons
+#[cfg_attr(not(test), allow(dead_code))]
+impl BotanTelemetry {
+    /// Fields that differ: analog values by more than `epsilon`, flag bytes in any bit
+    pub fn approx_diff(&self, other: &BotanTelemetry, epsilon: f64) -> Vec<&'static str> {
+        let analog = [
+            ("bat_v", self.bat_v, other.bat_v),
+            ("bat_i", self.bat_i, other.bat_i),
+            ("bat_t", self.bat_t, other.bat_t),
+            ("bpb_t", self.bpb_t, other.bpb_t),
+            ("raw_i", self.raw_i, other.raw_i),
+        ];
+        let mut fields: Vec<&'static str> = analog
+            .into_iter()
+            .filter(|(_, a, b)| (a - b).abs() > epsilon || a.is_nan() != b.is_nan())
+            .map(|(name, _, _)| name)
+            .collect();
+        for (name, same) in [
+            ("data1", self.data1 == other.data1),
+            ("data2", self.data2 == other.data2),
+            ("data3", self.data3 == other.data3),
+        ] {
+            if !same {
+                fields.push(name);
+            }
+        }
+        fields
+    }
+
+    /// Equal within `epsilon` in every analog value and exactly in the flags
+    pub fn approx_eq(&self, other: &BotanTelemetry, epsilon: f64) -> bool {
+        self.approx_diff(other, epsilon).is_empty()
+    }
+}
+
 /// Bitfield for data1 (Byte 6) - Power system status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Data1Flags {
     pub power_5v0: bool,      // Bit 7: 5V PWR Line On/Off
     pub power_depant: bool,   // Bit 6: Antenna Deployment PWR Line On/Off
@@ -43,7 +177,7 @@ pub struct Data1Flags {
 }
 
 /// Bitfield for data2 (Byte 7) - Command counters and KILL switch
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Data2Flags {
     pub reserve_cmd_counter: u8, // Bits 7-4: Reserved commands count
     pub cmd_uplink_counter: u8,  // Bits 3-1: Received commands count
@@ -51,7 +185,7 @@ pub struct Data2Flags {
 }
 
 /// Bitfield for data3 (Byte 8) - Mission status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Data3Flags {
     pub kill_counter: u8,        // Bits 7-6: KILL SW occurrences count
     pub mission_pic_on: bool,    // Bit 5: Mission PIC On/Off
@@ -61,175 +195,428 @@ pub struct Data3Flags {
     pub current_mis: u8,         // Bits 1-0: Current Mission (00:None, 01:Earth, 10:Sun)
 }
 
+/// Options for the human-readable report
+#[derive(Debug, Clone, Default)]
+pub struct DisplayOptions {
+    pub lang: Lang,
+    pub units: Units,
+    pub color: bool,       // ANSI colors for flag states, limit checks and missing values
+    pub limits: Limits,
+    pub verbose_bytes: bool,     // Append the annotated byte breakdown
+    pub bit_view: bool,          // Append the bit layout of the frame
+    pub calibration: CalibrationTable,   // Telemetry coefficients, shown in the byte breakdown
+    pub codes: FieldLabels,              // Labels of coded fields
+}
+
+/// Human-readable report of a beacon, see `BotanBeaconData::display`
+pub struct BeaconDisplay<'a> {
+    data: &'a BotanBeaconData,
+    options: &'a DisplayOptions,
+}
+
+impl BotanBeaconData {
+    pub fn display<'a>(&'a self, options: &'a DisplayOptions) -> BeaconDisplay<'a> {
+        BeaconDisplay { data: self, options }
+    }
+}
+
 impl fmt::Display for BotanBeaconData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "BOTAN Satellite Beacon Data")?;
+        self.display(&DisplayOptions::default()).fmt(f)
+    }
+}
+
+// Terminal column at which the values of the report start
+const VALUE_COLUMN: usize = 22;
+
+/// One "Label:   value" line, padded by display width so Japanese labels line up too
+fn field(f: &mut String, indent: &str, label: &str, value: impl fmt::Display) -> fmt::Result {
+    let used = indent.len() + display_width(label) + 1;
+    writeln!(f, "{}{}:{:pad$}{}", indent, label, "", value, pad = VALUE_COLUMN.saturating_sub(used).max(1))
+}
+
+impl BeaconDisplay<'_> {
+    /// Green when the flag is in its normal state, red otherwise
+    fn flag(&self, text: &'static str, normal: bool) -> Painted<&'static str> {
+        paint(text, if normal { Paint::Green } else { Paint::Red }, self.options.color)
+    }
+
+    /// A value colored by how close `raw` (V, mA or °C) is to its limit
+    fn level(&self, quantity: Quantity, limit: Option<Limit>, raw: f64) -> Painted<Quantity> {
+        let color = match limits::check(limit, raw) {
+            Severity::Normal => Paint::Plain,
+            Severity::Warning => Paint::Yellow,
+            Severity::Alarm => Paint::Red,
+        };
+        paint(quantity, color, self.options.color)
+    }
+}
+
+impl fmt::Display for BeaconDisplay<'_> {
+    fn fmt(&self, out: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let f = &mut String::new();
+        let l = self.options.lang.labels();
+        let u = self.options.units;
+        let lim = self.options.limits;
+        let on = |flag: bool| self.flag(l.on_off(flag), flag);
+        let data = self.data;
+        let t = &data.telemetry;
+        writeln!(f, "{}", l.title)?;
         writeln!(f, "==========================")?;
-        writeln!(f, "Satellite: {}", self.satellite_name)?;
-        writeln!(f, "Call Sign: {}", self.call_sign)?;
+        writeln!(f, "{}: {}", l.satellite, data.satellite_name)?;
+        writeln!(f, "{}: {}", l.call_sign, data.call_sign)?;
+        if let Some(confidence) = &data.confidence {
+            writeln!(f, "{}: {:.0}%", l.confidence, confidence.score * 100.0)?;
+        }
         writeln!(f)?;
-        writeln!(f, "Signal Information (lack definition):")?;
+        writeln!(f, "{}:", l.signal)?;
         writeln!(f, "--------------")?;
-        if let Some(rssi) = &self.rssi {
-            writeln!(f, "Unknown argument 1: {:.1}", rssi.rssi_dbm)?;
-            writeln!(f, "Unknown argument 2: {:.1}", rssi.snr_db)?;
+        if let Some(rssi) = &data.rssi {
+            match rssi.rssi_dbm {
+                Some(dbm) => field(f, "", l.rssi, format_args!("{:.1} dBm", dbm))?,
+                None => field(f, "", &format!("{}{}", l.rssi, l.uncalibrated), rssi.raw_rssi)?,
+            }
+            match rssi.snr_db {
+                Some(db) => field(f, "", l.snr, format_args!("{:.1} dB", db))?,
+                None => field(f, "", &format!("{}{}", l.snr, l.uncalibrated), rssi.raw_snr)?,
+            }
         } else {
-            writeln!(f, "Unknown argument 1: N/A")?;
-            writeln!(f, "Unknown argument 2: N/A")?;
+            field(f, "", l.rssi, paint(l.not_available, Paint::Dim, self.options.color))?;
+            field(f, "", l.snr, paint(l.not_available, Paint::Dim, self.options.color))?;
         }
         writeln!(f)?;
-        writeln!(f, "Telemetry Data:")?;
+        writeln!(f, "{}:", l.telemetry)?;
         writeln!(f, "--------------")?;
-        writeln!(f, "Battery Voltage:      {:.3} V", self.telemetry.bat_v)?;
-        writeln!(f, "Battery Current:      {:.1} mA", self.telemetry.bat_i)?;
-        writeln!(f, "Battery Temperature:  {:.1} °C", self.telemetry.bat_t)?;
-        writeln!(f, "Board Temperature:    {:.1} °C", self.telemetry.bpb_t)?;
-        writeln!(f, "Current Consumption:  {:.1} mA", self.telemetry.raw_i)?;
+        field(f, "", l.bat_v, self.level(u.voltage(t.battery_voltage()), lim.bat_v, t.bat_v))?;
+        field(f, "", l.bat_i, self.level(u.current(t.battery_current()), lim.bat_i, t.bat_i))?;
+        field(f, "", l.bat_t, self.level(u.temperature(t.battery_temperature()), lim.bat_t, t.bat_t))?;
+        field(f, "", l.bpb_t, self.level(u.temperature(t.board_temperature()), lim.bpb_t, t.bpb_t))?;
+        field(f, "", l.raw_i, self.level(u.current(t.consumption_current()), lim.raw_i, t.raw_i))?;
         writeln!(f)?;
-        writeln!(f, "Power System Status:")?;
-        writeln!(f, "  5V Power Line:      {}", if self.telemetry.data1.power_5v0 { "ON" } else { "OFF" })?;
-        writeln!(f, "  Antenna Deployment: {}", if self.telemetry.data1.power_depant { "ON" } else { "OFF" })?;
-        writeln!(f, "  Transponder:        {}", if self.telemetry.data1.power_com { "ON" } else { "OFF" })?;
-        writeln!(f, "  Solar Panels:")?;
-        writeln!(f, "    +X: {} | +Y: {} | -Y: {} | +Z: {} | -Z: {}", 
-                 if self.telemetry.data1.sap_x_pos { "ON" } else { "OFF" },
-                 if self.telemetry.data1.sap_y_pos { "ON" } else { "OFF" },
-                 if self.telemetry.data1.sap_y_neg { "ON" } else { "OFF" },
-                 if self.telemetry.data1.sap_z_pos { "ON" } else { "OFF" },
-                 if self.telemetry.data1.sap_z_neg { "ON" } else { "OFF" })?;
+        writeln!(f, "{}:", l.power)?;
+        field(f, "  ", l.power_5v0, on(t.data1.power_5v0))?;
+        field(f, "  ", l.power_depant, on(t.data1.power_depant))?;
+        field(f, "  ", l.power_com, on(t.data1.power_com))?;
+        writeln!(f, "  {}:", l.solar_panels)?;
+        writeln!(f, "    +X: {} | +Y: {} | -Y: {} | +Z: {} | -Z: {}",
+                 on(t.data1.sap_x_pos),
+                 on(t.data1.sap_y_pos),
+                 on(t.data1.sap_y_neg),
+                 on(t.data1.sap_z_pos),
+                 on(t.data1.sap_z_neg))?;
         writeln!(f)?;
-        writeln!(f, "Command Status:")?;
-        writeln!(f, "  Reserved Commands:  {}", self.telemetry.data2.reserve_cmd_counter)?;
-        writeln!(f, "  Uplink Commands:    {}", self.telemetry.data2.cmd_uplink_counter)?;
-        writeln!(f, "  KILL Switch:        {}", if self.telemetry.data2.kill_sw { "ON" } else { "OFF" })?;
+        writeln!(f, "{}:", l.commands)?;
+        field(f, "  ", l.reserve_cmd_counter, t.data2.reserve_cmd_counter)?;
+        field(f, "  ", l.cmd_uplink_counter, t.data2.cmd_uplink_counter)?;
+        field(f, "  ", l.kill_sw, self.flag(l.on_off(t.data2.kill_sw), !t.data2.kill_sw))?;
         writeln!(f)?;
-        writeln!(f, "Mission Status:")?;
-        writeln!(f, "  KILL Counter:       {}", self.telemetry.data3.kill_counter)?;
-        writeln!(f, "  Mission PIC:        {}", if self.telemetry.data3.mission_pic_on { "ON" } else { "OFF" })?;
-        writeln!(f, "  Mission Error:      {}", if self.telemetry.data3.mis_error_flag { "YES" } else { "NO" })?;
-        writeln!(f, "  Mission End:        {}", if self.telemetry.data3.mis_end_flag { "YES" } else { "NO" })?;
-        writeln!(f, "  APRS Mission:       {}", if self.telemetry.data3.aprs_flag { "ACTIVE" } else { "INACTIVE" })?;
-        writeln!(f, "  Current Mission:    {}", match self.telemetry.data3.current_mis {
-            0 => "None",
-            1 => "Earth",
-            2 => "Sun", 
-            _ => "Unknown"
-        })
-    }
-}
-
-/// Parse a BOTAN beacon string
+        writeln!(f, "{}:", l.mission)?;
+        field(f, "  ", l.kill_counter, t.data3.kill_counter)?;
+        field(f, "  ", l.mission_pic, on(t.data3.mission_pic_on))?;
+        field(f, "  ", l.mis_error, self.flag(l.yes_no(t.data3.mis_error_flag), !t.data3.mis_error_flag))?;
+        field(f, "  ", l.mis_end, self.flag(l.yes_no(t.data3.mis_end_flag), t.data3.mis_end_flag))?;
+        field(f, "  ", l.aprs, self.flag(l.active(t.data3.aprs_flag), t.data3.aprs_flag))?;
+        field(f, "  ", l.current_mis, self.options.codes.mission(t.data3.current_mis, l))?;
+        if self.options.verbose_bytes {
+            writeln!(f)?;
+            f.push_str(&breakdown::byte_breakdown(data, &self.options.calibration, u));
+        }
+        if self.options.bit_view {
+            writeln!(f)?;
+            f.push_str(&breakdown::bit_view(data));
+        }
+        out.write_str(f.trim_end_matches('\n'))
+    }
+}
+
+/// Parse a BOTAN beacon string with the flight calibration, leaving the signal report uncalibrated
 pub fn parse_botan_beacon(input: &str) -> Result<BotanBeaconData, String> {
-    let parts: Vec<&str> = input.trim().split_whitespace().collect();
+    parse_botan_beacon_with(input, &SignalCalibration::default(), &CalibrationTable::default())
+}
+
+/// Parse a BOTAN beacon string, converting the signal report with `calibration` and the
+/// telemetry with `table`. A well-formed line is parsed without allocating; bulk reprocessing
+/// of archives spends its time here.
+pub fn parse_botan_beacon_with(
+    input: &str,
+    calibration: &SignalCalibration,
+    table: &CalibrationTable,
+) -> Result<BotanBeaconData, String> {
+    let input = normalize_line(input);
+    trace!("parse beacon"; input = input);
+    let (rssi, data_str) = parse_beacon_header(&input, calibration)?;
+    if data_str.len() != 16 { // 8 bytes = 16 hex characters
+        return Err(format!("Invalid data length. Expected 16 hex characters, got {}", data_str.len()));
+    }
     
-    if parts.len() < 3 {
-        return Err("Invalid beacon format. Expected: BOTAN JS1YPT (Optional<RSSI>) <data>".to_string());
+    // Convert hex string to bytes
+    let mut frame = [0; 8];
+    parse_hex_into(data_str, &mut frame)?;
+    
+    // Parse telemetry according to the definition
+    let telemetry = parse_telemetry_bytes(&frame, table)?;
+    
+    Ok(BotanBeaconData {
+        satellite_name: "BOTAN",
+        call_sign: "JS1YPT",
+        rssi,
+        frame,
+        telemetry,
+        confidence: None,
+    })
+}
+
+/// `input` as the parser expects it: full-width characters and Unicode spaces as ASCII, and the
+/// data block as one run of uppercase hex digits however a logging tool grouped it, e.g.
+/// "a6 7c 8d 5e 2a a1 36 08", "A6-7C-8D-5E-2A-A1-36-08" or "A6:7C:8D:5E:2A:A1:36:08"
+pub fn normalize_line(input: &str) -> Cow<'_, str> {
+    let input = i18n::normalize_width(input);
+    if is_normal(&input) {
+        return input;
+    }
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    if parts.len() < 3 || !parts[0].eq_ignore_ascii_case("BOTAN") || !parts[1].eq_ignore_ascii_case("JS1YPT") {
+        return input;
+    }
+    let report = parts.len() > 3 && parts[2].len() == 6 && parts[2].get(..2).is_some_and(|si| si.eq_ignore_ascii_case("SI"));
+    let first = if report { 3 } else { 2 };
+    // Groups are joined up to the 16 digits of a housekeeping frame, so tokens after a complete
+    // block stay apart
+    let grouped = |part: &str| part.chars().all(|c| c.is_ascii_hexdigit() || c == '-' || c == ':');
+    let mut data = String::new();
+    let mut end = first;
+    while end < parts.len() && grouped(parts[end]) && data.len() < 16 {
+        data.extend(parts[end].chars().filter(char::is_ascii_hexdigit).map(|c| c.to_ascii_uppercase()));
+        end += 1;
+    }
+    if end == first || data.is_empty() {
+        return input;
     }
+    let mut fields: Vec<&str> = parts[..first].to_vec();
+    fields.push(&data);
+    fields.extend(&parts[end..]);
+    let line = fields.join(" ");
+    if line == input {
+        input
+    } else {
+        Cow::Owned(line)
+    }
+}
+
+/// Whether `line` is already as `normalize_line` would leave it: the header, an optional signal
+/// report and a data block of at least 16 uppercase hex digits, one space apart. Most lines are,
+/// and are checked without allocating.
+fn is_normal(line: &str) -> bool {
+    // One pass for stray whitespace; `normalize_width` has already turned non-ASCII spaces into ' '
+    let mut previous = b' ';
+    for &b in line.as_bytes() {
+        if matches!(b, b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r') || b == b' ' && previous == b' ' {
+            return false;
+        }
+        previous = b;
+    }
+    if previous == b' ' {
+        return false;
+    }
+    let mut parts = line.split(' ');
+    if parts.next() != Some("BOTAN") || parts.next() != Some("JS1YPT") {
+        return false;
+    }
+    let (third, fourth) = (parts.next(), parts.next());
+    let data = match third {
+        Some(report) if report.len() == 6 && report.starts_with("SI") => fourth,
+        _ => third,
+    };
+    data.is_some_and(|data| data.len() >= 16 && data.bytes().all(|b| b.is_ascii_digit() || (b'A'..=b'F').contains(&b)))
+}
+
+/// Check the "BOTAN JS1YPT" header of a beacon line and convert the optional signal report,
+/// returning it with the data block that follows
+pub(crate) fn parse_beacon_header<'a>(
+    input: &'a str,
+    calibration: &SignalCalibration,
+) -> Result<(Option<BotanRSSI>, &'a str), String> {
+    // Walked rather than collected, so a valid line costs no allocation
+    let mut parts = input.split_whitespace();
+    let (Some(satellite), Some(call_sign), Some(third)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err("Invalid beacon format. Expected: BOTAN JS1YPT (Optional<RSSI>) <data>".to_string());
+    };
     
     // Validate header
-    if parts[0] != "BOTAN" {
-        return Err(format!("Invalid satellite name. Expected 'BOTAN', got '{}'", parts[0]));
+    if satellite != "BOTAN" {
+        return Err(format!("Invalid satellite name. Expected 'BOTAN', got '{}'", satellite));
     }
     
-    if parts[1] != "JS1YPT" {
-        return Err(format!("Invalid call sign. Expected 'JS1YPT', got '{}'", parts[1]));
+    if call_sign != "JS1YPT" {
+        return Err(format!("Invalid call sign. Expected 'JS1YPT', got '{}'", call_sign));
     }
 
-    // Check for optional RSSI info
+    // Check for optional RSSI info, only when exactly four tokens
     // Format: SI<HEX data>     e.g., "SI8640"
-    let rssi = if parts.len() == 4 {
-        let rssi_str = parts[2];
+    let fourth = match (parts.next(), parts.next()) {
+        (Some(fourth), None) => Some(fourth),
+        _ => None,
+    };
+    let rssi = if fourth.is_some() {
+        let rssi_str = third;
         if !rssi_str.starts_with("SI") || rssi_str.len() != 6 {
             return Err("Invalid RSSI format. Expected 'SI' followed by 4 hex characters".to_string());
         }
         // Parse RSSI & SNR from hex
-        let rssi_hex = &rssi_str[2..4];
-        let snr_hex = &rssi_str[4..];
-        let rssi_dbm = match u8::from_str_radix(rssi_hex, 16) {
-            Ok(val) => val as f64,
-            Err(_) => return Err("Invalid RSSI hex value".to_string()),
+        let bytes = rssi_str.as_bytes();
+        let raw_rssi = match hex_byte(&bytes[2..4]) {
+            Some(val) => val,
+            None => return Err("Invalid RSSI hex value".to_string()),
         };
-        let snr_db = match u8::from_str_radix(snr_hex, 16) {
-            Ok(val) => val as f64,
-            Err(_) => return Err("Invalid SNR hex value".to_string()),
+        let raw_snr = match hex_byte(&bytes[4..]) {
+            Some(val) => val,
+            None => return Err("Invalid SNR hex value".to_string()),
         };
-        Some(BotanRSSI { rssi_dbm, snr_db })
+        Some(calibration.convert(raw_rssi, raw_snr))
     } else {
         None
     };
     
-    // Parse the 8-byte data block
-    let data_str = match rssi {
-        Some(_) => parts[3],
-        None => parts[2],
+    // The data block follows the optional signal report
+    let data_str = fourth.unwrap_or(third);
+    Ok((rssi, data_str))
+}
+
+/// One character of a hex field replaced by `correct_hex_confusions`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correction {
+    pub field: &'static str,    // "SI" or "data"
+    pub position: usize,        // Character within the field's hex digits
+    pub from: char,
+    pub to: char,
+}
+
+impl fmt::Display for Correction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}→{} at {} position {}", self.from, self.to, self.field, self.position)
+    }
+}
+
+/// Replace the letters O and I or l, which cannot be hex digits, with the 0 and 1 a hand copy
+/// most likely meant, in the signal report and the data block of a beacon line. Input is
+/// uppercased on most paths, so L counts as l. The header is left alone, so a line that is not
+/// a beacon comes back as it was.
+pub fn correct_hex_confusions(input: &str) -> (String, Vec<Correction>) {
+    let normalized = normalize_line(input);
+    let parts: Vec<&str> = normalized.split_whitespace().collect();
+    if parts.len() < 3 || !parts[0].eq_ignore_ascii_case("BOTAN") || !parts[1].eq_ignore_ascii_case("JS1YPT") {
+        return (input.to_string(), Vec::new());
+    }
+    let mut corrections = Vec::new();
+    let mut correct = |field: &'static str, digits: &str| -> String {
+        let digits = digits.chars().enumerate().map(|(position, from)| {
+            let to = match from {
+                'O' | 'o' => '0',
+                'I' | 'l' | 'L' => '1',
+                _ => return from,
+            };
+            corrections.push(Correction { field, position, from, to });
+            to
+        });
+        digits.collect()
     };
-    if data_str.len() != 16 { // 8 bytes = 16 hex characters
-        return Err(format!("Invalid data length. Expected 16 hex characters, got {}", data_str.len()));
+    // The same fields parse_beacon_header reads: a signal report only in a four-part line
+    let data = if parts.len() == 4 { 3 } else { 2 };
+    let mut fields = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        fields.push(match part.strip_prefix("SI") {
+            Some(digits) if i == 2 && data == 3 => format!("SI{}", correct("SI", digits)),
+            _ if i == data => correct("data", part),
+            _ => part.to_string(),
+        });
     }
-    
-    // Convert hex string to bytes
-    let mut bytes = Vec::new();
-    for i in (0..data_str.len()).step_by(2) {
-        match u8::from_str_radix(&data_str[i..i+2], 16) {
-            Ok(byte) => bytes.push(byte),
-            Err(_) => return Err(format!("Invalid hex data at position {}-{}: {}", i, i+1, &data_str[i..i+2])),
-        }
+    if corrections.is_empty() {
+        return (input.to_string(), corrections);
     }
-    
-    if bytes.len() != 8 {
-        return Err(format!("Expected 8 bytes, got {}", bytes.len()));
+    (fields.join(" "), corrections)
+}
+
+/// Tokens after the data block of a beacon line, which parsing ignores
+pub fn trailing_tokens(input: &str) -> Vec<&str> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    // parse_beacon_header reads a signal report only from a four-part line, so in a longer one
+    // the data block is the third
+    if parts.len() > 4 {
+        parts[3..].to_vec()
+    } else {
+        Vec::new()
     }
-    
-    // Parse telemetry according to the definition
-    let telemetry = parse_telemetry_bytes(&bytes)?;
-    
-    Ok(BotanBeaconData {
-        satellite_name: "BOTAN".to_string(),
-        call_sign: "JS1YPT".to_string(),
-        rssi,
-        telemetry,
-    })
 }
 
-fn parse_telemetry_bytes(bytes: &[u8]) -> Result<BotanTelemetry, String> {
+/// A fix of one character that would make a beacon's data block readable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repair {
+    Replace(usize),    // The character at this position in the line is not a hex digit
+    Insert(usize),     // One hex digit short; the data block ends at this position
+    Remove(usize),     // One hex digit too many; the data block ends at this position
+}
+
+/// The one-character fix `line` needs, if its header is fine and its data block is one invalid
+/// digit or one digit of length away from the 16 a housekeeping frame has. Positions count
+/// characters of `line`.
+pub fn one_character_repair(line: &str) -> Option<Repair> {
+    let (_, data) = parse_beacon_header(line, &SignalCalibration::default()).ok()?;
+    // The data block is a slice of `line`
+    let start = line[..data.as_ptr() as usize - line.as_ptr() as usize].chars().count();
+    let digits: Vec<char> = data.chars().collect();
+    let invalid: Vec<usize> = (0..digits.len()).filter(|&i| !digits[i].is_ascii_hexdigit()).collect();
+    match (digits.len(), invalid.as_slice()) {
+        (16, [i]) => Some(Repair::Replace(start + i)),
+        (15, []) => Some(Repair::Insert(start + 15)),
+        (17, []) => Some(Repair::Remove(start + 17)),
+        _ => None,
+    }
+}
+
+/// Hex data block of any length as bytes
+pub(crate) fn parse_hex_data(data_str: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; data_str.len().div_ceil(2)];
+    parse_hex_into(data_str, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Hex digits into `bytes`, which holds exactly as many bytes as there are digit pairs
+fn parse_hex_into(data_str: &str, bytes: &mut [u8]) -> Result<(), String> {
+    for (i, (pair, byte)) in data_str.as_bytes().chunks(2).zip(bytes).enumerate() {
+        *byte = hex_byte(pair).ok_or_else(|| {
+            let (start, pair) = (i * 2, String::from_utf8_lossy(pair));
+            format!("Invalid hex data at position {}-{}: {}", start, start + 1, pair)
+        })?;
+    }
+    Ok(())
+}
+
+/// Two hex digits as a byte. Works on bytes, so input with multi-byte characters is rejected
+/// rather than split mid-character, and unlike `from_str_radix` a sign is not accepted.
+fn hex_byte(pair: &[u8]) -> Option<u8> {
+    let digit = |b: u8| (b as char).to_digit(16);
+    match pair {
+        [high, low] => Some((digit(*high)? * 16 + digit(*low)?) as u8),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_telemetry_bytes(bytes: &[u8], table: &CalibrationTable) -> Result<BotanTelemetry, String> {
     if bytes.len() != 8 {
         return Err(format!("Expected 8 bytes for telemetry, got {}", bytes.len()));
     }
     
-    // Convert bytes to decimal values for calculations
-    let byte1 = bytes[0] as f64; // BAT_V
-    let byte2 = bytes[1] as f64; // BAT_I  
-    let byte3 = bytes[2] as f64; // BAT_T
-    let byte4 = bytes[3] as f64; // BPB_T
-    let byte5 = bytes[4] as f64; // RAW_I
+    // Convert the analog bytes according to the formulas in the definition
+    let value = |channel: Channel| table.value(channel, channel.field().extract(bytes)? as u8);
+    let bat_v = value(Channel::BatV)?;
+    let bat_i = value(Channel::BatI)?;
+    let bat_t = value(Channel::BatT)?;
+    let bpb_t = value(Channel::BpbT)?;
+    let raw_i = value(Channel::RawI)?;
     let byte6 = bytes[5];        // data1 (bitfield)
     let byte7 = bytes[6];        // data2 (bitfield)
     let byte8 = bytes[7];        // data3 (bitfield)
     
-    // Calculate converted values according to formulas in definition
-    let bat_v = byte1 * 0.025781;
-    let bat_i = byte2 * (-50.045) + 6330.4;
-    
-    // Battery temperature calculation (complex formula)
-    let bat_t = {
-        let inner = (byte3 * 0.01289) / (3.3 - byte3 * 0.01289);
-        if inner <= 0.0 {
-            return Err("Invalid battery temperature calculation: logarithm of non-positive number".to_string());
-        }
-        (1185000.0 / (inner.ln() * 298.0 + 3976.0)) - 273.0
-    };
-    
-    // Board temperature calculation
-    let bpb_t = {
-        let discriminant = 36.44506 - byte4 * 0.06875;
-        if discriminant < 0.0 {
-            return Err("Invalid board temperature calculation: square root of negative number".to_string());
-        }
-        30.0 - ((discriminant.sqrt() - 5.506) / 0.00352)
-    };
-    
-    let raw_i = byte5 * 51.84 - 1950.9;
-    
     // Parse bitfields
     let data1 = Data1Flags {
         power_5v0: (byte6 & 0x80) != 0,      // Bit 7
@@ -305,6 +692,67 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_full_width_beacon() {
+        // As pasted from a Japanese chat: full-width characters and an ideographic space
+        let pasted = parse_botan_beacon("ＢＯＴＡＮ　ＪＳ１ＹＰＴ　ＳＩ８６４０　Ａ５７ＥＢ７６８２３２１０Ｅ０８").unwrap();
+        assert_eq!(pasted, parse_botan_beacon("BOTAN JS1YPT SI8640 A57EB76823210E08").unwrap());
+    }
+
+    #[test]
+    fn test_correct_hex_confusions() {
+        let (line, corrections) = correct_hex_confusions("BOTAN JS1YPT SI864O A57EB7682321OEl8");
+        assert_eq!(line, "BOTAN JS1YPT SI8640 A57EB76823210E18");
+        let shown: Vec<String> = corrections.iter().map(Correction::to_string).collect();
+        assert_eq!(shown, ["O→0 at SI position 3", "O→0 at data position 12", "l→1 at data position 14"]);
+        assert!(parse_botan_beacon(&line).is_ok());
+
+        // Full-width letters as well, and the header is never touched
+        assert_eq!(correct_hex_confusions("ＢＯＴＡＮ　ＪＳ１ＹＰＴ　ＡＯ").0, "BOTAN JS1YPT A0");
+        assert_eq!(correct_hex_confusions("BOTAN JS1YPT A57EB76823210E08"), ("BOTAN JS1YPT A57EB76823210E08".to_string(), vec![]));
+        assert!(correct_hex_confusions("HELLO WORLD OIL").1.is_empty());
+    }
+
+    #[test]
+    fn test_grouped_data_block() {
+        let expected = parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap();
+        for line in [
+            "BOTAN JS1YPT SI8640 A6 7C 8D 5E 2A A1 36 08",
+            "BOTAN JS1YPT SI8640 a6-7c-8d-5e-2a-a1-36-08",
+            "BOTAN JS1YPT SI8640 A6:7C:8D:5E:2A:A1:36:08",
+            "BOTAN JS1YPT SI8640 A67C 8D5E 2AA1 3608",
+        ] {
+            assert_eq!(parse_botan_beacon(line).unwrap(), expected, "{}", line);
+        }
+        assert_eq!(normalize_line("BOTAN JS1YPT a6 7c 8d 5e 2a a1 36 08 73 TU"), "BOTAN JS1YPT A67C8D5E2AA13608 73 TU");
+        assert!(matches!(normalize_line("BOTAN JS1YPT A67C8D5E2AA13608"), Cow::Borrowed(_)));
+        assert!(matches!(normalize_line("BOTAN JS1YPT SI8640 A67C8D5E2AA13608 73 TU"), Cow::Borrowed(_)));
+        assert_eq!(normalize_line("BOTAN  JS1YPT SI8640 a67c8d5e2aa13608"), "BOTAN JS1YPT SI8640 A67C8D5E2AA13608");
+        // Only beacon lines are touched
+        assert_eq!(normalize_line("-.. . - -"), "-.. . - -");
+        assert!(parse_botan_beacon("BOTAN JS1YPT A6 7C 8D").unwrap_err().contains("got 6"));
+    }
+
+    #[test]
+    fn test_trailing_tokens() {
+        assert!(trailing_tokens("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").is_empty());
+        assert!(trailing_tokens("BOTAN JS1YPT A67C8D5E2AA13608").is_empty());
+        assert_eq!(trailing_tokens("BOTAN JS1YPT A67C8D5E2AA13608 73 TU"), ["73", "TU"]);
+        assert!(parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608 73 TU").is_ok());
+    }
+
+    #[test]
+    fn test_one_character_repair() {
+        assert_eq!(one_character_repair("BOTAN JS1YPT A67C8D5EGAA13608"), Some(Repair::Replace(21)));
+        assert_eq!(one_character_repair("BOTAN JS1YPT SI8640 A67C8D5E2AA1360"), Some(Repair::Insert(35)));
+        assert_eq!(one_character_repair("BOTAN JS1YPT A67C8D5E2AA136080"), Some(Repair::Remove(30)));
+        // Positions are characters, not bytes
+        assert_eq!(one_character_repair("BOTAN JS1YPT A67C8D5E2AA1360ー"), Some(Repair::Replace(28)));
+        assert_eq!(one_character_repair("BOTAN JS1YPT A67C8D5EGAA1360X"), None);
+        assert_eq!(one_character_repair("BOTAN JS1YPT A67C"), None);
+        assert_eq!(one_character_repair("BOTAM JS1YPT A67C8D5EGAA13608"), None);
+    }
+
     #[test]
     fn test_example_beacon() {
         let input = "BOTAN JS1YPT A57EB76823210E08";
@@ -323,6 +771,44 @@ mod tests {
         println!("Parsed beacon data:\n{}", beacon);
     }
 
+    #[test]
+    fn test_telemetry_comparison() {
+        let beacon = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let expected = BotanTelemetry {
+            bat_v: 4.280,
+            bat_i: 124.8,
+            bat_t: 20.56,
+            bpb_t: 38.63,
+            raw_i: 226.4,
+            data1: Data1Flags {
+                power_5v0: true,
+                power_depant: false,
+                power_com: true,
+                sap_x_pos: false,
+                sap_y_pos: false,
+                sap_y_neg: false,
+                sap_z_pos: false,
+                sap_z_neg: true,
+            },
+            data2: Data2Flags { reserve_cmd_counter: 3, cmd_uplink_counter: 3, kill_sw: false },
+            data3: Data3Flags {
+                kill_counter: 0,
+                mission_pic_on: false,
+                mis_error_flag: false,
+                mis_end_flag: true,
+                aprs_flag: false,
+                current_mis: 0,
+            },
+        };
+        assert!(beacon.telemetry.approx_eq(&expected, 0.05), "{:?}", beacon.telemetry.approx_diff(&expected, 0.05));
+        assert_eq!(beacon.telemetry.approx_diff(&expected, 0.001), ["bat_i", "bat_t", "bpb_t", "raw_i"]);
+
+        let mut flipped = expected.clone();
+        flipped.data2.kill_sw = true;
+        assert_eq!(beacon.telemetry.approx_diff(&flipped, 0.05), ["data2"]);
+        assert_eq!(beacon, parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap());
+    }
+
     #[test]
     fn test_different_beacon_values() {
         // Test with safe values that won't cause math errors
@@ -342,4 +828,124 @@ mod tests {
             println!("Zero values correctly failed due to math constraints");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_colored_report() {
+        let beacon = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"max": 4.3, "warn": 0.05}, "bat_t": {"max": 20}}"#).unwrap();
+        let options = DisplayOptions { color: true, limits, ..Default::default() };
+        let report = beacon.display(&options).to_string();
+        assert!(report.contains("Battery Voltage:      \x1b[33m4.280 V\x1b[0m"));
+        assert!(report.contains("Battery Temperature:  \x1b[31m20.6 °C\x1b[0m"));
+        assert!(report.contains("Board Temperature:    38.6 °C\n"));
+        assert!(report.contains("RSSI:                 \x1b[2mN/A\x1b[0m"));
+        assert!(report.contains("5V Power Line:      \x1b[32mON\x1b[0m"));
+        assert!(report.contains("KILL Switch:        \x1b[32mOFF\x1b[0m"));
+        assert!(!beacon.to_string().contains('\x1b'));
+    }
+
+    #[test]
+    fn test_signal_calibration() {
+        let input = "BOTAN JS1YPT SI8640 A57EB76823210E08";
+        let raw = parse_botan_beacon(input).unwrap().rssi.unwrap();
+        assert_eq!((raw.raw_rssi, raw.raw_snr), (0x86, 0x40));
+        assert!(raw.rssi_dbm.is_none() && raw.snr_db.is_none());
+
+        let calibration: SignalCalibration =
+            serde_json::from_str(r#"{"rssi": {"slope": -0.5, "offset": -20}, "snr": {"signed": true, "slope": 0.25}}"#)
+                .unwrap();
+        let beacon = parse_botan_beacon_with(input, &calibration, &CalibrationTable::default()).unwrap();
+        let rssi = beacon.rssi.as_ref().unwrap();
+        assert_eq!(rssi.rssi_dbm, Some(-87.0));
+        assert_eq!(rssi.snr_db, Some(16.0));
+        assert!(beacon.to_string().contains("RSSI:                 -87.0 dBm"));
+
+        // Signed bytes above 0x7F are negative
+        let signed = SignalConversion { slope: 1.0, offset: 0.0, signed: true };
+        assert_eq!(signed.apply(0xF6), -10.0);
+    }
+
+    /// One file of the tests/vectors corpus. The frames are synthetic and the snapshots were
+    /// written by this decoder, so they catch changes in its output, not disagreement with the
+    /// telemetry definition.
+    #[derive(Deserialize, Serialize)]
+    struct Vector {
+        description: String,
+        source: String,      // Where the frame came from: the README, the unit tests or "synthetic"
+        input: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        calibration: Option<serde_json::Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected: Option<serde_json::Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    }
+
+    /// Differences between a snapshot and the actual output; numbers match to 1e-9 relative
+    fn diff_json(expected: &serde_json::Value, actual: &serde_json::Value, path: &str, diffs: &mut Vec<String>) {
+        use serde_json::Value;
+        match (expected, actual) {
+            (Value::Object(e), Value::Object(a)) => {
+                for key in e.keys().chain(a.keys().filter(|k| !e.contains_key(*k))) {
+                    let (e, a) = (e.get(key).unwrap_or(&Value::Null), a.get(key).unwrap_or(&Value::Null));
+                    diff_json(e, a, &format!("{}.{}", path, key), diffs);
+                }
+            }
+            (Value::Number(e), Value::Number(a)) => {
+                let (e, a) = (e.as_f64().unwrap(), a.as_f64().unwrap());
+                if (e - a).abs() > 1e-9 * e.abs().max(1.0) {
+                    diffs.push(format!("{}: expected {}, got {}", path, e, a));
+                }
+            }
+            (e, a) if e != a => diffs.push(format!("{}: expected {}, got {}", path, e, a)),
+            _ => {}
+        }
+    }
+
+    /// Regression snapshots over tests/vectors/*.json; run with BOTAN_BLESS=1 to rewrite them
+    #[test]
+    fn test_golden_vectors() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("vectors");
+        let bless = std::env::var_os("BOTAN_BLESS").is_some();
+        let mut paths: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        assert!(paths.len() >= 10, "test vectors missing from {}", dir.display());
+
+        let mut failures = Vec::new();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let mut vector: Vector = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let calibration: SignalCalibration = vector
+                .calibration
+                .clone()
+                .map(|c| serde_json::from_value(c).unwrap())
+                .unwrap_or_default();
+            let (expected, error) = match parse_botan_beacon_with(&vector.input, &calibration, &CalibrationTable::default()) {
+                Ok(data) => (Some(serde_json::to_value(data).unwrap()), None),
+                Err(error) => (None, Some(error)),
+            };
+            if bless {
+                vector.expected = expected;
+                vector.error = error;
+                std::fs::write(&path, serde_json::to_string_pretty(&vector).unwrap() + "\n").unwrap();
+                continue;
+            }
+            match (&vector.expected, &expected) {
+                (Some(snapshot), Some(actual)) => {
+                    let mut diffs = Vec::new();
+                    diff_json(snapshot, actual, "", &mut diffs);
+                    failures.extend(diffs.into_iter().map(|diff| format!("{}: {}", name, diff)));
+                }
+                _ if vector.error != error => {
+                    failures.push(format!("{}: expected {:?} / error {:?}, got error {:?}", name, vector.expected, vector.error, error));
+                }
+                _ => {}
+            }
+        }
+        assert!(failures.is_empty(), "golden vectors differ (BOTAN_BLESS=1 accepts the new output):\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,191 @@
+// Views of the raw telemetry frame for checking the decoder against the telemetry definition
+// sheet: every byte with the formula or bit fields behind its values, and the bit layout.
+use std::fmt::Write as _;
+
+use crate::botan_parser::BotanBeaconData;
+use crate::calibration::{CalibrationTable, Channel};
+use crate::field::Field;
+use crate::units::Units;
+
+/// A group of bits inside one of the status bytes
+pub struct BitField {
+    pub byte: usize,    // Index into the frame
+    pub high: u8,       // Most significant bit of the group
+    pub low: u8,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+impl BitField {
+    pub fn width(&self) -> u8 {
+        self.high - self.low + 1
+    }
+
+    pub fn value(&self, frame: &[u8; 8]) -> u8 {
+        // At most 3 bits of a status byte, so always inside the frame and below 256
+        Field::bits(self.byte, self.high, self.low).extract(frame).unwrap_or_default() as u8
+    }
+}
+
+const fn bits(byte: usize, high: u8, low: u8, name: &'static str, description: &'static str) -> BitField {
+    BitField { byte, high, low, name, description }
+}
+
+/// Layout of data1-data3 as the parser reads them
+pub const BIT_FIELDS: [BitField; 18] = [
+    bits(5, 7, 7, "power_5v0", "5V power line"),
+    bits(5, 6, 6, "power_depant", "Antenna deployment power line"),
+    bits(5, 5, 5, "power_com", "Transponder power line"),
+    bits(5, 4, 4, "sap_x_pos", "+X panel generating"),
+    bits(5, 3, 3, "sap_y_pos", "+Y panel generating"),
+    bits(5, 2, 2, "sap_y_neg", "-Y panel generating"),
+    bits(5, 1, 1, "sap_z_pos", "+Z panel generating"),
+    bits(5, 0, 0, "sap_z_neg", "-Z panel generating"),
+    bits(6, 7, 7, "-", "Not used"),
+    bits(6, 6, 4, "reserve_cmd_counter", "Reserved commands"),
+    bits(6, 3, 1, "cmd_uplink_counter", "Received commands"),
+    bits(6, 0, 0, "kill_sw", "KILL switch"),
+    bits(7, 7, 6, "kill_counter", "KILL switch occurrences"),
+    bits(7, 5, 5, "mission_pic_on", "Mission PIC power"),
+    bits(7, 4, 4, "mis_error_flag", "Mission error"),
+    bits(7, 3, 3, "mis_end_flag", "Mission end"),
+    bits(7, 2, 2, "aprs_flag", "APRS mission executing"),
+    bits(7, 1, 0, "current_mis", "Current mission (0 none, 1 Earth, 2 Sun)"),
+];
+
+const STATUS: [&str; 3] = ["data1", "data2", "data3"];
+
+/// The annotated breakdown of all 8 bytes, with the formulas of `table`. Values are shown in
+/// `units`, as in the report; the formulas give V, mA and °C.
+pub fn byte_breakdown(data: &BotanBeaconData, table: &CalibrationTable, units: Units) -> String {
+    let t = &data.telemetry;
+    let values = [
+        units.voltage(t.battery_voltage()),
+        units.current(t.battery_current()),
+        units.temperature(t.battery_temperature()),
+        units.temperature(t.board_temperature()),
+        units.current(t.consumption_current()),
+    ]
+    .map(|quantity| quantity.to_string());
+
+    let mut out = String::new();
+    writeln!(out, "Byte Breakdown:").unwrap();
+    writeln!(out, "--------------").unwrap();
+    writeln!(out, "Byte  Hex  Dec  Field  Value      Formula").unwrap();
+    for (idx, (channel, value)) in Channel::ALL.iter().zip(&values).enumerate() {
+        let raw = data.frame[idx];
+        let formula = table.formula(*channel).replace("raw", &raw.to_string());
+        writeln!(out, "{:>4}  {:02X}  {:>4}  {:<5}  {:<9}  {}", idx + 1, raw, raw, channel.name(), value, formula).unwrap();
+    }
+    for (offset, name) in STATUS.iter().enumerate() {
+        let idx = Channel::ALL.len() + offset;
+        let raw = data.frame[idx];
+        writeln!(out, "{:>4}  {:02X}  {:>4}  {:<5}  {:08b}", idx + 1, raw, raw, name, raw).unwrap();
+        for field in BIT_FIELDS.iter().filter(|f| f.byte == idx) {
+            let position = if field.width() == 1 {
+                format!("bit {}", field.high)
+            } else {
+                format!("bits {}-{}", field.high, field.low)
+            };
+            let value = field.value(&data.frame);
+            let binary = format!("{:0width$b}", value, width = field.width() as usize);
+            writeln!(out, "      {:<9} {:>3} = {}  {:<20} {}", position, binary, value, field.name, field.description).unwrap();
+        }
+    }
+    out
+}
+
+/// The 64 frame bits with a space at every field boundary, followed by the bit range of each field
+pub fn bit_view(data: &BotanBeaconData) -> String {
+    // Bit groups of each byte: one for an analog byte, one per field for a status byte
+    let groups: Vec<String> = (0..8)
+        .map(|idx| {
+            let fields: Vec<String> = BIT_FIELDS
+                .iter()
+                .filter(|f| f.byte == idx)
+                .map(|f| format!("{:0width$b}", f.value(&data.frame), width = f.width() as usize))
+                .collect();
+            if fields.is_empty() { format!("{:08b}", data.frame[idx]) } else { fields.join(" ") }
+        })
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "Frame Bits:").unwrap();
+    writeln!(out, "--------------").unwrap();
+    let hex: Vec<String> = groups
+        .iter()
+        .zip(data.frame)
+        .map(|(group, byte)| format!("{:<width$}", format!("{:02X}", byte), width = group.len()))
+        .collect();
+    writeln!(out, "hex   {}", hex.join(" ").trim_end()).unwrap();
+    writeln!(out, "bits  {}", groups.join(" ")).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "Bits   Byte  Field                Value").unwrap();
+    for (idx, channel) in Channel::ALL.iter().enumerate() {
+        let high = 63 - idx * 8;
+        writeln!(out, "{:<5}  {:>4}  {:<20} {:08b}", format!("{}-{}", high, high - 7), idx + 1, channel.name(), data.frame[idx]).unwrap();
+    }
+    for field in &BIT_FIELDS {
+        let base = (7 - field.byte) * 8;
+        let bits = if field.width() == 1 {
+            (base + field.high as usize).to_string()
+        } else {
+            format!("{}-{}", base + field.high as usize, base + field.low as usize)
+        };
+        let name = if field.name == "-" { field.description } else { field.name };
+        let value = format!("{:0width$b}", field.value(&data.frame), width = field.width() as usize);
+        writeln!(out, "{:<5}  {:>4}  {:<20} {}", bits, field.byte + 1, name, value).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_byte_breakdown() {
+        let beacon = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let text = byte_breakdown(&beacon, &CalibrationTable::default(), Units::default());
+        assert!(text.contains("   1  A6   166  BAT_V  4.280 V    166 * 0.025781\n"));
+        assert!(text.contains("   2  7C   124  BAT_I  124.8 mA   124 * -50.045 + 6330.4\n"));
+        assert!(text.contains("   6  A1   161  data1  10100001\n"));
+        assert!(text.contains("   7  36    54  data2  00110110\n"));
+        assert!(text.contains("      bits 6-4  011 = 3  reserve_cmd_counter  Reserved commands\n"));
+        assert!(text.contains("      bit 0       0 = 0  kill_sw              KILL switch\n"));
+
+        // Values follow the display units, like the report above the breakdown
+        let units = Units { temperature: "f".parse().unwrap(), current: "a".parse().unwrap(), voltage: "mv".parse().unwrap() };
+        let text = byte_breakdown(&beacon, &CalibrationTable::default(), units);
+        assert!(text.contains("   1  A6   166  BAT_V  4280 mV    166 * 0.025781\n"));
+        assert!(text.contains("   2  7C   124  BAT_I  0.1248 A   124 * -50.045 + 6330.4\n"));
+        assert!(text.contains("  BAT_T  69.0 °F  "), "{}", text);
+    }
+
+    #[test]
+    fn test_bit_view() {
+        let beacon = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let text = bit_view(&beacon);
+        assert!(text.contains(
+            "hex   A6       7C       8D       5E       2A       A1              36          08\n"
+        ));
+        assert!(text.contains(
+            "bits  10100110 01111100 10001101 01011110 00101010 1 0 1 0 0 0 0 1 0 011 011 0 00 0 0 1 0 00\n"
+        ));
+        assert!(text.contains("63-56     1  BAT_V                10100110\n"));
+        assert!(text.contains("23        6  power_5v0            1\n"));
+        assert!(text.contains("1-0       8  current_mis          00\n"));
+    }
+
+    #[test]
+    fn test_bit_fields_cover_status_bytes() {
+        for byte in 5..8 {
+            let mask = BIT_FIELDS
+                .iter()
+                .filter(|f| f.byte == byte)
+                .fold(0u16, |mask, f| mask | (((1 << f.width()) - 1) << f.low));
+            assert_eq!(mask, 0xFF, "byte {}", byte + 1);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// Conversion of the five analog telemetry bytes to engineering values and back, with the
+// coefficients of the telemetry definition held in a `CalibrationTable` so a recalibration can
+// be loaded from a file instead of edited into the parser.
+//
+// The inverse (`CalibrationTable::raw`) returns the byte whose decoded value is nearest to the
+// given value: the exact inverse of the formula is worked out, and of the two whole counts
+// around it the one that decodes closer wins. All formulas are monotonic, so values beyond what
+// a byte can express saturate at the first or last valid count instead of failing. NaN has no
+// nearest count and gives None.
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::field::Field;
+
+/// One analog telemetry channel, in frame order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    BatV,
+    BatI,
+    BatT,
+    BpbT,
+    RawI,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [Channel::BatV, Channel::BatI, Channel::BatT, Channel::BpbT, Channel::RawI];
+
+    /// Name in the telemetry definition
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::BatV => "BAT_V",
+            Channel::BatI => "BAT_I",
+            Channel::BatT => "BAT_T",
+            Channel::BpbT => "BPB_T",
+            Channel::RawI => "RAW_I",
+        }
+    }
+
+    /// Where the channel is in the housekeeping frame: one unsigned byte
+    pub fn field(self) -> Field {
+        Field::byte(self as usize)
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Channel::BatV => "V",
+            Channel::BatI | Channel::RawI => "mA",
+            Channel::BatT | Channel::BpbT => "°C",
+        }
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown channel '{}'. Expected bat_v, bat_i, bat_t, bpb_t or raw_i", s))
+    }
+}
+
+/// value = raw * scale + offset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Linear {
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+/// Battery thermistor divider: value = numerator / (ln(x / (supply - x)) * ln_scale + ln_offset) - kelvin,
+/// where x = raw * volts_per_count
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thermistor {
+    pub volts_per_count: f64,
+    pub supply: f64,
+    pub numerator: f64,
+    pub ln_scale: f64,
+    pub ln_offset: f64,
+    pub kelvin: f64,
+}
+
+impl Default for Thermistor {
+    fn default() -> Self {
+        Thermistor { volts_per_count: 0.01289, supply: 3.3, numerator: 1185000.0, ln_scale: 298.0, ln_offset: 3976.0, kelvin: 273.0 }
+    }
+}
+
+/// Board temperature sensor: value = reference - (sqrt(sqrt_offset - raw * sqrt_scale) - root_offset) / divisor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardSensor {
+    pub reference: f64,
+    pub sqrt_offset: f64,
+    pub sqrt_scale: f64,
+    pub root_offset: f64,
+    pub divisor: f64,
+}
+
+impl Default for BoardSensor {
+    fn default() -> Self {
+        BoardSensor { reference: 30.0, sqrt_offset: 36.44506, sqrt_scale: 0.06875, root_offset: 5.506, divisor: 0.00352 }
+    }
+}
+
+/// Coefficients of the five analog channels; channels left out of a file keep the flight values
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalibrationTable {
+    pub bat_v: Linear,
+    pub bat_i: Linear,
+    pub bat_t: Thermistor,
+    pub bpb_t: BoardSensor,
+    pub raw_i: Linear,
+}
+
+impl Default for CalibrationTable {
+    /// The coefficients of the published telemetry definition
+    fn default() -> Self {
+        CalibrationTable {
+            bat_v: Linear { scale: 0.025781, offset: 0.0 },
+            bat_i: Linear { scale: -50.045, offset: 6330.4 },
+            bat_t: Thermistor::default(),
+            bpb_t: BoardSensor::default(),
+            raw_i: Linear { scale: 51.84, offset: -1950.9 },
+        }
+    }
+}
+
+impl CalibrationTable {
+    /// Load a JSON table, e.g. {"bat_v": {"scale": 0.0258}, "bpb_t": {"reference": 29.5}}
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read telemetry calibration '{}': {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid telemetry calibration '{}': {}", path, e))
+    }
+
+    /// Engineering value of a raw byte; an error where the formula is undefined
+    pub fn value(&self, channel: Channel, raw: u8) -> Result<f64, String> {
+        let raw = raw as f64;
+        match channel {
+            Channel::BatV => Ok(raw * self.bat_v.scale + self.bat_v.offset),
+            Channel::BatI => Ok(raw * self.bat_i.scale + self.bat_i.offset),
+            Channel::BatT => {
+                let t = &self.bat_t;
+                // x is the voltage across the thermistor
+                let x = raw * t.volts_per_count;
+                let ratio = x / (t.supply - x);
+                if ratio <= 0.0 {
+                    return Err("Invalid battery temperature calculation: logarithm of non-positive number".to_string());
+                }
+                Ok(t.numerator / (ratio.ln() * t.ln_scale + t.ln_offset) - t.kelvin)
+            }
+            Channel::BpbT => {
+                let b = &self.bpb_t;
+                let discriminant = b.sqrt_offset - raw * b.sqrt_scale;
+                if discriminant < 0.0 {
+                    return Err("Invalid board temperature calculation: square root of negative number".to_string());
+                }
+                Ok(b.reference - (discriminant.sqrt() - b.root_offset) / b.divisor)
+            }
+            Channel::RawI => Ok(raw * self.raw_i.scale + self.raw_i.offset),
+        }
+    }
+
+    /// Byte that decodes nearest to `value`; see the module notes for rounding and clamping
+    pub fn raw(&self, channel: Channel, value: f64) -> Option<u8> {
+        if value.is_nan() {
+            return None;
+        }
+        let exact = match channel {
+            Channel::BatV => (value - self.bat_v.offset) / self.bat_v.scale,
+            Channel::BatI => (value - self.bat_i.offset) / self.bat_i.scale,
+            // At and below absolute zero the thermistor ratio is infinite: the coldest count
+            Channel::BatT if value <= -self.bat_t.kelvin => 255.0,
+            Channel::BatT => {
+                let t = &self.bat_t;
+                let ratio = ((t.numerator / (value + t.kelvin) - t.ln_offset) / t.ln_scale).exp();
+                if ratio.is_infinite() { 255.0 } else { t.supply * ratio / (1.0 + ratio) / t.volts_per_count }
+            }
+            Channel::BpbT => {
+                let b = &self.bpb_t;
+                let root = (b.root_offset + (b.reference - value) * b.divisor).max(0.0);
+                (b.sqrt_offset - root * root) / b.sqrt_scale
+            }
+            Channel::RawI => (value - self.raw_i.offset) / self.raw_i.scale,
+        };
+        let distance = |raw: u8| self.value(channel, raw).ok().map(|v| (v - value).abs());
+        let nearest = |candidates: &[u8]| {
+            candidates
+                .iter()
+                .filter_map(|&raw| Some((raw, distance(raw)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(raw, _)| raw)
+        };
+        let (floor, ceil) = (exact.floor().clamp(0.0, 255.0) as u8, exact.ceil().clamp(0.0, 255.0) as u8);
+        // Where the formula rejects both (BAT_T is undefined at 0), the nearest count it accepts
+        nearest(&[floor, ceil]).or_else(|| nearest(&(0..=255).collect::<Vec<u8>>()))
+    }
+
+    /// The formula of `channel` with the byte written as `raw`, as in the telemetry definition
+    pub fn formula(&self, channel: Channel) -> String {
+        let linear = |c: &Linear| match c.offset {
+            offset if offset > 0.0 => format!("raw * {} + {}", c.scale, offset),
+            offset if offset < 0.0 => format!("raw * {} - {}", c.scale, -offset),
+            _ => format!("raw * {}", c.scale),
+        };
+        match channel {
+            Channel::BatV => linear(&self.bat_v),
+            Channel::BatI => linear(&self.bat_i),
+            Channel::BatT => {
+                let t = &self.bat_t;
+                format!(
+                    "{} / (ln(x / ({} - x)) * {} + {}) - {}, x = raw * {}",
+                    t.numerator, t.supply, t.ln_scale, t.ln_offset, t.kelvin, t.volts_per_count
+                )
+            }
+            Channel::BpbT => {
+                let b = &self.bpb_t;
+                format!("{} - (sqrt({} - raw * {}) - {}) / {}", b.reference, b.sqrt_offset, b.sqrt_scale, b.root_offset, b.divisor)
+            }
+            Channel::RawI => linear(&self.raw_i),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let table = CalibrationTable::default();
+        for channel in Channel::ALL {
+            for raw in 0..=255u8 {
+                if let Ok(value) = table.value(channel, raw) {
+                    assert_eq!(table.raw(channel, value), Some(raw), "{} {}", channel.name(), raw);
+                }
+            }
+        }
+        assert!(table.value(Channel::BatT, 0).is_err());
+    }
+
+    #[test]
+    fn test_inverse_rounding_and_clamping() {
+        let table = CalibrationTable::default();
+        // 3.6 V lies between 139 (3.584 V) and 140 (3.609 V)
+        assert_eq!(table.raw(Channel::BatV, 3.6), Some(140));
+        assert_eq!(table.raw(Channel::BatV, -1.0), Some(0));
+        assert_eq!(table.raw(Channel::BatV, 100.0), Some(255));
+        // Falling slope: high currents are low counts
+        assert_eq!(table.raw(Channel::BatI, 1e6), Some(0));
+        assert_eq!(table.raw(Channel::BatT, -273.0), Some(255));
+        assert_eq!(table.raw(Channel::BatT, -500.0), Some(255));
+        assert_eq!(table.raw(Channel::BatT, 1000.0), Some(1));
+        assert_eq!(table.raw(Channel::BpbT, f64::NAN), None);
+        assert_eq!("bpb_t".parse::<Channel>(), Ok(Channel::BpbT));
+    }
+
+    #[test]
+    fn test_table_from_json() {
+        let table: CalibrationTable =
+            serde_json::from_str(r#"{"bat_v": {"scale": 0.03}, "bpb_t": {"reference": 29.5}}"#).unwrap();
+        assert_eq!(table.bat_v, Linear { scale: 0.03, offset: 0.0 });
+        assert_eq!(table.bpb_t.sqrt_offset, 36.44506);
+        assert_eq!(table.bat_i, CalibrationTable::default().bat_i);
+        assert!((table.value(Channel::BatV, 100).unwrap() - 3.0).abs() < 1e-12);
+        assert_eq!(table.raw(Channel::BatV, 3.0), Some(100));
+        assert!(serde_json::from_str::<CalibrationTable>(r#"{"bat_x": {}}"#).is_err());
+        assert_eq!(table.formula(Channel::BpbT), "29.5 - (sqrt(36.44506 - raw * 0.06875) - 5.506) / 0.00352");
+        assert_eq!(CalibrationTable::default().formula(Channel::RawI), "raw * 51.84 - 1950.9");
+    }
+}
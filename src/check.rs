@@ -0,0 +1,304 @@
+// Validation of configuration files before they are used: morse mappings, telemetry calibration
+// tables, frame type definitions and limits. Loading only stops at what makes a file unusable;
+// `check` also reports what loads but decodes wrongly, such as a pattern mapped twice, a formula
+// that is undefined or not monotonic over the byte range, or fields that share bits.
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::calibration::{CalibrationTable, Channel};
+use crate::frames::FrameTypes;
+use crate::limits::Limits;
+use crate::mappings;
+
+/// What a configuration file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Mappings,
+    Calibration,
+    FrameTypes,
+    Limits,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mappings" => Ok(Kind::Mappings),
+            "calibration" => Ok(Kind::Calibration),
+            "frame-types" | "frames" => Ok(Kind::FrameTypes),
+            "limits" => Ok(Kind::Limits),
+            _ => Err(format!("Unknown kind '{}'. Expected mappings, calibration, frame-types or limits", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Mappings => "mappings",
+            Kind::Calibration => "calibration",
+            Kind::FrameTypes => "frame types",
+            Kind::Limits => "limits",
+        })
+    }
+}
+
+/// Problems found in one file; errors make it unusable or decode wrongly, warnings are suspicious
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Check the file at `path`, as `kind` or as what its content looks like
+pub fn check_file(path: &Path, kind: Option<Kind>) -> Result<(Kind, Report), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+    let kind = match kind {
+        Some(kind) => kind,
+        None => detect(path, &content)
+            .ok_or_else(|| format!("Cannot tell what '{}' holds; give --kind", path.display()))?,
+    };
+    let report = match kind {
+        Kind::Mappings => check_mappings(path, &content),
+        Kind::Calibration => check_calibration(&content),
+        Kind::FrameTypes => check_frame_types(&content),
+        Kind::Limits => check_limits(&content),
+    };
+    Ok((kind, report))
+}
+
+/// The kind of file `content` looks like: mappings unless it is a JSON object of other settings
+fn detect(path: &Path, content: &str) -> Option<Kind> {
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if !is_json {
+        return Some(Kind::Mappings);
+    }
+    let members = match serde_json::from_str::<Value>(content) {
+        Ok(Value::Object(members)) => members,
+        // Mappings as an array, or a broken file best reported by the mappings parser
+        _ => return Some(Kind::Mappings),
+    };
+    let has = |keys: &[&str]| members.values().any(|value| keys.iter().any(|key| value.get(key).is_some()));
+    if members.is_empty() || members.values().all(Value::is_string) {
+        Some(Kind::Mappings)
+    } else if has(&["length", "fields"]) {
+        Some(Kind::FrameTypes)
+    } else if !members.keys().all(|key| key.parse::<Channel>().is_ok()) {
+        None
+    } else if has(&["min", "max", "warn"]) {
+        Some(Kind::Limits)
+    } else {
+        Some(Kind::Calibration)
+    }
+}
+
+/// "line 12: " where the format has lines
+fn at(line: usize) -> String {
+    if line > 0 { format!("line {}: ", line) } else { String::new() }
+}
+
+fn check_mappings(path: &Path, content: &str) -> Report {
+    let mut report = Report::default();
+    let entries = match mappings::parse(path, content) {
+        Ok(entries) => entries,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+    if entries.is_empty() {
+        report.errors.push("no mappings".to_string());
+    }
+    let mut first: HashMap<&str, &mappings::Mapping> = HashMap::new();
+    for entry in &entries {
+        let Some(earlier) = first.get(entry.pattern.as_str()) else {
+            first.insert(&entry.pattern, entry);
+            continue;
+        };
+        let earlier_at = if earlier.line > 0 { format!(" on line {}", earlier.line) } else { String::new() };
+        if earlier.value == entry.value {
+            report.warnings.push(format!("{}'{}' = {} repeats the mapping{}", at(entry.line), entry.pattern, entry.value, earlier_at));
+        } else {
+            report.errors.push(format!(
+                "{}'{}' = {} conflicts with '{}' = {}{}; the later one wins",
+                at(entry.line),
+                entry.pattern,
+                entry.value,
+                earlier.pattern,
+                earlier.value,
+                earlier_at
+            ));
+        }
+    }
+    // The "pattern = value" format skips what it cannot read, which hides typos
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if !matches!(extension.as_deref(), Some("csv" | "json")) {
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') && !entries.iter().any(|entry| entry.line == number + 1) {
+                report.warnings.push(format!("line {}: '{}' is skipped, expected pattern = value", number + 1, line));
+            }
+        }
+    }
+    report
+}
+
+/// "0-3, 250-255"
+fn ranges(raws: &[u8]) -> String {
+    let mut ranges: Vec<(u8, u8)> = Vec::new();
+    for &raw in raws {
+        match ranges.last_mut() {
+            Some((_, end)) if *end as u16 + 1 == raw as u16 => *end = raw,
+            _ => ranges.push((raw, raw)),
+        }
+    }
+    let shown: Vec<String> =
+        ranges.iter().map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) }).collect();
+    shown.join(", ")
+}
+
+fn check_calibration(content: &str) -> Report {
+    let mut report = Report::default();
+    let table: CalibrationTable = match serde_json::from_str(content) {
+        Ok(table) => table,
+        Err(e) => {
+            report.errors.push(e.to_string());
+            return report;
+        }
+    };
+    for channel in Channel::ALL {
+        let name = channel.name();
+        let values: Vec<(u8, Result<f64, String>)> = (0..=255).map(|raw| (raw, table.value(channel, raw))).collect();
+        let undefined: Vec<u8> = values.iter().filter(|(_, value)| value.is_err()).map(|&(raw, _)| raw).collect();
+        // The thermistor formula has no value at 0 V, which the flight table accepts too
+        let expected: &[u8] = if channel == Channel::BatT { &[0] } else { &[] };
+        if undefined != expected {
+            report.warnings.push(format!("{}: formula is undefined for raw {}", name, ranges(&undefined)));
+        }
+        let defined: Vec<(u8, f64)> = values.iter().filter_map(|(raw, value)| Some((*raw, *value.as_ref().ok()?))).collect();
+        let not_finite: Vec<u8> = defined.iter().filter(|(_, value)| !value.is_finite()).map(|&(raw, _)| raw).collect();
+        if !not_finite.is_empty() {
+            report.errors.push(format!("{}: formula gives no finite value for raw {}", name, ranges(&not_finite)));
+            continue;
+        }
+        // Encoding searches for the nearest byte, which needs a formula that only rises or only falls
+        let rising = defined.windows(2).filter(|pair| pair[1].1 > pair[0].1).count();
+        let falling = defined.windows(2).filter(|pair| pair[1].1 < pair[0].1).count();
+        if defined.len() > 1 && rising == 0 && falling == 0 {
+            report.errors.push(format!("{}: formula gives {} for every raw byte", name, defined[0].1));
+        } else if rising > 0 && falling > 0 {
+            report.errors.push(format!("{}: formula both rises and falls over the raw range, so values are ambiguous", name));
+        }
+    }
+    report
+}
+
+fn check_frame_types(content: &str) -> Report {
+    let mut report = Report::default();
+    let types: FrameTypes = match serde_json::from_str(content) {
+        Ok(types) => types,
+        Err(e) => {
+            report.errors.push(e.to_string());
+            return report;
+        }
+    };
+    if let Err(e) = types.check() {
+        report.errors.push(e);
+    }
+    report.errors.extend(types.overlaps());
+    report
+}
+
+fn check_limits(content: &str) -> Report {
+    let mut report = Report::default();
+    let limits: Limits = match serde_json::from_str(content) {
+        Ok(limits) => limits,
+        Err(e) => {
+            report.errors.push(e.to_string());
+            return report;
+        }
+    };
+    let all = [("bat_v", limits.bat_v), ("bat_i", limits.bat_i), ("bat_t", limits.bat_t), ("bpb_t", limits.bpb_t), ("raw_i", limits.raw_i)];
+    for (name, limit) in all.into_iter().filter_map(|(name, limit)| Some((name, limit?))) {
+        match (limit.min, limit.max) {
+            (None, None) => report.warnings.push(format!("{}: neither min nor max is set, so it never alarms", name)),
+            (Some(min), Some(max)) if min > max => report.errors.push(format!("{}: min {} is above max {}", name, min, max)),
+            (Some(min), Some(max)) if min + limit.warn >= max - limit.warn => {
+                report.warnings.push(format!("{}: the warning margin of {} covers the whole range {} to {}", name, limit.warn, min, max))
+            }
+            _ => {}
+        }
+        if limit.warn < 0.0 {
+            report.errors.push(format!("{}: warn {} is negative", name, limit.warn));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let json = Path::new("x.json");
+        assert_eq!(detect(Path::new("x.txt"), "{}"), Some(Kind::Mappings));
+        assert_eq!(detect(json, r#"{".-": "A"}"#), Some(Kind::Mappings));
+        assert_eq!(detect(json, r#"[{"pattern": ".-", "value": "A"}]"#), Some(Kind::Mappings));
+        assert_eq!(detect(json, r#"{"bat_v": {"scale": 0.03}}"#), Some(Kind::Calibration));
+        assert_eq!(detect(json, r#"{"bat_v": {"min": 3.5}}"#), Some(Kind::Limits));
+        assert_eq!(detect(json, r#"{"mission": {"length": 12, "fields": []}}"#), Some(Kind::FrameTypes));
+        assert_eq!(detect(json, r#"{"rssi": {"slope": -0.5}}"#), None);
+        assert_eq!("frames".parse(), Ok(Kind::FrameTypes));
+    }
+
+    #[test]
+    fn test_check_mappings() {
+        let report = check_mappings(Path::new("map.txt"), "# table\n.- = A\n-... = B\n.- = A\n-... = V\n.-. R\n");
+        assert_eq!(report.errors, ["line 5: '-...' = V conflicts with '-...' = B on line 3; the later one wins"]);
+        assert_eq!(report.warnings, ["line 4: '.-' = A repeats the mapping on line 2", "line 6: '.-. R' is skipped, expected pattern = value"]);
+
+        let report = check_mappings(Path::new("map.json"), r#"{".-": "A", ".-": "B"}"#);
+        assert_eq!(report.errors, ["'.-' = B conflicts with '.-' = A; the later one wins"]);
+        assert_eq!(check_mappings(Path::new("map.csv"), ".-,A\n-...\n").errors, ["line 2: expected pattern,value, got 1 field(s)"]);
+        assert_eq!(check_mappings(Path::new("map.csv"), "pattern,value\n").errors, ["no mappings"]);
+
+        let saved = mappings::format_equals(crate::BotanDecoder::new().mappings()).unwrap();
+        let report = check_mappings(Path::new("map.txt"), &saved);
+        assert!(report.errors.is_empty() && report.warnings.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_check_calibration() {
+        let report = check_calibration(&serde_json::to_string(&CalibrationTable::default()).unwrap());
+        assert!(report.errors.is_empty() && report.warnings.is_empty(), "{:?}", report);
+
+        let report = check_calibration(r#"{"bat_v": {"scale": 0}, "bat_i": {"scale": 1e308}, "bpb_t": {"sqrt_offset": 10}, "bat_t": {"supply": 2.0}}"#);
+        assert_eq!(report.errors, ["BAT_V: formula gives 0 for every raw byte", "BAT_I: formula gives no finite value for raw 2-255"]);
+        assert_eq!(report.warnings, ["BAT_T: formula is undefined for raw 0, 156-255", "BPB_T: formula is undefined for raw 146-255"]);
+        assert!(check_calibration(r#"{"bat_v": {"scael": 0.03}}"#).errors[0].starts_with("unknown field `scael`"));
+    }
+
+    #[test]
+    fn test_check_frame_types() {
+        let report = check_frame_types(r#"{"a": {"length": 2, "fields": [{"name": "x", "field": "u8@0"}, {"name": "y", "field": "u4@4"}]}}"#);
+        assert_eq!(report.errors, ["a: fields 'x' and 'y' share bits 4-7"]);
+        let report = check_frame_types(r#"{"a": {"length": 2, "fields": [{"name": "x", "field": "u8@16"}]}}"#);
+        assert!(report.errors[0].starts_with("a.x: Field"));
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let report = check_limits(r#"{"bat_v": {"min": 4.2, "max": 3.5}, "bat_t": {"min": 0, "max": 10, "warn": 5}, "raw_i": {"warn": -1}}"#);
+        assert_eq!(report.errors, ["bat_v: min 4.2 is above max 3.5", "raw_i: warn -1 is negative"]);
+        assert_eq!(
+            report.warnings,
+            ["bat_t: the warning margin of 5 covers the whole range 0 to 10", "raw_i: neither min nor max is set, so it never alarms"]
+        );
+    }
+}
@@ -0,0 +1,1042 @@
+use clap::{ArgAction, Args, Parser, Subcommand};
+
+/// BOTAN satellite CW beacon decoder
+#[derive(Debug, Parser)]
+#[command(name = "botan_cw_decoder", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// JSON file converting the SIxxxx signal report bytes to dBm and dB
+    #[arg(long, global = true)]
+    pub signal_calibration: Option<String>,
+
+    /// JSON file replacing the telemetry conversion coefficients of the definition
+    #[arg(long, global = true)]
+    pub telemetry_calibration: Option<String>,
+
+    /// JSON file with labels for coded fields such as the current mission
+    #[arg(long, global = true)]
+    pub code_labels: Option<String>,
+
+    /// Morse table for non-BOTAN input in interactive mode, replacing the built-in one:
+    /// "pattern = value" lines, or CSV or JSON by extension
+    #[arg(long, global = true, value_name = "FILE")]
+    pub mappings: Option<String>,
+
+    /// JSON file describing frame types other than the 8-byte housekeeping frame
+    #[arg(long, global = true)]
+    pub frame_types: Option<String>,
+
+    /// Config file with option defaults [default: ~/.config/botan_cw_decoder/config.toml]
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Print beacons through a template file with {{field}} placeholders, e.g. {{telemetry.bat_v:.2}}
+    #[arg(long, global = true, value_name = "FILE")]
+    pub template: Option<String>,
+
+    /// Inline template, e.g. "{{time}} {{telemetry.bat_v:.2}} V"
+    #[arg(long, global = true, value_name = "TEMPLATE", conflicts_with = "template")]
+    pub template_string: Option<String>,
+
+    /// Print each frame as one line of telemetry JSON in the layout of gr-satellites
+    #[arg(long, global = true, conflicts_with_all = ["template", "template_string"])]
+    pub grsat_json: bool,
+
+    /// Output format: text (the report, a template or --grsat-json), xml (one element per frame)
+    /// or proto (length-delimited protobuf messages, see `schema --proto`). Goes before the
+    /// subcommand, since `decode-iq` and `export` have a --format of their own
+    #[arg(long, default_value = "text")]
+    pub format: crate::output::OutputFormat,
+
+    /// Language of the beacon report: en or ja
+    #[arg(long, global = true, default_value = "en")]
+    pub lang: crate::i18n::Lang,
+
+    /// Temperature unit: c or f
+    #[arg(long, global = true, default_value = "c")]
+    pub temperature_unit: crate::units::TemperatureUnit,
+
+    /// Current unit: ma or a
+    #[arg(long, global = true, default_value = "ma")]
+    pub current_unit: crate::units::CurrentUnit,
+
+    /// Voltage unit: v or mv
+    #[arg(long, global = true, default_value = "v")]
+    pub voltage_unit: crate::units::VoltageUnit,
+
+    /// Time zone to show times in: UTC, local, an offset such as +09:00, JST or a name such as
+    /// Asia/Tokyo. Saved history, JSON output and logs written for other programs stay in UTC
+    #[arg(long, global = true, value_name = "ZONE", default_value = "UTC")]
+    pub timezone: crate::timeutil::Zone,
+
+    /// Show every time in UTC, whatever --timezone or the config file says
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Color the beacon report: auto, always or never
+    #[arg(long, global = true, default_value = "auto")]
+    pub color: crate::style::ColorChoice,
+
+    /// JSON file with telemetry limits; values near or beyond them are highlighted
+    #[arg(long, global = true)]
+    pub limits: Option<String>,
+
+    /// Read the letters O, I and L in hex fields as 0 and 1, noting each correction before the frame
+    #[arg(long, global = true)]
+    pub autocorrect: bool,
+
+    /// Reject frames with any warning: values outside --limits, a failed integrity check, tokens
+    /// after the data block or --autocorrect corrections. Rejected frames are not forwarded
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Add a byte-by-byte breakdown (hex, raw value, formula, bit fields) to the report
+    #[arg(long, global = true)]
+    pub verbose_bytes: bool,
+
+    /// Add the 64 frame bits with the field boundaries marked to the report
+    #[arg(long, global = true)]
+    pub bit_view: bool,
+
+    /// POST every decoded frame as JSON to this URL; repeat for several
+    #[arg(long, global = true, value_name = "URL")]
+    pub webhook: Vec<String>,
+
+    /// Post a summary of each decoded frame to this Discord webhook
+    #[arg(long, global = true, value_name = "URL")]
+    pub discord_webhook: Option<String>,
+
+    /// Send a summary of each decoded frame through this Telegram bot
+    #[arg(long, global = true, value_name = "TOKEN", requires = "telegram_chat")]
+    pub telegram_bot: Option<String>,
+
+    /// Telegram chat the bot posts to
+    #[arg(long, global = true, value_name = "CHAT_ID", requires = "telegram_bot")]
+    pub telegram_chat: Option<String>,
+
+    /// Pop up a desktop notification for each decoded frame (notify-send, or osascript on macOS)
+    #[arg(long, global = true)]
+    pub desktop_notify: bool,
+
+    /// Play a short CW tone for each decoded frame, and a different one for alarms
+    #[arg(long, global = true)]
+    pub beep: bool,
+
+    /// Sound file to play for each decoded frame instead of the built-in tone (implies --beep)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub decode_sound: Option<String>,
+
+    /// Sound file to play for frames with a value outside --limits (implies --beep)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub alarm_sound: Option<String>,
+
+    /// Which frames Discord, Telegram and the desktop hear about: all, or alarms (values outside --limits)
+    #[arg(long, global = true, default_value = "all")]
+    pub notify_on: crate::notify::NotifyOn,
+
+    /// Where frames wait until the network services accept them [default: ~/.local/share/botan_cw_decoder/spool.jsonl]
+    #[arg(long, global = true, value_name = "FILE")]
+    pub spool: Option<String>,
+
+    /// Append every decoded frame to a Redis stream on this server, e.g. redis://localhost:6379/0
+    #[arg(long, global = true, value_name = "URL")]
+    pub redis: Option<crate::redis::Endpoint>,
+
+    /// Redis stream the frames are added to
+    #[arg(long, global = true, value_name = "KEY", default_value = "botan:frames")]
+    pub redis_stream: String,
+
+    /// Trim the Redis stream to about N entries as frames are added
+    #[arg(long, global = true, value_name = "N")]
+    pub redis_maxlen: Option<u64>,
+
+    /// Send requests to the HTTP services described in this JSON file, with their own method, headers and body template
+    #[arg(long, global = true, value_name = "FILE")]
+    pub http_sinks: Option<String>,
+
+    /// Report BOTAN as heard to the AMSAT live satellite status page under this call sign, once a quarter hour
+    #[arg(long, global = true, value_name = "CALL", requires = "amsat_grid")]
+    pub amsat_call: Option<String>,
+
+    /// Grid square the AMSAT status reports are made from, e.g. PM95vq
+    #[arg(long, global = true, value_name = "GRID", requires = "amsat_call")]
+    pub amsat_grid: Option<crate::adif::Grid>,
+
+    /// The satellite's name on the AMSAT status page
+    #[arg(long, global = true, value_name = "NAME", default_value = "BOTAN")]
+    pub amsat_satellite: String,
+
+    /// Send at most N requests a minute to each network service; the rest wait in the spool
+    #[arg(long, global = true, value_name = "N")]
+    pub forward_rate: Option<f64>,
+
+    /// Post at most N messages a minute to --discord-webhook, instead of --forward-rate
+    #[arg(long, global = true, value_name = "N", requires = "discord_webhook")]
+    pub discord_rate: Option<f64>,
+
+    /// Send at most N messages a minute through --telegram-bot, instead of --forward-rate
+    #[arg(long, global = true, value_name = "N", requires = "telegram_bot")]
+    pub telegram_rate: Option<f64>,
+
+    /// Add at most N frames a minute to the --redis stream, instead of --forward-rate
+    #[arg(long, global = true, value_name = "N", requires = "redis")]
+    pub redis_rate: Option<f64>,
+
+    /// Post up to N spooled frames in one --webhook request, as a JSON array
+    #[arg(long, global = true, value_name = "N", default_value_t = 1)]
+    pub webhook_batch: usize,
+
+    /// Serve the gRPC service of proto/botan_service.proto on this address, e.g. 127.0.0.1:50051
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub grpc: Option<String>,
+
+    /// Publish every decoded frame on a ZeroMQ PUB socket bound here, e.g. tcp://*:5556, with the satellite name as topic
+    #[cfg(feature = "zmq")]
+    #[arg(long, global = true, value_name = "ENDPOINT")]
+    pub zmq_pub: Option<String>,
+
+    /// Serve a live web dashboard on this address, e.g. 127.0.0.1:8080: the latest telemetry, flag
+    /// states and recent frames, updated as frames are decoded
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub dashboard: Option<String>,
+
+    /// Pass history the gRPC QueryHistory call reads
+    #[arg(long, global = true, value_name = "DIR", default_value = "passes")]
+    pub grpc_history: String,
+
+    /// Also append every decoded frame, as printed, to this file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub output_file: Option<String>,
+
+    /// Start a new output file and audit log: never, daily, or at a size such as 10M
+    #[arg(long, global = true, default_value = "never")]
+    pub rotate: crate::rotate::Rotation,
+
+    /// Rotated output files to keep; older ones are deleted (rotated audit logs are kept)
+    #[arg(long, global = true, default_value_t = 30)]
+    pub keep: usize,
+
+    /// Append every beacon line received, with its outcome, to this NDJSON audit log
+    #[arg(long, global = true, value_name = "FILE")]
+    pub audit_log: Option<String>,
+
+    /// Run as a service: stop cleanly on SIGTERM or SIGINT and log health regularly
+    #[arg(long, global = true)]
+    pub daemon: bool,
+
+    /// Write the process ID to this file while running, refusing to start if another instance holds it
+    #[arg(long, global = true, value_name = "FILE")]
+    pub pid_file: Option<String>,
+
+    /// Seconds between health log lines with --daemon
+    #[arg(long, global = true, default_value_t = 600)]
+    pub health_interval: u64,
+
+    /// Where the live receivers keep decode statistics [default: ~/.local/share/botan_cw_decoder/stats.json]
+    #[arg(long, global = true, value_name = "FILE")]
+    pub stats_file: Option<String>,
+
+    /// More diagnostics on stderr (-v debug, -vv trace); BOTAN_LOG sets levels per module
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Fewer diagnostics on stderr (-q warnings only, -qq errors only)
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "verbose")]
+    pub quiet: u8,
+
+    /// Log level instead of -v/-q: error, warn, info, debug or trace
+    #[arg(long, global = true)]
+    pub log_level: Option<crate::logging::Level>,
+
+    /// Where diagnostics go: stderr, syslog or journald
+    #[arg(long, global = true, default_value = "stderr")]
+    pub log_target: crate::logging::Target,
+
+    /// Syslog facility for syslog and journald: user, daemon or local0 to local7
+    #[arg(long, global = true, default_value = "daemon")]
+    pub syslog_facility: crate::logging::Facility,
+}
+
+/// Value of --config, found before the full parse so the config can shape it
+pub fn config_path_arg(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return iter.next().cloned();
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// A byte given as 0-255 or 0x00-0xFF
+fn parse_byte(s: &str) -> Result<u8, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("Invalid byte '{}'. Expected 0-255 or 0x00-0xFF", s))
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Synthesize a keyed CW sidetone WAV from a beacon frame
+    Synth(SynthArgs),
+    /// Demodulate and decode CW beacons from a raw IQ recording
+    DecodeIq(DecodeIqArgs),
+    /// Decode every beacon line in text archives, streaming files of any size
+    DecodeFile(DecodeFileArgs),
+    /// Decode beacons from a receiver's UDP audio stream (GQRX "UDP" output)
+    UdpAudio(UdpAudioArgs),
+    /// Decode beacons from AX.25 UI frames in a KISS stream, from a file or a TNC
+    DecodeKiss(DecodeKissArgs),
+    /// Decode raw binary frames, fixed-size or length-prefixed records, as some demodulators write them
+    DecodeBin(DecodeBinArgs),
+    /// Decode the frames in Direwolf monitor logs, with the times Direwolf stamped them
+    DecodeDirewolf(DecodeDirewolfArgs),
+    /// Decode beacon text or KISS frames from a serial port, e.g. a hardware TNC or a CW decoder
+    Serial(SerialArgs),
+    /// Follow the spots of the Reverse Beacon Network or a CW Skimmer server and decode beacons posted in them
+    Skimmer(SkimmerArgs),
+    /// Demodulate and decode AX.25 packets from a WAV recording of the 1200 baud AFSK downlink
+    DecodeAfsk(DecodeAfskArgs),
+    /// List upcoming BOTAN passes over the ground station
+    Passes(PassesArgs),
+    /// Run unattended: receive UDP audio during every pass and file the results per pass
+    Schedule(ScheduleArgs),
+    /// Download the latest BOTAN TLE into the local cache
+    UpdateTle(UpdateTleArgs),
+    /// Show how many frames the live receivers attempted, decoded and failed
+    Stats(StatsArgs),
+    /// Write the beacons saved by `schedule` as a table, one row per frame
+    Export(ExportArgs),
+    /// Write the receptions saved by `schedule` as an ADIF log for amateur radio logging programs
+    Adif(AdifArgs),
+    /// Downsample the saved history to statistics per time interval and channel
+    Aggregate(AggregateArgs),
+    /// Track battery voltage, current and resistance over the saved history to spot aging
+    Battery(BatteryArgs),
+    /// Summarise the saved history: coverage, channel ranges, flag changes and notable events
+    Report(ReportArgs),
+    /// Decode the frames of SatNOGS DB exports (CSV or JSON) and file them as passes
+    Import(ImportArgs),
+    /// Combine the histories and logs of several receive stations into one timeline of passes
+    Merge(MergeArgs),
+    /// Rewrite passes saved by an older version in the current schema
+    Migrate(MigrateArgs),
+    /// Print a recorded pass again through the normal output, optionally at its original pace
+    Replay(ReplayArgs),
+    /// Generate realistic synthetic beacons to exercise dashboards, alarms and forwarders
+    Simulate(SimulateArgs),
+    /// Work with the morse table used for non-BOTAN input (see --mappings)
+    Mappings(MappingsArgs),
+    /// Practice copying morse by ear: random characters, call signs or beacons, scored against what was sent
+    Practice(PracticeArgs),
+    /// Explain a beacon step by step: header, signal report, every byte and what it says about the satellite
+    Explain(ExplainArgs),
+    /// Validate mapping, calibration, frame type and limit files before using them
+    Check(CheckArgs),
+    /// Print the JSON Schema of a decoded frame, as in templates, pass files and :save output
+    Schema(SchemaArgs),
+    /// Convert an engineering value to the nearest raw telemetry byte, or a byte to its value
+    Convert(ConvertArgs),
+    /// Read arbitrary fields (wider, signed or unaligned) from a raw frame
+    Extract(ExtractArgs),
+    /// Receive and decode beacons live from an RTL-SDR dongle
+    #[cfg(feature = "rtlsdr")]
+    Rtlsdr(RtlsdrArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SynthArgs {
+    /// 16-hex-digit frame or a full beacon line ("BOTAN JS1YPT [SIxxxx] <hex>"); optional when
+    /// every channel value is given
+    pub frame: Option<String>,
+
+    /// Battery voltage [V], encoded with the telemetry calibration
+    #[arg(long, value_name = "V", allow_negative_numbers = true)]
+    pub bat_v: Option<f64>,
+
+    /// Battery current [mA], negative while discharging
+    #[arg(long, value_name = "MA", allow_negative_numbers = true)]
+    pub bat_i: Option<f64>,
+
+    /// Battery temperature [°C]
+    #[arg(long, value_name = "C", allow_negative_numbers = true)]
+    pub bat_t: Option<f64>,
+
+    /// Circuit board temperature [°C]
+    #[arg(long, value_name = "C", allow_negative_numbers = true)]
+    pub bpb_t: Option<f64>,
+
+    /// Current consumption [mA]
+    #[arg(long, value_name = "MA", allow_negative_numbers = true)]
+    pub raw_i: Option<f64>,
+
+    /// DATA1 status byte (power lines and sunlit panels), e.g. 0xA4; 0 without a frame
+    #[arg(long, value_name = "BYTE", value_parser = parse_byte)]
+    pub data1: Option<u8>,
+
+    /// DATA2 status byte (command counters and KILL switch)
+    #[arg(long, value_name = "BYTE", value_parser = parse_byte)]
+    pub data2: Option<u8>,
+
+    /// DATA3 status byte (mission flags)
+    #[arg(long, value_name = "BYTE", value_parser = parse_byte)]
+    pub data3: Option<u8>,
+
+    /// Output WAV file
+    #[arg(short, long, default_value = "beacon.wav")]
+    pub output: String,
+
+    /// Keying speed in words per minute
+    #[arg(long, default_value_t = 20.0)]
+    pub wpm: f64,
+
+    /// Sidetone pitch in Hz
+    #[arg(long, default_value_t = 700.0)]
+    pub pitch: f64,
+
+    /// Output sample rate in Hz
+    #[arg(long, default_value_t = 8000)]
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeIqArgs {
+    /// Raw IQ recording
+    pub file: String,
+
+    /// Sample encoding: cu8, cs8, cs16 or cf32
+    #[arg(short, long, default_value = "cs16")]
+    pub format: crate::iq::IqFormat,
+
+    /// Recording sample rate in Hz
+    #[arg(short, long)]
+    pub sample_rate: f64,
+
+    /// Centre frequency of the recording in Hz
+    #[arg(short, long)]
+    pub center_freq: f64,
+
+    /// Beacon carrier frequency in Hz (defaults to the centre frequency)
+    #[arg(short, long)]
+    pub beacon_freq: Option<f64>,
+
+    /// UTC time of the first sample, needed for Doppler correction
+    #[arg(long)]
+    pub start_time: Option<String>,
+
+    /// Detection bandwidth in Hz
+    #[arg(long, default_value_t = 200.0)]
+    pub bandwidth: f64,
+
+    /// Expected keying speed; the demodulator adapts from this starting point
+    #[arg(long, default_value_t = 20.0)]
+    pub wpm: f64,
+
+    #[command(flatten)]
+    pub station: StationArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeFileArgs {
+    /// Archive files with one beacon per line; text around the beacon is ignored
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// UTC date of lines stamped with only a time of day, until a line gives the date
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct UdpAudioArgs {
+    /// Address to listen on
+    #[arg(short, long, default_value = "0.0.0.0:7355")]
+    pub bind: String,
+
+    /// Audio sample rate in Hz
+    #[arg(short, long, default_value_t = 48_000.0)]
+    pub sample_rate: f64,
+
+    /// CW tone frequency in the audio in Hz
+    #[arg(short, long, default_value_t = 700.0)]
+    pub pitch: f64,
+
+    /// Beacon carrier frequency in Hz, needed for Doppler correction (read from the rig with --rigctld)
+    #[arg(long)]
+    pub freq: Option<f64>,
+
+    /// rigctld address (e.g. localhost:4532); the radio is set to CW and retuned for Doppler
+    #[arg(long)]
+    pub rigctld: Option<String>,
+
+    /// Detection bandwidth in Hz
+    #[arg(long, default_value_t = 200.0)]
+    pub bandwidth: f64,
+
+    /// Expected keying speed; the demodulator adapts from this starting point
+    #[arg(long, default_value_t = 20.0)]
+    pub wpm: f64,
+
+    #[command(flatten)]
+    pub station: StationArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeKissArgs {
+    /// KISS capture files or directories of them, or "-" for standard input
+    #[arg(required_unless_present = "tcp")]
+    pub files: Vec<String>,
+
+    /// Connect to a KISS TCP server instead, e.g. Direwolf at localhost:8001
+    #[arg(long, conflicts_with = "files")]
+    pub tcp: Option<String>,
+
+    /// Decode only UI frames from this source call sign, e.g. JS1YPT, skipping other stations
+    #[arg(long, value_name = "CALL")]
+    pub source: Option<String>,
+
+    /// Frames end in the 2-byte FCS, which is checked and removed
+    #[arg(long)]
+    pub fcs: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeBinArgs {
+    /// File of binary frames, or "-" for standard input
+    pub file: String,
+
+    /// How records are delimited: fixed (--record-size bytes each), or a length before each
+    /// record as u8, u16be, u16le, u32be or u32le
+    #[arg(long, default_value = "fixed")]
+    pub framing: crate::binary::Framing,
+
+    /// Bytes per record with --framing fixed
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub record_size: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeDirewolfArgs {
+    /// Direwolf monitor logs or directories of them
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// Decode only frames from this source call sign; frames of other stations are skipped
+    #[arg(long, value_name = "CALL", default_value = "JS1YPT")]
+    pub source: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SerialArgs {
+    /// Serial device, e.g. /dev/ttyUSB0
+    pub device: String,
+
+    /// Baud rate of the port
+    #[arg(long, default_value_t = 9600)]
+    pub baud: u32,
+
+    /// The port carries KISS frames from a TNC instead of text
+    #[arg(long)]
+    pub kiss: bool,
+
+    /// The port carries whole beacon lines, e.g. from a logging program, instead of copied words
+    #[arg(long, conflicts_with = "kiss")]
+    pub lines: bool,
+
+    /// KISS frames end in the 2-byte FCS, which is checked and removed
+    #[arg(long, requires = "kiss")]
+    pub fcs: bool,
+
+    /// Decode only UI frames from this source call sign
+    #[arg(long, value_name = "CALL", requires = "kiss")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SkimmerArgs {
+    /// Telnet spot server: the Reverse Beacon Network, a DX cluster or a local CW Skimmer Server
+    #[arg(long, default_value = "telnet.reversebeacon.net:7000")]
+    pub server: String,
+
+    /// Your call sign, sent when the server asks for it
+    #[arg(long, value_name = "CALL")]
+    pub login: String,
+
+    /// Report and decode only the spots of this call sign
+    #[arg(long, value_name = "CALL", default_value = "JS1YPT")]
+    pub call: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeAfskArgs {
+    /// 16-bit PCM WAV recording of the receiver audio (FM demodulated)
+    pub file: String,
+}
+
+#[derive(Debug, Args)]
+pub struct PassesArgs {
+    #[command(flatten)]
+    pub station: StationArgs,
+
+    /// Start of the prediction window in UTC (defaults to now)
+    #[arg(long)]
+    pub start: Option<String>,
+
+    /// Length of the prediction window in hours
+    #[arg(long, default_value_t = 24.0)]
+    pub hours: f64,
+
+    /// Only list passes peaking at or above this elevation in degrees
+    #[arg(long, default_value_t = 0.0)]
+    pub min_elevation: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct ScheduleArgs {
+    #[command(flatten)]
+    pub receiver: UdpAudioArgs,
+
+    /// Only receive passes peaking at or above this elevation in degrees
+    #[arg(long, default_value_t = 0.0)]
+    pub min_elevation: f64,
+
+    /// Directory the per-pass results are written to
+    #[arg(short, long, default_value = "passes")]
+    pub output_dir: String,
+
+    /// Also save the received audio of each pass as audio.wav
+    #[arg(long)]
+    pub record: bool,
+
+    /// Program to run after each pass, given the pass directory as its argument
+    #[arg(long)]
+    pub on_pass: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateTleArgs {
+    /// Where to download the element set from
+    #[arg(long, default_value = crate::tle_cache::CELESTRAK_BOTAN_URL)]
+    pub tle_url: String,
+
+    /// Download even if the cached copy is less than a day old
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Number of most recent passes to list
+    #[arg(long, default_value_t = 10)]
+    pub passes: usize,
+
+    /// Print the statistics file as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Start counting from zero
+    #[arg(long)]
+    pub reset: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Directory `schedule` wrote the passes to
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only frames received at or after this UTC time, e.g. 2024-05-01 or 2024-05-01T12:00:00Z
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only frames received before this UTC time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Table format: csv, tsv or parquet
+    #[arg(long, default_value = "csv")]
+    pub format: crate::export::ExportFormat,
+
+    /// Output file (defaults to standard output)
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AdifArgs {
+    /// Directory `schedule` wrote the passes to
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only frames received at or after this UTC time
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only frames received before this UTC time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// One record per pass or per frame
+    #[arg(long, default_value = "pass")]
+    pub per: crate::adif::Records,
+
+    /// Your call sign, logged as the receiving station
+    #[arg(long)]
+    pub call: Option<String>,
+
+    /// Your Maidenhead grid square, e.g. PM95vq
+    #[arg(long, conflicts_with_all = ["lat", "lon"])]
+    pub grid: Option<crate::adif::Grid>,
+
+    /// Observer latitude in degrees, to work out the grid square
+    #[arg(long, allow_negative_numbers = true, requires = "lon")]
+    pub lat: Option<f64>,
+
+    /// Observer longitude in degrees
+    #[arg(long, allow_negative_numbers = true, requires = "lat")]
+    pub lon: Option<f64>,
+
+    /// Output file (defaults to standard output)
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AggregateArgs {
+    /// Directory `schedule` wrote the passes to
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only frames received at or after this UTC time
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only frames received before this UTC time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Interval length, e.g. 15m, 1h or 1d
+    #[arg(long, default_value = "1h")]
+    pub interval: crate::aggregate::Interval,
+
+    /// Statistics per channel: count, mean, min, max, median, std, first or last
+    #[arg(long, value_delimiter = ',', default_value = "mean,min,max")]
+    pub stat: Vec<crate::aggregate::Stat>,
+
+    /// Only these channels, e.g. bat_v,bat_t (defaults to every numeric channel)
+    #[arg(long, value_delimiter = ',')]
+    pub channel: Vec<String>,
+
+    /// Output file (defaults to standard output)
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct BatteryArgs {
+    /// Directory `schedule` wrote the passes to
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only frames received at or after this UTC time
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only frames received before this UTC time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Window length, e.g. 1d or 1w
+    #[arg(long, default_value = "1w")]
+    pub window: crate::aggregate::Interval,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Directory `schedule` wrote the passes to
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only frames received at or after this UTC time, e.g. 2024-05-01
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only frames received before this UTC time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Report on a single pass, by its directory name (e.g. 20240501T123456Z) or its AOS
+    #[arg(long, value_name = "ID", conflicts_with_all = ["from", "to"])]
+    pub pass: Option<String>,
+
+    /// Write the report as JSON instead of Markdown
+    #[arg(long)]
+    pub json: bool,
+
+    /// Write the report as a standalone HTML page with charts to this file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["json", "output"])]
+    pub html: Option<String>,
+
+    /// Output file (defaults to standard output)
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// SatNOGS DB exports: "timestamp|frame" CSV or telemetry API JSON
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// Directory the passes are filed under, as by `schedule`
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// Stations as NAME=PATH, or a path named after itself: a history directory, a pass.json,
+    /// an --audit-log file or --grsat-json lines
+    #[arg(required = true)]
+    pub sources: Vec<crate::merge::Source>,
+
+    /// Directory the merged passes are filed under, as by `schedule`
+    #[arg(short, long, default_value = "merged")]
+    pub dir: String,
+
+    /// Seconds within which copies of a frame from different stations are one reception (0 keeps every copy)
+    #[arg(long, default_value_t = 10.0)]
+    pub window: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Directory of the saved passes, as written by `schedule`, `import` or `merge`
+    #[arg(short, long, default_value = "passes")]
+    pub dir: String,
+
+    /// Only list the passes that need upgrading
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// A pass.json saved by `schedule`, an --audit-log file, --grsat-json lines or plain beacon lines
+    pub file: String,
+
+    /// Wait between beacons as long as between their original receptions
+    #[arg(long)]
+    pub realtime: bool,
+
+    /// Play the original timing this many times faster (implies --realtime)
+    #[arg(long)]
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Seconds between beacons
+    #[arg(short, long, default_value_t = 30.0)]
+    pub interval: f64,
+
+    /// Stop after this many beacons
+    #[arg(short = 'n', long)]
+    pub count: Option<u64>,
+
+    /// Run the simulated clock this many times faster than real time
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f64,
+
+    /// Print every beacon at once instead of pacing them
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Probability of each bit of a frame being flipped, e.g. 0.001
+    #[arg(long, default_value_t = 0.0)]
+    pub bit_error_rate: f64,
+
+    /// Seed for reproducible traffic (random by default)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct MappingsArgs {
+    #[command(subcommand)]
+    pub action: MappingsAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MappingsAction {
+    /// Save the active table: "pattern = value" lines, or CSV or JSON by extension
+    Export {
+        /// File to write
+        file: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PracticeArgs {
+    /// What to send: characters, callsigns or beacons
+    #[arg(short, long, default_value = "characters")]
+    pub drill: crate::practice::Drill,
+
+    /// Number of rounds
+    #[arg(short = 'n', long, default_value_t = 10)]
+    pub count: usize,
+
+    /// Characters per group in the characters drill
+    #[arg(long, default_value_t = 5)]
+    pub length: usize,
+
+    /// Keying speed in words per minute
+    #[arg(long, default_value_t = 20.0)]
+    pub wpm: f64,
+
+    /// Sidetone pitch in Hz
+    #[arg(long, default_value_t = 700.0)]
+    pub pitch: f64,
+
+    /// Print the dots and dashes instead of playing them
+    #[arg(long)]
+    pub print: bool,
+
+    /// Seed for a reproducible drill (random by default)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExplainArgs {
+    /// Beacon line ("BOTAN JS1YPT [SIxxxx] <hex>") or just its 16 hex digits
+    #[arg(required = true, num_args = 1..)]
+    pub beacon: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Files to check
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// What the files hold: mappings, calibration, frame-types or limits (guessed by default)
+    #[arg(long)]
+    pub kind: Option<crate::check::Kind>,
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Print the protobuf message definitions of --format proto instead
+    #[arg(long)]
+    pub proto: bool,
+
+    /// Print the gRPC service definition of --grpc instead
+    #[arg(long, conflicts_with = "proto")]
+    pub grpc: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Telemetry channel: bat_v, bat_i, bat_t, bpb_t or raw_i
+    pub channel: crate::calibration::Channel,
+
+    /// Value in the channel's unit (V, mA or °C), or with --raw a byte such as 140 or 0x8C
+    #[arg(allow_negative_numbers = true)]
+    pub value: String,
+
+    /// Convert a raw byte to its engineering value instead
+    #[arg(long)]
+    pub raw: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    /// Frame in hex, of any length, or a beacon line ending in one
+    pub frame: String,
+
+    /// Fields as sign, width, byte order and bit offset, e.g. u8@0, s16le@8 or u3@49
+    #[arg(required = true)]
+    pub fields: Vec<crate::field::Field>,
+}
+
+#[cfg(feature = "rtlsdr")]
+#[derive(Debug, Args)]
+pub struct RtlsdrArgs {
+    /// Beacon carrier frequency in Hz
+    #[arg(short, long)]
+    pub freq: f64,
+
+    /// Device index
+    #[arg(short, long, default_value_t = 0)]
+    pub device: u32,
+
+    /// Tuner gain in dB (automatic when omitted)
+    #[arg(short, long)]
+    pub gain: Option<f64>,
+
+    /// Crystal frequency correction in ppm
+    #[arg(short, long, default_value_t = 0)]
+    pub ppm: i32,
+
+    /// Sample rate in Hz
+    #[arg(short, long, default_value_t = 1_024_000)]
+    pub sample_rate: u32,
+
+    /// Tune this far below the beacon to keep it clear of the DC spike, in Hz
+    #[arg(long, default_value_t = 25_000.0)]
+    pub tune_offset: f64,
+
+    /// Detection bandwidth in Hz
+    #[arg(long, default_value_t = 200.0)]
+    pub bandwidth: f64,
+
+    /// Expected keying speed; the demodulator adapts from this starting point
+    #[arg(long, default_value_t = 20.0)]
+    pub wpm: f64,
+
+    #[command(flatten)]
+    pub station: StationArgs,
+}
+
+/// Orbit and ground station location, used for Doppler correction and pass prediction
+#[derive(Debug, Args)]
+pub struct StationArgs {
+    /// TLE file for BOTAN; enables Doppler tracking of the beacon
+    #[arg(long)]
+    pub tle: Option<String>,
+
+    /// Use the cached TLE from --tle-url instead of a file, downloading it when over a day old
+    #[arg(long, conflicts_with = "tle")]
+    pub auto_tle: bool,
+
+    /// Where --auto-tle downloads the element set from
+    #[arg(long, default_value = crate::tle_cache::CELESTRAK_BOTAN_URL)]
+    pub tle_url: String,
+
+    /// Observer latitude in degrees (north positive)
+    #[arg(long, allow_negative_numbers = true)]
+    pub lat: Option<f64>,
+
+    /// Observer longitude in degrees (east positive)
+    #[arg(long, allow_negative_numbers = true)]
+    pub lon: Option<f64>,
+
+    /// Observer altitude in metres
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub alt: f64,
+}
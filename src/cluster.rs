@@ -0,0 +1,127 @@
+// The telnet spot feed of DX clusters, the Reverse Beacon Network and CW Skimmer Server. After
+// a login prompt the server sends one spot per line:
+//
+//   DX de KM3T-#:     7018.3  JS1YPT         CW    17 dB  22 WPM  CQ      1213Z
+//
+// Skimmers report the signal (mode, SNR, speed) and what kind of transmission it was; spots
+// posted by people carry free text instead, which may be a copy of the beacon.
+
+/// One spot from the feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    pub spotter: String,    // Without the "-#" skimmer suffix
+    pub freq_khz: f64,
+    pub call: String,
+    pub snr_db: Option<i32>,
+    pub wpm: Option<u32>,
+    pub text: String,       // The comment: "CQ", "BEACON" or free text
+}
+
+/// Parse a spot line; anything else the server sends gives None
+pub fn parse_spot(line: &str) -> Option<Spot> {
+    let rest = line.trim().strip_prefix("DX de ")?;
+    let (spotter, rest) = rest.split_once(':')?;
+    let mut words: Vec<&str> = rest.split_whitespace().collect();
+    if words.len() < 2 {
+        return None;
+    }
+    let freq_khz = words[0].parse().ok()?;
+    let call = words[1].to_ascii_uppercase();
+    let mut words = words.split_off(2);
+    // The time in UTC hours and minutes closes the line
+    if words.last().is_some_and(|w| w.len() == 5 && w.ends_with('Z') && w[..4].bytes().all(|b| b.is_ascii_digit())) {
+        words.pop();
+    }
+
+    let mut snr_db = None;
+    let mut wpm = None;
+    if words.first() == Some(&"CW") {
+        let mut fields = 1;
+        if let Some(snr) = words.get(1).filter(|_| words.get(2) == Some(&"dB")) {
+            snr_db = snr.parse().ok();
+            fields = 3;
+        }
+        if let Some(speed) = words.get(fields).filter(|_| words.get(fields + 1) == Some(&"WPM")) {
+            wpm = speed.parse().ok();
+            fields += 2;
+        }
+        if snr_db.is_some() || wpm.is_some() {
+            words.drain(..fields);
+        }
+    }
+    Some(Spot {
+        spotter: spotter.trim_end_matches("-#").to_ascii_uppercase(),
+        freq_khz,
+        call,
+        snr_db,
+        wpm,
+        text: words.join(" "),
+    })
+}
+
+/// Splits the received bytes into lines, keeping a prompt that has no line end yet
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    pending: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Add received bytes, returning the lines they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let Some(end) = self.pending.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let lines: Vec<u8> = self.pending.drain(..=end).collect();
+        String::from_utf8_lossy(&lines).lines().map(|line| line.trim_end_matches('\r').to_string()).collect()
+    }
+
+    /// Whether the server is waiting for the call sign to log in, taking the prompt if so
+    pub fn take_login_prompt(&mut self) -> bool {
+        let prompt = String::from_utf8_lossy(&self.pending).trim_end().to_ascii_lowercase();
+        let waiting = ["call:", "callsign:", "login:"].iter().any(|end| prompt.ends_with(end));
+        if waiting {
+            self.pending.clear();
+        }
+        waiting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spot() {
+        let rbn = parse_spot("DX de KM3T-#:     7018.3  JS1YPT         CW    17 dB  22 WPM  CQ      1213Z").unwrap();
+        assert_eq!(
+            rbn,
+            Spot {
+                spotter: "KM3T".to_string(),
+                freq_khz: 7018.3,
+                call: "JS1YPT".to_string(),
+                snr_db: Some(17),
+                wpm: Some(22),
+                text: "CQ".to_string(),
+            }
+        );
+
+        let posted = parse_spot("DX de JA1XYZ:   437375.0  js1ypt       BOTAN JS1YPT A67C8D5E2AA13608 0712Z").unwrap();
+        assert_eq!((posted.snr_db, posted.wpm), (None, None));
+        assert_eq!(posted.text, "BOTAN JS1YPT A67C8D5E2AA13608");
+        assert_eq!(posted.call, "JS1YPT");
+
+        assert!(parse_spot("Welcome to the Reverse Beacon Network").is_none());
+        assert!(parse_spot("DX de KM3T-#: not a frequency").is_none());
+    }
+
+    #[test]
+    fn test_line_splitter() {
+        let mut lines = LineSplitter::default();
+        assert!(lines.push(b"Welcome\r\nPlease enter your call: ").contains(&"Welcome".to_string()));
+        assert!(lines.take_login_prompt());
+        assert!(!lines.take_login_prompt());
+        assert_eq!(lines.push(b"DX de KM3T-#: 7018.3 JS1"), Vec::<String>::new());
+        assert_eq!(lines.push(b"YPT CW 1213Z\r\n"), ["DX de KM3T-#: 7018.3 JS1YPT CW 1213Z"]);
+    }
+}
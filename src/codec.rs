@@ -0,0 +1,177 @@
+// Framing of BOTAN beacons on byte streams, such as a serial port carrying the text of a TNC or
+// logging program: beacon lines in, decoded beacons out, and back. The codec reads borrowed
+// bytes and says how many it consumed, so it frames a `Vec<u8>` here and a tokio `BytesMut`
+// equally well, without copying what is left over. An async ingestion server wraps it in a few
+// lines:
+//
+//     impl tokio_util::codec::Decoder for Beacons {
+//         type Item = Result<BotanBeaconData, String>;
+//         type Error = std::io::Error;
+//         fn decode(&mut self, buf: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+//             let (used, beacon) = self.codec.decode(buf);
+//             buf.advance(used);
+//             Ok(beacon)
+//         }
+//     }
+//
+// A line that does not decode, or is too long, is an item rather than an error, so one bad
+// reception does not end the stream.
+use crate::botan_parser::{self, BotanBeaconData, SignalCalibration};
+use crate::calibration::CalibrationTable;
+
+/// Longest line kept by default; a stream without newlines would otherwise grow the buffer forever
+pub const MAX_LINE: usize = 1024;
+
+/// Decodes the beacon lines of a byte stream, fed in arbitrary chunks, and encodes beacons as lines
+#[derive(Debug, Clone)]
+pub struct BeaconCodec {
+    max_line: usize,
+    calibration: SignalCalibration,
+    table: CalibrationTable,
+    discarding: bool,    // Skipping the rest of an overlong line
+    scanned: usize,      // Bytes after the consumed ones already searched for a newline
+}
+
+impl BeaconCodec {
+    /// A codec for lines of up to `max_line` bytes, converting the signal report with
+    /// `calibration` and the telemetry with `table`
+    pub fn new(max_line: usize, calibration: SignalCalibration, table: CalibrationTable) -> Self {
+        BeaconCodec { max_line, calibration, table, discarding: false, scanned: 0 }
+    }
+
+    /// The number of bytes at the start of `buf` used up, and the beacon decoded from the next
+    /// complete line among them. Blank lines are skipped; without a line there is nothing to
+    /// consume until more bytes arrive, unless a line grew too long.
+    pub fn decode(&mut self, buf: &[u8]) -> (usize, Option<Result<BotanBeaconData, String>>) {
+        let (used, line) = self.next_line(buf);
+        (used, line.map(|line| self.parse(line)))
+    }
+
+    /// As `decode`, with the stream ended: a last line without a line end is decoded too
+    pub fn decode_eof(&mut self, buf: &[u8]) -> (usize, Option<Result<BotanBeaconData, String>>) {
+        let (used, line) = self.next_line(buf);
+        if line.is_some() {
+            return (used, line.map(|line| self.parse(line)));
+        }
+        self.scanned = 0;
+        (buf.len(), self.line(&buf[used..]).map(|line| self.parse(line)))
+    }
+
+    /// Append `item` to `dst` as a beacon line, as a receiver prints it
+    pub fn encode(&mut self, item: &BotanBeaconData, dst: &mut Vec<u8>) {
+        let signal = item.rssi.as_ref().map_or_else(String::new, |si| format!("SI{:02X}{:02X} ", si.raw_rssi, si.raw_snr));
+        let hex: String = item.frame.iter().map(|b| format!("{:02X}", b)).collect();
+        dst.extend_from_slice(format!("{} {} {}{}\n", item.satellite_name, item.call_sign, signal, hex).as_bytes());
+    }
+
+    fn parse(&self, line: Result<String, String>) -> Result<BotanBeaconData, String> {
+        line.and_then(|line| botan_parser::parse_botan_beacon_with(&line, &self.calibration, &self.table))
+    }
+
+    /// The bytes used up and the next complete line, with its line end and surrounding
+    /// whitespace removed
+    fn next_line(&mut self, buf: &[u8]) -> (usize, Option<Result<String, String>>) {
+        let mut start = 0;
+        loop {
+            let from = (start + self.scanned).min(buf.len());
+            let Some(end) = buf[from..].iter().position(|&b| b == b'\n').map(|i| from + i) else {
+                // One more byte may still be the '\r' of the line end
+                if self.discarding || buf.len() - start > self.max_line + 1 {
+                    self.discarding = true;
+                    self.scanned = 0;
+                    return (buf.len(), None);
+                }
+                self.scanned = buf.len() - start;
+                return (start, None);
+            };
+            self.scanned = 0;
+            let line = &buf[start..end];
+            start = end + 1;
+            if let Some(line) = self.line(line) {
+                return (start, Some(line));
+            }
+        }
+    }
+
+    /// None for a blank line
+    fn line(&mut self, line: &[u8]) -> Option<Result<String, String>> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if std::mem::take(&mut self.discarding) || line.len() > self.max_line {
+            return Some(Err(format!("Line longer than {} bytes", self.max_line)));
+        }
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        (!line.is_empty()).then(|| Ok(line.to_string()))
+    }
+}
+
+impl Default for BeaconCodec {
+    fn default() -> Self {
+        BeaconCodec::new(MAX_LINE, SignalCalibration::default(), CalibrationTable::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beacon_codec() {
+        let mut codec = BeaconCodec::default();
+        let mut buf = b"BOTAN JS1YPT SI86".to_vec();
+        assert_eq!(codec.decode(&buf), (0, None));
+        buf.extend_from_slice(b"40 A67C8D5E2AA13608\r\n\n noise \nBOTAN JS1YPT A67C");
+        let (used, beacon) = codec.decode(&buf);
+        assert_eq!(beacon, Some(botan_parser::parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608")));
+        buf.drain(..used);
+        let (used, beacon) = codec.decode(&buf);
+        assert!(beacon.unwrap().is_err());
+        buf.drain(..used);
+        assert_eq!(codec.decode(&buf), (0, None));
+
+        // A shorter buffer than the one scanned before is taken as it is
+        assert_eq!(codec.decode(b"BOT"), (0, None));
+        buf.extend_from_slice(b"8D5E2AA13608");
+        let (used, beacon) = codec.decode_eof(&buf);
+        assert_eq!(used, buf.len());
+        assert_eq!(beacon.unwrap().unwrap().frame, [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08]);
+        assert_eq!(codec.decode_eof(b" \r\n"), (3, None));
+    }
+
+    #[test]
+    fn test_calibration() {
+        let calibration: SignalCalibration = serde_json::from_str(r#"{"rssi": {"slope": -0.5}}"#).unwrap();
+        let mut codec = BeaconCodec::new(MAX_LINE, calibration, CalibrationTable::default());
+        let (_, beacon) = codec.decode(b"BOTAN JS1YPT SI8640 A67C8D5E2AA13608\n");
+        assert_eq!(beacon.unwrap().unwrap().rssi.unwrap().rssi_dbm, Some(-67.0));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut codec = BeaconCodec::default();
+        let mut buf = Vec::new();
+        for line in ["BOTAN JS1YPT SI8640 A67C8D5E2AA13608", "BOTAN JS1YPT A57EB76823210E08"] {
+            let beacon = botan_parser::parse_botan_beacon(line).unwrap();
+            codec.encode(&beacon, &mut buf);
+            assert!(buf.ends_with(format!("{}\n", line).as_bytes()));
+        }
+        let (used, first) = codec.decode(&buf);
+        assert_eq!(first.unwrap().unwrap(), botan_parser::parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        let (_, second) = codec.decode(&buf[used..]);
+        assert_eq!(second.unwrap().unwrap(), botan_parser::parse_botan_beacon("BOTAN JS1YPT A57EB76823210E08").unwrap());
+    }
+
+    #[test]
+    fn test_overlong_line() {
+        let mut codec = BeaconCodec::new(4, SignalCalibration::default(), CalibrationTable::default());
+        assert_eq!(codec.next_line(b"ABCD\r\nABCDE\n"), (6, Some(Ok("ABCD".to_string()))));
+        assert_eq!(codec.next_line(b"ABCDE\n"), (6, Some(Err("Line longer than 4 bytes".to_string()))));
+
+        // Bytes of a line already too long are dropped as they arrive, and the line reported once
+        assert_eq!(codec.next_line(b"ABCD\r"), (0, None));
+        assert_eq!(codec.next_line(b"ABCDEF"), (6, None));
+        assert_eq!(codec.next_line(b"GH\nAB\n"), (3, Some(Err("Line longer than 4 bytes".to_string()))));
+        assert_eq!(codec.next_line(b"AB\n"), (3, Some(Ok("AB".to_string()))));
+        assert_eq!(codec.decode(b"ABCDE\n"), (6, Some(Err("Line longer than 4 bytes".to_string()))));
+    }
+}
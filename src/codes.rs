@@ -0,0 +1,74 @@
+// Labels for coded telemetry fields. The built-in labels come with the display language; a
+// JSON file given with --code-labels replaces them, for when the mission team reassigns codes.
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::i18n::Labels;
+
+/// Labels of one coded field
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CodeLabels {
+    #[serde(default)]
+    pub labels: BTreeMap<u8, String>,
+    pub unknown: Option<String>,   // For codes without a label; "{}" is replaced by the code
+}
+
+impl CodeLabels {
+    /// Label of `code`; codes without a label and no `unknown` text keep the built-in label
+    fn label(&self, code: u8, builtin: &str) -> String {
+        match (self.labels.get(&code), &self.unknown) {
+            (Some(label), _) => label.clone(),
+            (None, Some(unknown)) => unknown.replace("{}", &code.to_string()),
+            (None, None) => builtin.to_string(),
+        }
+    }
+}
+
+/// Configured labels by field; fields left out keep the built-in labels
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldLabels {
+    pub current_mis: Option<CodeLabels>,
+}
+
+impl FieldLabels {
+    /// Load JSON labels, e.g. {"current_mis": {"labels": {"0": "None", "3": "Spare"}, "unknown": "Code {}"}}
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read code labels '{}': {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid code labels '{}': {}", path, e))
+    }
+
+    /// Name of mission code `code`
+    pub fn mission(&self, code: u8, labels: &Labels) -> String {
+        let builtin = labels.mission_name(code);
+        self.current_mis.as_ref().map_or_else(|| builtin.to_string(), |c| c.label(code, builtin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Lang;
+
+    #[test]
+    fn test_mission_labels() {
+        let en = Lang::En.labels();
+        assert_eq!(FieldLabels::default().mission(2, en), "Sun");
+        assert_eq!(FieldLabels::default().mission(3, en), "Unknown");
+
+        let codes: FieldLabels =
+            serde_json::from_str(r#"{"current_mis": {"labels": {"1": "Earth imaging", "3": "Spare"}}}"#).unwrap();
+        assert_eq!(codes.mission(1, en), "Earth imaging");
+        assert_eq!(codes.mission(3, en), "Spare");
+        assert_eq!(codes.mission(2, en), "Sun");
+
+        let codes: FieldLabels =
+            serde_json::from_str(r#"{"current_mis": {"labels": {"0": "None"}, "unknown": "Code {}"}}"#).unwrap();
+        assert_eq!(codes.mission(0, en), "None");
+        assert_eq!(codes.mission(2, en), "Code 2");
+        assert!(serde_json::from_str::<FieldLabels>(r#"{"kill_sw": {}}"#).is_err());
+    }
+}
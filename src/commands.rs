@@ -0,0 +1,1446 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::cli::{AdifArgs, AggregateArgs, BatteryArgs, CheckArgs, ConvertArgs, DecodeAfskArgs, DecodeBinArgs, DecodeDirewolfArgs, DecodeFileArgs, DecodeIqArgs, DecodeKissArgs, ExplainArgs, ExportArgs, ExtractArgs, ImportArgs, MappingsAction, MappingsArgs, MergeArgs, MigrateArgs, PassesArgs, PracticeArgs, ReplayArgs, ReportArgs, ScheduleArgs, SchemaArgs, SerialArgs, SimulateArgs, SkimmerArgs, StationArgs, StatsArgs, SynthArgs, UdpAudioArgs, UpdateTleArgs};
+use crate::adif::{self, Grid, Station};
+use crate::afsk::AfskDemodulator;
+use crate::aggregate;
+use crate::alert;
+use crate::archive;
+use crate::ax25;
+use crate::battery;
+use crate::binary::{self, Framing, RecordSplitter};
+use crate::calibration::CalibrationTable;
+use crate::check;
+use crate::cluster::{self, LineSplitter};
+use crate::codec::BeaconCodec;
+use crate::cw_demod::{self, BeaconScanner, CwDemodulator};
+use crate::cw_synth::{self, CwSynthConfig};
+use crate::daemon;
+use crate::direwolf;
+use crate::exit_code::{self, DecodeTally, ExitError};
+use crate::export;
+use crate::frames::Frame;
+use crate::grpc;
+use crate::grsat;
+use crate::html_report;
+use crate::iq::IqReader;
+use crate::kiss::KissDecoder;
+use crate::limits::Limits;
+use crate::logging::{debug, info, warn};
+use crate::merge;
+use crate::migrate;
+use crate::orbit::{DopplerTracker, Observer, OrbitState, SatTracker};
+use crate::output::BeaconPrinter;
+use crate::practice::{self, Practice, Score};
+use crate::replay;
+use crate::report;
+use crate::rigctl::{DopplerRig, RigctlClient};
+use crate::rng::Rng;
+use crate::proto;
+use crate::satnogs;
+use crate::simulate::Simulator;
+use crate::schema;
+use crate::scheduler::{self, PassRecord};
+use crate::serial::{self, WordSplitter};
+use crate::stats::{Lifetime, Recorder};
+use crate::timeutil::{self, LogClock};
+use crate::tle::Tle;
+use crate::tle_cache::{self, TleCache};
+use crate::udp_audio::UdpAudioSource;
+use crate::wav::{self, WavReader};
+
+/// Render a beacon frame to a CW sidetone WAV file
+pub fn run_synth(args: SynthArgs, table: &CalibrationTable) -> Result<(), Box<dyn std::error::Error>> {
+    if args.wpm <= 0.0 {
+        return Err("WPM must be positive".into());
+    }
+    if args.pitch <= 0.0 || args.pitch >= args.sample_rate as f64 / 2.0 {
+        return Err(format!("Pitch must be between 0 and {} Hz", args.sample_rate / 2).into());
+    }
+
+    let overrides = cw_synth::Overrides {
+        values: [args.bat_v, args.bat_i, args.bat_t, args.bpb_t, args.raw_i],
+        status: [args.data1, args.data2, args.data3],
+    };
+    let text = cw_synth::beacon_with(args.frame.as_deref(), &overrides, table)?;
+    let morse = crate::BotanDecoder::new().encode(&text)?;
+    let config = CwSynthConfig {
+        wpm: args.wpm,
+        pitch_hz: args.pitch,
+        sample_rate: args.sample_rate,
+        ..Default::default()
+    };
+    let samples = cw_synth::synthesize(&morse, &config);
+    wav::write_wav_mono16(&args.output, config.sample_rate, &samples)?;
+
+    println!("Text:     {}", text);
+    println!("Morse:    {}", morse);
+    println!("Duration: {:.1} s", samples.len() as f64 / config.sample_rate as f64);
+    println!("Wrote {}", args.output);
+    Ok(())
+}
+
+/// Demodulate a raw IQ recording and decode every beacon found in it
+pub fn run_decode_iq(args: DecodeIqArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let offset = args.beacon_freq.unwrap_or(args.center_freq) - args.center_freq;
+    if offset.abs() >= args.sample_rate / 2.0 {
+        return Err(format!(
+            "Beacon frequency is {:.0} Hz from centre, outside the recorded bandwidth",
+            offset
+        ).into());
+    }
+
+    let beacon_freq = args.beacon_freq.unwrap_or(args.center_freq);
+    let tracker = doppler_tracker(&args.station, beacon_freq)?;
+    let start_time = match (&tracker, &args.start_time) {
+        (_, Some(time)) => Some(timeutil::parse_utc(time)?),
+        (Some(_), None) => return Err("--start-time is required for Doppler correction".into()),
+        (None, None) => None,
+    };
+
+    let mut reader = IqReader::open(&args.file, args.format)?;
+    let mut demod = CwDemodulator::new(args.sample_rate, offset, args.bandwidth, args.wpm);
+    let mut scanner = BeaconScanner::new();
+    let mut block = Vec::new();
+    let mut morse = String::new();
+    let mut beacons = Vec::new();
+    let mut samples_read = 0usize;
+    while reader.read_block(&mut block)? > 0 {
+        if let (Some(tracker), Some(start_time)) = (&tracker, start_time) {
+            let now = start_time + samples_read as f64 / args.sample_rate;
+            demod.set_offset(offset + tracker.shift_hz(now)?);
+        }
+        samples_read += block.len();
+        let start = morse.len();
+        demod.process_iq(&block, &mut morse);
+        let seconds = samples_read as f64 / args.sample_rate;
+        let found = scanner.push(&morse[start..]);
+        let copy = if found.is_empty() { None } else { demod.take_copy() };
+        beacons.extend(found.into_iter().map(|b| (seconds, b, copy)));
+    }
+    let seconds = samples_read as f64 / args.sample_rate;
+    let start = morse.len();
+    demod.finish(&mut morse);
+    let mut found = scanner.push(&morse[start..]);
+    found.extend(scanner.finish());
+    let copy = demod.take_copy();
+    beacons.extend(found.into_iter().map(|b| (seconds, b, copy)));
+
+    let text = cw_demod::morse_to_text(&morse, &crate::BotanDecoder::new());
+    println!("Demodulated text ({:.0} WPM): {}", demod.wpm(), text);
+
+    if beacons.is_empty() {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No complete BOTAN beacon found in recording").into());
+    }
+    let mut tally = DecodeTally::default();
+    let mut stats = Recorder::new(None);
+    for (seconds, beacon, copy) in beacons {
+        // Time at which the beacon ended, resolved to one read block
+        let time = start_time.map(|start| start + seconds);
+        let orbit = match (&tracker, time) {
+            (Some(tracker), Some(time)) => Some(tracker.orbit_state(time)?),
+            _ => None,
+        };
+        printer.set_source(format!("decode-iq {} at {:.1} s", args.file, seconds));
+        let result = printer.print_line(&beacon, copy, time, orbit);
+        tally.record(result.is_ok());
+        stats.record(&result);
+    }
+    info!("Decode statistics: {}", stats.run);
+    Ok(tally.result()?)
+}
+
+/// Decode the beacon lines of text archives without reading them into memory
+pub fn run_decode_file(args: DecodeFileArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let date = args.date.as_deref().map(timeutil::parse_utc).transpose()?;
+    let mut tally = DecodeTally::default();
+    let mut stats = Recorder::new(None);
+    for file in &args.files {
+        let mut clock = LogClock::new(date);
+        archive::for_each_line(Path::new(file), |number, line| {
+            // Telemetry JSON written with --grsat-json is decoded again from its frame
+            let (time, line) = if line.trim_start().starts_with('{') {
+                match grsat::parse_line(line) {
+                    Ok((time, line)) => (time, line.to_ascii_uppercase()),
+                    Err(error) => {
+                        println!("BOTAN Parsing Error: {}", error);
+                        tally.record(false);
+                        stats.record(&Err(error));
+                        return;
+                    }
+                }
+            } else {
+                // The time a log line is stamped with is when the beacon was received
+                match timeutil::split_line_stamp(line) {
+                    Some((stamp, rest)) => (clock.resolve(stamp), rest.to_ascii_uppercase()),
+                    None => (None, line.to_ascii_uppercase()),
+                }
+            };
+            let Some(start) = line.find("BOTAN") else {
+                return;
+            };
+            printer.set_source(format!("decode-file {}:{}", file, number));
+            let result = printer.print_line(&line[start..], None, time, None);
+            if result.is_err() {
+                debug!("in archive"; file = file, line = number);
+            }
+            tally.record(result.is_ok());
+            stats.record(&result);
+        })
+        .map_err(|e| ExitError::io(format!("Cannot read {}: {}", file, e)))?;
+    }
+    info!("Decode statistics: {}", stats.run);
+    if tally.decoded + tally.failed == 0 {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No BOTAN beacon lines found").into());
+    }
+    Ok(tally.result()?)
+}
+
+/// Receive from an RTL-SDR dongle and print each beacon as soon as it is decoded
+#[cfg(feature = "rtlsdr")]
+pub fn run_rtlsdr(
+    args: crate::cli::RtlsdrArgs,
+    printer: &BeaconPrinter,
+    stats: &mut Recorder,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::iq::IqFormat;
+    use crate::rtlsdr::{RtlSdr, RtlSdrConfig};
+
+    let center = args.freq - args.tune_offset;
+    let config = RtlSdrConfig {
+        device_index: args.device,
+        center_freq_hz: center.round() as u32,
+        sample_rate: args.sample_rate,
+        gain_db: args.gain,
+        ppm: args.ppm,
+    };
+    let tracker = doppler_tracker(&args.station, args.freq)?;
+    let mut sdr = RtlSdr::open(&config)?;
+    let mut demod = CwDemodulator::new(args.sample_rate as f64, args.tune_offset, args.bandwidth, args.wpm);
+    let mut scanner = BeaconScanner::new();
+
+    info!("Listening on {:.3} MHz (Ctrl-C to stop)", args.freq / 1e6);
+    let mut raw = vec![0u8; 256 * 1024];
+    let mut block = Vec::new();
+    let mut morse = String::new();
+    while !daemon::stop_requested() {
+        let read = sdr.read_sync(&mut raw)?;
+        if let Some(tracker) = &tracker {
+            demod.set_offset(args.tune_offset + tracker.shift_hz(timeutil::now_unix())?);
+        }
+        block.clear();
+        IqFormat::Cu8.convert_block(&raw[..read], &mut block);
+        morse.clear();
+        demod.process_iq(&block, &mut morse);
+        let beacons = scanner.push(&morse);
+        let copy = if beacons.is_empty() { None } else { demod.take_copy() };
+        print_beacons(&beacons, copy, orbit_now(tracker.as_ref())?, printer, stats);
+        stats.heartbeat();
+    }
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// Listen for a receiver's UDP audio stream and print each beacon as soon as it is decoded
+pub fn run_udp_audio(args: UdpAudioArgs, printer: &BeaconPrinter, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+    let mut receiver = UdpReceiver::new(&args)?;
+    printer.set_source(format!("udp-audio {}", args.bind));
+    // Wake up regularly to log health and notice a stop request while the stream is quiet
+    receiver.source.set_read_timeout(Some(Duration::from_secs(1)))?;
+    while !daemon::stop_requested() {
+        let (beacons, copy) = receiver.poll()?;
+        print_beacons(&beacons, copy, orbit_now(receiver.tracker.as_ref())?, printer, stats);
+        stats.heartbeat();
+    }
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// Wait for each pass, receive from AOS to LOS and save what was decoded
+pub fn run_schedule(args: ScheduleArgs, printer: &BeaconPrinter, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((tle, observer)) = station(&args.receiver.station)? else {
+        return Err("--tle or --auto-tle is required for scheduling".into());
+    };
+    let sat = SatTracker::new(&tle, observer)?;
+    let mut receiver = UdpReceiver::new(&args.receiver)?;
+    // Wake up regularly so LOS is noticed even when the audio stream stops
+    receiver.source.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let output_dir = Path::new(&args.output_dir);
+
+    while !daemon::stop_requested() {
+        let now = timeutil::now_unix();
+        let Some(pass) = sat.passes(now, now + 86400.0, args.min_elevation)?.into_iter().next() else {
+            info!("No pass above {:.0}° in the next 24 h, checking again in an hour", args.min_elevation);
+            for _ in 0..3600 {
+                if daemon::stop_requested() {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(1));
+                stats.heartbeat();
+            }
+            continue;
+        };
+        info!(
+            "Next pass";
+            aos = timeutil::format_display(pass.aos_unix),
+            los = timeutil::format_display(pass.los_unix),
+            max_el = format!("{:.1}", pass.max_elevation_deg)
+        );
+        // Keep draining the socket so audio from before AOS is not decoded
+        while timeutil::now_unix() < pass.aos_unix && !daemon::stop_requested() {
+            receiver.source.read_block(&mut receiver.block)?;
+            stats.heartbeat();
+        }
+        if daemon::stop_requested() {
+            break;
+        }
+
+        info!("AOS, receiving until LOS"; los = timeutil::format_display(pass.los_unix));
+        let mut record = PassRecord::new(&pass, receiver.freq);
+        stats.start_pass(&record.aos);
+        let mut audio = Vec::new();
+        // A stop request ends the pass early; what was received so far is saved as usual
+        while timeutil::now_unix() < pass.los_unix && !daemon::stop_requested() {
+            let (beacons, copy) = receiver.poll()?;
+            if !beacons.is_empty() {
+                let now = timeutil::now_unix();
+                let orbit = sat.orbit_state(now)?;
+                for beacon in &beacons {
+                    let (provenance, parsed) = printer.print_traced(beacon, copy, Some(now), Some(orbit));
+                    stats.record(&parsed);
+                    record.add_beacon(now, beacon, Some(orbit), parsed).provenance = Some(provenance);
+                }
+            }
+            if args.record {
+                audio.extend(receiver.block.iter().map(|s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16));
+            }
+            stats.heartbeat();
+        }
+        record.wpm = Some(receiver.demod.wpm());
+
+        let audio = args.record.then_some((args.receiver.sample_rate as u32, audio.as_slice()));
+        let dir = scheduler::save_pass(output_dir, pass.aos_unix, &record, audio)?;
+        info!(
+            "LOS, pass saved";
+            beacons = record.beacons.len(),
+            decoded = stats.current_pass.decoded,
+            failed = stats.current_pass.failed_total(),
+            duplicates = stats.current_pass.duplicates,
+            dir = dir.display()
+        );
+        if let Some(program) = &args.on_pass {
+            match process::Command::new(program).arg(&dir).status() {
+                Ok(status) if status.success() => {}
+                Ok(status) => warn!("{} exited with {}", program, status),
+                Err(e) => warn!("Cannot run {}: {}", program, e),
+            }
+        }
+    }
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// UDP audio front-end with optional Doppler tracking and rig control
+struct UdpReceiver {
+    source: UdpAudioSource,
+    demod: CwDemodulator,
+    scanner: BeaconScanner,
+    pitch: f64,
+    freq: Option<f64>,
+    tracker: Option<DopplerTracker>,
+    rig: Option<DopplerRig>,
+    block: Vec<f32>,
+    morse: String,
+}
+
+impl UdpReceiver {
+    fn new(args: &UdpAudioArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        if args.pitch <= 0.0 || args.pitch >= args.sample_rate / 2.0 {
+            return Err(format!("Pitch must be between 0 and {} Hz", args.sample_rate / 2.0).into());
+        }
+
+        // With rigctld the radio follows the Doppler shift and the tone stays on the pitch
+        let mut client = match &args.rigctld {
+            Some(addr) => {
+                let mut client = RigctlClient::connect(addr).map_err(ExitError::io)?;
+                client.set_mode("CW", 0).map_err(ExitError::io)?;
+                Some(client)
+            }
+            None => None,
+        };
+        let freq = match (args.freq, &mut client) {
+            (Some(freq), _) => Some(freq),
+            (None, Some(client)) => Some(client.frequency().map_err(ExitError::io)?),
+            (None, None) => None,
+        };
+        let tracker = match freq {
+            Some(freq) => doppler_tracker(&args.station, freq)?,
+            None if args.station.tle.is_some() || args.station.auto_tle => {
+                return Err("--freq is required for Doppler correction".into())
+            }
+            None => None,
+        };
+        let rig = match (client, freq) {
+            (Some(client), Some(freq)) if tracker.is_some() => Some(DopplerRig::new(client, freq)),
+            _ => None,
+        };
+
+        let source = UdpAudioSource::bind(&args.bind)?;
+        info!("Listening for UDP audio on {} (Ctrl-C to stop)", source.local_addr()?);
+        Ok(UdpReceiver {
+            source,
+            demod: CwDemodulator::new(args.sample_rate, args.pitch, args.bandwidth, args.wpm),
+            scanner: BeaconScanner::new(),
+            pitch: args.pitch,
+            freq,
+            tracker,
+            rig,
+            block: Vec::new(),
+            morse: String::new(),
+        })
+    }
+
+    /// Demodulate the next block of audio and return the beacons it completed, with the share
+    /// of characters read unambiguously since the previous beacon
+    fn poll(&mut self) -> Result<(Vec<String>, Option<f64>), Box<dyn std::error::Error>> {
+        if self.source.read_block(&mut self.block)? == 0 {
+            return Ok((Vec::new(), None));
+        }
+        if let Some(tracker) = &self.tracker {
+            let shift = tracker.shift_hz(timeutil::now_unix())?;
+            match &mut self.rig {
+                Some(rig) => rig.update(shift).map_err(ExitError::io)?,
+                // With the receiver on a fixed frequency the tone moves by the full Doppler shift
+                None => self.demod.set_offset(self.pitch + shift),
+            }
+        }
+        self.morse.clear();
+        self.demod.process_audio(&self.block, &mut self.morse);
+        let beacons = self.scanner.push(&self.morse);
+        let copy = if beacons.is_empty() { None } else { self.demod.take_copy() };
+        Ok((beacons, copy))
+    }
+}
+
+/// List the upcoming passes over the ground station
+pub fn run_passes(args: PassesArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((tle, observer)) = station(&args.station)? else {
+        return Err("--tle or --auto-tle is required for pass prediction".into());
+    };
+    let start = match &args.start {
+        Some(time) => timeutil::parse_utc(time)?,
+        None => timeutil::now_unix(),
+    };
+    let tracker = SatTracker::new(&tle, observer)?;
+    let passes = tracker.passes(start, start + args.hours * 3600.0, args.min_elevation)?;
+
+    println!(
+        "BOTAN passes for {:.4}, {:.4} from {} ({:.0} h)",
+        observer.latitude_deg,
+        observer.longitude_deg,
+        timeutil::format_display(start),
+        args.hours
+    );
+    if passes.is_empty() {
+        println!("No passes above {:.0}° in this window", args.min_elevation);
+        return Ok(());
+    }
+    println!();
+    // Times end in Z or their offset from UTC, so the columns are as wide as the display zone needs
+    let width = timeutil::format_display(start).len();
+    println!("{:<width$}  {:<width$}  Duration  Peak      Max El  AOS Az  LOS Az", "AOS", "LOS", width = width);
+    for pass in passes {
+        let duration = (pass.los_unix - pass.aos_unix).round() as i64;
+        println!(
+            "{}  {}  {:>3}:{:02}    {}  {:>5.1}°  {:>5.1}°  {:>5.1}°",
+            timeutil::format_display(pass.aos_unix),
+            timeutil::format_display(pass.los_unix),
+            duration / 60,
+            duration % 60,
+            &timeutil::format_display(pass.max_elevation_unix)[11..19],
+            pass.max_elevation_deg,
+            pass.aos_azimuth_deg,
+            pass.los_azimuth_deg
+        );
+    }
+    Ok(())
+}
+
+/// Refresh the cached BOTAN element set
+pub fn run_update_tle(args: UpdateTleArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = TleCache::new(&args.tle_url, TleCache::default_path());
+    if !args.force && cache.is_fresh() {
+        println!("Cached TLE {} is less than a day old (use --force to download anyway)", cache.path().display());
+    } else {
+        cache.update().map_err(ExitError::io)?;
+        println!("Downloaded {} to {}", args.tle_url, cache.path().display());
+    }
+
+    let tle = Tle::from_file(&cache.path().to_string_lossy())?;
+    println!(
+        "{} (NORAD {}), epoch {} ({:.1} days old)",
+        tle.name.as_deref().unwrap_or("unnamed"),
+        tle.norad_id,
+        timeutil::format_display(tle.epoch_unix),
+        tle.age_days(timeutil::now_unix())
+    );
+    warn_if_stale(&tle);
+    Ok(())
+}
+
+/// Decode the AX.25 UI frames of a KISS stream. Frames from a TNC count towards the lifetime
+/// statistics like the other live receivers; frames read from a file or pipe do not.
+pub fn run_decode_kiss(args: DecodeKissArgs, printer: &BeaconPrinter, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+    let mut kiss = KissRun::new(args.fcs, args.source.clone(), printer);
+    if let Some(addr) = &args.tcp {
+        let stream = std::net::TcpStream::connect(addr)
+            .map_err(|e| ExitError::io(format!("Cannot connect to KISS server {}: {}", addr, e)))?;
+        // Wake up regularly to log health and notice a stop request
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        info!("Connected to KISS server"; address = addr);
+        printer.set_source(format!("decode-kiss {}", addr));
+        kiss.decode(stream, stats)?;
+        info!("Decode statistics: {}", stats.run);
+    } else {
+        let mut local_stats = Recorder::new(None);
+        for input in &args.files {
+            if input == "-" {
+                printer.set_source("decode-kiss stdin".to_string());
+                kiss.decode(std::io::stdin(), &mut local_stats)?;
+                continue;
+            }
+            let paths = archive::files(Path::new(input)).map_err(|e| ExitError::io(format!("Cannot read {}: {}", input, e)))?;
+            for path in paths {
+                let file = std::fs::File::open(&path)
+                    .map_err(|e| ExitError::io(format!("Cannot read {}: {}", path.display(), e)))?;
+                debug!("Reading KISS capture"; file = path.display());
+                printer.set_source(format!("decode-kiss {}", path.display()));
+                kiss.decode(file, &mut local_stats)?;
+            }
+        }
+        info!("Decode statistics: {}", local_stats.run);
+    }
+    if kiss.skipped > 0 {
+        info!("Skipped frames from other stations"; frames = kiss.skipped);
+    }
+    if daemon::stop_requested() {
+        return Ok(());
+    }
+    if kiss.tally.decoded + kiss.tally.failed == 0 {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No KISS data frames found").into());
+    }
+    Ok(kiss.tally.result()?)
+}
+
+/// Decoding state shared by every KISS input of one run
+struct KissRun<'a> {
+    fcs: bool,
+    source: Option<String>,
+    printer: &'a BeaconPrinter,
+    tally: DecodeTally,
+    skipped: usize,
+}
+
+impl<'a> KissRun<'a> {
+    fn new(fcs: bool, source: Option<String>, printer: &'a BeaconPrinter) -> Self {
+        KissRun { fcs, source, printer, tally: DecodeTally::default(), skipped: 0 }
+    }
+
+    /// Decode the frames of one KISS stream to its end; a frame split across inputs is not joined
+    fn decode(&mut self, mut reader: impl std::io::Read, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+        let mut kiss = KissDecoder::default();
+        let mut buf = [0u8; 4096];
+        while !daemon::stop_requested() {
+            stats.heartbeat();
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                Err(e) => return Err(ExitError::io(format!("KISS input failed: {}", e)).into()),
+            };
+            for frame in kiss.push(&buf[..len]) {
+                let result = match ax25::parse_ui(&frame, self.fcs) {
+                    Ok(ui) if self.source.as_ref().is_some_and(|call| !ui.source.call.eq_ignore_ascii_case(call)) => {
+                        self.skipped += 1;
+                        continue;
+                    }
+                    Ok(ui) => {
+                        debug!("UI frame"; source = ui.source, destination = ui.destination, bytes = ui.info.len());
+                        self.printer.print_line(&ui.beacon_line(), None, Some(timeutil::now_unix()), None)
+                    }
+                    Err(error) => {
+                        println!("AX.25 Error: {}", error);
+                        Err(error)
+                    }
+                };
+                self.tally.record(result.is_ok());
+                stats.record(&result);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode the frames of Direwolf monitor logs from the chosen station
+pub fn run_decode_direwolf(args: DecodeDirewolfArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tally = DecodeTally::default();
+    let mut stats = Recorder::new(None);
+    let mut skipped = 0;
+    for input in &args.files {
+        let paths = archive::files(Path::new(input)).map_err(|e| ExitError::io(format!("Cannot read {}: {}", input, e)))?;
+        for path in paths {
+            archive::for_each_line(&path, |number, line| {
+                let Some(monitored) = direwolf::parse_line(line) else {
+                    return;
+                };
+                if !monitored.frame.source.call.eq_ignore_ascii_case(&args.source) {
+                    skipped += 1;
+                    return;
+                }
+                printer.set_source(format!("decode-direwolf {}:{}", path.display(), number));
+                let result = printer.print_line(&monitored.frame.beacon_line(), None, monitored.time, None);
+                if result.is_err() {
+                    debug!("in Direwolf log"; file = path.display(), line = number);
+                }
+                tally.record(result.is_ok());
+                stats.record(&result);
+            })
+            .map_err(|e| ExitError::io(format!("Cannot read {}: {}", path.display(), e)))?;
+        }
+    }
+    info!("Decode statistics: {}", stats.run);
+    if skipped > 0 {
+        info!("Skipped frames from other stations"; frames = skipped);
+    }
+    if tally.decoded + tally.failed == 0 {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, format!("No frames from {} found", args.source)).into());
+    }
+    Ok(tally.result()?)
+}
+
+/// Decode what arrives on a serial port: KISS frames from a TNC, or text from a CW decoder
+pub fn run_serial(args: SerialArgs, printer: &BeaconPrinter, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+    let mut port = serial::open(&args.device, args.baud).map_err(ExitError::io)?;
+    info!("Opened serial port"; device = args.device, baud = args.baud);
+    printer.set_source(format!("serial {}", args.device));
+    if args.kiss {
+        let mut kiss = KissRun::new(args.fcs, args.source, printer);
+        kiss.decode(port, stats)?;
+        if kiss.skipped > 0 {
+            info!("Skipped frames from other stations"; frames = kiss.skipped);
+        }
+        info!("Stopped: {}", stats.run);
+        return Ok(());
+    }
+
+    if args.lines {
+        let mut codec = printer.beacon_codec();
+        let mut pending = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !daemon::stop_requested() {
+            stats.heartbeat();
+            let len = match port.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                Err(e) => return Err(ExitError::io(format!("Serial port {} failed: {}", args.device, e)).into()),
+            };
+            pending.extend_from_slice(&buf[..len]);
+            print_framed(&mut codec, &mut pending, false, printer, stats);
+        }
+        print_framed(&mut codec, &mut pending, true, printer, stats);
+        info!("Stopped: {}", stats.run);
+        return Ok(());
+    }
+
+    // Decoders send text as they copy it, so beacons are put together from whole words
+    let mut words = WordSplitter::default();
+    let mut scanner = BeaconScanner::new();
+    let mut buf = [0u8; 4096];
+    while !daemon::stop_requested() {
+        stats.heartbeat();
+        let len = match port.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(ExitError::io(format!("Serial port {} failed: {}", args.device, e)).into()),
+        };
+        print_beacons(&scanner.push_words(&words.push(&buf[..len])), None, None, printer, stats);
+    }
+    print_beacons(&scanner.push_words(&words.finish()), None, None, printer, stats);
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// Report the spots of BOTAN from a telnet spot server and decode the beacons their text holds
+pub fn run_skimmer(args: SkimmerArgs, printer: &BeaconPrinter, stats: &mut Recorder) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = std::net::TcpStream::connect(&args.server)
+        .map_err(|e| ExitError::io(format!("Cannot connect to spot server {}: {}", args.server, e)))?;
+    // Wake up regularly to log health and notice a stop request
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    info!("Connected to spot server"; address = args.server);
+
+    let mut lines = LineSplitter::default();
+    // Each spotter's text is reassembled on its own, so spots of different skimmers do not mix
+    let mut scanners: HashMap<String, BeaconScanner> = HashMap::new();
+    let mut buf = [0u8; 4096];
+    while !daemon::stop_requested() {
+        stats.heartbeat();
+        let len = match stream.read(&mut buf) {
+            Ok(0) => return Err(ExitError::io(format!("Spot server {} closed the connection", args.server)).into()),
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(ExitError::io(format!("Spot server input failed: {}", e)).into()),
+        };
+        for line in lines.push(&buf[..len]) {
+            let Some(spot) = cluster::parse_spot(&line) else {
+                debug!("server"; line = line);
+                continue;
+            };
+            if !spot.call.eq_ignore_ascii_case(&args.call) {
+                continue;
+            }
+            info!(
+                "Spot";
+                spotter = spot.spotter,
+                freq_khz = spot.freq_khz,
+                snr_db = spot.snr_db.map_or("-".to_string(), |snr| snr.to_string()),
+                wpm = spot.wpm.map_or("-".to_string(), |wpm| wpm.to_string()),
+                text = spot.text
+            );
+            printer.set_source(format!("skimmer {} spotted by {}", args.server, spot.spotter));
+            let scanner = scanners.entry(spot.spotter).or_default();
+            print_beacons(&scanner.push_words(&spot.text), None, None, printer, stats);
+        }
+        if lines.take_login_prompt() {
+            stream.write_all(format!("{}\r\n", args.login).as_bytes())?;
+            info!("Logged in"; call = args.login);
+        }
+    }
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// Decode the records of a binary frame file or stream, each as the data block of a beacon
+pub fn run_decode_bin(args: DecodeBinArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let framing = match args.framing {
+        Framing::Fixed(_) if args.record_size == 0 => return Err("Record size must be at least 1".into()),
+        Framing::Fixed(_) => Framing::Fixed(args.record_size),
+        framing => framing,
+    };
+    let mut reader: Box<dyn std::io::Read> = if args.file == "-" {
+        Box::new(std::io::stdin())
+    } else {
+        let file = std::fs::File::open(&args.file).map_err(|e| ExitError::io(format!("Cannot read {}: {}", args.file, e)))?;
+        Box::new(file)
+    };
+    printer.set_source(format!("decode-bin {}", args.file));
+
+    let mut splitter = RecordSplitter::new(framing);
+    let mut tally = DecodeTally::default();
+    let mut buf = [0u8; 4096];
+    while !daemon::stop_requested() {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(ExitError::io(format!("Binary input failed: {}", e)).into()),
+        };
+        for record in splitter.push(&buf[..len]) {
+            let result = printer.print_line(&binary::beacon_line(&record), None, Some(timeutil::now_unix()), None);
+            tally.record(result.is_ok());
+        }
+    }
+    if splitter.leftover() > 0 {
+        warn!("Incomplete record at the end of the input"; bytes = splitter.leftover());
+        tally.record(false);
+    }
+    if tally.decoded + tally.failed == 0 {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No binary frames found").into());
+    }
+    Ok(tally.result()?)
+}
+
+/// Demodulate the AFSK packets of a WAV recording and decode their payloads
+pub fn run_decode_afsk(args: DecodeAfskArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = WavReader::open(&args.file).map_err(|e| ExitError::io(format!("Cannot read {}: {}", args.file, e)))?;
+    let mut demod = AfskDemodulator::new(reader.sample_rate() as f64);
+    printer.set_source(format!("decode-afsk {}", args.file));
+    let mut block = Vec::new();
+    let mut frames = Vec::new();
+    while reader.read_block(&mut block)? > 0 {
+        frames.extend(demod.process(&block));
+    }
+    debug!("AFSK frames with a valid FCS"; count = frames.len());
+
+    if frames.is_empty() {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No AX.25 frame found in recording").into());
+    }
+    let mut tally = DecodeTally::default();
+    let mut stats = Recorder::new(None);
+    for frame in frames {
+        let result = match ax25::parse_ui(&frame, true) {
+            Ok(ui) => {
+                debug!("UI frame"; source = ui.source, destination = ui.destination, bytes = ui.info.len());
+                printer.print_line(&ui.beacon_line(), None, None, None)
+            }
+            Err(error) => {
+                println!("AX.25 Error: {}", error);
+                Err(error)
+            }
+        };
+        tally.record(result.is_ok());
+        stats.record(&result);
+    }
+    info!("Decode statistics: {}", stats.run);
+    Ok(tally.result()?)
+}
+
+/// Export the beacons of the saved passes in a time range as a table
+pub fn run_export(args: ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let from = args.from.as_deref().map(timeutil::parse_utc).transpose()?;
+    let to = args.to.as_deref().map(timeutil::parse_utc).transpose()?;
+    let dir = Path::new(&args.dir);
+    if args.format == export::ExportFormat::Parquet && args.output.is_none() {
+        return Err("--output is required for Parquet export".into());
+    }
+    let rows = match &args.output {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|e| ExitError::io(format!("Cannot write {}: {}", path, e)))?;
+            let mut out = std::io::BufWriter::new(file);
+            let rows = export::export(dir, from, to, args.format, &mut out).map_err(ExitError::io)?;
+            out.flush()?;
+            rows
+        }
+        None => export::export(dir, from, to, args.format, &mut std::io::stdout().lock()).map_err(ExitError::io)?,
+    };
+    info!("Exported frames"; rows = rows);
+    Ok(())
+}
+
+/// Write the saved receptions as an ADIF log, one record per pass or per frame
+pub fn run_adif(args: AdifArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let from = args.from.as_deref().map(timeutil::parse_utc).transpose()?;
+    let to = args.to.as_deref().map(timeutil::parse_utc).transpose()?;
+    let dir = Path::new(&args.dir);
+    let grid = args.grid.or(args.lat.zip(args.lon).map(|(lat, lon)| Grid::at(lat, lon)));
+    let station = Station { call: args.call, grid };
+    let records = match &args.output {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|e| ExitError::io(format!("Cannot write {}: {}", path, e)))?;
+            let mut out = std::io::BufWriter::new(file);
+            let records = adif::export(dir, from, to, args.per, &station, &mut out).map_err(ExitError::io)?;
+            out.flush()?;
+            records
+        }
+        None => adif::export(dir, from, to, args.per, &station, &mut std::io::stdout().lock()).map_err(ExitError::io)?,
+    };
+    info!("Exported receptions"; records = records);
+    Ok(())
+}
+
+/// Write statistics of the saved history per interval and channel as CSV
+pub fn run_aggregate(args: AggregateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let from = args.from.as_deref().map(timeutil::parse_utc).transpose()?;
+    let to = args.to.as_deref().map(timeutil::parse_utc).transpose()?;
+    let dir = Path::new(&args.dir);
+    let intervals = match &args.output {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(|e| ExitError::io(format!("Cannot write {}: {}", path, e)))?;
+            let mut out = std::io::BufWriter::new(file);
+            let intervals = aggregate::aggregate(dir, from, to, args.interval, &args.stat, &args.channel, &mut out).map_err(ExitError::io)?;
+            out.flush()?;
+            intervals
+        }
+        None => aggregate::aggregate(dir, from, to, args.interval, &args.stat, &args.channel, &mut std::io::stdout().lock()).map_err(ExitError::io)?,
+    };
+    info!("Aggregated history"; intervals = intervals);
+    Ok(())
+}
+
+/// Report battery health per window over the saved history, with warnings of aging
+pub fn run_battery(args: BatteryArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let from = args.from.as_deref().map(timeutil::parse_utc).transpose()?;
+    let to = args.to.as_deref().map(timeutil::parse_utc).transpose()?;
+    let report = battery::analyse(Path::new(&args.dir), from, to, args.window).map_err(ExitError::io)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    if report.windows.is_empty() {
+        println!("No housekeeping frames in {}", args.dir);
+        return Ok(());
+    }
+
+    let value = |value: Option<f64>, decimals: usize| value.map_or("-".to_string(), |v| format!("{:.*}", decimals, v));
+    println!(
+        "{:<20}  {:>6}  {:>6}  {:>9}  {:>6}  {:>9}  {:>12}  {:>6}  {:>6}  {:>7}",
+        "Window", "Frames", "Sun V", "Eclipse V", "Min V", "Charge mA", "Discharge mA", "R mΩ", "OCV V", "Temp °C"
+    );
+    for w in &report.windows {
+        println!(
+            "{:<20}  {:>6}  {:>6}  {:>9}  {:>6}  {:>9}  {:>12}  {:>6}  {:>6}  {:>7}",
+            w.start,
+            w.frames,
+            value(w.sunlight_v, 3),
+            value(w.eclipse_v, 3),
+            value(w.eclipse_min_v, 3),
+            value(w.charge_ma, 1),
+            value(w.discharge_ma, 1),
+            value(w.resistance_mohm, 0),
+            value(w.open_circuit_v, 3),
+            value(w.temperature_c, 1),
+        );
+    }
+    let trends = &report.trends;
+    let signed = |value: Option<f64>, decimals: usize| value.map_or("-".to_string(), |v| format!("{:+.*}", decimals, v));
+    println!();
+    println!(
+        "Trend per 30 days: eclipse voltage {} V, internal resistance {} mΩ, open-circuit voltage {} V",
+        signed(trends.eclipse_v, 3),
+        signed(trends.resistance_mohm, 1),
+        signed(trends.open_circuit_v, 3),
+    );
+    if report.warnings.is_empty() {
+        println!("No signs of battery aging");
+    }
+    for warning in &report.warnings {
+        println!("Warning: {}", warning);
+    }
+    Ok(())
+}
+
+/// Write the mission summary of the saved history as Markdown or JSON
+pub fn run_report(args: ReportArgs, limits: &Limits) -> Result<(), Box<dyn std::error::Error>> {
+    let from = args.from.as_deref().map(timeutil::parse_utc).transpose()?;
+    let to = args.to.as_deref().map(timeutil::parse_utc).transpose()?;
+    let dir = Path::new(&args.dir);
+    let (mut rows, pass) = match &args.pass {
+        Some(id) => {
+            let pass = scheduler::find_pass(dir, id).and_then(|path| scheduler::load_pass(&path)).map_err(ExitError::io)?;
+            (export::pass_rows(&pass, None, None), Some(pass))
+        }
+        None => (report::history(dir, from, to).map_err(ExitError::io)?, None),
+    };
+    let report = report::summarise(&mut rows, limits);
+    if let Some(path) = &args.html {
+        let page = html_report::page(&report, &rows, pass.as_ref(), limits);
+        std::fs::write(path, page).map_err(|e| ExitError::io(format!("Cannot write {}: {}", path, e)))?;
+    } else {
+        let text = if args.json { serde_json::to_string_pretty(&report)? + "\n" } else { report.markdown() };
+        match &args.output {
+            Some(path) => std::fs::write(path, text).map_err(|e| ExitError::io(format!("Cannot write {}: {}", path, e)))?,
+            None => print!("{}", text),
+        }
+    }
+    info!("Summarised history"; frames = report.frames, events = report.events.len());
+    Ok(())
+}
+
+/// Decode the frames of SatNOGS DB exports and file them as passes next to the scheduled ones.
+/// Passes already filed are skipped, so overlapping exports can be imported again.
+pub fn run_import(args: ImportArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let mut frames = Vec::new();
+    for file in &args.files {
+        let content = std::fs::read_to_string(file).map_err(|e| ExitError::io(format!("Cannot read {}: {}", file, e)))?;
+        let export = satnogs::read_export(&content)
+            .map_err(|e| ExitError::new(exit_code::PARSE_ERROR, format!("{}: {}", file, e)))?;
+        frames.extend(export);
+    }
+    frames.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let root = Path::new(&args.dir);
+    let mut tally = DecodeTally::default();
+    let (mut filed, mut skipped) = (0, 0);
+    for pass in satnogs::group_passes(frames) {
+        let (aos, los) = (pass[0].time, pass[pass.len() - 1].time);
+        if scheduler::pass_directory(root, aos).join("pass.json").exists() {
+            debug!("Pass already filed"; aos = timeutil::format_display(aos));
+            skipped += 1;
+            continue;
+        }
+        let mut record = PassRecord::imported(aos, los, "SatNOGS DB");
+        for frame in pass {
+            let line = satnogs::beacon_line(&frame.frame);
+            let parsed = printer.parse_frame(&line, None);
+            if let Err(error) = &parsed {
+                debug!("Frame not decoded"; time = timeutil::format_display(frame.time), error = error);
+            }
+            tally.record(parsed.is_ok());
+            printer.set_source(match frame.observation {
+                Some(id) => format!("import SatNOGS observation {}", id),
+                None => "import SatNOGS DB".to_string(),
+            });
+            let beacon = record.add_beacon(frame.time, &line, None, parsed);
+            beacon.observer = frame.observer;
+            beacon.provenance = Some(printer.provenance(Vec::new()));
+        }
+        scheduler::save_pass(root, aos, &record, None)?;
+        filed += 1;
+    }
+    info!("Imported SatNOGS frames"; passes = filed, skipped = skipped, decoded = tally.decoded, failed = tally.failed);
+    Ok(tally.result()?)
+}
+
+/// Put the receptions of several stations on one timeline and file it as passes, each beacon
+/// attributed to the station that heard it. Passes already filed are skipped, as by `import`.
+pub fn run_merge(args: MergeArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    if args.window < 0.0 || !args.window.is_finite() {
+        return Err("Window must not be negative".into());
+    }
+    let mut received = Vec::new();
+    for source in &args.sources {
+        let frames = merge::read_source(source).map_err(|e| ExitError::new(exit_code::PARSE_ERROR, e))?;
+        info!("Read station"; observer = source.observer, frames = frames.len());
+        received.extend(frames);
+    }
+    let observers: Vec<&str> = args.sources.iter().map(|s| s.observer.as_str()).collect();
+    let label = format!("merged: {}", observers.join(", "));
+
+    let root = Path::new(&args.dir);
+    let mut tally = DecodeTally::default();
+    let (mut filed, mut skipped, mut duplicates) = (0, 0, 0);
+    for pass in merge::timeline(received) {
+        let (aos, los) = (pass[0].time, pass[pass.len() - 1].time);
+        if scheduler::pass_directory(root, aos).join("pass.json").exists() {
+            debug!("Pass already filed"; aos = timeutil::format_display(aos));
+            skipped += 1;
+            continue;
+        }
+        let mut record = PassRecord::imported(aos, los, &label);
+        for (r, receptions) in merge::reconcile(pass, args.window) {
+            let parsed = printer.parse_frame(&r.line, None);
+            tally.record(parsed.is_ok());
+            duplicates += receptions.len().saturating_sub(1);
+            printer.set_source(format!("merge {}", r.observer));
+            let beacon = record.add_beacon(r.time, &r.line, r.orbit, parsed);
+            beacon.provenance = Some(printer.provenance(Vec::new()));
+            beacon.observer = Some(r.observer);
+            beacon.receptions = receptions;
+        }
+        scheduler::save_pass(root, aos, &record, None)?;
+        filed += 1;
+    }
+    info!("Merged stations"; passes = filed, skipped = skipped, duplicates = duplicates, decoded = tally.decoded, failed = tally.failed);
+    Ok(tally.result()?)
+}
+
+/// Upgrade every pass under a directory to the current schema. Readers upgrade old passes as
+/// they go, so this is only needed for other programs reading the files, or to stop paying for
+/// the upgrade on every read.
+pub fn run_migrate(args: MigrateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = Path::new(&args.dir);
+    let entries = std::fs::read_dir(dir).map_err(|e| ExitError::io(format!("Cannot read {}: {}", args.dir, e)))?;
+    let mut passes: Vec<_> = entries.filter_map(|entry| Some(entry.ok()?.path().join("pass.json"))).filter(|p| p.is_file()).collect();
+    passes.sort();
+    let mut upgraded = 0;
+    for path in &passes {
+        if let Some(version) = scheduler::upgrade_pass_file(path, args.dry_run).map_err(ExitError::io)? {
+            let path = path.display();
+            if args.dry_run {
+                println!("{} (schema version {})", path, version);
+            } else {
+                debug!("Upgraded pass"; file = path, from = version);
+            }
+            upgraded += 1;
+        }
+    }
+    if args.dry_run {
+        info!("Passes to upgrade"; upgrade = upgraded, passes = passes.len(), schema_version = migrate::SCHEMA_VERSION);
+    } else {
+        info!("Upgraded passes"; upgraded = upgraded, passes = passes.len(), schema_version = migrate::SCHEMA_VERSION);
+    }
+    Ok(())
+}
+
+/// Print a recorded pass again as if it were being received, to demo the output or exercise the
+/// forwarders. Beacons keep their original reception times; with --realtime or --speed the gaps
+/// between them are waited out too.
+pub fn run_replay(args: ReplayArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    let speed = args.speed.unwrap_or(1.0);
+    if speed <= 0.0 || !speed.is_finite() {
+        return Err("Speed must be positive".into());
+    }
+    let paced = args.realtime || args.speed.is_some();
+    let content = std::fs::read_to_string(&args.file).map_err(|e| ExitError::io(format!("Cannot read {}: {}", args.file, e)))?;
+    let recorded = replay::read_log(&content).map_err(|e| ExitError::new(exit_code::PARSE_ERROR, format!("{}: {}", args.file, e)))?;
+
+    let mut tally = DecodeTally::default();
+    let mut previous = None;
+    for beacon in recorded {
+        let line = beacon.line.to_ascii_uppercase();
+        let Some(start) = line.find("BOTAN") else {
+            continue;
+        };
+        if paced {
+            thread::sleep(Duration::from_secs_f64(replay::delay(previous, beacon.time, speed)));
+        }
+        previous = beacon.time.or(previous);
+        printer.set_source(format!("replay {}", args.file));
+        tally.record(printer.print_line(&line[start..], None, beacon.time, None).is_ok());
+    }
+    info!("Replayed beacons"; decoded = tally.decoded, failed = tally.failed);
+    if tally.decoded + tally.failed == 0 {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, "No BOTAN beacon lines found").into());
+    }
+    Ok(tally.result()?)
+}
+
+/// Print synthetic beacons from a simulated satellite, by default one every 30 s until stopped
+pub fn run_simulate(
+    args: SimulateArgs,
+    printer: &BeaconPrinter,
+    table: &CalibrationTable,
+    stats: &mut Recorder,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.interval <= 0.0 || !args.interval.is_finite() {
+        return Err("Interval must be positive".into());
+    }
+    if args.speed <= 0.0 || !args.speed.is_finite() {
+        return Err("Speed must be positive".into());
+    }
+    if !(0.0..=1.0).contains(&args.bit_error_rate) {
+        return Err("Bit error rate must be between 0 and 1".into());
+    }
+    let seed = args.seed.unwrap_or_else(Rng::seed_from_time);
+    info!("Simulating beacons"; seed = seed, interval_s = args.interval);
+    let mut simulator = Simulator::new(seed, args.bit_error_rate, *table);
+
+    let start = timeutil::now_unix();
+    let mut sent = 0;
+    while args.count.is_none_or(|count| sent < count) && !daemon::stop_requested() {
+        let time = start + sent as f64 * args.interval;
+        if !args.no_wait {
+            // Sleep in short steps to notice a stop request
+            let due = start + sent as f64 * args.interval / args.speed;
+            while timeutil::now_unix() < due && !daemon::stop_requested() {
+                thread::sleep(Duration::from_secs_f64((due - timeutil::now_unix()).clamp(0.0, 1.0)));
+                stats.heartbeat();
+            }
+        }
+        let line = simulator.next_line(args.interval);
+        let result = printer.print_line(&line, None, Some(time), None);
+        stats.record(&result);
+        sent += 1;
+    }
+    info!("Stopped: {}", stats.run);
+    Ok(())
+}
+
+/// Report the live receivers' lifetime and per-pass decode counts
+pub fn run_stats(args: StatsArgs, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if args.reset {
+        match std::fs::remove_file(path) {
+            Ok(()) => println!("Removed {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => println!("No statistics recorded yet"),
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(());
+    }
+
+    let lifetime = Lifetime::load(path).map_err(ExitError::io)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&lifetime)?);
+        return Ok(());
+    }
+    let Some(since) = &lifetime.since else {
+        println!("No statistics recorded yet in {}", path.display());
+        return Ok(());
+    };
+
+    let total = &lifetime.total;
+    println!("Decode statistics since {}", since);
+    println!("  Attempted:   {}", total.attempted);
+    println!(
+        "  Decoded:     {} ({:.1}%)",
+        total.decoded,
+        100.0 * total.decoded as f64 / total.attempted.max(1) as f64
+    );
+    println!("  Duplicates:  {}", total.duplicates);
+    println!("  Failed:      {}", total.failed_total());
+    for (category, count) in &total.failed {
+        println!("    {:<12}{}", format!("{}:", category), count);
+    }
+
+    if !lifetime.passes.is_empty() && args.passes > 0 {
+        println!();
+        println!("{:<22}{:>10}{:>9}{:>8}{:>12}", "Pass (AOS)", "Attempted", "Decoded", "Failed", "Duplicates");
+        let skip = lifetime.passes.len().saturating_sub(args.passes);
+        for (aos, pass) in lifetime.passes.iter().skip(skip) {
+            println!(
+                "{:<22}{:>10}{:>9}{:>8}{:>12}",
+                aos,
+                pass.attempted,
+                pass.decoded,
+                pass.failed_total(),
+                pass.duplicates
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Convert between a telemetry channel's raw byte and its engineering value
+pub fn run_convert(args: ConvertArgs, table: &CalibrationTable) -> Result<(), Box<dyn std::error::Error>> {
+    let channel = args.channel;
+    let show = |raw: u8| -> Result<String, String> {
+        Ok(format!("{} (0x{:02X}) = {:.3} {}", raw, raw, table.value(channel, raw)?, channel.unit()))
+    };
+    if args.raw {
+        let text = args.value.trim();
+        let raw = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => text.parse(),
+        }
+        .map_err(|_| ExitError::new(exit_code::PARSE_ERROR, format!("Invalid raw byte '{}'. Expected 0-255 or 0x00-0xFF", text)))?;
+        println!("{} {}", channel.name(), show(raw)?);
+    } else {
+        let value: f64 = args
+            .value
+            .parse()
+            .map_err(|_| ExitError::new(exit_code::PARSE_ERROR, format!("Invalid value '{}'", args.value)))?;
+        let raw = table
+            .raw(channel, value)
+            .ok_or_else(|| ExitError::new(exit_code::PARSE_ERROR, format!("Invalid value '{}'", args.value)))?;
+        println!("{} {} {} -> {}", channel.name(), value, channel.unit(), show(raw)?);
+    }
+    Ok(())
+}
+
+/// Save or otherwise handle the active morse table
+pub fn run_mappings(args: MappingsArgs, decoder: &crate::BotanDecoder) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        MappingsAction::Export { file } => {
+            decoder.save_to_file(&file).map_err(ExitError::io)?;
+            info!("Saved mappings"; file = file, count = decoder.mappings().len());
+        }
+    }
+    Ok(())
+}
+
+/// Send rounds of morse, read the operator's copy of each from stdin and score it
+pub fn run_practice(args: PracticeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.wpm <= 0.0 {
+        return Err("WPM must be positive".into());
+    }
+    if args.length == 0 {
+        return Err("Length must be at least 1".into());
+    }
+    let seed = args.seed.unwrap_or_else(Rng::seed_from_time);
+    let mut practice = Practice::new(args.drill, args.length, seed);
+    let decoder = crate::BotanDecoder::new();
+    let config = CwSynthConfig { wpm: args.wpm, pitch_hz: args.pitch, ..Default::default() };
+    let sound = std::env::temp_dir().join(format!("botan_cw_decoder_practice_{}.wav", process::id()));
+
+    let mut score = Score::default();
+    let mut lines = std::io::stdin().lines();
+    for round in 1..=args.count {
+        let text = practice.next_text();
+        let morse = decoder.encode(&text)?;
+        println!("Round {}/{}", round, args.count);
+        if args.print {
+            println!("{}", morse);
+        } else {
+            wav::write_wav_mono16(&sound.to_string_lossy(), config.sample_rate, &cw_synth::synthesize(&morse, &config))?;
+            alert::play_sound(&sound).map_err(|e| format!("{}; use --print to practice from printed morse", e))?;
+        }
+        print!("Copy: ");
+        std::io::stdout().flush()?;
+        let Some(copy) = lines.next().transpose()? else {
+            println!();
+            break;
+        };
+        let mistakes = practice::mistakes(&text, &copy);
+        score.record(&text, mistakes);
+        match mistakes {
+            0 => println!("Correct"),
+            n => println!("Sent: {}  ({} mistake{})", text, n, if n == 1 { "" } else { "s" }),
+        }
+        println!();
+    }
+    let _ = std::fs::remove_file(&sound);
+    println!(
+        "Score: {} of {} rounds perfect, {:.0}% of characters copied",
+        score.perfect,
+        score.rounds,
+        score.accuracy()
+    );
+    Ok(())
+}
+
+/// Print the step-by-step explanation of one housekeeping beacon
+pub fn run_explain(args: ExplainArgs, printer: &BeaconPrinter) -> Result<(), Box<dyn std::error::Error>> {
+    // The beacon may be given quoted or as separate words
+    let mut line = args.beacon.join(" ").trim().to_ascii_uppercase();
+    if !line.starts_with("BOTAN") {
+        line = format!("BOTAN JS1YPT {}", line);
+    }
+    match printer.parse_frame(&line, None) {
+        Ok(Frame::Housekeeping(data)) => println!("{}", printer.explain(&data)),
+        Ok(Frame::Typed(typed)) => {
+            return Err(ExitError::new(exit_code::PARSE_ERROR, format!("Only housekeeping frames can be explained, not {}", typed.frame_type)).into())
+        }
+        Err(error) => return Err(ExitError::new(exit_code::PARSE_ERROR, error).into()),
+    }
+    Ok(())
+}
+
+/// Report the problems of each configuration file; fails when any file has errors
+pub fn run_check(args: CheckArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed = 0;
+    for file in &args.files {
+        let (kind, report) = match check::check_file(Path::new(file), args.kind) {
+            Ok(checked) => checked,
+            Err(error) => {
+                eprintln!("{}: {}", file, error);
+                failed += 1;
+                continue;
+            }
+        };
+        if report.errors.is_empty() && report.warnings.is_empty() {
+            println!("{}: OK ({})", file, kind);
+            continue;
+        }
+        println!("{}: {} error(s), {} warning(s) ({})", file, report.errors.len(), report.warnings.len(), kind);
+        for error in &report.errors {
+            println!("  error: {}", error);
+        }
+        for warning in &report.warnings {
+            println!("  warning: {}", warning);
+        }
+        failed += usize::from(!report.errors.is_empty());
+    }
+    if failed > 0 {
+        return Err(ExitError::new(exit_code::FAILURE, format!("{} of {} file(s) failed the check", failed, args.files.len())).into());
+    }
+    Ok(())
+}
+
+/// Print the JSON Schema of decoded frames, or the protobuf definitions
+pub fn run_schema(args: SchemaArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.proto {
+        print!("{}", proto::DEFINITION);
+    } else if args.grpc {
+        print!("{}", grpc::DEFINITION);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&schema::schema())?);
+    }
+    Ok(())
+}
+
+/// Print the value of each requested field of a raw frame
+pub fn run_extract(args: ExtractArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let hex = args.frame.split_whitespace().last().unwrap_or_default();
+    if hex.is_empty() || !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ExitError::new(exit_code::PARSE_ERROR, format!("Invalid frame '{}'. Expected an even number of hex digits", hex)).into());
+    }
+    let frame: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+    for field in &args.fields {
+        let value = field.extract(&frame).map_err(|e| ExitError::new(exit_code::PARSE_ERROR, e))?;
+        println!("{:<44} {}", field.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Load the TLE and observer location when a TLE was given on the command line
+fn station(args: &StationArgs) -> Result<Option<(Tle, Observer)>, Box<dyn std::error::Error>> {
+    let flag = match (&args.tle, args.auto_tle) {
+        (Some(_), _) => "--tle",
+        (None, true) => "--auto-tle",
+        (None, false) => return Ok(None),
+    };
+    // Checked before a download that would be of no use
+    let (Some(lat), Some(lon)) = (args.lat, args.lon) else {
+        return Err(format!("--lat and --lon are required with {}", flag).into());
+    };
+    let tle = match &args.tle {
+        Some(path) => Tle::from_file(path)?,
+        None => TleCache::new(&args.tle_url, TleCache::default_path()).load().map_err(ExitError::io)?,
+    };
+    warn_if_stale(&tle);
+    Ok(Some((tle, Observer { latitude_deg: lat, longitude_deg: lon, altitude_m: args.alt })))
+}
+
+fn warn_if_stale(tle: &Tle) {
+    let age = tle.age_days(timeutil::now_unix());
+    if age > tle_cache::STALE_AGE_DAYS {
+        warn!(
+            "TLE epoch is {:.1} days old; update it (e.g. with update-tle) for accurate passes and Doppler",
+            age
+        );
+    }
+}
+
+/// Build a Doppler tracker when a TLE was given on the command line
+fn doppler_tracker(args: &StationArgs, freq_hz: f64) -> Result<Option<DopplerTracker>, Box<dyn std::error::Error>> {
+    let Some((tle, observer)) = station(args)? else {
+        return Ok(None);
+    };
+    let tracker = DopplerTracker::new(&tle, observer, freq_hz)?;
+
+    let now = timeutil::now_unix();
+    let look = tracker.look_angles(now)?;
+    info!(
+        "Doppler tracking NORAD {} with TLE epoch {} ({:.1} days old)",
+        tle.norad_id,
+        timeutil::format_display(tle.epoch_unix),
+        tle.age_days(now)
+    );
+    info!(
+        "BOTAN now at AZ {:.1}° EL {:.1}°, range {:.0} km, shift {:+.0} Hz",
+        look.azimuth_deg,
+        look.elevation_deg,
+        look.range_km,
+        tracker.shift_hz(now)?
+    );
+    Ok(Some(tracker))
+}
+
+/// Illumination and orbit number of the tracked satellite right now
+fn orbit_now(tracker: Option<&DopplerTracker>) -> Result<Option<OrbitState>, String> {
+    tracker.map(|t| t.orbit_state(timeutil::now_unix())).transpose()
+}
+
+/// Print the beacons `codec` decodes from `pending`, leaving an incomplete last line there
+/// unless the input has `ended`
+fn print_framed(codec: &mut BeaconCodec, pending: &mut Vec<u8>, ended: bool, printer: &BeaconPrinter, stats: &mut Recorder) {
+    let mut start = 0;
+    loop {
+        let (used, beacon) = if ended { codec.decode_eof(&pending[start..]) } else { codec.decode(&pending[start..]) };
+        start += used;
+        match beacon {
+            // Printed from its line like beacons from any other source
+            Some(Ok(beacon)) => {
+                let mut line = Vec::new();
+                codec.encode(&beacon, &mut line);
+                let line = String::from_utf8_lossy(&line);
+                stats.record(&printer.print_line(line.trim_end(), None, Some(timeutil::now_unix()), None));
+            }
+            Some(Err(error)) => {
+                warn!("Line dropped"; error = error);
+                stats.record(&Err(error));
+            }
+            None => break,
+        }
+    }
+    pending.drain(..start);
+}
+
+/// Parse and print beacons found by a live source
+fn print_beacons(
+    beacons: &[String],
+    copy: Option<f64>,
+    orbit: Option<OrbitState>,
+    printer: &BeaconPrinter,
+    stats: &mut Recorder,
+) {
+    let now = timeutil::now_unix();
+    for beacon in beacons {
+        stats.record(&printer.print_line(beacon, copy, Some(now), orbit));
+    }
+}
@@ -0,0 +1,146 @@
+// Confidence in a decoded frame, from 0 to 1, so consumers can weight a dubious copy differently
+// from a solid one. It is the mean of whichever of these are known:
+//   signal        the SNR of a calibrated signal report, 0 at 0 dB rising to 1 at 15 dB
+//   copy          the share of characters the CW demodulator read with no dot, dash or gap
+//                 near its decision threshold
+//   plausibility  the share of telemetry values within what the hardware can report; for a
+//                 typed frame, whether its integrity check passed
+//   agreement     for housekeeping, how closely the frame matches the most similar recent one:
+//                 analog bytes within a few counts and status bytes equal
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::botan_parser::{BotanBeaconData, BotanRSSI};
+use crate::frames::Frame;
+
+const FULL_SNR_DB: f64 = 15.0;
+const RECENT_FRAMES: usize = 8;
+const ANALOG_TOLERANCE: u8 = 8;    // Counts an analog byte may drift between beacons
+
+/// Physically possible range of each analog value, in V, mA and °C
+const PLAUSIBLE: [(f64, f64); 5] = [
+    (2.5, 4.5),          // BAT_V: a single Li-ion cell
+    (-2000.0, 2000.0),   // BAT_I
+    (-40.0, 85.0),       // BAT_T
+    (-40.0, 85.0),       // BPB_T
+    (0.0, 2000.0),       // RAW_I
+];
+
+/// The score and the components it was computed from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Confidence {
+    pub score: f64,
+    pub signal: Option<f64>,
+    pub copy: Option<f64>,
+    pub plausibility: Option<f64>,
+    pub agreement: Option<f64>,
+}
+
+impl Confidence {
+    /// Combine the known components; None when nothing is known
+    fn new(signal: Option<f64>, copy: Option<f64>, plausibility: Option<f64>, agreement: Option<f64>) -> Option<Self> {
+        let known: Vec<f64> = [signal, copy, plausibility, agreement].into_iter().flatten().collect();
+        if known.is_empty() {
+            return None;
+        }
+        let score = known.iter().sum::<f64>() / known.len() as f64;
+        Some(Confidence { score, signal, copy, plausibility, agreement })
+    }
+}
+
+/// Scores frames, remembering recent housekeeping frames to compare new ones with
+#[derive(Debug, Default)]
+pub struct Assessor {
+    recent: VecDeque<[u8; 8]>,
+}
+
+impl Assessor {
+    /// Fill in the confidence of `frame`; `copy` comes from the CW demodulator, when it was used
+    pub fn assess(&mut self, frame: &mut Frame, copy: Option<f64>) {
+        match frame {
+            Frame::Housekeeping(data) => {
+                let agreement = self.recent.iter().map(|recent| agreement(recent, &data.frame)).reduce(f64::max);
+                data.confidence = Confidence::new(signal(&data.rssi), copy, Some(plausibility(data)), agreement);
+                if self.recent.len() == RECENT_FRAMES {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(data.frame);
+            }
+            Frame::Typed(typed) => {
+                let plausibility = typed.check.as_ref().map(|check| if check.passed { 1.0 } else { 0.0 });
+                typed.confidence = Confidence::new(signal(&typed.rssi), copy, plausibility, None);
+            }
+        }
+    }
+}
+
+fn signal(rssi: &Option<BotanRSSI>) -> Option<f64> {
+    let snr = rssi.as_ref()?.snr_db?;
+    Some((snr / FULL_SNR_DB).clamp(0.0, 1.0))
+}
+
+fn plausibility(data: &BotanBeaconData) -> f64 {
+    let t = &data.telemetry;
+    let values = [t.bat_v, t.bat_i, t.bat_t, t.bpb_t, t.raw_i];
+    let plausible = values.iter().zip(PLAUSIBLE).filter(|(value, (min, max))| (min..=max).contains(value)).count();
+    plausible as f64 / values.len() as f64
+}
+
+/// Share of the bytes of `frame` that agree with `recent`
+fn agreement(recent: &[u8; 8], frame: &[u8; 8]) -> f64 {
+    let analog = (0..PLAUSIBLE.len()).filter(|&i| recent[i].abs_diff(frame[i]) <= ANALOG_TOLERANCE).count();
+    let status = (PLAUSIBLE.len()..8).filter(|&i| recent[i] == frame[i]).count();
+    (analog + status) as f64 / 8.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::{parse_botan_beacon_with, SignalCalibration};
+    use crate::calibration::CalibrationTable;
+
+    fn housekeeping(line: &str) -> Frame {
+        let calibration: SignalCalibration =
+            serde_json::from_str(r#"{"rssi": {"slope": -0.5}, "snr": {"slope": 0.25}}"#).unwrap();
+        Frame::Housekeeping(parse_botan_beacon_with(line, &calibration, &CalibrationTable::default()).unwrap())
+    }
+
+    fn confidence(frame: &Frame) -> Confidence {
+        match frame {
+            Frame::Housekeeping(data) => data.confidence.unwrap(),
+            Frame::Typed(typed) => typed.confidence.unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_assess() {
+        let mut assessor = Assessor::default();
+        // SNR 0x28 * 0.25 = 10 dB, all values plausible, nothing to compare with yet
+        let mut frame = housekeeping("BOTAN JS1YPT SI8628 A67C8D5E2AA13608");
+        assessor.assess(&mut frame, Some(0.9));
+        let first = confidence(&frame);
+        assert_eq!((first.signal, first.copy, first.plausibility, first.agreement), (Some(10.0 / 15.0), Some(0.9), Some(1.0), None));
+        assert!((first.score - (10.0 / 15.0 + 0.9 + 1.0) / 3.0).abs() < 1e-9);
+
+        // BAT_V drifted a little, and a flag byte changed
+        let mut frame = housekeeping("BOTAN JS1YPT A87C8D5E2AA13609");
+        assessor.assess(&mut frame, None);
+        assert_eq!(confidence(&frame).agreement, Some(7.0 / 8.0));
+        assert_eq!(confidence(&frame).score, (1.0 + 7.0 / 8.0) / 2.0);
+
+        // A garbled copy: BAT_V 0 V and RAW_I below zero are impossible
+        let mut frame = housekeeping("BOTAN JS1YPT 007C8D5E00A13608");
+        assessor.assess(&mut frame, None);
+        assert_eq!(confidence(&frame).plausibility, Some(3.0 / 5.0));
+        assert!(confidence(&frame).score < first.score);
+    }
+
+    #[test]
+    fn test_agreement() {
+        let frame = [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08];
+        assert_eq!(agreement(&frame, &frame), 1.0);
+        assert_eq!(agreement(&frame, &[0xAE, 0x74, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08]), 1.0);
+        assert_eq!(agreement(&frame, &[0x00, 0x7C, 0x8D, 0x5E, 0x2A, 0xA0, 0x36, 0x08]), 0.75);
+    }
+}
@@ -0,0 +1,234 @@
+// Defaults for command line options from ~/.config/botan_cw_decoder/config.toml.
+// Every key names a long option (`tle_url` or `tle-url`); keys at the top level or in
+// [station] / [receiver] / [display] apply to every command that has that option, keys in a section
+// named after a subcommand (e.g. [schedule]) only to that command. Flags on the command
+// line always win.
+use std::path::PathBuf;
+
+use clap::Command;
+
+/// One `key = value` line of the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+}
+
+/// Parsed config file: the flat subset of TOML the options need
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub entries: Vec<ConfigEntry>,
+}
+
+// Sections that group shared options rather than naming a subcommand
+const SHARED_SECTIONS: [&str; 4] = ["", "station", "receiver", "display"];
+
+impl Config {
+    /// $XDG_CONFIG_HOME/botan_cw_decoder/config.toml, or under ~/.config
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("botan_cw_decoder").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location if it exists
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read config '{}': {}", path.display(), e))?;
+        Config::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        let mut section = String::new();
+        for (idx, raw) in content.lines().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected 'key = value'", idx + 1))?;
+            entries.push(ConfigEntry {
+                section: section.clone(),
+                key: key.trim().replace('-', "_"),
+                value: parse_value(value.trim()).map_err(|e| format!("line {}: {}", idx + 1, e))?,
+                line: idx + 1,
+            });
+        }
+        Ok(Config { entries })
+    }
+
+    /// Install the config values as option defaults, so the command line can still override them
+    pub fn apply(&self, mut command: Command) -> Result<Command, String> {
+        for entry in &self.entries {
+            let mut used = false;
+            if SHARED_SECTIONS.contains(&entry.section.as_str()) && has_arg(&command, &entry.key) {
+                command = command.mut_arg(entry.key.as_str(), |arg| arg.default_value(entry.value.clone()));
+                used = true;
+            }
+            let subcommands: Vec<String> = command
+                .get_subcommands()
+                .filter(|sub| SHARED_SECTIONS.contains(&entry.section.as_str()) || sub.get_name() == entry.section)
+                .filter(|sub| has_arg(sub, &entry.key))
+                .map(|sub| sub.get_name().to_string())
+                .collect();
+            for name in subcommands {
+                command = command.mut_subcommand(name, |sub| {
+                    sub.mut_arg(entry.key.as_str(), |arg| arg.default_value(entry.value.clone()))
+                });
+                used = true;
+            }
+            if !used {
+                return Err(format!(
+                    "line {}: unknown option '{}'{}",
+                    entry.line,
+                    entry.key,
+                    if entry.section.is_empty() { String::new() } else { format!(" in [{}]", entry.section) }
+                ));
+            }
+        }
+        Ok(command)
+    }
+}
+
+fn has_arg(command: &Command, id: &str) -> bool {
+    command.get_arguments().any(|arg| arg.get_id() == id)
+}
+
+/// Drop a trailing # comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (idx, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Strings (basic or literal), numbers and booleans, returned as option text
+fn parse_value(value: &str) -> Result<String, String> {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return Ok(inner.to_string());
+    }
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        let mut out = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(c @ ('"' | '\\')) => out.push(c),
+                other => return Err(format!("unsupported escape '\\{}'", other.map(String::from).unwrap_or_default())),
+            }
+        }
+        return Ok(out);
+    }
+    if value == "true" || value == "false" || value.replace('_', "").parse::<f64>().is_ok() {
+        return Ok(value.replace('_', ""));
+    }
+    Err(format!("unsupported value '{}' (use a quoted string, number or boolean)", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Command as CliCommand};
+    use clap::{CommandFactory, FromArgMatches};
+
+    const CONFIG: &str = r#"
+# Ground station defaults
+signal-calibration = "signal.json"
+
+[station]
+lat = 35.68   # Tokyo
+lon = 139.77
+auto_tle = true
+
+[receiver]
+freq = 437_375_000
+rigctld = 'localhost:4532'
+
+[schedule]
+output_dir = "/var/lib/botan # passes"
+min_elevation = 10
+"#;
+
+    fn parse_with(config: &str, args: &[&str]) -> Cli {
+        let command = Config::parse(config).unwrap().apply(Cli::command()).unwrap();
+        let matches = command.try_get_matches_from(args).unwrap();
+        Cli::from_arg_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.entries.len(), 8);
+        assert_eq!(config.entries[0].key, "signal_calibration");
+        assert_eq!(config.entries[1].section, "station");
+        assert_eq!(config.entries[1].value, "35.68");
+        assert_eq!(config.entries[4].value, "437375000");
+        assert_eq!(config.entries[6].value, "/var/lib/botan # passes");
+        assert!(Config::parse("lat = tokyo").is_err());
+        assert!(Config::parse("lat").is_err());
+    }
+
+    #[test]
+    fn test_config_defaults_and_overrides() {
+        let cli = parse_with(CONFIG, &["botan_cw_decoder", "schedule", "--min-elevation", "20"]);
+        assert_eq!(cli.signal_calibration.as_deref(), Some("signal.json"));
+        let Some(CliCommand::Schedule(args)) = cli.command else { panic!("expected schedule") };
+        assert_eq!(args.receiver.station.lat, Some(35.68));
+        assert!(args.receiver.station.auto_tle);
+        assert_eq!(args.receiver.freq, Some(437375000.0));
+        assert_eq!(args.output_dir, "/var/lib/botan # passes");
+        assert_eq!(args.min_elevation, 20.0);
+
+        // [schedule] does not leak into other commands
+        let cli = parse_with(CONFIG, &["botan_cw_decoder", "passes"]);
+        let Some(CliCommand::Passes(args)) = cli.command else { panic!("expected passes") };
+        assert_eq!(args.min_elevation, 0.0);
+        assert_eq!(args.station.lon, Some(139.77));
+
+        let cli = parse_with("[display]\nlang = 'ja'\ntemperature_unit = 'f'", &["botan_cw_decoder"]);
+        assert_eq!(cli.lang, crate::i18n::Lang::Ja);
+        assert_eq!(cli.temperature_unit, crate::units::TemperatureUnit::Fahrenheit);
+    }
+
+    #[test]
+    fn test_config_path_arg() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(crate::cli::config_path_arg(&args(&["x", "passes", "--config", "a.toml"])).as_deref(), Some("a.toml"));
+        assert_eq!(crate::cli::config_path_arg(&args(&["x", "--config=b.toml"])).as_deref(), Some("b.toml"));
+        assert_eq!(crate::cli::config_path_arg(&args(&["x", "--", "--config", "c"])), None);
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        let config = Config::parse("[station]\nlatitude = 35.0").unwrap();
+        let error = config.apply(Cli::command()).unwrap_err();
+        assert!(error.contains("unknown option 'latitude' in [station]"));
+    }
+}
@@ -60,7 +60,7 @@ mod tests {
 
     #[test]
     fn test_custom_decoder() {
-        let decoder = create_custom_decoder();
+        let _decoder = create_custom_decoder();
         // Add tests for your custom patterns here
     }
 
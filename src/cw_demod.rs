@@ -0,0 +1,515 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::iq::Complex;
+use crate::logging::{debug, trace};
+use crate::morse_trie::MorseStream;
+
+/// Mixes the beacon carrier down to DC, decimates and produces a narrowband envelope
+pub struct Channelizer {
+    sample_rate: f64,
+    phase: f64,
+    phase_step: f64,
+    decimation: usize,
+    acc: Complex,
+    acc_count: usize,
+    window: VecDeque<Complex>,
+    window_sum: Complex,
+    window_len: usize,
+    output_rate: f64,
+}
+
+impl Channelizer {
+    /// `offset_hz` is the carrier (or audio tone) frequency relative to the input's DC
+    pub fn new(sample_rate: f64, offset_hz: f64, bandwidth_hz: f64) -> Self {
+        let decimation = ((sample_rate / (4.0 * bandwidth_hz)).floor() as usize).max(1);
+        let output_rate = sample_rate / decimation as f64;
+        let window_len = ((output_rate / bandwidth_hz).round() as usize).max(1);
+
+        Channelizer {
+            sample_rate,
+            phase: 0.0,
+            phase_step: -2.0 * PI * offset_hz / sample_rate,
+            decimation,
+            acc: Complex::default(),
+            acc_count: 0,
+            window: VecDeque::with_capacity(window_len),
+            window_sum: Complex::default(),
+            window_len,
+            output_rate,
+        }
+    }
+
+    /// Sample rate of the envelope produced by `process`
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Retune the mixer (e.g. for Doppler) without disturbing the filter state
+    pub fn set_offset(&mut self, offset_hz: f64) {
+        self.phase_step = -2.0 * PI * offset_hz / self.sample_rate;
+    }
+
+    pub fn process(&mut self, input: &[Complex], out: &mut Vec<f32>) {
+        for &sample in input {
+            let lo = Complex::new(self.phase.cos() as f32, self.phase.sin() as f32);
+            self.phase = (self.phase + self.phase_step) % (2.0 * PI);
+
+            // Integrate-and-dump decimation
+            self.acc = self.acc + sample * lo;
+            self.acc_count += 1;
+            if self.acc_count < self.decimation {
+                continue;
+            }
+            let scale = 1.0 / self.decimation as f32;
+            let decimated = Complex::new(self.acc.re * scale, self.acc.im * scale);
+            self.acc = Complex::default();
+            self.acc_count = 0;
+
+            // Moving average narrows the channel down to the detection bandwidth
+            self.window.push_back(decimated);
+            self.window_sum = self.window_sum + decimated;
+            if self.window.len() > self.window_len {
+                let oldest = self.window.pop_front().unwrap_or_default();
+                self.window_sum = self.window_sum - oldest;
+            }
+            out.push(self.window_sum.norm() / self.window.len() as f32);
+        }
+    }
+
+    /// Real audio input (e.g. receiver sidetone) treated as complex with zero imaginary part
+    pub fn process_audio(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let samples: Vec<Complex> = input.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        self.process(&samples, out);
+    }
+}
+
+/// A key-down (mark) or key-up (space) period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keying {
+    pub key_down: bool,
+    pub seconds: f64,
+}
+
+/// Turns an envelope into mark/space periods using adaptive peak and noise-floor tracking
+pub struct KeyingDetector {
+    sample_rate: f64,
+    peak: f32,
+    floor: f32,
+    key_down: bool,
+    run: usize,
+    candidate: usize,
+    debounce: usize,
+    peak_decay: f32,
+    floor_rise: f32,
+}
+
+impl KeyingDetector {
+    pub fn new(sample_rate: f64) -> Self {
+        KeyingDetector {
+            sample_rate,
+            peak: 0.0,
+            floor: f32::MAX,
+            key_down: false,
+            run: 0,
+            candidate: 0,
+            debounce: ((0.008 * sample_rate).round() as usize).max(1),
+            // Roughly 2 s and 0.5 s time constants
+            peak_decay: 1.0 / (2.0 * sample_rate) as f32,
+            floor_rise: 1.0 / (0.5 * sample_rate) as f32,
+        }
+    }
+
+    pub fn process(&mut self, envelope: &[f32], out: &mut Vec<Keying>) {
+        for &x in envelope {
+            if x > self.peak {
+                self.peak = x;
+            } else {
+                self.peak -= (self.peak - x) * self.peak_decay;
+            }
+            if x < self.floor {
+                self.floor = x;
+            } else {
+                self.floor += (x - self.floor) * self.floor_rise;
+            }
+
+            // Require ~6 dB between signal and noise before keying is believed
+            let span = self.peak - self.floor;
+            let detectable = self.peak > self.floor * 2.0 && span > 1e-6;
+            let threshold = if self.key_down {
+                self.floor + 0.4 * span
+            } else {
+                self.floor + 0.6 * span
+            };
+            let wants_down = detectable && x > threshold;
+
+            self.run += 1;
+            if wants_down != self.key_down {
+                self.candidate += 1;
+                if self.candidate >= self.debounce {
+                    let seconds = (self.run - self.candidate) as f64 / self.sample_rate;
+                    if seconds > 0.0 {
+                        out.push(Keying { key_down: self.key_down, seconds });
+                    }
+                    self.key_down = wants_down;
+                    self.run = self.candidate;
+                    self.candidate = 0;
+                }
+            } else {
+                self.candidate = 0;
+            }
+        }
+    }
+
+    /// Emit the final run, treating end of input as key-up
+    pub fn finish(&mut self, out: &mut Vec<Keying>) {
+        if self.run > 0 {
+            out.push(Keying { key_down: self.key_down, seconds: self.run as f64 / self.sample_rate });
+        }
+        self.run = 0;
+        self.candidate = 0;
+        self.key_down = false;
+    }
+}
+
+/// Classifies mark/space timing into dots, dashes and gaps with an adaptive dit estimate
+pub struct MorseAssembler {
+    dit: f64,
+    pending_element: bool,
+    ambiguous_char: bool,    // An element or gap of the current character was near the threshold
+    chars: usize,            // Characters completed since the last `take_copy`
+    ambiguous_chars: usize,
+}
+
+impl MorseAssembler {
+    /// `wpm_hint` seeds the dit estimate; it adapts to the actual keying speed
+    pub fn new(wpm_hint: f64) -> Self {
+        MorseAssembler { dit: 1.2 / wpm_hint, pending_element: false, ambiguous_char: false, chars: 0, ambiguous_chars: 0 }
+    }
+
+    /// Current speed estimate in words per minute
+    pub fn wpm(&self) -> f64 {
+        1.2 / self.dit
+    }
+
+    /// Share of the characters completed since the last call that had no element or gap within
+    /// half a dit of the 2-dit dot/dash and element/character threshold
+    pub fn take_copy(&mut self) -> Option<f64> {
+        let copy = (self.chars > 0).then(|| 1.0 - self.ambiguous_chars as f64 / self.chars as f64);
+        self.chars = 0;
+        self.ambiguous_chars = 0;
+        copy
+    }
+
+    pub fn push(&mut self, keying: Keying, out: &mut String) {
+        let near_threshold = (1.5..2.5).contains(&(keying.seconds / self.dit));
+        if keying.key_down {
+            if keying.seconds < 0.3 * self.dit {
+                return; // Noise spike
+            }
+            self.ambiguous_char |= near_threshold;
+            if keying.seconds < 2.0 * self.dit {
+                out.push('.');
+                self.dit = 0.8 * self.dit + 0.2 * keying.seconds;
+            } else {
+                out.push('-');
+                self.dit = 0.8 * self.dit + 0.2 * keying.seconds / 3.0;
+            }
+            self.pending_element = true;
+        } else if self.pending_element {
+            self.ambiguous_char |= near_threshold;
+            if keying.seconds >= 2.0 * self.dit {
+                self.chars += 1;
+                self.ambiguous_chars += self.ambiguous_char as usize;
+                self.ambiguous_char = false;
+            }
+            if keying.seconds >= 5.0 * self.dit {
+                out.push_str("  ");
+                self.pending_element = false;
+            } else if keying.seconds >= 2.0 * self.dit {
+                out.push(' ');
+                self.pending_element = false;
+            }
+        }
+    }
+}
+
+/// Complete CW demodulator from baseband samples to dot/dash text
+pub struct CwDemodulator {
+    channelizer: Channelizer,
+    detector: KeyingDetector,
+    assembler: MorseAssembler,
+    envelope: Vec<f32>,
+    keying: Vec<Keying>,
+}
+
+impl CwDemodulator {
+    pub fn new(sample_rate: f64, offset_hz: f64, bandwidth_hz: f64, wpm_hint: f64) -> Self {
+        let channelizer = Channelizer::new(sample_rate, offset_hz, bandwidth_hz);
+        let detector = KeyingDetector::new(channelizer.output_rate());
+        CwDemodulator {
+            channelizer,
+            detector,
+            assembler: MorseAssembler::new(wpm_hint),
+            envelope: Vec::new(),
+            keying: Vec::new(),
+        }
+    }
+
+    pub fn wpm(&self) -> f64 {
+        self.assembler.wpm()
+    }
+
+    /// Share of the characters since the last call read without ambiguity, see `MorseAssembler`
+    pub fn take_copy(&mut self) -> Option<f64> {
+        self.assembler.take_copy()
+    }
+
+    /// Move the detection channel to a new carrier/tone offset
+    pub fn set_offset(&mut self, offset_hz: f64) {
+        self.channelizer.set_offset(offset_hz);
+    }
+
+    /// Feed IQ samples, appending any newly completed morse symbols to `out`
+    pub fn process_iq(&mut self, input: &[Complex], out: &mut String) {
+        self.envelope.clear();
+        self.channelizer.process(input, &mut self.envelope);
+        self.assemble(out);
+    }
+
+    /// Feed real audio samples, appending any newly completed morse symbols to `out`
+    pub fn process_audio(&mut self, input: &[f32], out: &mut String) {
+        self.envelope.clear();
+        self.channelizer.process_audio(input, &mut self.envelope);
+        self.assemble(out);
+    }
+
+    pub fn finish(&mut self, out: &mut String) {
+        self.keying.clear();
+        self.detector.finish(&mut self.keying);
+        for keying in self.keying.drain(..) {
+            self.assembler.push(keying, out);
+        }
+        self.assembler.push(Keying { key_down: false, seconds: f64::MAX }, out);
+    }
+
+    fn assemble(&mut self, out: &mut String) {
+        self.keying.clear();
+        self.detector.process(&self.envelope, &mut self.keying);
+        for keying in self.keying.drain(..) {
+            self.assembler.push(keying, out);
+        }
+    }
+}
+
+/// Decode demodulated morse to text, substituting '*' for patterns the decoder does not know
+pub fn morse_to_text(morse: &str, decoder: &crate::BotanDecoder) -> String {
+    let mut text = String::with_capacity(morse.len() / 2);
+    for word in morse.split("  ") {
+        let start = text.len();
+        if start > 0 {
+            text.push(' ');
+        }
+        let letters = text.len();
+        for pattern in word.split(' ').filter(|s| !s.is_empty()) {
+            if decoder.decode_into(pattern, &mut text).is_err() {
+                text.push('*');
+            }
+        }
+        // Words without a pattern leave no gap
+        if text.len() == letters {
+            text.truncate(start);
+        }
+    }
+    text
+}
+
+/// Incrementally assembles beacon lines from a live morse stream, one completed word at a time
+pub struct BeaconScanner {
+    stream: MorseStream,
+    partial: String,
+    words: VecDeque<String>,
+}
+
+impl BeaconScanner {
+    pub fn new() -> Self {
+        BeaconScanner {
+            stream: crate::BotanDecoder::new().stream(),
+            partial: String::new(),
+            words: VecDeque::new(),
+        }
+    }
+
+    /// Feed newly demodulated morse, returning any beacons completed by it
+    pub fn push(&mut self, morse: &str) -> Vec<String> {
+        let mut text = String::new();
+        self.stream.push_str(morse, &mut text);
+        let mut beacons = Vec::new();
+        for c in text.chars() {
+            if c != ' ' {
+                self.partial.push(c);
+            } else if let Some(beacon) = self.complete_word() {
+                beacons.push(beacon);
+            }
+        }
+        beacons
+    }
+
+    /// Feed text already decoded elsewhere, e.g. by a skimmer, whose words are all complete
+    pub fn push_words(&mut self, text: &str) -> Vec<String> {
+        let mut beacons = Vec::new();
+        for word in text.split_whitespace() {
+            self.partial = word.to_ascii_uppercase();
+            beacons.extend(self.complete_word());
+        }
+        beacons
+    }
+
+    /// Flush the trailing word at end of signal
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut text = String::new();
+        self.stream.finish(&mut text);
+        self.partial.push_str(&text);
+        let beacon = self.complete_word();
+        self.words.clear();
+        beacon.into_iter().collect()
+    }
+
+    fn complete_word(&mut self) -> Option<String> {
+        let word = std::mem::take(&mut self.partial);
+        if word.is_empty() {
+            return None;
+        }
+        trace!("word"; text = word);
+        if word == "BOTAN" {
+            self.words.clear();
+        }
+        self.words.push_back(word);
+        // The longest beacon is BOTAN JS1YPT SIxxxx <data>
+        while self.words.len() > 4 {
+            self.words.pop_front();
+        }
+
+        let start = self.words.iter().position(|w| w == "BOTAN")?;
+        let candidate = self.words.iter().skip(start).cloned().collect::<Vec<_>>().join(" ");
+        if crate::botan_parser::parse_botan_beacon(&candidate).is_ok() {
+            debug!("beacon complete"; text = candidate);
+            self.words.clear();
+            return Some(candidate);
+        }
+        None
+    }
+}
+
+impl Default for BeaconScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cw_synth::{self, CwSynthConfig};
+
+    fn synth_beacon(wpm: f64) -> (String, Vec<f32>, f64) {
+        let text = "BOTAN JS1YPT A67C8D5E2AA13608";
+        let morse = crate::BotanDecoder::new().encode(text).unwrap();
+        let config = CwSynthConfig { wpm, ..Default::default() };
+        let audio = cw_synth::synthesize(&morse, &config)
+            .into_iter()
+            .map(|s| s as f32 / 32768.0)
+            .collect();
+        (morse, audio, config.sample_rate as f64)
+    }
+
+    #[test]
+    fn test_demodulate_audio() {
+        let (morse, audio, rate) = synth_beacon(20.0);
+        let mut demod = CwDemodulator::new(rate, 700.0, 200.0, 20.0);
+        let mut out = String::new();
+        demod.process_audio(&audio, &mut out);
+        demod.finish(&mut out);
+
+        assert_eq!(out.trim(), morse);
+        let text = morse_to_text(&out, &crate::BotanDecoder::new());
+        assert_eq!(text, "BOTAN JS1YPT A67C8D5E2AA13608");
+        // Clean keying leaves no character in doubt
+        assert_eq!(demod.take_copy(), Some(1.0));
+        assert_eq!(demod.take_copy(), None);
+    }
+
+    #[test]
+    fn test_ambiguous_characters() {
+        let mut assembler = MorseAssembler::new(20.0);
+        let dit = 0.06;
+        let mut out = String::new();
+        // "E", a 2.2-dit mark read as "T" though close to a dot, and "A"
+        for (key_down, dits) in [(true, 1.0), (false, 3.0), (true, 2.2), (false, 3.0), (true, 1.0), (false, 1.0), (true, 3.0), (false, 7.0)] {
+            assembler.push(Keying { key_down, seconds: dits * dit }, &mut out);
+        }
+        assert_eq!(out, ". - .-  ");
+        assert_eq!(assembler.take_copy(), Some(1.0 - 1.0 / 3.0));
+        // A character is only counted once its gap ends it
+        assembler.push(Keying { key_down: true, seconds: dit }, &mut out);
+        assembler.push(Keying { key_down: false, seconds: 1.7 * dit }, &mut out);
+        assert_eq!(assembler.take_copy(), None);
+    }
+
+    #[test]
+    fn test_demodulate_iq_with_offset_and_speed_change() {
+        let (morse, audio, rate) = synth_beacon(12.0);
+        // Shift the 700 Hz tone up to 1.3 kHz above centre and add a little noise
+        let mut seed = 1u32;
+        let iq: Vec<Complex> = audio
+            .iter()
+            .enumerate()
+            .map(|(n, &a)| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (seed >> 16) as f32 / 65536.0 - 0.5;
+                let phase = 2.0 * PI * 600.0 * n as f64 / rate;
+                let shift = Complex::new(phase.cos() as f32, phase.sin() as f32);
+                Complex::new(a, 0.0) * shift + Complex::new(0.1 * noise, 0.0)
+            })
+            .collect();
+
+        let mut demod = CwDemodulator::new(rate, 1300.0, 200.0, 20.0);
+        let mut out = String::new();
+        for block in iq.chunks(1000) {
+            demod.process_iq(block, &mut out);
+        }
+        demod.finish(&mut out);
+
+        assert_eq!(out.trim(), morse);
+        assert!((demod.wpm() - 12.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_beacon_scanner() {
+        let text = "VVV BOTAN JS1YPT SI8640 A67C8D5E2AA13608 BOTAN JS1YPT A57EB76823210E08";
+        let morse = crate::BotanDecoder::new().encode(text).unwrap();
+        let mut scanner = BeaconScanner::new();
+        let mut beacons = Vec::new();
+        for chunk in morse.as_bytes().chunks(7) {
+            beacons.extend(scanner.push(std::str::from_utf8(chunk).unwrap()));
+        }
+        beacons.extend(scanner.finish());
+
+        assert_eq!(beacons, vec![
+            "BOTAN JS1YPT SI8640 A67C8D5E2AA13608",
+            "BOTAN JS1YPT A57EB76823210E08",
+        ]);
+
+        // Words decoded elsewhere arrive whole, split anywhere across spots
+        let mut scanner = BeaconScanner::new();
+        assert!(scanner.push_words("CQ botan JS1YPT").is_empty());
+        assert_eq!(scanner.push_words("A67C8D5E2AA13608"), ["BOTAN JS1YPT A67C8D5E2AA13608"]);
+    }
+
+    #[test]
+    fn test_morse_to_text_marks_unknown() {
+        let decoder = crate::BotanDecoder::new();
+        assert_eq!(morse_to_text("... ........ ...", &decoder), "S*S");
+        assert_eq!(morse_to_text("  -... ---    .- -.  ", &decoder), "BO AN");
+    }
+}
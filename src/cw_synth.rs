@@ -0,0 +1,201 @@
+use std::f64::consts::PI;
+
+use crate::botan_parser::{parse_botan_beacon_with, SignalCalibration};
+use crate::calibration::{CalibrationTable, Channel};
+
+/// Settings for the keyed CW sidetone
+#[derive(Debug, Clone)]
+pub struct CwSynthConfig {
+    pub wpm: f64,           // Words per minute (PARIS timing)
+    pub pitch_hz: f64,      // Sidetone frequency [Hz]
+    pub sample_rate: u32,   // Output sample rate [Hz]
+    pub amplitude: f64,     // Peak amplitude, 0.0 - 1.0
+    pub rise_time_ms: f64,  // Raised-cosine key shaping to avoid clicks [ms]
+}
+
+impl Default for CwSynthConfig {
+    fn default() -> Self {
+        CwSynthConfig {
+            wpm: 20.0,
+            pitch_hz: 700.0,
+            sample_rate: 8000,
+            amplitude: 0.8,
+            rise_time_ms: 5.0,
+        }
+    }
+}
+
+impl CwSynthConfig {
+    /// Length of one dit in seconds (1200 ms / WPM)
+    pub fn dit_seconds(&self) -> f64 {
+        1.2 / self.wpm
+    }
+}
+
+/// Synthesize a keyed sidetone from a dot/dash pattern string
+pub fn synthesize(morse: &str, config: &CwSynthConfig) -> Vec<i16> {
+    let dit = (config.dit_seconds() * config.sample_rate as f64).round() as usize;
+    let mut keying: Vec<(bool, usize)> = Vec::new();
+
+    // Leading and trailing silence keeps receivers from clipping the first element
+    keying.push((false, dit * 7));
+    for (word_idx, word) in morse.split("  ").enumerate() {
+        if word_idx > 0 {
+            keying.push((false, dit * 7));
+        }
+        for (char_idx, pattern) in word.split(' ').filter(|s| !s.is_empty()).enumerate() {
+            if char_idx > 0 {
+                keying.push((false, dit * 3));
+            }
+            for (elem_idx, element) in pattern.chars().enumerate() {
+                if elem_idx > 0 {
+                    keying.push((false, dit));
+                }
+                match element {
+                    '.' => keying.push((true, dit)),
+                    '-' => keying.push((true, dit * 3)),
+                    _ => {}
+                }
+            }
+        }
+    }
+    keying.push((false, dit * 7));
+
+    let total: usize = keying.iter().map(|(_, len)| len).sum();
+    let mut samples = Vec::with_capacity(total);
+    let ramp = ((config.rise_time_ms / 1000.0) * config.sample_rate as f64).round() as usize;
+    let omega = 2.0 * PI * config.pitch_hz / config.sample_rate as f64;
+    let peak = config.amplitude.clamp(0.0, 1.0) * i16::MAX as f64;
+
+    for (key_down, len) in keying {
+        let start = samples.len();
+        for i in 0..len {
+            if !key_down {
+                samples.push(0);
+                continue;
+            }
+            let envelope = shape_envelope(i, len, ramp);
+            let value = (omega * (start + i) as f64).sin() * envelope * peak;
+            samples.push(value.round() as i16);
+        }
+    }
+    samples
+}
+
+/// Raised-cosine rise and fall at both ends of a key-down element
+fn shape_envelope(i: usize, len: usize, ramp: usize) -> f64 {
+    let ramp = ramp.min(len / 2);
+    if ramp == 0 {
+        return 1.0;
+    }
+    let position = if i < ramp {
+        i
+    } else if i >= len - ramp {
+        len - 1 - i
+    } else {
+        return 1.0;
+    };
+    0.5 - 0.5 * (PI * position as f64 / ramp as f64).cos()
+}
+
+/// Build the beacon text for a hex frame, or pass through a full beacon line after validation
+pub fn beacon_text(input: &str, table: &CalibrationTable) -> Result<String, String> {
+    let input = input.trim().to_ascii_uppercase();
+    let text = if input.starts_with("BOTAN") {
+        input
+    } else {
+        format!("BOTAN JS1YPT {}", input)
+    };
+    parse_botan_beacon_with(&text, &SignalCalibration::default(), table)?;
+    Ok(text)
+}
+
+/// Telemetry to put in a synthesized beacon in place of the frame's own: engineering values of
+/// the analog channels in `Channel::ALL` order, and the three status bytes
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub values: [Option<f64>; 5],
+    pub status: [Option<u8>; 3],
+}
+
+/// The beacon text for `input`, a frame or beacon line, with `overrides` encoded by `table`.
+/// Without an input every analog channel needs a value; status bytes default to 0.
+pub fn beacon_with(input: Option<&str>, overrides: &Overrides, table: &CalibrationTable) -> Result<String, String> {
+    let (header, mut frame) = match input {
+        Some(input) => {
+            let text = beacon_text(input, table)?;
+            let frame = parse_botan_beacon_with(&text, &SignalCalibration::default(), table)?.frame;
+            let header = text.rsplit_once(' ').map_or("BOTAN JS1YPT", |(header, _)| header).to_string();
+            (header, frame)
+        }
+        None => {
+            let missing: Vec<String> = Channel::ALL
+                .into_iter()
+                .zip(overrides.values)
+                .filter(|(_, value)| value.is_none())
+                .map(|(channel, _)| format!("--{}", channel.name().to_ascii_lowercase().replace('_', "-")))
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("Give a frame, or a value for every channel (missing {})", missing.join(", ")));
+            }
+            ("BOTAN JS1YPT".to_string(), [0; 8])
+        }
+    };
+    for (channel, value) in Channel::ALL.into_iter().zip(overrides.values) {
+        if let Some(value) = value {
+            frame[channel as usize] = table
+                .raw(channel, value)
+                .ok_or_else(|| format!("Invalid {} value {}", channel.name(), value))?;
+        }
+    }
+    for (byte, status) in frame[5..].iter_mut().zip(overrides.status) {
+        *byte = status.unwrap_or(*byte);
+    }
+    let hex: String = frame.iter().map(|b| format!("{:02X}", b)).collect();
+    beacon_text(&format!("{} {}", header, hex), table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthesize_timing() {
+        let config = CwSynthConfig { wpm: 20.0, sample_rate: 1000, ..Default::default() };
+        // 60 ms dit at 20 WPM: "E" is 7 + 1 + 7 dits including padding
+        let samples = synthesize(".", &config);
+        assert_eq!(samples.len(), 15 * 60);
+        assert!(samples[..7 * 60].iter().all(|&s| s == 0));
+        assert!(samples[7 * 60..8 * 60].iter().any(|&s| s != 0));
+    }
+
+    #[test]
+    fn test_beacon_text() {
+        let table = CalibrationTable::default();
+        assert_eq!(beacon_text("a67c8d5e2aa13608", &table).unwrap(), "BOTAN JS1YPT A67C8D5E2AA13608");
+        assert!(beacon_text("A67C", &table).is_err());
+    }
+
+    #[test]
+    fn test_beacon_with() {
+        let table = CalibrationTable::default();
+        // Values replace the frame's bytes, the signal report stays
+        let overrides = Overrides { values: [Some(4.0), None, None, None, None], status: [None, None, Some(0x25)] };
+        let raw = table.raw(Channel::BatV, 4.0).unwrap();
+        assert_eq!(
+            beacon_with(Some("BOTAN JS1YPT SI7A34 A67C8D5E2AA13608"), &overrides, &table).unwrap(),
+            format!("BOTAN JS1YPT SI7A34 {:02X}7C8D5E2AA13625", raw)
+        );
+        // Without a frame every channel is needed
+        let error = beacon_with(None, &overrides, &table).unwrap_err();
+        assert!(error.contains("--bat-i, --bat-t, --bpb-t, --raw-i"), "{}", error);
+        let values = [4.1, -120.0, 18.0, 25.0, 230.0];
+        let overrides = Overrides { values: values.map(Some), status: [Some(0xA4), None, None] };
+        let text = beacon_with(None, &overrides, &table).unwrap();
+        let beacon = crate::botan_parser::parse_botan_beacon(&text).unwrap();
+        for (channel, value) in Channel::ALL.into_iter().zip(values) {
+            assert_eq!(beacon.frame[channel as usize], table.raw(channel, value).unwrap());
+        }
+        assert_eq!(beacon.frame[5..], [0xA4, 0, 0]);
+    }
+}
@@ -0,0 +1,109 @@
+// Running unattended as a service, e.g. under systemd. With --daemon, SIGTERM and SIGINT ask the
+// live receivers to stop instead of killing the process: they finish the block in hand, save a
+// pass in progress, and give the forwarder a last chance to deliver spooled frames before exit.
+// A PID file keeps a second instance from starting on the same station.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::exit_code::{self, ExitError};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Whether a stop signal has arrived
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// Turn SIGTERM and SIGINT into a stop request. Blocking reads are interrupted rather than
+/// restarted, so a receiver waiting for data notices promptly.
+#[cfg(unix)]
+pub fn handle_stop_signals() -> Result<(), String> {
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        // SAFETY: sigaction is plain data, and the handler only stores to an atomic
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) } != 0 {
+            return Err(format!("Cannot handle signal {}: {}", signal, std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn handle_stop_signals() -> Result<(), String> {
+    Ok(())
+}
+
+/// A file holding the process ID while it runs; removed when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Write our PID to `path`, unless it names a process that is still running
+    pub fn create(path: &Path) -> Result<Self, ExitError> {
+        if let Some(pid) = fs::read_to_string(path).ok().and_then(|s| s.trim().parse::<u32>().ok()) {
+            if pid != std::process::id() && is_running(pid) {
+                return Err(ExitError::new(exit_code::FAILURE, format!("Already running with PID {} (see '{}')", pid, path.display())));
+            }
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| ExitError::io(format!("Cannot create '{}': {}", dir.display(), e)))?;
+        }
+        fs::write(path, format!("{}\n", std::process::id())).map_err(|e| ExitError::io(format!("Cannot write '{}': {}", path.display(), e)))?;
+        Ok(PidFile { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists; EPERM means it does, under another user
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file() {
+        let dir = std::env::temp_dir().join(format!("botan_pid_test_{}", std::process::id()));
+        let path = dir.join("botan.pid");
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", std::process::id()));
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // A PID left behind by a crash is taken over
+        fs::write(&path, "999999999\n").unwrap();
+        let _pid_file = PidFile::create(&path).unwrap();
+        #[cfg(unix)]
+        {
+            // PID 1 always runs
+            fs::write(&path, "1\n").unwrap();
+            let error = PidFile::create(&path).unwrap_err();
+            assert!(error.message.starts_with("Already running with PID 1"));
+            assert_eq!(error.code, exit_code::FAILURE);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
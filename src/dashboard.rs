@@ -0,0 +1,420 @@
+// The live web dashboard for --dashboard: one self-contained page (web/dashboard.html, compiled
+// in) showing the latest telemetry, the flag states and the recent frames, which follows new
+// frames over a WebSocket (RFC 6455) at /stream. Each message is a frame's JSON object as
+// --webhook sends it, with a "limits" member naming the values near or beyond --limits. A browser
+// that connects is sent the last `HISTORY` frames first, so the page is filled in straight away,
+// and one more than `QUEUE` messages behind loses messages instead of holding up the decoder.
+// The server speaks just enough HTTP/1.1 for this: GET requests, one per connection.
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::frames::Frame;
+use crate::grsat;
+use crate::limits::{Limits, Severity};
+use crate::logging::{debug, info, warn};
+use crate::orbit::OrbitState;
+use crate::provenance::Provenance;
+use crate::timeutil;
+
+const PAGE: &str = include_str!("../web/dashboard.html");
+
+/// Frames a browser is sent when it connects
+const HISTORY: usize = 100;
+/// Messages queued for one browser before newer ones are dropped
+const QUEUE: usize = HISTORY + 100;
+/// Request headers, and messages from a browser, larger than this end the connection
+const MAX_REQUEST: usize = 1 << 14;
+
+// WebSocket opcodes
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+/// 160-bit SHA-1 (FIPS 180-4), which the WebSocket handshake needs
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut digest = [0; 20];
+    for (out, h) in digest.chunks_mut(4).zip(h) {
+        out.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char } else { '=' });
+        }
+    }
+    out
+}
+
+/// The Sec-WebSocket-Accept answer to a browser's Sec-WebSocket-Key
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key.trim()).as_bytes()))
+}
+
+/// A server-to-browser message, which is never masked
+fn encode_message(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => message.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            message.push(126);
+            message.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            message.push(127);
+            message.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Read one browser message, masked as browsers send them: its opcode and payload
+fn read_message(input: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    input.read_exact(&mut head)?;
+    let size = match head[1] & 0x7F {
+        126 => {
+            let mut size = [0; 2];
+            input.read_exact(&mut size)?;
+            u16::from_be_bytes(size) as u64
+        }
+        127 => {
+            let mut size = [0; 8];
+            input.read_exact(&mut size)?;
+            u64::from_be_bytes(size)
+        }
+        size => size as u64,
+    };
+    if size > MAX_REQUEST as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Message from browser too large"));
+    }
+    let mut mask = [0; 4];
+    if head[1] & 0x80 != 0 {
+        input.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; size as usize];
+    input.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((head[0] & 0x0F, payload))
+}
+
+/// The head of an HTTP request
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,   // Names in lower case
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP request");
+    let mut lines = Vec::new();
+    let mut size = 0;
+    loop {
+        let mut line = String::new();
+        size += reader.read_line(&mut line)?;
+        if line.is_empty() || size > MAX_REQUEST {
+            return Err(invalid());
+        }
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    let request_line = lines.first().ok_or_else(invalid)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().ok_or_else(invalid)?.to_string(), parts.next().ok_or_else(invalid)?.to_string());
+    let headers = lines[1..]
+        .iter()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    Ok(Request { method, path, headers })
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)
+}
+
+/// The browsers following /stream and the frames a new one is sent first
+#[derive(Default)]
+struct State {
+    browsers: Vec<(u64, SyncSender<Arc<Vec<u8>>>)>,
+    recent: VecDeque<Arc<Vec<u8>>>,
+    connected: u64,    // Browsers so far, numbering them
+}
+
+type Shared = Arc<Mutex<State>>;
+
+/// Answer one connection: the page, or the frame stream after a WebSocket handshake
+fn connect(mut stream: TcpStream, state: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_request(&mut reader)?;
+    if request.method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"Only GET is supported\n");
+    }
+    match request.path.split('?').next().unwrap_or_default() {
+        "/" | "/index.html" => return respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE.as_bytes()),
+        "/stream" => {}
+        _ => return respond(&mut stream, "404 Not Found", "text/plain", b"Not found\n"),
+    }
+    let key = match request.header("sec-websocket-key") {
+        Some(key) if request.header("upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) => key,
+        _ => return respond(&mut stream, "400 Bad Request", "text/plain", b"Expected a WebSocket upgrade\n"),
+    };
+    let handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    stream.write_all(handshake.as_bytes())?;
+    stream.set_read_timeout(None)?;
+
+    let peer = stream.peer_addr()?;
+    let (queue, messages) = mpsc::sync_channel::<Arc<Vec<u8>>>(QUEUE);
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for message in messages {
+            if writer.write_all(&message).is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(Shutdown::Both);
+    });
+    let id = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        for message in &state.recent {
+            let _ = queue.try_send(Arc::clone(message));
+        }
+        state.connected += 1;
+        let id = state.connected;
+        state.browsers.push((id, queue.clone()));
+        id
+    };
+    debug!("Dashboard browser connected"; peer = peer);
+    // Browsers only ever close the stream or ping it
+    let result = loop {
+        match read_message(&mut reader) {
+            Ok((CLOSE, _)) => break Ok(()),
+            Ok((PING, payload)) => {
+                let _ = queue.try_send(Arc::new(encode_message(PONG, &payload)));
+            }
+            Ok(_) => {}
+            Err(error) => break Err(error),
+        }
+    };
+    // Dropping its last queue stops its writer
+    state.lock().unwrap_or_else(|e| e.into_inner()).browsers.retain(|(browser, _)| *browser != id);
+    drop(queue);
+    let _ = stream.shutdown(Shutdown::Both);
+    debug!("Dashboard browser left"; peer = peer);
+    result
+}
+
+/// The dashboard's listening socket
+pub struct Dashboard {
+    state: Shared,
+    limits: Limits,
+}
+
+impl Dashboard {
+    /// Serve the dashboard on `addr`, e.g. 127.0.0.1:8080, in the background
+    pub fn start(addr: &str, limits: Limits) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+        info!("Serving the dashboard"; url = format!("http://{}/", listener.local_addr().map_or_else(|_| addr.to_string(), |a| a.to_string())));
+        Ok(Self::serve(listener, limits))
+    }
+
+    fn serve(listener: TcpListener, limits: Limits) -> Self {
+        let state = Shared::default();
+        let accepted = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = Arc::clone(&accepted);
+                        thread::spawn(move || {
+                            if let Err(error) = connect(stream, &state) {
+                                debug!("Dashboard connection dropped"; error = error);
+                            }
+                        });
+                    }
+                    Err(error) => warn!("Cannot accept dashboard connection"; error = error),
+                }
+            }
+        });
+        Dashboard { state, limits }
+    }
+
+    /// Show a decoded frame on every open page
+    pub fn publish(&self, frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>, provenance: &Provenance) {
+        let json = grsat::frame_json(frame, Some(time.unwrap_or_else(timeutil::now_unix)), orbit, Some(provenance));
+        let mut object: Value = match json.and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string())) {
+            Ok(object) => object,
+            Err(error) => {
+                warn!("Cannot show frame on the dashboard"; error = error);
+                return;
+            }
+        };
+        let mut limits = Map::new();
+        if let Frame::Housekeeping(data) = frame {
+            for (name, severity) in self.limits.severities(&data.telemetry) {
+                match severity {
+                    Severity::Alarm => limits.insert(name.to_string(), "alarm".into()),
+                    Severity::Warning => limits.insert(name.to_string(), "warning".into()),
+                    Severity::Normal => None,
+                };
+            }
+        }
+        object["limits"] = limits.into();
+        self.send(encode_message(TEXT, object.to_string().as_bytes()));
+    }
+
+    fn send(&self, message: Vec<u8>) {
+        let message = Arc::new(message);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.recent.len() == HISTORY {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(Arc::clone(&message));
+        state.browsers.retain(|(_, browser)| match browser.try_send(Arc::clone(&message)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                debug!("Dashboard browser too slow, frame dropped");
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_handshake() {
+        // RFC 6455 section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(base64(b"BOTAN"), "Qk9UQU4=");
+        assert_eq!(base64(b"JS1YPT"), "SlMxWVBU");
+
+        // A masked "Hello" from the RFC's examples, and long messages from the server
+        let mut input: &[u8] = &[0x81, 0x85, 0x37, 0xFA, 0x21, 0x3D, 0x7F, 0x9F, 0x4D, 0x51, 0x58];
+        assert_eq!(read_message(&mut input).unwrap(), (TEXT, b"Hello".to_vec()));
+        let long = encode_message(TEXT, &[b'x'; 300]);
+        assert_eq!(&long[..4], [0x81, 126, 1, 44]);
+        assert_eq!(read_message(&mut &long[..]).unwrap().1.len(), 300);
+    }
+
+    #[test]
+    fn test_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.3}}"#).unwrap();
+        let dashboard = Dashboard::serve(listener, limits);
+        let frame = crate::frames::parse_frame(
+            "BOTAN JS1YPT A67C8D5E2AA13608",
+            &Default::default(),
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        let provenance = Provenance::new("udp-audio 0.0.0.0:7355", "builtin", Vec::new());
+        dashboard.publish(&frame, Some(1714566896.0), None, &provenance);
+
+        let mut page = String::new();
+        let mut browser = TcpStream::connect(addr).unwrap();
+        browser.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        browser.read_to_string(&mut page).unwrap();
+        assert!(page.starts_with("HTTP/1.1 200 OK\r\n") && page.contains("new WebSocket"));
+
+        let mut browser = TcpStream::connect(addr).unwrap();
+        browser.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let request = "GET /stream HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+        browser.write_all(request.as_bytes()).unwrap();
+        let mut reader = BufReader::new(browser.try_clone().unwrap());
+        // A response head reads like a request's
+        let response = read_request(&mut reader).unwrap();
+        assert_eq!((response.method.as_str(), response.path.as_str()), ("HTTP/1.1", "101"));
+        assert_eq!(response.header("sec-websocket-accept"), Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        // The frame from before the browser connected, then a new one
+        let (opcode, payload) = read_message(&mut reader).unwrap();
+        assert_eq!(opcode, TEXT);
+        let json: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(json["frame"], "A67C8D5E2AA13608");
+        assert_eq!(json["limits"], serde_json::json!({"bat_v": "alarm"}));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while dashboard.state.lock().unwrap().browsers.is_empty() {
+            assert!(Instant::now() < deadline, "browser not seen");
+            thread::sleep(Duration::from_millis(10));
+        }
+        dashboard.publish(&frame, Some(1714566926.0), None, &provenance);
+        let json: Value = serde_json::from_slice(&read_message(&mut reader).unwrap().1).unwrap();
+        assert_eq!(json["time"], "2024-05-01T12:35:26Z");
+    }
+}
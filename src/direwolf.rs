@@ -0,0 +1,87 @@
+// The monitor output of Direwolf, as saved with `direwolf -T "%Y-%m-%d %H:%M:%S" > direwolf.log`.
+// Each received frame is one line, "[0.4 2024-05-01 12:34:56] JS1YPT>CQ,WIDE1-1*:<info>": the
+// channel and, with -T, the time in brackets, then the addresses and the information field with
+// unprintable bytes written as "<0xa6>". Everything else Direwolf prints (audio levels, decoded
+// APRS fields, status messages) is skipped.
+use crate::ax25::{Address, UiFrame};
+use crate::timeutil;
+
+const PID_NO_LAYER3: u8 = 0xF0;
+
+/// A frame read from the monitor output
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitored {
+    pub time: Option<f64>,    // None without -T, or when its format has no date
+    pub frame: UiFrame,
+}
+
+/// The frame a line of monitor output shows, if it shows one
+pub fn parse_line(line: &str) -> Option<Monitored> {
+    let rest = line.trim_start().strip_prefix('[')?;
+    let (label, rest) = rest.split_once("] ")?;
+    let (channel, stamp) = label.split_once(' ').unwrap_or((label, ""));
+    if !channel.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let time = timeutil::parse_utc(stamp).ok();
+
+    let (header, info) = rest.split_once(':')?;
+    let (source, path) = header.split_once('>')?;
+    let mut path = path.split(',').map(|address| address.trim_end_matches('*').parse::<Address>());
+    let destination = path.next()?.ok()?;
+    let digipeaters = path.collect::<Result<Vec<_>, _>>().ok()?;
+    let frame = UiFrame {
+        destination,
+        source: source.parse().ok()?,
+        digipeaters,
+        pid: PID_NO_LAYER3,
+        info: unescape(info),
+    };
+    Some(Monitored { time, frame })
+}
+
+/// The bytes of an information field, with Direwolf's "<0xa6>" escapes turned back into bytes
+fn unescape(info: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(info.len());
+    let mut rest = info;
+    while !rest.is_empty() {
+        let escaped = rest
+            .strip_prefix("<0x")
+            .and_then(|hex| Some((hex.get(..2)?, hex.get(2..)?.strip_prefix('>')?)))
+            .and_then(|(hex, after)| Some((u8::from_str_radix(hex, 16).ok()?, after)));
+        if let Some((byte, after)) = escaped {
+            bytes.push(byte);
+            rest = after;
+        } else {
+            let c = rest.chars().next().unwrap();
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let text = parse_line("[0.4 2024-05-01 12:34:56] JS1YPT-1>CQ,WIDE1-1*:BOTAN JS1YPT A67C8D5E2AA13608<0x0d>").unwrap();
+        assert_eq!(text.time, Some(1714566896.0));
+        assert_eq!(text.frame.source.to_string(), "JS1YPT-1");
+        assert_eq!(text.frame.digipeaters, [Address { call: "WIDE1".to_string(), ssid: 1 }]);
+        assert_eq!(text.frame.info, b"BOTAN JS1YPT A67C8D5E2AA13608\r");
+        assert_eq!(text.frame.beacon_line(), "BOTAN JS1YPT A67C8D5E2AA13608");
+
+        // A binary payload, without -T
+        let binary = parse_line("[0] JS1YPT>CQ:<0xa6>|<0x8d>^*<0xa1>6<0x08>").unwrap();
+        assert_eq!(binary.time, None);
+        assert_eq!(binary.frame.beacon_line(), "BOTAN JS1YPT A67C8D5E2AA13608");
+
+        assert_eq!(parse_line("[0.3] JA1XYZ>APRS::<0x3c>0x").unwrap().frame.info, b":<0x");
+        assert!(parse_line("JS1YPT audio level = 48(22/11)   [NONE]   ___|||||_").is_none());
+        assert!(parse_line("[ig] JS1YPT>CQ:BOTAN").is_none());
+        assert!(parse_line("[0.4] NOT A CALL>CQ:BOTAN").is_none());
+    }
+}
@@ -0,0 +1,103 @@
+// Process exit codes, so scripts can tell a bad frame from a broken setup:
+//   0 success
+//   1 usage, configuration or other errors
+//   2 parse error: no input could be decoded
+//   3 partial decode: some input was decoded, some failed (reported as warnings)
+//   4 I/O error: files, sockets, rigctld or TLE download
+//   5 forwarding failure: frames left in the spool at exit, or refused by a service
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub const FAILURE: i32 = 1;
+pub const PARSE_ERROR: i32 = 2;
+pub const PARTIAL_DECODE: i32 = 3;
+pub const IO_ERROR: i32 = 4;
+pub const FORWARD_FAILURE: i32 = 5;
+
+/// An error that ends the process with a specific exit code
+#[derive(Debug)]
+pub struct ExitError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl ExitError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        ExitError { code, message: message.into() }
+    }
+
+    /// Wrap an I/O failure reported as text, e.g. by the rigctld client
+    pub fn io(message: impl Into<String>) -> Self {
+        ExitError::new(IO_ERROR, message)
+    }
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ExitError {}
+
+/// Exit code for an error returned by a command
+pub fn for_error(error: &(dyn Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<ExitError>() {
+        error.code
+    } else if error.downcast_ref::<io::Error>().is_some() {
+        IO_ERROR
+    } else {
+        FAILURE
+    }
+}
+
+/// Counts decoded and failed inputs to pick between success, partial decode and parse error
+#[derive(Debug, Default)]
+pub struct DecodeTally {
+    pub decoded: usize,
+    pub failed: usize,
+}
+
+impl DecodeTally {
+    pub fn record(&mut self, decoded: bool) {
+        if decoded {
+            self.decoded += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+
+    pub fn result(&self) -> Result<(), ExitError> {
+        match (self.decoded, self.failed) {
+            (_, 0) => Ok(()),
+            (0, failed) => Err(ExitError::new(PARSE_ERROR, format!("None of {} input(s) could be decoded", failed))),
+            (decoded, failed) => Err(ExitError::new(
+                PARTIAL_DECODE,
+                format!("{} of {} input(s) could not be decoded", failed, decoded + failed),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let mut tally = DecodeTally::default();
+        assert!(tally.result().is_ok());
+        tally.record(false);
+        assert_eq!(tally.result().unwrap_err().code, PARSE_ERROR);
+        tally.record(true);
+        assert_eq!(tally.result().unwrap_err().code, PARTIAL_DECODE);
+
+        let io_error: Box<dyn Error> = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert_eq!(for_error(io_error.as_ref()), IO_ERROR);
+        let text: Box<dyn Error> = "bad option".into();
+        assert_eq!(for_error(text.as_ref()), FAILURE);
+        let rig: Box<dyn Error> = ExitError::io("rigctld closed the connection").into();
+        assert_eq!(for_error(rig.as_ref()), IO_ERROR);
+    }
+}
@@ -0,0 +1,160 @@
+// A walk through one beacon for newcomers, e.g. at school outreach events: the header, the
+// optional signal report, how each byte of the frame is read, the formula that turns it into a
+// value, and what that value says about the satellite's health. The health notes use typical
+// ranges for a small satellite's lithium-ion battery and electronics; values outside --limits,
+// when given, are pointed out as well. The text is English only.
+use std::fmt::Write as _;
+
+use crate::botan_parser::{BotanBeaconData, DisplayOptions};
+use crate::breakdown::BIT_FIELDS;
+use crate::calibration::Channel;
+use crate::i18n::Lang;
+
+/// "A6 = 10 × 16 + 6 = 166"
+fn hex_arithmetic(byte: u8) -> String {
+    format!("{:02X} = {} × 16 + {} = {}", byte, byte >> 4, byte & 0xF, byte)
+}
+
+/// What an analog value means for the satellite
+fn meaning(channel: Channel, value: f64) -> &'static str {
+    match channel {
+        Channel::BatV if value < 3.5 => "The battery is nearly empty. The satellite may switch things off to save power.",
+        Channel::BatV if value < 3.7 => "The battery is on the low side but still working.",
+        Channel::BatV if value <= 4.35 => "A healthy charge: a lithium-ion battery is full at about 4.2-4.3 V and nearly empty at 3.5 V.",
+        Channel::BatV => "More than a full lithium-ion battery should show; worth checking against other beacons.",
+        Channel::BatI if value > 50.0 => "Positive: the battery is charging, so the solar panels make more power than the satellite uses.",
+        Channel::BatI if value < -50.0 => "Negative: the battery is discharging, as it does in the Earth's shadow or under heavy load.",
+        Channel::BatI => "Close to zero: the solar panels just about cover what the satellite uses.",
+        Channel::BatT if value < 0.0 => "Cold. Batteries should not be charged below about 0 °C.",
+        Channel::BatT if value <= 40.0 => "A comfortable temperature for the battery.",
+        Channel::BatT => "Warm. Batteries age faster above about 40 °C.",
+        Channel::BpbT if value < -20.0 => "Very cold for electronics.",
+        Channel::BpbT if value <= 60.0 => "Within the usual range for electronics in orbit.",
+        Channel::BpbT => "Very hot for electronics.",
+        Channel::RawI => "This is how much current the whole satellite draws; it rises when a mission or the transmitter runs.",
+    }
+}
+
+/// What a status bit field means for the satellite
+fn flag_meaning(name: &str, value: u8) -> Option<&'static str> {
+    let on = value != 0;
+    Some(match name {
+        "power_5v0" if !on => "The 5 V line is off, so the equipment it feeds is unpowered.",
+        "power_depant" if on => "The antenna deployment line is powered, which is normally only needed right after launch.",
+        "power_com" if !on => "The transponder is switched off.",
+        "kill_sw" if on => "The KILL switch is set: the ground has commanded the transmitter to stop.",
+        "mis_error_flag" if on => "The last mission reported an error.",
+        "cmd_uplink_counter" => "Counts commands received from ground stations; it starts again at 0 after 7.",
+        "reserve_cmd_counter" => "Counts commands stored to run later; it starts again at 0 after 7.",
+        "kill_counter" if value > 0 => "The KILL switch has been used before.",
+        _ => return None,
+    })
+}
+
+/// The step-by-step explanation of `data`
+pub fn explain(data: &BotanBeaconData, options: &DisplayOptions) -> String {
+    let table = &options.calibration;
+    let t = &data.telemetry;
+    let mut out = String::new();
+    let hex: String = data.frame.iter().map(|b| format!("{:02X}", b)).collect();
+
+    writeln!(out, "Step 1: The header").unwrap();
+    writeln!(out, "--------------").unwrap();
+    writeln!(out, "{:<6}  The satellite's name, sent first so listeners know what they hear.", data.satellite_name).unwrap();
+    writeln!(out, "{:<6}  Its amateur radio call sign. Every amateur station must identify itself.", data.call_sign).unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "Step 2: The signal report").unwrap();
+    writeln!(out, "--------------").unwrap();
+    match &data.rssi {
+        Some(rssi) => {
+            writeln!(out, "SI{:02X}{:02X}  \"SI\" and two bytes of signal information.", rssi.raw_rssi, rssi.raw_snr).unwrap();
+            let rssi_value = rssi.rssi_dbm.map_or("a raw count".to_string(), |dbm| format!("{:.1} dBm", dbm));
+            let snr_value = rssi.snr_db.map_or("a raw count".to_string(), |db| format!("{:.1} dB", db));
+            writeln!(out, "  {}  Received signal strength (RSSI): {}", hex_arithmetic(rssi.raw_rssi), rssi_value).unwrap();
+            writeln!(out, "  {}  Signal-to-noise ratio (SNR): {}", hex_arithmetic(rssi.raw_snr), snr_value).unwrap();
+        }
+        None => writeln!(out, "This beacon has no SI field. When present, it follows the call sign as \"SI\" and two bytes of signal information.").unwrap(),
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "Step 3: The telemetry frame {}", hex).unwrap();
+    writeln!(out, "--------------").unwrap();
+    writeln!(out, "16 hex digits are 8 bytes. A hex digit counts from 0 to F (15), so two make a byte from 0 to 255.").unwrap();
+    let values = [t.bat_v, t.bat_i, t.bat_t, t.bpb_t, t.raw_i];
+    for (idx, channel) in Channel::ALL.into_iter().enumerate() {
+        let raw = data.frame[idx];
+        writeln!(out).unwrap();
+        writeln!(out, "Byte {}: {}  ({})", idx + 1, hex_arithmetic(raw), channel.name()).unwrap();
+        let formula = table.formula(channel).replace("raw", &raw.to_string());
+        writeln!(out, "  {} = {:.3} {}", formula, values[idx], channel.unit()).unwrap();
+        writeln!(out, "  {}", meaning(channel, values[idx])).unwrap();
+    }
+    for idx in Channel::ALL.len()..8 {
+        let raw = data.frame[idx];
+        writeln!(out).unwrap();
+        writeln!(out, "Byte {}: {} = {:08b} in binary; each group of bits is its own field", idx + 1, hex_arithmetic(raw), raw).unwrap();
+        for field in BIT_FIELDS.iter().filter(|f| f.byte == idx && f.name != "-") {
+            let value = field.value(&data.frame);
+            let shown = match field.name {
+                "current_mis" => options.codes.mission(value, Lang::En.labels()),
+                _ if field.width() == 1 => if value == 1 { "yes" } else { "no" }.to_string(),
+                _ => value.to_string(),
+            };
+            let bits = format!("{:0width$b}", value, width = field.width() as usize);
+            writeln!(out, "  {:<3}  {}: {}", bits, field.description, shown).unwrap();
+            if let Some(text) = flag_meaning(field.name, value) {
+                writeln!(out, "       {}", text).unwrap();
+            }
+        }
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "Step 4: Health").unwrap();
+    writeln!(out, "--------------").unwrap();
+    let panels = [t.data1.sap_x_pos, t.data1.sap_y_pos, t.data1.sap_y_neg, t.data1.sap_z_pos, t.data1.sap_z_neg];
+    match panels.iter().filter(|&&on| on).count() {
+        0 => writeln!(out, "- No solar panel is generating: the satellite is probably in the Earth's shadow.").unwrap(),
+        n => writeln!(out, "- {} solar panel{} facing the Sun: the satellite is in sunlight.", n, if n == 1 { " is" } else { "s are" }).unwrap(),
+    }
+    let power = if t.bat_i > 50.0 { "charging" } else if t.bat_i < -50.0 { "discharging" } else { "steady" };
+    writeln!(out, "- The battery is at {:.2} V and {}.", t.bat_v, power).unwrap();
+    if t.data2.kill_sw || !t.data1.power_5v0 || t.data3.mis_error_flag {
+        writeln!(out, "- Some status bits need attention; see their notes above.").unwrap();
+    } else {
+        writeln!(out, "- The status bits look normal.").unwrap();
+    }
+    let alarms = options.limits.alarms(t);
+    if !alarms.is_empty() {
+        writeln!(out, "- Outside the configured limits: {}.", alarms.join(", ")).unwrap();
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_explain() {
+        let data = parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap();
+        let text = explain(&data, &DisplayOptions::default());
+        assert!(text.contains("  86 = 8 × 16 + 6 = 134  Received signal strength (RSSI): a raw count\n"));
+        assert!(text.contains("Byte 1: A6 = 10 × 16 + 6 = 166  (BAT_V)\n  166 * 0.025781 = 4.280 V\n  A healthy charge"));
+        assert!(text.contains("Byte 2: 7C = 7 × 16 + 12 = 124  (BAT_I)\n  124 * -50.045 + 6330.4 = 124.820 mA\n  Positive"));
+        assert!(text.contains("  011  Reserved commands: 3\n"));
+        assert!(text.contains("  00   Current mission (0 none, 1 Earth, 2 Sun): None\n"));
+        assert!(text.contains("- 1 solar panel is facing the Sun"));
+        assert!(!text.contains("configured limits"));
+
+        let limits = serde_json::from_str(r#"{"bat_v": {"min": 4.5}}"#).unwrap();
+        let options = DisplayOptions { limits, ..Default::default() };
+        let data = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2A000108").unwrap();
+        let text = explain(&data, &options);
+        assert!(text.contains("This beacon has no SI field."));
+        assert!(text.contains("KILL switch is set"));
+        assert!(text.contains("- No solar panel is generating"));
+        assert!(text.ends_with("- Outside the configured limits: bat_v."));
+    }
+}
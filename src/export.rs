@@ -0,0 +1,323 @@
+// Export of the decode history kept by `schedule`, one row per received line of every pass.json
+// under the pass directory. The columns are fixed and always written in the same order, so a
+// spreadsheet or script can rely on them; a value a row does not have is left empty. Values are
+// taken as stored at reception, in V, mA and °C, and lines that failed to decode are exported
+// with their error. Parquet files carry the same columns with their types.
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+#[cfg(feature = "arrow")]
+use crate::arrow;
+use crate::parquet::{self, Cell, ColumnType};
+use crate::scheduler;
+use crate::timeutil;
+
+/// Table formats `export` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    Parquet,
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+#[cfg(feature = "arrow")]
+const FORMATS: &str = "csv, tsv, parquet or arrow";
+#[cfg(not(feature = "arrow"))]
+const FORMATS: &str = "csv, tsv or parquet";
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "tsv" => Ok(ExportFormat::Tsv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(ExportFormat::Arrow),
+            _ => Err(format!("Unknown export format '{}'. Expected {}", s, FORMATS)),
+        }
+    }
+}
+
+/// Column names and where their values are in a decoded frame of pass.json
+const FRAME_COLUMNS: [(&str, &str); 27] = [
+    ("frame", "/frame"),
+    ("raw_rssi", "/rssi/raw_rssi"),
+    ("raw_snr", "/rssi/raw_snr"),
+    ("rssi_dbm", "/rssi/rssi_dbm"),
+    ("snr_db", "/rssi/snr_db"),
+    ("bat_v", "/telemetry/bat_v"),
+    ("bat_i", "/telemetry/bat_i"),
+    ("bat_t", "/telemetry/bat_t"),
+    ("bpb_t", "/telemetry/bpb_t"),
+    ("raw_i", "/telemetry/raw_i"),
+    ("power_5v0", "/telemetry/data1/power_5v0"),
+    ("power_depant", "/telemetry/data1/power_depant"),
+    ("power_com", "/telemetry/data1/power_com"),
+    ("sap_x_pos", "/telemetry/data1/sap_x_pos"),
+    ("sap_y_pos", "/telemetry/data1/sap_y_pos"),
+    ("sap_y_neg", "/telemetry/data1/sap_y_neg"),
+    ("sap_z_pos", "/telemetry/data1/sap_z_pos"),
+    ("sap_z_neg", "/telemetry/data1/sap_z_neg"),
+    ("reserve_cmd_counter", "/telemetry/data2/reserve_cmd_counter"),
+    ("cmd_uplink_counter", "/telemetry/data2/cmd_uplink_counter"),
+    ("kill_sw", "/telemetry/data2/kill_sw"),
+    ("kill_counter", "/telemetry/data3/kill_counter"),
+    ("mission_pic_on", "/telemetry/data3/mission_pic_on"),
+    ("mis_error_flag", "/telemetry/data3/mis_error_flag"),
+    ("mis_end_flag", "/telemetry/data3/mis_end_flag"),
+    ("aprs_flag", "/telemetry/data3/aprs_flag"),
+    ("current_mis", "/telemetry/data3/current_mis"),
+];
+
+/// Every column in order
+pub fn columns() -> Vec<&'static str> {
+    let mut columns = vec!["time", "pass_aos", "frame_type"];
+    columns.extend(FRAME_COLUMNS.iter().map(|(name, _)| *name));
+    columns.extend(["fields", "check_passed", "confidence", "illumination", "orbit", "observer", "error", "text"]);
+    columns
+}
+
+/// The Parquet type of a column
+pub fn column_type(name: &str) -> ColumnType {
+    match name {
+        "time" | "pass_aos" => ColumnType::Timestamp,
+        "raw_rssi" | "raw_snr" | "reserve_cmd_counter" | "cmd_uplink_counter" | "kill_counter" | "current_mis" | "orbit" => ColumnType::Int32,
+        "rssi_dbm" | "snr_db" | "bat_v" | "bat_i" | "bat_t" | "bpb_t" | "raw_i" | "confidence" => ColumnType::Double,
+        "frame_type" | "frame" | "fields" | "illumination" | "observer" | "error" | "text" => ColumnType::Text,
+        _ => ColumnType::Boolean,
+    }
+}
+
+/// A table value as a typed Parquet cell; empty values are null
+fn cell(value: &str, kind: ColumnType) -> Option<Cell> {
+    if value.is_empty() {
+        return None;
+    }
+    match kind {
+        ColumnType::Boolean => value.parse().ok().map(Cell::Boolean),
+        ColumnType::Int32 => value.parse().ok().map(Cell::Int32),
+        ColumnType::Double => value.parse().ok().map(Cell::Double),
+        ColumnType::Text => Some(Cell::Text(value.to_string())),
+        ColumnType::Timestamp => timeutil::parse_utc(value).ok().map(|time| Cell::Timestamp((time * 1000.0).round() as i64)),
+    }
+}
+
+/// One row per beacon of one saved pass, received between `from` and `to`
+pub fn pass_rows(pass: &Value, from: Option<f64>, to: Option<f64>) -> Vec<Vec<String>> {
+    let text = |value: Option<&Value>| match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    };
+    let mut rows = Vec::new();
+    for beacon in pass["beacons"].as_array().into_iter().flatten() {
+        let time = beacon["time"].as_str().and_then(|t| timeutil::parse_utc(t).ok());
+        let in_range = |time: f64| from.is_none_or(|from| time >= from) && to.is_none_or(|to| time < to);
+        if !time.is_some_and(in_range) {
+            continue;
+        }
+
+        let (frame, frame_type) = match (&beacon["data"], &beacon["typed"]) {
+            (data, _) if data.is_object() => (data, Value::from("housekeeping")),
+            (_, typed) if typed.is_object() => (typed, typed["frame_type"].clone()),
+            _ => (&Value::Null, Value::Null),
+        };
+        let mut row = vec![text(beacon.get("time")), text(pass.get("aos")), text(Some(&frame_type))];
+        row.extend(FRAME_COLUMNS.iter().map(|(_, pointer)| text(frame.pointer(pointer))));
+        let fields: Vec<String> = frame["fields"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|field| format!("{}={}", text(field.get("name")), text(field.get("value"))))
+            .collect();
+        row.push(fields.join(";"));
+        row.push(text(frame.pointer("/check/passed")));
+        row.push(text(frame.pointer("/confidence/score")));
+        row.push(text(beacon.get("illumination")));
+        row.push(text(beacon.get("orbit")));
+        row.push(text(beacon.get("observer")));
+        row.push(text(beacon.get("error")));
+        row.push(text(beacon.get("text")));
+        rows.push(row);
+    }
+    rows
+}
+
+/// Quote a CSV value when it holds a separator, quote or line break
+pub(crate) fn csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_row(out: &mut impl Write, format: ExportFormat, row: &[impl AsRef<str>]) -> io::Result<()> {
+    let values: Vec<String> = match format {
+        ExportFormat::Csv => row.iter().map(|v| csv_value(v.as_ref())).collect(),
+        // Tabs and line breaks cannot be quoted in TSV
+        ExportFormat::Tsv => row.iter().map(|v| v.as_ref().replace(['\t', '\n', '\r'], " ")).collect(),
+        _ => unreachable!("binary formats are written as typed cells"),
+    };
+    let separator = if format == ExportFormat::Csv { "," } else { "\t" };
+    writeln!(out, "{}", values.join(separator))
+}
+
+/// Call `visit` with every pass under `dir` in the current schema, in reception order
+pub fn for_each_pass(dir: &Path, mut visit: impl FnMut(Value) -> Result<(), String>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Cannot read '{}': {}", dir.display(), e))?;
+    let mut passes: Vec<_> = entries.filter_map(|entry| Some(entry.ok()?.path().join("pass.json"))).filter(|p| p.is_file()).collect();
+    // Directory names are AOS times, so this is reception order
+    passes.sort();
+    for path in passes {
+        visit(scheduler::load_pass(&path)?)?;
+    }
+    Ok(())
+}
+
+/// Call `visit` with the row of every beacon of every pass under `dir` received between `from`
+/// and `to` (exclusive), in reception order
+pub fn for_each_row(
+    dir: &Path,
+    from: Option<f64>,
+    to: Option<f64>,
+    mut visit: impl FnMut(Vec<String>) -> Result<(), String>,
+) -> Result<(), String> {
+    for_each_pass(dir, |pass| pass_rows(&pass, from, to).into_iter().try_for_each(&mut visit))
+}
+
+/// Write the beacons of every pass under `dir` received between `from` and `to` (exclusive);
+/// returns the number of rows. Parquet is written once every row is read, since its column
+/// chunks follow one another
+pub fn export(dir: &Path, from: Option<f64>, to: Option<f64>, format: ExportFormat, out: &mut impl Write) -> Result<usize, String> {
+    let write_error = |e: io::Error| format!("Cannot write export: {}", e);
+    let columns: Vec<(&str, ColumnType)> = columns().into_iter().map(|name| (name, column_type(name))).collect();
+    let typed = |row: Vec<String>| row.iter().zip(&columns).map(|(value, (_, kind))| cell(value, *kind)).collect();
+    if matches!(format, ExportFormat::Csv | ExportFormat::Tsv) {
+        write_row(out, format, &columns.iter().map(|(name, _)| name).collect::<Vec<_>>()).map_err(write_error)?;
+    }
+    #[cfg(feature = "arrow")]
+    let mut stream = match format {
+        ExportFormat::Arrow => Some(arrow::StreamWriter::start(&columns, out).map_err(write_error)?),
+        _ => None,
+    };
+    let mut table = Vec::new();
+    let mut count = 0;
+    for_each_row(dir, from, to, |row| {
+        match format {
+            ExportFormat::Parquet => table.push(typed(row)),
+            #[cfg(feature = "arrow")]
+            ExportFormat::Arrow => stream.as_mut().unwrap().push(typed(row), out).map_err(write_error)?,
+            _ => {
+                // Text tables show times in the display zone; typed columns are instants anyway
+                let mut row = row;
+                for value in &mut row[..2] {
+                    *value = timeutil::display_text(value);
+                }
+                write_row(out, format, &row).map_err(write_error)?
+            }
+        }
+        count += 1;
+        Ok(())
+    })?;
+    if format == ExportFormat::Parquet {
+        parquet::write(&columns, &table, out).map_err(write_error)?;
+    }
+    #[cfg(feature = "arrow")]
+    if let Some(stream) = stream {
+        stream.finish(out).map_err(write_error)?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::frames::Frame;
+    use crate::orbit::{Illumination, OrbitState, Pass};
+    use crate::scheduler::{save_pass, PassRecord};
+
+    #[test]
+    fn test_export_passes() {
+        let root = std::env::temp_dir().join(format!("botan_export_test_{}", std::process::id()));
+        for (aos, lines) in [
+            (1714566896.0, vec!["BOTAN JS1YPT SI8640 A67C8D5E2AA13608", "BOTAN JS1YPT A67C"]),
+            (1714653296.0, vec!["BOTAN JS1YPT A57EB76823210E08"]),
+        ] {
+            let pass = Pass {
+                aos_unix: aos,
+                los_unix: aos + 600.0,
+                max_elevation_unix: aos + 300.0,
+                max_elevation_deg: 42.5,
+                aos_azimuth_deg: 10.0,
+                los_azimuth_deg: 170.0,
+            };
+            let mut record = PassRecord::new(&pass, None);
+            let orbit = OrbitState { illumination: Illumination::Sunlight, number: Some(1234) };
+            for (i, line) in lines.iter().enumerate() {
+                let parsed = parse_botan_beacon(line).map(Frame::Housekeeping);
+                record.add_beacon(aos + 60.0 * (i + 1) as f64, line, Some(orbit), parsed);
+            }
+            save_pass(&root, aos, &record, None).unwrap();
+        }
+
+        let mut out = Vec::new();
+        assert_eq!(export(&root, None, None, ExportFormat::Csv, &mut out), Ok(3));
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("time,pass_aos,frame_type,frame,raw_rssi,raw_snr,rssi_dbm,snr_db,bat_v,"));
+        assert_eq!(lines[0].split(',').count(), columns().len());
+        assert!(lines[1].starts_with("2024-05-01T12:35:56Z,2024-05-01T12:34:56Z,housekeeping,A67C8D5E2AA13608,134,64,,,4.2796"));
+        assert!(lines[2].contains(",sunlight,1234,,\"Invalid data length. Expected 16 hex characters, got 4\",BOTAN JS1YPT A67C"));
+        assert_eq!(lines[1].split(',').count(), columns().len());
+
+        // Only the second day
+        let mut out = Vec::new();
+        let from = timeutil::parse_utc("2024-05-02").unwrap();
+        assert_eq!(export(&root, Some(from), None, ExportFormat::Tsv, &mut out), Ok(1));
+        assert!(String::from_utf8(out).unwrap().lines().nth(1).unwrap().starts_with("2024-05-02T12:35:56Z\t"));
+        let mut out = Vec::new();
+        assert_eq!(export(&root, None, Some(from), ExportFormat::Csv, &mut out), Ok(2));
+        let mut out = Vec::new();
+        assert_eq!(export(&root, None, None, ExportFormat::Parquet, &mut out), Ok(3));
+        assert!(out.starts_with(b"PAR1") && out.ends_with(b"PAR1"));
+        #[cfg(feature = "arrow")]
+        {
+            let mut out = Vec::new();
+            assert_eq!(export(&root, None, None, ExportFormat::Arrow, &mut out), Ok(3));
+            assert!(out.starts_with(&[0xFF; 4]) && out.ends_with(&[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]));
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_csv_value() {
+        assert_eq!(csv_value("4.28"), "4.28");
+        assert_eq!(csv_value("a,b"), "\"a,b\"");
+        assert_eq!(csv_value("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!("TSV".parse(), Ok(ExportFormat::Tsv));
+        assert!("xlsx".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_parquet_cells() {
+        assert_eq!(cell("2024-05-01T12:34:56Z", column_type("time")), Some(Cell::Timestamp(1714566896000)));
+        assert_eq!(cell("134", column_type("raw_rssi")), Some(Cell::Int32(134)));
+        assert_eq!(cell("4.2796", column_type("bat_v")), Some(Cell::Double(4.2796)));
+        assert_eq!(cell("true", column_type("kill_sw")), Some(Cell::Boolean(true)));
+        assert_eq!(cell("A67C", column_type("frame")), Some(Cell::Text("A67C".to_string())));
+        assert_eq!(cell("", column_type("bat_v")), None);
+        // Every column has a type of its own, not just the Boolean fallback
+        let booleans = columns().into_iter().filter(|name| column_type(name) == ColumnType::Boolean).count();
+        assert_eq!(booleans, 14);
+    }
+}
@@ -0,0 +1,176 @@
+// Extraction of telemetry fields from a raw frame. A field is any run of 1 to 32 bits, so it
+// may be part of a byte, a whole byte or a wider counter spanning several bytes, and can be
+// read as unsigned or two's complement. Bits are counted in the order they are sent, most
+// significant bit of the first byte first, which makes a multi-byte field big-endian; a
+// little-endian word has to be whole bytes, whose order is then reversed.
+//
+// On the command line a field is written as sign, width, optional byte order and bit offset:
+// "u8@0" is the first byte, "s16le@8" a signed little-endian word from the second byte on and
+// "u3@49" bits 6-4 of byte 7.
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Order of the bytes of a multi-byte field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Position and encoding of a field in a frame; in JSON files it is written as on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Field {
+    pub offset: usize,     // Bits before the field's first bit
+    pub width: u8,         // 1-32
+    pub order: ByteOrder,
+    pub signed: bool,      // Two's complement
+}
+
+impl Field {
+    /// The unsigned byte at `index`
+    pub const fn byte(index: usize) -> Self {
+        Field { offset: index * 8, width: 8, order: ByteOrder::Big, signed: false }
+    }
+
+    /// Bits `high` down to `low` of the byte at `index`, numbered 7 (MSB) to 0 as in the definition
+    pub const fn bits(index: usize, high: u8, low: u8) -> Self {
+        Field { offset: index * 8 + 7 - high as usize, width: high - low + 1, order: ByteOrder::Big, signed: false }
+    }
+
+    /// Whether the field can be read from a long enough frame
+    fn check(&self) -> Result<(), String> {
+        if !(1..=32).contains(&self.width) {
+            return Err(format!("Invalid field width {}. Expected 1-32 bits", self.width));
+        }
+        if self.order == ByteOrder::Little && !(self.offset.is_multiple_of(8) && self.width.is_multiple_of(8)) {
+            return Err(format!("Field ({}) is not whole bytes", self));
+        }
+        Ok(())
+    }
+
+    /// Value of the field in `frame`
+    pub fn extract(&self, frame: &[u8]) -> Result<i64, String> {
+        self.check()?;
+        let width = self.width as usize;
+        if self.offset + width > frame.len() * 8 {
+            return Err(format!("Field ({}) is beyond the {}-byte frame", self, frame.len()));
+        }
+
+        let raw = match self.order {
+            ByteOrder::Big => (self.offset..self.offset + width)
+                .fold(0u64, |acc, bit| acc << 1 | (frame[bit / 8] >> (7 - bit % 8) & 1) as u64),
+            ByteOrder::Little => {
+                let bytes = &frame[self.offset / 8..(self.offset + width) / 8];
+                bytes.iter().rev().fold(0u64, |acc, &b| acc << 8 | b as u64)
+            }
+        };
+        if self.signed && raw >> (width - 1) & 1 == 1 {
+            Ok(raw as i64 - (1i64 << width))
+        } else {
+            Ok(raw as i64)
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.signed { "signed" } else { "unsigned" };
+        let order = match (self.width > 8, self.order) {
+            (true, ByteOrder::Big) => " big-endian",
+            (true, ByteOrder::Little) => " little-endian",
+            (false, _) => "",
+        };
+        write!(f, "{} {}-bit{} at bit {}", sign, self.width, order, self.offset)
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid field '{}'. Expected e.g. u8@0, s16le@8 or u3@49", s);
+        let (format, offset) = s.split_once('@').ok_or_else(invalid)?;
+        let signed = match format.get(..1) {
+            Some("u") => false,
+            Some("s") => true,
+            _ => return Err(invalid()),
+        };
+        let (width, order) = match format[1..].strip_suffix("le") {
+            Some(width) => (width, ByteOrder::Little),
+            None => (format[1..].strip_suffix("be").unwrap_or(&format[1..]), ByteOrder::Big),
+        };
+        let field = Field {
+            offset: offset.parse().map_err(|_| invalid())?,
+            width: width.parse().map_err(|_| invalid())?,
+            order,
+            signed,
+        };
+        field.check()?;
+        Ok(field)
+    }
+}
+
+impl TryFrom<String> for Field {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: [u8; 8] = [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08];
+
+    fn field(offset: usize, width: u8, order: ByteOrder, signed: bool) -> Field {
+        Field { offset, width, order, signed }
+    }
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(Field::byte(0).extract(&FRAME), Ok(0xA6));
+        assert_eq!(Field::bits(6, 6, 4).extract(&FRAME), Ok(3));
+        assert_eq!(Field::bits(7, 1, 0).extract(&FRAME), Ok(0));
+        // 16-bit words both ways, and as two's complement
+        assert_eq!(field(0, 16, ByteOrder::Big, false).extract(&FRAME), Ok(0xA67C));
+        assert_eq!(field(0, 16, ByteOrder::Little, false).extract(&FRAME), Ok(0x7CA6));
+        assert_eq!(field(0, 16, ByteOrder::Big, true).extract(&FRAME), Ok(0xA67C - 0x10000));
+        assert_eq!(field(0, 8, ByteOrder::Big, true).extract(&FRAME), Ok(-90));
+        assert_eq!(field(8, 8, ByteOrder::Big, true).extract(&FRAME), Ok(0x7C));
+        // Across a byte boundary: the low nibble of 0x8D and the high nibble of 0x5E
+        assert_eq!(field(20, 8, ByteOrder::Big, false).extract(&FRAME), Ok(0xD5));
+        assert_eq!(field(20, 8, ByteOrder::Big, true).extract(&FRAME), Ok(0xD5 - 0x100));
+        assert_eq!(field(0, 32, ByteOrder::Little, false).extract(&FRAME), Ok(0x5E8D7CA6));
+        assert_eq!(field(32, 32, ByteOrder::Big, true).extract(&FRAME), Ok(0x2AA13608));
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        assert!(Field::byte(8).extract(&FRAME).unwrap_err().contains("beyond the 8-byte frame"));
+        assert!(field(60, 8, ByteOrder::Big, false).extract(&FRAME).is_err());
+        assert_eq!(
+            field(4, 16, ByteOrder::Little, false).extract(&FRAME),
+            Err("Field (unsigned 16-bit little-endian at bit 4) is not whole bytes".to_string())
+        );
+        assert!(field(0, 0, ByteOrder::Big, false).extract(&FRAME).is_err());
+        assert!(field(0, 33, ByteOrder::Big, false).extract(&FRAME).is_err());
+        assert_eq!(field(8, 16, ByteOrder::Little, true).to_string(), "signed 16-bit little-endian at bit 8");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("u8@0".parse(), Ok(Field::byte(0)));
+        assert_eq!("u3@49".parse(), Ok(Field::bits(6, 6, 4)));
+        assert_eq!("s16le@8".parse(), Ok(field(8, 16, ByteOrder::Little, true)));
+        assert_eq!("u24be@100".parse(), Ok(field(100, 24, ByteOrder::Big, false)));
+        for invalid in ["", "8@0", "u8", "x8@0", "u@0", "u8@-1", "u40@0", "s12le@0", "é8@0"] {
+            assert!(invalid.parse::<Field>().is_err(), "{}", invalid);
+        }
+    }
+}
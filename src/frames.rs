@@ -0,0 +1,337 @@
+// Frame types besides the 8-byte housekeeping block, such as mission data frames. Their layouts
+// are not in the published definition, so they are described in a JSON file given with
+// --frame-types: each type has a data length in bytes, optionally an ID field that must hold a
+// given value, and named fields. A beacon's data block is checked against the types in name
+// order and decoded by the first that matches; when none does, it is parsed as housekeeping.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::botan_parser::{self, BotanBeaconData, BotanRSSI, SignalCalibration};
+use crate::calibration::CalibrationTable;
+use crate::confidence::Confidence;
+use crate::field::Field;
+use crate::integrity::{Check, CheckResult};
+
+/// An ID field and the value that identifies the frame type
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrameId {
+    pub field: Field,
+    pub value: i64,
+}
+
+/// A named field of a frame type: value = raw * scale + offset
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldDef {
+    pub name: String,
+    pub field: Field,
+    #[serde(default = "one")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    pub unit: Option<String>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// Layout of one frame type
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrameType {
+    pub length: usize,           // Data bytes
+    pub id: Option<FrameId>,
+    pub check: Option<Check>,
+    pub fields: Vec<FieldDef>,
+}
+
+impl FrameType {
+    fn matches(&self, data: &[u8]) -> bool {
+        data.len() == self.length && self.id.as_ref().is_none_or(|id| id.field.extract(data) == Ok(id.value))
+    }
+}
+
+/// The configured frame types by name
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct FrameTypes(BTreeMap<String, FrameType>);
+
+impl FrameTypes {
+    /// Load JSON frame types, e.g. {"mission": {"length": 12, "id": {"field": "u8@0", "value": 77},
+    /// "fields": [{"name": "sequence", "field": "u16be@8"}]}}
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read frame types '{}': {}", path, e))?;
+        let types: FrameTypes =
+            serde_json::from_str(&content).map_err(|e| format!("Invalid frame types '{}': {}", path, e))?;
+        types.check().map_err(|e| format!("Invalid frame types '{}': {}", path, e))?;
+        Ok(types)
+    }
+
+    /// Every field must be inside its frame and named once, and an 8-byte type needs an ID to
+    /// tell it from housekeeping
+    pub fn check(&self) -> Result<(), String> {
+        for (name, frame_type) in &self.0 {
+            if frame_type.length == 8 && frame_type.id.is_none() {
+                return Err(format!("{}: an 8-byte frame type needs an id", name));
+            }
+            for (idx, field) in frame_type.fields.iter().enumerate() {
+                if frame_type.fields[..idx].iter().any(|f| f.name == field.name) {
+                    return Err(format!("{}: field '{}' is defined twice", name, field.name));
+                }
+            }
+            if let Some(check) = &frame_type.check {
+                check.validate().map_err(|e| format!("{}.check: {}", name, e))?;
+            }
+            let zeros = vec![0; frame_type.length];
+            let id = frame_type.id.iter().map(|id| ("id", &id.field));
+            let check = frame_type.check.iter().map(|check| ("check", &check.field));
+            for (field, spec) in id.chain(check).chain(frame_type.fields.iter().map(|f| (f.name.as_str(), &f.field))) {
+                spec.extract(&zeros).map_err(|e| format!("{}.{}: {}", name, field, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pairs of fields sharing bits; the ID may double as a field, but nothing else should overlap
+    pub fn overlaps(&self) -> Vec<String> {
+        let mut overlaps = Vec::new();
+        for (name, frame_type) in &self.0 {
+            let check = frame_type.check.iter().map(|check| ("check", &check.field));
+            let fields: Vec<_> = check.chain(frame_type.fields.iter().map(|f| (f.name.as_str(), &f.field))).collect();
+            for (idx, (a, field_a)) in fields.iter().enumerate() {
+                for (b, field_b) in &fields[idx + 1..] {
+                    let start = field_a.offset.max(field_b.offset);
+                    let end = (field_a.offset + field_a.width as usize).min(field_b.offset + field_b.width as usize);
+                    if start < end {
+                        overlaps.push(format!("{}: fields '{}' and '{}' share bits {}-{}", name, a, b, start, end - 1));
+                    }
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// The first type matching `data`
+    fn detect(&self, data: &[u8]) -> Option<(&str, &FrameType)> {
+        self.0.iter().find(|(_, t)| t.matches(data)).map(|(name, t)| (name.as_str(), t))
+    }
+}
+
+/// Value of one field of a decoded frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldValue {
+    pub name: String,
+    pub raw: i64,
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+/// A decoded frame of a configured type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypedFrame {
+    pub satellite_name: &'static str,
+    pub call_sign: &'static str,
+    pub frame_type: String,
+    pub rssi: Option<BotanRSSI>,
+    #[serde(serialize_with = "botan_parser::serialize_hex")]
+    pub frame: Vec<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check: Option<CheckResult>,
+    pub fields: Vec<FieldValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+}
+
+impl fmt::Display for TypedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BOTAN {} Frame", self.frame_type)?;
+        writeln!(f, "==========================")?;
+        writeln!(f, "Satellite: {}", self.satellite_name)?;
+        writeln!(f, "Call Sign: {}", self.call_sign)?;
+        if let Some(confidence) = &self.confidence {
+            writeln!(f, "Confidence: {:.0}%", confidence.score * 100.0)?;
+        }
+        if let Some(rssi) = &self.rssi {
+            match (rssi.rssi_dbm, rssi.snr_db) {
+                (Some(dbm), Some(db)) => writeln!(f, "Signal:    {:.1} dBm, SNR {:.1} dB", dbm, db)?,
+                _ => writeln!(f, "Signal:    SI{:02X}{:02X} (uncalibrated)", rssi.raw_rssi, rssi.raw_snr)?,
+            }
+        }
+        let hex: Vec<String> = self.frame.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "Frame:     {}", hex.join(" "))?;
+        if let Some(check) = &self.check {
+            match check.passed {
+                true => write!(f, "\nCheck:     {} OK", check.codec)?,
+                false => write!(
+                    f,
+                    "\nCheck:     {} FAILED (received {:X}, computed {:X})",
+                    check.codec, check.received, check.computed
+                )?,
+            }
+        }
+        let width = self.fields.iter().map(|v| v.name.len()).max().unwrap_or(0);
+        for v in &self.fields {
+            write!(f, "\n  {:<width$}  {}", format!("{}:", v.name), v.value, width = width + 1)?;
+            if let Some(unit) = &v.unit {
+                write!(f, " {}", unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A decoded beacon of either kind
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Housekeeping(BotanBeaconData),
+    Typed(TypedFrame),
+}
+
+impl Frame {
+    /// The raw data block
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Frame::Housekeeping(data) => &data.frame,
+            Frame::Typed(frame) => &frame.frame,
+        }
+    }
+}
+
+/// Parse a beacon line as the first of `types` its data block matches, or else as housekeeping
+pub fn parse_frame(
+    input: &str,
+    calibration: &SignalCalibration,
+    table: &CalibrationTable,
+    types: &FrameTypes,
+) -> Result<Frame, String> {
+    let input = &*botan_parser::normalize_line(input);
+    let housekeeping = || botan_parser::parse_botan_beacon_with(input, calibration, table).map(Frame::Housekeeping);
+    if types.0.is_empty() {
+        return housekeeping();
+    }
+    let (rssi, data_str) = botan_parser::parse_beacon_header(input, calibration)?;
+    // Malformed hex gets the housekeeping parser's error
+    let Ok(data) = botan_parser::parse_hex_data(data_str) else {
+        return housekeeping();
+    };
+    let Some((name, frame_type)) = types.detect(&data) else {
+        return housekeeping();
+    };
+
+    // Checked before any field is converted
+    let check = frame_type.check.as_ref().map(|check| check.verify(&data)).transpose()?;
+    let mut fields = Vec::new();
+    for def in &frame_type.fields {
+        let raw = def.field.extract(&data)?;
+        fields.push(FieldValue { name: def.name.clone(), raw, value: raw as f64 * def.scale + def.offset, unit: def.unit.clone() });
+    }
+    Ok(Frame::Typed(TypedFrame {
+        satellite_name: "BOTAN",
+        call_sign: "JS1YPT",
+        frame_type: name.to_string(),
+        rssi,
+        frame: data,
+        check,
+        fields,
+        confidence: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: &str = r#"{
+        "mission": {
+            "length": 6,
+            "id": {"field": "u8@0", "value": 77},
+            "fields": [
+                {"name": "sequence", "field": "u16be@8"},
+                {"name": "temperature", "field": "s8@24", "scale": 0.5, "unit": "°C"},
+                {"name": "counter", "field": "u16le@32"}
+            ]
+        },
+        "status": {"length": 8, "id": {"field": "u4@0", "value": 15}, "fields": [{"name": "mode", "field": "u4@4"}]},
+        "summed": {"length": 3, "check": {"codec": "sum8", "field": "u8@16"}, "fields": [{"name": "count", "field": "u8@8"}]}
+    }"#;
+
+    fn parse(input: &str) -> Result<Frame, String> {
+        let types: FrameTypes = serde_json::from_str(TYPES).unwrap();
+        types.check().unwrap();
+        parse_frame(input, &SignalCalibration::default(), &CalibrationTable::default(), &types)
+    }
+
+    #[test]
+    fn test_detect_frame_types() {
+        let Ok(Frame::Typed(frame)) = parse("BOTAN JS1YPT SI8640 4D0102F90201") else { panic!() };
+        assert_eq!(frame.frame_type, "mission");
+        assert_eq!(frame.rssi.as_ref().map(|r| r.raw_rssi), Some(0x86));
+        let values: Vec<(&str, f64)> = frame.fields.iter().map(|v| (v.name.as_str(), v.value)).collect();
+        assert_eq!(values, [("sequence", 258.0), ("temperature", -3.5), ("counter", 258.0)]);
+        assert_eq!(serde_json::to_value(&frame).unwrap()["frame"], "4D0102F90201");
+        assert!(frame.to_string().contains("\n  temperature:  -3.5 °C"));
+
+        // An 8-byte frame with the status ID, and housekeeping frames with or without types
+        assert!(matches!(parse("BOTAN JS1YPT F300000000000000"), Ok(Frame::Typed(f)) if f.fields[0].raw == 3));
+        assert!(matches!(parse("BOTAN JS1YPT A67C8D5E2AA13608"), Ok(Frame::Housekeeping(_))));
+        // Unknown lengths and IDs still fail as housekeeping frames
+        assert!(parse("BOTAN JS1YPT 4E0102F90201").unwrap_err().starts_with("Invalid data length"));
+        assert!(parse("BOTAN JS1YPT 4D0102F902").unwrap_err().starts_with("Invalid data length"));
+        assert!(parse("BOTAN JS1YPT 4D0102F9020G").unwrap_err().starts_with("Invalid data length"));
+        assert!(parse("BOTAN JS1YPT A67C8D5E2AA1360G").unwrap_err().starts_with("Invalid hex data"));
+    }
+
+    #[test]
+    fn test_integrity_check() {
+        let Ok(Frame::Typed(frame)) = parse("BOTAN JS1YPT 4D024F") else { panic!() };
+        assert_eq!(frame.frame_type, "summed");
+        assert!(frame.check.as_ref().unwrap().passed);
+        assert!(frame.to_string().contains("\nCheck:     Sum-8 OK\n"));
+        assert_eq!(serde_json::to_value(&frame).unwrap()["check"]["passed"], true);
+
+        // A failed check is recorded, and the fields are still decoded
+        let Ok(Frame::Typed(frame)) = parse("BOTAN JS1YPT 4D034F") else { panic!() };
+        assert!(!frame.check.as_ref().unwrap().passed);
+        assert_eq!(frame.fields[0].raw, 3);
+        assert!(frame.to_string().contains("Sum-8 FAILED (received 4F, computed 50)"));
+    }
+
+    #[test]
+    fn test_check_frame_types() {
+        let check = |json: &str| serde_json::from_str::<FrameTypes>(json).unwrap().check();
+        assert!(check(r#"{"a": {"length": 8, "fields": []}}"#).unwrap_err().contains("needs an id"));
+        assert!(check(r#"{"a": {"length": 2, "fields": [{"name": "x", "field": "u8@16"}]}}"#)
+            .unwrap_err()
+            .starts_with("a.x: Field"));
+        assert!(check(r#"{"a": {"length": 2, "check": {"codec": "crc16-x25", "field": "u8@8"}, "fields": []}}"#)
+            .unwrap_err()
+            .starts_with("a.check: CRC-16/X.25 needs"));
+        assert!(check(r#"{"a": {"length": 2, "check": {"codec": "crc16-x25", "field": "u16le@8"}, "fields": []}}"#)
+            .unwrap_err()
+            .starts_with("a.check: Field"));
+        assert!(serde_json::from_str::<FrameTypes>(r#"{"a": {"length": 2, "fields": [{"name": "x", "field": "q"}]}}"#).is_err());
+        assert_eq!(
+            check(r#"{"a": {"length": 2, "fields": [{"name": "x", "field": "u8@0"}, {"name": "x", "field": "u8@8"}]}}"#).unwrap_err(),
+            "a: field 'x' is defined twice"
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert!(serde_json::from_str::<FrameTypes>(TYPES).unwrap().overlaps().is_empty());
+        let types: FrameTypes = serde_json::from_str(
+            r#"{"a": {"length": 3, "id": {"field": "u8@0", "value": 1}, "check": {"codec": "sum8", "field": "u8@16"},
+                "fields": [{"name": "kind", "field": "u8@0"}, {"name": "x", "field": "u12@4"}, {"name": "y", "field": "u4@12"}, {"name": "z", "field": "u4@20"}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            types.overlaps(),
+            ["a: fields 'check' and 'z' share bits 20-23", "a: fields 'kind' and 'x' share bits 4-7", "a: fields 'x' and 'y' share bits 12-15"]
+        );
+    }
+}
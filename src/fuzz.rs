@@ -0,0 +1,87 @@
+// Randomized robustness tests for the parsers that see untrusted input: beacon lines typed,
+// piped or demodulated, and morse from the demodulator. Inputs are random bytes, random
+// mixes of beacon fragments and multi-byte characters, and mutations of a valid beacon;
+// nothing may panic, and a beacon that parses must round-trip its frame.
+//
+// A plain `cargo test` runs a short, fixed-seed round. For a longer run:
+//
+//   BOTAN_FUZZ_ITERATIONS=5000000 BOTAN_FUZZ_SEED=$RANDOM cargo test --release fuzz_ -- --nocapture
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::botan_parser::{normalize_line, parse_botan_beacon};
+use crate::rng::Rng;
+use crate::BotanDecoder;
+
+const VALID: &str = "BOTAN JS1YPT SI8640 A67C8D5E2AA13608";
+const FRAGMENTS: [&str; 16] = [
+    "BOTAN", "JS1YPT", "SI", " ", "  ", "A6", "7C", "F", "0", "x", "-", ".", "é", "日", "🛰", "\u{0301}",
+];
+
+fn random_input(rng: &mut Rng) -> String {
+    match rng.below(3) {
+        0 => {
+            let bytes: Vec<u8> = (0..rng.below(48)).map(|_| rng.next() as u8).collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        1 => (0..rng.below(12)).map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())]).collect(),
+        _ => {
+            let mut chars: Vec<char> = VALID.chars().collect();
+            for _ in 0..1 + rng.below(3) {
+                let at = rng.below(chars.len() + 1);
+                let c = FRAGMENTS[rng.below(FRAGMENTS.len())].chars().next().unwrap();
+                match rng.below(3) {
+                    0 if at < chars.len() => chars[at] = c,
+                    1 if at < chars.len() => {
+                        chars.remove(at);
+                    }
+                    _ => chars.insert(at, c),
+                }
+            }
+            chars.into_iter().collect()
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Feed `iterations` random inputs to `target`, naming the first input that panics
+fn fuzz(name: &str, target: impl Fn(&str)) {
+    let seed = env_or("BOTAN_FUZZ_SEED", 0x5EED_B07A);
+    let iterations = env_or("BOTAN_FUZZ_ITERATIONS", 5_000);
+    let mut rng = Rng::new(seed);
+    for i in 0..iterations {
+        let input = random_input(&mut rng);
+        if panic::catch_unwind(AssertUnwindSafe(|| target(&input))).is_err() {
+            panic!("{} panicked on input {:?} (seed {}, iteration {})", name, input, seed, i);
+        }
+    }
+}
+
+#[test]
+fn fuzz_parse_botan_beacon() {
+    // Inputs that once panicked by slicing inside a multi-byte character
+    assert!(parse_botan_beacon("BOTAN JS1YPT SIxé1 A67C8D5E2AA13608").is_err());
+    assert!(parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA136é").is_err());
+    assert!(parse_botan_beacon("BOTAN JS1YPT +67C8D5E2AA13608").is_err());
+
+    fuzz("parse_botan_beacon", |input| {
+        if let Ok(data) = parse_botan_beacon(input) {
+            let hex: String = data.frame.iter().map(|b| format!("{:02X}", b)).collect();
+            let line = normalize_line(input);
+            assert_eq!(line.split_whitespace().last().unwrap().to_ascii_uppercase(), hex);
+        }
+    });
+}
+
+#[test]
+fn fuzz_morse_decode() {
+    let decoder = BotanDecoder::new();
+    fuzz("BotanDecoder::decode", |input| {
+        // Also as dots, dashes and spaces, to reach the pattern lookups
+        let morse: String = input.chars().map(|c| ['.', '-', ' '][c as usize % 3]).collect();
+        let _ = decoder.decode(input);
+        let _ = decoder.decode(&morse);
+    });
+}
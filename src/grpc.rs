@@ -0,0 +1,476 @@
+// The gRPC service of --grpc ADDR, for ground segment software that would rather call than poll:
+// `Decode` parses one beacon line, `StreamDecodes` follows every frame the running decoder
+// prints, and `QueryHistory` reads frames back from the pass history. The service and its
+// messages are published in proto/botan_service.proto, next to the Frame of proto/botan.proto,
+// and the transport is cleartext HTTP/2 (see http2.rs). The service never offers a message
+// encoding, so compressed requests are refused.
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::botan_parser::SignalCalibration;
+use crate::calibration::CalibrationTable;
+use crate::export;
+use crate::frames::{self, Frame, FrameTypes};
+use crate::http2::{self, Request, Responder};
+use crate::logging::{debug, info, warn};
+use crate::orbit::{Illumination, OrbitState};
+use crate::proto::{self, Field};
+use crate::timeutil;
+
+/// The service definition, as shipped in proto/botan_service.proto
+pub const DEFINITION: &str = include_str!("../proto/botan_service.proto");
+
+// Status codes
+const OK: u32 = 0;
+const INVALID_ARGUMENT: u32 = 3;
+const UNIMPLEMENTED: u32 = 12;
+const INTERNAL: u32 = 13;
+
+/// How often a StreamDecodes call checks that its client is still there
+const STREAM_POLL: Duration = Duration::from_secs(1);
+
+/// A failed call
+#[derive(Debug, Clone, PartialEq)]
+struct Status {
+    code: u32,
+    message: String,
+}
+
+impl Status {
+    fn new(code: u32, message: impl Into<String>) -> Self {
+        Status { code, message: message.into() }
+    }
+}
+
+/// How the service parses lines: the calibrations and frame types the decoder runs with
+#[derive(Debug, Clone, Default)]
+pub struct Decoding {
+    pub calibration: SignalCalibration,
+    pub table: CalibrationTable,
+    pub frame_types: FrameTypes,
+}
+
+impl Decoding {
+    fn parse(&self, line: &str) -> Result<Frame, String> {
+        frames::parse_frame(line, &self.calibration, &self.table, &self.frame_types)
+    }
+}
+
+type Subscribers = Arc<Mutex<Vec<mpsc::Sender<Vec<u8>>>>>;
+
+/// The running service; frames published reach every StreamDecodes call open at the time
+#[derive(Clone)]
+pub struct Server {
+    subscribers: Subscribers,
+}
+
+impl Server {
+    /// Listen on `addr` and serve calls in the background. QueryHistory reads the pass
+    /// directories under `history`.
+    pub fn start(addr: &str, decoding: Decoding, history: PathBuf) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("Cannot listen for gRPC on '{}': {}", addr, e))?;
+        info!("gRPC service listening"; address = addr);
+        Ok(Self::serve(listener, decoding, history))
+    }
+
+    fn serve(listener: TcpListener, decoding: Decoding, history: PathBuf) -> Self {
+        let subscribers = Subscribers::default();
+        let service = Arc::new(Service { decoding, history, subscribers: Arc::clone(&subscribers) });
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        warn!("Cannot accept gRPC connection"; error = error);
+                        continue;
+                    }
+                };
+                let service = Arc::clone(&service);
+                thread::spawn(move || {
+                    let handle = Arc::new(move |request, responder| service.handle(request, responder));
+                    if let Err(error) = http2::serve_connection(stream, handle) {
+                        debug!("gRPC connection closed"; error = error);
+                    }
+                });
+            }
+        });
+        Server { subscribers }
+    }
+
+    /// Send a decoded frame to the clients following StreamDecodes
+    pub fn publish(&self, frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>) {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        if subscribers.is_empty() {
+            return;
+        }
+        let message = proto::encode(frame, Some(time.unwrap_or_else(timeutil::now_unix)), orbit);
+        subscribers.retain(|subscriber| subscriber.send(message.clone()).is_ok());
+    }
+}
+
+/// A response under way: HEADERS go out with the first message, or with the status when there
+/// is none (a Trailers-Only response)
+struct Call {
+    responder: Responder,
+    started: bool,
+}
+
+impl Call {
+    fn start(&mut self) -> std::io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.responder.headers(&[(":status", "200"), ("content-type", "application/grpc")], false)?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, message: &[u8]) -> std::io::Result<()> {
+        self.start()?;
+        let mut framed = Vec::with_capacity(5 + message.len());
+        framed.push(0);
+        framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        framed.extend_from_slice(message);
+        self.responder.data(&framed, false)
+    }
+
+    fn finish(self, status: Result<(), Status>) {
+        let (code, message) = match status {
+            Ok(()) => (OK, String::new()),
+            Err(status) => (status.code, status.message),
+        };
+        let code = code.to_string();
+        let message = percent_encode(&message);
+        let mut trailers = Vec::new();
+        if !self.started {
+            trailers.extend([(":status", "200"), ("content-type", "application/grpc")]);
+        }
+        trailers.push(("grpc-status", code.as_str()));
+        if !message.is_empty() {
+            trailers.push(("grpc-message", message.as_str()));
+        }
+        // A client that went away does not need its status
+        let _ = self.responder.headers(&trailers, true);
+    }
+}
+
+/// grpc-message is percent-encoded UTF-8
+fn percent_encode(message: &str) -> String {
+    message
+        .bytes()
+        .map(|b| match b {
+            b' '..=b'~' if b != b'%' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The messages of a request body
+fn messages(mut body: &[u8]) -> Result<Vec<&[u8]>, Status> {
+    let mut messages = Vec::new();
+    while !body.is_empty() {
+        let Some((&[compressed, a, b, c, d], rest)) = body.split_first_chunk::<5>() else {
+            return Err(Status::new(INVALID_ARGUMENT, "Truncated message"));
+        };
+        if compressed != 0 {
+            return Err(Status::new(UNIMPLEMENTED, "Compressed messages are not supported"));
+        }
+        let length = u32::from_be_bytes([a, b, c, d]) as usize;
+        let (message, rest) = rest.split_at_checked(length).ok_or_else(|| Status::new(INVALID_ARGUMENT, "Truncated message"))?;
+        messages.push(message);
+        body = rest;
+    }
+    Ok(messages)
+}
+
+/// The one message of a unary or server-streaming call
+fn single(body: &[u8]) -> Result<Vec<(u64, Field<'_>)>, Status> {
+    match messages(body)?[..] {
+        [message] => proto::read_fields(message).map_err(|e| Status::new(INVALID_ARGUMENT, e)),
+        _ => Err(Status::new(INVALID_ARGUMENT, "Expected one request message")),
+    }
+}
+
+/// An optional Timestamp field of a request
+fn time_field(fields: &[(u64, Field)], number: u64) -> Result<Option<f64>, Status> {
+    let field = fields.iter().rev().find(|(n, _)| *n == number);
+    match field {
+        Some((_, Field::Bytes(bytes))) => proto::read_timestamp(bytes).map(Some).map_err(|e| Status::new(INVALID_ARGUMENT, e)),
+        _ => Ok(None),
+    }
+}
+
+struct Service {
+    decoding: Decoding,
+    history: PathBuf,
+    subscribers: Subscribers,
+}
+
+impl Service {
+    fn handle(&self, request: Request, responder: Responder) {
+        let mut call = Call { responder, started: false };
+        let status = match request.path.as_str() {
+            "/botan.Decoder/Decode" => self.decode(&request, &mut call),
+            "/botan.Decoder/StreamDecodes" => self.stream_decodes(&request, &mut call),
+            "/botan.Decoder/QueryHistory" => self.query_history(&request, &mut call),
+            path => Err(Status::new(UNIMPLEMENTED, format!("Unknown method '{}'", path))),
+        };
+        if let Err(status) = &status {
+            debug!("gRPC call failed"; method = request.path, code = status.code, error = status.message);
+        }
+        call.finish(status);
+    }
+
+    fn decode(&self, request: &Request, call: &mut Call) -> Result<(), Status> {
+        let fields = single(&request.body)?;
+        let line = match fields.iter().rev().find(|(n, _)| *n == 1) {
+            Some((_, Field::Bytes(line))) => std::str::from_utf8(line).map_err(|_| Status::new(INVALID_ARGUMENT, "Line is not UTF-8"))?,
+            _ => "",
+        };
+        let time = time_field(&fields, 2)?;
+        let frame = self.decoding.parse(line).map_err(|e| Status::new(INVALID_ARGUMENT, e))?;
+        // The client has gone when this fails, and no status reaches it either
+        let _ = call.send(&proto::encode(&frame, time, None));
+        Ok(())
+    }
+
+    fn stream_decodes(&self, request: &Request, call: &mut Call) -> Result<(), Status> {
+        single(&request.body)?;
+        let (sender, frames) = mpsc::channel();
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        if call.start().is_err() {
+            return Ok(());
+        }
+        loop {
+            match frames.recv_timeout(STREAM_POLL) {
+                Ok(message) => {
+                    if call.send(&message).is_err() {
+                        return Ok(());
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) if call.responder.is_open() => {}
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    fn query_history(&self, request: &Request, call: &mut Call) -> Result<(), Status> {
+        let fields = single(&request.body)?;
+        let (from, to) = (time_field(&fields, 1)?, time_field(&fields, 2)?);
+        let mut gone = false;
+        let result = history_frames(&self.history, from, to, &self.decoding, |frame, time, orbit| {
+            call.send(&proto::encode(&frame, time, orbit)).map_err(|e| {
+                gone = true;
+                e.to_string()
+            })
+        });
+        match result {
+            Err(_) if gone => Ok(()),
+            Err(error) => Err(Status::new(INTERNAL, error)),
+            Ok(()) => Ok(()),
+        }
+    }
+}
+
+/// Decode the beacons stored under `dir` received between `from` and `to` (exclusive) again,
+/// with the reception time and orbit they were filed with. Lines that did not decode when they
+/// were received are left out.
+fn history_frames(
+    dir: &Path,
+    from: Option<f64>,
+    to: Option<f64>,
+    decoding: &Decoding,
+    mut visit: impl FnMut(Frame, Option<f64>, Option<OrbitState>) -> Result<(), String>,
+) -> Result<(), String> {
+    let columns = export::columns();
+    let column = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+    let (time, illumination, orbit, text) = (column("time"), column("illumination"), column("orbit"), column("text"));
+    export::for_each_row(dir, from, to, |row| {
+        let Ok(frame) = decoding.parse(&row[text]) else {
+            return Ok(());
+        };
+        let state = serde_json::from_value::<Illumination>(Value::from(row[illumination].as_str()))
+            .ok()
+            .map(|illumination| OrbitState { illumination, number: row[orbit].parse().ok() });
+        visit(frame, timeutil::parse_utc(&row[time]).ok(), state)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    type Block = Vec<(String, String)>;
+
+    /// A minimal h2c client: one call on stream 1, returning the response messages and the
+    /// header blocks (response headers and trailers, or the trailers alone)
+    struct Client {
+        stream: TcpStream,
+        decoder: http2::HeaderDecoder,
+    }
+
+    impl Client {
+        fn connect(addr: std::net::SocketAddr) -> Self {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").unwrap();
+            let mut client = Client { stream, decoder: http2::HeaderDecoder::new() };
+            client.frame(0x4, 0, 0, &[]);
+            client
+        }
+
+        fn frame(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) {
+            let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+            frame.extend_from_slice(&[kind, flags]);
+            frame.extend_from_slice(&stream.to_be_bytes());
+            frame.extend_from_slice(payload);
+            self.stream.write_all(&frame).unwrap();
+        }
+
+        fn call(&mut self, method: &str, message: &[u8]) {
+            let path = format!("/botan.Decoder/{}", method);
+            let headers = [(":method", "POST"), (":scheme", "http"), (":path", path.as_str()), ("content-type", "application/grpc"), ("te", "trailers")];
+            self.frame(0x1, 0x4, 1, &http2::encode_headers(&headers));
+            let mut body = vec![0];
+            body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            body.extend_from_slice(message);
+            self.frame(0x0, 0x1, 1, &body);
+        }
+
+        /// Read stream 1 until `wanted` messages have come, or else the first header block
+        fn read(&mut self, wanted: usize) -> (Vec<Block>, Vec<Vec<u8>>, bool) {
+            let (mut blocks, mut body, mut ended) = (Vec::new(), Vec::new(), false);
+            while !ended && messages(&body).len() < wanted.max(1) {
+                let mut header = [0; 9];
+                self.stream.read_exact(&mut header).unwrap();
+                let mut payload = vec![0; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+                self.stream.read_exact(&mut payload).unwrap();
+                if header[8] != 1 {
+                    continue;
+                }
+                ended = header[4] & 0x1 != 0;
+                match header[3] {
+                    0x1 => {
+                        blocks.push(self.decoder.decode(&payload).unwrap());
+                        if wanted == 0 {
+                            break;
+                        }
+                    }
+                    0x0 => body.extend_from_slice(&payload),
+                    _ => {}
+                }
+            }
+            (blocks, super::messages(&body).unwrap().into_iter().map(<[u8]>::to_vec).collect(), ended)
+        }
+    }
+
+    fn messages(body: &[u8]) -> Vec<&[u8]> {
+        super::messages(body).unwrap_or_default()
+    }
+
+    fn header<'a>(block: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        block.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn server(history: PathBuf) -> (Server, std::net::SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        (Server::serve(listener, Decoding::default(), history), addr)
+    }
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(messages(&[0, 0, 0, 0, 2, 8, 1, 0, 0, 0, 0, 0]), [&[8, 1][..], &[]]);
+        assert_eq!(super::messages(&[1, 0, 0, 0, 0]).unwrap_err().code, UNIMPLEMENTED);
+        assert_eq!(super::messages(&[0, 0, 0, 0, 2, 8]).unwrap_err().code, INVALID_ARGUMENT);
+        assert_eq!(percent_encode("Invalid hex 'ZZ' 100%\n"), "Invalid hex 'ZZ' 100%25%0A");
+    }
+
+    #[test]
+    fn test_decode_call() {
+        let (_server, addr) = server(PathBuf::new());
+        let mut client = Client::connect(addr);
+        // line = 1, time = 2 { seconds: 1714566896 }
+        let line = b"BOTAN JS1YPT SI8640 A67C8D5E2AA13608";
+        let mut request = vec![0x0A, line.len() as u8];
+        request.extend_from_slice(line);
+        request.extend_from_slice(&[0x12, 0x06, 0x08, 0xF0, 0xED, 0xC8, 0xB1, 0x06]);
+        client.call("Decode", &request);
+        let (blocks, messages, ended) = client.read(1);
+        assert_eq!(header(&blocks[0], "content-type"), Some("application/grpc"));
+        let decoded = proto::read_fields(&messages[0]).unwrap();
+        assert_eq!(decoded[1], (2, Field::Bytes(b"JS1YPT")));
+        let Some((_, Field::Bytes(time))) = decoded.iter().find(|f| f.0 == 5) else { panic!("no time") };
+        assert_eq!(proto::read_timestamp(time).unwrap(), 1714566896.0);
+        if !ended {
+            let (trailers, _, _) = client.read(0);
+            assert_eq!(header(&trailers[0], "grpc-status"), Some("0"));
+        }
+
+        // A line that does not parse gets a Trailers-Only response
+        let mut client = Client::connect(addr);
+        client.call("Decode", &[0x0A, 0x05, b'H', b'E', b'L', b'L', b'O']);
+        let (blocks, messages, ended) = client.read(0);
+        assert!(ended && messages.is_empty());
+        assert_eq!(header(&blocks[0], ":status"), Some("200"));
+        assert_eq!(header(&blocks[0], "grpc-status"), Some("3"));
+        assert!(header(&blocks[0], "grpc-message").is_some());
+
+        let mut client = Client::connect(addr);
+        client.call("Encode", &[]);
+        assert_eq!(header(&client.read(0).0[0], "grpc-status"), Some("12"));
+    }
+
+    #[test]
+    fn test_stream_decodes() {
+        let (server, addr) = server(PathBuf::new());
+        let mut client = Client::connect(addr);
+        client.call("StreamDecodes", &[]);
+        let (blocks, _, _) = client.read(0);
+        assert_eq!(header(&blocks[0], ":status"), Some("200"));
+        let frame = Decoding::default().parse("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let orbit = OrbitState { illumination: Illumination::Eclipse, number: Some(1234) };
+        server.publish(&frame, Some(1714566896.0), Some(orbit));
+        server.publish(&frame, None, None);
+        let (_, messages, ended) = client.read(2);
+        assert!(!ended);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(proto::read_fields(&messages[0]).unwrap().last(), Some(&(10, Field::Varint(1234))));
+    }
+
+    #[test]
+    fn test_query_history() {
+        let root = std::env::temp_dir().join(format!("botan_grpc_test_{}", std::process::id()));
+        fs::create_dir_all(root.join("20240501T123456Z")).unwrap();
+        let pass = r#"{"aos": "2024-05-01T12:34:56Z", "beacons": [
+            {"time": "2024-05-01T12:35:56Z", "text": "BOTAN JS1YPT A67C8D5E2AA13608", "illumination": "sunlight", "orbit": 1234},
+            {"time": "2024-05-01T12:36:00Z", "text": "BOTAN JS1YPT ZZ", "error": "Invalid hex"},
+            {"time": "2024-05-01T12:37:00Z", "text": "BOTAN JS1YPT A57EB76823210E08", "illumination": null}
+        ]}"#;
+        fs::write(root.join("20240501T123456Z/pass.json"), pass).unwrap();
+        let (_server, addr) = server(root.clone());
+
+        let mut client = Client::connect(addr);
+        client.call("QueryHistory", &[]);
+        let (_, messages, _) = client.read(2);
+        assert_eq!(messages.len(), 2);
+        let first = proto::read_fields(&messages[0]).unwrap();
+        assert!(first.contains(&(6, Field::Varint(1))));
+        assert!(first.contains(&(10, Field::Varint(1234))));
+        assert!(proto::read_fields(&messages[1]).unwrap().contains(&(4, Field::Bytes(&[0xA5, 0x7E, 0xB7, 0x68, 0x23, 0x21, 0x0E, 0x08]))));
+
+        // from = 12:36:30Z
+        let mut client = Client::connect(addr);
+        client.call("QueryHistory", &[0x0A, 0x06, 0x08, 0xCE, 0xEE, 0xC8, 0xB1, 0x06]);
+        let (_, messages, _) = client.read(1);
+        assert_eq!(messages.len(), 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
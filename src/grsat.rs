@@ -0,0 +1,110 @@
+// Telemetry JSON laid out like the output of gr-satellites' decoders, so frames from this decoder
+// can feed pipelines and dashboards built around gr-satellites. Each frame is one object on one
+// line: the metadata gr-satellites attaches to a PDU (time, satellite, decoder, frame hex, signal)
+// next to a "telemetry" container named after the fields of the frame definition. Lines in this
+// layout are also accepted back as input, so a recorded JSON stream can be decoded again.
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::frames::Frame;
+use crate::migrate;
+use crate::orbit::OrbitState;
+use crate::provenance::Provenance;
+use crate::timeutil;
+
+const DECODER: &str = "botan_cw_decoder";
+
+/// One frame as a single line of JSON, with how it was decoded when known
+pub fn frame_json(frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>, provenance: Option<&Provenance>) -> Result<String, String> {
+    let (satellite, call_sign, rssi, frame_hex, telemetry, frame_type) = match frame {
+        Frame::Housekeeping(data) => {
+            let telemetry = serde_json::to_value(&data.telemetry).map_err(|e| e.to_string())?;
+            (&data.satellite_name, &data.call_sign, &data.rssi, hex(&data.frame), telemetry, "housekeeping")
+        }
+        Frame::Typed(typed) => {
+            let values: Map<String, Value> = typed.fields.iter().map(|v| (v.name.clone(), v.value.into())).collect();
+            (&typed.satellite_name, &typed.call_sign, &typed.rssi, hex(&typed.frame), values.into(), typed.frame_type.as_str())
+        }
+    };
+    let mut object = json!({
+        "schema_version": migrate::SCHEMA_VERSION,
+        "time": time.map(timeutil::format_utc),
+        "satellite": satellite,
+        "call_sign": call_sign,
+        "decoder": DECODER,
+        "frame_type": frame_type,
+        "frame": frame_hex,
+        "rssi": rssi,
+        "telemetry": telemetry,
+    });
+    if let Some(orbit) = orbit {
+        object["illumination"] = serde_json::to_value(orbit.illumination).map_err(|e| e.to_string())?;
+        if let Some(number) = orbit.number {
+            object["orbit"] = number.into();
+        }
+    }
+    if let Some(provenance) = provenance {
+        object["provenance"] = serde_json::to_value(provenance).map_err(|e| e.to_string())?;
+    }
+    Ok(object.to_string())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// The fields of an input line that are needed to decode its frame again
+#[derive(Deserialize)]
+struct Input {
+    time: Option<String>,
+    call_sign: Option<String>,
+    frame: String,
+    rssi: Option<RawSignal>,
+}
+
+#[derive(Deserialize)]
+struct RawSignal {
+    raw_rssi: u8,
+    raw_snr: u8,
+}
+
+/// The reception time and beacon line of a JSON line in this layout. The telemetry is decoded
+/// again from the frame, so the line's own values are ignored.
+pub fn parse_line(line: &str) -> Result<(Option<f64>, String), String> {
+    let input: Input = serde_json::from_str(line)
+        .map_err(|e| e.to_string())
+        .and_then(migrate::upgrade_record)
+        .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Invalid telemetry JSON: {}", e))?;
+    let time = input.time.as_deref().map(timeutil::parse_utc).transpose()?;
+    let signal = input.rssi.map_or_else(String::new, |si| format!("SI{:02X}{:02X} ", si.raw_rssi, si.raw_snr));
+    let call_sign = input.call_sign.unwrap_or_else(|| "JS1YPT".to_string());
+    Ok((time, format!("BOTAN {} {}{}", call_sign, signal, input.frame)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::orbit::Illumination;
+
+    #[test]
+    fn test_round_trip() {
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        let orbit = OrbitState { illumination: Illumination::Sunlight, number: Some(1234) };
+        let line = frame_json(&frame, Some(1714566896.0), Some(orbit), None).unwrap();
+        assert!(!line.contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["time"], "2024-05-01T12:34:56Z");
+        assert_eq!(value["satellite"], "BOTAN");
+        assert_eq!(value["frame_type"], "housekeeping");
+        assert_eq!(value["frame"], "A67C8D5E2AA13608");
+        assert_eq!(value["telemetry"]["data2"]["kill_sw"], false);
+        assert_eq!(value["illumination"], "sunlight");
+        assert_eq!(value["orbit"], 1234);
+
+        assert_eq!(parse_line(&line).unwrap(), (Some(1714566896.0), "BOTAN JS1YPT SI8640 A67C8D5E2AA13608".to_string()));
+        assert_eq!(parse_line(r#"{"frame": "A57EB76823210E08"}"#).unwrap(), (None, "BOTAN JS1YPT A57EB76823210E08".to_string()));
+        assert!(parse_line(r#"{"time": "2024-05-01T12:34:56Z"}"#).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+// The standalone HTML page of `report --html`, to mail to the satellite team as it is: the
+// summary, a chart of every analog channel over time with its limits, the channel ranges, flag
+// changes and events of the Markdown report, and for a single pass every frame received. Styles
+// and charts (SVG) are inline and there is no script, so the page reads the same in a browser, a
+// mail client or on paper.
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use serde_json::Value;
+
+use crate::aggregate;
+use crate::export;
+use crate::limits::{self, Limit, Limits, Severity};
+use crate::report::{self, Report};
+use crate::timeutil;
+use crate::xml::escape;
+
+/// Analog channels charted and listed per frame, with their unit and decimals shown
+const ANALOG: [(&str, &str, &str, usize); 5] = [
+    ("bat_v", "BAT_V", "V", 3),
+    ("bat_i", "BAT_I", "mA", 0),
+    ("bat_t", "BAT_T", "°C", 1),
+    ("bpb_t", "BPB_T", "°C", 1),
+    ("raw_i", "RAW_I", "mA", 0),
+];
+
+// Chart size and plot margins, in SVG units
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 180.0;
+const LEFT: f64 = 56.0;
+const RIGHT: f64 = 12.0;
+const TOP: f64 = 10.0;
+const BOTTOM: f64 = 24.0;
+
+const STYLE: &str = "
+body { font: 14px/1.45 system-ui, -apple-system, 'Segoe UI', sans-serif; color: #1d2430; max-width: 1000px; margin: 2em auto; padding: 0 1em; }
+h1 { font-size: 1.6em; margin-bottom: 0.2em; }
+h2 { font-size: 1.15em; margin-top: 2em; border-bottom: 1px solid #d5dbe3; padding-bottom: 0.2em; }
+.lead { color: #4a5566; margin-top: 0; }
+table { border-collapse: collapse; margin: 0.5em 0; font-variant-numeric: tabular-nums; }
+th, td { padding: 0.25em 0.7em; border-bottom: 1px solid #e6eaf0; text-align: right; white-space: nowrap; }
+th:first-child, td:first-child { text-align: left; }
+th { background: #f3f5f8; font-weight: 600; }
+table.summary th { text-align: left; width: 12em; }
+table.summary td { text-align: left; white-space: normal; }
+td.text { text-align: left; white-space: normal; }
+.warning { color: #a86a00; font-weight: 600; }
+.alarm { color: #c4262e; font-weight: 600; }
+.charts { display: grid; grid-template-columns: repeat(auto-fit, minmax(440px, 1fr)); gap: 1em; }
+figure { margin: 0; }
+figcaption { font-weight: 600; margin-bottom: 0.2em; }
+svg { width: 100%; height: auto; }
+svg text { font: 11px system-ui, sans-serif; fill: #4a5566; }
+footer { margin-top: 3em; color: #7a8494; font-size: 0.9em; }
+";
+
+fn class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Normal => "",
+        Severity::Warning => "warning",
+        Severity::Alarm => "alarm",
+    }
+}
+
+/// A value rounded for the axis of a chart spanning `span`
+fn tick(value: f64, span: f64) -> String {
+    let decimals = (2.0 - span.log10().floor()).clamp(0.0, 6.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+/// A line chart of `points` (Unix time, value), with dashed lines at the limit's bounds
+fn chart(out: &mut String, label: &str, unit: &str, points: &[(f64, f64)], limit: Option<Limit>) {
+    let bounds = limit.map(|l| [l.min, l.max]).unwrap_or_default();
+    let values = points.iter().map(|(_, v)| *v).chain(bounds.iter().flatten().copied());
+    let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let pad = if high > low { (high - low) * 0.08 } else { low.abs().max(1.0) * 0.05 };
+    let (low, high) = (low - pad, high + pad);
+    let (start, end) = (points[0].0, points[points.len() - 1].0);
+    let x = |t: f64| if end > start { LEFT + (t - start) / (end - start) * (WIDTH - LEFT - RIGHT) } else { (LEFT + WIDTH - RIGHT) / 2.0 };
+    let y = |v: f64| TOP + (high - v) / (high - low) * (HEIGHT - TOP - BOTTOM);
+
+    writeln!(out, "<figure><figcaption>{} [{}]</figcaption>", label, escape(unit)).unwrap();
+    writeln!(out, r##"<svg viewBox="0 0 {} {}" role="img" aria-label="{} over time">"##, WIDTH, HEIGHT, label).unwrap();
+    writeln!(out, r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#f7f9fb" stroke="#d5dbe3"/>"##, LEFT, TOP, WIDTH - LEFT - RIGHT, HEIGHT - TOP - BOTTOM)
+        .unwrap();
+    for value in [low + pad, high - pad] {
+        writeln!(out, r##"<text x="{}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{}</text>"##, LEFT - 6.0, y(value), tick(value, high - low))
+            .unwrap();
+    }
+    for bound in bounds.iter().flatten() {
+        writeln!(out, r##"<line x1="{}" x2="{}" y1="{:.1}" y2="{2:.1}" stroke="#c4262e" stroke-dasharray="4 3"/>"##, LEFT, WIDTH - RIGHT, y(*bound))
+            .unwrap();
+    }
+    let bottom = HEIGHT - BOTTOM + 15.0;
+    let (first, last) = (timeutil::format_display(start), timeutil::format_display(end));
+    writeln!(out, r##"<text x="{}" y="{}">{}</text>"##, LEFT, bottom, escape(&first)).unwrap();
+    if end > start {
+        writeln!(out, r##"<text x="{}" y="{}" text-anchor="end">{}</text>"##, WIDTH - RIGHT, bottom, escape(&last)).unwrap();
+    }
+    let line: Vec<String> = points.iter().map(|&(t, v)| format!("{:.1},{:.1}", x(t), y(v))).collect();
+    writeln!(out, r##"<polyline points="{}" fill="none" stroke="#2f6fb3" stroke-width="1.5"/>"##, line.join(" ")).unwrap();
+    for &(t, v) in points {
+        let color = match limits::check(limit, v) {
+            Severity::Normal => "#2f6fb3",
+            Severity::Warning => "#e09a00",
+            Severity::Alarm => "#c4262e",
+        };
+        writeln!(out, r##"<circle cx="{:.1}" cy="{:.1}" r="2.5" fill="{}"><title>{} {}</title></circle>"##, x(t), y(v), color, escape(&timeutil::format_display(t)), v)
+            .unwrap();
+    }
+    writeln!(out, "</svg></figure>").unwrap();
+}
+
+/// The page for `report`, made from the rows it summarised; `pass` is the pass.json when the
+/// report is of a single pass, which adds its details and a table of every frame
+pub fn page(report: &Report, rows: &[Vec<String>], pass: Option<&Value>, limits: &Limits) -> String {
+    let columns = export::columns();
+    let index = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+    let title = match pass.and_then(|pass| pass["aos"].as_str()) {
+        Some(aos) => format!("BOTAN pass {}", timeutil::display_text(aos)),
+        None => "BOTAN status report".to_string(),
+    };
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", escape(&title), STYLE).unwrap();
+    writeln!(out, "<h1>{}</h1>", escape(&title)).unwrap();
+    let (Some(first), Some(last)) = (&report.first, &report.last) else {
+        writeln!(out, "<p class=\"lead\">No frames received.</p>\n</body>\n</html>").unwrap();
+        return out;
+    };
+    writeln!(
+        out,
+        "<p class=\"lead\">{} lines received from {} to {}, {} frames decoded ({:.1}%).</p>",
+        report.lines,
+        escape(first),
+        escape(last),
+        report.frames,
+        100.0 * report.frames as f64 / report.lines.max(1) as f64
+    )
+    .unwrap();
+
+    writeln!(out, "<h2>Summary</h2>\n<table class=\"summary\">").unwrap();
+    let mut summary: Vec<(&str, String)> = Vec::new();
+    if let Some(pass) = pass {
+        let time = |key: &str| pass[key].as_str().map(timeutil::display_text);
+        summary.extend([("AOS", time("aos")), ("LOS", time("los"))].into_iter().filter_map(|(name, value)| Some((name, value?))));
+        if let Some(elevation) = pass["max_elevation_deg"].as_f64() {
+            summary.push(("Maximum elevation", format!("{:.1}°", elevation)));
+        }
+        if let Some(frequency) = pass["frequency_hz"].as_f64() {
+            summary.push(("Frequency", format!("{:.4} MHz", frequency / 1e6)));
+        }
+        if let Some(wpm) = pass["wpm"].as_f64() {
+            summary.push(("Keying speed", format!("{:.1} WPM", wpm)));
+        }
+        if let Some(source) = pass["source"].as_str() {
+            summary.push(("Source", source.to_string()));
+        }
+    } else {
+        summary.push(("Passes", report.passes.to_string()));
+    }
+    summary.push(("Lines received", report.lines.to_string()));
+    summary.push(("Frames decoded", report.frames.to_string()));
+    summary.push(("Failed", report.failed.to_string()));
+    if !report.frame_types.is_empty() {
+        let types: Vec<String> = report.frame_types.iter().map(|(name, count)| format!("{} {}", name, count)).collect();
+        summary.push(("Frame types", types.join(", ")));
+    }
+    let orbits: BTreeSet<&str> = rows.iter().map(|row| row[index("orbit")].as_str()).filter(|orbit| !orbit.is_empty()).collect();
+    if !orbits.is_empty() {
+        summary.push(("Orbit", orbits.into_iter().collect::<Vec<_>>().join(", ")));
+    }
+    let alarms = report.events.iter().filter(|event| event.text.contains("outside its limits")).count();
+    summary.push(("Events", format!("{}, {} of them limit violations", report.events.len(), alarms)));
+    for (name, value) in summary {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", name, escape(&value)).unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    let time = index("time");
+    let charts: Vec<_> = ANALOG
+        .iter()
+        .filter_map(|&(name, label, unit, _)| {
+            let idx = index(name);
+            let points: Vec<(f64, f64)> =
+                rows.iter().filter_map(|row| Some((timeutil::parse_utc(&row[time]).ok()?, row[idx].parse().ok()?))).collect();
+            (!points.is_empty()).then_some((name, label, unit, points))
+        })
+        .collect();
+    if !charts.is_empty() {
+        writeln!(out, "<h2>Telemetry</h2>\n<div class=\"charts\">").unwrap();
+        for (name, label, unit, points) in &charts {
+            chart(&mut out, label, unit, points, report::limit(limits, name));
+        }
+        writeln!(out, "</div>").unwrap();
+    }
+
+    if !report.channels.is_empty() {
+        writeln!(out, "<h2>Channels</h2>\n<table>\n<tr><th>Channel</th><th>Frames</th><th>Min</th><th>Max</th><th>Mean</th></tr>").unwrap();
+        for c in &report.channels {
+            let limit = report::limit(limits, &c.name);
+            let n = aggregate::number;
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td class=\"{}\">{}</td><td>{}</td></tr>",
+                escape(&c.name),
+                c.frames,
+                class(limits::check(limit, c.min)),
+                n(c.min),
+                class(limits::check(limit, c.max)),
+                n(c.max),
+                n(c.mean)
+            )
+            .unwrap();
+        }
+        writeln!(out, "</table>").unwrap();
+    }
+
+    if !report.flags.is_empty() {
+        writeln!(out, "<h2>Flags</h2>\n<table>\n<tr><th>Flag</th><th>Set</th><th>Cleared</th><th>Last</th></tr>").unwrap();
+        for f in &report.flags {
+            writeln!(out, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>", f.name, f.set, f.cleared, if f.last { "on" } else { "off" }).unwrap();
+        }
+        writeln!(out, "</table>").unwrap();
+    }
+
+    writeln!(out, "<h2>Events</h2>").unwrap();
+    if report.events.is_empty() {
+        writeln!(out, "<p>None.</p>").unwrap();
+    } else {
+        writeln!(out, "<table>\n<tr><th>Time</th><th>Event</th></tr>").unwrap();
+        for event in &report.events {
+            let alarm = if event.text.contains("outside its limits") { " alarm" } else { "" };
+            writeln!(out, "<tr><td>{}</td><td class=\"text{}\">{}</td></tr>", escape(&event.time), alarm, escape(&event.text)).unwrap();
+        }
+        writeln!(out, "</table>").unwrap();
+    }
+
+    if pass.is_some() {
+        writeln!(out, "<h2>Frames</h2>\n<table>\n<tr><th>Received</th><th>Frame</th>").unwrap();
+        for (_, label, unit, _) in ANALOG {
+            write!(out, "<th>{} [{}]</th>", label, unit).unwrap();
+        }
+        writeln!(out, "<th>SI</th><th>Illumination</th><th>Note</th></tr>").unwrap();
+        for row in rows {
+            let cell = |name: &str| row[index(name)].as_str();
+            let frame = match cell("frame_type") {
+                "housekeeping" | "" => cell("frame").to_string(),
+                other => format!("{} {}", other, cell("frame")),
+            };
+            write!(out, "<tr><td>{}</td><td>{}</td>", escape(&timeutil::display_text(cell("time"))), escape(&frame)).unwrap();
+            for (name, _, _, decimals) in ANALOG {
+                match cell(name).parse::<f64>() {
+                    Ok(value) => {
+                        let severity = limits::check(report::limit(limits, name), value);
+                        write!(out, "<td class=\"{}\">{:.*}</td>", class(severity), decimals, value).unwrap();
+                    }
+                    Err(_) => write!(out, "<td></td>").unwrap(),
+                }
+            }
+            let signal = match (cell("raw_rssi"), cell("raw_snr")) {
+                ("", _) | (_, "") => String::new(),
+                (rssi, snr) => format!("{} / {}", rssi, snr),
+            };
+            let note = if cell("error").is_empty() { cell("confidence").parse::<f64>().map_or(String::new(), |c| format!("confidence {:.2}", c)) } else { cell("error").to_string() };
+            let note_class = if cell("error").is_empty() { "text" } else { "text alarm" };
+            writeln!(
+                out,
+                "<td>{}</td><td>{}</td><td class=\"{}\">{}</td></tr>",
+                escape(&signal),
+                escape(cell("illumination")),
+                note_class,
+                escape(&note)
+            )
+            .unwrap();
+        }
+        writeln!(out, "</table>").unwrap();
+    }
+
+    writeln!(
+        out,
+        "<footer>Made by botan_cw_decoder {} on {}.</footer>\n</body>\n</html>",
+        env!("CARGO_PKG_VERSION"),
+        escape(&timeutil::format_display(timeutil::now_unix()))
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::frames::Frame;
+    use crate::orbit::{Illumination, OrbitState, Pass};
+    use crate::scheduler::PassRecord;
+
+    #[test]
+    fn test_page() {
+        let pass = Pass {
+            aos_unix: 1714566896.0,
+            los_unix: 1714567496.0,
+            max_elevation_unix: 1714567196.0,
+            max_elevation_deg: 42.5,
+            aos_azimuth_deg: 10.0,
+            los_azimuth_deg: 170.0,
+        };
+        let mut record = PassRecord::new(&pass, Some(437_375_000.0));
+        let orbit = OrbitState { illumination: Illumination::Sunlight, number: Some(1234) };
+        for (i, line) in ["BOTAN JS1YPT SI8640 A67C8D5E2AA13608", "BOTAN JS1YPT A57EB76823210F08", "BOTAN JS1YPT A67C"].iter().enumerate() {
+            let parsed = parse_botan_beacon(line).map(Frame::Housekeeping);
+            record.add_beacon(pass.aos_unix + 60.0 * (i + 1) as f64, line, Some(orbit), parsed);
+        }
+        let value = serde_json::to_value(&record).unwrap();
+        let mut rows = export::pass_rows(&value, None, None);
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.26}}"#).unwrap();
+        let report = report::summarise(&mut rows, &limits);
+        let html = page(&report, &rows, Some(&value), &limits);
+
+        assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+        assert!(html.contains("<title>BOTAN pass 2024-05-01T12:34:56Z</title>"));
+        assert!(html.contains("<tr><th>Frequency</th><td>437.3750 MHz</td></tr>"));
+        assert!(html.contains("<tr><th>Orbit</th><td>1234</td></tr>"));
+        assert_eq!(html.matches("<figure>").count(), 5);
+        assert!(html.contains("stroke-dasharray"), "the BAT_V limit is drawn");
+        // The frame below the BAT_V limit, and the line that failed to decode
+        assert!(html.contains("<td class=\"alarm\">4.254</td>"));
+        assert!(html.contains("<td class=\"text alarm\">Invalid data length"));
+        let frames = &html[html.find("<h2>Frames</h2>").unwrap()..];
+        assert_eq!(frames.matches("<tr><td>2024-05-01T12:3").count(), 3);
+        assert!(!html.contains("<script"));
+
+        let history = page(&report, &rows, None, &limits);
+        assert!(history.contains("<title>BOTAN status report</title>") && !history.contains("<h2>Frames</h2>"));
+    }
+}
@@ -0,0 +1,730 @@
+// The server side of HTTP/2 that the gRPC service needs: cleartext connections with prior
+// knowledge (h2c, which gRPC clients use without TLS), HPACK header decoding including
+// Huffman-coded strings, and flow control on what is sent. A request is handed over once its
+// body is complete, on a thread of its own, and its response can be written from there while
+// the connection keeps reading; a long server-streaming response does not hold up the others.
+// The server faces the network, so header blocks, bodies and open streams are limited.
+// Server push, priorities and TLS are not supported.
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// Frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+// Frame flags
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+// Settings
+const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+
+// Error codes
+const INTERNAL_ERROR: u32 = 0x2;
+const REFUSED_STREAM: u32 = 0x7;
+const ENHANCE_YOUR_CALM: u32 = 0xB;
+
+/// Frame payloads and windows before the peer's settings say otherwise
+const DEFAULT_MAX_FRAME: usize = 16384;
+const DEFAULT_WINDOW: i64 = 65535;
+const HEADER_TABLE_SIZE: usize = 4096;
+/// Request bodies larger than this end the connection
+const MAX_BODY: usize = 4 << 20;
+/// Header blocks larger than this, however many CONTINUATION frames carry them, end the connection
+const MAX_HEADER_BLOCK: usize = 64 << 10;
+/// Streams a client may have open at once, being received or answered; each answered one has a
+/// thread, and each received one up to MAX_BODY buffered. Streams above this are refused.
+const MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// The HPACK static table (RFC 7541 Appendix A), from index 1
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Code lengths of the HPACK Huffman code (RFC 7541 Appendix B) by symbol, 256 being the end of
+/// string. The code is canonical, so the codes themselves follow from the lengths.
+const HUFFMAN_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10,
+    13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6,
+    15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6, 6, 5, 6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23, 24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23, 21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25, 19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23, 26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+/// Symbols in code order, and the number of codes of each length
+fn huffman_table() -> &'static (Vec<u16>, [u32; 31]) {
+    static TABLE: OnceLock<(Vec<u16>, [u32; 31])> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut symbols: Vec<u16> = (0..257).collect();
+        symbols.sort_by_key(|&symbol| (HUFFMAN_LENGTHS[symbol as usize], symbol));
+        let mut counts = [0; 31];
+        for &length in &HUFFMAN_LENGTHS {
+            counts[length as usize] += 1;
+        }
+        (symbols, counts)
+    })
+}
+
+/// Decode a Huffman-coded header string
+fn huffman_decode(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let (symbols, counts) = huffman_table();
+    let mut out = Vec::new();
+    // The code read so far, its length, the first code of that length and its symbol's position
+    let (mut code, mut length, mut first, mut index) = (0u32, 0usize, 0u32, 0u32);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            code = code << 1 | (byte >> bit & 1) as u32;
+            length += 1;
+            let count = counts[length];
+            if code < first + count {
+                let symbol = symbols[(index + code - first) as usize];
+                if symbol == 256 {
+                    return Err("Huffman-coded header holds the end of string symbol".to_string());
+                }
+                out.push(symbol as u8);
+                (code, length, first, index) = (0, 0, 0, 0);
+            } else if length == 30 {
+                return Err("Invalid Huffman code in header".to_string());
+            } else {
+                index += count;
+                first = (first + count) << 1;
+            }
+        }
+    }
+    // Up to 7 bits of the end of string code pad the last byte
+    if length > 7 || code != (1 << length) - 1 {
+        return Err("Invalid Huffman padding in header".to_string());
+    }
+    Ok(out)
+}
+
+/// Read an HPACK integer with an `prefix`-bit prefix at `pos`
+fn read_integer(block: &[u8], pos: &mut usize, prefix: u32) -> Result<usize, String> {
+    let truncated = || "Truncated header block".to_string();
+    let mask = (1usize << prefix) - 1;
+    let mut value = *block.get(*pos).ok_or_else(truncated)? as usize & mask;
+    *pos += 1;
+    if value < mask {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let byte = *block.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        value += ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        if shift > 28 {
+            return Err("Header integer too large".to_string());
+        }
+    }
+}
+
+/// Read an HPACK string literal at `pos`
+fn read_string(block: &[u8], pos: &mut usize) -> Result<String, String> {
+    let huffman = block.get(*pos).is_some_and(|b| b & 0x80 != 0);
+    let length = read_integer(block, pos, 7)?;
+    let bytes = block.get(*pos..*pos + length).ok_or("Truncated header block")?;
+    *pos += length;
+    let bytes = if huffman { huffman_decode(bytes)? } else { bytes.to_vec() };
+    String::from_utf8(bytes).map_err(|_| "Header is not UTF-8".to_string())
+}
+
+fn write_integer(out: &mut Vec<u8>, first: u8, prefix: u32, mut value: usize) {
+    let mask = (1usize << prefix) - 1;
+    if value < mask {
+        out.push(first | value as u8);
+        return;
+    }
+    out.push(first | mask as u8);
+    value -= mask;
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Encode headers without touching the peer's dynamic table: literals without indexing, and
+/// the static entry for ":status: 200"
+pub fn encode_headers(headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (name, value) in headers {
+        if let Some(index) = STATIC_TABLE.iter().position(|entry| entry == &(*name, *value)) {
+            write_integer(&mut out, 0x80, 7, index + 1);
+            continue;
+        }
+        out.push(0);
+        for text in [name, value] {
+            write_integer(&mut out, 0, 7, text.len());
+            out.extend_from_slice(text.as_bytes());
+        }
+    }
+    out
+}
+
+/// The HPACK decoding context of one connection
+pub struct HeaderDecoder {
+    table: VecDeque<(String, String)>,   // Dynamic table, newest first
+    size: usize,
+    max_size: usize,
+}
+
+impl HeaderDecoder {
+    pub fn new() -> Self {
+        HeaderDecoder { table: VecDeque::new(), size: 0, max_size: HEADER_TABLE_SIZE }
+    }
+
+    fn entry(&self, index: usize) -> Result<(String, String), String> {
+        match index {
+            1..=61 => Ok((STATIC_TABLE[index - 1].0.to_string(), STATIC_TABLE[index - 1].1.to_string())),
+            _ => self.table.get(index.wrapping_sub(62)).cloned().ok_or_else(|| format!("Invalid header index {}", index)),
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            let (name, value) = self.table.pop_back().unwrap();
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>, String> {
+        let mut headers = Vec::new();
+        let mut pos = 0;
+        while pos < block.len() {
+            let byte = block[pos];
+            if byte & 0x80 != 0 {
+                let index = read_integer(block, &mut pos, 7)?;
+                headers.push(self.entry(index)?);
+            } else if byte & 0xE0 == 0x20 {
+                let size = read_integer(block, &mut pos, 5)?;
+                if size > HEADER_TABLE_SIZE {
+                    return Err("Header table size above the limit".to_string());
+                }
+                self.max_size = size;
+                self.evict();
+            } else {
+                // With incremental indexing, or without (never) indexing
+                let indexed = byte & 0xC0 == 0x40;
+                let index = read_integer(block, &mut pos, if indexed { 6 } else { 4 })?;
+                let name = if index == 0 { read_string(block, &mut pos)? } else { self.entry(index)?.0 };
+                let value = read_string(block, &mut pos)?;
+                if indexed {
+                    self.size += name.len() + value.len() + 32;
+                    self.table.push_front((name.clone(), value.clone()));
+                    self.evict();
+                }
+                headers.push((name, value));
+            }
+        }
+        Ok(headers)
+    }
+}
+
+/// A complete request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// What the connection knows about sending
+struct SendState {
+    connection_window: i64,
+    initial_window: i64,
+    max_frame: usize,
+    streams: HashMap<u32, i64>,   // Send windows of the streams still open for responses
+    closed: bool,
+}
+
+struct Connection {
+    writer: Mutex<TcpStream>,
+    state: Mutex<SendState>,
+    changed: Condvar,
+}
+
+impl Connection {
+    fn write_frame(&self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(9 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.writer.lock().unwrap_or_else(|e| e.into_inner()).write_all(&frame)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SendState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn close(&self) {
+        self.state().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Writes the response to one request
+pub struct Responder {
+    connection: Arc<Connection>,
+    stream: u32,
+}
+
+impl Responder {
+    fn gone() -> io::Error {
+        io::Error::new(io::ErrorKind::BrokenPipe, "Stream closed by the client")
+    }
+
+    fn finish(&self) {
+        self.connection.state().streams.remove(&self.stream);
+    }
+
+    /// Send a header block, the trailers when `end_stream`
+    pub fn headers(&self, headers: &[(&str, &str)], end_stream: bool) -> io::Result<()> {
+        let open = self.connection.state().streams.contains_key(&self.stream);
+        if !open {
+            return Err(Self::gone());
+        }
+        let flags = END_HEADERS | if end_stream { END_STREAM } else { 0 };
+        if end_stream {
+            self.finish();
+        }
+        self.connection.write_frame(HEADERS, flags, self.stream, &encode_headers(headers))
+    }
+
+    /// Send body bytes, waiting for the client to open its flow control windows as needed
+    pub fn data(&self, mut data: &[u8], end_stream: bool) -> io::Result<()> {
+        loop {
+            let chunk = {
+                let mut state = self.connection.state();
+                loop {
+                    if state.closed {
+                        return Err(Self::gone());
+                    }
+                    let Some(&window) = state.streams.get(&self.stream) else {
+                        return Err(Self::gone());
+                    };
+                    if data.is_empty() || (window > 0 && state.connection_window > 0) {
+                        let chunk = (data.len() as i64).min(window).min(state.connection_window).min(state.max_frame as i64) as usize;
+                        *state.streams.get_mut(&self.stream).unwrap() -= chunk as i64;
+                        state.connection_window -= chunk as i64;
+                        break chunk;
+                    }
+                    state = self.connection.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+            };
+            let last = chunk == data.len();
+            if last && end_stream {
+                self.finish();
+            }
+            self.connection.write_frame(DATA, if last && end_stream { END_STREAM } else { 0 }, self.stream, &data[..chunk])?;
+            data = &data[chunk..];
+            if last {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Whether the client still wants the response
+    pub fn is_open(&self) -> bool {
+        let state = self.connection.state();
+        !state.closed && state.streams.contains_key(&self.stream)
+    }
+}
+
+impl Drop for Responder {
+    /// A response given up without ending its stream resets it, so it stops counting as open
+    fn drop(&mut self) {
+        let open = self.connection.state().streams.remove(&self.stream).is_some();
+        if open {
+            self.connection.changed.notify_all();
+            let _ = self.connection.write_frame(RST_STREAM, 0, self.stream, &INTERNAL_ERROR.to_be_bytes());
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Strip the padding of a padded frame
+fn unpad(payload: &[u8], flags: u8) -> io::Result<&[u8]> {
+    if flags & PADDED == 0 {
+        return Ok(payload);
+    }
+    let pad = *payload.first().ok_or_else(|| invalid("Empty padded frame"))? as usize;
+    payload.get(1..payload.len().saturating_sub(pad)).filter(|_| pad < payload.len()).ok_or_else(|| invalid("Invalid padding"))
+}
+
+/// Serve one client connection until it closes, calling `handle` on a new thread for every
+/// complete request
+pub fn serve_connection(stream: TcpStream, handle: Arc<dyn Fn(Request, Responder) + Send + Sync>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let connection = Arc::new(Connection {
+        writer: Mutex::new(stream),
+        state: Mutex::new(SendState {
+            connection_window: DEFAULT_WINDOW,
+            initial_window: DEFAULT_WINDOW,
+            max_frame: DEFAULT_MAX_FRAME,
+            streams: HashMap::new(),
+            closed: false,
+        }),
+        changed: Condvar::new(),
+    });
+    let result = read_frames(&mut reader, &connection, handle);
+    connection.close();
+    result
+}
+
+fn read_frames(reader: &mut impl Read, connection: &Arc<Connection>, handle: Arc<dyn Fn(Request, Responder) + Send + Sync>) -> io::Result<()> {
+    let mut preface = [0; PREFACE.len()];
+    reader.read_exact(&mut preface)?;
+    if preface != PREFACE {
+        return Err(invalid("Not an HTTP/2 prior knowledge connection"));
+    }
+    let mut settings = SETTINGS_MAX_CONCURRENT_STREAMS.to_be_bytes().to_vec();
+    settings.extend_from_slice(&MAX_CONCURRENT_STREAMS.to_be_bytes());
+    connection.write_frame(SETTINGS, 0, 0, &settings)?;
+
+    let mut decoder = HeaderDecoder::new();
+    let mut last_stream = 0u32;
+    let mut requests: HashMap<u32, Request> = HashMap::new();
+    // A header block continued in CONTINUATION frames: stream, block so far, END_STREAM
+    let mut continued: Option<(u32, Vec<u8>, bool)> = None;
+    loop {
+        let mut header = [0; 9];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let (kind, flags) = (header[3], header[4]);
+        let stream = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF;
+        if length > DEFAULT_MAX_FRAME {
+            return Err(invalid("Frame larger than the maximum frame size"));
+        }
+        let mut payload = vec![0; length];
+        reader.read_exact(&mut payload)?;
+        if continued.is_some() && kind != CONTINUATION {
+            return Err(invalid("Header block interrupted"));
+        }
+
+        let block = match kind {
+            HEADERS => {
+                let mut fragment = unpad(&payload, flags)?;
+                if flags & PRIORITY != 0 {
+                    fragment = fragment.get(5..).ok_or_else(|| invalid("Short HEADERS frame"))?;
+                }
+                Some((stream, fragment.to_vec(), flags & END_STREAM != 0))
+            }
+            CONTINUATION => {
+                let Some((first, mut block, end_stream)) = continued.take().filter(|(first, _, _)| *first == stream) else {
+                    return Err(invalid("Unexpected CONTINUATION frame"));
+                };
+                block.extend_from_slice(&payload);
+                if block.len() > MAX_HEADER_BLOCK {
+                    // A flood of CONTINUATION frames
+                    let mut goaway = last_stream.to_be_bytes().to_vec();
+                    goaway.extend_from_slice(&ENHANCE_YOUR_CALM.to_be_bytes());
+                    connection.write_frame(GOAWAY, 0, 0, &goaway)?;
+                    return Err(invalid("Header block too large"));
+                }
+                Some((first, block, end_stream))
+            }
+            DATA => {
+                let data = unpad(&payload, flags)?;
+                if let Some(request) = requests.get_mut(&stream) {
+                    if request.body.len() + data.len() > MAX_BODY {
+                        return Err(invalid("Request body too large"));
+                    }
+                    request.body.extend_from_slice(data);
+                }
+                // Give the flow control credit back at once
+                if !payload.is_empty() {
+                    let increment = (payload.len() as u32).to_be_bytes();
+                    connection.write_frame(WINDOW_UPDATE, 0, 0, &increment)?;
+                    if flags & END_STREAM == 0 {
+                        connection.write_frame(WINDOW_UPDATE, 0, stream, &increment)?;
+                    }
+                }
+                if flags & END_STREAM != 0 {
+                    dispatch(&mut requests, stream, connection, &handle);
+                }
+                None
+            }
+            SETTINGS if flags & ACK == 0 => {
+                let mut state = connection.state();
+                for setting in payload.chunks_exact(6) {
+                    let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+                    match u16::from_be_bytes([setting[0], setting[1]]) {
+                        SETTINGS_INITIAL_WINDOW_SIZE => {
+                            let delta = value as i64 - state.initial_window;
+                            state.initial_window = value as i64;
+                            state.streams.values_mut().for_each(|window| *window += delta);
+                        }
+                        SETTINGS_MAX_FRAME_SIZE => state.max_frame = (value as usize).clamp(DEFAULT_MAX_FRAME, 1 << 24),
+                        SETTINGS_HEADER_TABLE_SIZE => {}    // Responses never use the dynamic table
+                        _ => {}
+                    }
+                }
+                drop(state);
+                connection.changed.notify_all();
+                connection.write_frame(SETTINGS, ACK, 0, &[])?;
+                None
+            }
+            PING if flags & ACK == 0 => {
+                connection.write_frame(PING, ACK, 0, &payload)?;
+                None
+            }
+            WINDOW_UPDATE => {
+                let increment = u32::from_be_bytes(payload.get(..4).ok_or_else(|| invalid("Short WINDOW_UPDATE"))?.try_into().unwrap()) & 0x7FFF_FFFF;
+                let mut state = connection.state();
+                match stream {
+                    0 => state.connection_window += increment as i64,
+                    _ => {
+                        if let Some(window) = state.streams.get_mut(&stream) {
+                            *window += increment as i64;
+                        }
+                    }
+                }
+                drop(state);
+                connection.changed.notify_all();
+                None
+            }
+            RST_STREAM => {
+                requests.remove(&stream);
+                connection.state().streams.remove(&stream);
+                connection.changed.notify_all();
+                None
+            }
+            GOAWAY => return Ok(()),
+            // PRIORITY, settings and ping acknowledgements, unknown types
+            _ => None,
+        };
+
+        if let Some((stream, block, end_stream)) = block {
+            if flags & END_HEADERS == 0 {
+                continued = Some((stream, block, end_stream));
+                continue;
+            }
+            let headers = decoder.decode(&block).map_err(|e| invalid(&e))?;
+            // Trailers of a request carry nothing the service needs
+            if let Entry::Vacant(entry) = requests.entry(stream) {
+                if connection.state().streams.len() >= MAX_CONCURRENT_STREAMS as usize {
+                    connection.write_frame(RST_STREAM, 0, stream, &REFUSED_STREAM.to_be_bytes())?;
+                    continue;
+                }
+                last_stream = last_stream.max(stream);
+                let path = headers.iter().find(|(name, _)| name == ":path").map(|(_, path)| path.clone()).unwrap_or_default();
+                entry.insert(Request { path, headers, body: Vec::new() });
+                let mut state = connection.state();
+                let window = state.initial_window;
+                state.streams.insert(stream, window);
+            }
+            if end_stream {
+                dispatch(&mut requests, stream, connection, &handle);
+            }
+        }
+    }
+}
+
+fn dispatch(requests: &mut HashMap<u32, Request>, stream: u32, connection: &Arc<Connection>, handle: &Arc<dyn Fn(Request, Responder) + Send + Sync>) {
+    let Some(request) = requests.remove(&stream) else {
+        return;
+    };
+    let responder = Responder { connection: Arc::clone(connection), stream };
+    let handle = Arc::clone(handle);
+    thread::spawn(move || handle(request, responder));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits: Vec<u8> = text.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits.chunks(2).map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_huffman() {
+        // RFC 7541 C.4.1
+        assert_eq!(huffman_decode(&hex("f1e3 c2e5 f23a 6ba0 ab90 f4ff")).unwrap(), b"www.example.com");
+        assert_eq!(huffman_decode(&hex("a8eb 1064 9cbf")).unwrap(), b"no-cache");
+        // Padding longer than 7 bits, or not all ones
+        assert!(huffman_decode(&hex("f1e3 c2e5 f23a 6ba0 ab90 f4ff ff")).is_err());
+        assert!(huffman_decode(&hex("f1e3 c2e5 f23a 6ba0 ab90 f4fe")).is_err());
+    }
+
+    #[test]
+    fn test_header_decoding() {
+        // RFC 7541 C.4: three requests sharing the dynamic table
+        let mut decoder = HeaderDecoder::new();
+        let first = decoder.decode(&hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff")).unwrap();
+        assert_eq!(first[3], (":authority".to_string(), "www.example.com".to_string()));
+        let second = decoder.decode(&hex("8286 84be 5886 a8eb 1064 9cbf")).unwrap();
+        assert_eq!(second[3], (":authority".to_string(), "www.example.com".to_string()));
+        assert_eq!(second[4], ("cache-control".to_string(), "no-cache".to_string()));
+        let third = decoder.decode(&hex("8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf")).unwrap();
+        assert_eq!(third[4], ("custom-key".to_string(), "custom-value".to_string()));
+        assert_eq!(decoder.size, 164);
+
+        let encoded = encode_headers(&[(":status", "200"), ("grpc-status", "0")]);
+        assert_eq!(encoded[0], 0x88);
+        let decoded = HeaderDecoder::new().decode(&encoded).unwrap();
+        assert_eq!(decoded[1], ("grpc-status".to_string(), "0".to_string()));
+        assert!(HeaderDecoder::new().decode(&[0xFF]).is_err());
+    }
+
+    /// Connect a client to a server that never answers, past the preface and settings
+    fn connect() -> (TcpStream, thread::JoinHandle<io::Result<()>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let server = thread::spawn(move || serve_connection(stream, Arc::new(|_, _| {})));
+        client.write_all(PREFACE).unwrap();
+        let (kind, _, payload) = read_frame(&mut client);
+        assert_eq!(kind, SETTINGS);
+        assert_eq!(payload, [0, 3, 0, 0, 0, 100]);
+        (client, server)
+    }
+
+    fn write_frame(client: &mut TcpStream, kind: u8, flags: u8, stream: u32, payload: &[u8]) {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        client.write_all(&frame).unwrap();
+    }
+
+    fn read_frame(client: &mut TcpStream) -> (u8, u32, Vec<u8>) {
+        let mut header = [0; 9];
+        client.read_exact(&mut header).unwrap();
+        let mut payload = vec![0; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+        client.read_exact(&mut payload).unwrap();
+        (header[3], u32::from_be_bytes(header[5..].try_into().unwrap()), payload)
+    }
+
+    #[test]
+    fn test_continuation_flood() {
+        let (mut client, server) = connect();
+        let request = encode_headers(&[(":method", "POST"), (":path", "/")]);
+        write_frame(&mut client, HEADERS, 0, 1, &request);
+        // The fourth takes the block past the limit
+        for _ in 0..4 {
+            write_frame(&mut client, CONTINUATION, 0, 1, &[0; 16384]);
+        }
+        let goaway = loop {
+            match read_frame(&mut client) {
+                (GOAWAY, _, payload) => break payload,
+                _ => continue,
+            }
+        };
+        assert_eq!(goaway[4..], ENHANCE_YOUR_CALM.to_be_bytes());
+        assert!(server.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_concurrent_streams() {
+        let (mut client, server) = connect();
+        let request = encode_headers(&[(":method", "POST"), (":path", "/")]);
+        for stream in 0..=MAX_CONCURRENT_STREAMS {
+            write_frame(&mut client, HEADERS, END_HEADERS, 2 * stream + 1, &request);
+        }
+        let refused = loop {
+            match read_frame(&mut client) {
+                (RST_STREAM, stream, payload) => break (stream, payload),
+                _ => continue,
+            }
+        };
+        assert_eq!(refused, (2 * MAX_CONCURRENT_STREAMS + 1, REFUSED_STREAM.to_be_bytes().to_vec()));
+        drop(client);
+        assert!(server.join().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use crate::orbit::Illumination;
+
+/// Language of the human-readable beacon report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" | "english" => Ok(Lang::En),
+            "ja" | "jp" | "japanese" => Ok(Lang::Ja),
+            _ => Err(format!("Unknown language '{}'. Expected en or ja", s)),
+        }
+    }
+}
+
+impl Lang {
+    pub fn labels(self) -> &'static Labels {
+        match self {
+            Lang::En => &EN,
+            Lang::Ja => &JA,
+        }
+    }
+}
+
+/// Field names and status words of the report in one language
+pub struct Labels {
+    pub title: &'static str,
+    pub satellite: &'static str,
+    pub call_sign: &'static str,
+    pub confidence: &'static str,
+    pub signal: &'static str,
+    pub rssi: &'static str,
+    pub snr: &'static str,
+    pub uncalibrated: &'static str,
+    pub not_available: &'static str,
+    pub telemetry: &'static str,
+    pub bat_v: &'static str,
+    pub bat_i: &'static str,
+    pub bat_t: &'static str,
+    pub bpb_t: &'static str,
+    pub raw_i: &'static str,
+    pub power: &'static str,
+    pub power_5v0: &'static str,
+    pub power_depant: &'static str,
+    pub power_com: &'static str,
+    pub solar_panels: &'static str,
+    pub commands: &'static str,
+    pub reserve_cmd_counter: &'static str,
+    pub cmd_uplink_counter: &'static str,
+    pub kill_sw: &'static str,
+    pub mission: &'static str,
+    pub kill_counter: &'static str,
+    pub mission_pic: &'static str,
+    pub mis_error: &'static str,
+    pub mis_end: &'static str,
+    pub aprs: &'static str,
+    pub current_mis: &'static str,
+    pub on_off: [&'static str; 2],        // [false, true]
+    pub yes_no: [&'static str; 2],
+    pub active: [&'static str; 2],
+    pub missions: [&'static str; 4],      // None, Earth, Sun, unknown
+    pub illumination: &'static str,
+    pub sunlight: &'static str,
+    pub eclipse: &'static str,
+    pub orbit: &'static str,
+}
+
+impl Labels {
+    pub fn on_off(&self, on: bool) -> &'static str {
+        self.on_off[on as usize]
+    }
+
+    pub fn yes_no(&self, yes: bool) -> &'static str {
+        self.yes_no[yes as usize]
+    }
+
+    pub fn active(&self, active: bool) -> &'static str {
+        self.active[active as usize]
+    }
+
+    pub fn mission_name(&self, mission: u8) -> &'static str {
+        self.missions[(mission as usize).min(3)]
+    }
+
+    pub fn illumination_state(&self, illumination: Illumination) -> &'static str {
+        match illumination {
+            Illumination::Sunlight => self.sunlight,
+            Illumination::Eclipse => self.eclipse,
+        }
+    }
+}
+
+static EN: Labels = Labels {
+    title: "BOTAN Satellite Beacon Data",
+    satellite: "Satellite",
+    call_sign: "Call Sign",
+    confidence: "Confidence",
+    signal: "Signal Information",
+    rssi: "RSSI",
+    snr: "SNR",
+    uncalibrated: " (uncalibrated)",
+    not_available: "N/A",
+    telemetry: "Telemetry Data",
+    bat_v: "Battery Voltage",
+    bat_i: "Battery Current",
+    bat_t: "Battery Temperature",
+    bpb_t: "Board Temperature",
+    raw_i: "Current Consumption",
+    power: "Power System Status",
+    power_5v0: "5V Power Line",
+    power_depant: "Antenna Deployment",
+    power_com: "Transponder",
+    solar_panels: "Solar Panels",
+    commands: "Command Status",
+    reserve_cmd_counter: "Reserved Commands",
+    cmd_uplink_counter: "Uplink Commands",
+    kill_sw: "KILL Switch",
+    mission: "Mission Status",
+    kill_counter: "KILL Counter",
+    mission_pic: "Mission PIC",
+    mis_error: "Mission Error",
+    mis_end: "Mission End",
+    aprs: "APRS Mission",
+    current_mis: "Current Mission",
+    on_off: ["OFF", "ON"],
+    yes_no: ["NO", "YES"],
+    active: ["INACTIVE", "ACTIVE"],
+    missions: ["None", "Earth", "Sun", "Unknown"],
+    illumination: "Illumination",
+    sunlight: "Sunlight",
+    eclipse: "Eclipse",
+    orbit: "Orbit",
+};
+
+static JA: Labels = Labels {
+    title: "BOTAN 衛星ビーコンデータ",
+    satellite: "衛星",
+    call_sign: "コールサイン",
+    confidence: "信頼度",
+    signal: "信号情報",
+    rssi: "RSSI",
+    snr: "SNR",
+    uncalibrated: "（未校正）",
+    not_available: "なし",
+    telemetry: "テレメトリ",
+    bat_v: "バッテリ電圧",
+    bat_i: "バッテリ電流",
+    bat_t: "バッテリ温度",
+    bpb_t: "基板温度",
+    raw_i: "消費電流",
+    power: "電源系ステータス",
+    power_5v0: "5V電源ライン",
+    power_depant: "アンテナ展開電源",
+    power_com: "トランスポンダ",
+    solar_panels: "太陽電池パネル発電",
+    commands: "コマンドステータス",
+    reserve_cmd_counter: "予約コマンド数",
+    cmd_uplink_counter: "受信コマンド数",
+    kill_sw: "KILLスイッチ",
+    mission: "ミッションステータス",
+    kill_counter: "KILL回数",
+    mission_pic: "ミッションPIC",
+    mis_error: "ミッションエラー",
+    mis_end: "ミッション終了",
+    aprs: "APRSミッション",
+    current_mis: "実行中ミッション",
+    on_off: ["オフ", "オン"],
+    yes_no: ["いいえ", "はい"],
+    active: ["停止中", "実行中"],
+    missions: ["なし", "地球", "太陽", "不明"],
+    illumination: "日照状態",
+    sunlight: "日照",
+    eclipse: "日陰",
+    orbit: "周回番号",
+};
+
+/// Terminal columns taken by `text`; CJK and full-width characters take two
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// `text` as typed on an ASCII keyboard: full-width letters, digits and signs from a Japanese IME
+/// become ASCII, ideographic and other Unicode spaces plain ones, and zero-width characters go
+pub fn normalize_width(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.chars()
+            .filter(|c| !matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}'))
+            .map(|c| match c {
+                '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+                c if c.is_whitespace() && !c.is_ascii() => ' ',
+                c => c,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lang_and_width() {
+        assert_eq!("ja".parse::<Lang>(), Ok(Lang::Ja));
+        assert_eq!("EN".parse::<Lang>(), Ok(Lang::En));
+        assert!("fr".parse::<Lang>().is_err());
+        assert_eq!(display_width("KILL Switch"), 11);
+        assert_eq!(display_width("KILLスイッチ"), 12);
+        assert_eq!(display_width("（未校正）"), 10);
+        assert_eq!(Lang::Ja.labels().mission_name(7), "不明");
+    }
+
+    #[test]
+    fn test_normalize_width() {
+        assert_eq!(normalize_width("ＢＯＴＡＮ　ＪＳ１ＹＰＴ\u{00A0}ＳＩ８６４０ ９ａ"), "BOTAN JS1YPT SI8640 9a");
+        assert_eq!(normalize_width("\u{FEFF}BOTAN JS1\u{200B}YPT"), "BOTAN JS1YPT");
+        assert!(matches!(normalize_width("BOTAN JS1YPT"), Cow::Borrowed(_)));
+        // Other CJK text is left alone
+        assert_eq!(normalize_width("日照（ＯＮ）"), "日照(ON)");
+        assert_eq!(normalize_width("日陰"), "日陰");
+    }
+}
@@ -0,0 +1,130 @@
+// Integrity checks of frames that carry check bytes. The housekeeping frame has none, but a frame
+// type in --frame-types may name a codec and the field holding the check value; the value is
+// computed over every byte before that field and compared before any field is converted. The
+// outcome is part of the decoded frame, so a frame that fails is still shown, marked as such.
+use serde::{Deserialize, Serialize};
+
+use crate::ax25;
+use crate::field::Field;
+
+/// Algorithms a check field can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Codec {
+    Crc16X25,      // As the AX.25 FCS: reflected 0x1021, init and final XOR 0xFFFF
+    Crc16Ccitt,    // CRC-16/CCITT-FALSE: 0x1021, init 0xFFFF
+    Crc8,          // CRC-8/SMBUS: 0x07, init 0
+    Sum8,          // Sum of the bytes modulo 256
+    Xor8,          // XOR of the bytes
+    Parity,        // Even parity bit
+}
+
+impl Codec {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Crc16X25 => "CRC-16/X.25",
+            Codec::Crc16Ccitt => "CRC-16/CCITT",
+            Codec::Crc8 => "CRC-8",
+            Codec::Sum8 => "Sum-8",
+            Codec::Xor8 => "XOR-8",
+            Codec::Parity => "Parity",
+        }
+    }
+
+    /// Bits of the check value
+    fn width(&self) -> u8 {
+        match self {
+            Codec::Crc16X25 | Codec::Crc16Ccitt => 16,
+            Codec::Crc8 | Codec::Sum8 | Codec::Xor8 => 8,
+            Codec::Parity => 1,
+        }
+    }
+
+    /// Check value of `data`
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        match self {
+            Codec::Crc16X25 => ax25::fcs(data) as u32,
+            Codec::Crc16Ccitt => data.iter().fold(0xFFFFu16, |crc, &byte| {
+                (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| if crc & 0x8000 != 0 { crc << 1 ^ 0x1021 } else { crc << 1 })
+            }) as u32,
+            Codec::Crc8 => data.iter().fold(0u8, |crc, &byte| {
+                (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { crc << 1 ^ 0x07 } else { crc << 1 })
+            }) as u32,
+            Codec::Sum8 => data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) as u32,
+            Codec::Xor8 => data.iter().fold(0, |acc, &byte| acc ^ byte) as u32,
+            Codec::Parity => data.iter().map(|byte| byte.count_ones()).sum::<u32>() & 1,
+        }
+    }
+}
+
+/// Where a frame type keeps its check value, and how it is computed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Check {
+    pub codec: Codec,
+    pub field: Field,    // Covers every byte before the one this field starts in
+}
+
+/// Outcome of checking one frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckResult {
+    pub codec: &'static str,
+    pub received: u32,
+    pub computed: u32,
+    pub passed: bool,
+}
+
+impl Check {
+    /// The field must be unsigned and as wide as the codec's value
+    pub fn validate(&self) -> Result<(), String> {
+        if self.field.signed || self.field.width != self.codec.width() {
+            return Err(format!(
+                "{} needs an unsigned {}-bit field, not {}",
+                self.codec.name(),
+                self.codec.width(),
+                self.field
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compare the check value in `data` with the one computed from it
+    pub fn verify(&self, data: &[u8]) -> Result<CheckResult, String> {
+        let received = self.field.extract(data)? as u32;
+        let computed = self.codec.compute(&data[..self.field.offset / 8]);
+        Ok(CheckResult { codec: self.codec.name(), received, computed, passed: received == computed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codecs() {
+        // The standard check input of each CRC catalogue entry
+        let data = b"123456789";
+        assert_eq!(Codec::Crc16X25.compute(data), 0x906E);
+        assert_eq!(Codec::Crc16Ccitt.compute(data), 0x29B1);
+        assert_eq!(Codec::Crc8.compute(data), 0xF4);
+        assert_eq!(Codec::Sum8.compute(data), 0xDD);
+        assert_eq!(Codec::Xor8.compute(data), 0x31);
+        assert_eq!(Codec::Parity.compute(&[0x01, 0x03]), 1);
+    }
+
+    #[test]
+    fn test_verify() {
+        let check: Check = serde_json::from_str(r#"{"codec": "crc16-ccitt", "field": "u16be@72"}"#).unwrap();
+        check.validate().unwrap();
+        let mut data = b"123456789\x29\xB1".to_vec();
+        assert!(check.verify(&data).unwrap().passed);
+        data[0] ^= 1;
+        let result = check.verify(&data).unwrap();
+        assert_eq!((result.received, result.passed), (0x29B1, false));
+        assert!(check.verify(&data[..10]).is_err());
+
+        let wrong: Check = serde_json::from_str(r#"{"codec": "crc8", "field": "u16be@0"}"#).unwrap();
+        assert_eq!(wrong.validate().unwrap_err(), "CRC-8 needs an unsigned 8-bit field, not unsigned 16-bit big-endian at bit 0");
+        assert!(serde_json::from_str::<Check>(r#"{"codec": "md5", "field": "u8@0"}"#).is_err());
+    }
+}
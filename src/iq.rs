@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+/// A single complex baseband sample
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn norm(&self) -> f32 {
+        (self.re * self.re + self.im * self.im).sqrt()
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// Sample encodings used by common SDR recording tools
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IqFormat {
+    Cu8,   // Interleaved unsigned 8-bit I/Q (rtl_sdr native)
+    Cs8,   // Interleaved signed 8-bit I/Q
+    Cs16,  // Interleaved signed 16-bit little-endian I/Q
+    Cf32,  // Interleaved 32-bit float little-endian I/Q
+}
+
+impl IqFormat {
+    /// Size in bytes of one complex sample
+    pub fn sample_size(&self) -> usize {
+        match self {
+            IqFormat::Cu8 | IqFormat::Cs8 => 2,
+            IqFormat::Cs16 => 4,
+            IqFormat::Cf32 => 8,
+        }
+    }
+
+    /// Convert a buffer of whole samples, ignoring any trailing partial sample
+    pub fn convert_block(&self, raw: &[u8], out: &mut Vec<Complex>) {
+        for sample in raw.chunks_exact(self.sample_size()) {
+            out.push(self.convert(sample));
+        }
+    }
+
+    /// Convert one complex sample from raw bytes, scaled to roughly +/-1.0
+    fn convert(&self, raw: &[u8]) -> Complex {
+        match self {
+            IqFormat::Cu8 => Complex::new(
+                (raw[0] as f32 - 127.5) / 128.0,
+                (raw[1] as f32 - 127.5) / 128.0,
+            ),
+            IqFormat::Cs8 => Complex::new(
+                raw[0] as i8 as f32 / 128.0,
+                raw[1] as i8 as f32 / 128.0,
+            ),
+            IqFormat::Cs16 => Complex::new(
+                i16::from_le_bytes([raw[0], raw[1]]) as f32 / 32768.0,
+                i16::from_le_bytes([raw[2], raw[3]]) as f32 / 32768.0,
+            ),
+            IqFormat::Cf32 => Complex::new(
+                f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]),
+                f32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]),
+            ),
+        }
+    }
+}
+
+impl FromStr for IqFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cu8" | "u8" => Ok(IqFormat::Cu8),
+            "cs8" | "s8" => Ok(IqFormat::Cs8),
+            "cs16" | "s16" => Ok(IqFormat::Cs16),
+            "cf32" | "fc32" | "f32" => Ok(IqFormat::Cf32),
+            _ => Err(format!("Unknown IQ format '{}'. Expected cu8, cs8, cs16 or cf32", s)),
+        }
+    }
+}
+
+/// Reads a raw IQ recording in fixed-size blocks so multi-gigabyte passes never sit in memory
+pub struct IqReader<R: Read> {
+    reader: R,
+    format: IqFormat,
+    buffer: Vec<u8>,
+    pending: usize,
+}
+
+impl IqReader<BufReader<File>> {
+    pub fn open(path: &str, format: IqFormat) -> io::Result<Self> {
+        Ok(IqReader::new(BufReader::new(File::open(path)?), format))
+    }
+}
+
+impl<R: Read> IqReader<R> {
+    pub fn new(reader: R, format: IqFormat) -> Self {
+        IqReader {
+            reader,
+            format,
+            buffer: vec![0; 65536 * format.sample_size()],
+            pending: 0,
+        }
+    }
+
+    /// Read the next block of samples; an empty block means end of file
+    pub fn read_block(&mut self, out: &mut Vec<Complex>) -> io::Result<usize> {
+        out.clear();
+        let size = self.format.sample_size();
+
+        loop {
+            let read = self.reader.read(&mut self.buffer[self.pending..])?;
+            self.pending += read;
+            if read == 0 || self.pending >= size {
+                break;
+            }
+        }
+
+        let whole = self.pending - self.pending % size;
+        self.format.convert_block(&self.buffer[..whole], out);
+        // Keep a trailing partial sample for the next read
+        self.buffer.copy_within(whole..self.pending, 0);
+        self.pending -= whole;
+        Ok(out.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("CS16".parse::<IqFormat>().unwrap(), IqFormat::Cs16);
+        assert_eq!("cf32".parse::<IqFormat>().unwrap(), IqFormat::Cf32);
+        assert_eq!("cu8".parse::<IqFormat>().unwrap(), IqFormat::Cu8);
+        assert!("wav".parse::<IqFormat>().is_err());
+    }
+
+    #[test]
+    fn test_read_cs16() {
+        let mut raw = Vec::new();
+        for value in [16384i16, -16384, 0, 32767] {
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+        raw.push(0xFF); // Truncated trailing sample is ignored
+
+        let mut reader = IqReader::new(raw.as_slice(), IqFormat::Cs16);
+        let mut block = Vec::new();
+        assert_eq!(reader.read_block(&mut block).unwrap(), 2);
+        assert_eq!(block[0], Complex::new(0.5, -0.5));
+        assert_eq!(reader.read_block(&mut block).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_convert_cu8() {
+        let mut block = Vec::new();
+        IqFormat::Cu8.convert_block(&[255, 0, 128], &mut block);
+        assert_eq!(block.len(), 1);
+        assert!((block[0].re - 0.996).abs() < 0.01);
+        assert!((block[0].im + 0.996).abs() < 0.01);
+    }
+}
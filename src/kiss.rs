@@ -0,0 +1,59 @@
+// KISS framing as spoken by TNCs and soft modems such as Direwolf (TCP port 8001): frames are
+// delimited by FEND, with FEND and FESC inside a frame escaped. The first byte of each frame
+// holds the TNC port and command; only data frames (command 0) are passed on.
+const FEND: u8 = 0xC0;
+const FESC: u8 = 0xDB;
+const TFEND: u8 = 0xDC;
+const TFESC: u8 = 0xDD;
+
+/// Splits a KISS byte stream, fed in arbitrary chunks, into the frames it carries
+#[derive(Debug, Default)]
+pub struct KissDecoder {
+    frame: Vec<u8>,
+    escaped: bool,
+}
+
+impl KissDecoder {
+    /// Add received bytes, returning the data frames they complete without their type byte
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            match (byte, self.escaped) {
+                (FEND, _) => {
+                    self.escaped = false;
+                    let frame = std::mem::take(&mut self.frame);
+                    if let Some((&kind, data)) = frame.split_first() {
+                        if kind & 0x0F == 0 && !data.is_empty() {
+                            frames.push(data.to_vec());
+                        }
+                    }
+                }
+                (FESC, false) => self.escaped = true,
+                (_, true) => {
+                    self.escaped = false;
+                    self.frame.push(match byte {
+                        TFEND => FEND,
+                        TFESC => FESC,
+                        other => other,
+                    });
+                }
+                (_, false) => self.frame.push(byte),
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kiss_decoder() {
+        let mut kiss = KissDecoder::default();
+        // Split mid-escape; the second frame is a TX delay command and is dropped
+        assert!(kiss.push(&[FEND, 0x00, 0x01, FESC]).is_empty());
+        let frames = kiss.push(&[TFEND, FESC, TFESC, 0x02, FEND, FEND, 0x01, 0x32, FEND, 0x10, 0x07, FEND]);
+        assert_eq!(frames, [vec![0x01, FEND, FESC, 0x02], vec![0x07]]);
+    }
+}
@@ -0,0 +1,99 @@
+use serde::Deserialize;
+
+use crate::botan_parser::BotanTelemetry;
+
+/// How close a telemetry value is to its configured limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Normal,
+    Warning,    // Within the warning margin of a limit
+    Alarm,      // Outside the limits
+}
+
+/// Allowed range of one telemetry value, in V, mA or °C whatever the display units
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub warn: f64,    // Margin inside min/max that already counts as a warning
+}
+
+impl Limit {
+    pub fn check(&self, value: f64) -> Severity {
+        let below = |limit: Option<f64>, margin: f64| limit.is_some_and(|min| value < min + margin);
+        let above = |limit: Option<f64>, margin: f64| limit.is_some_and(|max| value > max - margin);
+        if below(self.min, 0.0) || above(self.max, 0.0) {
+            Severity::Alarm
+        } else if below(self.min, self.warn) || above(self.max, self.warn) {
+            Severity::Warning
+        } else {
+            Severity::Normal
+        }
+    }
+}
+
+/// Limits for the analog telemetry values; values without a limit are never flagged
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    pub bat_v: Option<Limit>,
+    pub bat_i: Option<Limit>,
+    pub bat_t: Option<Limit>,
+    pub bpb_t: Option<Limit>,
+    pub raw_i: Option<Limit>,
+}
+
+impl Limits {
+    /// Load JSON limits, e.g. {"bat_v": {"min": 3.5, "max": 4.3, "warn": 0.1}}
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read limits '{}': {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid limits '{}': {}", path, e))
+    }
+
+    /// Names of the values of `t` outside their limits
+    pub fn alarms(&self, t: &BotanTelemetry) -> Vec<&'static str> {
+        self.severities(t).into_iter().filter(|&(_, severity)| severity == Severity::Alarm).map(|(name, _)| name).collect()
+    }
+
+    /// Each analog value of `t` by name, with how close it is to its limits
+    pub fn severities(&self, t: &BotanTelemetry) -> [(&'static str, Severity); 5] {
+        [
+            ("bat_v", check(self.bat_v, t.bat_v)),
+            ("bat_i", check(self.bat_i, t.bat_i)),
+            ("bat_t", check(self.bat_t, t.bat_t)),
+            ("bpb_t", check(self.bpb_t, t.bpb_t)),
+            ("raw_i", check(self.raw_i, t.raw_i)),
+        ]
+    }
+}
+
+/// Severity of `value` under an optional limit
+pub fn check(limit: Option<Limit>, value: f64) -> Severity {
+    limit.map_or(Severity::Normal, |limit| limit.check(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_severity() {
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"min": 3.5, "max": 4.3, "warn": 0.1}, "bat_t": {"max": 45}}"#).unwrap();
+        assert_eq!(check(limits.bat_v, 4.0), Severity::Normal);
+        assert_eq!(check(limits.bat_v, 3.55), Severity::Warning);
+        assert_eq!(check(limits.bat_v, 4.25), Severity::Warning);
+        assert_eq!(check(limits.bat_v, 3.4), Severity::Alarm);
+        assert_eq!(check(limits.bat_t, -30.0), Severity::Normal);
+        assert_eq!(check(limits.bat_t, 46.0), Severity::Alarm);
+        assert_eq!(check(limits.raw_i, 1e6), Severity::Normal);
+        assert!(serde_json::from_str::<Limits>(r#"{"bat_x": {}}"#).is_err());
+
+        let t = crate::botan_parser::parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap().telemetry;
+        assert_eq!(limits.alarms(&t), Vec::<&str>::new());
+        let strict: Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.3}, "bpb_t": {"max": 30}}"#).unwrap();
+        assert_eq!(strict.alarms(&t), ["bat_v", "bpb_t"]);
+    }
+}
@@ -0,0 +1,504 @@
+// Line editing for the interactive prompt: cursor keys, Emacs-style shortcuts, a history kept
+// across sessions (Up/Down) and Ctrl-R reverse search. A line can also be handed back for a
+// quick fix, already filled in. Without a terminal (piped input, or not on Unix) lines are read
+// as they are.
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+
+use crate::i18n::display_width;
+use crate::logging::debug;
+
+// Entries kept in the history file
+const HISTORY_SIZE: usize = 1000;
+
+/// Result of reading one line
+#[derive(Debug, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    Interrupted,  // Ctrl-C
+    Eof,          // Ctrl-D on an empty line, or end of input
+}
+
+pub struct LineEditor {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// $XDG_DATA_HOME/botan_cw_decoder/history, or under ~/.local/share
+    pub fn default_history_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+        Some(base.join("botan_cw_decoder").join("history"))
+    }
+
+    /// Editor with the history stored at `history_path`, if any
+    pub fn new(history_path: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        if history.len() > HISTORY_SIZE {
+            history.drain(..history.len() - HISTORY_SIZE);
+            if let Some(path) = &history_path {
+                let _ = fs::write(path, history.join("\n") + "\n");
+            }
+        }
+        LineEditor { history, history_path }
+    }
+
+    /// Remember a line for Up/Down and Ctrl-R, here and in later sessions
+    pub fn add_history(&mut self, line: &str) {
+        if line.is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        let Some(path) = &self.history_path else { return };
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(error) = saved {
+            debug!("cannot save history"; path = path.display(), error = error);
+        }
+    }
+
+    /// Whether lines are typed at a terminal, where they can be edited
+    pub fn is_terminal(&self) -> bool {
+        #[cfg(unix)]
+        {
+            use std::io::IsTerminal;
+            io::stdin().is_terminal()
+        }
+        #[cfg(not(unix))]
+        false
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<ReadLine> {
+        #[cfg(unix)]
+        if self.is_terminal() {
+            return self.read_line_raw(prompt, LineState::default());
+        }
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(ReadLine::Eof);
+        }
+        Ok(ReadLine::Line(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    /// Let the user fix `line` in place, with the cursor at `cursor`; with `overwrite`, the first
+    /// character typed replaces the one under the cursor. Without a terminal there is nothing to
+    /// edit, and this is Eof.
+    pub fn edit_line(&mut self, prompt: &str, line: &str, cursor: usize, overwrite: bool) -> io::Result<ReadLine> {
+        #[cfg(unix)]
+        if self.is_terminal() {
+            return self.read_line_raw(prompt, LineState::filled(line, cursor, overwrite));
+        }
+        let _ = (prompt, line, cursor, overwrite);
+        Ok(ReadLine::Eof)
+    }
+
+    #[cfg(unix)]
+    fn read_line_raw(&mut self, prompt: &str, mut state: LineState) -> io::Result<ReadLine> {
+        let _raw = RawMode::enable()?;
+        let mut stdout = io::stdout();
+        let mut stdin = io::stdin().lock();
+        loop {
+            write!(stdout, "{}", state.render(prompt))?;
+            stdout.flush()?;
+            let outcome = state.handle(read_key(&mut stdin)?, &self.history);
+            if !matches!(outcome, Outcome::Continue) {
+                // Repaint without the search prompt, then leave the line
+                state.search = None;
+                write!(stdout, "{}\r\n", state.render(prompt))?;
+                stdout.flush()?;
+            }
+            match outcome {
+                Outcome::Continue => {}
+                Outcome::Accept(line) => return Ok(ReadLine::Line(line)),
+                Outcome::Interrupted => return Ok(ReadLine::Interrupted),
+                Outcome::Eof => return Ok(ReadLine::Eof),
+            }
+        }
+    }
+}
+
+/// Puts the terminal into raw mode until dropped
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        // SAFETY: termios is plain data, filled in by tcgetattr before use
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawMode { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.original) };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,       // Also Ctrl-D on a non-empty line
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToEnd,    // Ctrl-K
+    KillToStart,  // Ctrl-U
+    KillWord,     // Ctrl-W
+    Search,       // Ctrl-R
+    Cancel,       // Ctrl-G
+    Interrupt,    // Ctrl-C
+    Eof,          // Ctrl-D
+    Other,
+}
+
+#[cfg(unix)]
+fn read_byte(input: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0u8];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read one key press, decoding UTF-8 and the common VT100/xterm escape sequences
+#[cfg(unix)]
+fn read_key(input: &mut impl Read) -> io::Result<Key> {
+    let byte = read_byte(input)?;
+    Ok(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::Eof,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x07 => Key::Cancel,
+        0x0b => Key::KillToEnd,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x12 => Key::Search,
+        0x15 => Key::KillToStart,
+        0x17 => Key::KillWord,
+        0x1b => match read_byte(input)? {
+            b'[' | b'O' => match read_byte(input)? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                digit @ b'0'..=b'9' => {
+                    // ESC [ n ~, possibly with modifiers we ignore
+                    let mut last = digit;
+                    while !(0x40..=0x7e).contains(&last) {
+                        last = read_byte(input)?;
+                    }
+                    match (digit, last) {
+                        (b'1' | b'7', b'~') => Key::Home,
+                        (b'4' | b'8', b'~') => Key::End,
+                        (b'3', b'~') => Key::Delete,
+                        _ => Key::Other,
+                    }
+                }
+                _ => Key::Other,
+            },
+            _ => Key::Other,
+        },
+        byte if byte < 0x20 => Key::Other,
+        byte => {
+            let len = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                bytes.push(read_byte(input)?);
+            }
+            std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()).map_or(Key::Other, Key::Char)
+        }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Continue,
+    Accept(String),
+    Interrupted,
+    Eof,
+}
+
+#[derive(Debug, Default)]
+struct Search {
+    query: String,
+    found: Option<usize>,    // History index of the current match
+    original: Vec<char>,     // Line to restore if the search is cancelled
+}
+
+/// The line being edited, independent of the terminal
+#[derive(Debug, Default)]
+struct LineState {
+    buffer: Vec<char>,
+    cursor: usize,
+    history_index: Option<usize>,
+    draft: Vec<char>,          // The new line, kept while browsing the history
+    search: Option<Search>,
+    overwrite: bool,           // The next character typed replaces the one under the cursor
+}
+
+impl LineState {
+    fn filled(line: &str, cursor: usize, overwrite: bool) -> Self {
+        let buffer: Vec<char> = line.chars().collect();
+        LineState { cursor: cursor.min(buffer.len()), buffer, overwrite, ..LineState::default() }
+    }
+
+    fn handle(&mut self, key: Key, history: &[String]) -> Outcome {
+        if self.search.is_some() {
+            match key {
+                Key::Char(c) => {
+                    let search = self.search.as_mut().unwrap();
+                    search.query.push(c);
+                    let from = search.found.map_or(history.len(), |i| i + 1);
+                    self.find(history, from);
+                    return Outcome::Continue;
+                }
+                Key::Backspace => {
+                    let search = self.search.as_mut().unwrap();
+                    search.query.pop();
+                    self.find(history, history.len());
+                    return Outcome::Continue;
+                }
+                Key::Search => {
+                    let from = self.search.as_ref().unwrap().found.unwrap_or(history.len());
+                    self.find(history, from);
+                    return Outcome::Continue;
+                }
+                Key::Cancel | Key::Interrupt => {
+                    let search = self.search.take().unwrap();
+                    self.set_buffer(search.original);
+                    return Outcome::Continue;
+                }
+                // Any other key keeps the match and is then handled as usual
+                _ => self.search = None,
+            }
+        }
+
+        let overwrite = std::mem::take(&mut self.overwrite);
+        match key {
+            Key::Char(c) if overwrite && self.cursor < self.buffer.len() => {
+                self.buffer[self.cursor] = c;
+                self.cursor += 1;
+            }
+            Key::Char(c) => {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Enter => return Outcome::Accept(self.buffer.iter().collect()),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.buffer.remove(self.cursor);
+            }
+            Key::Eof if self.buffer.is_empty() => return Outcome::Eof,
+            Key::Delete | Key::Eof if self.cursor < self.buffer.len() => {
+                self.buffer.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.buffer.len(),
+            Key::Up if !history.is_empty() => {
+                let index = match self.history_index {
+                    None => {
+                        self.draft = self.buffer.clone();
+                        history.len() - 1
+                    }
+                    Some(index) => index.saturating_sub(1),
+                };
+                self.history_index = Some(index);
+                self.set_buffer(history[index].chars().collect());
+            }
+            Key::Down => match self.history_index {
+                Some(index) if index + 1 < history.len() => {
+                    self.history_index = Some(index + 1);
+                    self.set_buffer(history[index + 1].chars().collect());
+                }
+                Some(_) => {
+                    self.history_index = None;
+                    let draft = std::mem::take(&mut self.draft);
+                    self.set_buffer(draft);
+                }
+                None => {}
+            },
+            Key::KillToEnd => self.buffer.truncate(self.cursor),
+            Key::KillToStart => {
+                self.buffer.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::KillWord => {
+                let mut start = self.cursor;
+                while start > 0 && self.buffer[start - 1] == ' ' {
+                    start -= 1;
+                }
+                while start > 0 && self.buffer[start - 1] != ' ' {
+                    start -= 1;
+                }
+                self.buffer.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            Key::Search => {
+                self.search = Some(Search { original: self.buffer.clone(), ..Search::default() });
+            }
+            Key::Interrupt => return Outcome::Interrupted,
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    /// Show the newest history entry before `before` that contains the query
+    fn find(&mut self, history: &[String], before: usize) {
+        let search = self.search.as_mut().unwrap();
+        if let Some(index) = (0..before.min(history.len())).rev().find(|&i| history[i].contains(&search.query)) {
+            search.found = Some(index);
+            let line: Vec<char> = history[index].chars().collect();
+            self.set_buffer(line);
+        }
+    }
+
+    fn set_buffer(&mut self, buffer: Vec<char>) {
+        self.cursor = buffer.len();
+        self.buffer = buffer;
+    }
+
+    /// Escape sequence repainting the line and placing the cursor
+    fn render(&self, prompt: &str) -> String {
+        let line: String = self.buffer.iter().collect();
+        let after: String = self.buffer[self.cursor..].iter().collect();
+        let shown = match &self.search {
+            Some(search) => format!("(reverse-i-search)`{}': {}", search.query, line),
+            None => format!("{}{}", prompt, line),
+        };
+        let back = display_width(&after);
+        let mut out = format!("\r{}\x1b[K", shown);
+        if back > 0 {
+            out.push_str(&format!("\x1b[{}D", back));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(state: &mut LineState, keys: &[Key], history: &[String]) -> Outcome {
+        let mut outcome = Outcome::Continue;
+        for key in keys {
+            outcome = state.handle(*key, history);
+        }
+        outcome
+    }
+
+    fn chars(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut state = LineState::default();
+        type_keys(&mut state, &chars("BOTAN JS1YPT A67C8D5E2AA1360"), &[]);
+        // Fix a typo in the middle of the hex
+        type_keys(&mut state, &[Key::Home, Key::Right, Key::Delete, Key::Char('O')], &[]);
+        let outcome = type_keys(&mut state, &[Key::End, Key::Char('8'), Key::Enter], &[]);
+        assert_eq!(outcome, Outcome::Accept("BOTAN JS1YPT A67C8D5E2AA13608".to_string()));
+
+        let mut state = LineState::default();
+        type_keys(&mut state, &chars("BOTAN JS1YPT XXXX"), &[]);
+        type_keys(&mut state, &[Key::KillWord], &[]);
+        assert_eq!(state.buffer.iter().collect::<String>(), "BOTAN JS1YPT ");
+        assert_eq!(type_keys(&mut state, &[Key::KillToStart, Key::Eof], &[]), Outcome::Eof);
+        assert_eq!(type_keys(&mut state, &[Key::Interrupt], &[]), Outcome::Interrupted);
+    }
+
+    #[test]
+    fn test_history_and_search() {
+        let history = vec!["BOTAN JS1YPT A67C8D5E2AA13608".to_string(), "... --- ...".to_string()];
+        let mut state = LineState::default();
+        type_keys(&mut state, &chars("draft"), &history);
+        type_keys(&mut state, &[Key::Up, Key::Up], &history);
+        assert_eq!(state.buffer.iter().collect::<String>(), history[0]);
+        type_keys(&mut state, &[Key::Down, Key::Down], &history);
+        assert_eq!(state.buffer.iter().collect::<String>(), "draft");
+
+        let mut state = LineState::default();
+        type_keys(&mut state, &[Key::Search], &history);
+        type_keys(&mut state, &chars("A67"), &history);
+        assert_eq!(state.buffer.iter().collect::<String>(), history[0]);
+        assert!(state.render("> ").contains("(reverse-i-search)`A67'"));
+        // Moving keeps the match and leaves the search
+        type_keys(&mut state, &[Key::Home], &history);
+        assert!(state.search.is_none());
+        assert_eq!(state.cursor, 0);
+
+        let mut state = LineState::default();
+        type_keys(&mut state, &chars("x"), &history);
+        type_keys(&mut state, &[Key::Search, Key::Char('-'), Key::Cancel], &history);
+        assert_eq!(state.buffer.iter().collect::<String>(), "x");
+    }
+
+    #[test]
+    fn test_filled_line() {
+        // One keystroke replaces the character under the cursor, the next ones insert as usual
+        let mut state = LineState::filled("BOTAN JS1YPT A67C8D5EGAA13608", 21, true);
+        assert_eq!(type_keys(&mut state, &[Key::Char('2'), Key::Enter], &[]), Outcome::Accept("BOTAN JS1YPT A67C8D5E2AA13608".to_string()));
+        let mut state = LineState::filled("BOTAN JS1YPT A67C8D5EGAA13608", 21, true);
+        type_keys(&mut state, &[Key::Right, Key::Char('0')], &[]);
+        assert_eq!(state.buffer.iter().collect::<String>(), "BOTAN JS1YPT A67C8D5EG0AA13608");
+        let mut state = LineState::filled("BOTAN JS1YPT A67C8D5E2AA1360", 99, false);
+        assert_eq!(type_keys(&mut state, &[Key::Char('8'), Key::Enter], &[]), Outcome::Accept("BOTAN JS1YPT A67C8D5E2AA13608".to_string()));
+    }
+
+    #[test]
+    fn test_history_file() {
+        let path = std::env::temp_dir().join(format!("botan_history_test_{}", std::process::id()));
+        let mut editor = LineEditor::new(Some(path.clone()));
+        editor.add_history("first");
+        editor.add_history("first");
+        editor.add_history("second");
+        let editor = LineEditor::new(Some(path.clone()));
+        assert_eq!(editor.history, ["first", "second"]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+// Advisory locks on files that several instances of the program may share, such as the
+// forwarder's spool. The lock is taken on a separate lock file, left in place, so the data file
+// itself can still be replaced by a rename while it is held. The kernel releases it when the
+// process exits, however it exits. Elsewhere than on Unix nothing is locked.
+use std::fs::{self, File};
+use std::path::Path;
+
+/// An exclusive lock, held until dropped
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Take the lock on `path`, creating it as needed, waiting while someone else holds it
+    pub fn exclusive(path: &Path) -> Result<Self, String> {
+        let file = open(path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(format!("Cannot lock '{}': {}", path.display(), error));
+                }
+            }
+        }
+        Ok(FileLock { _file: file })
+    }
+
+    /// Take the lock on `path`, creating it as needed; None while someone else holds it
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>, String> {
+        let file = open(path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let error = std::io::Error::last_os_error();
+                if error.raw_os_error() == Some(libc::EWOULDBLOCK) {
+                    return Ok(None);
+                }
+                return Err(format!("Cannot lock '{}': {}", path.display(), error));
+            }
+        }
+        Ok(Some(FileLock { _file: file }))
+    }
+}
+
+fn open(path: &Path) -> Result<File, String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Cannot open '{}': {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive() {
+        let dir = std::env::temp_dir().join(format!("botan_lock_test_{}", std::process::id()));
+        let path = dir.join("spool.lock");
+        let lock = FileLock::try_exclusive(&path).unwrap();
+        assert!(lock.is_some());
+        #[cfg(unix)]
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(lock);
+        let lock = FileLock::exclusive(&path).unwrap();
+        #[cfg(unix)]
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(lock);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
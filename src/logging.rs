@@ -0,0 +1,329 @@
+// Leveled diagnostics on stderr, separate from the decoded output on stdout.
+// The level comes from -v/-q or --log-level; BOTAN_LOG refines it per module like RUST_LOG,
+// e.g. BOTAN_LOG=debug or BOTAN_LOG=info,udp_audio=trace,rigctl=debug.
+// A service can send them to syslog (/dev/log) or the systemd journal instead; the journal gets
+// each key = value pair as a field of its own, e.g. LOS=2024-05-01T12:44:56Z.
+//
+//   info!("AOS, receiving"; los = timeutil::format_utc(pass.los_unix));
+//
+// prints "2024-05-01T12:34:56Z  INFO commands: AOS, receiving los=2024-05-01T12:44:56Z".
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::timeutil;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 5] = [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace];
+
+    /// Syslog severity
+    fn severity(self) -> u8 {
+        match self {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Level::ALL
+            .into_iter()
+            .find(|level| level.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown log level '{}'. Expected error, warn, info, debug or trace", s))
+    }
+}
+
+/// Maximum level overall and per module path prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    default: Option<Level>,              // None silences everything
+    targets: Vec<(String, Option<Level>)>,
+}
+
+impl Filter {
+    /// Info, raised by each -v and lowered by each -q (-qqq silences even errors)
+    pub fn from_verbosity(verbosity: i8) -> Self {
+        let index = 2 + verbosity as isize;
+        let default = usize::try_from(index).ok().map(|i| Level::ALL[i.min(4)]);
+        Filter { default, targets: Vec::new() }
+    }
+
+    /// Apply BOTAN_LOG-style directives: "level", "module=level" or "module=off", comma separated
+    pub fn parse_directives(mut self, directives: &str) -> Result<Self, String> {
+        for directive in directives.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |s: &str| if s.eq_ignore_ascii_case("off") { Ok(None) } else { s.parse().map(Some) };
+            match directive.split_once('=') {
+                Some((target, level)) => self.targets.push((target.trim().to_string(), parse_level(level.trim())?)),
+                None => self.default = parse_level(directive)?,
+            }
+        }
+        // Longest prefix first, so the most specific directive wins
+        self.targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(self)
+    }
+
+    pub fn enabled(&self, level: Level, module: &str) -> bool {
+        let module = module.split_once("::").map_or("", |(_, rest)| rest);
+        let max = self
+            .targets
+            .iter()
+            .find(|(target, _)| module.strip_prefix(target.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with("::")))
+            .map_or(self.default, |(_, level)| *level);
+        max.is_some_and(|max| level <= max)
+    }
+}
+
+/// Where log lines go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Stderr,
+    Syslog,
+    Journald,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "stderr" => Ok(Target::Stderr),
+            "syslog" => Ok(Target::Syslog),
+            "journald" | "journal" => Ok(Target::Journald),
+            _ => Err(format!("Unknown log target '{}'. Expected stderr, syslog or journald", s)),
+        }
+    }
+}
+
+/// Syslog facility of the messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Facility(u8);
+
+impl FromStr for Facility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        let code = match s.as_str() {
+            "user" => 1,
+            "daemon" => 3,
+            _ => match s.strip_prefix("local").and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if n <= 7 => 16 + n,
+                _ => return Err(format!("Unknown syslog facility '{}'. Expected user, daemon or local0 to local7", s)),
+            },
+        };
+        Ok(Facility(code))
+    }
+}
+
+/// An open connection to the log target
+#[derive(Debug)]
+enum Sink {
+    Stderr,
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram, Facility),
+    #[cfg(unix)]
+    Journald(std::os::unix::net::UnixDatagram, Facility),
+}
+
+impl Sink {
+    fn open(target: Target, facility: Facility) -> Result<Self, String> {
+        #[cfg(unix)]
+        {
+            let connect = |path: &str| {
+                let socket = std::os::unix::net::UnixDatagram::unbound().and_then(|socket| socket.connect(path).map(|_| socket));
+                socket.map_err(|e| format!("Cannot connect to {}: {}", path, e))
+            };
+            match target {
+                Target::Stderr => Ok(Sink::Stderr),
+                Target::Syslog => Ok(Sink::Syslog(connect("/dev/log")?, facility)),
+                Target::Journald => Ok(Sink::Journald(connect("/run/systemd/journal/socket")?, facility)),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = facility;
+            match target {
+                Target::Stderr => Ok(Sink::Stderr),
+                _ => Err("Syslog and journald logging need a Unix system".to_string()),
+            }
+        }
+    }
+}
+
+static FILTER: OnceLock<Filter> = OnceLock::new();
+static SINK: OnceLock<Sink> = OnceLock::new();
+
+/// Set up logging for the process; call once before anything is logged. `level` overrides the
+/// level set by `verbosity`.
+pub fn init(verbosity: i8, level: Option<Level>, target: Target, facility: Facility) -> Result<(), String> {
+    let mut filter = Filter::from_verbosity(verbosity);
+    if let Some(level) = level {
+        filter.default = Some(level);
+    }
+    if let Ok(directives) = std::env::var("BOTAN_LOG") {
+        filter = filter.parse_directives(&directives).map_err(|e| format!("BOTAN_LOG: {}", e))?;
+    }
+    SINK.set(Sink::open(target, facility)?).map_err(|_| "Logging initialized twice".to_string())?;
+    FILTER.set(filter).map_err(|_| "Logging initialized twice".to_string())
+}
+
+pub fn enabled(level: Level, module: &str) -> bool {
+    FILTER.get_or_init(|| Filter::from_verbosity(0)).enabled(level, module)
+}
+
+pub fn write(level: Level, module: &str, message: fmt::Arguments<'_>, fields: &[(&str, &dyn fmt::Display)]) {
+    let target = module.split_once("::").map_or(module, |(_, rest)| rest);
+    let message = message.to_string();
+    let fields: Vec<(&str, String)> = fields.iter().map(|(key, value)| (*key, value.to_string())).collect();
+    let mut text = format!("{}: {}", target, message);
+    for (key, value) in &fields {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            text.push_str(&format!(" {}={:?}", key, value));
+        } else {
+            text.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    let sent = match SINK.get().unwrap_or(&Sink::Stderr) {
+        Sink::Stderr => false,
+        #[cfg(unix)]
+        Sink::Syslog(socket, facility) => socket.send(syslog_message(level, *facility, &text).as_bytes()).is_ok(),
+        #[cfg(unix)]
+        Sink::Journald(socket, facility) => {
+            socket.send(&journal_entry(level, *facility, target, &message, &fields)).is_ok()
+        }
+    };
+    // Lines the daemon did not take still reach stderr
+    if !sent {
+        eprintln!("{} {:>5} {}", timeutil::format_display(timeutil::now_unix()), level.name(), text);
+    }
+}
+
+/// An RFC 3164 message as /dev/log expects it; syslog adds the time and host
+#[cfg_attr(not(unix), allow(dead_code))]
+fn syslog_message(level: Level, facility: Facility, text: &str) -> String {
+    format!("<{}>botan_cw_decoder[{}]: {}", facility.0 * 8 + level.severity(), std::process::id(), text)
+}
+
+/// An entry in the journal's native protocol: KEY=value lines, or the length-prefixed form
+/// for values with line breaks
+#[cfg_attr(not(unix), allow(dead_code))]
+fn journal_entry(level: Level, facility: Facility, target: &str, message: &str, fields: &[(&str, String)]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    let mut add = |key: &str, value: &str| {
+        if value.contains('\n') {
+            entry.extend_from_slice(key.as_bytes());
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            entry.extend_from_slice(value.as_bytes());
+            entry.push(b'\n');
+        } else {
+            entry.extend_from_slice(format!("{}={}\n", key, value).as_bytes());
+        }
+    };
+    add("MESSAGE", message);
+    add("PRIORITY", &level.severity().to_string());
+    add("SYSLOG_FACILITY", &facility.0.to_string());
+    add("SYSLOG_IDENTIFIER", "botan_cw_decoder");
+    add("CODE_MODULE", target);
+    for (key, value) in fields {
+        // Field names are upper case letters, digits and underscores, not starting with one
+        let name: String = key.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+        add(name.trim_start_matches('_'), value);
+    }
+    entry
+}
+
+macro_rules! log_at {
+    ($level:expr, $fmt:literal $(, $arg:expr)* $(,)? $(; $($key:ident = $value:expr),+ $(,)?)?) => {
+        if $crate::logging::enabled($level, module_path!()) {
+            $crate::logging::write(
+                $level,
+                module_path!(),
+                format_args!($fmt $(, $arg)*),
+                &[$($((stringify!($key), &$value as &dyn std::fmt::Display)),+)?],
+            );
+        }
+    };
+}
+
+// `warn` itself would clash with the built-in #[warn] attribute
+macro_rules! warn_ {
+    ($($t:tt)+) => { $crate::logging::log_at!($crate::logging::Level::Warn, $($t)+) };
+}
+
+macro_rules! info {
+    ($($t:tt)+) => { $crate::logging::log_at!($crate::logging::Level::Info, $($t)+) };
+}
+
+macro_rules! debug {
+    ($($t:tt)+) => { $crate::logging::log_at!($crate::logging::Level::Debug, $($t)+) };
+}
+
+macro_rules! trace {
+    ($($t:tt)+) => { $crate::logging::log_at!($crate::logging::Level::Trace, $($t)+) };
+}
+
+pub(crate) use {debug, info, log_at, trace, warn_ as warn};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        let filter = Filter::from_verbosity(0);
+        assert!(filter.enabled(Level::Info, "botan_cw_decoder::commands"));
+        assert!(!filter.enabled(Level::Debug, "botan_cw_decoder::commands"));
+        assert!(Filter::from_verbosity(2).enabled(Level::Trace, "botan_cw_decoder::rigctl"));
+        assert!(!Filter::from_verbosity(-1).enabled(Level::Info, "botan_cw_decoder::rigctl"));
+        assert!(!Filter::from_verbosity(-3).enabled(Level::Error, "botan_cw_decoder::rigctl"));
+    }
+
+    #[test]
+    fn test_directives() {
+        let filter = Filter::from_verbosity(0).parse_directives("warn, udp_audio=trace, rigctl=off").unwrap();
+        assert!(!filter.enabled(Level::Info, "botan_cw_decoder::commands"));
+        assert!(filter.enabled(Level::Trace, "botan_cw_decoder::udp_audio"));
+        assert!(!filter.enabled(Level::Error, "botan_cw_decoder::rigctl"));
+        assert!(!filter.enabled(Level::Trace, "botan_cw_decoder::udp_audio_extra"));
+        assert!(Filter::from_verbosity(0).parse_directives("loud").is_err());
+    }
+
+    #[test]
+    fn test_system_logs() {
+        let facility: Facility = "local3".parse().unwrap();
+        assert_eq!(facility, Facility(19));
+        assert!("local8".parse::<Facility>().is_err());
+        assert!(syslog_message(Level::Warn, facility, "rigctl: lost").starts_with("<156>botan_cw_decoder["));
+
+        let entry = journal_entry(Level::Info, "daemon".parse().unwrap(), "commands", "AOS", &[("los", "12:44".to_string()), ("note", "a\nb".to_string())]);
+        let text = String::from_utf8_lossy(&entry);
+        assert!(text.starts_with("MESSAGE=AOS\nPRIORITY=6\nSYSLOG_FACILITY=3\nSYSLOG_IDENTIFIER=botan_cw_decoder\nCODE_MODULE=commands\nLOS=12:44\n"));
+        assert!(entry.ends_with(b"NOTE\n\x03\0\0\0\0\0\0\0a\nb\n"));
+        assert_eq!("journal".parse(), Ok(Target::Journald));
+    }
+}
@@ -1,136 +1,347 @@
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::sync::{Arc, OnceLock};
+
+use clap::{CommandFactory, FromArgMatches};
+
+use crate::exit_code::ExitError;
 
 mod custom_definitions;
+mod adif;
+mod afsk;
+mod aggregate;
+mod alert;
+mod archive;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod audit;
+mod ax25;
+mod battery;
+#[cfg(test)]
+mod bench;
+mod binary;
 mod botan_parser;
+mod breakdown;
+mod calibration;
+mod check;
+mod cli;
+mod cluster;
+mod codec;
+mod codes;
+mod commands;
+mod confidence;
+mod config;
+mod cw_demod;
+mod cw_synth;
+mod daemon;
+mod dashboard;
+mod direwolf;
+mod exit_code;
+mod explain;
+mod export;
+mod field;
+mod forward;
+mod frames;
+#[cfg(test)]
+mod fuzz;
+mod grpc;
+mod grsat;
+mod html_report;
+mod http2;
+mod i18n;
+mod integrity;
+mod iq;
+mod kiss;
+mod limits;
+mod line_editor;
+mod lock;
+mod logging;
+mod mappings;
+mod merge;
+mod migrate;
+mod morse_trie;
+mod notify;
+mod orbit;
+mod output;
+mod parquet;
+mod proto;
+mod practice;
+mod provenance;
+mod redis;
+mod repl;
+mod replay;
+mod report;
+mod rigctl;
+mod rng;
+mod rotate;
+#[cfg(feature = "rtlsdr")]
+mod rtlsdr;
+mod satnogs;
+mod schema;
+mod scheduler;
+mod serial;
+mod sgp4;
+mod simulate;
+mod stats;
+mod style;
+mod template;
+mod timeutil;
+mod tle;
+mod tle_cache;
+mod udp_audio;
+mod units;
+mod wav;
+mod xml;
+#[cfg(feature = "zmq")]
+mod zmq;
+
+/// The built-in morse table: letters, digits, common punctuation and prosigns
+const DEFAULT_MAPPINGS: [(&str, &str); 44] = [
+    // Letters
+    (".-", "A"),
+    ("-...", "B"),
+    ("-.-.", "C"),
+    ("-..", "D"),
+    (".", "E"),
+    ("..-.", "F"),
+    ("--.", "G"),
+    ("....", "H"),
+    ("..", "I"),
+    (".---", "J"),
+    ("-.-", "K"),
+    (".-..", "L"),
+    ("--", "M"),
+    ("-.", "N"),
+    ("---", "O"),
+    (".--.", "P"),
+    ("--.-", "Q"),
+    (".-.", "R"),
+    ("...", "S"),
+    ("-", "T"),
+    ("..-", "U"),
+    ("...-", "V"),
+    (".--", "W"),
+    ("-..-", "X"),
+    ("-.--", "Y"),
+    ("--..", "Z"),
+
+    // Numbers
+    (".----", "1"),
+    ("..---", "2"),
+    ("...--", "3"),
+    ("....-", "4"),
+    (".....", "5"),
+    ("-....", "6"),
+    ("--...", "7"),
+    ("---..", "8"),
+    ("----.", "9"),
+    ("-----", "0"),
+
+    // Punctuation
+    ("-..-.", "/"),
+    (".-.-.-", "."),
+    ("--..--", ","),
+    ("..--..", "?"),
+    ("-...-", "="),  // Also the BT prosign
+
+    // Prosigns, shown in angle brackets
+    (".-.-.", "<AR>"),
+    ("...-.-", "<SK>"),
+    ("-.--.", "<KN>"),
+];
 
 /// A simple decoder for CW beacon messages
+///
+/// A decoder is Send and Sync and never changes behind a shared reference, so worker threads
+/// can decode through one `&BotanDecoder` or an `Arc<BotanDecoder>`. Cloning is cheap: clones
+/// share the tables until one of them changes its mappings, which copies them for that clone
+/// only. Decoding never blocks another thread.
+#[derive(Clone)]
 pub struct BotanDecoder {
     /// Mapping table for character/pattern decoding
-    decode_map: HashMap<String, String>,
+    decode_map: Arc<HashMap<String, String>>,
+    /// The same table as a prefix tree, used for lookups and streaming decode
+    trie: Arc<morse_trie::MorseTrie>,
 }
 
 impl BotanDecoder {
-    /// Create a new decoder with default mappings
+    /// Create a new decoder with default mappings. The default table is built once and shared,
+    /// so this costs no more than a clone.
     pub fn new() -> Self {
-        let mut decode_map = HashMap::new();
-        
-        // Add some common CW/morse patterns - you can customize these based on your definitions
-        // These are examples that you should replace with your actual definitions
-        decode_map.insert(".-".to_string(), "A".to_string());
-        decode_map.insert("-...".to_string(), "B".to_string());
-        decode_map.insert("-.-.".to_string(), "C".to_string());
-        decode_map.insert("-..".to_string(), "D".to_string());
-        decode_map.insert(".".to_string(), "E".to_string());
-        decode_map.insert("..-.".to_string(), "F".to_string());
-        decode_map.insert("--.".to_string(), "G".to_string());
-        decode_map.insert("....".to_string(), "H".to_string());
-        decode_map.insert("..".to_string(), "I".to_string());
-        decode_map.insert(".---".to_string(), "J".to_string());
-        decode_map.insert("-.-".to_string(), "K".to_string());
-        decode_map.insert(".-..".to_string(), "L".to_string());
-        decode_map.insert("--".to_string(), "M".to_string());
-        decode_map.insert("-.".to_string(), "N".to_string());
-        decode_map.insert("---".to_string(), "O".to_string());
-        decode_map.insert(".--.".to_string(), "P".to_string());
-        decode_map.insert("--.-".to_string(), "Q".to_string());
-        decode_map.insert(".-.".to_string(), "R".to_string());
-        decode_map.insert("...".to_string(), "S".to_string());
-        decode_map.insert("-".to_string(), "T".to_string());
-        decode_map.insert("..-".to_string(), "U".to_string());
-        decode_map.insert("...-".to_string(), "V".to_string());
-        decode_map.insert(".--".to_string(), "W".to_string());
-        decode_map.insert("-..-".to_string(), "X".to_string());
-        decode_map.insert("-.--".to_string(), "Y".to_string());
-        decode_map.insert("--..".to_string(), "Z".to_string());
-        
-        // Numbers
-        decode_map.insert(".----".to_string(), "1".to_string());
-        decode_map.insert("..---".to_string(), "2".to_string());
-        decode_map.insert("...--".to_string(), "3".to_string());
-        decode_map.insert("....-".to_string(), "4".to_string());
-        decode_map.insert(".....".to_string(), "5".to_string());
-        decode_map.insert("-....".to_string(), "6".to_string());
-        decode_map.insert("--...".to_string(), "7".to_string());
-        decode_map.insert("---..".to_string(), "8".to_string());
-        decode_map.insert("----.".to_string(), "9".to_string());
-        decode_map.insert("-----".to_string(), "0".to_string());
-
-        BotanDecoder { decode_map }
+        static DEFAULT: OnceLock<BotanDecoder> = OnceLock::new();
+        DEFAULT
+            .get_or_init(|| {
+                let mappings = DEFAULT_MAPPINGS.iter().map(|(pattern, decoded)| (pattern.to_string(), decoded.to_string()));
+                Self::with_custom_mappings(mappings.collect())
+            })
+            .clone()
     }
 
     /// Create a decoder with custom mappings
     pub fn with_custom_mappings(mappings: HashMap<String, String>) -> Self {
         BotanDecoder {
-            decode_map: mappings,
+            trie: Arc::new(Self::build_trie(&mappings)),
+            decode_map: Arc::new(mappings),
         }
     }
 
-    /// Load decoder from configuration file
-    pub fn from_config_file(config_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        use std::fs;
-        
-        let content = fs::read_to_string(config_path)?;
-        let mut decode_map = HashMap::new();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-            
-            // Parse pattern = value format
-            if let Some(eq_pos) = line.find('=') {
-                let pattern = line[..eq_pos].trim().to_string();
-                let value = line[eq_pos + 1..].trim().to_string();
-                
-                if !pattern.is_empty() && !value.is_empty() {
-                    decode_map.insert(pattern, value);
-                }
-            }
+    fn build_trie(mappings: &HashMap<String, String>) -> morse_trie::MorseTrie {
+        let mut trie = morse_trie::MorseTrie::new();
+        for (pattern, decoded) in mappings {
+            trie.insert(pattern, decoded);
         }
-        
-        Ok(BotanDecoder { decode_map })
+        trie
+    }
+
+    /// Load decoder from configuration file: "pattern = value" lines, or CSV or JSON by extension
+    pub fn from_config_file(config_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let decode_map = mappings::load(std::path::Path::new(config_path))?;
+        Ok(Self::with_custom_mappings(decode_map))
     }
 
     /// Add or update a mapping
     pub fn add_mapping(&mut self, pattern: String, decoded: String) {
-        self.decode_map.insert(pattern, decoded);
+        Arc::make_mut(&mut self.trie).insert(&pattern, &decoded);
+        Arc::make_mut(&mut self.decode_map).insert(pattern, decoded);
+    }
+
+    /// Remove a mapping, returning the value it decoded to
+    pub fn remove_mapping(&mut self, pattern: &str) -> Option<String> {
+        if !self.decode_map.contains_key(pattern) {
+            return None;
+        }
+        let removed = Arc::make_mut(&mut self.decode_map).remove(pattern)?;
+        // Rebuilt rather than pruned, so streams still see where no longer pattern can follow
+        self.trie = Arc::new(Self::build_trie(&self.decode_map));
+        Some(removed)
+    }
+
+    /// Remove every mapping
+    pub fn clear(&mut self) {
+        self.decode_map = Arc::default();
+        self.trie = Arc::new(morse_trie::MorseTrie::new());
+    }
+
+    /// Add or update many mappings at once
+    pub fn extend(&mut self, mappings: impl IntoIterator<Item = (String, String)>) {
+        let (trie, decode_map) = (Arc::make_mut(&mut self.trie), Arc::make_mut(&mut self.decode_map));
+        for (pattern, decoded) in mappings {
+            trie.insert(&pattern, &decoded);
+            decode_map.insert(pattern, decoded);
+        }
+    }
+
+    /// Add the mappings of `other`; where both have a pattern, `other`'s value wins
+    pub fn merge(&mut self, other: &BotanDecoder) {
+        self.extend(other.iter().map(|(pattern, decoded)| (pattern.to_string(), decoded.to_string())));
+    }
+
+    /// The (pattern, value) pairs, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.decode_map.iter().map(|(pattern, decoded)| (pattern.as_str(), decoded.as_str()))
+    }
+
+    /// Start decoding a symbol stream incrementally, see `MorseStream`
+    pub fn stream(&self) -> morse_trie::MorseStream {
+        morse_trie::MorseStream::new(Arc::clone(&self.trie))
+    }
+
+    /// Decode morse symbols ('.', '-' and spaces as in `decode`) as they are pulled from
+    /// `symbols`, yielding the text a character at a time, see `DecodeIter`
+    pub fn decode_iter<I: IntoIterator<Item = char>>(&self, symbols: I) -> morse_trie::DecodeIter<I::IntoIter> {
+        morse_trie::DecodeIter::new(self.stream(), symbols.into_iter())
     }
 
     /// Decode a CW beacon string
     /// Expects patterns to be separated by spaces, words by multiple spaces or special delimiters
-    pub fn decode(&self, input: &str) -> Result<String, String> {
+    pub fn decode(&self, input: &str) -> Result<String, UnknownPattern> {
+        let mut result = String::new();
+        self.decode_into(input, &mut result)?;
+        Ok(result)
+    }
+
+    /// Decode like `decode`, appending to `out`. Reusing one buffer across calls, decoding
+    /// allocates nothing once the buffer has grown. On an unknown pattern `out` keeps what was
+    /// decoded before it.
+    pub fn decode_into(&self, input: &str, out: &mut String) -> Result<(), UnknownPattern> {
         if input.trim().is_empty() {
-            return Ok(String::new());
+            return Ok(());
         }
 
-        let mut result = String::new();
-        
         // Split by double spaces to separate words
-        let words: Vec<&str> = input.split("  ").collect();
-        
-        for (word_idx, word) in words.iter().enumerate() {
+        for (word_idx, word) in input.split("  ").enumerate() {
             if word_idx > 0 {
-                result.push(' ');
+                out.push(' ');
             }
             
             // Split each word by single spaces to get individual patterns
-            let patterns: Vec<&str> = word.split(' ').filter(|s| !s.is_empty()).collect();
-            
-            for pattern in patterns {
-                match self.decode_map.get(pattern) {
-                    Some(decoded_char) => result.push_str(decoded_char),
+            for pattern in word.split(' ').filter(|s| !s.is_empty()) {
+                match self.trie.get(pattern) {
+                    Some(decoded_char) => out.push_str(decoded_char),
                     None => {
-                        return Err(format!("Unknown pattern: '{}'", pattern));
+                        return Err(UnknownPattern {
+                            pattern: pattern.to_string(),
+                            suggestions: self.suggest(pattern, 3),
+                        });
                     }
                 }
             }
         }
 
-        Ok(result)
+        Ok(())
+    }
+
+    /// Known patterns closest to `pattern` by edit distance (at most 2 edits), nearest first
+    pub fn suggest(&self, pattern: &str, limit: usize) -> Vec<PatternSuggestion> {
+        let mut suggestions: Vec<PatternSuggestion> = self
+            .decode_map
+            .iter()
+            .map(|(known, decoded)| PatternSuggestion {
+                pattern: known.clone(),
+                decoded: decoded.clone(),
+                distance: edit_distance(pattern, known),
+            })
+            .filter(|s| s.distance <= 2)
+            .collect();
+        suggestions.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.pattern.cmp(&b.pattern)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Encode text to dot/dash patterns using the reverse of the decode map,
+    /// separating patterns with one space and words with two
+    pub fn encode(&self, text: &str) -> Result<String, String> {
+        // Several patterns may decode to the same text; prefer the shortest, then the first in sort order
+        let mut reverse: HashMap<String, &str> = HashMap::new();
+        for (pattern, decoded) in self.decode_map.iter() {
+            let entry = reverse.entry(decoded.to_uppercase()).or_insert(pattern);
+            if (pattern.len(), pattern.as_str()) < (entry.len(), *entry) {
+                *entry = pattern;
+            }
+        }
+        let longest = reverse.keys().map(|k| k.chars().count()).max().unwrap_or(0);
+
+        let mut words = Vec::new();
+        for word in text.split_whitespace() {
+            let chars: Vec<char> = word.to_uppercase().chars().collect();
+            let mut patterns = Vec::new();
+            let mut pos = 0;
+            // Greedy longest match, so multi-character values such as "<AR>" win over their letters
+            while pos < chars.len() {
+                let found = (1..=longest.min(chars.len() - pos)).rev().find_map(|len| {
+                    let key: String = chars[pos..pos + len].iter().collect();
+                    reverse.get(&key).map(|pattern| (len, *pattern))
+                });
+                match found {
+                    Some((len, pattern)) => {
+                        patterns.push(pattern);
+                        pos += len;
+                    }
+                    None => return Err(format!("No morse pattern for character '{}'", chars[pos])),
+                }
+            }
+            words.push(patterns.join(" "));
+        }
+        Ok(words.join("  "))
     }
 
     /// Get all available patterns
@@ -138,16 +349,77 @@ impl BotanDecoder {
         self.decode_map.keys().cloned().collect()
     }
 
-    /// Print available mappings
-    pub fn print_mappings(&self) {
-        println!("Available mappings:");
+    /// Available mappings as a listing sorted by pattern
+    pub fn format_mappings(&self) -> String {
         let mut mappings: Vec<_> = self.decode_map.iter().collect();
         mappings.sort_by_key(|(pattern, _)| pattern.as_str());
-        
+
+        let mut out = String::from("Available mappings:");
         for (pattern, decoded) in mappings {
-            println!("  '{}' -> '{}'", pattern, decoded);
+            out.push_str(&format!("\n  '{}' -> '{}'", pattern, decoded));
+        }
+        out
+    }
+
+    /// The current pattern -> value table
+    pub fn mappings(&self) -> &HashMap<String, String> {
+        &self.decode_map
+    }
+
+    /// Save the current mappings to a file that `from_config_file` reads back: "pattern = value"
+    /// lines grouped under comment headings, or CSV or JSON by extension
+    pub fn save_to_file(&self, path: &str) -> Result<(), String> {
+        mappings::save(std::path::Path::new(path), &self.decode_map)
+    }
+
+    /// Print available mappings
+    pub fn print_mappings(&self) {
+        println!("{}", self.format_mappings());
+    }
+}
+
+/// A known pattern close to one that failed to decode
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternSuggestion {
+    pub pattern: String,
+    pub decoded: String,
+    pub distance: usize,  // Symbols inserted, deleted or flipped
+}
+
+/// Error returned by `BotanDecoder::decode`, with the nearest known patterns
+#[derive(Debug, Clone)]
+pub struct UnknownPattern {
+    pub pattern: String,
+    pub suggestions: Vec<PatternSuggestion>,
+}
+
+impl std::fmt::Display for UnknownPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown pattern: '{}'", self.pattern)?;
+        for (idx, suggestion) in self.suggestions.iter().enumerate() {
+            let separator = if idx == 0 { "; closest: " } else { ", " };
+            write!(f, "{}'{}' ({})", separator, suggestion.pattern, suggestion.decoded)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownPattern {}
+
+/// Levenshtein distance between two patterns
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
         }
     }
+    row[b.len()]
 }
 
 /// Decode a BOTAN beacon message - main entry point for BOTAN decoding
@@ -164,7 +436,240 @@ impl Default for BotanDecoder {
     }
 }
 
+// Decoding and parsing are shared by worker threads, so the decoder and everything the parser
+// reads must stay Send and Sync. A cache added to any of them goes behind a Mutex or OnceLock:
+// a Cell or RefCell would stop this compiling.
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+    send_sync::<BotanDecoder>();
+    send_sync::<morse_trie::MorseStream>();
+    send_sync::<botan_parser::SignalCalibration>();
+    send_sync::<calibration::CalibrationTable>();
+    send_sync::<frames::FrameTypes>();
+    send_sync::<frames::Frame>();
+};
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let config_path = cli::config_path_arg(&args)
+        .or_else(|| config::Config::default_path().filter(|path| path.exists()).map(|path| path.to_string_lossy().into_owned()));
+    let config = match &config_path {
+        Some(path) => load(path, |path| config::Config::load(Some(path))),
+        None => config::Config::default(),
+    };
+    let command = config.apply(cli::Cli::command()).unwrap_or_else(|error| fail(error));
+    let (source, cli) = command
+        .try_get_matches_from(args)
+        .and_then(|matches| Ok((matches.subcommand_name().unwrap_or("interactive").to_string(), cli::Cli::from_arg_matches(&matches)?)))
+        .unwrap_or_else(|error| {
+            // clap's own code for usage errors (2) would read as a parse error
+            let _ = error.print();
+            std::process::exit(if error.use_stderr() { exit_code::FAILURE } else { 0 });
+        });
+    timeutil::set_display_zone(if cli.utc { timeutil::Zone::Utc } else { cli.timezone.clone() });
+    if let Err(error) = logging::init(cli.verbose as i8 - cli.quiet as i8, cli.log_level, cli.log_target, cli.syslog_facility) {
+        fail(error);
+    }
+    let calibration = match &cli.signal_calibration {
+        Some(path) => load(path, botan_parser::SignalCalibration::from_file),
+        None => botan_parser::SignalCalibration::default(),
+    };
+    let table = match &cli.telemetry_calibration {
+        Some(path) => load(path, calibration::CalibrationTable::from_file),
+        None => calibration::CalibrationTable::default(),
+    };
+    let template = match (&cli.template, &cli.template_string) {
+        (Some(path), _) => Some(load(path, template::Template::from_file)),
+        (None, Some(source)) => Some(template::Template::parse(source).unwrap_or_else(|error| fail(error))),
+        (None, None) => None,
+    };
+    if cli.format != output::OutputFormat::Text && (template.is_some() || cli.grsat_json) {
+        fail("--format only applies to the built-in output, not to templates or --grsat-json");
+    }
+    if cli.format == output::OutputFormat::Proto && cli.command.is_none() {
+        fail("--format proto needs a command that streams frames, such as decode-file or udp-audio");
+    }
+    let units = units::Units {
+        temperature: cli.temperature_unit,
+        current: cli.current_unit,
+        voltage: cli.voltage_unit,
+    };
+    let limits = match &cli.limits {
+        Some(path) => load(path, limits::Limits::from_file),
+        None => limits::Limits::default(),
+    };
+    let codes = match &cli.code_labels {
+        Some(path) => load(path, codes::FieldLabels::from_file),
+        None => codes::FieldLabels::default(),
+    };
+    let options = botan_parser::DisplayOptions {
+        lang: cli.lang,
+        units,
+        color: cli.color.enabled(),
+        limits,
+        verbose_bytes: cli.verbose_bytes,
+        bit_view: cli.bit_view,
+        calibration: table,
+        codes,
+    };
+    let decoder = match &cli.mappings {
+        Some(path) => load(path, BotanDecoder::from_config_file),
+        None => BotanDecoder::new(),
+    };
+    let frame_types = match &cli.frame_types {
+        Some(path) => load(path, frames::FrameTypes::from_file),
+        None => frames::FrameTypes::default(),
+    };
+    let mut sinks: Vec<forward::Sink> = cli.webhook.iter().cloned().map(forward::Sink::Webhook).collect();
+    sinks.extend(cli.discord_webhook.clone().map(forward::Sink::Discord));
+    if let (Some(token), Some(chat_id)) = (&cli.telegram_bot, &cli.telegram_chat) {
+        sinks.push(forward::Sink::Telegram { token: token.clone(), chat_id: chat_id.clone() });
+    }
+    sinks.extend(cli.redis.as_ref().map(|endpoint| forward::Sink::Redis {
+        url: endpoint.to_string(),
+        stream: cli.redis_stream.clone(),
+        maxlen: cli.redis_maxlen,
+    }));
+    if let Some(path) = &cli.http_sinks {
+        let http_sinks = load(path, forward::HttpSink::from_file);
+        sinks.extend(http_sinks.into_iter().map(forward::Sink::Http));
+    }
+    if let (Some(call), Some(grid)) = (&cli.amsat_call, &cli.amsat_grid) {
+        let status = forward::AmsatStatus { call: call.to_uppercase(), grid: grid.0.clone(), satellite: cli.amsat_satellite.clone() };
+        sinks.push(forward::Sink::Amsat(status));
+    }
+    let forwarder = (!sinks.is_empty()).then(|| {
+        let spool = cli.spool.as_ref().map_or_else(forward::Spool::default_path, std::path::PathBuf::from);
+        let pacing = forward::Pacing {
+            per_minute: cli.forward_rate,
+            discord: cli.discord_rate,
+            telegram: cli.telegram_rate,
+            redis: cli.redis_rate,
+            batch: cli.webhook_batch,
+        };
+        forward::Forwarder::start(sinks, cli.notify_on, options.limits, &spool, pacing).unwrap_or_else(|error| fail(error))
+    });
+    let desktop = cli.desktop_notify.then_some(notify::Desktop { notify_on: cli.notify_on, limits: options.limits });
+    let alert = (cli.beep || cli.decode_sound.is_some() || cli.alarm_sound.is_some()).then(|| {
+        alert::Alert::new(cli.decode_sound.as_deref(), cli.alarm_sound.as_deref(), options.limits).unwrap_or_else(|error| fail(ExitError::io(error)))
+    });
+    let output_file = cli.output_file.as_ref().map(|path| rotate::RotatingFile::open(std::path::Path::new(path), cli.rotate, cli.keep));
+    let output_file = output_file.transpose().unwrap_or_else(|error| fail(ExitError::io(error)));
+    let audit = cli.audit_log.as_ref().map(|path| audit::AuditLog::open(std::path::Path::new(path), cli.rotate));
+    let audit = audit.transpose().unwrap_or_else(|error| fail(ExitError::io(error)));
+    let grpc = cli.grpc.as_ref().map(|addr| {
+        let decoding = grpc::Decoding { calibration: calibration.clone(), table, frame_types: frame_types.clone() };
+        grpc::Server::start(addr, decoding, std::path::PathBuf::from(&cli.grpc_history))
+    });
+    let grpc = grpc.transpose().unwrap_or_else(|error| fail(ExitError::io(error)));
+    let dashboard = cli.dashboard.as_deref().map(|addr| dashboard::Dashboard::start(addr, options.limits));
+    let dashboard = dashboard.transpose().unwrap_or_else(|error| fail(ExitError::io(error)));
+    #[cfg(feature = "zmq")]
+    let zmq = cli.zmq_pub.as_deref().map(zmq::Publisher::bind).transpose().unwrap_or_else(|error| fail(ExitError::io(error)));
+    let calibration_version = provenance::calibration_version(&[
+        ("signal", cli.signal_calibration.as_deref()),
+        ("telemetry", cli.telemetry_calibration.as_deref()),
+    ])
+    .unwrap_or_else(|error| fail(ExitError::io(error)));
+    let printer = output::BeaconPrinter::new(calibration, options, template)
+        .with_provenance(&source, calibration_version)
+        .with_frame_types(frame_types)
+        .with_grsat_json(cli.grsat_json)
+        .with_format(cli.format)
+        .with_autocorrect(cli.autocorrect)
+        .with_strict(cli.strict)
+        .with_forwarder(forwarder)
+        .with_desktop(desktop)
+        .with_alert(alert)
+        .with_output_file(output_file)
+        .with_audit_log(audit)
+        .with_grpc(grpc)
+        .with_dashboard(dashboard);
+    #[cfg(feature = "zmq")]
+    let printer = printer.with_zmq(zmq);
+    let stats_path = cli.stats_file.as_ref().map_or_else(stats::Lifetime::default_path, std::path::PathBuf::from);
+    let health_interval = cli.daemon.then(|| std::time::Duration::from_secs(cli.health_interval));
+    let mut recorder = stats::Recorder::new(Some(stats_path.clone())).with_health_log(health_interval);
+
+    let pid_file = cli.pid_file.as_ref().map(|path| daemon::PidFile::create(std::path::Path::new(path))).transpose();
+    let pid_file = pid_file.unwrap_or_else(|error| fail(error));
+    if cli.daemon {
+        if cli.command.is_none() {
+            fail("--daemon needs a receiver command such as udp-audio or schedule");
+        }
+        daemon::handle_stop_signals().unwrap_or_else(|error| fail(error));
+        logging::info!("Started"; pid = std::process::id());
+    }
+
+    let result = match cli.command {
+        None => run_interactive(&printer, &decoder),
+        Some(cli::Command::Synth(args)) => commands::run_synth(args, &table),
+        Some(cli::Command::DecodeIq(args)) => commands::run_decode_iq(args, &printer),
+        Some(cli::Command::DecodeFile(args)) => commands::run_decode_file(args, &printer),
+        Some(cli::Command::UdpAudio(args)) => commands::run_udp_audio(args, &printer, &mut recorder),
+        Some(cli::Command::DecodeAfsk(args)) => commands::run_decode_afsk(args, &printer),
+        Some(cli::Command::DecodeKiss(args)) => commands::run_decode_kiss(args, &printer, &mut recorder),
+        Some(cli::Command::DecodeBin(args)) => commands::run_decode_bin(args, &printer),
+        Some(cli::Command::DecodeDirewolf(args)) => commands::run_decode_direwolf(args, &printer),
+        Some(cli::Command::Serial(args)) => commands::run_serial(args, &printer, &mut recorder),
+        Some(cli::Command::Skimmer(args)) => commands::run_skimmer(args, &printer, &mut recorder),
+        Some(cli::Command::Passes(args)) => commands::run_passes(args),
+        Some(cli::Command::Schedule(args)) => commands::run_schedule(args, &printer, &mut recorder),
+        Some(cli::Command::UpdateTle(args)) => commands::run_update_tle(args),
+        Some(cli::Command::Stats(args)) => commands::run_stats(args, &stats_path),
+        Some(cli::Command::Export(args)) => commands::run_export(args),
+        Some(cli::Command::Adif(args)) => commands::run_adif(args),
+        Some(cli::Command::Aggregate(args)) => commands::run_aggregate(args),
+        Some(cli::Command::Battery(args)) => commands::run_battery(args),
+        Some(cli::Command::Report(args)) => commands::run_report(args, &limits),
+        Some(cli::Command::Import(args)) => commands::run_import(args, &printer),
+        Some(cli::Command::Merge(args)) => commands::run_merge(args, &printer),
+        Some(cli::Command::Migrate(args)) => commands::run_migrate(args),
+        Some(cli::Command::Replay(args)) => commands::run_replay(args, &printer),
+        Some(cli::Command::Simulate(args)) => commands::run_simulate(args, &printer, &table, &mut recorder),
+        Some(cli::Command::Mappings(args)) => commands::run_mappings(args, &decoder),
+        Some(cli::Command::Practice(args)) => commands::run_practice(args),
+        Some(cli::Command::Explain(args)) => commands::run_explain(args, &printer),
+        Some(cli::Command::Check(args)) => commands::run_check(args),
+        Some(cli::Command::Schema(args)) => commands::run_schema(args),
+        Some(cli::Command::Convert(args)) => commands::run_convert(args, &table),
+        Some(cli::Command::Extract(args)) => commands::run_extract(args),
+        #[cfg(feature = "rtlsdr")]
+        Some(cli::Command::Rtlsdr(args)) => commands::run_rtlsdr(args, &printer, &mut recorder),
+    };
+
+    // Frames still on their way to network services get a last chance to go out
+    let forwarded = printer.flush(std::time::Duration::from_secs(10));
+    drop(pid_file);
+    let result = result.and_then(|()| forwarded.map_err(|e| ExitError::new(exit_code::FORWARD_FAILURE, e).into()));
+    if let Err(error) = result {
+        fail(error);
+    }
+}
+
+/// Report an error and end the process with its exit code: 4 for I/O errors, 1 for a bad
+/// setup, or the code an `ExitError` carries
+fn fail(error: impl Into<Box<dyn std::error::Error>>) -> ! {
+    let error = error.into();
+    eprintln!("Error: {}", error);
+    std::process::exit(exit_code::for_error(error.as_ref()))
+}
+
+/// The contents of the file at `path`, as `load` reads it, or the end of the process: an I/O
+/// error when the file cannot be read, a setup error when `load` finds something invalid in it
+fn load<T, E: Into<Box<dyn std::error::Error>>>(path: &str, load: impl FnOnce(&str) -> Result<T, E>) -> T {
+    load(path).unwrap_or_else(|error| {
+        let error = error.into();
+        if std::fs::File::open(path).is_err() {
+            fail(ExitError::io(error.to_string()));
+        }
+        fail(error)
+    })
+}
+
+
+/// Interactive decode loop on stdin; also decodes a file of beacon lines piped in
+fn run_interactive(printer: &output::BeaconPrinter, decoder: &BotanDecoder) -> Result<(), Box<dyn std::error::Error>> {
     println!("BOTAN Satellite Beacon Decoder");
     println!("==============================");
     println!("This decoder processes BOTAN satellite beacon messages.");
@@ -173,14 +678,15 @@ fn main() {
     println!();
     
     // Interactive mode
+    let mut session = repl::Session::new();
+    let mut editor = line_editor::LineEditor::new(line_editor::LineEditor::default_history_path());
     loop {
-        print!("Enter BOTAN beacon to decode (or 'quit' to exit): ");
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let input = input.trim().to_ascii_uppercase();
+        match editor.read_line("Enter BOTAN beacon to decode (or 'quit' to exit, ':help' for commands): ") {
+            Ok(line_editor::ReadLine::Eof) => break,
+            Ok(line_editor::ReadLine::Interrupted) => continue,
+            Ok(line_editor::ReadLine::Line(input)) => {
+                let input = input.trim();
+                editor.add_history(input);
                 
                 if input.eq_ignore_ascii_case("quit") || input.eq_ignore_ascii_case("exit") {
                     println!("Goodbye!");
@@ -191,32 +697,47 @@ fn main() {
                     continue;
                 }
                 
-                // Check if it's a BOTAN beacon format
-                if input.starts_with("BOTAN") {
-                    match botan_parser::parse_botan_beacon(&input) {
-                        Ok(beacon_data) => {
-                            println!("\n{}", beacon_data);
-                        },
-                        Err(error) => {
-                            println!("BOTAN Parsing Error: {}", error);
-                        }
+                if input.starts_with(':') {
+                    match input.parse().and_then(|command| session.run(&command, printer, decoder)) {
+                        Ok(output) => println!("{}", output),
+                        Err(error) => println!("{}", error),
                     }
                 } else {
-                    // Fall back to legacy morse code decoder for non-BOTAN inputs
-                    let decoder = BotanDecoder::new();
-                    match decoder.decode(&input) {
-                        Ok(decoded) => println!("Legacy Morse Decoded: {}", decoded),
-                        Err(error) => println!("Legacy Decoding Error: {}", error),
+                    let mut line = input.to_ascii_uppercase();
+                    println!("{}", session.decode(&line, printer, decoder));
+                    // A line one character off is handed back for that character to be fixed
+                    while session.last_failed() {
+                        let Some(fixed) = offer_repair(&mut editor, &line, printer)? else { break };
+                        editor.add_history(&fixed);
+                        line = fixed.to_ascii_uppercase();
+                        println!();
+                        println!("{}", session.redecode(&line, printer, decoder));
                     }
                 }
             }
-            Err(error) => {
-                println!("Error reading input: {}", error);
-                break;
-            }
+            Err(error) => return Err(error.into()),
         }
         println!();
     }
+    Ok(session.tally.result()?)
+}
+
+/// Show where `line` is one character off and let the user fix it in place; the fixed line, or
+/// None when it cannot be repaired that way or the user leaves it
+fn offer_repair(editor: &mut line_editor::LineEditor, line: &str, printer: &output::BeaconPrinter) -> std::io::Result<Option<String>> {
+    let line = botan_parser::normalize_line(line);
+    let Some(repair) = botan_parser::one_character_repair(&line).filter(|_| editor.is_terminal()) else {
+        return Ok(None);
+    };
+    let (cursor, overwrite) = match repair {
+        botan_parser::Repair::Replace(i) => (i, true),
+        botan_parser::Repair::Insert(i) | botan_parser::Repair::Remove(i) => (i, false),
+    };
+    println!("{}", repl::repair_hint(&line, repair, printer.color()));
+    match editor.edit_line("Fix (Enter to decode, Ctrl-C to leave it): ", &line, cursor, overwrite)? {
+        line_editor::ReadLine::Line(fixed) if fixed.trim() != line => Ok(Some(fixed.trim().to_string())),
+        _ => Ok(None),
+    }
 }
 
 #[cfg(test)]
@@ -246,10 +767,45 @@ mod tests {
         assert_eq!(decoder.decode(".---- ..--- ...--").unwrap(), "123");
     }
 
+    #[test]
+    fn test_encode() {
+        let decoder = BotanDecoder::new();
+        assert_eq!(decoder.encode("SOS").unwrap(), "... --- ...");
+        assert_eq!(decoder.encode("hi world").unwrap(), ".... ..  .-- --- .-. .-.. -..");
+        assert_eq!(decoder.encode("73 <SK>").unwrap(), "--... ...--  ...-.-");
+        assert!(decoder.encode("#").is_err());
+
+        let text = "BOTAN JS1YPT SI8640 A67C8D5E2AA13608 <AR>";
+        assert_eq!(decoder.decode(&decoder.encode(text).unwrap()).unwrap(), text);
+    }
+
     #[test]
     fn test_unknown_pattern() {
         let decoder = BotanDecoder::new();
-        assert!(decoder.decode(".-.-.-").is_err());
+        assert!(decoder.decode("..--").is_err());
+    }
+
+    #[test]
+    fn test_unknown_pattern_suggestions() {
+        let decoder = BotanDecoder::new();
+        let error = decoder.decode("... .-.-.. ...").unwrap_err();
+        assert_eq!(error.pattern, ".-.-..");
+        assert_eq!(error.suggestions[0].pattern, ".-.-.");
+        assert_eq!(error.suggestions[0].decoded, "<AR>");
+        assert_eq!(error.suggestions[0].distance, 1);
+        assert!(error.suggestions.iter().any(|s| s.decoded == "."));
+        assert!(error.to_string().starts_with("Unknown pattern: '.-.-..'; closest: '.-.-.' (<AR>), "));
+
+        assert_eq!(edit_distance("", ".-"), 2);
+        assert_eq!(edit_distance("-.-.", "-..-"), 2);
+        assert!(decoder.suggest("--------------", 3).is_empty());
+    }
+
+    #[test]
+    fn test_punctuation_and_prosigns() {
+        let decoder = BotanDecoder::new();
+        assert_eq!(decoder.decode("-.. . -..-. .---- -...- ..--.. .-.-.- --..--").unwrap(), "DE/1=?.,");
+        assert_eq!(decoder.decode(".-.-.  ...-.-  -.--.").unwrap(), "<AR> <SK> <KN>");
     }
 
     #[test]
@@ -268,4 +824,60 @@ mod tests {
         let decoder = BotanDecoder::with_custom_mappings(custom_map);
         assert_eq!(decoder.decode("X Y").unwrap(), "SPECIALCODE");
     }
+
+    #[test]
+    fn test_default_table() {
+        let (first, mut second) = (BotanDecoder::new(), BotanDecoder::new());
+        assert!(Arc::ptr_eq(&first.trie, &second.trie) && Arc::ptr_eq(&first.decode_map, &second.decode_map));
+        assert_eq!(first.mappings().len(), DEFAULT_MAPPINGS.len());
+        // Changing one decoder leaves the shared default alone
+        second.add_mapping(".-".to_string(), "a".to_string());
+        assert_eq!(BotanDecoder::new().decode(".-").unwrap(), "A");
+    }
+
+    #[test]
+    fn test_shared_decoder() {
+        let decoder = Arc::new(BotanDecoder::new());
+        let decoded: Vec<String> = std::thread::scope(|scope| {
+            let workers: Vec<_> = ["-... --- - .- -.", ".--- ... .---- -.-- .--. -"]
+                .into_iter()
+                .map(|morse| {
+                    let decoder = Arc::clone(&decoder);
+                    scope.spawn(move || decoder.decode(morse).unwrap())
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+        assert_eq!(decoded, ["BOTAN", "JS1YPT"]);
+    }
+
+    #[test]
+    fn test_mapping_management() {
+        let mut decoder = BotanDecoder::new();
+        let streamed = |decoder: &BotanDecoder, symbols: &str| {
+            let (mut stream, mut out) = (decoder.stream(), String::new());
+            stream.push_str(symbols, &mut out);
+            stream.finish(&mut out);
+            out
+        };
+        assert_eq!(decoder.remove_mapping("-.--."), Some("<KN>".to_string()));
+        assert_eq!(decoder.remove_mapping("-.--."), None);
+        assert!(decoder.decode("-.--.").is_err());
+        assert_eq!(streamed(&decoder, "-.--"), "Y");
+
+        let mut extra = BotanDecoder::with_custom_mappings(HashMap::new());
+        extra.extend(HashMap::from([("-.--.".to_string(), "(".to_string()), (".-".to_string(), "a".to_string())]));
+        assert_eq!(extra.iter().count(), 2);
+        decoder.merge(&extra);
+        assert_eq!(decoder.decode(".- -.--.").unwrap(), "a(");
+        assert_eq!(decoder.iter().count(), BotanDecoder::new().mappings().len());
+
+        // Clones share the tables until they change, and only the clone that changes sees it
+        let shared = decoder.clone();
+        assert!(Arc::ptr_eq(&shared.trie, &decoder.trie));
+        decoder.clear();
+        assert_eq!(shared.decode(".- -.--.").unwrap(), "a(");
+        assert_eq!(decoder.iter().next(), None);
+        assert!(decoder.decode(".-").is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,312 @@
+// Files of morse mappings for `BotanDecoder`, in one of three formats picked by extension:
+//
+//   .csv   one "pattern,value" row per mapping, as a spreadsheet exports it; an optional
+//          "pattern,value" header, and fields quoted when they hold a comma or quote
+//   .json  an object {"pattern": "value", ...} or an array of {"pattern": ..., "value": ...}
+//   other  one "pattern = value" line per mapping, with # comments
+//
+// CSV and JSON files are checked strictly and errors name the line, so a bad export shows up
+// at once instead of as a missing character later.
+//
+// Saving picks the format the same way. The "pattern = value" form is grouped under comment
+// headings like the built-in table: letters, numbers, punctuation, prosigns and other values.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::export::csv_value;
+
+/// One mapping as written, with its line in the file; 0 where the format does not say
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub line: usize,
+    pub pattern: String,
+    pub value: String,
+}
+
+/// Load the mappings of `path`, in the format its extension names
+pub fn load(path: &Path) -> Result<HashMap<String, String>, String> {
+    read(path).map(collect)
+}
+
+/// Every mapping of `path` in file order, duplicates included
+pub fn read(path: &Path) -> Result<Vec<Mapping>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Cannot read mappings '{}': {}", path.display(), e))?;
+    parse(path, &content).map_err(|e| format!("Invalid mappings '{}': {}", path.display(), e))
+}
+
+/// The mappings in `content`, read in the format the extension of `path` names
+pub fn parse(path: &Path, content: &str) -> Result<Vec<Mapping>, String> {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("csv") => parse_csv(content),
+        Some("json") => parse_json(content),
+        _ => Ok(parse_equals(content)),
+    }
+}
+
+/// A later mapping of a pattern replaces an earlier one
+fn collect(entries: Vec<Mapping>) -> HashMap<String, String> {
+    entries.into_iter().map(|entry| (entry.pattern, entry.value)).collect()
+}
+
+/// "pattern = value" lines; lines without both are skipped
+pub fn parse_equals(content: &str) -> Vec<Mapping> {
+    let mut mappings = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        // Skip empty lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((pattern, value)) = line.split_once('=') {
+            let (pattern, value) = (pattern.trim(), value.trim());
+            if !pattern.is_empty() && !value.is_empty() {
+                mappings.push(Mapping { line: number + 1, pattern: pattern.to_string(), value: value.to_string() });
+            }
+        }
+    }
+    mappings
+}
+
+/// Fields of one CSV row, trimmed; a quoted field may hold commas and doubled quotes
+fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    Ok(fields.iter().map(|field| field.trim().to_string()).collect())
+}
+
+/// "pattern,value" rows
+pub fn parse_csv(content: &str) -> Result<Vec<Mapping>, String> {
+    let mut mappings = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let at_line = |e: String| format!("line {}: {}", number + 1, e);
+        let line = line.trim_start_matches('\u{feff}').trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = csv_fields(line).map_err(at_line)?;
+        // Spreadsheets pad rows with empty columns
+        let used = fields.iter().rposition(|field| !field.is_empty()).map_or(0, |last| last + 1);
+        match &fields[..used.max(2).min(fields.len())] {
+            [pattern, value] if number == 0 && pattern.eq_ignore_ascii_case("pattern") && value.eq_ignore_ascii_case("value") => {}
+            [pattern, value] if pattern.is_empty() || value.is_empty() => return Err(at_line("empty pattern or value".to_string())),
+            [pattern, value] => mappings.push(Mapping { line: number + 1, pattern: pattern.to_string(), value: value.to_string() }),
+            _ => return Err(at_line(format!("expected pattern,value, got {} field(s)", used))),
+        }
+    }
+    Ok(mappings)
+}
+
+/// One entry of the array form
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    pattern: String,
+    value: String,
+}
+
+/// Members of a JSON object in file order, keeping repeated keys that a map would merge
+struct Members(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Members {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Members;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Members, A::Error> {
+                let mut members = Vec::new();
+                while let Some(member) = map.next_entry()? {
+                    members.push(member);
+                }
+                Ok(Members(members))
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// {"pattern": "value"} or [{"pattern": ..., "value": ...}]
+pub fn parse_json(content: &str) -> Result<Vec<Mapping>, String> {
+    let json: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let entries = match json {
+        // Parsed again as members, so a pattern given twice is not merged away
+        Value::Object(_) => serde_json::from_str::<Members>(content)
+            .map_err(|e| e.to_string())?
+            .0
+            .into_iter()
+            .map(|(pattern, value)| match value {
+                Value::String(value) => Ok(Entry { pattern, value }),
+                other => Err(format!("value of '{}' is {}, expected a string", pattern, other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        // Parsed again as entries, so errors keep their line numbers
+        Value::Array(_) => serde_json::from_str::<Vec<Entry>>(content).map_err(|e| e.to_string())?,
+        _ => return Err("expected an object of pattern: value or an array of {pattern, value}".to_string()),
+    };
+    let mut mappings = Vec::new();
+    for entry in entries {
+        if entry.pattern.trim().is_empty() {
+            return Err("empty pattern".to_string());
+        }
+        mappings.push(Mapping { line: 0, pattern: entry.pattern.trim().to_string(), value: entry.value });
+    }
+    Ok(mappings)
+}
+
+/// Write `mappings` to `path`, in the format its extension names
+pub fn save(path: &Path, mappings: &HashMap<String, String>) -> Result<(), String> {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let content = match extension.as_deref() {
+        Some("csv") => format_csv(mappings),
+        Some("json") => format_json(mappings)?,
+        _ => format_equals(mappings)?,
+    };
+    std::fs::write(path, content).map_err(|e| format!("Cannot write mappings '{}': {}", path.display(), e))
+}
+
+/// Heading a value is listed under in the "pattern = value" form
+fn group(value: &str) -> &'static str {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => "Letters",
+        (Some(c), None) if c.is_ascii_digit() => "Numbers",
+        (Some(c), None) if c.is_ascii_punctuation() => "Punctuation",
+        _ if value.starts_with('<') && value.ends_with('>') => "Prosigns, shown in angle brackets",
+        _ => "Other",
+    }
+}
+
+const GROUPS: [&str; 5] = ["Letters", "Numbers", "Punctuation", "Prosigns, shown in angle brackets", "Other"];
+
+/// Sorted by value, then pattern, so the file reads like the built-in table
+fn sorted(mappings: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut sorted: Vec<_> = mappings.iter().collect();
+    sorted.sort_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)));
+    sorted
+}
+
+pub fn format_equals(mappings: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::from("# Morse mappings: pattern = value\n");
+    for heading in GROUPS {
+        let entries: Vec<_> = sorted(mappings).into_iter().filter(|(_, value)| group(value) == heading).collect();
+        if entries.is_empty() {
+            continue;
+        }
+        writeln!(out, "\n# {}", heading).unwrap();
+        for (pattern, value) in entries {
+            // Reading splits at the first '=' and trims both sides
+            if pattern.contains('=') || pattern.starts_with('#') || pattern.trim() != pattern || value.trim() != value {
+                return Err(format!("Mapping '{}' -> '{}' cannot be written as pattern = value; save as .csv or .json", pattern, value));
+            }
+            writeln!(out, "{} = {}", pattern, value).unwrap();
+        }
+    }
+    Ok(out)
+}
+
+pub fn format_csv(mappings: &HashMap<String, String>) -> String {
+    let mut out = String::from("pattern,value\n");
+    for (pattern, value) in sorted(mappings) {
+        writeln!(out, "{},{}", csv_value(pattern), csv_value(value)).unwrap();
+    }
+    out
+}
+
+pub fn format_json(mappings: &HashMap<String, String>) -> Result<String, String> {
+    let ordered: BTreeMap<_, _> = mappings.iter().collect();
+    serde_json::to_string_pretty(&ordered).map(|json| json + "\n").map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "\u{feff}Pattern,Value\r\n.-,A\r\n--..--,\",\"\r\n.-..-.,\"\"\"\",,\n\n# comment\n-...-, = \n";
+        let mappings = collect(parse_csv(csv).unwrap());
+        assert_eq!(mappings.len(), 4);
+        assert_eq!(mappings[".-"], "A");
+        assert_eq!(mappings["--..--"], ",");
+        assert_eq!(mappings[".-..-."], "\"");
+        assert_eq!(mappings["-...-"], "=");
+
+        assert_eq!(parse_csv(".-,A\n-...\n").unwrap_err(), "line 2: expected pattern,value, got 1 field(s)");
+        assert_eq!(parse_csv(".-,A,B\n").unwrap_err(), "line 1: expected pattern,value, got 3 field(s)");
+        assert_eq!(parse_csv(".-,\"A\n").unwrap_err(), "line 1: unterminated quote");
+        assert_eq!(parse_csv(",A\n").unwrap_err(), "line 1: empty pattern or value");
+        assert_eq!(parse_csv(".-,\"\"\n").unwrap_err(), "line 1: empty pattern or value");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let mappings = collect(parse_json(r#"{".-": "A", "-...": "B"}"#).unwrap());
+        assert_eq!(mappings["-..."], "B");
+        let mappings = collect(parse_json("[\n  {\"pattern\": \".-\", \"value\": \"A\"}\n]").unwrap());
+        assert_eq!(mappings[".-"], "A");
+
+        assert!(parse_json("[\n  {\"pattern\": \".-\"}\n]").unwrap_err().contains("line 2"));
+        assert!(parse_json("{\n\".-\": \"A\",\n}").unwrap_err().contains("line 3"));
+        assert_eq!(parse_json(r#"{".-": 1}"#).unwrap_err(), "value of '.-' is 1, expected a string");
+        // A repeated key is kept, so it can be reported
+        assert_eq!(parse_json(r#"{".-": "A", ".-": "B"}"#).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_load_by_extension() {
+        let dir = std::env::temp_dir().join(format!("botan_mappings_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [("map.CSV", ".-,A\n"), ("map.json", r#"{".-": "A"}"#), ("map.txt", "# table\n.- = A\n")] {
+            std::fs::write(dir.join(name), content).unwrap();
+            assert_eq!(load(&dir.join(name)).unwrap()[".-"], "A", "{}", name);
+        }
+        std::fs::write(dir.join("bad.csv"), ".-\n").unwrap();
+        assert!(load(&dir.join("bad.csv")).unwrap_err().ends_with("bad.csv': line 1: expected pattern,value, got 1 field(s)"));
+
+        // Every format reads back what was saved
+        let mappings = crate::BotanDecoder::new().mappings().clone();
+        for name in ["saved.csv", "saved.json", "saved.txt"] {
+            save(&dir.join(name), &mappings).unwrap();
+            assert_eq!(load(&dir.join(name)).unwrap(), mappings, "{}", name);
+        }
+        let text = std::fs::read_to_string(dir.join("saved.txt")).unwrap();
+        assert!(text.contains("\n# Letters\n.- = A\n-... = B\n"));
+        assert!(text.contains("\n# Prosigns, shown in angle brackets\n.-.-. = <AR>\n"));
+        let odd = HashMap::from([("a=b".to_string(), "X".to_string())]);
+        assert!(save(&dir.join("odd.txt"), &odd).unwrap_err().contains("save as .csv or .json"));
+        save(&dir.join("odd.csv"), &odd).unwrap();
+        assert_eq!(load(&dir.join("odd.csv")).unwrap(), odd);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
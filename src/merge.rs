@@ -0,0 +1,234 @@
+// Receptions of several ground stations combined into one history for `merge`. Each source is a
+// station's history directory (pass.json files, as `schedule` and `import` write them) or a log
+// `replay` reads: a single pass.json, an audit log or --grsat-json lines. Every reception is
+// put on one UTC timeline, whatever offset its station logged in, and keeps the station it came
+// from as its observer. Lines without a reception time cannot be placed and are left out.
+//
+// A frame heard by several stations arrives once per station. Copies of the same data bytes from
+// different stations within a short window are collapsed into one beacon, the first reception,
+// which lists every station's copy with its signal report and delay. A station's own repeats
+// are never collapsed, since BOTAN sends the same telemetry again when nothing has changed.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::botan_parser::{self, BotanRSSI, SignalCalibration};
+use crate::migrate;
+use crate::orbit::{Illumination, OrbitState};
+use crate::replay;
+use crate::satnogs::PASS_GAP_SECONDS;
+use crate::scheduler::{self, Reception};
+use crate::timeutil;
+
+/// One station's history or log, attributed to `observer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub observer: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    /// "NAME=PATH", or a bare path named after its file or directory
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (observer, path) = match s.split_once('=') {
+            Some((observer, path)) => (observer.trim().to_string(), PathBuf::from(path)),
+            None => {
+                let path = PathBuf::from(s);
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+                (stem, path)
+            }
+        };
+        if observer.is_empty() || path.as_os_str().is_empty() {
+            return Err(format!("Invalid source '{}'. Expected NAME=PATH or a path", s));
+        }
+        Ok(Source { observer, path })
+    }
+}
+
+/// One beacon line as received by one station
+#[derive(Debug, Clone, PartialEq)]
+pub struct Received {
+    pub time: f64,
+    pub line: String,
+    pub observer: String,
+    pub orbit: Option<OrbitState>,   // As the station's TLE put it, where it had one
+}
+
+/// The beacons of one saved pass; frames the station imported keep their own observer
+fn pass_beacons(pass: &Value, observer: &str) -> Result<Vec<Received>, String> {
+    let mut received = Vec::new();
+    for beacon in pass["beacons"].as_array().into_iter().flatten() {
+        let (Some(time), Some(line)) = (beacon["time"].as_str(), beacon["text"].as_str()) else {
+            continue;
+        };
+        let illumination: Option<Illumination> = serde_json::from_value(beacon["illumination"].clone()).unwrap_or(None);
+        received.push(Received {
+            time: timeutil::parse_utc(time)?,
+            line: line.to_string(),
+            observer: beacon["observer"].as_str().unwrap_or(observer).to_string(),
+            orbit: illumination.map(|illumination| OrbitState { illumination, number: beacon["orbit"].as_u64() }),
+        });
+    }
+    Ok(received)
+}
+
+/// Every timed reception of a source
+pub fn read_source(source: &Source) -> Result<Vec<Received>, String> {
+    let read = |path: &Path| fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e));
+    if source.path.is_dir() {
+        let entries = fs::read_dir(&source.path).map_err(|e| format!("Cannot read '{}': {}", source.path.display(), e))?;
+        let mut received = Vec::new();
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path().join("pass.json"))).filter(|p| p.is_file()) {
+            let pass = scheduler::load_pass(&path)?;
+            received.extend(pass_beacons(&pass, &source.observer).map_err(|e| format!("{}: {}", path.display(), e))?);
+        }
+        return Ok(received);
+    }
+
+    let content = read(&source.path)?;
+    if let Ok(pass) = serde_json::from_str::<Value>(&content) {
+        if pass.get("beacons").is_some() {
+            let pass = migrate::upgrade_pass(pass).map_err(|e| format!("{}: {}", source.path.display(), e))?;
+            return pass_beacons(&pass, &source.observer).map_err(|e| format!("{}: {}", source.path.display(), e));
+        }
+    }
+    let recorded = replay::read_log(&content).map_err(|e| format!("{}: {}", source.path.display(), e))?;
+    Ok(recorded
+        .into_iter()
+        .filter_map(|r| Some(Received { time: r.time?, line: r.line, observer: source.observer.clone(), orbit: None }))
+        .collect())
+}
+
+/// Sort receptions of every station into one timeline and split it into passes wherever it is
+/// quiet for more than `PASS_GAP_SECONDS`
+pub fn timeline(mut received: Vec<Received>) -> Vec<Vec<Received>> {
+    received.sort_by(|a, b| a.time.total_cmp(&b.time).then_with(|| a.observer.cmp(&b.observer)));
+    let mut passes: Vec<Vec<Received>> = Vec::new();
+    for r in received {
+        match passes.last_mut() {
+            Some(pass) if r.time - pass[pass.len() - 1].time <= PASS_GAP_SECONDS => pass.push(r),
+            _ => passes.push(vec![r]),
+        }
+    }
+    passes
+}
+
+/// Data bytes and SI bytes of a beacon line, if it has a readable frame
+fn frame_bytes(line: &str) -> Option<(Vec<u8>, Option<BotanRSSI>)> {
+    let line = botan_parser::normalize_line(line);
+    let (rssi, data) = botan_parser::parse_beacon_header(&line, &SignalCalibration::default()).ok()?;
+    Some((botan_parser::parse_hex_data(data).ok()?, rssi))
+}
+
+/// Collapse the copies of one frame that other stations heard within `window` seconds of the
+/// first; returns each remaining reception with every station's copy when there was more than
+/// one. Receptions must be in time order, as `timeline` leaves them.
+pub fn reconcile(pass: Vec<Received>, window: f64) -> Vec<(Received, Vec<Reception>)> {
+    struct Copy {
+        received: Received,
+        frame: Option<Vec<u8>>,
+        receptions: Vec<Reception>,
+    }
+    let mut copies: Vec<Copy> = Vec::new();
+    for r in pass {
+        let (frame, signal) = frame_bytes(&r.line).map_or((None, None), |(frame, signal)| (Some(frame), signal));
+        let reception = |first: f64| Reception {
+            observer: r.observer.clone(),
+            time_offset: r.time - first,
+            raw_rssi: signal.as_ref().map(|s| s.raw_rssi),
+            raw_snr: signal.as_ref().map(|s| s.raw_snr),
+        };
+        let duplicate = copies.iter_mut().rev().take_while(|c| r.time - c.received.time <= window).find(|c| {
+            frame.is_some() && c.frame == frame && !c.receptions.iter().any(|other| other.observer == r.observer)
+        });
+        match duplicate {
+            Some(copy) => {
+                let reception = reception(copy.received.time);
+                copy.receptions.push(reception);
+            }
+            None => copies.push(Copy { receptions: vec![reception(r.time)], received: r, frame }),
+        }
+    }
+    copies
+        .into_iter()
+        .map(|c| {
+            let receptions = if c.receptions.len() > 1 { c.receptions } else { Vec::new() };
+            (c.received, receptions)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source() {
+        let source: Source = "tokyo=/data/tokyo/passes".parse().unwrap();
+        assert_eq!(source, Source { observer: "tokyo".to_string(), path: PathBuf::from("/data/tokyo/passes") });
+        let source: Source = "logs/osaka.ndjson".parse().unwrap();
+        assert_eq!(source.observer, "osaka");
+        assert!("=passes".parse::<Source>().is_err());
+    }
+
+    #[test]
+    fn test_merge_timeline() {
+        let root = std::env::temp_dir().join(format!("botan_merge_test_{}", std::process::id()));
+        fs::create_dir_all(root.join("20240501T123456Z")).unwrap();
+        // A saved pass with one frame of its own and one imported from SatNOGS
+        let pass = r#"{"beacons": [
+            {"time": "2024-05-01T12:35:56Z", "text": "BOTAN JS1YPT A67C8D5E2AA13608", "illumination": "sunlight", "orbit": 1234},
+            {"time": "2024-05-01T12:37:00Z", "text": "BOTAN JS1YPT A57EB76823210E08", "illumination": null, "observer": "JA1XYZ-PM95"}
+        ]}"#;
+        fs::write(root.join("20240501T123456Z/pass.json"), pass).unwrap();
+        // Another station logs in JST, and a line without a time cannot be placed
+        let log = concat!(
+            r#"{"time": "2024-05-01T21:36:30+09:00", "frame": "A67C8D5E2AA13608"}"#,
+            "\n",
+            r#"{"time": "2024-05-02T09:00:00+09:00", "frame": "A57EB76823210E08"}"#,
+            "\nBOTAN JS1YPT A67C8D5E2AA13608\n",
+        );
+        fs::write(root.join("osaka.ndjson"), log).unwrap();
+
+        let mut received = read_source(&format!("tokyo={}", root.display()).parse().unwrap()).unwrap();
+        assert_eq!(received.len(), 2);
+        received.extend(read_source(&Source { observer: "osaka".to_string(), path: root.join("osaka.ndjson") }).unwrap());
+        assert_eq!(received.len(), 4);
+
+        let passes = timeline(received);
+        assert_eq!(passes.len(), 2);
+        let observers: Vec<&str> = passes[0].iter().map(|r| r.observer.as_str()).collect();
+        assert_eq!(observers, ["tokyo", "osaka", "JA1XYZ-PM95"]);
+        assert_eq!(passes[0][1].time, 1714566990.0);
+        assert_eq!(passes[0][0].orbit, Some(OrbitState { illumination: Illumination::Sunlight, number: Some(1234) }));
+        assert_eq!(passes[0][2].orbit, None);
+        assert_eq!(timeutil::format_utc(passes[1][0].time), "2024-05-02T00:00:00Z");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let at = |time: f64, observer: &str, line: &str| Received { time, line: line.to_string(), observer: observer.to_string(), orbit: None };
+        let pass = vec![
+            at(100.0, "tokyo", "BOTAN JS1YPT SI8640 A67C8D5E2AA13608"),
+            at(101.5, "osaka", "BOTAN JS1YPT SI9040 A67C8D5E2AA13608"),
+            // tokyo's own repeat, and a copy outside the window
+            at(104.0, "tokyo", "BOTAN JS1YPT A67C8D5E2AA13608"),
+            at(130.0, "sapporo", "BOTAN JS1YPT A67C8D5E2AA13608"),
+            // Lines without a frame are never collapsed
+            at(131.0, "tokyo", "BOTAN JS1YPT A67Q"),
+            at(131.0, "osaka", "BOTAN JS1YPT A67Q"),
+        ];
+        let merged = reconcile(pass, 10.0);
+        let observers: Vec<&str> = merged.iter().map(|(r, _)| r.observer.as_str()).collect();
+        assert_eq!(observers, ["tokyo", "tokyo", "sapporo", "tokyo", "osaka"]);
+        let receptions = &merged[0].1;
+        assert_eq!(receptions[0], Reception { observer: "tokyo".to_string(), time_offset: 0.0, raw_rssi: Some(0x86), raw_snr: Some(0x40) });
+        assert_eq!(receptions[1], Reception { observer: "osaka".to_string(), time_offset: 1.5, raw_rssi: Some(0x90), raw_snr: Some(0x40) });
+        assert!(merged[1..].iter().all(|(_, receptions)| receptions.is_empty()));
+    }
+}
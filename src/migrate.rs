@@ -0,0 +1,110 @@
+// Versions of the records this decoder keeps or hands to other programs: pass.json files, the
+// lifetime statistics, audit log entries and telemetry JSON lines. Each carries the
+// `schema_version` it was written with; records from before versions were recorded count as
+// version 1. Readers pass every record through `upgrade_pass` or `upgrade_record`, which apply the
+// steps from its version up to the current one, so a history written by an older release reads
+// like a new one. Changing the shape of a record means bumping SCHEMA_VERSION and adding a step
+// for every kind of record, a no-op where that kind did not change.
+use serde_json::Value;
+
+/// The version of the records written by this build
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Upgrades a record from one version to the next, the first from version 1 to 2
+type Step = fn(&mut Value);
+
+const PASS_STEPS: [Step; (SCHEMA_VERSION - 1) as usize] = [pass_v2];
+const RECORD_STEPS: [Step; (SCHEMA_VERSION - 1) as usize] = [|_| {}];
+
+/// A pass.json in the current schema
+pub fn upgrade_pass(pass: Value) -> Result<Value, String> {
+    upgrade(pass, &PASS_STEPS)
+}
+
+/// Lifetime statistics, an audit log entry or a telemetry JSON line in the current schema. Only
+/// passes changed shape so far.
+pub fn upgrade_record(record: Value) -> Result<Value, String> {
+    upgrade(record, &RECORD_STEPS)
+}
+
+/// The version a record was written with
+pub fn version(record: &Value) -> Result<u64, String> {
+    match record.get("schema_version") {
+        None => Ok(1),
+        Some(version) => version.as_u64().filter(|v| *v >= 1).ok_or_else(|| format!("Invalid schema_version {}", version)),
+    }
+}
+
+fn upgrade(mut record: Value, steps: &[Step]) -> Result<Value, String> {
+    if !record.is_object() {
+        return Err("Not a JSON object".to_string());
+    }
+    let version = version(&record)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Written with schema version {} by a newer decoder; this one reads up to version {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    for step in &steps[version as usize - 1..] {
+        step(&mut record);
+    }
+    record["schema_version"] = SCHEMA_VERSION.into();
+    Ok(record)
+}
+
+/// Version 1 passes were written before the SI bytes could be calibrated: `rssi_dbm` and `snr_db`
+/// held the raw bytes, which are now `raw_rssi` and `raw_snr` next to the calibrated values. A
+/// demodulator that never settled on a speed wrote a `wpm` of 0 rather than null.
+fn pass_v2(pass: &mut Value) {
+    if pass["wpm"] == 0.0 {
+        pass["wpm"] = Value::Null;
+    }
+    let Some(beacons) = pass.get_mut("beacons").and_then(Value::as_array_mut) else { return };
+    for beacon in beacons {
+        for frame in ["data", "typed"] {
+            let Some(rssi) = beacon.get_mut(frame).and_then(|frame| frame.get_mut("rssi")).and_then(Value::as_object_mut) else {
+                continue;
+            };
+            if rssi.contains_key("raw_rssi") {
+                continue;
+            }
+            for (raw, old) in [("raw_rssi", "rssi_dbm"), ("raw_snr", "snr_db")] {
+                let value = rssi.insert(old.to_string(), Value::Null).unwrap_or(Value::Null);
+                rssi.insert(raw.to_string(), value.as_f64().map_or(Value::Null, |v| (v as u8).into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_upgrade_pass() {
+        let old = json!({
+            "aos": "2024-05-01T12:34:56Z",
+            "max_elevation_deg": 42.0,
+            "wpm": 0.0,
+            "beacons": [
+                {"time": "2024-05-01T12:35:56Z", "data": {"rssi": {"rssi_dbm": 134.0, "snr_db": 64.0}}, "illumination": "sunlight"},
+                {"time": "2024-05-01T12:36:26Z", "data": null, "error": "Invalid data length"}
+            ]
+        });
+        let pass = upgrade_pass(old).unwrap();
+        assert_eq!(pass["schema_version"], SCHEMA_VERSION);
+        assert_eq!(pass["wpm"], Value::Null);
+        assert_eq!(pass["beacons"][0]["data"]["rssi"], json!({"raw_rssi": 134, "raw_snr": 64, "rssi_dbm": null, "snr_db": null}));
+        assert_eq!(pass["beacons"][1]["error"], "Invalid data length");
+
+        // Current passes come through as they are
+        assert_eq!(upgrade_pass(pass.clone()).unwrap(), pass);
+        let newer = json!({"schema_version": SCHEMA_VERSION + 1, "beacons": []});
+        assert!(upgrade_pass(newer).unwrap_err().contains("newer decoder"));
+        assert!(upgrade_record(json!({"schema_version": "2"})).is_err());
+        assert!(upgrade_record(json!([])).is_err());
+        assert_eq!(upgrade_record(json!({"input": "BOTAN"})).unwrap()["schema_version"], SCHEMA_VERSION);
+    }
+}
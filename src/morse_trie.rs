@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+// Character substituted for a pattern the table does not know
+const UNKNOWN: &str = "*";
+const ROOT: usize = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: Vec<(char, usize)>,  // Usually just '.' and '-'
+    value: Option<String>,
+}
+
+/// Prefix tree over morse patterns, walked one symbol at a time
+#[derive(Debug, Clone)]
+pub struct MorseTrie {
+    nodes: Vec<Node>,
+}
+
+impl MorseTrie {
+    pub fn new() -> Self {
+        MorseTrie { nodes: vec![Node::default()] }
+    }
+
+    /// Add or replace the value of a pattern
+    pub fn insert(&mut self, pattern: &str, value: &str) {
+        let mut node = ROOT;
+        for symbol in pattern.chars() {
+            node = match self.child(node, symbol) {
+                Some(next) => next,
+                None => {
+                    self.nodes.push(Node::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].children.push((symbol, next));
+                    next
+                }
+            };
+        }
+        self.nodes[node].value = Some(value.to_string());
+    }
+
+    pub fn get(&self, pattern: &str) -> Option<&str> {
+        let mut node = ROOT;
+        for symbol in pattern.chars() {
+            node = self.child(node, symbol)?;
+        }
+        self.nodes[node].value.as_deref()
+    }
+
+    fn child(&self, node: usize, symbol: char) -> Option<usize> {
+        self.nodes[node].children.iter().find(|(c, _)| *c == symbol).map(|(_, next)| *next)
+    }
+
+    /// No longer pattern starts with this one, so its character is already certain
+    fn is_leaf(&self, node: usize) -> bool {
+        self.nodes[node].children.is_empty()
+    }
+}
+
+impl Default for MorseTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes a morse symbol stream as it arrives. A single space ends a character and two or more
+/// end a word; characters are also emitted as soon as no longer pattern could follow, and a symbol
+/// that cannot extend the current pattern starts a new character, so gapless input still decodes.
+pub struct MorseStream {
+    trie: Arc<MorseTrie>,
+    node: usize,
+    spaces: usize,
+    in_word: bool,
+}
+
+impl MorseStream {
+    pub fn new(trie: Arc<MorseTrie>) -> Self {
+        MorseStream { trie, node: ROOT, spaces: 0, in_word: false }
+    }
+
+    /// Feed one symbol ('.', '-' or ' '), appending any decoded text to `out`
+    pub fn push(&mut self, symbol: char, out: &mut String) {
+        if symbol == ' ' {
+            self.flush(out);
+            self.spaces += 1;
+            if self.spaces == 2 && self.in_word {
+                out.push(' ');
+                self.in_word = false;
+            }
+            return;
+        }
+
+        self.spaces = 0;
+        match self.trie.child(self.node, symbol) {
+            Some(next) => self.node = next,
+            None => {
+                self.flush(out);
+                match self.trie.child(ROOT, symbol) {
+                    Some(next) => self.node = next,
+                    None => {
+                        out.push_str(UNKNOWN);
+                        self.in_word = true;
+                    }
+                }
+            }
+        }
+        if self.node != ROOT && self.trie.is_leaf(self.node) {
+            self.flush(out);
+        }
+    }
+
+    pub fn push_str(&mut self, symbols: &str, out: &mut String) {
+        for symbol in symbols.chars() {
+            self.push(symbol, out);
+        }
+    }
+
+    /// Emit the pattern in progress at end of input
+    pub fn finish(&mut self, out: &mut String) {
+        self.flush(out);
+    }
+
+    fn flush(&mut self, out: &mut String) {
+        if self.node == ROOT {
+            return;
+        }
+        out.push_str(self.trie.nodes[self.node].value.as_deref().unwrap_or(UNKNOWN));
+        self.in_word = true;
+        self.node = ROOT;
+    }
+}
+
+/// The text decoded from an iterator of morse symbols, a character at a time. Symbols are only
+/// pulled until the next character is certain, so over a live source, such as a channel fed by
+/// the demodulator, each character comes out as soon as it is keyed.
+pub struct DecodeIter<I> {
+    stream: MorseStream,
+    symbols: I,
+    out: String,         // Decoded but not yet yielded, from `next`
+    next: usize,
+    finished: bool,
+}
+
+impl<I: Iterator<Item = char>> DecodeIter<I> {
+    pub fn new(stream: MorseStream, symbols: I) -> Self {
+        DecodeIter { stream, symbols, out: String::new(), next: 0, finished: false }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for DecodeIter<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            if let Some(c) = self.out[self.next..].chars().next() {
+                self.next += c.len_utf8();
+                return Some(c);
+            }
+            self.out.clear();
+            self.next = 0;
+            if self.finished {
+                return None;
+            }
+            match self.symbols.next() {
+                Some(symbol) => self.stream.push(symbol, &mut self.out),
+                None => {
+                    self.stream.finish(&mut self.out);
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> MorseStream {
+        crate::BotanDecoder::new().stream()
+    }
+
+    #[test]
+    fn test_trie_lookup() {
+        let mut trie = MorseTrie::new();
+        trie.insert(".-", "A");
+        trie.insert(".", "E");
+        assert_eq!(trie.get(".-"), Some("A"));
+        assert_eq!(trie.get("."), Some("E"));
+        assert_eq!(trie.get("-"), None);
+        assert_eq!(trie.get(""), None);
+        trie.insert(".-", "a");
+        assert_eq!(trie.get(".-"), Some("a"));
+    }
+
+    #[test]
+    fn test_stream_with_gaps() {
+        let mut stream = stream();
+        let mut out = String::new();
+        stream.push_str("-... --- - .- -.  .--- ... .---- -.-- .--. -", &mut out);
+        assert_eq!(out, "BOTAN JS1YP");
+        stream.finish(&mut out);
+        assert_eq!(out, "BOTAN JS1YPT");
+    }
+
+    #[test]
+    fn test_stream_emits_unambiguous_characters_early() {
+        let mut stream = stream();
+        let mut out = String::new();
+        // "0" cannot be the start of anything longer
+        stream.push_str("-----", &mut out);
+        assert_eq!(out, "0");
+        // "E" could still become "A", "I", ...
+        stream.push_str(".", &mut out);
+        assert_eq!(out, "0");
+    }
+
+    #[test]
+    fn test_decode_iter() {
+        let decoder = crate::BotanDecoder::new();
+        let text: String = decoder.decode_iter("-... --- - .- -.  .-.-.".chars()).collect();
+        assert_eq!(text, "BOTAN <AR>");
+
+        // Characters come out as soon as they are certain, without waiting for the rest
+        let (send, receive) = std::sync::mpsc::channel();
+        let mut decoded = decoder.decode_iter(receive);
+        for symbol in "----- ".chars() {
+            send.send(symbol).unwrap();
+        }
+        assert_eq!(decoded.next(), Some('0'));
+        send.send('.').unwrap();
+        drop(send);
+        assert_eq!(decoded.collect::<String>(), "E");
+    }
+
+    #[test]
+    fn test_stream_without_gaps() {
+        let mut stream = stream();
+        let mut out = String::new();
+        stream.push_str("....-.....--...", &mut out);
+        stream.finish(&mut out);
+        assert_eq!(out, "457");
+    }
+}
@@ -0,0 +1,143 @@
+// Short human-readable summaries of decoded frames for notification services and the desktop,
+// and which frames are worth one. A summary is a title line plus the analog values in V, mA and
+// °C, or the fields of a typed frame; values outside --limits are listed as alarms. Desktop
+// notifications go through notify-send (freedesktop.org) or osascript on macOS.
+use std::process::Command;
+use std::str::FromStr;
+use std::thread;
+
+use crate::frames::Frame;
+use crate::limits::Limits;
+use crate::logging::debug;
+use crate::timeutil;
+
+/// Which frames raise a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyOn {
+    All,
+    Alarms,    // Only frames with a value outside its limits
+}
+
+impl FromStr for NotifyOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "all" => Ok(NotifyOn::All),
+            "alarms" => Ok(NotifyOn::Alarms),
+            _ => Err(format!("Unknown notification filter '{}'. Expected all or alarms", s)),
+        }
+    }
+}
+
+/// Notification text for one frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub title: String,
+    pub text: String,
+    pub alarms: Vec<&'static str>,
+}
+
+impl Summary {
+    pub fn new(frame: &Frame, time: Option<f64>, limits: &Limits) -> Self {
+        let received = time.map_or_else(String::new, |time| format!(" at {}", timeutil::format_display(time)));
+        match frame {
+            Frame::Housekeeping(data) => {
+                let t = &data.telemetry;
+                let alarms = limits.alarms(t);
+                let mut text = format!(
+                    "BAT_V {:.2} V, BAT_I {:.0} mA, BAT_T {:.1} °C, BPB_T {:.1} °C, RAW_I {:.0} mA",
+                    t.bat_v, t.bat_i, t.bat_t, t.bpb_t, t.raw_i
+                );
+                if !alarms.is_empty() {
+                    text += &format!("\nALARM: {}", alarms.iter().map(|name| name.to_uppercase()).collect::<Vec<_>>().join(", "));
+                }
+                Summary { title: format!("{} {} beacon{}", data.satellite_name, data.call_sign, received), text, alarms }
+            }
+            Frame::Typed(typed) => {
+                let fields: Vec<String> =
+                    typed.fields.iter().map(|v| format!("{} {}{}", v.name, v.value, v.unit.as_deref().map_or(String::new(), |u| format!(" {}", u)))).collect();
+                Summary {
+                    title: format!("{} {} {} frame{}", typed.satellite_name, typed.call_sign, typed.frame_type, received),
+                    text: fields.join(", "),
+                    alarms: Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// Whether `filter` lets this frame through
+    pub fn wanted(&self, filter: NotifyOn) -> bool {
+        filter == NotifyOn::All || !self.alarms.is_empty()
+    }
+}
+
+/// Pops up a desktop notification for decoded frames
+#[derive(Debug, Clone, Copy)]
+pub struct Desktop {
+    pub notify_on: NotifyOn,
+    pub limits: Limits,
+}
+
+impl Desktop {
+    pub fn notify(&self, frame: &Frame, time: Option<f64>) {
+        let summary = Summary::new(frame, time, &self.limits);
+        if !summary.wanted(self.notify_on) {
+            return;
+        }
+        let mut command = desktop_command(&summary);
+        // The notification daemon may take a while to answer; don't hold up decoding
+        thread::spawn(move || {
+            if let Err(error) = command.status() {
+                debug!("desktop notification failed"; error = error);
+            }
+        });
+    }
+}
+
+fn desktop_command(summary: &Summary) -> Command {
+    if cfg!(target_os = "macos") {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let script = format!("display notification {} with title {}", quote(&summary.text), quote(&summary.title));
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let urgency = if summary.alarms.is_empty() { "normal" } else { "critical" };
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", "botan_cw_decoder", "--urgency", urgency, &summary.title, &summary.text]);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_summary() {
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap());
+        let summary = Summary::new(&frame, Some(1714566896.0), &Limits::default());
+        assert_eq!(summary.title, "BOTAN JS1YPT beacon at 2024-05-01T12:34:56Z");
+        assert_eq!(summary.text, "BAT_V 4.28 V, BAT_I 125 mA, BAT_T 20.6 °C, BPB_T 38.6 °C, RAW_I 226 mA");
+        assert!(summary.wanted(NotifyOn::All) && !summary.wanted(NotifyOn::Alarms));
+
+        let limits: Limits = serde_json::from_str(r#"{"bat_t": {"max": 20}}"#).unwrap();
+        let summary = Summary::new(&frame, None, &limits);
+        assert_eq!(summary.title, "BOTAN JS1YPT beacon");
+        assert!(summary.text.ends_with("\nALARM: BAT_T"));
+        assert!(summary.wanted(NotifyOn::Alarms));
+        assert_eq!("Alarms".parse(), Ok(NotifyOn::Alarms));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_desktop_command() {
+        let summary = Summary { title: "BOTAN JS1YPT beacon".to_string(), text: "ALARM: BAT_T".to_string(), alarms: vec!["bat_t"] };
+        let command = desktop_command(&summary);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(command.get_program(), "notify-send");
+        assert_eq!(args, ["--app-name", "botan_cw_decoder", "--urgency", "critical", "BOTAN JS1YPT beacon", "ALARM: BAT_T"]);
+    }
+}
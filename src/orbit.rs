@@ -0,0 +1,374 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sgp4::{Sgp4, StateVector};
+use crate::timeutil;
+use crate::tle::Tle;
+
+const EARTH_ROTATION_RAD_S: f64 = 7.292115e-5;
+const SPEED_OF_LIGHT_KM_S: f64 = 299792.458;
+// WGS-84 ellipsoid for the ground station
+const WGS84_A_KM: f64 = 6378.137;
+const WGS84_F: f64 = 1.0 / 298.257223563;
+const AU_KM: f64 = 149597870.7;
+
+/// Ground station location
+#[derive(Debug, Clone, Copy)]
+pub struct Observer {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,  // East positive
+    pub altitude_m: f64,
+}
+
+/// Satellite position as seen from the observer
+#[derive(Debug, Clone, Copy)]
+pub struct LookAngles {
+    pub azimuth_deg: f64,
+    pub elevation_deg: f64,
+    pub range_km: f64,
+    pub range_rate_km_s: f64,  // Positive when receding
+}
+
+impl Observer {
+    /// Earth-fixed position [km]
+    fn ecef(&self) -> [f64; 3] {
+        let lat = self.latitude_deg.to_radians();
+        let lon = self.longitude_deg.to_radians();
+        let alt = self.altitude_m / 1000.0;
+        let e2 = WGS84_F * (2.0 - WGS84_F);
+        let n = WGS84_A_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        [
+            (n + alt) * lat.cos() * lon.cos(),
+            (n + alt) * lat.cos() * lon.sin(),
+            (n * (1.0 - e2) + alt) * lat.sin(),
+        ]
+    }
+}
+
+/// Greenwich mean sidereal time (IAU-82) [rad]
+pub fn gmst(unix: f64) -> f64 {
+    let tut1 = (timeutil::julian_date(unix) - 2451545.0) / 36525.0;
+    let seconds = -6.2e-6 * tut1.powi(3)
+        + 0.093104 * tut1 * tut1
+        + (876600.0 * 3600.0 + 8640184.812866) * tut1
+        + 67310.54841;
+    (seconds * PI / 180.0 / 240.0).rem_euclid(2.0 * PI)
+}
+
+/// Topocentric look angles and range rate from a TEME state at the given time
+pub fn look_angles(state: &StateVector, observer: &Observer, unix: f64) -> LookAngles {
+    let theta = gmst(unix);
+    let (sin_t, cos_t) = theta.sin_cos();
+
+    // Observer position and velocity rotated into the inertial frame
+    let obs = observer.ecef();
+    let obs_eci = [cos_t * obs[0] - sin_t * obs[1], sin_t * obs[0] + cos_t * obs[1], obs[2]];
+    let obs_vel = [-EARTH_ROTATION_RAD_S * obs_eci[1], EARTH_ROTATION_RAD_S * obs_eci[0], 0.0];
+
+    let range: Vec<f64> = (0..3).map(|i| state.position[i] - obs_eci[i]).collect();
+    let range_km = (range[0].powi(2) + range[1].powi(2) + range[2].powi(2)).sqrt();
+    let range_rate_km_s = (0..3)
+        .map(|i| range[i] * (state.velocity[i] - obs_vel[i]))
+        .sum::<f64>() / range_km;
+
+    // Rotate the range vector to Earth-fixed, then to south/east/zenith
+    let rx = cos_t * range[0] + sin_t * range[1];
+    let ry = -sin_t * range[0] + cos_t * range[1];
+    let rz = range[2];
+    let lat = observer.latitude_deg.to_radians();
+    let lon = observer.longitude_deg.to_radians();
+    let south = lat.sin() * lon.cos() * rx + lat.sin() * lon.sin() * ry - lat.cos() * rz;
+    let east = -lon.sin() * rx + lon.cos() * ry;
+    let zenith = lat.cos() * lon.cos() * rx + lat.cos() * lon.sin() * ry + lat.sin() * rz;
+
+    LookAngles {
+        azimuth_deg: east.atan2(-south).to_degrees().rem_euclid(360.0),
+        elevation_deg: (zenith / range_km).clamp(-1.0, 1.0).asin().to_degrees(),
+        range_km,
+        range_rate_km_s,
+    }
+}
+
+/// Frequency shift seen by the observer for a transmitter at `freq_hz`
+pub fn doppler_shift_hz(freq_hz: f64, range_rate_km_s: f64) -> f64 {
+    -freq_hz * range_rate_km_s / SPEED_OF_LIGHT_KM_S
+}
+
+/// Whether the satellite is lit by the sun
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Illumination {
+    Sunlight,
+    Eclipse,
+}
+
+impl fmt::Display for Illumination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Illumination::Sunlight => write!(f, "Sunlight"),
+            Illumination::Eclipse => write!(f, "Eclipse"),
+        }
+    }
+}
+
+/// Geocentric position of the sun, Astronomical Almanac low-precision formulae (~0.01°) [km]
+pub fn sun_position(unix: f64) -> [f64; 3] {
+    let n = timeutil::julian_date(unix) - 2451545.0;
+    let mean_longitude = (280.460 + 0.9856474 * n).to_radians();
+    let mean_anomaly = (357.528 + 0.9856003 * n).to_radians();
+    let longitude = mean_longitude
+        + (1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+    let distance_km =
+        (1.00014 - 0.01671 * mean_anomaly.cos() - 0.00014 * (2.0 * mean_anomaly).cos()) * AU_KM;
+    [
+        distance_km * longitude.cos(),
+        distance_km * obliquity.cos() * longitude.sin(),
+        distance_km * obliquity.sin() * longitude.sin(),
+    ]
+}
+
+/// Sunlight or eclipse for a satellite position, using a cylindrical earth shadow
+pub fn illumination(state: &StateVector, unix: f64) -> Illumination {
+    let sun = sun_position(unix);
+    let sun_norm = (sun[0] * sun[0] + sun[1] * sun[1] + sun[2] * sun[2]).sqrt();
+    let along: f64 = (0..3).map(|i| state.position[i] * sun[i] / sun_norm).sum();
+    let across_sq = (0..3)
+        .map(|i| (state.position[i] - along * sun[i] / sun_norm).powi(2))
+        .sum::<f64>();
+    if along < 0.0 && across_sq.sqrt() < WGS84_A_KM {
+        Illumination::Eclipse
+    } else {
+        Illumination::Sunlight
+    }
+}
+
+/// Where BOTAN is in its orbit at one time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrbitState {
+    pub illumination: Illumination,
+    pub number: Option<u64>,   // Orbit number, when the TLE has a revolution number
+}
+
+/// One visibility window of the satellite above the observer's horizon
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+    pub aos_unix: f64,
+    pub los_unix: f64,
+    pub max_elevation_unix: f64,
+    pub max_elevation_deg: f64,
+    pub aos_azimuth_deg: f64,
+    pub los_azimuth_deg: f64,
+}
+
+/// Propagates BOTAN and reports its position as seen by one observer
+pub struct SatTracker {
+    sgp4: Sgp4,
+    tle: Tle,
+    observer: Observer,
+}
+
+impl SatTracker {
+    pub fn new(tle: &Tle, observer: Observer) -> Result<Self, String> {
+        Ok(SatTracker { sgp4: Sgp4::new(tle)?, tle: tle.clone(), observer })
+    }
+
+    pub fn look_angles(&self, unix: f64) -> Result<LookAngles, String> {
+        let state = self.sgp4.propagate_unix(unix)?;
+        Ok(look_angles(&state, &self.observer, unix))
+    }
+
+    pub fn orbit_state(&self, unix: f64) -> Result<OrbitState, String> {
+        Ok(OrbitState {
+            illumination: illumination(&self.sgp4.propagate_unix(unix)?, unix),
+            number: self.tle.orbit_number(unix),
+        })
+    }
+
+    fn elevation(&self, unix: f64) -> Result<f64, String> {
+        Ok(self.look_angles(unix)?.elevation_deg)
+    }
+
+    /// Passes rising above the horizon between `start` and `end` whose peak reaches `min_elevation_deg`
+    pub fn passes(&self, start: f64, end: f64, min_elevation_deg: f64) -> Result<Vec<Pass>, String> {
+        // 30 s steps cannot skip a LEO pass; crossings are then refined by bisection
+        const STEP: f64 = 30.0;
+        let mut passes = Vec::new();
+        let mut t = start;
+        let mut above = self.elevation(t)? > 0.0;
+        let mut aos = if above { Some(start) } else { None };
+
+        while t < end {
+            let next = (t + STEP).min(end);
+            let next_above = self.elevation(next)? > 0.0;
+            if next_above && !above {
+                aos = Some(self.refine_crossing(t, next, true)?);
+            } else if !next_above && above {
+                if let Some(aos_unix) = aos.take() {
+                    let los_unix = self.refine_crossing(t, next, false)?;
+                    if let Some(pass) = self.build_pass(aos_unix, los_unix, min_elevation_deg)? {
+                        passes.push(pass);
+                    }
+                }
+            }
+            above = next_above;
+            t = next;
+        }
+        Ok(passes)
+    }
+
+    /// Bisect a horizon crossing to one-second resolution
+    fn refine_crossing(&self, mut low: f64, mut high: f64, rising: bool) -> Result<f64, String> {
+        while high - low > 1.0 {
+            let mid = 0.5 * (low + high);
+            if (self.elevation(mid)? > 0.0) == rising {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        Ok(0.5 * (low + high))
+    }
+
+    fn build_pass(&self, aos_unix: f64, los_unix: f64, min_elevation_deg: f64) -> Result<Option<Pass>, String> {
+        // Elevation is unimodal over a LEO pass, so a ternary search finds the peak
+        let (mut low, mut high) = (aos_unix, los_unix);
+        while high - low > 1.0 {
+            let m1 = low + (high - low) / 3.0;
+            let m2 = high - (high - low) / 3.0;
+            if self.elevation(m1)? < self.elevation(m2)? {
+                low = m1;
+            } else {
+                high = m2;
+            }
+        }
+        let max_elevation_unix = 0.5 * (low + high);
+        let max_elevation_deg = self.elevation(max_elevation_unix)?;
+        if max_elevation_deg < min_elevation_deg {
+            return Ok(None);
+        }
+        Ok(Some(Pass {
+            aos_unix,
+            los_unix,
+            max_elevation_unix,
+            max_elevation_deg,
+            aos_azimuth_deg: self.look_angles(aos_unix)?.azimuth_deg,
+            los_azimuth_deg: self.look_angles(los_unix)?.azimuth_deg,
+        }))
+    }
+}
+
+/// Tracks BOTAN for one observer and reports the expected Doppler shift
+pub struct DopplerTracker {
+    tracker: SatTracker,
+    freq_hz: f64,
+}
+
+impl DopplerTracker {
+    pub fn new(tle: &Tle, observer: Observer, freq_hz: f64) -> Result<Self, String> {
+        Ok(DopplerTracker { tracker: SatTracker::new(tle, observer)?, freq_hz })
+    }
+
+    pub fn look_angles(&self, unix: f64) -> Result<LookAngles, String> {
+        self.tracker.look_angles(unix)
+    }
+
+    pub fn orbit_state(&self, unix: f64) -> Result<OrbitState, String> {
+        self.tracker.orbit_state(unix)
+    }
+
+    /// Doppler shift at the given time [Hz]
+    pub fn shift_hz(&self, unix: f64) -> Result<f64, String> {
+        Ok(doppler_shift_hz(self.freq_hz, self.look_angles(unix)?.range_rate_km_s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gmst_at_j2000() {
+        // 2000-01-01T12:00:00 UT1: GMST = 280.46061837 deg
+        let gmst_deg = gmst(946728000.0).to_degrees();
+        assert!((gmst_deg - 280.46061837).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_overhead_satellite() {
+        // A satellite 500 km directly above an equatorial observer on the Greenwich meridian,
+        // moving straight away
+        let observer = Observer { latitude_deg: 0.0, longitude_deg: 0.0, altitude_m: 0.0 };
+        let unix = 946728000.0;
+        let theta = gmst(unix);
+        let r = WGS84_A_KM + 500.0;
+        let state = StateVector {
+            position: [r * theta.cos(), r * theta.sin(), 0.0],
+            velocity: [theta.cos(), theta.sin(), 0.0],
+        };
+        let look = look_angles(&state, &observer, unix);
+        assert!((look.elevation_deg - 90.0).abs() < 0.01);
+        assert!((look.range_km - 500.0).abs() < 0.01);
+        assert!((look.range_rate_km_s - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pass_prediction() {
+        let tle = Tle::parse(
+            None,
+            "1 88888U          80275.98708465  .00073094  13844-3  66816-4 0    8",
+            "2 88888  72.8435 115.9689 0086731  52.6988 110.5714 16.05824518  105",
+        ).unwrap();
+        let observer = Observer { latitude_deg: 35.0, longitude_deg: 139.0, altitude_m: 0.0 };
+        let tracker = SatTracker::new(&tle, observer).unwrap();
+
+        let passes = tracker.passes(tle.epoch_unix, tle.epoch_unix + 86400.0, 0.0).unwrap();
+        // A 72.8° inclination LEO satellite is seen a handful of times a day at 35° N
+        assert!(passes.len() >= 2 && passes.len() <= 8, "{} passes", passes.len());
+        for pass in &passes {
+            let duration = pass.los_unix - pass.aos_unix;
+            assert!(duration > 0.0 && duration < 20.0 * 60.0);
+            assert!(pass.max_elevation_unix > pass.aos_unix && pass.max_elevation_unix < pass.los_unix);
+            assert!(tracker.look_angles(pass.aos_unix).unwrap().elevation_deg.abs() < 0.1);
+            assert!(tracker.look_angles(pass.los_unix).unwrap().elevation_deg.abs() < 0.1);
+        }
+
+        let high = tracker.passes(tle.epoch_unix, tle.epoch_unix + 86400.0, 30.0).unwrap();
+        assert!(high.iter().all(|p| p.max_elevation_deg >= 30.0));
+        assert!(high.len() < passes.len());
+    }
+
+    #[test]
+    fn test_doppler_sign() {
+        // Approaching satellite is heard high
+        assert!((doppler_shift_hz(437e6, -7.0) - 10203.7).abs() < 0.1);
+        assert!(doppler_shift_hz(437e6, 7.0) < 0.0);
+    }
+
+    #[test]
+    fn test_sun_at_equinox() {
+        // March equinox 2024-03-20T03:06Z: the sun crosses the equator at ecliptic longitude 0
+        let sun = sun_position(1710903960.0);
+        let r = (sun[0] * sun[0] + sun[1] * sun[1] + sun[2] * sun[2]).sqrt();
+        assert!((r / AU_KM - 0.996).abs() < 0.001);
+        assert!(sun[0] > 0.0);
+        assert!((sun[1] / r).abs() < 1e-3 && (sun[2] / r).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_illumination() {
+        let unix = 1710903960.0;
+        let sun = sun_position(unix);
+        let r = (sun[0] * sun[0] + sun[1] * sun[1] + sun[2] * sun[2]).sqrt();
+        let at = |scale: f64, offset_z: f64| StateVector {
+            position: [sun[0] / r * scale, sun[1] / r * scale, sun[2] / r * scale + offset_z],
+            velocity: [0.0; 3],
+        };
+        assert_eq!(illumination(&at(7000.0, 0.0), unix), Illumination::Sunlight);
+        assert_eq!(illumination(&at(-7000.0, 0.0), unix), Illumination::Eclipse);
+        // Behind the earth but outside the shadow cylinder
+        assert_eq!(illumination(&at(-3000.0, 6600.0), unix), Illumination::Sunlight);
+    }
+}
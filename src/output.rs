@@ -0,0 +1,511 @@
+use std::cell::RefCell;
+use std::io::Write as _;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::alert::Alert;
+use crate::audit::AuditLog;
+use crate::botan_parser::{self, BotanBeaconData, DisplayOptions, SignalCalibration};
+use crate::codec::{self, BeaconCodec};
+use crate::confidence::Assessor;
+use crate::dashboard::Dashboard;
+use crate::explain;
+use crate::forward::Forwarder;
+use crate::frames::{self, Frame, FrameTypes};
+use crate::grpc;
+use crate::grsat;
+use crate::logging::warn;
+use crate::notify::Desktop;
+use crate::orbit::OrbitState;
+use crate::proto;
+use crate::provenance::Provenance;
+use crate::rotate::RotatingFile;
+use crate::template::Template;
+use crate::timeutil;
+use crate::xml;
+#[cfg(feature = "zmq")]
+use crate::zmq;
+
+/// How frames are written to stdout and --output-file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,     // The report, a template or --grsat-json lines
+    Xml,      // One element per frame
+    Proto,    // Length-delimited protobuf messages
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "xml" => Ok(OutputFormat::Xml),
+            "proto" | "protobuf" => Ok(OutputFormat::Proto),
+            _ => Err(format!("Unknown output format '{}'. Expected text, xml or proto", s)),
+        }
+    }
+}
+
+/// Parses beacon lines and formats them with the built-in layout or a user template
+pub struct BeaconPrinter {
+    calibration: SignalCalibration,
+    options: DisplayOptions,
+    template: Option<Template>,
+    frame_types: FrameTypes,
+    assessor: RefCell<Assessor>,
+    grsat_json: bool,
+    format: OutputFormat,
+    autocorrect: bool,
+    strict: bool,
+    source: RefCell<String>,
+    calibration_version: String,
+    forwarder: Option<Forwarder>,
+    desktop: Option<Desktop>,
+    alert: Option<Alert>,
+    output_file: Option<RefCell<RotatingFile>>,
+    audit: Option<RefCell<AuditLog>>,
+    grpc: Option<grpc::Server>,
+    dashboard: Option<Dashboard>,
+    #[cfg(feature = "zmq")]
+    zmq: Option<zmq::Publisher>,
+}
+
+impl BeaconPrinter {
+    pub fn new(calibration: SignalCalibration, options: DisplayOptions, template: Option<Template>) -> Self {
+        BeaconPrinter {
+            calibration,
+            options,
+            template,
+            frame_types: FrameTypes::default(),
+            assessor: RefCell::default(),
+            grsat_json: false,
+            format: OutputFormat::Text,
+            autocorrect: false,
+            strict: false,
+            source: RefCell::new("interactive".to_string()),
+            calibration_version: "builtin".to_string(),
+            forwarder: None,
+            desktop: None,
+            alert: None,
+            output_file: None,
+            audit: None,
+            grpc: None,
+            dashboard: None,
+            #[cfg(feature = "zmq")]
+            zmq: None,
+        }
+    }
+
+    /// Also decode the data blocks of these frame types
+    pub fn with_frame_types(mut self, frame_types: FrameTypes) -> Self {
+        self.frame_types = frame_types;
+        self
+    }
+
+    /// Print each frame as a line of gr-satellites style telemetry JSON instead of a report
+    pub fn with_grsat_json(mut self, enabled: bool) -> Self {
+        self.grsat_json = enabled;
+        self
+    }
+
+    /// Write frames in `format` rather than as text
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Read the letters O, I and L in hex fields as the 0 and 1 they most likely were, noting
+    /// each replacement before the frame
+    pub fn with_autocorrect(mut self, enabled: bool) -> Self {
+        self.autocorrect = enabled;
+        self
+    }
+
+    /// Reject frames with anything to warn about, so that only pristine frames are printed and
+    /// forwarded
+    pub fn with_strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Record decodes as coming from `source` (the command), with calibration files named by
+    /// provenance::calibration_version
+    pub fn with_provenance(mut self, source: &str, calibration_version: String) -> Self {
+        self.source = RefCell::new(source.to_string());
+        self.calibration_version = calibration_version;
+        self
+    }
+
+    /// Where the lines decoded from now on come from, e.g. "decode-file beacons.txt:12"
+    pub fn set_source(&self, source: String) {
+        *self.source.borrow_mut() = source;
+    }
+
+    /// Provenance for a decode of the current source with these corrections
+    pub fn provenance(&self, corrections: Vec<String>) -> Provenance {
+        Provenance::new(&self.source.borrow(), &self.calibration_version, corrections)
+    }
+
+    /// Also send every frame printed to the forwarder's network services
+    pub fn with_forwarder(mut self, forwarder: Option<Forwarder>) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    /// Also pop up a desktop notification for every frame printed
+    pub fn with_desktop(mut self, desktop: Option<Desktop>) -> Self {
+        self.desktop = desktop;
+        self
+    }
+
+    /// Also play a sound for every frame printed
+    pub fn with_alert(mut self, alert: Option<Alert>) -> Self {
+        self.alert = alert;
+        self
+    }
+
+    /// Also append every frame printed to a file, e.g. NDJSON with --grsat-json
+    pub fn with_output_file(mut self, file: Option<RotatingFile>) -> Self {
+        self.output_file = file.map(RefCell::new);
+        self
+    }
+
+    /// Also log every line decoded, successfully or not, to an audit log
+    pub fn with_audit_log(mut self, audit: Option<AuditLog>) -> Self {
+        self.audit = audit.map(RefCell::new);
+        self
+    }
+
+    /// Also stream every frame printed to the gRPC service's StreamDecodes calls
+    pub fn with_grpc(mut self, grpc: Option<grpc::Server>) -> Self {
+        self.grpc = grpc;
+        self
+    }
+
+    /// Also show every frame printed on the web dashboard
+    pub fn with_dashboard(mut self, dashboard: Option<Dashboard>) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    /// Also publish every frame printed on a ZeroMQ PUB socket
+    #[cfg(feature = "zmq")]
+    pub fn with_zmq(mut self, zmq: Option<zmq::Publisher>) -> Self {
+        self.zmq = zmq;
+        self
+    }
+
+    /// Whether the report is colored
+    pub fn color(&self) -> bool {
+        self.options.color
+    }
+
+    /// A codec decoding beacon lines from a byte stream with the calibrations of this printer
+    pub fn beacon_codec(&self) -> BeaconCodec {
+        BeaconCodec::new(codec::MAX_LINE, self.calibration.clone(), self.options.calibration)
+    }
+
+    /// Step-by-step explanation of a housekeeping beacon for newcomers
+    pub fn explain(&self, data: &BotanBeaconData) -> String {
+        explain::explain(data, &self.options)
+    }
+
+    /// Give frames on their way to network services up to `timeout` to be delivered; fails
+    /// when some are left undelivered or were refused
+    pub fn flush(&self, timeout: std::time::Duration) -> Result<(), String> {
+        match &self.forwarder {
+            Some(forwarder) => forwarder.flush(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// With --autocorrect, `line` with O, I and L in its hex fields read as 0 and 1, and a note
+    /// listing the corrections; None when there was nothing to correct
+    pub fn correct(&self, line: &str) -> Option<(String, String)> {
+        self.corrections(line).map(|(fixed, notes)| (fixed, format!("Corrected: {}", notes.join(", "))))
+    }
+
+    /// With --autocorrect, `line` corrected and each correction made
+    fn corrections(&self, line: &str) -> Option<(String, Vec<String>)> {
+        if !self.autocorrect {
+            return None;
+        }
+        let (fixed, corrections) = botan_parser::correct_hex_confusions(line);
+        let notes: Vec<String> = corrections.iter().map(|c| c.to_string()).collect();
+        (!notes.is_empty()).then_some((fixed, notes))
+    }
+
+    /// With --strict, an error naming what is wrong with a frame parsed from `line`: values outside
+    /// the limits, a failed integrity check, tokens after the data block, or corrections made
+    pub fn vet(&self, line: &str, corrected: bool, frame: Frame) -> Result<Frame, String> {
+        if !self.strict {
+            return Ok(frame);
+        }
+        let mut warnings = Vec::new();
+        match &frame {
+            Frame::Housekeeping(data) => {
+                let alarms = self.options.limits.alarms(&data.telemetry);
+                if !alarms.is_empty() {
+                    let names: Vec<String> = alarms.iter().map(|name| name.to_uppercase()).collect();
+                    warnings.push(format!("{} outside limits", names.join(", ")));
+                }
+            }
+            Frame::Typed(typed) => {
+                if let Some(check) = typed.check.as_ref().filter(|check| !check.passed) {
+                    warnings.push(format!("{} check failed", check.codec));
+                }
+            }
+        }
+        let line = botan_parser::normalize_line(line);
+        let trailing = botan_parser::trailing_tokens(&line);
+        if !trailing.is_empty() {
+            warnings.push(format!("unexpected '{}' after the data", trailing.join(" ")));
+        }
+        if corrected {
+            warnings.push("characters autocorrected".to_string());
+        }
+        if warnings.is_empty() {
+            Ok(frame)
+        } else {
+            Err(format!("Rejected by --strict: {}", warnings.join("; ")))
+        }
+    }
+
+    /// Parse a beacon line of any configured frame type and score it against the frames before.
+    /// `copy` is the share of its characters the CW demodulator read unambiguously, if it did.
+    pub fn parse_frame(&self, line: &str, copy: Option<f64>) -> Result<Frame, String> {
+        let mut frame = frames::parse_frame(line, &self.calibration, &self.options.calibration, &self.frame_types)?;
+        self.assessor.borrow_mut().assess(&mut frame, copy);
+        Ok(frame)
+    }
+
+    /// Format a decoded frame of any type. For a typed frame, templates see its serialized form
+    /// plus `values.<field name>`.
+    pub fn render_frame(&self, frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>, provenance: Option<&Provenance>) -> Result<String, String> {
+        if self.grsat_json {
+            return grsat::frame_json(frame, time, orbit, provenance);
+        }
+        if self.format == OutputFormat::Xml {
+            return xml::frame_xml(frame, time, orbit);
+        }
+        match (frame, &self.template) {
+            (Frame::Housekeeping(data), _) => self.render(data, time, orbit),
+            (Frame::Typed(typed), None) => Ok(format!("\n{}{}", typed, self.orbit_lines(orbit))),
+            (Frame::Typed(typed), Some(template)) => {
+                let mut context = serde_json::to_value(typed).map_err(|e| e.to_string())?;
+                let values: serde_json::Map<String, Value> =
+                    typed.fields.iter().map(|v| (v.name.clone(), v.value.into())).collect();
+                context["values"] = values.into();
+                render_template(template, context, time, orbit)
+            }
+        }
+    }
+
+    /// Format one decoded beacon. Templates see every field of the beacon, in the display units,
+    /// plus `time` (UTC, when known), `illumination` ("sunlight"/"eclipse", with a TLE) and
+    /// `orbit` (the orbit number, with a TLE that has a revolution number).
+    pub fn render(&self, data: &BotanBeaconData, time: Option<f64>, orbit: Option<OrbitState>) -> Result<String, String> {
+        let Some(template) = &self.template else {
+            return Ok(format!("\n{}{}", data.display(&self.options), self.orbit_lines(orbit)));
+        };
+
+        let mut context = serde_json::to_value(data).map_err(|e| e.to_string())?;
+        let units = self.options.units;
+        let telemetry = &mut context["telemetry"];
+        let t = &data.telemetry;
+        telemetry["bat_v"] = units.voltage(t.battery_voltage()).value.into();
+        telemetry["bat_i"] = units.current(t.battery_current()).value.into();
+        telemetry["bat_t"] = units.temperature(t.battery_temperature()).value.into();
+        telemetry["bpb_t"] = units.temperature(t.board_temperature()).value.into();
+        telemetry["raw_i"] = units.current(t.consumption_current()).value.into();
+        render_template(template, context, time, orbit)
+    }
+
+    /// The report's last lines when the satellite's position is known
+    fn orbit_lines(&self, orbit: Option<OrbitState>) -> String {
+        let labels = self.options.lang.labels();
+        let Some(orbit) = orbit else {
+            return String::new();
+        };
+        let mut lines = format!("\n{}: {}", labels.illumination, labels.illumination_state(orbit.illumination));
+        if let Some(number) = orbit.number {
+            lines.push_str(&format!("\n{}: {}", labels.orbit, number));
+        }
+        lines
+    }
+
+    /// Parse and print a beacon line, reporting parse errors in place
+    pub fn print_line(
+        &self,
+        line: &str,
+        copy: Option<f64>,
+        time: Option<f64>,
+        orbit: Option<OrbitState>,
+    ) -> Result<Frame, String> {
+        self.print_traced(line, copy, time, orbit).1
+    }
+
+    /// print_line, also returning the provenance the decode was recorded with
+    pub fn print_traced(
+        &self,
+        line: &str,
+        copy: Option<f64>,
+        time: Option<f64>,
+        orbit: Option<OrbitState>,
+    ) -> (Provenance, Result<Frame, String>) {
+        let corrected = self.corrections(line);
+        let parsed = match &corrected {
+            Some((fixed, notes)) => {
+                let note = format!("Corrected: {}", notes.join(", "));
+                if self.format == OutputFormat::Text && !self.grsat_json {
+                    println!("{}", note);
+                } else {
+                    eprintln!("{}", note);
+                }
+                self.parse_frame(fixed, copy)
+            }
+            None => self.parse_frame(line, copy),
+        };
+        let parsed = parsed.and_then(|frame| self.vet(line, corrected.is_some(), frame));
+        let provenance = self.provenance(corrected.map(|(_, notes)| notes).unwrap_or_default());
+        if let Some(audit) = &self.audit {
+            if let Err(error) = audit.borrow_mut().record(line, time, copy, &parsed, &provenance) {
+                warn!("Frame not audited"; error = error);
+            }
+        }
+        let result = parsed.and_then(|frame| self.write_frame(&frame, time, orbit, &provenance).map(|()| frame));
+        let result = match result {
+            Ok(frame) => {
+                if let Some(forwarder) = &self.forwarder {
+                    forwarder.submit(&frame, time, &provenance);
+                }
+                if let Some(desktop) = &self.desktop {
+                    desktop.notify(&frame, time);
+                }
+                if let Some(alert) = &self.alert {
+                    alert.play(&frame);
+                }
+                if let Some(grpc) = &self.grpc {
+                    grpc.publish(&frame, time, orbit);
+                }
+                if let Some(dashboard) = &self.dashboard {
+                    dashboard.publish(&frame, time, orbit, &provenance);
+                }
+                #[cfg(feature = "zmq")]
+                if let Some(zmq) = &self.zmq {
+                    zmq.publish(&frame, time, orbit, &provenance);
+                }
+                Ok(frame)
+            }
+            // Kept off stdout when it carries messages that must stay well-formed
+            Err(error) if self.format != OutputFormat::Text => {
+                eprintln!("BOTAN Parsing Error: {}", error);
+                Err(error)
+            }
+            Err(error) => {
+                println!("BOTAN Parsing Error: {}", error);
+                Err(error)
+            }
+        };
+        (provenance, result)
+    }
+
+    /// Write a decoded frame to stdout and the output file
+    fn write_frame(&self, frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>, provenance: &Provenance) -> Result<(), String> {
+        let now = time.unwrap_or_else(timeutil::now_unix);
+        let written = match self.format {
+            OutputFormat::Text | OutputFormat::Xml => {
+                let text = self.render_frame(frame, time, orbit, Some(provenance))?;
+                println!("{}", text);
+                self.output_file.as_ref().map(|file| file.borrow_mut().write_line(&text, now))
+            }
+            OutputFormat::Proto => {
+                let message = proto::encode_delimited(frame, time, orbit);
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&message).and_then(|()| stdout.flush()).map_err(|e| format!("Cannot write to stdout: {}", e))?;
+                self.output_file.as_ref().map(|file| file.borrow_mut().write_record(&message, now))
+            }
+        };
+        if let Some(Err(error)) = written {
+            warn!("Cannot write output file"; error = error);
+        }
+        Ok(())
+    }
+}
+
+/// Render `template` with `context` plus `time` (UTC, when known), `illumination` and `orbit`
+fn render_template(template: &Template, mut context: Value, time: Option<f64>, orbit: Option<OrbitState>) -> Result<String, String> {
+    if let Value::Object(map) = &mut context {
+        map.insert("time".to_string(), time.map(timeutil::format_display).into());
+        map.insert(
+            "illumination".to_string(),
+            serde_json::to_value(orbit.map(|o| o.illumination)).map_err(|e| e.to_string())?,
+        );
+        map.insert("orbit".to_string(), orbit.and_then(|o| o.number).into());
+    }
+    let out = template.render(&context)?;
+    Ok(out.strip_suffix('\n').unwrap_or(&out).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::orbit::Illumination;
+    use crate::units::{TemperatureUnit, Units, VoltageUnit};
+
+    #[test]
+    fn test_template_output() {
+        let template = Template::parse("{{time}} {{call_sign}} {{telemetry.bat_v:.3}} V {{illumination}} {{orbit}}\n").unwrap();
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), Some(template));
+        let data = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let orbit = OrbitState { illumination: Illumination::Eclipse, number: Some(1234) };
+        let text = printer.render(&data, Some(1714566896.0), Some(orbit)).unwrap();
+        assert_eq!(text, "2024-05-01T12:34:56Z JS1YPT 4.280 V eclipse 1234");
+
+        let text = printer.render(&data, None, None).unwrap();
+        assert_eq!(text, " JS1YPT 4.280 V  ");
+
+        let template = Template::parse("{{telemetry.bat_v:.0}} mV {{telemetry.bat_t:.1}}").unwrap();
+        let units = Units { voltage: VoltageUnit::MilliVolt, temperature: TemperatureUnit::Fahrenheit, ..Default::default() };
+        let options = DisplayOptions { units, ..Default::default() };
+        let printer = BeaconPrinter::new(SignalCalibration::default(), options, Some(template));
+        assert_eq!(printer.render(&data, None, None).unwrap(), "4280 mV 69.0");
+    }
+
+    #[test]
+    fn test_strict() {
+        let limits: crate::limits::Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.3}}"#).unwrap();
+        let options = DisplayOptions { limits, ..Default::default() };
+        let printer = BeaconPrinter::new(SignalCalibration::default(), options, None);
+        let line = "BOTAN JS1YPT A67C8D5E2AA13608 73 TU";
+        let frame = printer.parse_frame(line, None).unwrap();
+        assert!(printer.vet(line, true, frame.clone()).is_ok());
+
+        let printer = printer.with_strict(true);
+        let error = printer.vet(line, true, frame.clone()).unwrap_err();
+        assert_eq!(error, "Rejected by --strict: BAT_V outside limits; unexpected '73 TU' after the data; characters autocorrected");
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None).with_strict(true);
+        assert!(printer.vet("BOTAN JS1YPT A67C8D5E2AA13608", false, frame).is_ok());
+    }
+
+    #[test]
+    fn test_default_output() {
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None);
+        let data = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let sunlight = OrbitState { illumination: Illumination::Sunlight, number: None };
+        let text = printer.render(&data, None, Some(sunlight)).unwrap();
+        assert!(text.starts_with("\nBOTAN Satellite Beacon Data"));
+        assert!(text.ends_with("Illumination: Sunlight"));
+
+        let japanese = DisplayOptions { lang: crate::i18n::Lang::Ja, ..Default::default() };
+        let printer = BeaconPrinter::new(SignalCalibration::default(), japanese, None);
+        let eclipse = OrbitState { illumination: Illumination::Eclipse, number: Some(1234) };
+        let text = printer.render(&data, None, Some(eclipse)).unwrap();
+        assert!(text.contains("\n  KILLスイッチ:       オフ\n"));
+        assert!(text.ends_with("日照状態: 日陰\n周回番号: 1234"));
+    }
+}
@@ -0,0 +1,441 @@
+// A minimal Parquet writer for `export --format parquet`: flat tables of optional columns,
+// PLAIN encoded and uncompressed, in row groups of up to ROW_GROUP_ROWS rows with one data page
+// per column. That is all pandas, polars, DuckDB or Spark need to load the history with its
+// types, and it keeps the file format to the parts written below:
+//
+//   "PAR1" | per row group, per column: page header, definition levels, values | footer | "PAR1"
+//
+// Page headers and the footer are Thrift structs in the compact protocol. Definition levels
+// (1 where a value is present) are written as bit-packed runs of the RLE/bit-packing hybrid.
+use std::io::{self, Write};
+
+const MAGIC: &[u8] = b"PAR1";
+const ROW_GROUP_ROWS: usize = 65536;
+
+/// Physical and logical type of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Boolean,
+    Int32,
+    Double,
+    Text,         // UTF-8 byte array
+    Timestamp,    // Milliseconds since the Unix epoch, UTC
+}
+
+impl ColumnType {
+    /// Parquet physical type
+    fn physical(self) -> i32 {
+        match self {
+            ColumnType::Boolean => 0,
+            ColumnType::Int32 => 1,
+            ColumnType::Timestamp => 2,
+            ColumnType::Double => 5,
+            ColumnType::Text => 6,
+        }
+    }
+
+    /// Parquet converted type, where the physical type needs one
+    fn converted(self) -> Option<i32> {
+        match self {
+            ColumnType::Text => Some(0),         // UTF8
+            ColumnType::Timestamp => Some(9),    // TIMESTAMP_MILLIS
+            _ => None,
+        }
+    }
+}
+
+/// A value of one cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Boolean(bool),
+    Int32(i32),
+    Double(f64),
+    Text(String),
+    Timestamp(i64),
+}
+
+impl Cell {
+    fn write_plain(&self, out: &mut Vec<u8>) {
+        match self {
+            Cell::Boolean(_) => unreachable!("booleans are bit-packed per page"),
+            Cell::Int32(value) => out.extend_from_slice(&value.to_le_bytes()),
+            Cell::Double(value) => out.extend_from_slice(&value.to_le_bytes()),
+            Cell::Timestamp(value) => out.extend_from_slice(&value.to_le_bytes()),
+            Cell::Text(text) => {
+                out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                out.extend_from_slice(text.as_bytes());
+            }
+        }
+    }
+}
+
+/// Thrift compact protocol types
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// A Thrift struct being written in the compact protocol
+struct Thrift {
+    out: Vec<u8>,
+    last_field: Vec<i16>,    // Per open struct, for the field id deltas
+}
+
+impl Thrift {
+    fn new() -> Self {
+        Thrift { out: Vec::new(), last_field: vec![0] }
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_field.last_mut().unwrap();
+        let delta = id - std::mem::replace(last, id);
+        if (1..=15).contains(&delta) {
+            self.out.push((delta as u8) << 4 | kind);
+        } else {
+            self.out.push(kind);
+            self.zigzag(id as i64);
+        }
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.zigzag(value as i64);
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, id: i16, value: &str) {
+        self.field(id, BINARY);
+        self.binary(value.as_bytes());
+    }
+
+    fn list_header(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.out.push((len as u8) << 4 | kind);
+        } else {
+            self.out.push(0xF0 | kind);
+            self.varint(len as u64);
+        }
+    }
+
+    fn i32_list(&mut self, id: i16, values: &[i32]) {
+        self.list_header(id, I32, values.len());
+        for &value in values {
+            self.zigzag(value as i64);
+        }
+    }
+
+    fn string_list(&mut self, id: i16, values: &[&str]) {
+        self.list_header(id, BINARY, values.len());
+        for value in values {
+            self.binary(value.as_bytes());
+        }
+    }
+
+    /// Open a struct, as field `id` or as the next list element when `id` is None
+    fn begin(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, STRUCT);
+        }
+        self.last_field.push(0);
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.last_field.pop();
+    }
+}
+
+/// Bit-pack `bits` LSB first, padded to whole bytes
+fn bit_pack(bits: impl Iterator<Item = bool>, out: &mut Vec<u8>) {
+    let mut packed = Vec::new();
+    for (idx, bit) in bits.enumerate() {
+        if idx % 8 == 0 {
+            packed.push(0);
+        }
+        *packed.last_mut().unwrap() |= (bit as u8) << (idx % 8);
+    }
+    out.extend(packed);
+}
+
+/// Where one written column chunk is and how big it is
+struct Chunk {
+    offset: u64,
+    size: u64,
+}
+
+/// The page of one column of one row group: definition levels, then the values present
+fn page(kind: ColumnType, cells: &[Option<Cell>]) -> Vec<u8> {
+    let mut levels = Vec::new();
+    // One bit-packed run of groups of 8 levels: header (groups << 1 | 1)
+    let mut header = Thrift::new();
+    header.varint(((cells.len().div_ceil(8) as u64) << 1) | 1);
+    levels.extend(header.out);
+    bit_pack(cells.iter().map(Option::is_some), &mut levels);
+
+    let mut data = (levels.len() as u32).to_le_bytes().to_vec();
+    data.extend(levels);
+    let present = cells.iter().flatten();
+    if kind == ColumnType::Boolean {
+        bit_pack(present.map(|cell| *cell == Cell::Boolean(true)), &mut data);
+    } else {
+        for cell in present {
+            cell.write_plain(&mut data);
+        }
+    }
+    data
+}
+
+fn page_header(rows: usize, size: usize) -> Vec<u8> {
+    let mut header = Thrift::new();
+    header.i32(1, 0);               // DATA_PAGE
+    header.i32(2, size as i32);     // Uncompressed size
+    header.i32(3, size as i32);     // Compressed size
+    header.begin(Some(5));          // DataPageHeader
+    header.i32(1, rows as i32);
+    header.i32(2, 0);               // PLAIN values
+    header.i32(3, 3);               // RLE definition levels
+    header.i32(4, 3);               // RLE repetition levels
+    header.end();
+    header.out.push(0);
+    header.out
+}
+
+/// Write a table with `columns` (names and types) and `rows` of optional cells
+pub fn write(columns: &[(&str, ColumnType)], rows: &[Vec<Option<Cell>>], out: &mut impl Write) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as u64;
+    let mut groups = Vec::new();
+    for group in rows.chunks(ROW_GROUP_ROWS) {
+        let mut chunks = Vec::new();
+        for (idx, &(_, kind)) in columns.iter().enumerate() {
+            let cells: Vec<Option<Cell>> = group.iter().map(|row| row[idx].clone()).collect();
+            let data = page(kind, &cells);
+            let header = page_header(cells.len(), data.len());
+            out.write_all(&header)?;
+            out.write_all(&data)?;
+            let size = (header.len() + data.len()) as u64;
+            chunks.push(Chunk { offset, size });
+            offset += size;
+        }
+        groups.push((group.len(), chunks));
+    }
+
+    let mut footer = Thrift::new();
+    footer.i32(1, 1);    // Version
+    footer.list_header(2, STRUCT, columns.len() + 1);
+    footer.begin(None);
+    footer.string(4, "schema");
+    footer.i32(5, columns.len() as i32);
+    footer.end();
+    for &(name, kind) in columns {
+        footer.begin(None);
+        footer.i32(1, kind.physical());
+        footer.i32(3, 1);    // OPTIONAL
+        footer.string(4, name);
+        if let Some(converted) = kind.converted() {
+            footer.i32(6, converted);
+        }
+        footer.end();
+    }
+    footer.i64(3, rows.len() as i64);
+    footer.list_header(4, STRUCT, groups.len());
+    for (group_rows, chunks) in &groups {
+        footer.begin(None);
+        footer.list_header(1, STRUCT, chunks.len());
+        for (chunk, &(name, kind)) in chunks.iter().zip(columns) {
+            footer.begin(None);
+            footer.i64(2, chunk.offset as i64);
+            footer.begin(Some(3));    // ColumnMetaData
+            footer.i32(1, kind.physical());
+            footer.i32_list(2, &[0, 3]);    // PLAIN, RLE
+            footer.string_list(3, &[name]);
+            footer.i32(4, 0);    // UNCOMPRESSED
+            footer.i64(5, *group_rows as i64);
+            footer.i64(6, chunk.size as i64);
+            footer.i64(7, chunk.size as i64);
+            footer.i64(9, chunk.offset as i64);
+            footer.end();
+            footer.end();
+        }
+        footer.i64(2, chunks.iter().map(|chunk| chunk.size as i64).sum());
+        footer.i64(3, *group_rows as i64);
+        footer.end();
+    }
+    footer.string(6, concat!("botan_cw_decoder version ", env!("CARGO_PKG_VERSION")));
+    footer.out.push(0);
+
+    out.write_all(&footer.out)?;
+    out.write_all(&(footer.out.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A decoded Thrift compact value, enough to read back what `write` produces
+    #[derive(Debug, Clone, PartialEq)]
+    enum Value {
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Value>),
+        Struct(Vec<(i16, Value)>),
+    }
+
+    impl Value {
+        fn get(&self, id: i16) -> &Value {
+            let Value::Struct(fields) = self else { panic!("not a struct") };
+            &fields.iter().find(|(field, _)| *field == id).unwrap_or_else(|| panic!("no field {}", id)).1
+        }
+
+        fn int(&self) -> i64 {
+            let Value::Int(value) = self else { panic!("not an integer") };
+            *value
+        }
+
+        fn list(&self) -> &[Value] {
+            let Value::List(items) = self else { panic!("not a list") };
+            items
+        }
+    }
+
+    struct Reader<'a>(&'a [u8]);
+
+    impl Reader<'_> {
+        fn byte(&mut self) -> u8 {
+            let byte = self.0[0];
+            self.0 = &self.0[1..];
+            byte
+        }
+
+        fn varint(&mut self) -> u64 {
+            let (mut value, mut shift) = (0, 0);
+            loop {
+                let byte = self.byte();
+                value |= ((byte & 0x7F) as u64) << shift;
+                shift += 7;
+                if byte < 0x80 {
+                    return value;
+                }
+            }
+        }
+
+        fn zigzag(&mut self) -> i64 {
+            let value = self.varint();
+            (value >> 1) as i64 ^ -((value & 1) as i64)
+        }
+
+        fn value(&mut self, kind: u8) -> Value {
+            match kind {
+                I32 | I64 => Value::Int(self.zigzag()),
+                BINARY => {
+                    let len = self.varint() as usize;
+                    let bytes = self.0[..len].to_vec();
+                    self.0 = &self.0[len..];
+                    Value::Binary(bytes)
+                }
+                LIST => {
+                    let header = self.byte();
+                    let len = if header >> 4 == 15 { self.varint() as usize } else { (header >> 4) as usize };
+                    Value::List((0..len).map(|_| self.value(header & 0x0F)).collect())
+                }
+                STRUCT => {
+                    let (mut fields, mut last) = (Vec::new(), 0i16);
+                    loop {
+                        let header = self.byte();
+                        if header == 0 {
+                            return Value::Struct(fields);
+                        }
+                        let id = if header >> 4 == 0 { self.zigzag() as i16 } else { last + (header >> 4) as i16 };
+                        last = id;
+                        fields.push((id, self.value(header & 0x0F)));
+                    }
+                }
+                _ => panic!("unexpected type {}", kind),
+            }
+        }
+    }
+
+    #[test]
+    fn test_thrift_field_ids() {
+        let mut thrift = Thrift::new();
+        thrift.i32(1, -1);
+        thrift.i32(20, 300);
+        thrift.i64(21, 1);
+        thrift.out.push(0);
+        assert_eq!(thrift.out, [0x15, 0x01, 0x05, 0x28, 0xD8, 0x04, 0x16, 0x02, 0x00]);
+        assert_eq!(Reader(&thrift.out).value(STRUCT), Value::Struct(vec![(1, Value::Int(-1)), (20, Value::Int(300)), (21, Value::Int(1))]));
+    }
+
+    #[test]
+    fn test_write() {
+        let columns = [("time", ColumnType::Timestamp), ("bat_v", ColumnType::Double), ("kill_sw", ColumnType::Boolean), ("frame", ColumnType::Text)];
+        let rows: Vec<Vec<Option<Cell>>> = (0..ROW_GROUP_ROWS + 3)
+            .map(|i| {
+                vec![
+                    Some(Cell::Timestamp(1714566896000 + i as i64)),
+                    (i % 2 == 0).then_some(Cell::Double(4.0 + i as f64)),
+                    Some(Cell::Boolean(i % 3 == 0)),
+                    (i == 1).then(|| Cell::Text("A67C".to_string())),
+                ]
+            })
+            .collect();
+        let mut file = Vec::new();
+        write(&columns, &rows, &mut file).unwrap();
+        assert!(file.starts_with(MAGIC) && file.ends_with(MAGIC));
+
+        let footer_len = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let footer = Reader(&file[file.len() - 8 - footer_len..file.len() - 8]).value(STRUCT);
+        assert_eq!(footer.get(3).int(), rows.len() as i64);
+        let names: Vec<&Value> = footer.get(2).list().iter().map(|element| element.get(4)).collect();
+        assert_eq!(names, [b"schema".as_ref(), b"time", b"bat_v", b"kill_sw", b"frame"].map(|n| Value::Binary(n.to_vec())).iter().collect::<Vec<_>>());
+        let groups = footer.get(4).list();
+        assert_eq!(groups.iter().map(|group| group.get(3).int()).collect::<Vec<_>>(), [ROW_GROUP_ROWS as i64, 3]);
+
+        // The last row group's pages: 3 rows, values only where present
+        let chunks = groups[1].get(1).list();
+        let page_of = |column: usize| {
+            let meta = chunks[column].get(3);
+            let chunk = &file[meta.get(9).int() as usize..];
+            let mut reader = Reader(chunk);
+            let header = reader.value(STRUCT);
+            assert_eq!(header.get(5).get(1).int(), 3);
+            let size = header.get(2).int() as usize;
+            assert_eq!(meta.get(6).int() as usize, chunk.len() - reader.0.len() + size);
+            reader.0[..size].to_vec()
+        };
+        // Rows 65536-65538: bat_v present for the even ones, kill_sw true for 65538 (divisible by 3)
+        let bat_v = page_of(1);
+        assert_eq!(&bat_v[..6], [2, 0, 0, 0, 0x03, 0b101]);
+        assert_eq!(f64::from_le_bytes(bat_v[6..14].try_into().unwrap()), 4.0 + ROW_GROUP_ROWS as f64);
+        assert_eq!(bat_v.len(), 6 + 16);
+        let kill_sw = page_of(2);
+        assert_eq!(kill_sw[6..], [0b100]);
+        let time = page_of(0);
+        assert_eq!(i64::from_le_bytes(time[6..14].try_into().unwrap()), 1714566896000 + ROW_GROUP_ROWS as i64);
+        assert_eq!(page_of(3)[4..], [0x03, 0]);
+    }
+}
@@ -0,0 +1,140 @@
+// Copying practice for operators learning to read the beacon by ear: random characters, call
+// signs or whole BOTAN beacons are sent as morse, played or printed, and the operator's copy is
+// scored against what was sent. Mistakes are counted per character as the edit distance, so a
+// dropped or extra character costs one mistake rather than shifting the rest out of place.
+use std::str::FromStr;
+
+use crate::calibration::CalibrationTable;
+use crate::rng::Rng;
+use crate::simulate::Simulator;
+
+const CHARACTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const PREFIXES: [&str; 14] = ["JA", "JH", "JR", "JE", "JF", "JG", "JS", "7K", "W", "K", "N", "G", "DL", "VK"];
+
+/// What is sent in each round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drill {
+    Characters,
+    Callsigns,
+    Beacons,
+}
+
+impl FromStr for Drill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "characters" | "chars" => Ok(Drill::Characters),
+            "callsigns" | "calls" => Ok(Drill::Callsigns),
+            "beacons" | "frames" => Ok(Drill::Beacons),
+            _ => Err(format!("Unknown drill '{}'. Expected characters, callsigns or beacons", s)),
+        }
+    }
+}
+
+/// Source of the text sent in each round
+pub struct Practice {
+    drill: Drill,
+    length: usize,    // Characters per group in the character drill
+    rng: Rng,
+    satellite: Simulator,
+}
+
+impl Practice {
+    pub fn new(drill: Drill, length: usize, seed: u64) -> Self {
+        Practice { drill, length, rng: Rng::new(seed), satellite: Simulator::new(seed, 0.0, CalibrationTable::default()) }
+    }
+
+    /// The text of the next round
+    pub fn next_text(&mut self) -> String {
+        match self.drill {
+            Drill::Characters => (0..self.length).map(|_| CHARACTERS[self.rng.below(CHARACTERS.len())] as char).collect(),
+            Drill::Callsigns => {
+                let prefix = PREFIXES[self.rng.below(PREFIXES.len())];
+                let digit = self.rng.below(10);
+                let suffix: String = (0..2 + self.rng.below(2)).map(|_| LETTERS[self.rng.below(LETTERS.len())] as char).collect();
+                format!("{}{}{}", prefix, digit, suffix)
+            }
+            // A realistic beacon, as the satellite sends it every 30 s
+            Drill::Beacons => self.satellite.next_line(30.0),
+        }
+    }
+}
+
+/// Uppercase with single spaces, as copy is compared
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_ascii_uppercase()
+}
+
+/// Mistakes in `copy` of `sent`: characters wrong, missing or extra
+pub fn mistakes(sent: &str, copy: &str) -> usize {
+    crate::edit_distance(&normalize(sent), &normalize(copy))
+}
+
+/// Score over all rounds
+#[derive(Debug, Default)]
+pub struct Score {
+    pub rounds: usize,
+    pub perfect: usize,
+    pub characters: usize,
+    pub mistakes: usize,
+}
+
+impl Score {
+    pub fn record(&mut self, sent: &str, mistakes: usize) {
+        let characters = normalize(sent).chars().count();
+        self.rounds += 1;
+        self.perfect += usize::from(mistakes == 0);
+        self.characters += characters;
+        self.mistakes += mistakes.min(characters);
+    }
+
+    /// Share of characters copied correctly, 0-100
+    pub fn accuracy(&self) -> f64 {
+        if self.characters == 0 {
+            return 0.0;
+        }
+        100.0 * (self.characters - self.mistakes) as f64 / self.characters as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_drills() {
+        let mut practice = Practice::new(Drill::Characters, 5, 1);
+        let text = practice.next_text();
+        assert_eq!(text.len(), 5);
+        assert!(text.bytes().all(|b| CHARACTERS.contains(&b)));
+
+        let mut practice = Practice::new(Drill::Callsigns, 5, 1);
+        for _ in 0..20 {
+            let call = practice.next_text();
+            assert!(PREFIXES.iter().any(|prefix| call.starts_with(prefix)), "{}", call);
+            assert!(call.bytes().any(|b| b.is_ascii_digit()));
+        }
+
+        let mut practice = Practice::new(Drill::Beacons, 5, 1);
+        assert!(parse_botan_beacon(&practice.next_text()).is_ok());
+        assert_eq!("frames".parse(), Ok(Drill::Beacons));
+        assert!("words".parse::<Drill>().is_err());
+    }
+
+    #[test]
+    fn test_scoring() {
+        assert_eq!(mistakes("BOTAN JS1YPT A67C", "botan  js1ypt a67c"), 0);
+        assert_eq!(mistakes("K7QX2", "K7X2"), 1);
+        assert_eq!(mistakes("K7QX2", "K8QX2"), 1);
+
+        let mut score = Score::default();
+        score.record("K7QX2", 0);
+        score.record("JA1ABC", 2);
+        score.record("E", 3);    // More mistakes than characters count as all wrong
+        assert_eq!((score.rounds, score.perfect, score.characters, score.mistakes), (3, 1, 12, 3));
+        assert_eq!(score.accuracy(), 75.0);
+    }
+}
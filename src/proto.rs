@@ -0,0 +1,399 @@
+// Protocol Buffers encoding of decoded frames for --format proto, following proto/botan.proto.
+// The wire format is written directly: proto3 leaves out scalars at their default value, while
+// `optional` fields and messages are written whenever they are present. Each frame is preceded by
+// its length as a varint, so a stream of them can be read with parseDelimitedFrom.
+use crate::botan_parser::{BotanBeaconData, BotanRSSI, BotanTelemetry};
+use crate::confidence::Confidence;
+use crate::frames::{Frame, TypedFrame};
+use crate::orbit::{Illumination, OrbitState};
+
+/// The message definitions, as shipped in proto/botan.proto
+pub const DEFINITION: &str = include_str!("../proto/botan.proto");
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LENGTH: u64 = 2;
+const FIXED32: u64 = 5;
+
+/// A message being written
+#[derive(Debug, Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u64, wire_type: u64) {
+        self.varint(field << 3 | wire_type);
+    }
+
+    fn uint(&mut self, field: u64, value: u64) {
+        if value != 0 {
+            self.key(field, VARINT);
+            self.varint(value);
+        }
+    }
+
+    fn optional_uint(&mut self, field: u64, value: Option<u64>) {
+        if let Some(value) = value {
+            self.key(field, VARINT);
+            self.varint(value);
+        }
+    }
+
+    fn sint(&mut self, field: u64, value: i64) {
+        self.uint(field, ((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn bool(&mut self, field: u64, value: bool) {
+        self.uint(field, value as u64);
+    }
+
+    fn double(&mut self, field: u64, value: f64) {
+        if value.to_bits() != 0 {
+            self.optional_double(field, Some(value));
+        }
+    }
+
+    fn optional_double(&mut self, field: u64, value: Option<f64>) {
+        if let Some(value) = value {
+            self.key(field, FIXED64);
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn bytes(&mut self, field: u64, value: &[u8]) {
+        if !value.is_empty() {
+            self.length_delimited(field, value);
+        }
+    }
+
+    fn string(&mut self, field: u64, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u64, message: Message) {
+        self.length_delimited(field, &message.0);
+    }
+
+    fn length_delimited(&mut self, field: u64, value: &[u8]) {
+        self.key(field, LENGTH);
+        self.varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+}
+
+fn signal_report(rssi: &BotanRSSI) -> Message {
+    let mut m = Message::default();
+    m.uint(1, rssi.raw_rssi as u64);
+    m.uint(2, rssi.raw_snr as u64);
+    m.optional_double(3, rssi.rssi_dbm);
+    m.optional_double(4, rssi.snr_db);
+    m
+}
+
+fn timestamp(unix: f64) -> Message {
+    let seconds = unix.floor();
+    let mut m = Message::default();
+    m.uint(1, seconds as i64 as u64);
+    m.uint(2, (((unix - seconds) * 1e9).round() as u64).min(999_999_999));
+    m
+}
+
+fn confidence(confidence: &Confidence) -> Message {
+    let mut m = Message::default();
+    m.double(1, confidence.score);
+    m.optional_double(2, confidence.signal);
+    m.optional_double(3, confidence.copy);
+    m.optional_double(4, confidence.plausibility);
+    m.optional_double(5, confidence.agreement);
+    m
+}
+
+fn telemetry(t: &BotanTelemetry) -> Message {
+    let mut m = Message::default();
+    for (field, value) in (1..).zip([t.bat_v, t.bat_i, t.bat_t, t.bpb_t, t.raw_i]) {
+        m.double(field, value);
+    }
+
+    let d = &t.data1;
+    let mut data1 = Message::default();
+    let flags = [d.power_5v0, d.power_depant, d.power_com, d.sap_x_pos, d.sap_y_pos, d.sap_y_neg, d.sap_z_pos, d.sap_z_neg];
+    for (field, flag) in (1..).zip(flags) {
+        data1.bool(field, flag);
+    }
+    m.message(6, data1);
+
+    let mut data2 = Message::default();
+    data2.uint(1, t.data2.reserve_cmd_counter as u64);
+    data2.uint(2, t.data2.cmd_uplink_counter as u64);
+    data2.bool(3, t.data2.kill_sw);
+    m.message(7, data2);
+
+    let d = &t.data3;
+    let mut data3 = Message::default();
+    data3.uint(1, d.kill_counter as u64);
+    data3.bool(2, d.mission_pic_on);
+    data3.bool(3, d.mis_error_flag);
+    data3.bool(4, d.mis_end_flag);
+    data3.bool(5, d.aprs_flag);
+    data3.uint(6, d.current_mis as u64);
+    m.message(8, data3);
+    m
+}
+
+fn typed_data(typed: &TypedFrame) -> Message {
+    let mut m = Message::default();
+    m.string(1, &typed.frame_type);
+    if let Some(check) = &typed.check {
+        let mut result = Message::default();
+        result.string(1, check.codec);
+        result.uint(2, check.received as u64);
+        result.uint(3, check.computed as u64);
+        result.bool(4, check.passed);
+        m.message(2, result);
+    }
+    for value in &typed.fields {
+        let mut field = Message::default();
+        field.string(1, &value.name);
+        field.sint(2, value.raw);
+        field.double(3, value.value);
+        if let Some(unit) = &value.unit {
+            field.length_delimited(4, unit.as_bytes());
+        }
+        m.message(3, field);
+    }
+    m
+}
+
+/// One Frame message
+pub fn encode(frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>) -> Vec<u8> {
+    let (satellite_name, call_sign, rssi, bytes, score) = match frame {
+        Frame::Housekeeping(BotanBeaconData { satellite_name, call_sign, rssi, frame, confidence, .. }) => {
+            (satellite_name, call_sign, rssi, &frame[..], confidence)
+        }
+        Frame::Typed(TypedFrame { satellite_name, call_sign, rssi, frame, confidence, .. }) => {
+            (satellite_name, call_sign, rssi, &frame[..], confidence)
+        }
+    };
+    let mut m = Message::default();
+    m.string(1, satellite_name);
+    m.string(2, call_sign);
+    if let Some(rssi) = rssi {
+        m.message(3, signal_report(rssi));
+    }
+    m.bytes(4, bytes);
+    if let Some(time) = time {
+        m.message(5, timestamp(time));
+    }
+    m.uint(6, orbit.map_or(0, |orbit| match orbit.illumination {
+        Illumination::Sunlight => 1,
+        Illumination::Eclipse => 2,
+    }));
+    if let Some(score) = score {
+        m.message(7, confidence(score));
+    }
+    match frame {
+        Frame::Housekeeping(data) => m.message(8, telemetry(&data.telemetry)),
+        Frame::Typed(typed) => m.message(9, typed_data(typed)),
+    }
+    m.optional_uint(10, orbit.and_then(|orbit| orbit.number));
+    m.0
+}
+
+/// One Frame message preceded by its length
+pub fn encode_delimited(frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>) -> Vec<u8> {
+    let message = encode(frame, time, orbit);
+    let mut delimited = Message::default();
+    delimited.varint(message.len() as u64);
+    delimited.0.extend_from_slice(&message);
+    delimited.0
+}
+
+/// A field of a message being read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),   // Strings, bytes and nested messages
+    Fixed32(u32),
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, String> {
+    let (mut value, mut shift) = (0u64, 0);
+    loop {
+        let (&byte, rest) = bytes.split_first().ok_or("Truncated message")?;
+        *bytes = rest;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+        shift += 7;
+        if shift > 63 {
+            return Err("Varint too long".to_string());
+        }
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> Result<&'a [u8], String> {
+    let (value, rest) = bytes.split_at_checked(length).ok_or("Truncated message")?;
+    *bytes = rest;
+    Ok(value)
+}
+
+/// The top-level fields of a message with their numbers, in the order written. Fields a
+/// reader does not know are left for it to skip, as proto3 requires.
+pub fn read_fields(mut bytes: &[u8]) -> Result<Vec<(u64, Field<'_>)>, String> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        let field = match key & 7 {
+            VARINT => Field::Varint(read_varint(&mut bytes)?),
+            FIXED64 => Field::Fixed64(u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap())),
+            LENGTH => {
+                let length = read_varint(&mut bytes)? as usize;
+                Field::Bytes(take(&mut bytes, length)?)
+            }
+            FIXED32 => Field::Fixed32(u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap())),
+            wire_type => return Err(format!("Unsupported wire type {}", wire_type)),
+        };
+        fields.push((key >> 3, field));
+    }
+    Ok(fields)
+}
+
+/// A google.protobuf.Timestamp message as a Unix time
+pub fn read_timestamp(bytes: &[u8]) -> Result<f64, String> {
+    let (mut seconds, mut nanos) = (0i64, 0i32);
+    for (number, field) in read_fields(bytes)? {
+        match (number, field) {
+            (1, Field::Varint(value)) => seconds = value as i64,
+            (2, Field::Varint(value)) => nanos = value as i32,
+            _ => {}
+        }
+    }
+    if !(0..1_000_000_000).contains(&nanos) {
+        return Err(format!("Invalid timestamp nanos {}", nanos));
+    }
+    Ok(seconds as f64 + nanos as f64 / 1e9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::{parse_botan_beacon, SignalCalibration};
+    use crate::calibration::CalibrationTable;
+    use crate::frames::{parse_frame, FrameTypes};
+
+    /// Top-level fields of a message: number, wire type and payload
+    fn fields(mut bytes: &[u8]) -> Vec<(u64, u64, Vec<u8>)> {
+        let varint = |bytes: &mut &[u8]| {
+            let (mut value, mut shift) = (0u64, 0);
+            loop {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= ((byte & 0x7F) as u64) << shift;
+                shift += 7;
+                if byte < 0x80 {
+                    return value;
+                }
+            }
+        };
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes);
+            let payload = match key & 7 {
+                VARINT => varint(&mut bytes).to_le_bytes().to_vec(),
+                FIXED64 => bytes[..8].to_vec(),
+                _ => {
+                    let length = varint(&mut bytes) as usize;
+                    bytes[..length].to_vec()
+                }
+            };
+            if key & 7 != VARINT {
+                bytes = &bytes[payload.len()..];
+            }
+            fields.push((key >> 3, key & 7, payload));
+        }
+        fields
+    }
+
+    fn field(message: &[u8], number: u64) -> Vec<u8> {
+        fields(message).into_iter().find(|f| f.0 == number).map(|f| f.2).unwrap_or_else(|| panic!("no field {}", number))
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut m = Message::default();
+        m.uint(1, 300);
+        m.sint(2, -3);
+        m.uint(3, 0);
+        assert_eq!(m.0, [0x08, 0xAC, 0x02, 0x10, 0x05]);
+    }
+
+    #[test]
+    fn test_encode_beacon() {
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        let orbit = OrbitState { illumination: Illumination::Eclipse, number: Some(300) };
+        let message = encode(&frame, Some(1714566896.5), Some(orbit));
+        let numbers: Vec<u64> = fields(&message).iter().map(|f| f.0).collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5, 6, 8, 10]);
+        assert_eq!(field(&message, 2), b"JS1YPT");
+        assert_eq!(field(&message, 4), [0xA6, 0x7C, 0x8D, 0x5E, 0x2A, 0xA1, 0x36, 0x08]);
+        assert_eq!(field(&message, 3), [0x08, 0x86, 0x01, 0x10, 0x40]);
+        assert_eq!(fields(&field(&message, 5)).iter().map(|f| f.2[0]).collect::<Vec<_>>(), [0xF0, 0x00]);
+        assert_eq!(field(&message, 6)[0], 2);
+        assert_eq!(u64::from_le_bytes(field(&message, 10).try_into().unwrap()), 300);
+
+        let telemetry = field(&message, 8);
+        assert_eq!(f64::from_le_bytes(field(&telemetry, 1).try_into().unwrap()), 166.0 * 0.025781);
+        // 0x36: 3 reserved commands, 3 received, KILL switch off
+        assert_eq!(fields(&field(&telemetry, 7)).iter().map(|f| (f.0, f.2[0])).collect::<Vec<_>>(), [(1, 3), (2, 3)]);
+
+        let delimited = encode_delimited(&frame, None, None);
+        assert_eq!(delimited[0] as usize, delimited.len() - 1);
+    }
+
+    #[test]
+    fn test_encode_typed() {
+        let types: FrameTypes = serde_json::from_str(
+            r#"{"summed": {"length": 3, "check": {"codec": "sum8", "field": "u8@16"}, "fields": [{"name": "count", "field": "s8@8", "unit": "n"}]}}"#,
+        )
+        .unwrap();
+        let frame = parse_frame("BOTAN JS1YPT 4DFF4C", &SignalCalibration::default(), &CalibrationTable::default(), &types).unwrap();
+        let message = encode(&frame, None, None);
+        let numbers: Vec<u64> = fields(&message).iter().map(|f| f.0).collect();
+        assert_eq!(numbers, [1, 2, 4, 9]);
+        let typed = field(&message, 9);
+        assert_eq!(field(&typed, 1), b"summed");
+        assert_eq!(field(&field(&typed, 2), 4)[0], 1);
+        let value = field(&typed, 3);
+        assert_eq!(field(&value, 2)[0], 1);    // -1 zigzag encoded
+        assert_eq!(field(&value, 4), b"n");
+    }
+
+    #[test]
+    fn test_read_fields() {
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        let message = encode(&frame, Some(1714566896.5), None);
+        let read = read_fields(&message).unwrap();
+        assert_eq!(read[0], (1, Field::Bytes(b"BOTAN")));
+        let Some((5, Field::Bytes(time))) = read.iter().find(|f| f.0 == 5) else { panic!("no time") };
+        assert_eq!(read_timestamp(time).unwrap(), 1714566896.5);
+        assert_eq!(read_fields(&[0x0D, 1, 0, 0, 0]).unwrap(), [(1, Field::Fixed32(1))]);
+        assert!(read_fields(&message[..message.len() - 1]).is_err());
+        assert!(read_fields(&[0x0B]).is_err());
+    }
+
+    #[test]
+    fn test_definition_lists_every_message() {
+        for name in ["message Frame", "message SignalReport", "message Telemetry", "message TypedData", "enum Illumination"] {
+            assert!(DEFINITION.contains(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// Provenance of a decode: a unique ID, where the input came from, and what produced the result
+// (software version, calibration, corrections applied to the input). It goes with the frame into
+// pass.json, the audit log and every JSON record printed or forwarded, so when two pipelines
+// disagree about a frame each result can be traced back to how it was made.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::rng::Rng;
+
+/// How one decode was produced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Provenance {
+    pub id: String,             // A random (version 4) UUID
+    pub source: String,         // The command and its input, e.g. "decode-file beacons.txt:12"
+    pub version: &'static str,
+    pub calibration: String,    // "builtin", or the calibration files in use by content hash
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub corrections: Vec<String>,
+}
+
+impl Provenance {
+    pub fn new(source: &str, calibration: &str, corrections: Vec<String>) -> Self {
+        Provenance {
+            id: uuid_v4(),
+            source: source.to_string(),
+            version: env!("CARGO_PKG_VERSION"),
+            calibration: calibration.to_string(),
+            corrections,
+        }
+    }
+}
+
+/// Name the calibration files in use by what they hold, e.g. "signal:3f2a9c0d41b7 telemetry:..",
+/// so a result can be matched to the exact tables even after a file was edited in place
+pub fn calibration_version(files: &[(&str, Option<&str>)]) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for (kind, path) in files {
+        let Some(path) = path else { continue };
+        let content = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        parts.push(format!("{}:{:012x}", kind, fnv1a(&content) >> 16));
+    }
+    Ok(if parts.is_empty() { "builtin".to_string() } else { parts.join(" ") })
+}
+
+/// 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+/// A random UUID from the system's random source, or from the clock and a counter without one
+fn uuid_v4() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut bytes = [0u8; 16];
+    let random = std::fs::File::open("/dev/urandom").and_then(|mut file| std::io::Read::read_exact(&mut file, &mut bytes));
+    if random.is_err() {
+        let seed = Rng::seed_from_time() ^ ((std::process::id() as u64) << 32) ^ COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut rng = Rng::new(seed);
+        bytes[..8].copy_from_slice(&rng.next().to_be_bytes());
+        bytes[8..].copy_from_slice(&rng.next().to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance() {
+        let first = Provenance::new("decode-file beacons.txt:3", "builtin", vec!["O→0 at data position 12".to_string()]);
+        let second = Provenance::new("decode-file beacons.txt:3", "builtin", Vec::new());
+        assert_ne!(first.id, second.id);
+        let id: Vec<&str> = first.id.split('-').collect();
+        assert_eq!(id.iter().map(|part| part.len()).collect::<Vec<_>>(), [8, 4, 4, 4, 12]);
+        assert!(id[2].starts_with('4') && "89ab".contains(&id[3][..1]));
+
+        let json = serde_json::to_value(&second).unwrap();
+        assert_eq!(json["source"], "decode-file beacons.txt:3");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json.get("corrections").is_none());
+
+        assert_eq!(calibration_version(&[("signal", None)]).unwrap(), "builtin");
+        let path = std::env::temp_dir().join(format!("botan_provenance_test_{}", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let version = calibration_version(&[("telemetry", path.to_str())]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(version.starts_with("telemetry:") && version.len() == 22, "{}", version);
+    }
+}
@@ -0,0 +1,316 @@
+// Session state of interactive mode and its colon-commands:
+//   :last          re-print the most recent beacon
+//   :raw           hex of the most recent beacon frame
+//   :explain       step-by-step explanation of the most recent beacon
+//   :mappings      the morse table used for non-BOTAN input
+//   :stats         counters for this session
+//   :save <file>   export every input and its result as JSON lines
+//   :help          list the commands
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::botan_parser::{BotanBeaconData, Repair};
+use crate::exit_code::DecodeTally;
+use crate::frames::{Frame, TypedFrame};
+use crate::output::BeaconPrinter;
+use crate::style::{paint, Paint};
+use crate::{i18n, timeutil, BotanDecoder};
+
+const HELP: &str = "Commands:
+  :last          Re-print the most recent beacon
+  :raw           Show the hex of the most recent beacon frame
+  :explain       Explain the most recent beacon step by step
+  :mappings      List the morse table
+  :stats         Show counters for this session
+  :save <file>   Export the session as JSON lines
+  :help          Show this list";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Last,
+    Raw,
+    Explain,
+    Mappings,
+    Stats,
+    Save(String),
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = s.split_once(char::is_whitespace).map_or((s, ""), |(n, a)| (n, a.trim()));
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            (":last", "") => Ok(Command::Last),
+            (":raw", "") => Ok(Command::Raw),
+            (":explain", "") => Ok(Command::Explain),
+            (":mappings", "") => Ok(Command::Mappings),
+            (":stats", "") => Ok(Command::Stats),
+            (":save", "") => Err("Usage: :save <file>".to_string()),
+            (":save", path) => Ok(Command::Save(path.to_string())),
+            (":help", "") => Ok(Command::Help),
+            (":last" | ":raw" | ":explain" | ":mappings" | ":stats" | ":help", _) => Err(format!("{} takes no argument", name)),
+            _ => Err(format!("Unknown command '{}'. Type :help for the list", name)),
+        }
+    }
+}
+
+/// One decoded or failed input, as exported by :save
+#[derive(Debug, Serialize)]
+struct Entry {
+    time: String,
+    input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beacon: Option<BotanBeaconData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    typed: Option<TypedFrame>,     // A frame of a configured type other than housekeeping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,          // Legacy morse decode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// `line` with the character `repair` is about marked, and a caret under it saying what is wrong
+pub fn repair_hint(line: &str, repair: Repair, color: bool) -> String {
+    let (position, problem) = match repair {
+        Repair::Replace(i) => (i, "not a hex digit; type the right one"),
+        Repair::Insert(i) => (i, "one hex digit short; type the missing one where it belongs"),
+        Repair::Remove(i) => (i, "one hex digit too many; delete the extra one"),
+    };
+    let before: String = line.chars().take(position).collect();
+    let marked: String = match repair {
+        Repair::Replace(_) => {
+            let bad = line.chars().nth(position).unwrap_or(' ');
+            let after: String = line.chars().skip(position + 1).collect();
+            format!("{}{}{}", before, paint(bad, Paint::Red, color), after)
+        }
+        Repair::Insert(_) | Repair::Remove(_) => line.to_string(),
+    };
+    format!("{}\n{}^ {}", marked, " ".repeat(i18n::display_width(&before)), problem)
+}
+
+pub struct Session {
+    started: f64,
+    entries: Vec<Entry>,
+    last_beacon: Option<BotanBeaconData>,
+    pub tally: DecodeTally,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session { started: timeutil::now_unix(), entries: Vec::new(), last_beacon: None, tally: DecodeTally::default() }
+    }
+
+    /// Decode a BOTAN beacon, or failing the "BOTAN" prefix a line of morse, and format the result
+    pub fn decode(&mut self, input: &str, printer: &BeaconPrinter, decoder: &BotanDecoder) -> String {
+        let mut entry = Entry {
+            time: timeutil::format_utc(timeutil::now_unix()),
+            input: input.to_string(),
+            beacon: None,
+            typed: None,
+            text: None,
+            error: None,
+        };
+        let normalized = i18n::normalize_width(input);
+        let output = if normalized.starts_with("BOTAN") {
+            let (line, note) = match printer.correct(&normalized) {
+                Some((fixed, note)) => (fixed, format!("{}\n", note)),
+                None => (normalized.to_string(), String::new()),
+            };
+            let parsed = printer.parse_frame(&line, None).and_then(|frame| printer.vet(&line, !note.is_empty(), frame));
+            match parsed.and_then(|frame| printer.render_frame(&frame, None, None, None).map(|text| (frame, text))) {
+                Ok((Frame::Housekeeping(data), text)) => {
+                    self.last_beacon = Some(data.clone());
+                    entry.beacon = Some(data);
+                    note + &text
+                }
+                Ok((Frame::Typed(typed), text)) => {
+                    entry.typed = Some(typed);
+                    note + &text
+                }
+                Err(error) => {
+                    let output = format!("{}BOTAN Parsing Error: {}", note, error);
+                    entry.error = Some(error);
+                    output
+                }
+            }
+        } else {
+            // Fall back to legacy morse code decoder for non-BOTAN inputs
+            match decoder.decode(&normalized) {
+                Ok(decoded) => {
+                    let output = format!("Legacy Morse Decoded: {}", decoded);
+                    entry.text = Some(decoded);
+                    output
+                }
+                Err(error) => {
+                    entry.error = Some(error.to_string());
+                    format!("Legacy Decoding Error: {}", error)
+                }
+            }
+        };
+        self.tally.record(entry.error.is_none());
+        self.entries.push(entry);
+        output
+    }
+
+    /// Whether the most recent input could not be decoded
+    pub fn last_failed(&self) -> bool {
+        self.entries.last().is_some_and(|entry| entry.error.is_some())
+    }
+
+    /// Decode `input` as the fix of the most recent input, which then no longer counts as failed
+    pub fn redecode(&mut self, input: &str, printer: &BeaconPrinter, decoder: &BotanDecoder) -> String {
+        if self.last_failed() {
+            self.entries.pop();
+            self.tally.failed -= 1;
+        }
+        self.decode(input, printer, decoder)
+    }
+
+    pub fn run(&self, command: &Command, printer: &BeaconPrinter, decoder: &BotanDecoder) -> Result<String, String> {
+        match command {
+            Command::Last => {
+                let data = self.last_beacon.as_ref().ok_or("No beacon decoded yet")?;
+                printer.render(data, None, None)
+            }
+            Command::Raw => {
+                let data = self.last_beacon.as_ref().ok_or("No beacon decoded yet")?;
+                let bytes: Vec<String> = data.frame.iter().map(|b| format!("{:02X}", b)).collect();
+                Ok(format!("Raw frame: {}", bytes.join(" ")))
+            }
+            Command::Explain => {
+                let data = self.last_beacon.as_ref().ok_or("No beacon decoded yet")?;
+                Ok(printer.explain(data))
+            }
+            Command::Mappings => Ok(decoder.format_mappings()),
+            Command::Stats => Ok(self.stats()),
+            Command::Save(path) => {
+                self.save(path).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+                Ok(format!("Saved {} entries to {}", self.entries.len(), path))
+            }
+            Command::Help => Ok(HELP.to_string()),
+        }
+    }
+
+    fn stats(&self) -> String {
+        let beacons = self.entries.iter().filter(|e| e.beacon.is_some() || e.typed.is_some()).count();
+        let morse = self.entries.iter().filter(|e| e.text.is_some()).count();
+        let elapsed = (timeutil::now_unix() - self.started).max(0.0) as u64;
+        format!(
+            "Session: {:02}:{:02}:{:02}\nInputs:  {}\nBeacons: {}\nMorse:   {}\nFailed:  {}",
+            elapsed / 3600,
+            elapsed / 60 % 60,
+            elapsed % 60,
+            self.entries.len(),
+            beacons,
+            morse,
+            self.tally.failed,
+        )
+    }
+
+    fn save(&self, path: &str) -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        for entry in &self.entries {
+            serde_json::to_writer(&mut out, entry)?;
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::{DisplayOptions, SignalCalibration};
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(":last".parse::<Command>(), Ok(Command::Last));
+        assert_eq!(":STATS".parse::<Command>(), Ok(Command::Stats));
+        assert_eq!(":save  session.jsonl ".parse::<Command>(), Ok(Command::Save("session.jsonl".to_string())));
+        assert!(":save".parse::<Command>().is_err());
+        assert!(":raw now".parse::<Command>().is_err());
+        assert!(":frobnicate".parse::<Command>().unwrap_err().contains(":help"));
+    }
+
+    #[test]
+    fn test_session_commands() {
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None);
+        let decoder = BotanDecoder::new();
+        let mut session = Session::new();
+        assert!(session.run(&Command::Last, &printer, &decoder).is_err());
+
+        session.decode("BOTAN JS1YPT A67C8D5E2AA13608", &printer, &decoder);
+        assert!(session.decode("BOTAN JS1YPT A67C", &printer, &decoder).starts_with("BOTAN Parsing Error"));
+        assert_eq!(session.decode("... --- ...", &printer, &decoder), "Legacy Morse Decoded: SOS");
+
+        let last = session.run(&Command::Last, &printer, &decoder).unwrap();
+        assert!(last.contains("Call Sign: JS1YPT"));
+        assert_eq!(session.run(&Command::Raw, &printer, &decoder).unwrap(), "Raw frame: A6 7C 8D 5E 2A A1 36 08");
+        assert!(session.run(&Command::Explain, &printer, &decoder).unwrap().starts_with("Step 1: The header"));
+        assert!(session.run(&Command::Mappings, &printer, &decoder).unwrap().contains("  '...' -> 'S'"));
+        let stats = session.run(&Command::Stats, &printer, &decoder).unwrap();
+        assert!(stats.contains("Inputs:  3\nBeacons: 1\nMorse:   1\nFailed:  1"));
+
+        let path = std::env::temp_dir().join(format!("botan_session_test_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        session.run(&Command::Save(path.to_string()), &printer, &decoder).unwrap();
+        let saved = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<serde_json::Value> = saved.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["beacon"]["frame"], "A67C8D5E2AA13608");
+        assert!(lines[1]["error"].is_string());
+        assert_eq!(lines[2]["text"], "SOS");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_full_width_input() {
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None);
+        let decoder = BotanDecoder::new();
+        let mut session = Session::new();
+        assert!(!session.decode("ＢＯＴＡＮ　ＪＳ１ＹＰＴ　Ａ６７Ｃ８Ｄ５Ｅ２ＡＡ１３６０８", &printer, &decoder).starts_with("BOTAN Parsing Error"));
+        assert_eq!(session.decode("．．．　－－－　．．．", &printer, &decoder), "Legacy Morse Decoded: SOS");
+        assert_eq!(session.run(&Command::Raw, &printer, &decoder).unwrap(), "Raw frame: A6 7C 8D 5E 2A A1 36 08");
+    }
+
+    #[test]
+    fn test_autocorrect() {
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None);
+        let decoder = BotanDecoder::new();
+        let mut session = Session::new();
+        assert!(session.decode("BOTAN JS1YPT A67C8D5E2AA1360O", &printer, &decoder).starts_with("BOTAN Parsing Error"));
+        let printer = printer.with_autocorrect(true);
+        let output = session.decode("BOTAN JS1YPT A67C8D5E2AA1360O", &printer, &decoder);
+        assert!(output.starts_with("Corrected: O→0 at data position 15\n"));
+        assert_eq!(session.run(&Command::Raw, &printer, &decoder).unwrap(), "Raw frame: A6 7C 8D 5E 2A A1 36 00");
+    }
+
+    #[test]
+    fn test_redecode() {
+        let printer = BeaconPrinter::new(SignalCalibration::default(), DisplayOptions::default(), None);
+        let decoder = BotanDecoder::new();
+        let mut session = Session::new();
+        session.decode("BOTAN JS1YPT A67C8D5EGAA13608", &printer, &decoder);
+        assert!(session.last_failed());
+        session.redecode("BOTAN JS1YPT A67C8D5E2AA13608", &printer, &decoder);
+        assert!(!session.last_failed());
+        assert_eq!((session.entries.len(), session.tally.decoded, session.tally.failed), (1, 1, 0));
+    }
+
+    #[test]
+    fn test_repair_hint() {
+        assert_eq!(
+            repair_hint("BOTAN JS1YPT A67C8D5EGAA13608", Repair::Replace(21), false),
+            "BOTAN JS1YPT A67C8D5EGAA13608\n                     ^ not a hex digit; type the right one"
+        );
+        assert!(repair_hint("BOTAN JS1YPT A67C8D5EGAA13608", Repair::Replace(21), true).contains("A67C8D5E\x1b[31mG\x1b[0mAA"));
+        let hint = repair_hint("BOTAN JS1YPT A67C8D5E2AA1360", Repair::Insert(28), false);
+        assert!(hint.ends_with(&format!("\n{}^ one hex digit short; type the missing one where it belongs", " ".repeat(28))));
+    }
+}
@@ -0,0 +1,95 @@
+// Recorded beacons read back for `replay`: a pass.json saved by `schedule`, an audit log, or a
+// stream of --grsat-json lines, each beacon with its reception time where the log has one.
+// Plain beacon lines, as `decode-file` reads, are taken too but carry no timing.
+use serde_json::Value;
+
+use crate::grsat;
+use crate::migrate;
+use crate::timeutil;
+
+/// One recorded beacon line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorded {
+    pub time: Option<f64>,
+    pub line: String,
+}
+
+/// The beacons of a log in the order they were received
+pub fn read_log(content: &str) -> Result<Vec<Recorded>, String> {
+    let parse_time = |value: &Value| value.as_str().map(timeutil::parse_utc).transpose();
+    // A saved pass is a single JSON document
+    if let Ok(pass) = serde_json::from_str::<Value>(content) {
+        if pass.get("beacons").is_some_and(Value::is_array) {
+            let pass = migrate::upgrade_pass(pass)?;
+            let beacons = pass["beacons"].as_array().into_iter().flatten();
+            return beacons
+                .map(|beacon| {
+                    let line = beacon["text"].as_str().ok_or("Pass beacon without text")?;
+                    Ok(Recorded { time: parse_time(&beacon["time"])?, line: line.to_string() })
+                })
+                .collect();
+        }
+    }
+
+    let mut recorded = Vec::new();
+    for (number, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let at_line = |e: String| format!("Line {}: {}", number + 1, e);
+        if !line.trim_start().starts_with('{') {
+            recorded.push(Recorded { time: None, line: line.trim().to_string() });
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|e| e.to_string()).and_then(migrate::upgrade_record).map_err(at_line)?;
+        match value.get("input").and_then(Value::as_str) {
+            // An audit log entry
+            Some(input) => recorded.push(Recorded { time: parse_time(&value["time"]).map_err(at_line)?, line: input.to_string() }),
+            None => {
+                let (time, line) = grsat::parse_line(line).map_err(at_line)?;
+                recorded.push(Recorded { time, line });
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// Seconds to wait before `next` when the log is played `speed` times as fast as it was received
+pub fn delay(previous: Option<f64>, next: Option<f64>, speed: f64) -> f64 {
+    match (previous, next) {
+        (Some(previous), Some(next)) => ((next - previous) / speed).max(0.0),
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_log() {
+        let pass = r#"{"aos": "2024-05-01T12:34:56Z", "beacons": [
+            {"time": "2024-05-01T12:35:56Z", "text": "BOTAN JS1YPT A67C8D5E2AA13608", "data": null, "error": null},
+            {"time": "2024-05-01T12:36:26Z", "text": "BOTAN JS1YPT A67C", "data": null, "error": "short"}
+        ]}"#;
+        let recorded = read_log(pass).unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[1], Recorded { time: Some(1714566986.0), line: "BOTAN JS1YPT A67C".to_string() });
+
+        let lines = concat!(
+            r#"{"time":"2024-05-01T12:34:56Z","input":"BOTAN JS1YPT A67C","outcome":"failed"}"#,
+            "\n",
+            r#"{"time":"2024-05-01T12:35:56Z","frame":"A57EB76823210E08","telemetry":{}}"#,
+            "\n\nBOTAN JS1YPT A67C8D5E2AA13608\n"
+        );
+        let recorded = read_log(lines).unwrap();
+        assert_eq!(recorded.iter().map(|r| r.time).collect::<Vec<_>>(), [Some(1714566896.0), Some(1714566956.0), None]);
+        assert_eq!(recorded[1].line, "BOTAN JS1YPT A57EB76823210E08");
+        assert!(read_log("{\"time\": 5}\n").unwrap_err().starts_with("Line 1:"));
+    }
+
+    #[test]
+    fn test_delay() {
+        assert_eq!(delay(Some(100.0), Some(130.0), 1.0), 30.0);
+        assert_eq!(delay(Some(100.0), Some(130.0), 10.0), 3.0);
+        assert_eq!(delay(Some(130.0), Some(100.0), 1.0), 0.0);
+        assert_eq!(delay(None, Some(100.0), 1.0), 0.0);
+    }
+}
@@ -0,0 +1,349 @@
+// The mission summary written by `report`, over the history `export` reads: totals, coverage
+// per UTC day (days without frames included, so gaps stand out), the range of every numeric
+// channel, how often each status flag changed, and notable events in time order: the status
+// changes operators care about, values outside --limits and long silences.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::aggregate::{self, Stat};
+use crate::export;
+use crate::limits::{self, Limit, Limits, Severity};
+use crate::parquet::ColumnType;
+use crate::timeutil;
+
+/// Flags whose every change is an event
+const EVENT_FLAGS: [(&str, &str); 6] = [
+    ("kill_sw", "KILL switch"),
+    ("mis_error_flag", "Mission error flag"),
+    ("mis_end_flag", "Mission end flag"),
+    ("aprs_flag", "APRS mission"),
+    ("mission_pic_on", "Mission PIC"),
+    ("power_depant", "Antenna deployment line"),
+];
+
+/// Counters whose every change is an event
+const EVENT_COUNTERS: [(&str, &str); 3] =
+    [("kill_counter", "KILL counter"), ("cmd_uplink_counter", "Uplink command counter"), ("current_mis", "Current mission")];
+
+/// A stretch without decoded frames longer than this is an event [s]
+const SILENCE: f64 = 2.0 * 86400.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Day {
+    pub date: String,
+    pub passes: usize,
+    pub frames: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Channel {
+    pub name: String,
+    pub frames: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Flag {
+    pub name: String,
+    pub set: usize,        // false -> true
+    pub cleared: usize,    // true -> false
+    pub last: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub time: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub first: Option<String>,
+    pub last: Option<String>,
+    pub passes: usize,
+    pub lines: usize,
+    pub frames: usize,
+    pub failed: usize,
+    pub frame_types: BTreeMap<String, usize>,
+    pub days: Vec<Day>,
+    pub channels: Vec<Channel>,
+    pub flags: Vec<Flag>,
+    pub events: Vec<Event>,
+}
+
+/// The limit of an analog channel
+pub(crate) fn limit(limits: &Limits, name: &str) -> Option<Limit> {
+    match name {
+        "bat_v" => limits.bat_v,
+        "bat_i" => limits.bat_i,
+        "bat_t" => limits.bat_t,
+        "bpb_t" => limits.bpb_t,
+        "raw_i" => limits.raw_i,
+        _ => None,
+    }
+}
+
+/// Day-by-day coverage from the first to the last day with received lines
+fn coverage(rows: &[Vec<String>], time: usize, pass: usize, frame: usize) -> Vec<Day> {
+    let mut days: BTreeMap<i64, (BTreeSet<&str>, usize, usize)> = BTreeMap::new();
+    for row in rows {
+        let Ok(unix) = timeutil::parse_utc(&row[time]) else { continue };
+        let day = days.entry(timeutil::display_day(unix)).or_default();
+        day.0.insert(&row[pass]);
+        if row[frame].is_empty() {
+            day.2 += 1;
+        } else {
+            day.1 += 1;
+        }
+    }
+    let (Some(&first), Some(&last)) = (days.keys().next(), days.keys().last()) else {
+        return Vec::new();
+    };
+    (first..=last)
+        .map(|day| {
+            let (passes, frames, failed) = days.get(&day).map_or((0, 0, 0), |(passes, frames, failed)| (passes.len(), *frames, *failed));
+            Day { date: timeutil::format_utc(day as f64 * 86400.0)[..10].to_string(), passes, frames, failed }
+        })
+        .collect()
+}
+
+/// The rows of the history under `dir` received between `from` and `to`
+pub fn history(dir: &Path, from: Option<f64>, to: Option<f64>) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    export::for_each_row(dir, from, to, |row| {
+        rows.push(row);
+        Ok(())
+    })?;
+    Ok(rows)
+}
+
+/// Summarise rows laid out as `export::columns`, flagging values outside `limits`. The rows are
+/// put in reception order.
+pub fn summarise(rows: &mut [Vec<String>], limits: &Limits) -> Report {
+    let columns = export::columns();
+    let index = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+    let (time, pass, frame_type, frame) = (index("time"), index("pass_aos"), index("frame_type"), index("frame"));
+    rows.sort_by_key(|row| timeutil::parse_utc(&row[time]).map(|t| (t * 1000.0) as i64).unwrap_or_default());
+
+    let decoded: Vec<&Vec<String>> = rows.iter().filter(|row| !row[frame].is_empty()).collect();
+    let mut frame_types = BTreeMap::new();
+    for row in &decoded {
+        *frame_types.entry(row[frame_type].clone()).or_insert(0) += 1;
+    }
+
+    let mut values: Vec<(String, Vec<f64>)> = Vec::new();
+    for row in &decoded {
+        for (name, value) in aggregate::row_channels(row) {
+            match values.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, list)) => list.push(value),
+                None => values.push((name, vec![value])),
+            }
+        }
+    }
+    let channels = values
+        .iter()
+        .map(|(name, list)| Channel {
+            name: name.clone(),
+            frames: list.len(),
+            min: Stat::Min.of(list),
+            max: Stat::Max.of(list),
+            mean: Stat::Mean.of(list),
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    let housekeeping: Vec<&&Vec<String>> = decoded.iter().filter(|row| row[frame_type] == "housekeeping").collect();
+    let mut flags = Vec::new();
+    for (idx, name) in columns.iter().enumerate().filter(|(_, name)| export::column_type(name) == ColumnType::Boolean && **name != "check_passed") {
+        let mut flag = Flag { name: name.to_string(), set: 0, cleared: 0, last: false };
+        let mut previous: Option<bool> = None;
+        for row in &housekeeping {
+            let Ok(value) = row[idx].parse::<bool>() else { continue };
+            if let Some(before) = previous.filter(|before| *before != value) {
+                if before { flag.cleared += 1 } else { flag.set += 1 }
+                if let Some((_, label)) = EVENT_FLAGS.iter().find(|(n, _)| n == name) {
+                    events.push(Event { time: row[time].clone(), text: format!("{} {}", label, if value { "set" } else { "cleared" }) });
+                }
+            }
+            previous = Some(value);
+            flag.last = value;
+        }
+        if previous.is_some() {
+            flags.push(flag);
+        }
+    }
+    for (name, label) in EVENT_COUNTERS {
+        let idx = index(name);
+        for pair in housekeeping.windows(2) {
+            if pair[0][idx] != pair[1][idx] {
+                events.push(Event { time: pair[1][time].clone(), text: format!("{} changed from {} to {}", label, pair[0][idx], pair[1][idx]) });
+            }
+        }
+    }
+    for name in ["bat_v", "bat_i", "bat_t", "bpb_t", "raw_i"] {
+        let idx = index(name);
+        let alarms: Vec<(&String, f64)> = housekeeping
+            .iter()
+            .filter_map(|row| Some((&row[time], row[idx].parse().ok()?)))
+            .filter(|(_, value)| limits::check(limit(limits, name), *value) == Severity::Alarm)
+            .collect();
+        if let Some((first, value)) = alarms.first() {
+            events.push(Event { time: first.to_string(), text: format!("{} outside its limits ({}), {} frames in all", name, aggregate::number(*value), alarms.len()) });
+        }
+    }
+    for pair in decoded.windows(2) {
+        let (Ok(before), Ok(after)) = (timeutil::parse_utc(&pair[0][time]), timeutil::parse_utc(&pair[1][time])) else { continue };
+        if after - before > SILENCE {
+            events.push(Event { time: pair[0][time].clone(), text: format!("No frames for {:.1} days, until {}", (after - before) / 86400.0, timeutil::display_text(&pair[1][time])) });
+        }
+    }
+    // Stable, so events at the same time keep the order above
+    events.sort_by(|a, b| a.time.cmp(&b.time));
+    for event in &mut events {
+        event.time = timeutil::display_text(&event.time);
+    }
+
+    Report {
+        first: rows.first().map(|row| timeutil::display_text(&row[time])),
+        last: rows.last().map(|row| timeutil::display_text(&row[time])),
+        passes: rows.iter().map(|row| &row[pass]).collect::<BTreeSet<_>>().len(),
+        lines: rows.len(),
+        frames: decoded.len(),
+        failed: rows.len() - decoded.len(),
+        frame_types,
+        days: coverage(rows, time, pass, frame),
+        channels,
+        flags,
+        events,
+    }
+}
+
+impl Report {
+    /// The report as a Markdown document
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# BOTAN status report").unwrap();
+        writeln!(out).unwrap();
+        let (Some(first), Some(last)) = (&self.first, &self.last) else {
+            writeln!(out, "No frames received.").unwrap();
+            return out;
+        };
+        writeln!(
+            out,
+            "From {} to {}: {} passes, {} lines received, {} frames decoded ({:.1}%), {} failed.",
+            first,
+            last,
+            self.passes,
+            self.lines,
+            self.frames,
+            100.0 * self.frames as f64 / self.lines.max(1) as f64,
+            self.failed
+        )
+        .unwrap();
+        if !self.frame_types.is_empty() {
+            let types: Vec<String> = self.frame_types.iter().map(|(name, count)| format!("{} {}", name, count)).collect();
+            writeln!(out, "Frame types: {}.", types.join(", ")).unwrap();
+        }
+
+        writeln!(out, "\n## Coverage per day\n").unwrap();
+        writeln!(out, "| Date | Passes | Frames | Failed |\n|---|---:|---:|---:|").unwrap();
+        for day in &self.days {
+            writeln!(out, "| {} | {} | {} | {} |", day.date, day.passes, day.frames, day.failed).unwrap();
+        }
+        let empty = self.days.iter().filter(|day| day.frames == 0).count();
+        writeln!(out, "\n{} of {} days without decoded frames.", empty, self.days.len()).unwrap();
+
+        if !self.channels.is_empty() {
+            writeln!(out, "\n## Channels\n").unwrap();
+            writeln!(out, "| Channel | Frames | Min | Max | Mean |\n|---|---:|---:|---:|---:|").unwrap();
+            for c in &self.channels {
+                let n = aggregate::number;
+                writeln!(out, "| {} | {} | {} | {} | {} |", c.name, c.frames, n(c.min), n(c.max), n(c.mean)).unwrap();
+            }
+        }
+
+        if !self.flags.is_empty() {
+            writeln!(out, "\n## Flags\n").unwrap();
+            writeln!(out, "| Flag | Set | Cleared | Last |\n|---|---:|---:|---|").unwrap();
+            for f in &self.flags {
+                writeln!(out, "| {} | {} | {} | {} |", f.name, f.set, f.cleared, f.last).unwrap();
+            }
+        }
+
+        writeln!(out, "\n## Events\n").unwrap();
+        if self.events.is_empty() {
+            writeln!(out, "None.").unwrap();
+        }
+        for event in &self.events {
+            writeln!(out, "- {} {}", event.time, event.text).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+    use crate::frames::Frame;
+    use crate::orbit::{Illumination, OrbitState, Pass};
+    use crate::scheduler::{save_pass, PassRecord};
+
+    #[test]
+    fn test_report() {
+        let root = std::env::temp_dir().join(format!("botan_report_test_{}", std::process::id()));
+        // The second day's frame has KILL switch set (0x0F) and a lower voltage; nothing on the
+        // next three days
+        for (aos, lines) in [
+            (1714566896.0, vec!["BOTAN JS1YPT SI8640 A67C8D5E2AA13608", "BOTAN JS1YPT A67C"]),
+            (1714653296.0, vec!["BOTAN JS1YPT A57EB76823210F08"]),
+            (1714998896.0, vec!["BOTAN JS1YPT A57EB76823210E08"]),
+        ] {
+            let pass = Pass {
+                aos_unix: aos,
+                los_unix: aos + 600.0,
+                max_elevation_unix: aos + 300.0,
+                max_elevation_deg: 42.5,
+                aos_azimuth_deg: 10.0,
+                los_azimuth_deg: 170.0,
+            };
+            let mut record = PassRecord::new(&pass, None);
+            let orbit = OrbitState { illumination: Illumination::Sunlight, number: None };
+            for (i, line) in lines.iter().enumerate() {
+                let parsed = parse_botan_beacon(line).map(Frame::Housekeeping);
+                record.add_beacon(aos + 60.0 * (i + 1) as f64, line, Some(orbit), parsed);
+            }
+            save_pass(&root, aos, &record, None).unwrap();
+        }
+        let limits: Limits = serde_json::from_str(r#"{"bat_v": {"min": 4.26}}"#).unwrap();
+        let report = summarise(&mut history(&root, None, None).unwrap(), &limits);
+        std::fs::remove_dir_all(root).unwrap();
+
+        assert_eq!((report.passes, report.lines, report.frames, report.failed), (3, 4, 3, 1));
+        assert_eq!(report.days.len(), 6);
+        assert_eq!(report.days[0], Day { date: "2024-05-01".to_string(), passes: 1, frames: 1, failed: 1 });
+        assert_eq!(report.days.iter().filter(|day| day.frames == 0).count(), 3);
+        let bat_v = report.channels.iter().find(|c| c.name == "bat_v").unwrap();
+        assert_eq!((bat_v.frames, bat_v.min, bat_v.max), (3, 165.0 * 0.025781, 166.0 * 0.025781));
+        let kill_sw = report.flags.iter().find(|f| f.name == "kill_sw").unwrap();
+        assert_eq!((kill_sw.set, kill_sw.cleared, kill_sw.last), (1, 1, false));
+
+        let events: Vec<&str> = report.events.iter().map(|e| e.text.as_str()).collect();
+        assert!(events.contains(&"KILL switch set"), "{:?}", events);
+        assert!(events.contains(&"KILL switch cleared"));
+        assert!(events.iter().any(|e| e.starts_with("bat_v outside its limits (4.253865), 2 frames")));
+        assert!(events.iter().any(|e| e.starts_with("No frames for 4.0 days")));
+
+        let markdown = report.markdown();
+        assert!(markdown.starts_with("# BOTAN status report\n\nFrom 2024-05-01T12:35:56Z to 2024-05-06T12:35:56Z: 3 passes, 4 lines received, 3 frames decoded (75.0%), 1 failed.\n"));
+        assert!(markdown.contains("| 2024-05-03 | 0 | 0 | 0 |\n"));
+        assert!(markdown.contains("\n3 of 6 days without decoded frames.\n"));
+        assert!(markdown.contains("| kill_sw | 1 | 1 | false |\n"));
+    }
+}
@@ -0,0 +1,165 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::logging::{debug, trace};
+
+/// Client for Hamlib's rigctld network protocol (also spoken by GQRX remote control)
+pub struct RigctlClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl RigctlClient {
+    /// Connect to rigctld, e.g. "localhost:4532"
+    pub fn connect(addr: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| format!("Cannot connect to rigctld at {}: {}", addr, e))?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .map_err(|e| e.to_string())?;
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        debug!("connected to rigctld"; addr = addr);
+        Ok(RigctlClient { reader: BufReader::new(stream), writer })
+    }
+
+    /// Tune the receiver [Hz]
+    pub fn set_frequency(&mut self, freq_hz: f64) -> Result<(), String> {
+        self.command(&format!("F {:.0}", freq_hz))
+    }
+
+    /// Set the mode, e.g. "CW"; a passband of 0 keeps the rig's default filter
+    pub fn set_mode(&mut self, mode: &str, passband_hz: u32) -> Result<(), String> {
+        self.command(&format!("M {} {}", mode, passband_hz))
+    }
+
+    /// Current receiver frequency [Hz]
+    pub fn frequency(&mut self) -> Result<f64, String> {
+        self.send("f")?;
+        let line = self.read_line()?;
+        if let Some(code) = line.strip_prefix("RPRT ") {
+            return Err(format!("rigctld error {}", code));
+        }
+        line.parse::<f64>()
+            .map_err(|_| format!("Unexpected rigctld frequency reply: '{}'", line))
+    }
+
+    /// Send a set command and check its "RPRT <code>" reply
+    fn command(&mut self, command: &str) -> Result<(), String> {
+        self.send(command)?;
+        let line = self.read_line()?;
+        match line.strip_prefix("RPRT ") {
+            Some("0") => Ok(()),
+            Some(code) => Err(format!("rigctld rejected '{}' (error {})", command, code)),
+            None => Err(format!("Unexpected rigctld reply to '{}': '{}'", command, line)),
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        debug!("send"; command = command);
+        writeln!(self.writer, "{}", command).map_err(|e| format!("rigctld write failed: {}", e))
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err("rigctld closed the connection".to_string()),
+            Ok(_) => {
+                trace!("reply"; line = line.trim());
+                Ok(line.trim().to_string())
+            }
+            Err(e) => Err(format!("rigctld read failed: {}", e)),
+        }
+    }
+}
+
+/// Keeps a rig tuned to a Doppler-shifted beacon frequency
+pub struct DopplerRig {
+    client: RigctlClient,
+    freq_hz: f64,
+    tuned_shift_hz: Option<f64>,
+}
+
+impl DopplerRig {
+    // Retune in 10 Hz steps to avoid flooding the rig with commands
+    const TUNING_STEP_HZ: f64 = 10.0;
+
+    pub fn new(client: RigctlClient, freq_hz: f64) -> Self {
+        DopplerRig { client, freq_hz, tuned_shift_hz: None }
+    }
+
+    /// Retune when the shift has moved by a tuning step since the last command
+    pub fn update(&mut self, shift_hz: f64) -> Result<(), String> {
+        if self
+            .tuned_shift_hz
+            .is_none_or(|tuned| (shift_hz - tuned).abs() >= Self::TUNING_STEP_HZ)
+        {
+            debug!("retune for Doppler"; shift_hz = format!("{:+.0}", shift_hz));
+            self.client.set_frequency(self.freq_hz + shift_hz)?;
+            self.tuned_shift_hz = Some(shift_hz);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// A tiny rigctld stand-in that remembers the frequency and mode
+    fn fake_rigctld() -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut received = Vec::new();
+            let mut freq = "145000000".to_string();
+            for line in BufReader::new(stream).lines() {
+                let line = line.unwrap();
+                let reply = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["F", hz] => {
+                        freq = hz.to_string();
+                        "RPRT 0".to_string()
+                    }
+                    ["f"] => freq.clone(),
+                    ["M", "CW", _] => "RPRT 0".to_string(),
+                    _ => "RPRT -1".to_string(),
+                };
+                received.push(line);
+                writeln!(writer, "{}", reply).unwrap();
+            }
+            received
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_rigctl_commands() {
+        let (addr, handle) = fake_rigctld();
+        let mut rig = RigctlClient::connect(&addr).unwrap();
+        rig.set_mode("CW", 0).unwrap();
+        rig.set_frequency(437_384_212.4).unwrap();
+        assert_eq!(rig.frequency().unwrap(), 437_384_212.0);
+        assert!(rig.set_mode("FM", 0).is_err());
+        drop(rig);
+
+        let received = handle.join().unwrap();
+        assert_eq!(received, vec!["M CW 0", "F 437384212", "f", "M FM 0"]);
+    }
+
+    #[test]
+    fn test_doppler_rig_steps() {
+        let (addr, handle) = fake_rigctld();
+        let mut rig = DopplerRig::new(RigctlClient::connect(&addr).unwrap(), 437_375_000.0);
+        for shift in [9000.0, 8995.0, 8990.0, 8975.0] {
+            rig.update(shift).unwrap();
+        }
+        drop(rig);
+
+        let received = handle.join().unwrap();
+        assert_eq!(received, vec!["F 437384000", "F 437383990", "F 437383975"]);
+    }
+}
@@ -0,0 +1,44 @@
+// Small seeded random number generator for synthetic traffic, practice drills and fuzzing:
+// xorshift64*, so a seed reproduces the same sequence exactly. Not for anything secret.
+
+/// xorshift64*
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    /// A seed from the clock, for runs that need not be reproducible
+    pub fn seed_from_time() -> u64 {
+        crate::timeutil::now_unix().to_bits()
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Uniform in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+
+    /// Normally distributed noise with standard deviation `sigma`
+    pub fn noise(&mut self, sigma: f64) -> f64 {
+        let (u, v) = (1.0 - self.unit(), self.unit());
+        sigma * (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+}
@@ -0,0 +1,253 @@
+// Append-mode output files for months of unattended operation. The file is closed and renamed
+// once it reaches a size or at the end of each UTC day, and only the newest renamed files are
+// kept. A renamed file carries the date (daily) or the time of rotation (by size) before its
+// extension, e.g. decodes.2024-05-01.jsonl or decodes.2024-05-01T123456.jsonl, and files rotated
+// in the same second are numbered after it, decodes.2024-05-01T123456-2.jsonl and on. Files are
+// pruned oldest first by that label and number, as the names alone do not sort in time order.
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::num::IntErrorKind;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use crate::logging::warn;
+use crate::timeutil;
+
+/// When to start a new file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Never,
+    Daily,
+    Size(u64),    // Bytes
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        match lower.as_str() {
+            "never" => return Ok(Rotation::Never),
+            "daily" => return Ok(Rotation::Daily),
+            _ => {}
+        }
+        let (number, scale) = match lower.trim_end_matches('b').char_indices().last() {
+            Some((i, 'k')) => (&lower[..i], 1 << 10),
+            Some((i, 'm')) => (&lower[..i], 1 << 20),
+            Some((i, 'g')) => (&lower[..i], 1 << 30),
+            _ => (lower.trim_end_matches('b'), 1),
+        };
+        match number.parse::<u64>() {
+            Ok(size) if size > 0 => size
+                .checked_mul(scale)
+                .map(Rotation::Size)
+                .ok_or_else(|| format!("Rotation size '{}' is too large", s)),
+            Err(e) if *e.kind() == IntErrorKind::PosOverflow => Err(format!("Rotation size '{}' is too large", s)),
+            _ => Err(format!("Unknown rotation '{}'. Expected never, daily or a size such as 10M", s)),
+        }
+    }
+}
+
+/// A file appended to, rotated by `rotation`, keeping `keep` rotated files
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    keep: usize,
+    file: File,
+    size: u64,
+    day: i64,    // UTC day of the first line in the file
+}
+
+fn day_of(unix: f64) -> i64 {
+    (unix / 86400.0).floor() as i64
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: Rotation, keep: usize) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+        }
+        let file = File::options().create(true).append(true).open(path).map_err(|e| format!("Cannot open '{}': {}", path.display(), e))?;
+        let metadata = file.metadata().map_err(|e| format!("Cannot open '{}': {}", path.display(), e))?;
+        // An existing file started on the day it was last written, as far as we can tell
+        let modified = metadata.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs_f64());
+        let day = day_of(modified.filter(|_| metadata.len() > 0).unwrap_or_else(timeutil::now_unix));
+        Ok(RotatingFile { path: path.to_path_buf(), rotation, keep, file, size: metadata.len(), day })
+    }
+
+    /// Append one line, first rotating if it is due at `now`
+    pub fn write_line(&mut self, line: &str, now: f64) -> io::Result<()> {
+        self.write_record(format!("{}\n", line).as_bytes(), now)
+    }
+
+    /// Append one record as it is, e.g. a binary message, first rotating if it is due at `now`
+    pub fn write_record(&mut self, record: &[u8], now: f64) -> io::Result<()> {
+        let due = self.size > 0
+            && match self.rotation {
+                Rotation::Never => false,
+                Rotation::Daily => day_of(now) != self.day,
+                Rotation::Size(max) => self.size + record.len() as u64 > max,
+            };
+        if due {
+            self.rotate(now)?;
+        }
+        if self.size == 0 {
+            self.day = day_of(now);
+        }
+        self.file.write_all(record)?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    /// Label of the file rotated at `now`
+    fn label(&self, now: f64) -> String {
+        match self.rotation {
+            Rotation::Daily => timeutil::format_utc(self.day as f64 * 86400.0)[..10].to_string(),
+            _ => timeutil::format_utc(now).replace(':', "").trim_end_matches('Z').to_string(),
+        }
+    }
+
+    /// Name of the rotated file with `label`; `n` tells apart files rotated in the same second
+    fn rotated_path(&self, label: &str, n: usize) -> PathBuf {
+        let label = if n > 1 { format!("{}-{}", label, n) } else { label.to_string() };
+        let name = match (self.path.file_stem(), self.path.extension()) {
+            (Some(stem), Some(ext)) => format!("{}.{}.{}", stem.to_string_lossy(), label, ext.to_string_lossy()),
+            _ => format!("{}.{}", self.path.file_name().unwrap_or_default().to_string_lossy(), label),
+        };
+        self.path.with_file_name(name)
+    }
+
+    fn rotate(&mut self, now: f64) -> io::Result<()> {
+        self.file.flush()?;
+        // Numbered after the last file of the same second, even once older ones were pruned
+        let label = self.label(now);
+        let rotated = self.rotated()?;
+        let n = rotated.iter().filter(|((l, _), _)| *l == label).map(|((_, n), _)| n + 1).max().unwrap_or(1);
+        fs::rename(&self.path, self.rotated_path(&label, n))?;
+        self.file = File::options().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        if let Err(error) = self.prune() {
+            warn!("Cannot remove old rotated files"; error = error, file = self.path.display());
+        }
+        Ok(())
+    }
+
+    /// Rotated files, oldest first, with their label and number
+    fn rotated(&self) -> io::Result<Vec<((String, usize), PathBuf)>> {
+        let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let ext = self.path.extension().map(|ext| format!(".{}", ext.to_string_lossy()));
+        let mut rotated: Vec<((String, usize), PathBuf)> = fs::read_dir(dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                // stem.<label>[-n][.ext], with a label starting with the year
+                let rest = name.strip_prefix(&format!("{}.", stem))?;
+                let label = match &ext {
+                    Some(ext) => rest.strip_suffix(ext.as_str())?,
+                    None => rest,
+                };
+                if label.len() < 10 || !label.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+                let key = match label.get(10..)?.rsplit_once('-') {
+                    Some((time, n)) => (format!("{}{}", &label[..10], time), n.parse().ok()?),
+                    None => (label.to_string(), 1),
+                };
+                Some((key, path))
+            })
+            .collect();
+        rotated.sort();
+        Ok(rotated)
+    }
+
+    /// Delete all but the newest `keep` rotated files
+    fn prune(&self) -> io::Result<()> {
+        let rotated = self.rotated()?;
+        let excess = rotated.len().saturating_sub(self.keep);
+        for (_, path) in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_rotation() {
+        assert_eq!("10M".parse(), Ok(Rotation::Size(10 << 20)));
+        assert_eq!("512kb".parse(), Ok(Rotation::Size(512 << 10)));
+        assert_eq!("100".parse(), Ok(Rotation::Size(100)));
+        assert_eq!("Daily".parse(), Ok(Rotation::Daily));
+        assert!("0".parse::<Rotation>().is_err());
+        assert!("weekly".parse::<Rotation>().is_err());
+        assert_eq!("17179869184G".parse::<Rotation>(), Err("Rotation size '17179869184G' is too large".to_string()));
+        assert!("99999999999999999999G".parse::<Rotation>().unwrap_err().contains("too large"));
+        assert_eq!("17179869183G".parse(), Ok(Rotation::Size(17179869183 << 30)));
+    }
+
+    #[test]
+    fn test_daily() {
+        let dir = std::env::temp_dir().join(format!("botan_rotate_daily_{}", std::process::id()));
+        let path = dir.join("decodes.jsonl");
+        let mut file = RotatingFile::open(&path, Rotation::Daily, 2).unwrap();
+        let day = 1714521600.0;    // 2024-05-01
+        for n in 0..4 {
+            file.write_line(&format!("{{\"day\": {}}}", n), day + n as f64 * 86400.0 + 3600.0).unwrap();
+            file.write_line("{}", day + n as f64 * 86400.0 + 7200.0).unwrap();
+        }
+        // The first day was pruned
+        assert_eq!(names(&dir), ["decodes.2024-05-02.jsonl", "decodes.2024-05-03.jsonl", "decodes.jsonl"]);
+        assert_eq!(fs::read_to_string(dir.join("decodes.2024-05-03.jsonl")).unwrap(), "{\"day\": 2}\n{}\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_size() {
+        let dir = std::env::temp_dir().join(format!("botan_rotate_size_{}", std::process::id()));
+        let path = dir.join("decodes.csv");
+        let mut file = RotatingFile::open(&path, Rotation::Size(10), 5).unwrap();
+        for _ in 0..3 {
+            file.write_line("12345678", 1714566896.0).unwrap();
+        }
+        assert_eq!(names(&dir), ["decodes.2024-05-01T123456-2.csv", "decodes.2024-05-01T123456.csv", "decodes.csv"]);
+        // Newer files of the same second sort after older ones
+        assert_eq!(file.rotated().unwrap().iter().map(|((_, n), _)| *n).collect::<Vec<_>>(), [1, 2]);
+        // Reopening continues the current file
+        let mut file = RotatingFile::open(&path, Rotation::Size(20), 5).unwrap();
+        file.write_line("1", 1714566896.0).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12345678\n1\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_same_second() {
+        let dir = std::env::temp_dir().join(format!("botan_rotate_prune_{}", std::process::id()));
+        let path = dir.join("decodes.csv");
+        let mut file = RotatingFile::open(&path, Rotation::Size(2), 3).unwrap();
+        // One line a file, all rotated in the same second
+        for n in 1..=12 {
+            file.write_line(&n.to_string(), 1714566896.0).unwrap();
+        }
+        assert_eq!(
+            names(&dir),
+            ["decodes.2024-05-01T123456-10.csv", "decodes.2024-05-01T123456-11.csv", "decodes.2024-05-01T123456-9.csv", "decodes.csv"]
+        );
+        // The newest rotated files are the ones kept
+        let kept: Vec<String> = file.rotated().unwrap().iter().map(|(_, path)| fs::read_to_string(path).unwrap()).collect();
+        assert_eq!(kept, ["9\n", "10\n", "11\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+// Minimal binding to librtlsdr, enabled with the `rtlsdr` cargo feature
+
+use std::ffi::c_void;
+use std::os::raw::c_int;
+
+use crate::logging::{debug, trace};
+
+#[repr(C)]
+struct RtlSdrDev {
+    _private: [u8; 0],
+}
+
+#[link(name = "rtlsdr")]
+extern "C" {
+    fn rtlsdr_get_device_count() -> u32;
+    fn rtlsdr_open(dev: *mut *mut RtlSdrDev, index: u32) -> c_int;
+    fn rtlsdr_close(dev: *mut RtlSdrDev) -> c_int;
+    fn rtlsdr_set_center_freq(dev: *mut RtlSdrDev, freq: u32) -> c_int;
+    fn rtlsdr_set_freq_correction(dev: *mut RtlSdrDev, ppm: c_int) -> c_int;
+    fn rtlsdr_set_sample_rate(dev: *mut RtlSdrDev, rate: u32) -> c_int;
+    fn rtlsdr_set_tuner_gain_mode(dev: *mut RtlSdrDev, manual: c_int) -> c_int;
+    fn rtlsdr_set_tuner_gain(dev: *mut RtlSdrDev, gain: c_int) -> c_int;
+    fn rtlsdr_reset_buffer(dev: *mut RtlSdrDev) -> c_int;
+    fn rtlsdr_read_sync(dev: *mut RtlSdrDev, buf: *mut c_void, len: c_int, n_read: *mut c_int) -> c_int;
+}
+
+/// Receiver settings for an RTL-SDR dongle
+#[derive(Debug, Clone)]
+pub struct RtlSdrConfig {
+    pub device_index: u32,
+    pub center_freq_hz: u32,
+    pub sample_rate: u32,
+    pub gain_db: Option<f64>,  // None selects tuner AGC
+    pub ppm: i32,
+}
+
+/// An open RTL-SDR device, closed on drop
+pub struct RtlSdr {
+    dev: *mut RtlSdrDev,
+}
+
+impl RtlSdr {
+    pub fn device_count() -> u32 {
+        unsafe { rtlsdr_get_device_count() }
+    }
+
+    /// Open and configure the device, leaving it ready for `read_sync`
+    pub fn open(config: &RtlSdrConfig) -> Result<Self, String> {
+        if config.device_index >= Self::device_count() {
+            return Err(format!("RTL-SDR device {} not found", config.device_index));
+        }
+
+        let mut dev = std::ptr::null_mut();
+        check(unsafe { rtlsdr_open(&mut dev, config.device_index) }, "open device")?;
+        let sdr = RtlSdr { dev };
+        debug!("opened RTL-SDR"; device = config.device_index, rate = config.sample_rate, freq = config.center_freq_hz);
+
+        check(unsafe { rtlsdr_set_sample_rate(sdr.dev, config.sample_rate) }, "set sample rate")?;
+        // librtlsdr returns -2 when the correction is unchanged, which is not an error for us
+        if config.ppm != 0 {
+            check(unsafe { rtlsdr_set_freq_correction(sdr.dev, config.ppm) }, "set ppm correction")?;
+        }
+        check(unsafe { rtlsdr_set_center_freq(sdr.dev, config.center_freq_hz) }, "set frequency")?;
+        match config.gain_db {
+            Some(gain) => {
+                check(unsafe { rtlsdr_set_tuner_gain_mode(sdr.dev, 1) }, "set manual gain")?;
+                // Gain is given to librtlsdr in tenths of a dB
+                let tenths = (gain * 10.0).round() as c_int;
+                check(unsafe { rtlsdr_set_tuner_gain(sdr.dev, tenths) }, "set gain")?;
+            }
+            None => check(unsafe { rtlsdr_set_tuner_gain_mode(sdr.dev, 0) }, "set automatic gain")?,
+        }
+        check(unsafe { rtlsdr_reset_buffer(sdr.dev) }, "reset buffer")?;
+        Ok(sdr)
+    }
+
+    /// Blocking read of raw cu8 IQ bytes; `buf` length should be a multiple of 512
+    pub fn read_sync(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        let mut n_read: c_int = 0;
+        check(
+            unsafe {
+                rtlsdr_read_sync(self.dev, buf.as_mut_ptr() as *mut c_void, buf.len() as c_int, &mut n_read)
+            },
+            "read samples",
+        )?;
+        trace!("read samples"; bytes = n_read);
+        Ok(n_read.max(0) as usize)
+    }
+}
+
+impl Drop for RtlSdr {
+    fn drop(&mut self) {
+        unsafe {
+            rtlsdr_close(self.dev);
+        }
+    }
+}
+
+fn check(status: c_int, action: &str) -> Result<(), String> {
+    if status < 0 {
+        Err(format!("RTL-SDR failed to {} (error {})", action, status))
+    } else {
+        Ok(())
+    }
+}
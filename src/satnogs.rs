@@ -0,0 +1,161 @@
+// Frame exports of SatNOGS DB, the community telemetry archive. Two layouts are read: the CSV
+// from a satellite's "Download data" page, one "timestamp|hex frame" line per frame, and the
+// JSON list of the telemetry API, whose entries also name the observer and the observation.
+// A frame is a raw AX.25 frame, text copied from CW, or the bare data block, whichever it holds.
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::ax25;
+use crate::botan_parser;
+use crate::timeutil;
+
+/// Frames received further apart than this are filed as separate passes
+pub const PASS_GAP_SECONDS: f64 = 900.0;
+
+/// One frame of an export
+#[derive(Debug, Clone, PartialEq)]
+pub struct SatnogsFrame {
+    pub time: f64,
+    pub frame: Vec<u8>,
+    pub observer: Option<String>,    // Call sign and locator, e.g. "JA1XYZ-PM95"
+    pub observation: Option<u64>,
+}
+
+/// An entry of the telemetry API; other fields are ignored
+#[derive(Deserialize)]
+struct ApiFrame {
+    timestamp: String,
+    frame: String,
+    observer: Option<String>,
+    observation_id: Option<u64>,
+}
+
+/// The API's paginated response wraps the list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ApiExport {
+    List(Vec<ApiFrame>),
+    Page { results: Vec<ApiFrame> },
+}
+
+/// Read an export in either layout, sorted by time
+pub fn read_export(content: &str) -> Result<Vec<SatnogsFrame>, String> {
+    let mut frames = Vec::new();
+    if content.trim_start().starts_with(['[', '{']) {
+        let export: ApiExport = serde_json::from_str(content).map_err(|e| format!("Invalid SatNOGS JSON: {}", e))?;
+        let (ApiExport::List(list) | ApiExport::Page { results: list }) = export;
+        for entry in list {
+            frames.push(SatnogsFrame {
+                time: timeutil::parse_utc(&entry.timestamp)?,
+                frame: parse_hex(&entry.frame)?,
+                observer: entry.observer.filter(|o| !o.is_empty()),
+                observation: entry.observation_id,
+            });
+        }
+    } else {
+        for (number, line) in content.lines().enumerate() {
+            let Some((time, hex)) = line.split_once('|') else {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err(format!("Line {}: expected timestamp|frame", number + 1));
+            };
+            let Ok(time) = timeutil::parse_utc(time) else {
+                // A header line
+                if number == 0 {
+                    continue;
+                }
+                return Err(format!("Line {}: invalid timestamp '{}'", number + 1, time));
+            };
+            let frame = parse_hex(hex).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+            frames.push(SatnogsFrame { time, frame, observer: None, observation: None });
+        }
+    }
+    frames.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(frames)
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) {
+        return Err(format!("Invalid frame '{}': odd number of hex digits", hex));
+    }
+    botan_parser::parse_hex_data(hex)
+}
+
+/// The beacon line a frame holds
+pub fn beacon_line(frame: &[u8]) -> String {
+    if let Ok(ui) = ax25::parse_ui(frame, false) {
+        return ui.beacon_line();
+    }
+    if let Some(start) = std::str::from_utf8(frame).ok().and_then(|text| text.find("BOTAN")) {
+        return String::from_utf8_lossy(&frame[start..]).trim_end().to_string();
+    }
+    let hex: String = frame.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("BOTAN JS1YPT {}", hex)
+}
+
+/// Split time-sorted frames into passes: by observation where the export names them, otherwise
+/// wherever frames are more than `PASS_GAP_SECONDS` apart
+pub fn group_passes(frames: Vec<SatnogsFrame>) -> Vec<Vec<SatnogsFrame>> {
+    let mut observations: BTreeMap<u64, Vec<SatnogsFrame>> = BTreeMap::new();
+    let mut passes: Vec<Vec<SatnogsFrame>> = Vec::new();
+    for frame in frames {
+        if let Some(id) = frame.observation {
+            observations.entry(id).or_default().push(frame);
+            continue;
+        }
+        match passes.last_mut() {
+            Some(pass) if frame.time - pass[pass.len() - 1].time <= PASS_GAP_SECONDS => pass.push(frame),
+            _ => passes.push(vec![frame]),
+        }
+    }
+    passes.extend(observations.into_values());
+    passes.sort_by(|a, b| a[0].time.total_cmp(&b[0].time));
+    passes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_csv() {
+        let text: String = b"BOTAN JS1YPT A67C8D5E2AA13608".iter().map(|b| format!("{:02X}", b)).collect();
+        let csv = format!("timestamp|frame\n2024-05-01 12:40:00|A57EB76823210E08\n\n2024-05-01 12:35:56|{}\n", text);
+        let frames = read_export(&csv).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].time, 1714566956.0);
+        assert_eq!(beacon_line(&frames[0].frame), "BOTAN JS1YPT A67C8D5E2AA13608");
+        assert_eq!(beacon_line(&frames[1].frame), "BOTAN JS1YPT A57EB76823210E08");
+
+        assert_eq!(read_export("2024-05-01 12:40:00|A57\n").unwrap_err(), "Line 1: Invalid frame 'A57': odd number of hex digits");
+        assert!(read_export("2024-05-01 12:40:00|A5\nyesterday|A5\n").unwrap_err().starts_with("Line 2: invalid timestamp"));
+    }
+
+    #[test]
+    fn test_read_json() {
+        let json = r#"{"count": 2, "results": [
+            {"sat_id": "X", "frame": "A57EB76823210E08", "observer": "JA1XYZ-PM95", "timestamp": "2024-05-01T13:00:00Z", "observation_id": 8},
+            {"sat_id": "X", "frame": "A67C8D5E2AA13608", "observer": "", "timestamp": "2024-05-01T12:59:00Z", "observation_id": 7}
+        ]}"#;
+        let frames = read_export(json).unwrap();
+        assert_eq!(frames[0].observer, None);
+        assert_eq!(frames[1].observer.as_deref(), Some("JA1XYZ-PM95"));
+        // Different observations of the same pass are kept apart
+        assert_eq!(group_passes(frames).len(), 2);
+        assert!(read_export("[{\"frame\": \"00\"}]").is_err());
+    }
+
+    #[test]
+    fn test_group_passes() {
+        let frame = |time, observation| SatnogsFrame { time, frame: vec![], observer: None, observation };
+        let passes = group_passes(vec![frame(0.0, None), frame(600.0, None), frame(1400.0, None), frame(90000.0, None)]);
+        assert_eq!(passes.iter().map(Vec::len).collect::<Vec<_>>(), [3, 1]);
+
+        // Two stations receiving the same pass
+        let passes = group_passes(vec![frame(0.0, Some(1)), frame(10.0, Some(2)), frame(20.0, Some(1)), frame(30.0, Some(2))]);
+        assert_eq!(passes.iter().map(|p| p.iter().map(|f| f.time).collect()).collect::<Vec<Vec<f64>>>(), [[0.0, 20.0], [10.0, 30.0]]);
+    }
+}
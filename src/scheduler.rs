@@ -0,0 +1,231 @@
+// Per-pass result files written by the `schedule` daemon, and by `import` for frames from SatNOGS:
+// <output dir>/<AOS, e.g. 20240501T123456Z>/pass.json (+ audio.wav when recording)
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::botan_parser::BotanBeaconData;
+use crate::frames::{Frame, TypedFrame};
+use crate::migrate;
+use crate::orbit::{Illumination, OrbitState, Pass};
+use crate::provenance::Provenance;
+use crate::timeutil;
+use crate::wav;
+
+/// One beacon decoded during a scheduled pass
+#[derive(Debug, Serialize)]
+pub struct PassBeacon {
+    pub time: String,
+    pub text: String,
+    pub data: Option<BotanBeaconData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typed: Option<TypedFrame>,   // A frame of a configured type other than housekeeping
+    pub error: Option<String>,
+    pub illumination: Option<Illumination>,   // Not known for imported frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<u64>,                   // Orbit number, with a TLE that has a revolution number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observer: Option<String>,             // Station that received an imported frame
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub receptions: Vec<Reception>,           // Every station's copy, when merged stations heard it more than once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,       // How the frame was decoded
+}
+
+/// One station's copy of a frame heard by several, in a merged history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reception {
+    pub observer: String,
+    pub time_offset: f64,          // After the first reception [s]
+    pub raw_rssi: Option<u8>,      // SI bytes, where the station's receiver reports them
+    pub raw_snr: Option<u8>,
+}
+
+/// Everything received during one scheduled pass, saved as pass.json
+#[derive(Debug, Serialize)]
+pub struct PassRecord {
+    pub schema_version: u64,       // See migrate
+    pub aos: String,
+    pub los: String,
+    pub max_elevation_deg: Option<f64>,
+    pub frequency_hz: Option<f64>,
+    pub wpm: Option<f64>,          // Keying speed the demodulator settled on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,    // Where imported frames came from
+    pub beacons: Vec<PassBeacon>,
+}
+
+impl PassRecord {
+    pub fn new(pass: &Pass, frequency_hz: Option<f64>) -> Self {
+        PassRecord {
+            schema_version: migrate::SCHEMA_VERSION,
+            aos: timeutil::format_utc(pass.aos_unix),
+            los: timeutil::format_utc(pass.los_unix),
+            max_elevation_deg: Some(pass.max_elevation_deg),
+            frequency_hz,
+            wpm: None,
+            source: None,
+            beacons: Vec::new(),
+        }
+    }
+
+    /// A pass of frames received elsewhere, spanning `aos_unix` to `los_unix`
+    pub fn imported(aos_unix: f64, los_unix: f64, source: &str) -> Self {
+        PassRecord {
+            schema_version: migrate::SCHEMA_VERSION,
+            aos: timeutil::format_utc(aos_unix),
+            los: timeutil::format_utc(los_unix),
+            max_elevation_deg: None,
+            frequency_hz: None,
+            wpm: None,
+            source: Some(source.to_string()),
+            beacons: Vec::new(),
+        }
+    }
+
+    /// File a beacon received at `unix` with its parse result; lines that failed are kept with the error
+    pub fn add_beacon(
+        &mut self,
+        unix: f64,
+        text: &str,
+        orbit: Option<OrbitState>,
+        parsed: Result<Frame, String>,
+    ) -> &mut PassBeacon {
+        let (data, typed, error) = match parsed {
+            Ok(Frame::Housekeeping(data)) => (Some(data), None, None),
+            Ok(Frame::Typed(typed)) => (None, Some(typed), None),
+            Err(error) => (None, None, Some(error)),
+        };
+        self.beacons.push(PassBeacon {
+            time: timeutil::format_utc(unix),
+            text: text.to_string(),
+            data,
+            typed,
+            error,
+            illumination: orbit.map(|o| o.illumination),
+            orbit: orbit.and_then(|o| o.number),
+            observer: None,
+            receptions: Vec::new(),
+            provenance: None,
+        });
+        self.beacons.last_mut().unwrap()
+    }
+}
+
+/// Directory of the pass with the given AOS under `root`
+pub fn pass_directory(root: &Path, aos_unix: f64) -> PathBuf {
+    let name: String = timeutil::format_utc(aos_unix).chars().filter(|c| *c != '-' && *c != ':').collect();
+    root.join(name)
+}
+
+/// The pass.json of a saved pass, picked by its directory name (e.g. 20240501T123456Z) or its AOS
+pub fn find_pass(root: &Path, id: &str) -> Result<PathBuf, String> {
+    let by_name = root.join(id).join("pass.json");
+    if by_name.is_file() {
+        return Ok(by_name);
+    }
+    let by_aos = timeutil::parse_utc(id).ok().map(|aos| pass_directory(root, aos).join("pass.json"));
+    by_aos.filter(|path| path.is_file()).ok_or_else(|| format!("No pass '{}' under '{}'", id, root.display()))
+}
+
+/// Read a pass.json in the current schema, whichever version wrote it
+pub fn load_pass(path: &Path) -> Result<Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| e.to_string())
+        .and_then(migrate::upgrade_pass)
+        .map_err(|e| format!("Invalid pass file '{}': {}", path.display(), e))
+}
+
+/// Rewrite a pass.json written with an older schema in the current one, unless `dry_run`;
+/// returns the version it had, or None when it was current already
+pub fn upgrade_pass_file(path: &Path, dry_run: bool) -> Result<Option<u64>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+    let invalid = |e: String| format!("Invalid pass file '{}': {}", path.display(), e);
+    let pass: Value = serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+    let version = migrate::version(&pass).map_err(invalid)?;
+    if version == migrate::SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let pass = migrate::upgrade_pass(pass).map_err(invalid)?;
+    if !dry_run {
+        let json = serde_json::to_string_pretty(&pass).map_err(|e| e.to_string())?;
+        // Write then rename so an interrupted upgrade leaves the old file whole
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json + "\n").map_err(|e| format!("Cannot write '{}': {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+    }
+    Ok(Some(version))
+}
+
+/// Write pass.json and, if given, the recorded audio; returns the pass directory
+pub fn save_pass(root: &Path, aos_unix: f64, record: &PassRecord, audio: Option<(u32, &[i16])>) -> Result<PathBuf, String> {
+    let dir = pass_directory(root, aos_unix);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+
+    let json = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+    let path = dir.join("pass.json");
+    fs::write(&path, json + "\n").map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+
+    if let Some((sample_rate, samples)) = audio {
+        let path = dir.join("audio.wav");
+        wav::write_wav_mono16(&path.to_string_lossy(), sample_rate, samples)
+            .map_err(|e| format!("Cannot write '{}': {}", path.display(), e))?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_save_pass() {
+        let pass = Pass {
+            aos_unix: 1714566896.0,
+            los_unix: 1714567496.0,
+            max_elevation_unix: 1714567196.0,
+            max_elevation_deg: 42.5,
+            aos_azimuth_deg: 10.0,
+            los_azimuth_deg: 170.0,
+        };
+        let mut record = PassRecord::new(&pass, Some(437375000.0));
+        for (unix, text, illumination) in [
+            (1714567000.0, "BOTAN JS1YPT A67C8D5E2AA13608", Illumination::Sunlight),
+            (1714567100.0, "BOTAN JS1YPT A67C", Illumination::Eclipse),
+        ] {
+            let orbit = OrbitState { illumination, number: Some(1234) };
+            record.add_beacon(unix, text, Some(orbit), parse_botan_beacon(text).map(Frame::Housekeeping));
+        }
+
+        let root = std::env::temp_dir().join(format!("botan_pass_test_{}", std::process::id()));
+        let dir = save_pass(&root, pass.aos_unix, &record, Some((8000, &[0, 1, -1]))).unwrap();
+        assert!(dir.ends_with("20240501T123456Z"));
+        assert!(dir.join("audio.wav").exists());
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("pass.json")).unwrap()).unwrap();
+        assert_eq!(saved["aos"], "2024-05-01T12:34:56Z");
+        assert_eq!(saved["beacons"][0]["data"]["call_sign"], "JS1YPT");
+        assert_eq!(saved["beacons"][0]["illumination"], "sunlight");
+        assert_eq!(saved["beacons"][0]["orbit"], 1234);
+        assert!(saved["beacons"][1]["data"].is_null());
+        assert!(saved["beacons"][1]["error"].is_string());
+        assert_eq!(saved["schema_version"], migrate::SCHEMA_VERSION);
+
+        // A pass saved before schema versions reads as a current one, and is upgraded on disk
+        let path = dir.join("pass.json");
+        let old = r#"{"aos": "2024-05-01T12:34:56Z", "wpm": 0.0, "beacons": [{"data": {"rssi": {"rssi_dbm": 134.0, "snr_db": 64.0}}}]}"#;
+        fs::write(&path, old).unwrap();
+        assert_eq!(load_pass(&path).unwrap()["beacons"][0]["data"]["rssi"]["raw_rssi"], 134);
+        assert_eq!(upgrade_pass_file(&path, true).unwrap(), Some(1));
+        assert_eq!(fs::read_to_string(&path).unwrap(), old);
+        assert_eq!(upgrade_pass_file(&path, false).unwrap(), Some(1));
+        assert_eq!(upgrade_pass_file(&path, false).unwrap(), None);
+        assert_eq!(load_pass(&path).unwrap()["schema_version"], migrate::SCHEMA_VERSION);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+// JSON Schema of a decoded frame, as it appears in templates, pass.json files (`data` and
+// `typed`) and :save output: a housekeeping beacon or a frame of a configured type. The schema is
+// written out by hand to match the Serialize derives; the tests check it against serialized
+// frames so a field added to one of the structs fails until it is described here too.
+use serde_json::{json, Value};
+
+fn number(description: &str) -> Value {
+    json!({"type": "number", "description": description})
+}
+
+fn flag(description: &str) -> Value {
+    json!({"type": "boolean", "description": description})
+}
+
+fn counter(max: u8, description: &str) -> Value {
+    json!({"type": "integer", "minimum": 0, "maximum": max, "description": description})
+}
+
+fn nullable(mut schema: Value) -> Value {
+    let kind = schema["type"].take();
+    schema["type"] = json!([kind, "null"]);
+    schema
+}
+
+/// The schema of one decoded frame
+pub fn schema() -> Value {
+    let hex = |description: &str| json!({"type": "string", "pattern": "^([0-9A-F]{2})+$", "description": description});
+    let unit_fraction = |description: &str| nullable(json!({"type": "number", "minimum": 0, "maximum": 1, "description": description}));
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Decoded BOTAN frame",
+        "description": "A housekeeping beacon, or a frame of a type configured with --frame-types",
+        "oneOf": [{"$ref": "#/$defs/beacon"}, {"$ref": "#/$defs/typed_frame"}],
+        "$defs": {
+            "beacon": {
+                "type": "object",
+                "description": "An 8-byte housekeeping beacon",
+                "properties": {
+                    "satellite_name": {"type": "string"},
+                    "call_sign": {"type": "string"},
+                    "rssi": {"oneOf": [{"$ref": "#/$defs/rssi"}, {"type": "null"}]},
+                    "frame": {"type": "string", "pattern": "^[0-9A-F]{16}$", "description": "The 8 telemetry bytes as hex"},
+                    "telemetry": {"$ref": "#/$defs/telemetry"},
+                    "confidence": {"$ref": "#/$defs/confidence"},
+                },
+                "required": ["satellite_name", "call_sign", "rssi", "frame", "telemetry"],
+                "additionalProperties": false,
+            },
+            "typed_frame": {
+                "type": "object",
+                "description": "A frame of a configured type other than housekeeping",
+                "properties": {
+                    "satellite_name": {"type": "string"},
+                    "call_sign": {"type": "string"},
+                    "frame_type": {"type": "string", "description": "Name of the type in the --frame-types file"},
+                    "rssi": {"oneOf": [{"$ref": "#/$defs/rssi"}, {"type": "null"}]},
+                    "frame": hex("The data bytes as hex"),
+                    "check": {
+                        "type": "object",
+                        "description": "Outcome of the type's integrity check",
+                        "properties": {
+                            "codec": {"type": "string"},
+                            "received": {"type": "integer", "minimum": 0},
+                            "computed": {"type": "integer", "minimum": 0},
+                            "passed": {"type": "boolean"},
+                        },
+                        "required": ["codec", "received", "computed", "passed"],
+                        "additionalProperties": false,
+                    },
+                    "fields": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string"},
+                                "raw": {"type": "integer"},
+                                "value": number("raw * scale + offset"),
+                                "unit": nullable(json!({"type": "string"})),
+                            },
+                            "required": ["name", "raw", "value", "unit"],
+                            "additionalProperties": false,
+                        },
+                    },
+                    "confidence": {"$ref": "#/$defs/confidence"},
+                },
+                "required": ["satellite_name", "call_sign", "frame_type", "rssi", "frame", "fields"],
+                "additionalProperties": false,
+            },
+            "rssi": {
+                "type": "object",
+                "description": "The SI signal report",
+                "properties": {
+                    "raw_rssi": counter(255, "First SI byte"),
+                    "raw_snr": counter(255, "Second SI byte"),
+                    "rssi_dbm": nullable(number("RSSI in dBm, once a conversion is configured")),
+                    "snr_db": nullable(number("SNR in dB, once a conversion is configured")),
+                },
+                "required": ["raw_rssi", "raw_snr", "rssi_dbm", "snr_db"],
+                "additionalProperties": false,
+            },
+            "telemetry": {
+                "type": "object",
+                "properties": {
+                    "bat_v": number("Battery voltage [V]"),
+                    "bat_i": number("Battery current [mA], positive while charging"),
+                    "bat_t": number("Battery temperature [°C]"),
+                    "bpb_t": number("Circuit board temperature [°C]"),
+                    "raw_i": number("Current consumption [mA]"),
+                    "data1": {
+                        "type": "object",
+                        "description": "Byte 6: power system status",
+                        "properties": {
+                            "power_5v0": flag("5 V line on"),
+                            "power_depant": flag("Antenna deployment line on"),
+                            "power_com": flag("Transponder line on"),
+                            "sap_x_pos": flag("+X panel generating"),
+                            "sap_y_pos": flag("+Y panel generating"),
+                            "sap_y_neg": flag("-Y panel generating"),
+                            "sap_z_pos": flag("+Z panel generating"),
+                            "sap_z_neg": flag("-Z panel generating"),
+                        },
+                        "required": ["power_5v0", "power_depant", "power_com", "sap_x_pos", "sap_y_pos", "sap_y_neg", "sap_z_pos", "sap_z_neg"],
+                        "additionalProperties": false,
+                    },
+                    "data2": {
+                        "type": "object",
+                        "description": "Byte 7: command counters and KILL switch",
+                        "properties": {
+                            "reserve_cmd_counter": counter(15, "Reserved commands"),
+                            "cmd_uplink_counter": counter(7, "Commands received"),
+                            "kill_sw": flag("KILL switch set"),
+                        },
+                        "required": ["reserve_cmd_counter", "cmd_uplink_counter", "kill_sw"],
+                        "additionalProperties": false,
+                    },
+                    "data3": {
+                        "type": "object",
+                        "description": "Byte 8: mission status",
+                        "properties": {
+                            "kill_counter": counter(3, "KILL switch occurrences"),
+                            "mission_pic_on": flag("Mission PIC on"),
+                            "mis_error_flag": flag("Mission error"),
+                            "mis_end_flag": flag("Mission ended"),
+                            "aprs_flag": flag("APRS mission running"),
+                            "current_mis": counter(3, "Current mission: 0 none, 1 Earth, 2 Sun"),
+                        },
+                        "required": ["kill_counter", "mission_pic_on", "mis_error_flag", "mis_end_flag", "aprs_flag", "current_mis"],
+                        "additionalProperties": false,
+                    },
+                },
+                "required": ["bat_v", "bat_i", "bat_t", "bpb_t", "raw_i", "data1", "data2", "data3"],
+                "additionalProperties": false,
+            },
+            "confidence": {
+                "type": "object",
+                "description": "How far the frame can be trusted; components that are not known are null",
+                "properties": {
+                    "score": {"type": "number", "minimum": 0, "maximum": 1},
+                    "signal": unit_fraction("From the SNR of a calibrated signal report"),
+                    "copy": unit_fraction("Share of characters the CW demodulator read clearly"),
+                    "plausibility": unit_fraction("Share of values the hardware can report, or whether the check passed"),
+                    "agreement": unit_fraction("Similarity to recent housekeeping frames"),
+                },
+                "required": ["score", "signal", "copy", "plausibility", "agreement"],
+                "additionalProperties": false,
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::{parse_botan_beacon, SignalCalibration};
+    use crate::calibration::CalibrationTable;
+    use crate::confidence::Assessor;
+    use crate::frames::{parse_frame, Frame, FrameTypes};
+
+    /// Whether `value` conforms to `schema`, for the keywords the schema above uses
+    fn conforms(value: &Value, schema: &Value, root: &Value) -> Result<(), String> {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/$defs/");
+            return conforms(value, &root["$defs"][name], root);
+        }
+        if let Some(options) = schema["oneOf"].as_array() {
+            let matching = options.iter().filter(|option| conforms(value, option, root).is_ok()).count();
+            return if matching == 1 { Ok(()) } else { Err(format!("{} matches {} of oneOf", value, matching)) };
+        }
+        let kinds: Vec<&str> = match &schema["type"] {
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            kind => kind.as_str().into_iter().collect(),
+        };
+        let kind_matches = |kind: &str| match kind {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        };
+        if !kinds.iter().any(|kind| kind_matches(kind)) {
+            return Err(format!("{} is not {:?}", value, kinds));
+        }
+        if let (Some(number), Some(max)) = (value.as_f64(), schema["maximum"].as_f64()) {
+            if number > max {
+                return Err(format!("{} is above {}", number, max));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                if !object.contains_key(required) {
+                    return Err(format!("missing '{}'", required));
+                }
+            }
+            for (key, member) in object {
+                let property = &schema["properties"][key];
+                if property.is_null() {
+                    return Err(format!("'{}' is not in the schema", key));
+                }
+                conforms(member, property, root).map_err(|e| format!("{}: {}", key, e))?;
+            }
+        }
+        for item in value.as_array().into_iter().flatten() {
+            conforms(item, &schema["items"], root)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_frames_conform() {
+        let schema = schema();
+        let mut beacon = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        Assessor::default().assess(&mut beacon, Some(0.9));
+        let Frame::Housekeeping(data) = beacon else { panic!() };
+        conforms(&serde_json::to_value(&data).unwrap(), &schema, &schema).unwrap();
+        let data = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        conforms(&serde_json::to_value(&data).unwrap(), &schema, &schema).unwrap();
+
+        let types: FrameTypes = serde_json::from_str(
+            r#"{"summed": {"length": 3, "check": {"codec": "sum8", "field": "u8@16"}, "fields": [{"name": "count", "field": "u8@8", "unit": "n"}]}}"#,
+        )
+        .unwrap();
+        let frame = parse_frame("BOTAN JS1YPT 4D024F", &SignalCalibration::default(), &CalibrationTable::default(), &types).unwrap();
+        let Frame::Typed(typed) = frame else { panic!() };
+        conforms(&serde_json::to_value(&typed).unwrap(), &schema, &schema).unwrap();
+
+        // A field the schema does not describe is caught
+        let mut value = serde_json::to_value(&data).unwrap();
+        value["telemetry"]["bat_w"] = json!(1.0);
+        assert!(conforms(&value, &schema, &schema).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+// Serial ports, through which hardware TNCs and homebrew CW decoders hand over what they
+// receive, usually as USB serial adapters (/dev/ttyUSB0, /dev/ttyACM0). The port is opened
+// raw, 8 data bits, no parity, one stop bit and no flow control, so every byte arrives as sent.
+use std::fs::File;
+
+/// Baud rates a port can be set to
+pub const BAUD_RATES: [u32; 9] = [1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400];
+
+/// Open `device` for reading at `baud`
+#[cfg(unix)]
+pub fn open(device: &str, baud: u32) -> Result<File, String> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    let speed = match baud {
+        1200 => libc::B1200,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        _ => return Err(unsupported(baud)),
+    };
+    // Without O_NOCTTY the port could become the controlling terminal and hang us up
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(device)
+        .map_err(|e| format!("Cannot open serial port {}: {}", device, e))?;
+    let fd = file.as_raw_fd();
+    let fail = |what: &str| format!("Cannot {} serial port {}: {}", what, device, std::io::Error::last_os_error());
+    // SAFETY: termios is plain data, filled in by tcgetattr before use
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+        return Err(fail("configure"));
+    }
+    unsafe { libc::cfmakeraw(&mut termios) };
+    termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+    termios.c_cflag &= !(libc::CSTOPB | libc::CRTSCTS);
+    termios.c_cc[libc::VMIN] = 1;
+    termios.c_cc[libc::VTIME] = 0;
+    if unsafe { libc::cfsetspeed(&mut termios, speed) } != 0 {
+        return Err(fail("set the baud rate of"));
+    }
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+        return Err(fail("configure"));
+    }
+    // Drop whatever was received before the port was set up
+    unsafe { libc::tcflush(fd, libc::TCIFLUSH) };
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+pub fn open(_device: &str, baud: u32) -> Result<File, String> {
+    if !BAUD_RATES.contains(&baud) {
+        return Err(unsupported(baud));
+    }
+    Err("Serial ports are only supported on Unix".to_string())
+}
+
+fn unsupported(baud: u32) -> String {
+    let rates: Vec<String> = BAUD_RATES.iter().map(u32::to_string).collect();
+    format!("Unsupported baud rate {}. Expected one of {}", baud, rates.join(", "))
+}
+
+/// Splits received text into whole words, holding back a word that may continue in the next read
+#[derive(Debug, Default)]
+pub struct WordSplitter {
+    pending: String,
+}
+
+impl WordSplitter {
+    /// Add received bytes, returning the text of the words they complete
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.push_str(&String::from_utf8_lossy(bytes));
+        let end = self.pending.char_indices().rev().find(|(_, c)| c.is_whitespace()).map(|(i, c)| i + c.len_utf8());
+        match end {
+            Some(end) => self.pending.drain(..end).collect(),
+            None => String::new(),
+        }
+    }
+
+    /// The last word at the end of the input
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_splitter() {
+        let mut words = WordSplitter::default();
+        assert_eq!(words.push(b"BOT"), "");
+        assert_eq!(words.push(b"AN JS1YPT A67C"), "BOTAN JS1YPT ");
+        assert_eq!(words.push(b"8D5E2AA13608\r\nBO"), "A67C8D5E2AA13608\r\n");
+        assert_eq!(words.finish(), "BO");
+        assert!(open("/dev/null", 300).unwrap_err().starts_with("Unsupported baud rate 300"));
+    }
+}
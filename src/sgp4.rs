@@ -0,0 +1,400 @@
+// Near-earth SGP4 propagator following Vallado et al., "Revisiting Spacetrack Report #3" (2006).
+// Deep-space (SDP4) orbits with periods of 225 minutes or more are rejected; BOTAN is in LEO.
+
+use std::f64::consts::PI;
+
+use crate::tle::Tle;
+
+// WGS-72 constants, as used to generate TLEs
+const MU: f64 = 398600.8;               // [km^3/s^2]
+const EARTH_RADIUS_KM: f64 = 6378.135;
+const J2: f64 = 0.001082616;
+const J3: f64 = -0.00000253881;
+const J4: f64 = -0.00000165597;
+const TWO_PI: f64 = 2.0 * PI;
+const X2O3: f64 = 2.0 / 3.0;
+
+/// Position [km] and velocity [km/s] in the TEME frame
+#[derive(Debug, Clone, Copy)]
+pub struct StateVector {
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+}
+
+/// Initialized SGP4 model for one element set
+#[derive(Debug, Clone)]
+pub struct Sgp4 {
+    epoch_unix: f64,
+    bstar: f64,
+    ecco: f64,
+    inclo: f64,
+    nodeo: f64,
+    argpo: f64,
+    mo: f64,
+    no_unkozai: f64,
+    isimp: bool,
+    con41: f64,
+    x1mth2: f64,
+    x7thm1: f64,
+    cc1: f64,
+    cc4: f64,
+    cc5: f64,
+    d2: f64,
+    d3: f64,
+    d4: f64,
+    delmo: f64,
+    eta: f64,
+    argpdot: f64,
+    omgcof: f64,
+    sinmao: f64,
+    t2cof: f64,
+    t3cof: f64,
+    t4cof: f64,
+    t5cof: f64,
+    xlcof: f64,
+    aycof: f64,
+    xmcof: f64,
+    nodecf: f64,
+    mdot: f64,
+    nodedot: f64,
+}
+
+fn xke() -> f64 {
+    60.0 / (EARTH_RADIUS_KM.powi(3) / MU).sqrt()
+}
+
+impl Sgp4 {
+    pub fn new(tle: &Tle) -> Result<Self, String> {
+        let xke = xke();
+        let j3oj2 = J3 / J2;
+        let ecco = tle.eccentricity;
+        let inclo = tle.inclination;
+        let argpo = tle.arg_perigee;
+        let mo = tle.mean_anomaly;
+        let bstar = tle.bstar;
+        let no_kozai = tle.mean_motion * TWO_PI / 1440.0;  // [rad/min]
+
+        if !(0.0..1.0).contains(&ecco) || no_kozai <= 0.0 {
+            return Err("TLE has invalid eccentricity or mean motion".to_string());
+        }
+
+        // Recover the original mean motion and semi-major axis from the Kozai mean motion
+        let eccsq = ecco * ecco;
+        let omeosq = 1.0 - eccsq;
+        let rteosq = omeosq.sqrt();
+        let cosio = inclo.cos();
+        let cosio2 = cosio * cosio;
+        let ak = (xke / no_kozai).powf(X2O3);
+        let d1 = 0.75 * J2 * (3.0 * cosio2 - 1.0) / (rteosq * omeosq);
+        let del = d1 / (ak * ak);
+        let adel = ak * (1.0 - del * del - del * (1.0 / 3.0 + 134.0 * del * del / 81.0));
+        let del = d1 / (adel * adel);
+        let no_unkozai = no_kozai / (1.0 + del);
+
+        if TWO_PI / no_unkozai >= 225.0 {
+            return Err("Deep-space orbits (period >= 225 min) are not supported".to_string());
+        }
+
+        let ao = (xke / no_unkozai).powf(X2O3);
+        let sinio = inclo.sin();
+        let po = ao * omeosq;
+        let con42 = 1.0 - 5.0 * cosio2;
+        let con41 = -con42 - cosio2 - cosio2;
+        let posq = po * po;
+        let rp = ao * (1.0 - ecco);
+
+        let isimp = rp < 220.0 / EARTH_RADIUS_KM + 1.0;
+
+        // Atmospheric density parameters, adjusted for low perigee
+        let ss = 78.0 / EARTH_RADIUS_KM + 1.0;
+        let qzms2t = ((120.0 - 78.0) / EARTH_RADIUS_KM).powi(4);
+        let mut sfour = ss;
+        let mut qzms24 = qzms2t;
+        let perige = (rp - 1.0) * EARTH_RADIUS_KM;
+        if perige < 156.0 {
+            sfour = if perige < 98.0 { 20.0 } else { perige - 78.0 };
+            qzms24 = ((120.0 - sfour) / EARTH_RADIUS_KM).powi(4);
+            sfour = sfour / EARTH_RADIUS_KM + 1.0;
+        }
+
+        let pinvsq = 1.0 / posq;
+        let tsi = 1.0 / (ao - sfour);
+        let eta = ao * ecco * tsi;
+        let etasq = eta * eta;
+        let eeta = ecco * eta;
+        let psisq = (1.0 - etasq).abs();
+        let coef = qzms24 * tsi.powi(4);
+        let coef1 = coef / psisq.powf(3.5);
+        let cc2 = coef1 * no_unkozai
+            * (ao * (1.0 + 1.5 * etasq + eeta * (4.0 + etasq))
+                + 0.375 * J2 * tsi / psisq * con41 * (8.0 + 3.0 * etasq * (8.0 + etasq)));
+        let cc1 = bstar * cc2;
+        let cc3 = if ecco > 1.0e-4 {
+            -2.0 * coef * tsi * j3oj2 * no_unkozai * sinio / ecco
+        } else {
+            0.0
+        };
+        let x1mth2 = 1.0 - cosio2;
+        let cc4 = 2.0 * no_unkozai * coef1 * ao * omeosq
+            * (eta * (2.0 + 0.5 * etasq) + ecco * (0.5 + 2.0 * etasq)
+                - J2 * tsi / (ao * psisq)
+                    * (-3.0 * con41 * (1.0 - 2.0 * eeta + etasq * (1.5 - 0.5 * eeta))
+                        + 0.75 * x1mth2 * (2.0 * etasq - eeta * (1.0 + etasq)) * (2.0 * argpo).cos()));
+        let cc5 = 2.0 * coef1 * ao * omeosq * (1.0 + 2.75 * (etasq + eeta) + eeta * etasq);
+
+        // Secular rates from J2/J4
+        let cosio4 = cosio2 * cosio2;
+        let temp1 = 1.5 * J2 * pinvsq * no_unkozai;
+        let temp2 = 0.5 * temp1 * J2 * pinvsq;
+        let temp3 = -0.46875 * J4 * pinvsq * pinvsq * no_unkozai;
+        let mdot = no_unkozai
+            + 0.5 * temp1 * rteosq * con41
+            + 0.0625 * temp2 * rteosq * (13.0 - 78.0 * cosio2 + 137.0 * cosio4);
+        let argpdot = -0.5 * temp1 * con42
+            + 0.0625 * temp2 * (7.0 - 114.0 * cosio2 + 395.0 * cosio4)
+            + temp3 * (3.0 - 36.0 * cosio2 + 49.0 * cosio4);
+        let xhdot1 = -temp1 * cosio;
+        let nodedot = xhdot1 + (0.5 * temp2 * (4.0 - 19.0 * cosio2) + 2.0 * temp3 * (3.0 - 7.0 * cosio2)) * cosio;
+
+        let omgcof = bstar * cc3 * argpo.cos();
+        let xmcof = if ecco > 1.0e-4 { -X2O3 * coef * bstar / eeta } else { 0.0 };
+        let nodecf = 3.5 * omeosq * xhdot1 * cc1;
+        let t2cof = 1.5 * cc1;
+        let xlcof = if (cosio + 1.0).abs() > 1.5e-12 {
+            -0.25 * j3oj2 * sinio * (3.0 + 5.0 * cosio) / (1.0 + cosio)
+        } else {
+            -0.25 * j3oj2 * sinio * (3.0 + 5.0 * cosio) / 1.5e-12
+        };
+        let aycof = -0.5 * j3oj2 * sinio;
+        let delmo = (1.0 + eta * mo.cos()).powi(3);
+        let sinmao = mo.sin();
+        let x7thm1 = 7.0 * cosio2 - 1.0;
+
+        let (mut d2, mut d3, mut d4, mut t3cof, mut t4cof, mut t5cof) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        if !isimp {
+            let cc1sq = cc1 * cc1;
+            d2 = 4.0 * ao * tsi * cc1sq;
+            let temp = d2 * tsi * cc1 / 3.0;
+            d3 = (17.0 * ao + sfour) * temp;
+            d4 = 0.5 * temp * ao * tsi * (221.0 * ao + 31.0 * sfour) * cc1;
+            t3cof = d2 + 2.0 * cc1sq;
+            t4cof = 0.25 * (3.0 * d3 + cc1 * (12.0 * d2 + 10.0 * cc1sq));
+            t5cof = 0.2 * (3.0 * d4 + 12.0 * cc1 * d3 + 6.0 * d2 * d2 + 15.0 * cc1sq * (2.0 * d2 + cc1sq));
+        }
+
+        Ok(Sgp4 {
+            epoch_unix: tle.epoch_unix,
+            bstar,
+            ecco,
+            inclo,
+            nodeo: tle.raan,
+            argpo,
+            mo,
+            no_unkozai,
+            isimp,
+            con41,
+            x1mth2,
+            x7thm1,
+            cc1,
+            cc4,
+            cc5,
+            d2,
+            d3,
+            d4,
+            delmo,
+            eta,
+            argpdot,
+            omgcof,
+            sinmao,
+            t2cof,
+            t3cof,
+            t4cof,
+            t5cof,
+            xlcof,
+            aycof,
+            xmcof,
+            nodecf,
+            mdot,
+            nodedot,
+        })
+    }
+
+    /// Propagate to a Unix time
+    pub fn propagate_unix(&self, unix: f64) -> Result<StateVector, String> {
+        self.propagate((unix - self.epoch_unix) / 60.0)
+    }
+
+    /// Propagate to `tsince` minutes after the element set epoch
+    pub fn propagate(&self, tsince: f64) -> Result<StateVector, String> {
+        let xke = xke();
+        let t = tsince;
+
+        // Secular gravity and atmospheric drag
+        let xmdf = self.mo + self.mdot * t;
+        let argpdf = self.argpo + self.argpdot * t;
+        let nodedf = self.nodeo + self.nodedot * t;
+        let mut argpm = argpdf;
+        let mut mm = xmdf;
+        let t2 = t * t;
+        let mut nodem = nodedf + self.nodecf * t2;
+        let mut tempa = 1.0 - self.cc1 * t;
+        let mut tempe = self.bstar * self.cc4 * t;
+        let mut templ = self.t2cof * t2;
+
+        if !self.isimp {
+            let delomg = self.omgcof * t;
+            let delm = self.xmcof * ((1.0 + self.eta * xmdf.cos()).powi(3) - self.delmo);
+            let temp = delomg + delm;
+            mm = xmdf + temp;
+            argpm = argpdf - temp;
+            let t3 = t2 * t;
+            let t4 = t3 * t;
+            tempa = tempa - self.d2 * t2 - self.d3 * t3 - self.d4 * t4;
+            tempe += self.bstar * self.cc5 * (mm.sin() - self.sinmao);
+            templ += self.t3cof * t3 + t4 * (self.t4cof + t * self.t5cof);
+        }
+
+        let am = (xke / self.no_unkozai).powf(X2O3) * tempa * tempa;
+        let nm = xke / am.powf(1.5);
+        let mut em = self.ecco - tempe;
+        if !(-0.001..1.0).contains(&em) {
+            return Err(format!("Eccentricity out of range at t={:.1} min (decayed?)", t));
+        }
+        if em < 1.0e-6 {
+            em = 1.0e-6;
+        }
+        mm += self.no_unkozai * templ;
+        let xlm = mm + argpm + nodem;
+        nodem %= TWO_PI;
+        argpm %= TWO_PI;
+        let xlm = xlm % TWO_PI;
+        mm = (xlm - argpm - nodem) % TWO_PI;
+
+        let sinip = self.inclo.sin();
+        let cosip = self.inclo.cos();
+
+        // Long-period periodics
+        let axnl = em * argpm.cos();
+        let temp = 1.0 / (am * (1.0 - em * em));
+        let aynl = em * argpm.sin() + temp * self.aycof;
+        let xl = mm + argpm + nodem + temp * self.xlcof * axnl;
+
+        // Solve Kepler's equation
+        let u = (xl - nodem) % TWO_PI;
+        let mut eo1 = u;
+        let mut tem5: f64 = 9999.9;
+        let mut ktr = 1;
+        let (mut sineo1, mut coseo1) = (0.0, 0.0);
+        while tem5.abs() >= 1.0e-12 && ktr <= 10 {
+            sineo1 = eo1.sin();
+            coseo1 = eo1.cos();
+            tem5 = 1.0 - coseo1 * axnl - sineo1 * aynl;
+            tem5 = (u - aynl * coseo1 + axnl * sineo1 - eo1) / tem5;
+            if tem5.abs() >= 0.95 {
+                tem5 = 0.95f64.copysign(tem5);
+            }
+            eo1 += tem5;
+            ktr += 1;
+        }
+
+        // Short-period periodics
+        let ecose = axnl * coseo1 + aynl * sineo1;
+        let esine = axnl * sineo1 - aynl * coseo1;
+        let el2 = axnl * axnl + aynl * aynl;
+        let pl = am * (1.0 - el2);
+        if pl < 0.0 {
+            return Err(format!("Semi-latus rectum negative at t={:.1} min", t));
+        }
+        let rl = am * (1.0 - ecose);
+        let rdotl = am.sqrt() * esine / rl;
+        let rvdotl = pl.sqrt() / rl;
+        let betal = (1.0 - el2).sqrt();
+        let temp = esine / (1.0 + betal);
+        let sinu = am / rl * (sineo1 - aynl - axnl * temp);
+        let cosu = am / rl * (coseo1 - axnl + aynl * temp);
+        let mut su = sinu.atan2(cosu);
+        let sin2u = (cosu + cosu) * sinu;
+        let cos2u = 1.0 - 2.0 * sinu * sinu;
+        let temp = 1.0 / pl;
+        let temp1 = 0.5 * J2 * temp;
+        let temp2 = temp1 * temp;
+
+        let mrt = rl * (1.0 - 1.5 * temp2 * betal * self.con41) + 0.5 * temp1 * self.x1mth2 * cos2u;
+        su -= 0.25 * temp2 * self.x7thm1 * sin2u;
+        let xnode = nodem + 1.5 * temp2 * cosip * sin2u;
+        let xinc = self.inclo + 1.5 * temp2 * cosip * sinip * cos2u;
+        let mvt = rdotl - nm * temp1 * self.x1mth2 * sin2u / xke;
+        let rvdot = rvdotl + nm * temp1 * (self.x1mth2 * cos2u + 1.5 * self.con41) / xke;
+
+        if mrt < 1.0 {
+            return Err(format!("Satellite has decayed at t={:.1} min", t));
+        }
+
+        // Orientation vectors
+        let (sinsu, cossu) = su.sin_cos();
+        let (snod, cnod) = xnode.sin_cos();
+        let (sini, cosi) = xinc.sin_cos();
+        let xmx = -snod * cosi;
+        let xmy = cnod * cosi;
+        let ux = xmx * sinsu + cnod * cossu;
+        let uy = xmy * sinsu + snod * cossu;
+        let uz = sini * sinsu;
+        let vx = xmx * cossu - cnod * sinsu;
+        let vy = xmy * cossu - snod * sinsu;
+        let vz = sini * cossu;
+
+        let vkmpersec = EARTH_RADIUS_KM * xke / 60.0;
+        Ok(StateVector {
+            position: [
+                mrt * ux * EARTH_RADIUS_KM,
+                mrt * uy * EARTH_RADIUS_KM,
+                mrt * uz * EARTH_RADIUS_KM,
+            ],
+            velocity: [
+                (mvt * ux + rvdot * vx) * vkmpersec,
+                (mvt * uy + rvdot * vy) * vkmpersec,
+                (mvt * uz + rvdot * vz) * vkmpersec,
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test case from Spacetrack Report #3, reference values from Vallado's verification output
+    const TLE_LINE1: &str = "1 88888U          80275.98708465  .00073094  13844-3  66816-4 0    8";
+    const TLE_LINE2: &str = "2 88888  72.8435 115.9689 0086731  52.6988 110.5714 16.05824518  105";
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3], tolerance: f64) {
+        for i in 0..3 {
+            assert!(
+                (actual[i] - expected[i]).abs() < tolerance,
+                "component {}: {} vs {}", i, actual[i], expected[i]
+            );
+        }
+    }
+
+    #[test]
+    fn test_spacetrack_report_3() {
+        let tle = Tle::parse(None, TLE_LINE1, TLE_LINE2).unwrap();
+        let sgp4 = Sgp4::new(&tle).unwrap();
+
+        let state = sgp4.propagate(0.0).unwrap();
+        assert_close(state.position, [2328.96975262, -5995.22051338, 1719.97297192], 1e-4);
+        assert_close(state.velocity, [2.91207328, -0.98341796, -7.09081621], 1e-7);
+
+        let state = sgp4.propagate(360.0).unwrap();
+        assert_close(state.position, [2456.10706533, -6071.93855503, 1222.89768554], 1e-4);
+        assert_close(state.velocity, [2.67939004, -0.44829081, -7.22879215], 1e-7);
+    }
+
+    #[test]
+    fn test_deep_space_rejected() {
+        // Geostationary-like mean motion
+        let line2 = "2 88888  72.8435 115.9689 0086731  52.6988 110.5714  1.00273791  105";
+        let tle = Tle::parse(None, TLE_LINE1, line2).unwrap();
+        assert!(Sgp4::new(&tle).is_err());
+    }
+}
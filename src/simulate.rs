@@ -0,0 +1,174 @@
+// Synthetic BOTAN traffic for exercising dashboards, alarms and forwarders between passes. The
+// simulated satellite follows a simple orbit: in sunlight the panels facing the Sun generate and
+// the battery charges and warms up; in eclipse it discharges and cools. Commands are uplinked
+// now and then and missions run for a few frames, so the status bits change as they would in
+// flight. Frames are encoded with the active calibration table, so they decode to the simulated
+// values, unless bit errors are injected at --bit-error-rate.
+use crate::calibration::{CalibrationTable, Channel};
+use crate::rng::Rng;
+
+const ORBIT_S: f64 = 5640.0;     // About 94 minutes
+const SUNLIT: f64 = 0.62;        // Fraction of the orbit in sunlight
+const THERMAL_TAU_S: f64 = 900.0;
+const CAPACITY_MAH: f64 = 2600.0;
+
+/// Generates one frame after another from a simulated satellite
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    rng: Rng,
+    errors: Rng,    // Separate, so injecting errors does not change the simulated values
+    table: CalibrationTable,
+    bit_error_rate: f64,
+    elapsed_s: f64,
+    bat_v: f64,
+    bat_t: f64,
+    bpb_t: f64,
+    panels: u8,               // Data1 bits 4-0
+    reserve_cmd: u8,
+    cmd_uplink: u8,
+    mission: u8,              // 1 Earth, 2 Sun while running
+    mission_frames: u32,      // Left in the running mission
+    mission_ended: bool,
+    mission_error: bool,
+}
+
+impl Simulator {
+    pub fn new(seed: u64, bit_error_rate: f64, table: CalibrationTable) -> Self {
+        Simulator {
+            rng: Rng::new(seed),
+            errors: Rng::new(seed ^ 0xB07A_B17E),
+            table,
+            bit_error_rate,
+            elapsed_s: 0.0,
+            bat_v: 4.0,
+            bat_t: 15.0,
+            bpb_t: 25.0,
+            panels: 0,
+            reserve_cmd: 0,
+            cmd_uplink: 0,
+            mission: 0,
+            mission_frames: 0,
+            mission_ended: false,
+            mission_error: false,
+        }
+    }
+
+    fn sunlit(&self) -> bool {
+        self.elapsed_s % ORBIT_S < SUNLIT * ORBIT_S
+    }
+
+    /// Advance the satellite by `dt_s` seconds and return its next beacon line
+    pub fn next_line(&mut self, dt_s: f64) -> String {
+        let frame = self.next_frame(dt_s);
+        let (rssi, snr) = (0x70 + self.rng.below(0x20) as u8, 0x30 + self.rng.below(0x20) as u8);
+        let hex: String = frame.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("BOTAN JS1YPT SI{:02X}{:02X} {}", rssi, snr, hex)
+    }
+
+    fn next_frame(&mut self, dt_s: f64) -> [u8; 8] {
+        self.elapsed_s += dt_s;
+        let sunlit = self.sunlit();
+        self.step_status();
+
+        // A tumbling satellite shows one to three faces to the Sun
+        self.panels = if sunlit {
+            (0..1 + self.rng.below(3)).fold(0, |panels, _| panels | 1 << self.rng.below(5))
+        } else {
+            0
+        };
+        let raw_i = 200.0 + if self.mission != 0 { 150.0 } else { 0.0 } + self.rng.noise(15.0);
+        let generated = 120.0 * self.panels.count_ones() as f64;
+        let mut bat_i = generated - raw_i + self.rng.noise(20.0);
+        if self.bat_v >= 4.2 && bat_i > 0.0 {
+            bat_i = self.rng.noise(10.0);    // Charge regulator cuts off
+        }
+        self.bat_v = (self.bat_v + bat_i / CAPACITY_MAH * dt_s / 3600.0).clamp(3.5, 4.2);
+
+        let relax = 1.0 - (-dt_s / THERMAL_TAU_S).exp();
+        let (bat_target, bpb_target) = if sunlit { (25.0, 35.0) } else { (5.0, 12.0) };
+        self.bat_t += (bat_target - self.bat_t) * relax + self.rng.noise(0.2);
+        self.bpb_t += (bpb_target - self.bpb_t) * relax + self.rng.noise(0.3);
+
+        let values = [self.bat_v + self.rng.noise(0.01), bat_i, self.bat_t, self.bpb_t, raw_i];
+        let mut frame = [0u8; 8];
+        for (channel, value) in Channel::ALL.into_iter().zip(values) {
+            frame[channel as usize] = self.table.raw(channel, value).unwrap_or(0);
+        }
+        // 5 V and transponder lines on, antenna deployment line off after deployment
+        frame[5] = 0b1010_0000 | self.panels;
+        frame[6] = (self.reserve_cmd << 4) | (self.cmd_uplink << 1);
+        frame[7] = ((self.mission != 0) as u8) << 5
+            | ((self.mission_error as u8) << 4)
+            | ((self.mission_ended as u8) << 3)
+            | self.mission;
+
+        for bit in 0..64 {
+            if self.bit_error_rate > 0.0 && self.errors.chance(self.bit_error_rate) {
+                frame[bit / 8] ^= 0x80 >> (bit % 8);
+            }
+        }
+        frame
+    }
+
+    /// Commands and missions
+    fn step_status(&mut self) {
+        if self.rng.chance(0.02) {
+            self.cmd_uplink = (self.cmd_uplink + 1) % 8;
+            if self.rng.chance(0.3) {
+                self.reserve_cmd = (self.reserve_cmd + 1) % 16;
+            }
+        }
+        if self.mission != 0 {
+            self.mission_frames = self.mission_frames.saturating_sub(1);
+            if self.mission_frames == 0 {
+                self.mission = 0;
+                self.mission_ended = true;
+            }
+        } else if self.rng.chance(0.01) {
+            self.mission = 1 + self.rng.below(2) as u8;
+            self.mission_frames = 5 + self.rng.below(20) as u32;
+            self.mission_ended = false;
+            self.mission_error = self.rng.chance(0.1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_simulated_frames() {
+        let mut simulator = Simulator::new(42, 0.0, CalibrationTable::default());
+        let (mut sunlit, mut eclipse) = (0, 0);
+        for _ in 0..400 {
+            let line = simulator.next_line(30.0);
+            let telemetry = parse_botan_beacon(&line).unwrap().telemetry;
+            assert!((3.4..=4.3).contains(&telemetry.bat_v), "{}", line);
+            assert!((-10.0..=45.0).contains(&telemetry.bpb_t), "{}", line);
+            assert!(telemetry.data1.power_5v0 && !telemetry.data2.kill_sw);
+            let panels = [telemetry.data1.sap_x_pos, telemetry.data1.sap_y_pos, telemetry.data1.sap_y_neg, telemetry.data1.sap_z_pos, telemetry.data1.sap_z_neg];
+            if simulator.sunlit() {
+                sunlit += 1;
+                assert!(panels.contains(&true));
+            } else {
+                eclipse += 1;
+                assert!(!panels.contains(&true));
+                assert!(telemetry.bat_i < 0.0, "{}", line);
+            }
+        }
+        assert!(sunlit > 0 && eclipse > 0);
+        assert_ne!(simulator.cmd_uplink, 0);
+    }
+
+    #[test]
+    fn test_bit_errors() {
+        let table = CalibrationTable::default();
+        let (mut clean, mut flipped) = (Simulator::new(7, 0.0, table), Simulator::new(7, 1.0, table));
+        for _ in 0..10 {
+            let (a, b) = (clean.next_frame(30.0), flipped.next_frame(30.0));
+            assert_eq!(a.map(|byte| !byte), b);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// Counts of frames attempted, decoded and failed, for the current run and over the station's
+// lifetime. The live receivers (udp-audio, rtlsdr, schedule) add every frame to the lifetime
+// totals in $XDG_DATA_HOME/botan_cw_decoder/stats.json, per pass when scheduled; the `stats`
+// subcommand reports them. Decodes of recordings and typed lines are only counted per run.
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frames::Frame;
+use crate::lock::FileLock;
+use crate::logging::{info, warn};
+use crate::migrate;
+use crate::timeutil;
+
+// Frames remembered for duplicate detection; BOTAN repeats a frame within minutes, and
+// remembering every frame of a long archive would grow without bound
+const RECENT_FRAMES: usize = 4096;
+
+/// Frame counts for a run, a pass or the lifetime of the station
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Counters {
+    pub attempted: u64,
+    pub decoded: u64,
+    pub failed: BTreeMap<String, u64>,  // By error category, see `category`
+    pub duplicates: u64,                // Decoded frames identical to a recent one in the run or pass
+}
+
+impl Counters {
+    pub fn failed_total(&self) -> u64 {
+        self.failed.values().sum()
+    }
+
+    fn add(&mut self, other: &Counters) {
+        self.attempted += other.attempted;
+        self.decoded += other.decoded;
+        self.duplicates += other.duplicates;
+        for (category, count) in &other.failed {
+            *self.failed.entry(category.clone()).or_default() += count;
+        }
+    }
+}
+
+impl fmt::Display for Counters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attempted={} decoded={} failed={} duplicates={}",
+            self.attempted,
+            self.decoded,
+            self.failed_total(),
+            self.duplicates
+        )
+    }
+}
+
+/// Category of a beacon parse error, for the failure breakdown
+pub fn category(error: &str) -> &'static str {
+    const CATEGORIES: [(&str, &str); 11] = [
+        ("Invalid beacon format", "format"),
+        ("Invalid satellite name", "header"),
+        ("Invalid call sign", "header"),
+        ("Invalid RSSI", "signal_report"),
+        ("Invalid SNR", "signal_report"),
+        ("Invalid data length", "length"),
+        ("Invalid hex data", "hex"),
+        ("Invalid battery temperature", "telemetry"),
+        ("Invalid board temperature", "telemetry"),
+        ("Invalid AX.25 FCS", "fcs"),
+        ("Invalid AX.25", "ax25"),
+    ];
+    CATEGORIES
+        .iter()
+        .find(|(prefix, _)| error.starts_with(prefix))
+        .map_or("other", |(_, category)| category)
+}
+
+/// Totals kept across runs
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lifetime {
+    pub schema_version: u64,                  // See migrate
+    pub since: Option<String>,                // UTC of the first recorded frame
+    pub total: Counters,
+    pub passes: BTreeMap<String, Counters>,   // Scheduled passes by AOS (UTC)
+}
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Lifetime { schema_version: migrate::SCHEMA_VERSION, since: None, total: Counters::default(), passes: BTreeMap::new() }
+    }
+}
+
+impl Lifetime {
+    /// $XDG_DATA_HOME/botan_cw_decoder/stats.json, or under ~/.local/share
+    pub fn default_path() -> PathBuf {
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))
+            .unwrap_or_else(|| PathBuf::from("."));
+        base.join("botan_cw_decoder").join("stats.json")
+    }
+
+    /// Load the totals; a missing file means nothing was recorded yet
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| e.to_string())
+                .and_then(migrate::upgrade_record)
+                .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+                .map_err(|e| format!("Invalid stats file '{}': {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Lifetime::default()),
+            Err(e) => Err(format!("Cannot read '{}': {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        // Write then rename so a concurrent reader never sees a half-written file; the temporary
+        // file is our own, so another receiver saving at the same time cannot rename it
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&tmp, json + "\n").map_err(|e| format!("Cannot write '{}': {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Cannot write '{}': {}", path.display(), e))
+    }
+}
+
+/// Counts the frames of one run, adding them to the lifetime totals when given a stats file
+pub struct Recorder {
+    path: Option<PathBuf>,
+    pass: Option<String>,
+    seen: HashSet<Vec<u8>>,
+    recent: VecDeque<Vec<u8>>,   // `seen` in arrival order, oldest first
+    pub run: Counters,
+    pub current_pass: Counters,
+    health: Option<Health>,
+}
+
+/// When the counters of a long run were last logged
+#[derive(Debug)]
+struct Health {
+    interval: Duration,
+    started: Instant,
+    logged: Instant,
+}
+
+impl Recorder {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Recorder {
+            path,
+            pass: None,
+            seen: HashSet::new(),
+            recent: VecDeque::new(),
+            run: Counters::default(),
+            current_pass: Counters::default(),
+            health: None,
+        }
+    }
+
+    /// Log the run's counters every `interval` from `heartbeat`
+    pub fn with_health_log(mut self, interval: Option<Duration>) -> Self {
+        self.health = interval.map(|interval| Health { interval, started: Instant::now(), logged: Instant::now() });
+        self
+    }
+
+    /// Called regularly by the live receivers, whether or not anything was decoded
+    pub fn heartbeat(&mut self) {
+        let Some(health) = &mut self.health else { return };
+        if health.logged.elapsed() < health.interval {
+            return;
+        }
+        health.logged = Instant::now();
+        info!("Running: {}", self.run; uptime_s = health.started.elapsed().as_secs());
+    }
+
+    /// Count the following frames towards the pass starting at `aos` (UTC)
+    pub fn start_pass(&mut self, aos: &str) {
+        self.pass = Some(aos.to_string());
+        self.seen.clear();
+        self.recent.clear();
+        self.current_pass = Counters::default();
+    }
+
+    /// Count one frame given its parse result
+    pub fn record(&mut self, result: &Result<Frame, String>) {
+        let mut frame = Counters { attempted: 1, ..Counters::default() };
+        match result {
+            Ok(decoded) => {
+                frame.decoded = 1;
+                if self.seen.insert(decoded.bytes().to_vec()) {
+                    self.recent.push_back(decoded.bytes().to_vec());
+                    if self.recent.len() > RECENT_FRAMES {
+                        let oldest = self.recent.pop_front().unwrap();
+                        self.seen.remove(&oldest);
+                    }
+                } else {
+                    frame.duplicates = 1;
+                }
+            }
+            Err(error) => {
+                frame.failed.insert(category(error).to_string(), 1);
+            }
+        }
+        self.run.add(&frame);
+        self.current_pass.add(&frame);
+
+        let Some(path) = &self.path else { return };
+        // Re-read each time, under a lock, so several receivers can share the file
+        let saved = FileLock::exclusive(&path.with_extension("lock")).and_then(|_lock| {
+            let mut lifetime = Lifetime::load(path)?;
+            lifetime.since.get_or_insert_with(|| timeutil::format_utc(timeutil::now_unix()));
+            lifetime.total.add(&frame);
+            if let Some(pass) = &self.pass {
+                lifetime.passes.entry(pass.clone()).or_default().add(&frame);
+            }
+            lifetime.save(path)
+        });
+        if let Err(error) = saved {
+            warn!("Decode statistics not saved: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::parse_botan_beacon;
+
+    #[test]
+    fn test_categories() {
+        for (input, expected) in [
+            ("BOTAN JS1YPT", "format"),
+            ("BOTAN JA1ABC A67C8D5E2AA13608", "header"),
+            ("BOTAN JS1YPT SIXX40 A67C8D5E2AA13608", "signal_report"),
+            ("BOTAN JS1YPT A67C8D5E2AA136", "length"),
+            ("BOTAN JS1YPT A67C8D5E2AA1360G", "hex"),
+        ] {
+            assert_eq!(category(&parse_botan_beacon(input).unwrap_err()), expected, "{}", input);
+        }
+        assert_eq!(category("Template error"), "other");
+    }
+
+    #[test]
+    fn test_recorder() {
+        let path = std::env::temp_dir().join(format!("botan_stats_test_{}.json", std::process::id()));
+        let good = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").map(Frame::Housekeeping);
+        let bad = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA1360G").map(Frame::Housekeeping);
+
+        let mut recorder = Recorder::new(Some(path.clone()));
+        recorder.record(&good);
+        recorder.start_pass("2024-05-01T12:34:56Z");
+        recorder.record(&good);
+        recorder.record(&good);
+        recorder.record(&bad);
+        assert_eq!(recorder.run.to_string(), "attempted=4 decoded=3 failed=1 duplicates=1");
+        assert_eq!(recorder.current_pass.to_string(), "attempted=3 decoded=2 failed=1 duplicates=1");
+
+        // A second run adds to the same totals
+        Recorder::new(Some(path.clone())).record(&bad);
+        let lifetime = Lifetime::load(&path).unwrap();
+        assert!(lifetime.since.is_some());
+        assert_eq!(lifetime.total.attempted, 5);
+        assert_eq!(lifetime.total.failed["hex"], 2);
+        assert_eq!(lifetime.passes["2024-05-01T12:34:56Z"].decoded, 2);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("lock")).unwrap();
+    }
+
+    #[test]
+    fn test_shared_file() {
+        let path = std::env::temp_dir().join(format!("botan_stats_shared_{}.json", std::process::id()));
+        let good = parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").map(Frame::Housekeeping);
+        // Receivers saving at the same time lose no counts
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut recorder = Recorder::new(Some(path.clone()));
+                    for _ in 0..25 {
+                        recorder.record(&good);
+                    }
+                });
+            }
+        });
+        assert_eq!(Lifetime::load(&path).unwrap().total.attempted, 100);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("lock")).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// When to color the beacon report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("Unknown color choice '{}'. Expected auto, always or never", s)),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Auto colors only a terminal stdout, and respects NO_COLOR
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+        }
+    }
+}
+
+/// ANSI styles used by the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paint {
+    Plain,
+    Green,
+    Yellow,
+    Red,
+    Dim,
+}
+
+impl Paint {
+    fn code(self) -> Option<&'static str> {
+        match self {
+            Paint::Plain => None,
+            Paint::Green => Some("32"),
+            Paint::Yellow => Some("33"),
+            Paint::Red => Some("31"),
+            Paint::Dim => Some("2"),
+        }
+    }
+}
+
+/// A value wrapped in an ANSI style when coloring is on
+pub struct Painted<T> {
+    value: T,
+    paint: Paint,
+    enabled: bool,
+}
+
+pub fn paint<T: fmt::Display>(value: T, paint: Paint, enabled: bool) -> Painted<T> {
+    Painted { value, paint, enabled }
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.paint.code().filter(|_| self.enabled) {
+            Some(code) => write!(f, "\x1b[{}m{}\x1b[0m", code, self.value),
+            None => self.value.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("ON", Paint::Green, true).to_string(), "\x1b[32mON\x1b[0m");
+        assert_eq!(paint("ON", Paint::Green, false).to_string(), "ON");
+        assert_eq!(paint(4.2, Paint::Plain, true).to_string(), "4.2");
+        assert_eq!("Never".parse::<ColorChoice>(), Ok(ColorChoice::Never));
+        assert!(!ColorChoice::Never.enabled());
+    }
+}
@@ -0,0 +1,128 @@
+use serde_json::Value;
+
+// Output templates: literal text with {{field.path}} placeholders resolved against the decoded
+// beacon, e.g. "{{time}} {{telemetry.bat_v:.2}} V {{telemetry.data1.power_com}}".
+// An optional ":.N" fixes the number of decimals; null fields render as empty text.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field { path: Vec<String>, precision: Option<usize> },
+}
+
+/// A parsed output template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("Unclosed '{{{{' in template at '{}'", &rest[start..]))?;
+            let field = rest[start + 2..start + end].trim();
+            let (name, precision) = match field.split_once(":.") {
+                Some((name, digits)) => {
+                    let digits = digits
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid precision in template field '{}'", field))?;
+                    (name.trim(), Some(digits))
+                }
+                None => (field, None),
+            };
+            if name.is_empty() || name.split('.').any(|p| p.is_empty()) {
+                return Err(format!("Invalid template field '{{{{{}}}}}'", field));
+            }
+            segments.push(Segment::Field { path: name.split('.').map(String::from).collect(), precision });
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Template { segments })
+    }
+
+    /// Load a template file; a trailing newline is kept so each beacon ends its own line
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read template '{}': {}", path, e))?;
+        Template::parse(&source).map_err(|e| format!("{} in '{}'", e, path))
+    }
+
+    /// Fill in the placeholders from `context`; unknown fields are an error so typos show up
+    pub fn render(&self, context: &Value) -> Result<String, String> {
+        self.render_escaped(context, str::to_string)
+    }
+
+    /// Like `render`, passing each value's text through `escape`, e.g. to keep it inside a JSON
+    /// string literal
+    pub fn render_escaped(&self, context: &Value, escape: impl Fn(&str) -> String) -> Result<String, String> {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field { path, precision } => {
+                    let mut value = context;
+                    for key in path {
+                        value = match value {
+                            Value::Object(map) => map.get(key),
+                            // Fields inside an absent optional block, e.g. rssi.raw_rssi without SIxxxx
+                            Value::Null => Some(&Value::Null),
+                            _ => None,
+                        }
+                        .ok_or_else(|| format!("Unknown template field '{}'", path.join(".")))?;
+                    }
+                    match (value, precision) {
+                        (Value::Null, _) => {}
+                        (Value::String(s), _) => out.push_str(&escape(s)),
+                        (Value::Number(n), Some(digits)) => {
+                            out.push_str(&format!("{:.*}", digits, n.as_f64().unwrap_or(f64::NAN)))
+                        }
+                        (other, _) => out.push_str(&escape(&other.to_string())),
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template() {
+        let context = json!({
+            "time": "2024-05-01T12:34:56Z",
+            "rssi": null,
+            "telemetry": { "bat_v": 4.2538, "data1": { "power_com": true } },
+        });
+        let template = Template::parse("{{time}} V={{ telemetry.bat_v:.2 }} COM={{telemetry.data1.power_com}} [{{rssi.raw_rssi}}]").unwrap();
+        assert_eq!(template.render(&context).unwrap(), "2024-05-01T12:34:56Z V=4.25 COM=true []");
+
+        let quoted = Template::parse("{{time}} '{{note}}' {{telemetry.bat_v}}").unwrap();
+        let rendered = quoted.render_escaped(&json!({ "time": "12:34", "note": "it's", "telemetry": { "bat_v": 4.25 } }), |s| s.replace('\'', "''"));
+        assert_eq!(rendered.unwrap(), "12:34 'it''s' 4.25");
+
+        let unknown = Template::parse("{{telemetry.bat_x}}").unwrap();
+        assert!(unknown.render(&context).unwrap_err().contains("telemetry.bat_x"));
+    }
+
+    #[test]
+    fn test_template_syntax_errors() {
+        assert!(Template::parse("{{time").is_err());
+        assert!(Template::parse("{{}}").is_err());
+        assert!(Template::parse("{{telemetry..bat_v}}").is_err());
+        assert!(Template::parse("{{bat_v:.x}}").is_err());
+        assert_eq!(Template::parse("plain").unwrap().render(&json!({})).unwrap(), "plain");
+    }
+}
@@ -0,0 +1,392 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current time as fractional Unix seconds (UTC)
+pub fn now_unix() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// Julian date for a Unix timestamp
+pub fn julian_date(unix: f64) -> f64 {
+    unix / 86400.0 + 2440587.5
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Calendar date for a count of days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Format a Unix timestamp as ISO 8601 UTC, e.g. "2024-05-01T12:34:56Z"
+pub fn format_utc(unix: f64) -> String {
+    let secs = unix.floor() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let rem = secs.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60
+    )
+}
+
+/// The time zone times are shown in; everything is kept in UTC underneath
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Zone {
+    Utc,
+    Fixed(i32),       // Minutes east of UTC, e.g. +540 for JST
+    Named(String),    // A tz database name such as "Asia/Tokyo", with its daylight saving rules
+    Local,            // The system's zone
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// "UTC", "local", an offset ("+09:00", "+0900", "-05"), "JST" or a tz database name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Unknown time zone '{}'. Expected UTC, local, an offset such as +09:00, JST or a name such as Asia/Tokyo", s);
+        match s.to_ascii_uppercase().as_str() {
+            "UTC" | "Z" | "GMT" => return Ok(Zone::Utc),
+            "LOCAL" => return Ok(Zone::Local),
+            "JST" => return Ok(Zone::Fixed(540)),
+            _ => {}
+        }
+        if let Some(sign) = s.strip_prefix(['+', '-']).map(|_| if s.starts_with('-') { -1 } else { 1 }) {
+            let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+            if !(digits.len() == 2 || digits.len() == 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(err());
+            }
+            let value: i32 = digits.parse().unwrap();
+            let minutes = if digits.len() == 2 { value * 60 } else { value / 100 * 60 + value % 100 };
+            if minutes > 14 * 60 || (digits.len() == 4 && value % 100 >= 60) {
+                return Err(err());
+            }
+            return Ok(if minutes == 0 { Zone::Utc } else { Zone::Fixed(sign * minutes) });
+        }
+        let dir = std::env::var_os("TZDIR").map_or_else(|| std::path::PathBuf::from("/usr/share/zoneinfo"), std::path::PathBuf::from);
+        if cfg!(unix) && !s.contains("..") && dir.join(s).is_file() {
+            return Ok(Zone::Named(s.to_string()));
+        }
+        Err(err())
+    }
+}
+
+impl Zone {
+    /// Minutes east of UTC at `unix`
+    fn offset_minutes(&self, unix: f64) -> i32 {
+        match self {
+            Zone::Utc => 0,
+            Zone::Fixed(minutes) => *minutes,
+            // A named zone is installed as the process's local zone by set_display_zone
+            Zone::Named(_) | Zone::Local => local_offset_minutes(unix),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn local_offset_minutes(unix: f64) -> i32 {
+    let secs = unix.floor() as libc::time_t;
+    // SAFETY: tm is plain data, filled in by localtime_r
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return 0;
+    }
+    (tm.tm_gmtoff / 60) as i32
+}
+
+#[cfg(not(unix))]
+fn local_offset_minutes(_unix: f64) -> i32 {
+    0
+}
+
+static DISPLAY_ZONE: OnceLock<Zone> = OnceLock::new();
+
+/// Show times in `zone` from now on. Called once at startup, before any thread is spawned,
+/// since a named zone is installed through the TZ environment variable.
+pub fn set_display_zone(zone: Zone) {
+    if let Zone::Named(name) = &zone {
+        std::env::set_var("TZ", name);
+        #[cfg(unix)]
+        {
+            extern "C" {
+                fn tzset();
+            }
+            unsafe { tzset() };
+        }
+    }
+    let _ = DISPLAY_ZONE.set(zone);
+}
+
+/// Format a Unix timestamp as ISO 8601 in the display zone, e.g. "2024-05-01T21:34:56+09:00",
+/// or as format_utc does while the zone is UTC
+pub fn format_display(unix: f64) -> String {
+    format_with_offset(unix, DISPLAY_ZONE.get().map_or(0, |zone| zone.offset_minutes(unix)))
+}
+
+fn format_with_offset(unix: f64, offset: i32) -> String {
+    if offset == 0 {
+        return format_utc(unix);
+    }
+    let local = format_utc(unix + (offset * 60) as f64);
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!("{}{}{:02}:{:02}", local.trim_end_matches('Z'), sign, offset.abs() / 60, offset.abs() % 60)
+}
+
+/// A stored UTC time string shown in the display zone; anything that is not a time is kept
+pub fn display_text(stored: &str) -> String {
+    match parse_utc(stored) {
+        Ok(unix) if !stored.is_empty() => format_display(unix),
+        _ => stored.to_string(),
+    }
+}
+
+/// The number of the day `unix` falls on in the display zone, counted like days_from_civil
+pub fn display_day(unix: f64) -> i64 {
+    let offset = DISPLAY_ZONE.get().map_or(0, |zone| zone.offset_minutes(unix));
+    ((unix + (offset * 60) as f64) / 86400.0).floor() as i64
+}
+
+/// Parse a UTC time given as ISO 8601 ("2024-05-01T12:34:56Z", "2024-05-01 12:34:56.5",
+/// "2024-05-01T21:34:56+09:00") or as plain Unix seconds
+pub fn parse_utc(input: &str) -> Result<f64, String> {
+    let input = input.trim();
+    if let Ok(unix) = input.parse::<f64>() {
+        return Ok(unix);
+    }
+
+    let err = || format!("Invalid UTC time '{}'. Expected YYYY-MM-DDTHH:MM:SSZ", input);
+    let body = input.strip_suffix('Z').unwrap_or(input);
+    let (date, time) = body.split_once(['T', ' ']).unwrap_or((body, "00:00:00"));
+    // A local time with its offset from UTC, "+09:00", "+0900" or "-05"
+    let (time, offset) = match time.find(['+', '-']) {
+        Some(pos) => {
+            let (sign, zone) = time[pos..].split_at(1);
+            let digits: String = zone.chars().filter(|c| *c != ':').collect();
+            if !(digits.len() == 2 || digits.len() == 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(err());
+            }
+            let value: i64 = digits.parse().unwrap();
+            let minutes = if digits.len() == 2 { value * 60 } else { value / 100 * 60 + value % 100 };
+            (time[..pos].trim_end(), if sign == "-" { -minutes } else { minutes })
+        }
+        None => (time, 0),
+    };
+
+    let date: Vec<&str> = date.split('-').collect();
+    if date.len() != 3 {
+        return Err(err());
+    }
+    let year: i64 = date[0].parse().map_err(|_| err())?;
+    let month: u32 = date[1].parse().map_err(|_| err())?;
+    let day: u32 = date[2].parse().map_err(|_| err())?;
+
+    let time: Vec<&str> = time.split(':').collect();
+    if time.len() < 2 || time.len() > 3 {
+        return Err(err());
+    }
+    let hour: u32 = time[0].parse().map_err(|_| err())?;
+    let minute: u32 = time[1].parse().map_err(|_| err())?;
+    let second: f64 = match time.get(2) {
+        Some(s) => s.parse().map_err(|_| err())?,
+        None => 0.0,
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second >= 61.0 {
+        return Err(err());
+    }
+    let days = days_from_civil(year, month, day);
+    Ok(days as f64 * 86400.0 + (hour * 3600 + minute * 60) as f64 + second - (offset * 60) as f64)
+}
+
+/// A timestamp at the start of a log line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineStamp {
+    Utc(f64),          // A full date and time
+    TimeOfDay(f64),    // Seconds since midnight UTC; the date comes from elsewhere
+}
+
+/// Split the timestamp off the start of a log line, returning it and the rest of the line.
+/// Recognized are ISO 8601 ("2024-05-01T12:34:56Z", "2024-05-01 12:34:56"), a bare time of day
+/// ("12:34:56"), either in brackets, fldigi's "RX (2024-05-01 12:34Z):" and Direwolf's
+/// "[0.4 2024-05-01 12:34:56]".
+pub fn split_line_stamp(line: &str) -> Option<(LineStamp, &str)> {
+    let line = line.trim_start();
+    let (stamp, rest) = if let Some(inner) = line.strip_prefix("RX (").or_else(|| line.strip_prefix("TX (")) {
+        inner.split_once("):")?
+    } else if let Some(inner) = line.strip_prefix('[') {
+        let (inner, rest) = inner.split_once(']')?;
+        // Direwolf puts the channel first, e.g. "0.4" or "1L"
+        let stamp = match inner.split_once(' ') {
+            Some((channel, stamp)) if channel.starts_with(|c: char| c.is_ascii_digit()) && !channel.contains(['-', ':']) => stamp,
+            _ => inner,
+        };
+        (stamp, rest)
+    } else {
+        let mut words = line.splitn(3, ' ');
+        let first = words.next()?;
+        match words.next() {
+            // A date and a time as separate words
+            Some(time) if is_date(first) && time.contains(':') => (&line[..first.len() + 1 + time.len()], words.next().unwrap_or("")),
+            _ => (first, line[first.len()..].trim_start()),
+        }
+    };
+    // A separator may follow the stamp without a space, e.g. "12:34:56: BOTAN"
+    let stamp = parse_line_stamp(stamp.trim().trim_end_matches([':', ',']))?;
+    Some((stamp, rest.trim_start_matches([' ', '\t', ':', '-', '|', ','])))
+}
+
+fn is_date(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 10 && bytes[4] == b'-' && bytes[7] == b'-'
+}
+
+fn parse_line_stamp(stamp: &str) -> Option<LineStamp> {
+    // Every format has a colon, so no beacon word or number is taken for a time
+    if !stamp.contains(':') {
+        return None;
+    }
+    if stamp.get(..10).is_some_and(is_date) {
+        return parse_utc(stamp).ok().map(LineStamp::Utc);
+    }
+    parse_utc(&format!("1970-01-01 {}", stamp)).ok().map(LineStamp::TimeOfDay)
+}
+
+/// Resolves the stamps of successive log lines to UTC. A bare time of day takes the date of the
+/// last dated line, moving on a day when the time wraps past midnight.
+#[derive(Debug, Default)]
+pub struct LogClock {
+    day: Option<f64>,
+    last: Option<f64>,
+}
+
+impl LogClock {
+    /// A clock starting on `date` (any time of that day), for logs that never give a date
+    pub fn new(date: Option<f64>) -> Self {
+        LogClock { day: date.map(|date| (date / 86400.0).floor() * 86400.0), last: None }
+    }
+
+    /// The UTC time of a stamp, None for a time of day before any date is known
+    pub fn resolve(&mut self, stamp: LineStamp) -> Option<f64> {
+        let time = match stamp {
+            LineStamp::Utc(time) => time,
+            LineStamp::TimeOfDay(seconds) => {
+                let mut time = self.day? + seconds;
+                if self.last.is_some_and(|last| time < last - 43200.0) {
+                    time += 86400.0;
+                }
+                time
+            }
+        };
+        self.day = Some((time / 86400.0).floor() * 86400.0);
+        self.last = Some(time);
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        for days in [-1000, 0, 11016, 11017, 20000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let unix = parse_utc("2024-05-01T12:34:56Z").unwrap();
+        assert_eq!(unix, 1714566896.0);
+        assert_eq!(format_utc(unix), "2024-05-01T12:34:56Z");
+        assert_eq!(parse_utc("2024-05-01 12:34:56.5").unwrap(), 1714566896.5);
+        assert_eq!(parse_utc("1714566896").unwrap(), 1714566896.0);
+        assert!(parse_utc("2024-13-01T00:00:00Z").is_err());
+        assert_eq!(parse_utc("2024-05-01T21:34:56+09:00").unwrap(), 1714566896.0);
+        assert_eq!(parse_utc("2024-05-01 07:34:56.5-0500").unwrap(), 1714566896.5);
+        assert!(parse_utc("2024-05-01T21:34:56+9").is_err());
+    }
+
+    #[test]
+    fn test_julian_date() {
+        // J2000.0 epoch
+        assert!((julian_date(946728000.0) - 2451545.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_line_stamp() {
+        let beacon = "BOTAN JS1YPT A67C8D5E2AA13608";
+        let utc = LineStamp::Utc(1714566896.0);
+        for line in [
+            "2024-05-01T12:34:56Z BOTAN JS1YPT A67C8D5E2AA13608",
+            "2024-05-01 12:34:56 BOTAN JS1YPT A67C8D5E2AA13608",
+            "2024-05-01 21:34:56+09:00 | BOTAN JS1YPT A67C8D5E2AA13608",
+            "[2024-05-01 12:34:56] BOTAN JS1YPT A67C8D5E2AA13608",
+            "[0.4 2024-05-01 12:34:56] BOTAN JS1YPT A67C8D5E2AA13608",
+        ] {
+            assert_eq!(split_line_stamp(line), Some((utc, beacon)), "{}", line);
+        }
+        assert_eq!(
+            split_line_stamp("RX (2024-05-01 12:34Z): BOTAN JS1YPT A67C8D5E2AA13608"),
+            Some((LineStamp::Utc(1714566840.0), beacon))
+        );
+        assert_eq!(split_line_stamp("12:34:56.5: BOTAN JS1YPT"), Some((LineStamp::TimeOfDay(45296.5), "BOTAN JS1YPT")));
+        assert_eq!(split_line_stamp(beacon), None);
+        assert_eq!(split_line_stamp("[0.4] JS1YPT>CQ:BOTAN"), None);
+        assert_eq!(split_line_stamp("2024-05-01 BOTAN"), None);
+    }
+
+    #[test]
+    fn test_log_clock() {
+        let mut clock = LogClock::default();
+        assert_eq!(clock.resolve(LineStamp::TimeOfDay(3600.0)), None);
+        assert_eq!(clock.resolve(LineStamp::Utc(1714566896.0)), Some(1714566896.0));
+        assert_eq!(clock.resolve(LineStamp::TimeOfDay(86000.0)), Some(1714521600.0 + 86000.0));
+        // Past midnight
+        assert_eq!(clock.resolve(LineStamp::TimeOfDay(60.0)), Some(1714608000.0 + 60.0));
+        let mut clock = LogClock::new(Some(1714566896.0));
+        assert_eq!(clock.resolve(LineStamp::TimeOfDay(60.0)), Some(1714521660.0));
+    }
+
+    #[test]
+    fn test_zone() {
+        assert_eq!("utc".parse::<Zone>(), Ok(Zone::Utc));
+        assert_eq!("+00:00".parse::<Zone>(), Ok(Zone::Utc));
+        assert_eq!("JST".parse::<Zone>(), Ok(Zone::Fixed(540)));
+        assert_eq!("+0930".parse::<Zone>(), Ok(Zone::Fixed(570)));
+        assert_eq!("-05".parse::<Zone>(), Ok(Zone::Fixed(-300)));
+        assert_eq!("Local".parse::<Zone>(), Ok(Zone::Local));
+        for bad in ["+9", "+09:75", "+15:00", "Mars/Olympus_Mons", "../etc/passwd"] {
+            assert!(bad.parse::<Zone>().is_err(), "{}", bad);
+        }
+
+        assert_eq!(format_with_offset(1714566896.0, 0), "2024-05-01T12:34:56Z");
+        assert_eq!(format_with_offset(1714566896.0, 540), "2024-05-01T21:34:56+09:00");
+        assert_eq!(format_with_offset(1714566896.0, -330), "2024-05-01T07:04:56-05:30");
+        assert_eq!(parse_utc(&format_with_offset(1714566896.0, -330)), Ok(1714566896.0));
+    }
+}
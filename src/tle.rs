@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+use crate::timeutil;
+
+/// A parsed NORAD two-line element set
+#[derive(Debug, Clone)]
+pub struct Tle {
+    pub name: Option<String>,
+    pub norad_id: u32,
+    pub epoch_unix: f64,       // Element set epoch [Unix seconds, UTC]
+    pub bstar: f64,            // Drag term [1/earth radii]
+    pub inclination: f64,      // [rad]
+    pub raan: f64,             // Right ascension of ascending node [rad]
+    pub eccentricity: f64,
+    pub arg_perigee: f64,      // [rad]
+    pub mean_anomaly: f64,     // [rad]
+    pub mean_motion: f64,      // [rev/day]
+    pub mean_motion_dot: f64,  // Half the first derivative of mean motion [rev/day²]
+    pub rev_number: Option<u32>,   // Revolution number at epoch, left blank in some element sets
+}
+
+impl Tle {
+    /// Parse the two element lines of a TLE
+    pub fn parse(name: Option<&str>, line1: &str, line2: &str) -> Result<Self, String> {
+        let line1 = line1.trim_end();
+        let line2 = line2.trim_end();
+        if !line1.starts_with("1 ") || line1.len() < 64 || !line1.is_ascii() {
+            return Err("Invalid TLE line 1".to_string());
+        }
+        if !line2.starts_with("2 ") || line2.len() < 63 || !line2.is_ascii() {
+            return Err("Invalid TLE line 2".to_string());
+        }
+
+        let field = |line: &str, start: usize, end: usize, what: &str| -> Result<f64, String> {
+            line[start..end.min(line.len())]
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid TLE {} field: '{}'", what, &line[start..end.min(line.len())]))
+        };
+
+        let norad_id = field(line1, 2, 7, "catalog number")? as u32;
+        let epoch_year = field(line1, 18, 20, "epoch year")? as i64;
+        let epoch_day = field(line1, 20, 32, "epoch day")?;
+        let mean_motion_dot = field(line1, 33, 43, "mean motion derivative")?;
+        let bstar = parse_exponent_field(&line1[53..61])
+            .ok_or_else(|| format!("Invalid TLE B* field: '{}'", &line1[53..61]))?;
+
+        let year = if epoch_year < 57 { 2000 + epoch_year } else { 1900 + epoch_year };
+        let epoch_unix = timeutil::days_from_civil(year, 1, 1) as f64 * 86400.0 + (epoch_day - 1.0) * 86400.0;
+
+        let deg = PI / 180.0;
+        Ok(Tle {
+            name: name.map(|n| n.trim().trim_start_matches("0 ").to_string()),
+            norad_id,
+            epoch_unix,
+            bstar,
+            inclination: field(line2, 8, 16, "inclination")? * deg,
+            raan: field(line2, 17, 25, "RAAN")? * deg,
+            eccentricity: field(line2, 26, 33, "eccentricity").map(|e| e / 1e7)?,
+            arg_perigee: field(line2, 34, 42, "argument of perigee")? * deg,
+            mean_anomaly: field(line2, 43, 51, "mean anomaly")? * deg,
+            mean_motion: field(line2, 52, 63, "mean motion")?,
+            mean_motion_dot,
+            rev_number: line2.get(63..68.min(line2.len())).and_then(|rev| rev.trim().parse().ok()),
+        })
+    }
+
+    /// Parse every TLE in a 2-line or 3-line (named) element file
+    pub fn parse_all(content: &str) -> Result<Vec<Tle>, String> {
+        let lines: Vec<&str> = content.lines().map(|l| l.trim_end()).filter(|l| !l.is_empty()).collect();
+        let mut tles = Vec::new();
+        let mut idx = 0;
+        while idx < lines.len() {
+            if lines[idx].starts_with("1 ") && idx + 1 < lines.len() {
+                tles.push(Tle::parse(None, lines[idx], lines[idx + 1])?);
+                idx += 2;
+            } else if idx + 2 < lines.len() && lines[idx + 1].starts_with("1 ") {
+                tles.push(Tle::parse(Some(lines[idx]), lines[idx + 1], lines[idx + 2])?);
+                idx += 3;
+            } else {
+                return Err(format!("Unexpected line in TLE file: '{}'", lines[idx]));
+            }
+        }
+        Ok(tles)
+    }
+
+    /// Load the BOTAN element set from a file, falling back to the first entry
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read TLE file '{}': {}", path, e))?;
+        Tle::find_botan(&content).map_err(|e| format!("{} in '{}'", e, path))
+    }
+
+    /// Pick the BOTAN element set from a TLE file's contents, falling back to the first entry
+    pub fn find_botan(content: &str) -> Result<Self, String> {
+        let mut tles = Tle::parse_all(content)?;
+        let botan = tles
+            .iter()
+            .position(|t| t.name.as_deref().is_some_and(|n| n.to_ascii_uppercase().contains("BOTAN")))
+            .unwrap_or(0);
+        if tles.is_empty() {
+            return Err("No TLE found".to_string());
+        }
+        Ok(tles.swap_remove(botan))
+    }
+
+    /// Age of the element set at the given time, in days
+    pub fn age_days(&self, unix: f64) -> f64 {
+        (unix - self.epoch_unix) / 86400.0
+    }
+
+    /// Orbit number at the given time, counted from the ascending node like the revolution
+    /// number at epoch; None when the element set has none or the time is before orbit 0
+    pub fn orbit_number(&self, unix: f64) -> Option<u64> {
+        let rev = self.rev_number?;
+        let days = self.age_days(unix);
+        // Mean argument of latitude at epoch, as a fraction of the orbit since the node
+        let since_node = ((self.arg_perigee + self.mean_anomaly) / (2.0 * PI)).rem_euclid(1.0);
+        let orbits = (since_node + self.mean_motion * days + self.mean_motion_dot * days * days).floor();
+        u64::try_from(rev as i64 + orbits as i64).ok()
+    }
+}
+
+/// Decode the TLE "assumed decimal point" exponent notation, e.g. " 66816-4" = 0.66816e-4
+fn parse_exponent_field(field: &str) -> Option<f64> {
+    let field = field.trim();
+    if field.is_empty() {
+        return Some(0.0);
+    }
+    let (mantissa, exponent) = match field.rfind(['-', '+']) {
+        Some(pos) if pos > 0 => (&field[..pos], &field[pos..]),
+        _ => (field, "0"),
+    };
+    let (sign, digits) = match mantissa.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, mantissa.trim_start_matches('+')),
+    };
+    let mantissa: f64 = format!("0.{}", digits).parse().ok()?;
+    let exponent: i32 = exponent.parse().ok()?;
+    Some(sign * mantissa * 10f64.powi(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub const TEST_TLE: &str = "\
+TEST SAT
+1 88888U          80275.98708465  .00073094  13844-3  66816-4 0    8
+2 88888  72.8435 115.9689 0086731  52.6988 110.5714 16.05824518  105
+";
+
+    #[test]
+    fn test_parse_tle() {
+        let tles = Tle::parse_all(TEST_TLE).unwrap();
+        assert_eq!(tles.len(), 1);
+        let tle = &tles[0];
+        assert_eq!(tle.name.as_deref(), Some("TEST SAT"));
+        assert_eq!(tle.norad_id, 88888);
+        assert!((tle.bstar - 0.66816e-4).abs() < 1e-12);
+        assert!((tle.eccentricity - 0.0086731).abs() < 1e-12);
+        assert!((tle.mean_motion - 16.05824518).abs() < 1e-9);
+        assert_eq!(timeutil::format_utc(tle.epoch_unix), "1980-10-01T23:41:24Z");
+        assert!((tle.mean_motion_dot - 0.00073094).abs() < 1e-12);
+        assert_eq!(tle.rev_number, Some(105));
+    }
+
+    #[test]
+    fn test_orbit_number() {
+        let tle = &Tle::parse_all(TEST_TLE).unwrap()[0];
+        // 45% of the way round from the node at epoch, 16.06 orbits a day
+        assert_eq!(tle.orbit_number(tle.epoch_unix), Some(105));
+        assert_eq!(tle.orbit_number(tle.epoch_unix + 0.5 / 16.05824518 * 86400.0), Some(105));
+        assert_eq!(tle.orbit_number(tle.epoch_unix + 0.6 / 16.05824518 * 86400.0), Some(106));
+        assert_eq!(tle.orbit_number(tle.epoch_unix + 86400.0), Some(121));
+        assert_eq!(tle.orbit_number(tle.epoch_unix - 86400.0), Some(89));
+        assert_eq!(tle.orbit_number(tle.epoch_unix - 8.0 * 86400.0), None);
+
+        // No revolution number in the element set
+        let line2 = "2 88888  72.8435 115.9689 0086731  52.6988 110.5714 16.05824518";
+        let tle = Tle::parse(None, "1 88888U          80275.98708465  .00073094  13844-3  66816-4 0    8", line2).unwrap();
+        assert_eq!(tle.rev_number, None);
+        assert_eq!(tle.orbit_number(tle.epoch_unix), None);
+    }
+
+    #[test]
+    fn test_exponent_field() {
+        assert_eq!(parse_exponent_field(" 00000-0"), Some(0.0));
+        assert!((parse_exponent_field("-11606-4").unwrap() + 0.11606e-4).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,135 @@
+// Downloads BOTAN's element set and keeps a local copy so passes and Doppler work offline
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use crate::logging::{debug, warn};
+use crate::tle::Tle;
+
+pub const CELESTRAK_BOTAN_URL: &str = "https://celestrak.org/NORAD/elements/gp.php?NAME=BOTAN&FORMAT=tle";
+
+/// Element sets older than this give noticeably wrong pass times and Doppler [days]
+pub const STALE_AGE_DAYS: f64 = 7.0;
+
+// CelesTrak updates LEO elements a few times a day; don't hit it more than daily
+const REFRESH_INTERVAL: Duration = Duration::from_secs(86400);
+
+/// Local cache of a TLE file downloaded from a URL
+pub struct TleCache {
+    url: String,
+    path: PathBuf,
+}
+
+impl TleCache {
+    pub fn new(url: &str, path: PathBuf) -> Self {
+        TleCache { url: url.to_string(), path }
+    }
+
+    /// $XDG_CACHE_HOME/botan_cw_decoder/botan.tle, or under ~/.cache
+    pub fn default_path() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .unwrap_or_else(|| PathBuf::from("."));
+        base.join("botan_cw_decoder").join("botan.tle")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the cached file was downloaded within the refresh interval
+    pub fn is_fresh(&self) -> bool {
+        fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age < REFRESH_INTERVAL)
+    }
+
+    /// Download the element set and replace the cached copy
+    pub fn update(&self) -> Result<Tle, String> {
+        debug!("downloading TLE"; url = self.url);
+        let content = fetch(&self.url)?;
+        let tle = Tle::find_botan(&content).map_err(|e| format!("{} at {}", e, self.url))?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+        }
+        // Write then rename so a reader never sees a half-written file
+        let partial = self.path.with_extension("tle.part");
+        fs::write(&partial, &content).map_err(|e| format!("Cannot write '{}': {}", partial.display(), e))?;
+        fs::rename(&partial, &self.path).map_err(|e| format!("Cannot write '{}': {}", self.path.display(), e))?;
+        Ok(tle)
+    }
+
+    /// The cached element set, refreshed first if it is out of date.
+    /// A failed download falls back to the cached copy.
+    pub fn load(&self) -> Result<Tle, String> {
+        let cached = self.path.to_string_lossy();
+        if self.is_fresh() {
+            return Tle::from_file(&cached);
+        }
+        match self.update() {
+            Ok(tle) => Ok(tle),
+            Err(error) if self.path.exists() => {
+                warn!("TLE download failed, using the cached copy"; error = error, cache = cached);
+                Tle::from_file(&cached)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Fetch a URL's body; file:// URLs are read directly, anything else goes through curl
+fn fetch(url: &str) -> Result<String, String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path, e));
+    }
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "30", url])
+        .output()
+        .map_err(|e| format!("Cannot run curl to download TLE: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Download of {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} did not return text", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TLE: &str = "\
+BOTAN
+1 88888U          80275.98708465  .00073094  13844-3  66816-4 0    8
+2 88888  72.8435 115.9689 0086731  52.6988 110.5714 16.05824518  105
+";
+
+    #[test]
+    fn test_update_and_fallback() {
+        let dir = std::env::temp_dir().join(format!("botan_tle_cache_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.tle");
+        fs::write(&source, TEST_TLE).unwrap();
+
+        let cache = TleCache::new(&format!("file://{}", source.display()), dir.join("cache").join("botan.tle"));
+        assert!(!cache.is_fresh());
+        assert_eq!(cache.load().unwrap().norad_id, 88888);
+        assert!(cache.is_fresh());
+
+        // Source gone and cache expired: loading falls back to the cached copy
+        fs::remove_file(&source).unwrap();
+        assert!(cache.update().is_err());
+        let stale = SystemTime::now() - 2 * REFRESH_INTERVAL;
+        fs::File::options().write(true).open(cache.path()).unwrap().set_modified(stale).unwrap();
+        assert!(!cache.is_fresh());
+        assert_eq!(cache.load().unwrap().name.as_deref(), Some("BOTAN"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
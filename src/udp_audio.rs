@@ -0,0 +1,113 @@
+use std::io;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use crate::logging::trace;
+
+/// Receives the raw audio stream GQRX (and similar receivers) send over UDP:
+/// signed 16-bit little-endian mono samples, one chunk per datagram
+pub struct UdpAudioSource {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+}
+
+impl UdpAudioSource {
+    /// Bind to e.g. "0.0.0.0:7355", the GQRX default port
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        Ok(UdpAudioSource {
+            socket: UdpSocket::bind(addr)?,
+            buffer: vec![0; 65536],
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Limit how long `read_block` waits for a datagram
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+
+    /// Block until the next datagram arrives and convert it to samples in +/-1.0;
+    /// returns 0 if the read timeout expires first
+    pub fn read_block(&mut self, out: &mut Vec<f32>) -> io::Result<usize> {
+        out.clear();
+        let len = match self.socket.recv_from(&mut self.buffer) {
+            Ok((len, from)) => {
+                trace!("datagram"; bytes = len, from = from);
+                len
+            }
+            // Interrupted: a stop signal arrived while waiting
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {
+                trace!("read timed out");
+                return Ok(0);
+            }
+            Err(e) => return Err(e),
+        };
+        pcm16_to_f32(&self.buffer[..len], out);
+        Ok(out.len())
+    }
+}
+
+/// Convert signed 16-bit little-endian PCM to floating point samples
+pub fn pcm16_to_f32(raw: &[u8], out: &mut Vec<f32>) {
+    for sample in raw.chunks_exact(2) {
+        out.push(i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cw_demod::{BeaconScanner, CwDemodulator};
+    use crate::cw_synth::{self, CwSynthConfig};
+
+    #[test]
+    fn test_pcm16_conversion() {
+        let mut out = Vec::new();
+        pcm16_to_f32(&[0x00, 0x40, 0x00, 0xC0, 0x01], &mut out);
+        assert_eq!(out, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_decode_over_loopback() {
+        let mut source = UdpAudioSource::bind("127.0.0.1:0").unwrap();
+        let target = source.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let config = CwSynthConfig { sample_rate: 48000, ..Default::default() };
+        let morse = crate::BotanDecoder::new().encode("BOTAN JS1YPT A67C8D5E2AA13608").unwrap();
+        let samples = cw_synth::synthesize(&morse, &config);
+        let datagrams: Vec<Vec<u8>> = samples
+            .chunks(1024)
+            .map(|chunk| chunk.iter().flat_map(|s| s.to_le_bytes()).collect())
+            .collect();
+
+        let mut demod = CwDemodulator::new(48000.0, 700.0, 200.0, 20.0);
+        let mut scanner = BeaconScanner::new();
+        let mut block = Vec::new();
+        let mut beacons = Vec::new();
+        let mut text = String::new();
+        for datagram in &datagrams {
+            // Send and receive in lockstep so the socket buffer never overflows
+            sender.send_to(datagram, target).unwrap();
+            source.read_block(&mut block).unwrap();
+            text.clear();
+            demod.process_audio(&block, &mut text);
+            beacons.extend(scanner.push(&text));
+        }
+        beacons.extend(scanner.finish());
+
+        assert_eq!(beacons, vec!["BOTAN JS1YPT A67C8D5E2AA13608"]);
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let mut source = UdpAudioSource::bind("127.0.0.1:0").unwrap();
+        source.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let mut block = vec![1.0];
+        assert_eq!(source.read_block(&mut block).unwrap(), 0);
+        assert!(block.is_empty());
+    }
+}
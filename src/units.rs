@@ -0,0 +1,141 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Display units for the telemetry values. Parsing always yields V, mA and °C;
+// conversion happens only when a value is shown.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CurrentUnit {
+    #[default]
+    MilliAmpere,
+    Ampere,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VoltageUnit {
+    #[default]
+    Volt,
+    MilliVolt,
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "c" | "celsius" => Ok(TemperatureUnit::Celsius),
+            "f" | "fahrenheit" => Ok(TemperatureUnit::Fahrenheit),
+            _ => Err(format!("Unknown temperature unit '{}'. Expected c or f", s)),
+        }
+    }
+}
+
+impl FromStr for CurrentUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ma" => Ok(CurrentUnit::MilliAmpere),
+            "a" => Ok(CurrentUnit::Ampere),
+            _ => Err(format!("Unknown current unit '{}'. Expected ma or a", s)),
+        }
+    }
+}
+
+impl FromStr for VoltageUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "v" => Ok(VoltageUnit::Volt),
+            "mv" => Ok(VoltageUnit::MilliVolt),
+            _ => Err(format!("Unknown voltage unit '{}'. Expected v or mv", s)),
+        }
+    }
+}
+
+// Decoded values in the units the conversion formulas produce. Wrapping them keeps a
+// battery current from being passed where a voltage or temperature is expected.
+
+/// Voltage [V]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Volts(pub f64);
+
+/// Current [mA]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Milliamps(pub f64);
+
+/// Temperature [°C]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Celsius(pub f64);
+
+/// A converted value with its unit and the decimals worth showing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: &'static str,
+    pub precision: usize,
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.*} {}", self.precision, self.value, self.unit)
+    }
+}
+
+/// Units chosen for display
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub current: CurrentUnit,
+    pub voltage: VoltageUnit,
+}
+
+impl Units {
+    pub fn temperature(&self, Celsius(celsius): Celsius) -> Quantity {
+        match self.temperature {
+            TemperatureUnit::Celsius => Quantity { value: celsius, unit: "°C", precision: 1 },
+            TemperatureUnit::Fahrenheit => Quantity { value: celsius * 1.8 + 32.0, unit: "°F", precision: 1 },
+        }
+    }
+
+    pub fn current(&self, Milliamps(milliamperes): Milliamps) -> Quantity {
+        match self.current {
+            CurrentUnit::MilliAmpere => Quantity { value: milliamperes, unit: "mA", precision: 1 },
+            CurrentUnit::Ampere => Quantity { value: milliamperes / 1000.0, unit: "A", precision: 4 },
+        }
+    }
+
+    pub fn voltage(&self, Volts(volts): Volts) -> Quantity {
+        match self.voltage {
+            VoltageUnit::Volt => Quantity { value: volts, unit: "V", precision: 3 },
+            VoltageUnit::MilliVolt => Quantity { value: volts * 1000.0, unit: "mV", precision: 0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_conversion() {
+        let units = Units {
+            temperature: "F".parse().unwrap(),
+            current: "a".parse().unwrap(),
+            voltage: "mv".parse().unwrap(),
+        };
+        assert_eq!(units.temperature(Celsius(20.0)).to_string(), "68.0 °F");
+        assert_eq!(units.current(Milliamps(124.8)).to_string(), "0.1248 A");
+        assert_eq!(units.voltage(Volts(4.28)).to_string(), "4280 mV");
+        assert_eq!(Units::default().temperature(Celsius(-40.0)).to_string(), "-40.0 °C");
+        assert!("k".parse::<TemperatureUnit>().is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// Write mono 16-bit PCM samples to a WAV file
+pub fn write_wav_mono16(path: &str, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    write_wav_to(&mut writer, sample_rate, samples)?;
+    writer.flush()
+}
+
+/// Write a canonical 44-byte RIFF header followed by the sample data
+pub fn write_wav_to<W: Write>(writer: &mut W, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let channels: u16 = 1;
+    let bits_per_sample: u16 = 16;
+    let block_align = channels * bits_per_sample / 8;
+    let byte_rate = sample_rate * block_align as u32;
+    let data_len = (samples.len() * 2) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;       // fmt chunk size
+    writer.write_all(&1u16.to_le_bytes())?;        // PCM
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads the samples of a 16-bit PCM WAV recording in blocks, keeping only the first channel
+pub struct WavReader<R: Read> {
+    reader: R,
+    sample_rate: u32,
+    channels: usize,
+    remaining: u64,     // Bytes left in the data chunk
+    buffer: Vec<u8>,
+    pending: usize,
+}
+
+impl WavReader<BufReader<File>> {
+    pub fn open(path: &str) -> io::Result<Self> {
+        WavReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> WavReader<R> {
+    /// Read the header up to the start of the sample data
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff)?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(invalid("Not a WAV file"));
+        }
+
+        let mut format = None;
+        loop {
+            let mut header = [0u8; 8];
+            reader.read_exact(&mut header).map_err(|_| invalid("WAV file has no data chunk"))?;
+            let len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as u64;
+            match &header[0..4] {
+                b"fmt " => {
+                    let mut fmt = vec![0u8; len as usize];
+                    reader.read_exact(&mut fmt)?;
+                    if fmt.len() < 16 {
+                        return Err(invalid("WAV format chunk is too short"));
+                    }
+                    let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    let sample_rate = u32::from_le_bytes(fmt[4..8].try_into().unwrap());
+                    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                    // 0xFFFE is WAVE_FORMAT_EXTENSIBLE, which wraps the same PCM samples
+                    if !matches!(tag, 1 | 0xFFFE) || bits != 16 || channels == 0 {
+                        return Err(invalid("Only 16-bit PCM WAV files are supported"));
+                    }
+                    format = Some((sample_rate, channels as usize));
+                }
+                b"data" => {
+                    let (sample_rate, channels) = format.ok_or_else(|| invalid("WAV data comes before its format"))?;
+                    return Ok(WavReader {
+                        reader,
+                        sample_rate,
+                        channels,
+                        // Streaming writers leave the length at 0 or all ones
+                        remaining: if len == 0 || len == u32::MAX as u64 { u64::MAX } else { len },
+                        buffer: vec![0; 65536 * 2 * channels],
+                        pending: 0,
+                    });
+                }
+                _ => {
+                    // Skip other chunks, padded to an even length
+                    io::copy(&mut (&mut reader).take(len + len % 2), &mut io::sink())?;
+                }
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Read the next block of samples in +/-1.0; an empty block means end of file
+    pub fn read_block(&mut self, out: &mut Vec<f32>) -> io::Result<usize> {
+        out.clear();
+        let size = 2 * self.channels;
+
+        while self.pending < size && self.remaining > 0 {
+            let end = (self.buffer.len() as u64).min(self.pending as u64 + self.remaining) as usize;
+            let read = self.reader.read(&mut self.buffer[self.pending..end])?;
+            if read == 0 {
+                break;
+            }
+            self.pending += read;
+            self.remaining -= read as u64;
+        }
+
+        let whole = self.pending - self.pending % size;
+        for frame in self.buffer[..whole].chunks_exact(size) {
+            out.push(i16::from_le_bytes([frame[0], frame[1]]) as f32 / 32768.0);
+        }
+        self.buffer.copy_within(whole..self.pending, 0);
+        self.pending -= whole;
+        Ok(out.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let mut buffer = Vec::new();
+        write_wav_to(&mut buffer, 8000, &[0, 1, -1]).unwrap();
+
+        assert_eq!(buffer.len(), 44 + 6);
+        assert_eq!(&buffer[0..4], b"RIFF");
+        assert_eq!(&buffer[8..12], b"WAVE");
+        assert_eq!(u32::from_le_bytes(buffer[24..28].try_into().unwrap()), 8000);
+        assert_eq!(u32::from_le_bytes(buffer[40..44].try_into().unwrap()), 6);
+    }
+
+    #[test]
+    fn test_read_wav() {
+        let mut buffer = Vec::new();
+        write_wav_to(&mut buffer, 22050, &[16384, -16384, 0]).unwrap();
+        buffer.push(0xFF); // Bytes after the data chunk are ignored
+        let mut reader = WavReader::new(buffer.as_slice()).unwrap();
+        assert_eq!(reader.sample_rate(), 22050);
+        let mut block = Vec::new();
+        assert_eq!(reader.read_block(&mut block).unwrap(), 3);
+        assert_eq!(block, [0.5, -0.5, 0.0]);
+        assert_eq!(reader.read_block(&mut block).unwrap(), 0);
+
+        assert!(WavReader::new(&b"RIFF\0\0\0\0WAVEdata\0\0\0\0"[..]).is_err());
+        assert!(WavReader::new(&buffer[4..]).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+// XML rendering of decoded frames for --format xml, for logging and telemetry tools that only
+// import XML. Elements follow the JSON form of a frame member by member, so the two never
+// disagree: objects become nested elements, array items repeat the singular of the array's name,
+// and null values are left out. Analog telemetry values carry their unit as an attribute.
+use std::fmt::Write as _;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::frames::Frame;
+use crate::orbit::OrbitState;
+use crate::timeutil;
+
+/// Units of the telemetry values, always V, mA and °C as in JSON
+const UNITS: [(&str, &str); 5] = [("bat_v", "V"), ("bat_i", "mA"), ("bat_t", "°C"), ("bpb_t", "°C"), ("raw_i", "mA")];
+
+/// `text` with the characters XML reserves escaped
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A serialized value with object members in declaration order, which serde_json's `Value`
+/// sorts by name
+enum Node {
+    Null,
+    Scalar(String),
+    Text(String),
+    List(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON value")
+            }
+
+            fn visit_unit<E>(self) -> Result<Node, E> {
+                Ok(Node::Null)
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Node, E> {
+                Ok(Node::Scalar(value.to_string()))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Node, E> {
+                Ok(Node::Scalar(value.to_string()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Node, E> {
+                Ok(Node::Scalar(value.to_string()))
+            }
+
+            // Written as in JSON, e.g. 2.0 rather than 2
+            fn visit_f64<E>(self, value: f64) -> Result<Node, E> {
+                Ok(Node::Scalar(Value::from(value).to_string()))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Node, E> {
+                Ok(Node::Text(value.to_string()))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(Node::List(items))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut members = Vec::new();
+                while let Some(member) = map.next_entry()? {
+                    members.push(member);
+                }
+                Ok(Node::Object(members))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// "fields" -> "field"
+fn singular(name: &str) -> &str {
+    name.strip_suffix('s').unwrap_or(name)
+}
+
+fn write_element(out: &mut String, name: &str, node: &Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match node {
+        Node::Null => {}
+        Node::Object(members) => {
+            writeln!(out, "{}<{}>", indent, name).unwrap();
+            for (key, member) in members {
+                write_element(out, key, member, depth + 1);
+            }
+            writeln!(out, "{}</{}>", indent, name).unwrap();
+        }
+        Node::List(items) => {
+            writeln!(out, "{}<{}>", indent, name).unwrap();
+            for item in items {
+                write_element(out, singular(name), item, depth + 1);
+            }
+            writeln!(out, "{}</{}>", indent, name).unwrap();
+        }
+        Node::Text(text) => writeln!(out, "{}<{}>{}</{}>", indent, name, escape(text), name).unwrap(),
+        Node::Scalar(scalar) => {
+            let unit = UNITS.iter().find(|(key, _)| *key == name).map(|(_, unit)| format!(" unit=\"{}\"", unit));
+            writeln!(out, "{}<{}{}>{}</{}>", indent, name, unit.unwrap_or_default(), scalar, name).unwrap()
+        }
+    }
+}
+
+/// One frame as an XML element: <beacon> for housekeeping, <typed_frame> for configured types
+pub fn frame_xml(frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>) -> Result<String, String> {
+    let (name, json) = match frame {
+        Frame::Housekeeping(data) => ("beacon", serde_json::to_string(data)),
+        Frame::Typed(typed) => ("typed_frame", serde_json::to_string(typed)),
+    };
+    let node: Node = json.and_then(|json| serde_json::from_str(&json)).map_err(|e| e.to_string())?;
+    let Node::Object(members) = node else {
+        return Err("Frame did not serialize to an object".to_string());
+    };
+    let mut out = String::new();
+    match time {
+        Some(time) => writeln!(out, "<{} time=\"{}\">", name, timeutil::format_display(time)).unwrap(),
+        None => writeln!(out, "<{}>", name).unwrap(),
+    }
+    for (key, member) in &members {
+        write_element(&mut out, key, member, 1);
+    }
+    if let Some(orbit) = orbit {
+        writeln!(out, "  <illumination>{}</illumination>", orbit.illumination.to_string().to_lowercase()).unwrap();
+        if let Some(number) = orbit.number {
+            writeln!(out, "  <orbit>{}</orbit>", number).unwrap();
+        }
+    }
+    write!(out, "</{}>", name).unwrap();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::botan_parser::{parse_botan_beacon, SignalCalibration};
+    use crate::calibration::CalibrationTable;
+    use crate::frames::{parse_frame, FrameTypes};
+    use crate::orbit::Illumination;
+
+    #[test]
+    fn test_beacon_xml() {
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT SI8640 A67C8D5E2AA13608").unwrap());
+        let orbit = OrbitState { illumination: Illumination::Eclipse, number: Some(1234) };
+        let xml = frame_xml(&frame, Some(1714566896.0), Some(orbit)).unwrap();
+        assert!(xml.starts_with("<beacon time=\"2024-05-01T12:34:56Z\">\n  <satellite_name>BOTAN</satellite_name>\n"));
+        assert!(xml.contains("\n  <rssi>\n    <raw_rssi>134</raw_rssi>\n    <raw_snr>64</raw_snr>\n  </rssi>\n"));
+        assert!(xml.contains("\n  <frame>A67C8D5E2AA13608</frame>\n"));
+        assert!(xml.contains("\n    <bat_i unit=\"mA\">124.8"));
+        assert!(xml.contains("\n      <kill_sw>false</kill_sw>\n"));
+        assert!(xml.ends_with("\n  <illumination>eclipse</illumination>\n  <orbit>1234</orbit>\n</beacon>"));
+
+        let frame = Frame::Housekeeping(parse_botan_beacon("BOTAN JS1YPT A67C8D5E2AA13608").unwrap());
+        let xml = frame_xml(&frame, None, None).unwrap();
+        assert!(xml.starts_with("<beacon>\n"));
+        assert!(!xml.contains("rssi") && !xml.contains("illumination"));
+    }
+
+    #[test]
+    fn test_typed_frame_xml() {
+        let types: FrameTypes =
+            serde_json::from_str(r#"{"m&s": {"length": 2, "fields": [{"name": "count", "field": "u8@8", "unit": "<n>"}]}}"#).unwrap();
+        let frame = parse_frame("BOTAN JS1YPT 4D02", &SignalCalibration::default(), &CalibrationTable::default(), &types).unwrap();
+        let xml = frame_xml(&frame, None, None).unwrap();
+        assert!(xml.contains("\n  <frame_type>m&amp;s</frame_type>\n"));
+        assert!(xml.contains("\n  <fields>\n    <field>\n      <name>count</name>\n      <raw>2</raw>\n      <value>2.0</value>\n      <unit>&lt;n&gt;</unit>\n    </field>\n  </fields>\n"));
+    }
+}
@@ -0,0 +1,339 @@
+// A ZeroMQ PUB socket for --zmq-pub, speaking ZMTP 3.0 (rfc.zeromq.org/spec/23) with the NULL
+// security mechanism, so SUB sockets of libzmq, pyzmq or GNU Radio can subscribe without the
+// decoder linking libzmq. Every frame is published as a two-part message: the satellite name as
+// the topic, then the frame's JSON object as --webhook sends it. Subscriptions match topics by
+// prefix, as in libzmq, and a subscriber more than `HIGH_WATER_MARK` messages behind loses
+// messages instead of holding up the decoder.
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::frames::Frame;
+use crate::grsat;
+use crate::logging::{debug, info, warn};
+use crate::orbit::OrbitState;
+use crate::provenance::Provenance;
+use crate::timeutil;
+
+/// Messages queued for one subscriber before newer ones are dropped
+const HIGH_WATER_MARK: usize = 1000;
+/// Frames a subscriber may send; they only carry subscriptions
+const MAX_FRAME: u64 = 1 << 16;
+
+// Frame flags
+const MORE: u8 = 0x1;
+const LONG: u8 = 0x2;
+const COMMAND: u8 = 0x4;
+
+/// The address to bind for a tcp:// endpoint, "*" meaning every interface
+pub fn bind_address(endpoint: &str) -> Result<String, String> {
+    let address = match endpoint.split_once("://") {
+        Some(("tcp", address)) => address,
+        Some((transport, _)) => return Err(format!("Unsupported ZeroMQ transport '{}'. Expected tcp", transport)),
+        None => endpoint,
+    };
+    match address.rsplit_once(':') {
+        Some(("*", port)) => Ok(format!("0.0.0.0:{}", port)),
+        Some((host, port)) if !host.is_empty() && !port.is_empty() => Ok(address.to_string()),
+        _ => Err(format!("Invalid ZeroMQ endpoint '{}'. Expected tcp://HOST:PORT", endpoint)),
+    }
+}
+
+/// The 64-byte greeting: signature, version 3.0, the NULL mechanism and the server flag unset
+fn greeting() -> [u8; 64] {
+    let mut greeting = [0; 64];
+    greeting[0] = 0xFF;
+    greeting[9] = 0x7F;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    greeting
+}
+
+fn write_frame(out: &mut impl Write, flags: u8, body: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(9 + body.len());
+    if body.len() > 255 {
+        frame.push(flags | LONG);
+        frame.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    } else {
+        frame.extend_from_slice(&[flags, body.len() as u8]);
+    }
+    frame.extend_from_slice(body);
+    out.write_all(&frame)
+}
+
+/// Read one frame: its flags and body
+fn read_frame(input: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut flags = [0];
+    input.read_exact(&mut flags)?;
+    let size = if flags[0] & LONG != 0 {
+        let mut size = [0; 8];
+        input.read_exact(&mut size)?;
+        u64::from_be_bytes(size)
+    } else {
+        let mut size = [0];
+        input.read_exact(&mut size)?;
+        size[0] as u64
+    };
+    if size > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Frame from subscriber too large"));
+    }
+    let mut body = vec![0; size as usize];
+    input.read_exact(&mut body)?;
+    Ok((flags[0], body))
+}
+
+/// A READY command announcing `socket_type`
+fn ready(socket_type: &str) -> Vec<u8> {
+    let mut body = vec![5];
+    body.extend_from_slice(b"READY");
+    body.push(11);
+    body.extend_from_slice(b"Socket-Type");
+    body.extend_from_slice(&(socket_type.len() as u32).to_be_bytes());
+    body.extend_from_slice(socket_type.as_bytes());
+    body
+}
+
+/// The Socket-Type property of a READY command
+fn socket_type(command: &[u8]) -> Option<&[u8]> {
+    let mut rest = command.strip_prefix(b"\x05READY")?;
+    while let Some((&length, after)) = rest.split_first() {
+        let (name, after) = after.split_at_checked(length as usize)?;
+        let (length, after) = after.split_first_chunk::<4>()?;
+        let (value, after) = after.split_at_checked(u32::from_be_bytes(*length) as usize)?;
+        if name.eq_ignore_ascii_case(b"Socket-Type") {
+            return Some(value);
+        }
+        rest = after;
+    }
+    None
+}
+
+/// Exchange greetings and READY commands with a subscriber
+fn handshake(stream: &mut TcpStream) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    stream.write_all(&greeting())?;
+    let mut peer = [0; 64];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xFF || peer[9] & 1 != 1 || peer[10] < 3 {
+        return Err(invalid("Not a ZMTP 3 peer"));
+    }
+    if peer[12..32] != greeting()[12..32] {
+        return Err(invalid("Peer wants a security mechanism other than NULL"));
+    }
+    write_frame(stream, COMMAND, &ready("PUB"))?;
+    let (flags, command) = read_frame(stream)?;
+    match socket_type(&command) {
+        Some(b"SUB" | b"XSUB") if flags & COMMAND != 0 => Ok(()),
+        _ => Err(invalid("Peer is not a SUB socket")),
+    }
+}
+
+/// Whether any of a subscriber's subscriptions is a prefix of `topic`
+fn wants(subscriptions: &[Vec<u8>], topic: &[u8]) -> bool {
+    subscriptions.iter().any(|subscription| topic.starts_with(subscription))
+}
+
+/// A connected subscriber
+struct Subscriber {
+    subscriptions: Arc<Mutex<Vec<Vec<u8>>>>,
+    queue: SyncSender<Arc<(Vec<u8>, Vec<u8>)>>,
+}
+
+type Subscribers = Arc<Mutex<Vec<Subscriber>>>;
+
+/// Read a subscriber's subscriptions until it leaves
+fn follow(mut stream: TcpStream, subscriptions: &Mutex<Vec<Vec<u8>>>) -> io::Result<()> {
+    loop {
+        let (flags, body) = read_frame(&mut stream)?;
+        // Subscriptions are messages starting with 1 (subscribe) or 0 (cancel); ZMTP 3.1 peers
+        // may send them as SUBSCRIBE and CANCEL commands instead
+        let change = match (flags & COMMAND != 0, body.split_first()) {
+            (false, Some((&1, topic))) => Some((true, topic)),
+            (false, Some((&0, topic))) => Some((false, topic)),
+            (true, _) => match body.strip_prefix(b"\x09SUBSCRIBE") {
+                Some(topic) => Some((true, topic)),
+                None => body.strip_prefix(b"\x06CANCEL").map(|topic| (false, topic)),
+            },
+            _ => None,
+        };
+        let mut subscriptions = subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        match change {
+            Some((true, topic)) => subscriptions.push(topic.to_vec()),
+            Some((false, topic)) => {
+                if let Some(index) = subscriptions.iter().position(|s| s == topic) {
+                    subscriptions.remove(index);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn connect(mut stream: TcpStream, subscribers: &Subscribers) -> io::Result<()> {
+    handshake(&mut stream)?;
+    let peer = stream.peer_addr()?;
+    let (queue, messages) = mpsc::sync_channel::<Arc<(Vec<u8>, Vec<u8>)>>(HIGH_WATER_MARK);
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for message in messages {
+            let (topic, payload) = &*message;
+            if write_frame(&mut writer, MORE, topic).and_then(|()| write_frame(&mut writer, 0, payload)).is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(Shutdown::Both);
+    });
+    let subscriptions = Arc::new(Mutex::new(Vec::new()));
+    subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(Subscriber { subscriptions: Arc::clone(&subscriptions), queue });
+    debug!("ZeroMQ subscriber connected"; peer = peer);
+    let result = follow(stream.try_clone()?, &subscriptions);
+    // Dropping its queue stops its writer
+    subscribers.lock().unwrap_or_else(|e| e.into_inner()).retain(|s| !Arc::ptr_eq(&s.subscriptions, &subscriptions));
+    let _ = stream.shutdown(Shutdown::Both);
+    debug!("ZeroMQ subscriber left"; peer = peer);
+    result
+}
+
+/// The bound PUB socket
+pub struct Publisher {
+    subscribers: Subscribers,
+}
+
+impl Publisher {
+    /// Bind to a tcp:// endpoint such as tcp://*:5556 and accept subscribers in the background
+    pub fn bind(endpoint: &str) -> Result<Self, String> {
+        let address = bind_address(endpoint)?;
+        let listener = TcpListener::bind(&address).map_err(|e| format!("Cannot bind ZeroMQ endpoint '{}': {}", endpoint, e))?;
+        info!("Publishing frames on ZeroMQ"; endpoint = endpoint);
+        Ok(Self::serve(listener))
+    }
+
+    fn serve(listener: TcpListener) -> Self {
+        let subscribers = Subscribers::default();
+        let accepted = Arc::clone(&subscribers);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let subscribers = Arc::clone(&accepted);
+                        thread::spawn(move || {
+                            if let Err(error) = connect(stream, &subscribers) {
+                                debug!("ZeroMQ subscriber dropped"; error = error);
+                            }
+                        });
+                    }
+                    Err(error) => warn!("Cannot accept ZeroMQ subscriber"; error = error),
+                }
+            }
+        });
+        Publisher { subscribers }
+    }
+
+    /// Publish a decoded frame under its satellite's name
+    pub fn publish(&self, frame: &Frame, time: Option<f64>, orbit: Option<OrbitState>, provenance: &Provenance) {
+        let topic = match frame {
+            Frame::Housekeeping(data) => &data.satellite_name,
+            Frame::Typed(typed) => &typed.satellite_name,
+        };
+        match grsat::frame_json(frame, Some(time.unwrap_or_else(timeutil::now_unix)), orbit, Some(provenance)) {
+            Ok(json) => self.send(topic.as_bytes(), json.into_bytes()),
+            Err(error) => warn!("Cannot publish frame"; error = error),
+        }
+    }
+
+    fn send(&self, topic: &[u8], payload: Vec<u8>) {
+        let message = Arc::new((topic.to_vec(), payload));
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|subscriber| {
+            if !wants(&subscriber.subscriptions.lock().unwrap_or_else(|e| e.into_inner()), topic) {
+                return true;
+            }
+            match subscriber.queue.try_send(Arc::clone(&message)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    debug!("ZeroMQ subscriber too slow, message dropped"; topic = String::from_utf8_lossy(topic));
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_bind_address() {
+        assert_eq!(bind_address("tcp://*:5556").unwrap(), "0.0.0.0:5556");
+        assert_eq!(bind_address("127.0.0.1:5556").unwrap(), "127.0.0.1:5556");
+        assert!(bind_address("ipc:///tmp/botan").is_err());
+        assert!(bind_address("tcp://5556").is_err());
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut out = Vec::new();
+        write_frame(&mut out, MORE, b"BOTAN").unwrap();
+        write_frame(&mut out, 0, &[0x42; 300]).unwrap();
+        assert_eq!(&out[..7], b"\x01\x05BOTAN");
+        assert_eq!(&out[7..16], [0x02, 0, 0, 0, 0, 0, 0, 1, 44]);
+        let mut input = &out[..];
+        assert_eq!(read_frame(&mut input).unwrap(), (MORE, b"BOTAN".to_vec()));
+        assert_eq!(read_frame(&mut input).unwrap().1.len(), 300);
+        assert_eq!(socket_type(&ready("SUB")), Some(&b"SUB"[..]));
+        assert!(wants(&[b"BOT".to_vec()], b"BOTAN") && wants(&[Vec::new()], b"BOTAN"));
+        assert!(!wants(&[b"BOTANX".to_vec()], b"BOTAN"));
+    }
+
+    #[test]
+    fn test_publish() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let publisher = Publisher::serve(listener);
+
+        // A SUB socket, as libzmq would speak it
+        let mut sub = TcpStream::connect(addr).unwrap();
+        sub.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        sub.write_all(&greeting()).unwrap();
+        let mut greeting = [0; 64];
+        sub.read_exact(&mut greeting).unwrap();
+        assert_eq!((greeting[0], greeting[10]), (0xFF, 3));
+        write_frame(&mut sub, COMMAND, &ready("SUB")).unwrap();
+        assert_eq!(socket_type(&read_frame(&mut sub).unwrap().1), Some(&b"PUB"[..]));
+        write_frame(&mut sub, 0, b"\x01BOT").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while publisher.subscribers.lock().unwrap().first().is_none_or(|s| s.subscriptions.lock().unwrap().is_empty()) {
+            assert!(Instant::now() < deadline, "subscription not seen");
+            thread::sleep(Duration::from_millis(10));
+        }
+        publisher.send(b"OTHER", b"skipped".to_vec());
+        let frame = crate::frames::parse_frame(
+            "BOTAN JS1YPT A67C8D5E2AA13608",
+            &Default::default(),
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        let provenance = Provenance::new("udp-audio 0.0.0.0:7355", "builtin", Vec::new());
+        publisher.publish(&frame, Some(1714566896.0), None, &provenance);
+        assert_eq!(read_frame(&mut sub).unwrap(), (MORE, b"BOTAN".to_vec()));
+        let (flags, payload) = read_frame(&mut sub).unwrap();
+        assert_eq!(flags & MORE, 0);
+        let json: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(json["frame"], "A67C8D5E2AA13608");
+        assert_eq!(json["provenance"]["id"], provenance.id);
+
+        drop(sub);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !publisher.subscribers.lock().unwrap().is_empty() {
+            assert!(Instant::now() < deadline, "subscriber not dropped");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}